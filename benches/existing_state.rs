@@ -0,0 +1,63 @@
+//! Convert time against the number of existing reservations.
+//!
+//! Each ISC mapping is checked against the backend's existing entries, so
+//! a run should grow linearly with them. Run with `cargo bench`; the bench
+//! fails if the time per reservation at 50k is more than three times the
+//! time at 5k.
+
+use isc2kea::{convert_config, generate_fixture, Backend, FixtureSpec, MigrationOptions};
+use std::io::Cursor;
+use std::time::Duration;
+
+const INTERFACES: usize = 10;
+const SIZES: [usize; 4] = [5_000, 10_000, 25_000, 50_000];
+const MAX_GROWTH: f64 = 3.0;
+
+/// Best convert time of three runs with `existing` reservations and as many
+/// ISC mappings again.
+fn convert_time(backend: &Backend, existing: usize) -> Duration {
+    let xml = generate_fixture(&FixtureSpec {
+        interfaces: INTERFACES,
+        maps_per_iface: existing * 2 / INTERFACES,
+        existing_per_iface: existing / INTERFACES,
+        seed: 1,
+    })
+    .expect("fixture");
+    let options = MigrationOptions {
+        backend: backend.clone(),
+        ..Default::default()
+    };
+    (0..3)
+        .map(|_| {
+            let stats =
+                convert_config(Cursor::new(&xml), std::io::sink(), &options).expect("convert");
+            stats.timing.convert
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let mut failed = false;
+    for backend in [Backend::Kea, Backend::Dnsmasq] {
+        let mut first = None;
+        for existing in SIZES {
+            let elapsed = convert_time(&backend, existing);
+            let per_entry = elapsed.as_secs_f64() / existing as f64;
+            let growth = per_entry / *first.get_or_insert(per_entry);
+            println!(
+                "{:<8} {:>6} existing  {:>9.1?}  {:>6.2}us/entry  x{:.2}",
+                backend.to_string(),
+                existing,
+                elapsed,
+                per_entry * 1e6,
+                growth
+            );
+            failed |= growth > MAX_GROWTH;
+        }
+    }
+    if failed {
+        eprintln!("time per reservation grew more than {}x", MAX_GROWTH);
+        std::process::exit(1);
+    }
+}
@@ -1,12 +1,60 @@
+#[cfg(feature = "cli")]
+use clap::builder::PossibleValue;
 use std::fmt;
 
-#[derive(Debug, Clone, Default, clap::ValueEnum)]
+/// Target DHCP backend.
+///
+/// Every variant exists whatever cargo features are enabled; backends left
+/// out of the build are not offered on the command line, and selecting one
+/// through the library fails when the run looks it up.
+#[derive(Debug, Clone)]
 pub enum Backend {
     /// Kea DHCP (default)
-    #[default]
     Kea,
     /// dnsmasq DHCP
     Dnsmasq,
+    /// Kea and dnsmasq from the same ISC source in one run
+    Both,
+}
+
+impl Backend {
+    /// Backends compiled into this build, in `--backend` order.
+    #[cfg(all(feature = "kea", feature = "dnsmasq"))]
+    const AVAILABLE: &'static [Backend] = &[Backend::Kea, Backend::Dnsmasq, Backend::Both];
+    #[cfg(all(feature = "kea", not(feature = "dnsmasq")))]
+    const AVAILABLE: &'static [Backend] = &[Backend::Kea];
+    #[cfg(all(not(feature = "kea"), feature = "dnsmasq"))]
+    const AVAILABLE: &'static [Backend] = &[Backend::Dnsmasq];
+    #[cfg(not(any(feature = "kea", feature = "dnsmasq")))]
+    const AVAILABLE: &'static [Backend] = &[];
+
+    /// Backends compiled into this build.
+    pub fn available() -> &'static [Backend] {
+        Self::AVAILABLE
+    }
+}
+
+impl Default for Backend {
+    /// Kea, or dnsmasq in builds without the `kea` feature.
+    fn default() -> Self {
+        Self::AVAILABLE.first().cloned().unwrap_or(Backend::Kea)
+    }
+}
+
+#[cfg(feature = "cli")]
+impl clap::ValueEnum for Backend {
+    fn value_variants<'a>() -> &'a [Self] {
+        Self::AVAILABLE
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Backend::Kea => PossibleValue::new("kea").help("Kea DHCP (default)"),
+            Backend::Dnsmasq => PossibleValue::new("dnsmasq").help("dnsmasq DHCP"),
+            Backend::Both => PossibleValue::new("both")
+                .help("Kea and dnsmasq from the same ISC source in one run"),
+        })
+    }
 }
 
 impl fmt::Display for Backend {
@@ -14,6 +62,7 @@ impl fmt::Display for Backend {
         match self {
             Backend::Kea => write!(f, "Kea"),
             Backend::Dnsmasq => write!(f, "dnsmasq"),
+            Backend::Both => write!(f, "Kea+dnsmasq"),
         }
     }
 }
@@ -0,0 +1,648 @@
+//! `api-import`: create the planned Kea reservations through the OPNsense
+//! API instead of writing config.xml.
+//!
+//! The plan is the set of reservations `convert` would add to the given
+//! config.xml. Each one is posted to `kea/dhcpv4` or `kea/dhcpv6`
+//! `add_reservation` in turn, with a pause between requests and retries
+//! with backoff on transport errors, `429` and `5xx`. Every reservation the
+//! API saves is appended to the journal, so a rerun with the same journal
+//! skips it.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use xmltree::Element;
+
+use super::{json_string, ApiImportArgs};
+use crate::extract::{kea_reservations_v4, kea_reservations_v6};
+use crate::model::{ReservationNode, ReservationV6Node};
+use crate::term;
+use crate::workspace::{Workspace, WorkspaceArea};
+use crate::{convert_config, Backend};
+
+/// A reservation to create, as sent to the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlannedReservation {
+    v6: bool,
+    /// Journal key: family and IP address, or the client identifier of a
+    /// placeholder without one
+    key: String,
+    /// `(field, value)` pairs of the API's `reservation` object
+    fields: Vec<(&'static str, String)>,
+}
+
+impl PlannedReservation {
+    fn v4(node: ReservationNode) -> Self {
+        let id = match (&node.ip_address, &node.client_id) {
+            (ip, _) if !ip.is_empty() => ip.clone(),
+            (_, Some(client_id)) if node.hw_address.is_empty() => {
+                format!("client_id={}", client_id)
+            }
+            _ => format!("mac={}", node.hw_address),
+        };
+        let mut fields = vec![
+            ("subnet", node.subnet),
+            ("ip_address", node.ip_address.clone()),
+            ("hw_address", node.hw_address),
+        ];
+        fields.extend(node.hostname.map(|v| ("hostname", v)));
+        fields.extend(node.client_id.map(|v| ("client_id", v)));
+        fields.extend(node.description.map(|v| ("description", v)));
+        PlannedReservation {
+            v6: false,
+            key: format!("v4 {}", id),
+            fields,
+        }
+    }
+
+    fn v6(node: ReservationV6Node) -> Self {
+        let id = if node.ip_address.is_empty() {
+            format!("duid={}", node.duid)
+        } else {
+            node.ip_address.clone()
+        };
+        let mut fields = vec![
+            ("subnet", node.subnet),
+            ("ip_address", node.ip_address.clone()),
+            ("duid", node.duid),
+        ];
+        fields.extend(node.prefixes.map(|v| ("prefixes", v)));
+        fields.extend(node.hostname.map(|v| ("hostname", v)));
+        fields.extend(node.domain_search.map(|v| ("domain_search", v)));
+        fields.extend(node.description.map(|v| ("description", v)));
+        PlannedReservation {
+            v6: true,
+            key: format!("v6 {}", id),
+            fields,
+        }
+    }
+
+    fn endpoint(&self) -> &'static str {
+        if self.v6 {
+            "/api/kea/dhcpv6/add_reservation"
+        } else {
+            "/api/kea/dhcpv4/add_reservation"
+        }
+    }
+
+    fn body(&self) -> String {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", json_string(name), json_string(value)))
+            .collect();
+        format!("{{\"reservation\": {{{}}}}}", fields.join(", "))
+    }
+}
+
+/// POSTs a JSON body to an OPNsense API path, returning the HTTP status and
+/// response body.
+trait ApiClient {
+    fn post(&self, path: &str, body: &str) -> Result<(u16, String)>;
+}
+
+/// [`ApiClient`] running `curl`, which handles TLS. The key and secret are
+/// passed on its stdin so they do not show up in the process list.
+struct CurlClient {
+    base_url: String,
+    key: String,
+    secret: String,
+    insecure: bool,
+}
+
+impl ApiClient for CurlClient {
+    fn post(&self, path: &str, body: &str) -> Result<(u16, String)> {
+        let mut command = Command::new("curl");
+        command
+            .args([
+                "--silent",
+                "--show-error",
+                "--max-time",
+                "60",
+                "--config",
+                "-",
+            ])
+            .args([
+                "--request",
+                "POST",
+                "--header",
+                "Content-Type: application/json",
+            ])
+            .args(["--data-binary", body, "--write-out", "\n%{http_code}"]);
+        if self.insecure {
+            command.arg("--insecure");
+        }
+        command.arg(format!("{}{}", self.base_url.trim_end_matches('/'), path));
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run curl (needed for api-import)")?;
+        let config = format!("user = \"{}:{}\"\n", self.key, self.secret);
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open curl stdin"))?
+            .write_all(config.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!(
+                "curl failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = stdout
+            .rsplit_once('\n')
+            .ok_or_else(|| anyhow!("curl printed no HTTP status"))?;
+        let status = status
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("curl printed an invalid HTTP status: {}", status))?;
+        Ok((status, body.to_string()))
+    }
+}
+
+/// Pacing and retry settings.
+struct Throttle {
+    delay: Duration,
+    retries: u32,
+    backoff: Duration,
+}
+
+enum Outcome {
+    Saved(String),
+    Rejected(String),
+}
+
+pub(crate) fn run_api_import(args: ApiImportArgs) -> Result<()> {
+    if !matches!(args.migration.backend, Backend::Kea) {
+        bail!("api-import only creates Kea reservations; use --backend kea");
+    }
+    if args.migration.create_subnets {
+        bail!(
+            "api-import cannot create subnets; create them in the GUI or with convert first, \
+             then import the reservations"
+        );
+    }
+    let (key, secret) = read_api_key_file(&args.api_key_file)?;
+    let client = CurlClient {
+        base_url: args.url.clone(),
+        key,
+        secret,
+        insecure: args.insecure,
+    };
+    let throttle = Throttle {
+        delay: Duration::from_millis(args.delay_ms),
+        retries: args.retries,
+        backoff: Duration::from_secs(1),
+    };
+
+    let mut input = Vec::new();
+    File::open(&args.r#in)
+        .and_then(|mut file| file.read_to_end(&mut input))
+        .with_context(|| format!("Failed to read input file: {}", args.r#in.display()))?;
+    // The planned config and the API's rejections are kept in a workspace
+    // when the import fails
+    let mut workspace = Workspace::new().context("Failed to create a workspace")?;
+    let result = import_into(&args, &client, &throttle, &input, &workspace);
+    match result {
+        Ok(()) => {
+            workspace.mark_success();
+            Ok(())
+        }
+        Err(e) => Err(e.context(format!(
+            "The planned config and API responses are kept in {}",
+            workspace.path().display()
+        ))),
+    }
+}
+
+fn import_into(
+    args: &ApiImportArgs,
+    client: &CurlClient,
+    throttle: &Throttle,
+    input: &[u8],
+    workspace: &Workspace,
+) -> Result<()> {
+    let (plan, converted) = plan_reservations(input, &args.migration.to_options()?)?;
+    workspace
+        .write_file(WorkspaceArea::Outputs, "config.xml", &converted)
+        .context("Failed to write the planned config to the workspace")?;
+    let done = match &args.journal {
+        Some(path) => load_journal(path)?,
+        None => HashSet::new(),
+    };
+
+    let mut journal = match &args.journal {
+        Some(path) => Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open journal: {}", path.display()))?,
+        ),
+        None => None,
+    };
+    let (created, skipped, rejected) = import(
+        client,
+        &plan,
+        &done,
+        throttle,
+        args.migration.verbose,
+        journal.as_mut().map(|file| file as &mut dyn Write),
+    )?;
+
+    println!("Reservations planned: {}", plan.len());
+    println!("Reservations created via API: {}", created);
+    println!("Reservations already in the journal: {}", skipped);
+    println!("Reservations rejected by the API: {}", rejected.len());
+    if !rejected.is_empty() {
+        workspace
+            .write_file(
+                WorkspaceArea::Reports,
+                "rejected.txt",
+                rejected.join("\n") + "\n",
+            )
+            .context("Failed to write the API rejections to the workspace")?;
+    }
+
+    if created > 0 && !args.no_reconfigure {
+        let (status, body) = client.post("/api/kea/service/reconfigure", "{}")?;
+        if status != 200 {
+            bail!(
+                "Kea reconfigure failed (HTTP {}): {}. The reservations are saved; \
+                 apply them from the GUI.",
+                status,
+                body.trim()
+            );
+        }
+        println!("Kea reconfigured.");
+    }
+    if !rejected.is_empty() {
+        bail!(
+            "{} reservation(s) were rejected by the API; fix them and rerun{}",
+            rejected.len(),
+            if args.journal.is_some() {
+                " with the same --journal"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Reservations `convert` would add to the config in `input`, and the
+/// converted config.
+fn plan_reservations(
+    input: &[u8],
+    options: &crate::MigrationOptions,
+) -> Result<(Vec<PlannedReservation>, Vec<u8>)> {
+    let before = Element::parse(Cursor::new(input)).context("Failed to parse XML")?;
+    let mut output = Vec::new();
+    convert_config(Cursor::new(input), &mut output, options)?;
+    let after = Element::parse(Cursor::new(&output)).context("Failed to parse converted XML")?;
+
+    let existing_v4: HashSet<String> = kea_reservations_v4(&before).map(|r| r.uuid).collect();
+    let existing_v6: HashSet<String> = kea_reservations_v6(&before).map(|r| r.uuid).collect();
+    let mut plan: Vec<PlannedReservation> = kea_reservations_v4(&after)
+        .filter(|r| !existing_v4.contains(&r.uuid))
+        .map(PlannedReservation::v4)
+        .collect();
+    plan.extend(
+        kea_reservations_v6(&after)
+            .filter(|r| !existing_v6.contains(&r.uuid))
+            .map(PlannedReservation::v6),
+    );
+    Ok((plan, output))
+}
+
+/// Create every planned reservation not in `done`, returning how many were
+/// created and skipped, and why each rejected one was rejected.
+fn import(
+    client: &dyn ApiClient,
+    plan: &[PlannedReservation],
+    done: &HashSet<String>,
+    throttle: &Throttle,
+    verbose: bool,
+    mut journal: Option<&mut dyn Write>,
+) -> Result<(usize, usize, Vec<String>)> {
+    let (mut created, mut skipped, mut rejected) = (0, 0, Vec::new());
+    for (i, reservation) in plan.iter().enumerate() {
+        if done.contains(&reservation.key) {
+            skipped += 1;
+            continue;
+        }
+        if i > 0 && !throttle.delay.is_zero() {
+            thread::sleep(throttle.delay);
+        }
+        match post_with_retry(client, reservation, throttle)? {
+            Outcome::Saved(uuid) => {
+                if verbose {
+                    term::action("API", format_args!("{} -> {}", reservation.key, uuid));
+                }
+                if let Some(journal) = journal.as_mut() {
+                    writeln!(journal, "{} {}", reservation.key, uuid)
+                        .and_then(|_| journal.flush())
+                        .context("Failed to write journal")?;
+                }
+                created += 1;
+            }
+            Outcome::Rejected(reason) => {
+                term::warning(format_args!(
+                    "API rejected reservation {}: {}",
+                    reservation.key, reason
+                ));
+                rejected.push(format!("{}: {}", reservation.key, reason));
+            }
+        }
+    }
+    Ok((created, skipped, rejected))
+}
+
+fn post_with_retry(
+    client: &dyn ApiClient,
+    reservation: &PlannedReservation,
+    throttle: &Throttle,
+) -> Result<Outcome> {
+    let body = reservation.body();
+    let mut backoff = throttle.backoff;
+    let mut attempt = 0;
+    loop {
+        let error = match client.post(reservation.endpoint(), &body) {
+            Ok((200, response)) => {
+                return Ok(match json_value(&response, "result").as_deref() {
+                    Some("saved") => {
+                        Outcome::Saved(json_value(&response, "uuid").unwrap_or_default())
+                    }
+                    _ => Outcome::Rejected(response.trim().to_string()),
+                });
+            }
+            Ok((status, response)) if status == 429 || status >= 500 => {
+                format!("HTTP {}: {}", status, response.trim())
+            }
+            Ok((status, response)) => {
+                bail!(
+                    "OPNsense API returned HTTP {} for {}: {}",
+                    status,
+                    reservation.key,
+                    response.trim()
+                )
+            }
+            Err(e) => format!("{:#}", e),
+        };
+        attempt += 1;
+        if attempt > throttle.retries {
+            bail!(
+                "Giving up on {} after {} attempt(s): {}",
+                reservation.key,
+                attempt,
+                error
+            );
+        }
+        term::warning(format_args!(
+            "{} failed ({}); retrying in {:?}",
+            reservation.key, error, backoff
+        ));
+        thread::sleep(backoff);
+        backoff = backoff.saturating_mul(2);
+    }
+}
+
+/// String value of a top-level `"name": "value"` pair in a flat JSON object.
+fn json_value(json: &str, name: &str) -> Option<String> {
+    let rest = &json[json.find(&format!("\"{}\"", name))? + name.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let value = rest.strip_prefix('"')?;
+    Some(value[..value.find('"')?].to_string())
+}
+
+/// Key and secret from an OPNsense `apikey.txt` (`key=...` and `secret=...`).
+fn read_api_key_file(path: &Path) -> Result<(String, String)> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read API key file: {}", path.display()))?;
+    let value = |name: &str| {
+        contents
+            .lines()
+            .filter_map(|line| line.trim().split_once('='))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| anyhow!("API key file {} has no {}=", path.display(), name))
+    };
+    let (key, secret) = (value("key")?, value("secret")?);
+    if key.contains(['"', '\\']) || secret.contains(['"', '\\']) {
+        bail!("API key file {} holds an invalid key", path.display());
+    }
+    Ok((key, secret))
+}
+
+/// Journal keys of reservations created by earlier runs.
+fn load_journal(path: &Path) -> Result<HashSet<String>> {
+    if !path.exists() {
+        return Ok(HashSet::new());
+    }
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read journal: {}", path.display()))?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.splitn(3, ' ');
+            Some(format!("{} {}", words.next()?, words.next()?))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Replays scripted responses and records the requests.
+    struct FakeClient {
+        responses: RefCell<Vec<Result<(u16, String)>>>,
+        requests: RefCell<Vec<(String, String)>>,
+    }
+
+    impl ApiClient for FakeClient {
+        fn post(&self, path: &str, body: &str) -> Result<(u16, String)> {
+            self.requests
+                .borrow_mut()
+                .push((path.to_string(), body.to_string()));
+            self.responses.borrow_mut().remove(0)
+        }
+    }
+
+    fn planned(ip: &str) -> PlannedReservation {
+        PlannedReservation::v4(ReservationNode {
+            subnet: "subnet-uuid".into(),
+            ip_address: ip.into(),
+            hw_address: "00:11:22:33:44:55".into(),
+            hostname: Some("host \"one\"".into()),
+            ..Default::default()
+        })
+    }
+
+    fn placeholder(mac: &str) -> PlannedReservation {
+        PlannedReservation::v4(ReservationNode {
+            subnet: "subnet-uuid".into(),
+            hw_address: mac.into(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_api_import_retries_and_journals() {
+        let client = FakeClient {
+            responses: RefCell::new(vec![
+                Err(anyhow!("connection refused")),
+                Ok((503, "busy".into())),
+                Ok((200, r#"{"result":"saved","uuid":"new-uuid"}"#.into())),
+                Ok((
+                    200,
+                    r#"{"result":"failed","validations":{"reservation.ip_address":"in use"}}"#
+                        .into(),
+                )),
+            ]),
+            requests: RefCell::new(Vec::new()),
+        };
+        let plan = vec![
+            planned("192.168.1.9"),
+            planned("192.168.1.10"),
+            planned("192.168.1.11"),
+        ];
+        let done = HashSet::from(["v4 192.168.1.9".to_string()]);
+        let throttle = Throttle {
+            delay: Duration::ZERO,
+            retries: 2,
+            backoff: Duration::ZERO,
+        };
+        let mut journal = Vec::new();
+
+        let counts = import(
+            &client,
+            &plan,
+            &done,
+            &throttle,
+            false,
+            Some(&mut journal as &mut dyn Write),
+        )
+        .unwrap();
+        assert_eq!(
+            counts,
+            (
+                1,
+                1,
+                vec![
+                    r#"v4 192.168.1.11: {"result":"failed","validations":{"reservation.ip_address":"in use"}}"#
+                        .to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            String::from_utf8(journal).unwrap(),
+            "v4 192.168.1.10 new-uuid\n"
+        );
+
+        let requests = client.requests.borrow();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].0, "/api/kea/dhcpv4/add_reservation");
+        assert_eq!(
+            requests[0].1,
+            r#"{"reservation": {"subnet": "subnet-uuid", "ip_address": "192.168.1.10", "hw_address": "00:11:22:33:44:55", "hostname": "host \"one\""}}"#
+        );
+    }
+
+    #[test]
+    fn test_api_import_gives_up_after_retries() {
+        let client = FakeClient {
+            responses: RefCell::new(vec![Ok((502, "".into())), Ok((502, "".into()))]),
+            requests: RefCell::new(Vec::new()),
+        };
+        let throttle = Throttle {
+            delay: Duration::ZERO,
+            retries: 1,
+            backoff: Duration::ZERO,
+        };
+        let err = import(
+            &client,
+            &[planned("192.168.1.10")],
+            &HashSet::new(),
+            &throttle,
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Giving up on v4 192.168.1.10 after 2 attempt(s)"));
+    }
+
+    #[test]
+    fn test_api_import_rerun_skips_journaled_placeholders() {
+        let journal_path = std::env::temp_dir().join(format!(
+            "isc2kea_api_import_journal_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let plan = vec![
+            placeholder("00:11:22:33:44:55"),
+            placeholder("00:11:22:33:44:66"),
+            PlannedReservation::v6(ReservationV6Node {
+                subnet: "subnet6-uuid".into(),
+                duid: "00:01:00:01:aa:bb".into(),
+                ..Default::default()
+            }),
+        ];
+        let throttle = Throttle {
+            delay: Duration::ZERO,
+            retries: 0,
+            backoff: Duration::ZERO,
+        };
+        let saved = |uuid: &str| Ok((200, format!(r#"{{"result":"saved","uuid":"{}"}}"#, uuid)));
+
+        let client = FakeClient {
+            responses: RefCell::new(vec![saved("uuid-1"), saved("uuid-2"), saved("uuid-3")]),
+            requests: RefCell::new(Vec::new()),
+        };
+        let mut journal = std::fs::File::create(&journal_path).unwrap();
+        let counts = import(
+            &client,
+            &plan,
+            &load_journal(&journal_path).unwrap(),
+            &throttle,
+            false,
+            Some(&mut journal as &mut dyn Write),
+        )
+        .unwrap();
+        assert_eq!(counts, (3, 0, Vec::new()));
+        drop(journal);
+        assert_eq!(
+            std::fs::read_to_string(&journal_path).unwrap(),
+            "v4 mac=00:11:22:33:44:55 uuid-1\n\
+             v4 mac=00:11:22:33:44:66 uuid-2\n\
+             v6 duid=00:01:00:01:aa:bb uuid-3\n"
+        );
+
+        // Every placeholder has its own key, so the rerun skips all of them
+        let client = FakeClient {
+            responses: RefCell::new(Vec::new()),
+            requests: RefCell::new(Vec::new()),
+        };
+        let done = load_journal(&journal_path).unwrap();
+        std::fs::remove_file(&journal_path).unwrap();
+        let counts = import(&client, &plan, &done, &throttle, false, None).unwrap();
+        assert_eq!(counts, (0, 3, Vec::new()));
+        assert!(client.requests.borrow().is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+//! `audit`: compare live leases with the reservations of a converted config.
+//!
+//! Run against the output of `convert` and a current lease file (Kea
+//! `kea-leases4.csv` or ISC `dhcpd.leases`), it lists devices leasing an
+//! address other than their reservation, and fails when there are any, so
+//! a stale static map is caught before cutover.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+
+use super::summary::Summary;
+use super::AuditArgs;
+use crate::clock::rfc3339_utc;
+use crate::extract::parse_leases;
+use crate::term::SKIPPED;
+use crate::{audit_config, Clock, LeaseAudit};
+
+pub(crate) fn run_audit(args: AuditArgs) -> Result<()> {
+    let text = std::fs::read_to_string(&args.leases)
+        .with_context(|| format!("Failed to read lease file: {}", args.leases.display()))?;
+    let leases = parse_leases(&text);
+    let file = File::open(&args.r#in)
+        .with_context(|| format!("Failed to open input file: {}", args.r#in.display()))?;
+    let audit = audit_config(file, &args.backend, &leases, &Clock::System)?;
+
+    audit_summary(&audit).print();
+    if !audit.mismatched.is_empty() {
+        return Err(anyhow::anyhow!(
+            "audit: {} device(s) lease an address other than their reservation",
+            audit.mismatched.len()
+        ));
+    }
+    Ok(())
+}
+
+fn audit_summary(audit: &LeaseAudit) -> Summary {
+    let mut summary = Summary::default();
+    summary.row("Reservations checked", audit.reservations);
+    summary.row("Active leases", audit.active_leases);
+    summary.row("Leasing their reserved address", audit.matched);
+    summary.row("Not leasing", audit.not_leasing);
+    summary.count("Leasing another address", audit.mismatched.len(), SKIPPED);
+    for mismatch in &audit.mismatched {
+        let name = mismatch
+            .hostname
+            .as_deref()
+            .map(|name| format!(" ({})", name))
+            .unwrap_or_default();
+        let ends = mismatch
+            .lease_ends
+            .map(|ends| format!(" until {}", rfc3339_utc(ends)))
+            .unwrap_or_default();
+        summary.detail(format!(
+            "  {}{}: reserved {}, leasing {}{}",
+            mismatch.mac, name, mismatch.reserved_ip, mismatch.leased_ip, ends
+        ));
+    }
+    summary
+}
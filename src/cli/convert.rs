@@ -1,10 +1,23 @@
-use crate::{convert_config, MigrationOptions};
+use crate::migrate::peer_config;
+use crate::term;
+use crate::workspace::{Workspace, WorkspaceArea};
+use crate::xml_helpers::XmlDocument;
+use crate::{
+    validate_output, Backend, EmitMode, MigrationPlan, MigrationStats, OutputStyle, ParsedConfig,
+};
 use anyhow::{bail, Context, Result};
-use std::fs::{File, OpenOptions};
+use std::fs::File;
 use std::io;
+use std::path::Path;
+use xmltree::Element;
 
-use super::print_convert_stats;
+use super::cutover::{cutover_script, CutoverPlan};
+use super::export::write_file;
+use super::journal::Journal;
+use super::preview::{check_preview_dir, write_preview};
+use super::stats_log::append_stats_log;
 use super::ConvertArgs;
+use super::{print_convert_stats, print_limit_warnings, print_suppressed_warnings, print_timing};
 
 pub(crate) fn run_convert(args: ConvertArgs) -> Result<()> {
     // Critical safety check: prevent input == output
@@ -44,74 +57,274 @@ pub(crate) fn run_convert(args: ConvertArgs) -> Result<()> {
         }
     }
 
-    let input_file = File::open(&args.r#in)
+    let input = std::fs::read(&args.r#in)
         .with_context(|| format!("Failed to open input file: {}", args.r#in.display()))?;
 
-    if !args.force && args.out.exists() {
-        bail!(
-            "Output file already exists: {} (use --force to overwrite)",
-            args.out.display()
-        );
+    let merge_target = if args.merge_into {
+        let mut buffer = Vec::new();
+        File::open(&args.out)
+            .and_then(|mut file| io::Read::read_to_end(&mut file, &mut buffer))
+            .with_context(|| {
+                format!(
+                    "Failed to read merge target (--merge-into needs an existing output file): {}",
+                    args.out.display()
+                )
+            })?;
+        Some(buffer)
+    } else {
+        if !args.force && args.out.exists() {
+            bail!(
+                "Output file already exists: {} (use --force to overwrite or --merge-into to merge)",
+                args.out.display()
+            );
+        }
+        None
+    };
+
+    if let Some(script) = &args.emit_cutover_script {
+        if args.emit == EmitMode::ChangesOnly {
+            bail!(
+                "--emit-cutover-script installs a full config; it cannot be used with \
+                 --emit changes-only"
+            );
+        }
+        if !args.force && script.exists() {
+            bail!(
+                "Cutover script already exists: {} (use --force to overwrite)",
+                script.display()
+            );
+        }
     }
 
-    let tmp_path = args
-        .out
-        .with_extension(format!("tmp.{}", std::process::id()));
-    let mut tmp_file = OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(&tmp_path)
-        .with_context(|| {
-            format!(
-                "Failed to create temporary output file: {}",
-                tmp_path.display()
-            )
-        })?;
+    if let Some(peer) = &args.peer_out {
+        if args.emit == EmitMode::ChangesOnly {
+            bail!("--peer-out writes a full config; it cannot be used with --emit changes-only");
+        }
+        if !args.force && peer.exists() {
+            bail!(
+                "Peer output file already exists: {} (use --force to overwrite)",
+                peer.display()
+            );
+        }
+    }
 
-    let options = MigrationOptions {
-        fail_if_existing: args.fail_if_existing,
-        verbose: args.verbose,
-        backend: args.backend.clone(),
-        create_subnets: args.create_subnets,
-        force_subnets: args.force_subnets,
-        create_options: args.create_options,
-        force_options: args.force_options,
-        enable_backend: args.enable_backend,
-    };
+    if let Some(dir) = &args.emit_preview_dir {
+        if args.emit == EmitMode::ChangesOnly {
+            bail!(
+                "--emit-preview-dir reads a full config; it cannot be used with \
+                 --emit changes-only"
+            );
+        }
+        check_preview_dir(dir, &args.migration.backend, args.force)?;
+    }
 
-    let stats = match convert_config(input_file, &mut tmp_file, &options) {
-        Ok(stats) => stats,
-        Err(e) => {
-            let _ = std::fs::remove_file(&tmp_path);
-            return Err(e);
+    let mut options = args.migration.to_options()?;
+    options.emit = args.emit;
+    options.output_style = args.output_style;
+    if let Some(path) = &args.plan {
+        let plan = MigrationPlan::load(path)?;
+        if std::mem::discriminant(&plan.backend) != std::mem::discriminant(&options.backend) {
+            bail!(
+                "{} was planned for {}, not {}; pass the same --backend to apply it",
+                path.display(),
+                plan.backend,
+                options.backend
+            );
+        }
+        options.plan = Some(plan);
+    }
+
+    // The input is parsed once, to convert and to check the output against
+    let parsed = ParsedConfig::parse(io::Cursor::new(&input))?;
+
+    let mut journal = match &args.resume {
+        Some(path) => {
+            let journal = Journal::load(path)?;
+            options.offset = journal.offset;
+            Some(journal)
         }
+        None => None,
     };
 
-    if let Err(e) = tmp_file.sync_all() {
-        let _ = std::fs::remove_file(&tmp_path);
-        return Err(e).with_context(|| {
+    // The converted config is written into a workspace next to the output,
+    // so it moves into place with a rename; a failed run keeps the workspace
+    // with the converted config and the error for inspection
+    let mut workspace = Workspace::new_in(output_dir(&args.out)).with_context(|| {
+        format!(
+            "Failed to create a workspace next to {}",
+            args.out.display()
+        )
+    })?;
+    let result = (|| -> Result<MigrationStats> {
+        let name = args
+            .out
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("config.xml");
+        let (tmp_path, mut tmp_file) = workspace
+            .create_file(WorkspaceArea::Outputs, name)
+            .context("Failed to create temporary output file")?;
+
+        let result = match &merge_target {
+            Some(target) => parsed.merge(io::Cursor::new(target), &mut tmp_file, &options),
+            None => parsed.convert(&mut tmp_file, &options),
+        };
+        let stats = result?;
+        if let Some(journal) = journal.as_mut() {
+            journal.record(&stats, &args.migration.backend)?;
+        }
+        tmp_file.sync_all().with_context(|| {
             format!(
                 "Failed to sync temporary output file: {}",
                 tmp_path.display()
             )
-        });
-    }
-
-    if args.force && args.out.exists() {
-        std::fs::remove_file(&args.out).with_context(|| {
-            format!(
-                "Failed to remove existing output file: {}",
-                args.out.display()
-            )
         })?;
-    }
 
-    std::fs::rename(&tmp_path, &args.out)
-        .with_context(|| format!("Failed to replace output file: {}", args.out.display()))?;
+        // A full config is read back from disk and checked before it
+        // replaces anything
+        if args.emit == EmitMode::Full {
+            let target = merge_target
+                .as_deref()
+                .map(|target| Element::parse(io::Cursor::new(target)))
+                .transpose()
+                .context("Failed to re-parse merge target")?;
+            let before = target.as_ref().unwrap_or(parsed.root());
+            check_written_output(before, &tmp_path, &args.migration.backend)?;
+        }
+
+        if (args.force || args.merge_into) && args.out.exists() {
+            std::fs::remove_file(&args.out).with_context(|| {
+                format!(
+                    "Failed to remove existing output file: {}",
+                    args.out.display()
+                )
+            })?;
+        }
+        std::fs::rename(&tmp_path, &args.out)
+            .with_context(|| format!("Failed to replace output file: {}", args.out.display()))?;
+        Ok(stats)
+    })();
+    let stats = match result {
+        Ok(stats) => {
+            workspace.mark_success();
+            stats
+        }
+        Err(e) => {
+            let _ = workspace.write_file(WorkspaceArea::Reports, "error.log", format!("{:#}\n", e));
+            return Err(e.context(format!(
+                "{} was not written; the run is kept in {} for inspection",
+                args.out.display(),
+                workspace.path().display()
+            )));
+        }
+    };
+
+    let peer_unchanged = match &args.peer_out {
+        Some(path) => write_peer_config(&args.out, path, &options.output_style)?,
+        None => Vec::new(),
+    };
+    let preview = match &args.emit_preview_dir {
+        Some(dir) => write_preview(
+            &args.out,
+            dir,
+            &args.migration.backend,
+            &options.output_style,
+        )?,
+        None => Vec::new(),
+    };
 
     println!("\nMigration completed successfully!");
-    print_convert_stats(&stats, &args.backend);
+    print_convert_stats(&stats, &args.migration.backend);
     println!("Output written to: {}", args.out.display());
+    if let Some(path) = &args.emit_cutover_script {
+        let plan = CutoverPlan::new(&args.r#in, &args.out, &args.migration.backend, &stats)?;
+        write_file(path, &cutover_script(&plan))?;
+        println!("Cutover script written to: {}", path.display());
+    }
+    if let Some(path) = &args.peer_out {
+        println!("Peer config written to: {}", path.display());
+        if !peer_unchanged.is_empty() {
+            term::warning(format_args!(
+                "No ISC failover peer address for {}; \
+                 the peer config keeps this node's address there.",
+                peer_unchanged.join(", ")
+            ));
+        }
+    }
+    if !preview.is_empty() {
+        let names: Vec<String> = preview.iter().map(|p| p.display().to_string()).collect();
+        println!("Preview written to: {}", names.join(", "));
+    }
+    if args.emit == EmitMode::ChangesOnly {
+        print_changed_sections(&stats.changed_sections);
+    }
+    if let (Some(journal), Some(path)) = (&journal, &args.resume) {
+        journal.save(path)?;
+        print_journal(journal, path);
+    }
+    print_limit_warnings(&stats);
+    print_suppressed_warnings(&stats);
+    if args.migration.timing {
+        print_timing(&stats.timing);
+    }
+    if let Some(log) = &args.migration.stats_log {
+        append_stats_log(log, "convert", &args.r#in, &options, &stats)?;
+    }
+
+    Ok(())
+}
+
+/// Directory the file at `path` is written to.
+fn output_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
 
+/// Re-parse the output written to `path` and validate it against `before`.
+fn check_written_output(before: &Element, path: &Path, backend: &Backend) -> Result<()> {
+    let output = File::open(path)
+        .with_context(|| format!("Failed to read back output file: {}", path.display()))?;
+    validate_output(before, io::BufReader::new(output), backend)?;
     Ok(())
 }
+
+/// Write the converted config at `out` rewritten for the HA backup to
+/// `path`, returning the interfaces that keep this node's address.
+fn write_peer_config(out: &Path, path: &Path, style: &OutputStyle) -> Result<Vec<String>> {
+    let file = File::open(out)
+        .with_context(|| format!("Failed to read converted config: {}", out.display()))?;
+    let mut doc = XmlDocument::parse(file).context("Failed to parse converted config")?;
+    let unchanged = peer_config(&mut doc.root);
+    let mut xml = Vec::new();
+    doc.write(&mut xml, style)?;
+    write_file(path, &String::from_utf8(xml)?)?;
+    Ok(unchanged)
+}
+
+fn print_changed_sections(sections: &[String]) {
+    if sections.is_empty() {
+        println!("No sections changed; the output file is empty.");
+    } else {
+        println!("Changed sections written: {}", sections.join(", "));
+    }
+}
+
+fn print_journal(journal: &Journal, path: &std::path::Path) {
+    println!(
+        "Resume journal: {} (run {}, {} of {} mappings done, {} remaining)",
+        path.display(),
+        journal.runs,
+        journal.offset.min(journal.total),
+        journal.total,
+        journal.remaining()
+    );
+    println!(
+        "Totals across runs: {} reservations created, {} (v6) created, {} skipped, {} (v6) skipped",
+        journal.reservations_created,
+        journal.reservations_v6_created,
+        journal.reservations_skipped,
+        journal.reservations_v6_skipped
+    );
+}
@@ -0,0 +1,286 @@
+//! Cutover script for the firewall (`convert --emit-cutover-script`).
+//!
+//! The script backs up `/conf/config.xml`, installs the converted config,
+//! restarts ISC DHCP so it stops on the interfaces the new config disables
+//! it on, restarts the new backend and checks that it answers. A failed
+//! step restores the backup and brings ISC DHCP back.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::io::Read;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use xmltree::Element;
+
+use crate::extract::iface_display;
+use crate::xml_helpers::get_child_ci;
+use crate::{Backend, MigrationStats};
+
+/// What the script switches over.
+#[derive(Debug, Default)]
+pub(crate) struct CutoverPlan {
+    /// File name of the converted config, looked for in `/tmp`
+    pub(crate) config_name: String,
+    /// `configctl` services to restart for the new backend
+    pub(crate) services: Vec<&'static str>,
+    /// Daemons that must be running afterwards
+    pub(crate) daemons: Vec<&'static str>,
+    /// Interfaces moved off ISC DHCP, for the script's comments
+    pub(crate) interfaces: Vec<String>,
+    /// IPv4 addresses of those interfaces, checked with `dhcping`
+    pub(crate) addresses: Vec<String>,
+}
+
+impl CutoverPlan {
+    /// Plan the cutover of a conversion of `input` with `stats`.
+    pub(crate) fn new(
+        input: &Path,
+        out: &Path,
+        backend: &Backend,
+        stats: &MigrationStats,
+    ) -> anyhow::Result<Self> {
+        let backends: Vec<(&Backend, &MigrationStats)> = if stats.per_backend.is_empty() {
+            vec![(backend, stats)]
+        } else {
+            stats.per_backend.iter().map(|(b, s)| (b, s)).collect()
+        };
+
+        let mut plan = CutoverPlan {
+            config_name: out
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "config.xml.new".to_string()),
+            ..Default::default()
+        };
+        let mut ifaces_v4 = BTreeSet::new();
+        let mut ifaces = BTreeSet::new();
+        for (backend, stats) in backends {
+            ifaces_v4.extend(stats.isc_disabled_v4.iter().cloned());
+            ifaces.extend(stats.isc_disabled_v4.iter().cloned());
+            ifaces.extend(stats.isc_disabled_v6.iter().cloned());
+            match backend {
+                Backend::Kea => {
+                    plan.services.push("kea");
+                    if !stats.isc_disabled_v4.is_empty() {
+                        plan.daemons.push("kea-dhcp4");
+                    }
+                    if !stats.isc_disabled_v6.is_empty() {
+                        plan.daemons.push("kea-dhcp6");
+                    }
+                }
+                Backend::Dnsmasq => {
+                    plan.services.push("dnsmasq");
+                    plan.daemons.push("dnsmasq");
+                }
+                Backend::Both => {}
+            }
+        }
+
+        let addresses = interface_addresses(input)?;
+        plan.addresses = ifaces_v4
+            .iter()
+            .filter_map(|iface| addresses.get(iface).cloned())
+            .collect();
+        plan.interfaces = ifaces
+            .iter()
+            .map(|iface| iface_display(iface, &stats.interface_labels))
+            .collect();
+        Ok(plan)
+    }
+}
+
+/// Static IPv4 address of each interface in the config at `path`.
+fn interface_addresses(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let mut text = String::new();
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let root = Element::parse(text.as_bytes())?;
+    let Some(interfaces) = get_child_ci(&root, "interfaces") else {
+        return Ok(HashMap::new());
+    };
+    Ok(interfaces
+        .children
+        .iter()
+        .filter_map(|node| node.as_element())
+        .filter_map(|iface| {
+            let ip = get_child_ci(iface, "ipaddr")?.get_text()?;
+            let ip = ip.trim().parse::<Ipv4Addr>().ok()?;
+            Some((iface.name.clone(), ip.to_string()))
+        })
+        .collect())
+}
+
+/// The `/bin/sh` script carrying out `plan`.
+pub(crate) fn cutover_script(plan: &CutoverPlan) -> String {
+    let mut script = String::new();
+    let _ = writeln!(
+        script,
+        "#!/bin/sh\n\
+         # ISC DHCP to {} cutover, generated by isc2kea {}.\n\
+         #\n\
+         # Copy the converted config to /tmp/{} on the firewall and run this\n\
+         # script there as root (sh cutover.sh). /conf/config.xml is backed up\n\
+         # first and restored when a step or health check fails. To restore a\n\
+         # backup later: sh cutover.sh rollback <backup>\n\
+         #\n\
+         # Interfaces moved off ISC DHCP: {}",
+        plan.services.join(" and "),
+        env!("CARGO_PKG_VERSION"),
+        comment_text(&plan.config_name),
+        if plan.interfaces.is_empty() {
+            "none".to_string()
+        } else {
+            comment_text(&plan.interfaces.join(", "))
+        }
+    );
+    let _ = writeln!(
+        script,
+        "\nset -u\n\n\
+         CONFIG=/conf/config.xml\n\
+         DEFAULT_CONFIG={}\n\
+         NEW_CONFIG=\"${{NEW_CONFIG:-$DEFAULT_CONFIG}}\"\n\
+         BACKUP=\"/conf/config.xml.pre-isc2kea.$(date +%Y%m%d%H%M%S)\"",
+        shell_quote(&format!("/tmp/{}", plan.config_name))
+    );
+
+    script.push_str("\nrollback() {\n    echo \"Rolling back to $1\" >&2\n");
+    script.push_str(
+        "    cp -p \"$1\" \"$CONFIG\" || { echo \"Restore failed; the backup is $1\" >&2; exit 2; }\n",
+    );
+    for service in &plan.services {
+        let _ = writeln!(script, "    configctl {} stop", service);
+    }
+    script.push_str("    configctl dhcpd restart\n    configctl dhcpd6 restart\n    exit 1\n}\n");
+    script.push_str(
+        "\nif [ \"${1:-}\" = rollback ]; then\n    \
+         [ -f \"${2:-}\" ] || { echo \"usage: $0 rollback <backup>\" >&2; exit 2; }\n    \
+         rollback \"$2\"\nfi\n\n\
+         [ \"$(id -u)\" -eq 0 ] || { echo \"Run as root\" >&2; exit 2; }\n\
+         [ -f \"$NEW_CONFIG\" ] || { echo \"Converted config not found: $NEW_CONFIG\" >&2; exit 2; }\n",
+    );
+
+    script.push_str(
+        "\necho \"Backing up $CONFIG to $BACKUP\"\n\
+         cp -p \"$CONFIG\" \"$BACKUP\" || exit 2\n\
+         \necho \"Installing $NEW_CONFIG\"\n\
+         cp \"$NEW_CONFIG\" \"$CONFIG\" || rollback \"$BACKUP\"\n\
+         \n# ISC DHCP stops on the interfaces the new config disables it on\n\
+         echo \"Restarting ISC DHCP\"\n\
+         configctl dhcpd restart || rollback \"$BACKUP\"\n\
+         configctl dhcpd6 restart || rollback \"$BACKUP\"\n",
+    );
+    for service in &plan.services {
+        let _ = writeln!(
+            script,
+            "\necho \"Restarting {0}\"\nconfigctl {0} restart || rollback \"$BACKUP\"",
+            service
+        );
+    }
+
+    script.push_str("\n# Health checks\nsleep 5\n");
+    for daemon in &plan.daemons {
+        let _ = writeln!(
+            script,
+            "pgrep -x {0} >/dev/null || {{ echo \"{0} is not running\" >&2; rollback \"$BACKUP\"; }}",
+            daemon
+        );
+    }
+    if !plan.addresses.is_empty() {
+        let _ = writeln!(
+            script,
+            "if command -v dhcping >/dev/null 2>&1; then\n    \
+             for addr in {}; do\n        \
+             dhcping -q -t 5 -s \"$addr\" || {{ echo \"No DHCP answer from $addr\" >&2; rollback \"$BACKUP\"; }}\n    \
+             done\n\
+             else\n    \
+             echo \"dhcping is not installed (pkg install dhcping); skipping DHCP answer checks\"\n\
+             fi",
+            plan.addresses.join(" ")
+        );
+    }
+    script.push_str("\necho \"Cutover complete. Previous config: $BACKUP\"\n");
+    script
+}
+
+/// `text` for a script comment, with line breaks and other control
+/// characters (which would end the comment) replaced by spaces.
+fn comment_text(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// `text` as one single-quoted shell word.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cutover_script() {
+        let plan = CutoverPlan {
+            config_name: "config.xml.new".to_string(),
+            services: vec!["kea"],
+            daemons: vec!["kea-dhcp4"],
+            interfaces: vec!["lan".to_string(), "opt1 (IoT)".to_string()],
+            addresses: vec!["192.168.1.1".to_string(), "10.0.0.1".to_string()],
+        };
+        let script = cutover_script(&plan);
+        assert!(script.starts_with("#!/bin/sh\n# ISC DHCP to kea cutover"));
+        assert!(script.contains("# Interfaces moved off ISC DHCP: lan, opt1 (IoT)"));
+        assert!(script.contains(
+            "DEFAULT_CONFIG='/tmp/config.xml.new'\nNEW_CONFIG=\"${NEW_CONFIG:-$DEFAULT_CONFIG}\""
+        ));
+        assert!(script.contains("    configctl kea stop\n    configctl dhcpd restart\n"));
+        assert!(script.contains("configctl kea restart || rollback \"$BACKUP\""));
+        assert!(script.contains("pgrep -x kea-dhcp4 >/dev/null ||"));
+        assert!(script.contains("for addr in 192.168.1.1 10.0.0.1; do"));
+
+        // The ISC restart comes before the new backend starts
+        let isc = script.find("configctl dhcpd restart ||").unwrap();
+        let kea = script.find("configctl kea restart").unwrap();
+        assert!(isc < kea);
+
+        let script = cutover_script(&CutoverPlan {
+            addresses: Vec::new(),
+            ..plan
+        });
+        assert!(!script.contains("dhcping"));
+    }
+
+    #[test]
+    fn test_cutover_script_escapes_config_values() {
+        let name = "it's \"$(touch pwned)`id`.xml";
+        let script = cutover_script(&CutoverPlan {
+            config_name: name.to_string(),
+            services: vec!["kea"],
+            interfaces: vec!["lan (Office\nrm -rf /)".to_string()],
+            ..Default::default()
+        });
+        assert!(script.contains("# Interfaces moved off ISC DHCP: lan (Office rm -rf /)\n"));
+        assert!(!script.lines().any(|line| line.starts_with("rm ")));
+
+        // The shell reads the name back unchanged, running nothing
+        let assignments: Vec<&str> = script
+            .lines()
+            .filter(|line| line.starts_with("DEFAULT_CONFIG=") || line.starts_with("NEW_CONFIG="))
+            .collect();
+        assert_eq!(assignments.len(), 2);
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "unset NEW_CONFIG\n{}\nprintf %s \"$NEW_CONFIG\"",
+                assignments.join("\n")
+            ))
+            .output()
+            .expect("run sh");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("/tmp/{}", name)
+        );
+    }
+}
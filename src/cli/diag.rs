@@ -0,0 +1,106 @@
+//! `diag`: a redacted diagnostics bundle for bug reports.
+//!
+//! The bundle is one text file: the tool version and platform, a summary
+//! of the input, the scan result as JSON, and the DHCP-related sections of
+//! the config. Everything is taken from the input after [`Redactor`] has
+//! pseudonymized it, so the bundle can be attached to a public issue.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use xmltree::{Element, EmitterConfig};
+
+use super::export::write_file;
+use super::redact::Redactor;
+use super::{stats_json, DiagArgs};
+use crate::scan_config;
+use crate::xml_helpers::{count_elements, find_descendant_ci, get_child_ci};
+
+/// Sections copied into the bundle.
+const SECTIONS: &[&str] = &["interfaces", "dhcpd", "dhcpdv6", "Kea", "dnsmasq"];
+
+pub(crate) fn run_diag(args: DiagArgs) -> Result<()> {
+    let input = std::fs::read(&args.r#in)
+        .with_context(|| format!("Failed to read input file: {}", args.r#in.display()))?;
+    let mut options = args.migration.to_options()?;
+    let redactor = Redactor::new();
+    redactor.redact_leases(&mut options.leases);
+
+    let mut bundle = String::new();
+    writeln!(bundle, "# isc2kea diagnostics")?;
+    writeln!(
+        bundle,
+        "# MACs, DUIDs, client IDs and hostnames are pseudonyms that only match\n\
+         # within this bundle. IP addresses and descriptions are kept."
+    )?;
+    writeln!(bundle, "\n## Environment")?;
+    writeln!(
+        bundle,
+        "isc2kea {} ({} {})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    writeln!(bundle, "backend: {}", args.migration.backend)?;
+
+    writeln!(bundle, "\n## Input")?;
+    writeln!(bundle, "size: {} bytes", input.len())?;
+    let redacted = match redactor.redact_config(&input) {
+        Ok(redacted) => redacted,
+        Err(e) => {
+            // Nothing of the config can be shown without parsing it
+            writeln!(bundle, "error: {:#}", e)?;
+            return write_bundle(&args, &bundle);
+        }
+    };
+    let root = Element::parse(Cursor::new(&redacted)).context("Failed to parse XML")?;
+    writeln!(bundle, "root: <{}>", root.name)?;
+    if let Some(version) = get_child_ci(&root, "version").and_then(|v| v.get_text()) {
+        writeln!(bundle, "version: {}", version.trim())?;
+    }
+    for name in SECTIONS {
+        match find_descendant_ci(&root, name) {
+            Some(section) => writeln!(bundle, "{}: {} elements", name, count_elements(section))?,
+            None => writeln!(bundle, "{}: missing", name)?,
+        }
+    }
+
+    writeln!(bundle, "\n## Scan")?;
+    match scan_config(Cursor::new(&redacted), &options) {
+        Ok(stats) => bundle.push_str(&stats_json(&stats)),
+        Err(e) => writeln!(bundle, "error: {:#}", e)?,
+    }
+
+    writeln!(bundle, "\n## Config")?;
+    for name in SECTIONS {
+        let Some(section) = find_descendant_ci(&root, name) else {
+            continue;
+        };
+        let mut xml = Vec::new();
+        section.write_with_config(
+            &mut xml,
+            EmitterConfig::new()
+                .perform_indent(true)
+                .indent_string("  ")
+                .write_document_declaration(false),
+        )?;
+        bundle.push_str(&String::from_utf8_lossy(&xml));
+        bundle.push('\n');
+    }
+
+    write_bundle(&args, &bundle)
+}
+
+fn write_bundle(args: &DiagArgs, bundle: &str) -> Result<()> {
+    match &args.out {
+        Some(path) => {
+            write_file(path, bundle)?;
+            eprintln!("Wrote diagnostics bundle to {}", path.display());
+            Ok(())
+        }
+        None => std::io::stdout()
+            .lock()
+            .write_all(bundle.as_bytes())
+            .context("Failed to write diagnostics bundle"),
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{export_config, export_config_split};
+use anyhow::{bail, Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use super::summary::Summary;
+use super::{disabled_iface_rows, ExportArgs};
+use crate::term::{ADDED, SKIPPED};
+use crate::{ExportFile, ExportFormat, MigrationOptions, MigrationStats};
+
+pub(crate) fn run_export(args: ExportArgs) -> Result<()> {
+    let input_file = File::open(&args.r#in)
+        .with_context(|| format!("Failed to open input file: {}", args.r#in.display()))?;
+
+    let options = MigrationOptions {
+        verbose: args.verbose,
+        reservation_order: args.sort_reservations,
+        include_disabled_interfaces: args.include_disabled_interfaces,
+        orphaned_ifaces: args.orphaned_interfaces,
+        source: args.source,
+        merge_ranges: args.merge_ranges,
+        ..Default::default()
+    };
+    let (files, stats) = if args.split_output_per_subnet {
+        // Kea resolves include paths from its working directory
+        export_config_split(input_file, &absolute(&args.out), args.format, &options)?
+    } else {
+        let mut buffer = Vec::new();
+        let stats = export_config(input_file, &mut buffer, args.format, &options)?;
+        let file = ExportFile {
+            path: args.out.clone(),
+            contents: String::from_utf8(buffer).context("Export is not valid UTF-8")?,
+        };
+        (vec![file], stats)
+    };
+
+    if !args.force {
+        if let Some(existing) = files.iter().find(|f| f.path.exists()) {
+            bail!(
+                "Output file already exists: {} (use --force to overwrite)",
+                existing.path.display()
+            );
+        }
+    }
+    for file in &files {
+        write_file(&file.path, &file.contents)?;
+    }
+
+    println!("\nExport completed successfully!");
+    print_export_stats(&stats, args.format);
+    println!("Output written to: {}", args.out.display());
+    if files.len() > 1 {
+        println!("Subnet include files written: {}", files.len() - 1);
+    }
+
+    Ok(())
+}
+
+/// `path` with its parent directory made absolute.
+fn absolute(path: &Path) -> std::path::PathBuf {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (std::fs::canonicalize(parent), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Write `contents` to a temporary file next to `path`, then move it into place.
+pub(crate) fn write_file(path: &Path, contents: &str) -> Result<()> {
+    let tmp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .with_context(|| {
+            format!(
+                "Failed to write temporary output file: {}",
+                tmp_path.display()
+            )
+        });
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    if path.exists() {
+        std::fs::remove_file(path).with_context(|| {
+            format!("Failed to remove existing output file: {}", path.display())
+        })?;
+    }
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to replace output file: {}", path.display()))
+}
+
+fn print_export_stats(stats: &MigrationStats, format: ExportFormat) {
+    let mut summary = Summary::default();
+    summary.row("ISC DHCP static mappings found", stats.isc_mappings_found);
+    summary.row(
+        "ISC DHCPv6 static mappings found",
+        stats.isc_mappings_v6_found,
+    );
+    disabled_iface_rows(&mut summary, stats);
+    summary.row("Subnets exported", stats.target_subnets_found);
+    if stats.ranges_merged > 0 {
+        summary.row("ISC ranges merged", stats.ranges_merged);
+    }
+    if format != ExportFormat::KeaDhcp6 {
+        summary.count("Reservations exported", stats.reservations_to_create, ADDED);
+    }
+    if format != ExportFormat::KeaDhcp4 {
+        summary.count(
+            "Reservations (v6) exported",
+            stats.reservations_v6_to_create,
+            ADDED,
+        );
+    }
+    if stats.custom_options_exported > 0 {
+        summary.row(
+            "Custom options exported",
+            format!(
+                "{} ({} option-def)",
+                stats.custom_options_exported, stats.option_defs_exported
+            ),
+        );
+    }
+    if stats.reservations_skipped + stats.reservations_v6_skipped > 0 {
+        summary.count(
+            "Reservations skipped (not representable)",
+            stats.reservations_skipped + stats.reservations_v6_skipped,
+            SKIPPED,
+        );
+    }
+    if matches!(format, ExportFormat::Routeros | ExportFormat::Uci) && stats.isc_ranges_v6_found > 0
+    {
+        summary.count(
+            "ISC DHCPv6 ranges not exported",
+            stats.isc_ranges_v6_found,
+            SKIPPED,
+        );
+    }
+    summary.print();
+}
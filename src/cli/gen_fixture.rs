@@ -0,0 +1,28 @@
+//! `gen-fixture`: a synthetic config.xml from [`generate_fixture`].
+
+use anyhow::{Context, Result};
+use std::io::Write;
+
+use super::export::write_file;
+use super::GenFixtureArgs;
+use crate::generate_fixture;
+
+pub(crate) fn run_gen_fixture(args: GenFixtureArgs) -> Result<()> {
+    let xml = generate_fixture(&args.spec)?;
+    match &args.out {
+        Some(path) => {
+            write_file(path, &xml)?;
+            eprintln!(
+                "Wrote {} interfaces with {} static mappings each to {}",
+                args.spec.interfaces,
+                args.spec.maps_per_iface,
+                path.display()
+            );
+            Ok(())
+        }
+        None => std::io::stdout()
+            .lock()
+            .write_all(xml.as_bytes())
+            .context("Failed to write fixture"),
+    }
+}
@@ -0,0 +1,436 @@
+//! `inspect`: list what the target backend already has in a config.xml.
+//!
+//! No ISC data is read. Each kind of entry (subnets, reservations, hosts,
+//! ranges, options) becomes one table, printed aligned or as a JSON object
+//! of arrays, so the Kea or dnsmasq state can be snapshotted before and
+//! after a migration and compared.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::Cursor;
+use xmltree::Element;
+
+use super::{json_string, InspectArgs};
+use crate::{Backend, InspectFormat};
+
+/// One kind of entry: a name, column headers and a row per entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InspectTable {
+    /// JSON member name, e.g. `kea_subnet4`
+    pub(crate) name: &'static str,
+    pub(crate) title: &'static str,
+    pub(crate) columns: &'static [&'static str],
+    pub(crate) rows: Vec<Vec<String>>,
+}
+
+pub(crate) fn run_inspect(args: InspectArgs) -> Result<()> {
+    let input = std::fs::read(&args.r#in)
+        .with_context(|| format!("Failed to read input file: {}", args.r#in.display()))?;
+    let root = Element::parse(Cursor::new(&input)).context("Failed to parse XML")?;
+    let tables = inspect_tables(&root, &args.backend);
+    match args.format {
+        InspectFormat::Table => print!("{}", render_tables(&tables)),
+        InspectFormat::Json => print!("{}", render_json(&tables)),
+    }
+    Ok(())
+}
+
+/// The tables for `backend`; [`Backend::Both`] lists Kea, then dnsmasq.
+#[cfg_attr(
+    not(any(feature = "kea", feature = "dnsmasq")),
+    allow(unused_variables, unused_mut)
+)]
+pub(crate) fn inspect_tables(root: &Element, backend: &Backend) -> Vec<InspectTable> {
+    let mut tables = Vec::new();
+    #[cfg(feature = "kea")]
+    if matches!(backend, Backend::Kea | Backend::Both) {
+        tables.extend(kea_tables(root));
+    }
+    #[cfg(feature = "dnsmasq")]
+    if matches!(backend, Backend::Dnsmasq | Backend::Both) {
+        tables.extend(dnsmasq_tables(root));
+    }
+    tables
+}
+
+#[cfg(feature = "kea")]
+fn kea_tables(root: &Element) -> Vec<InspectTable> {
+    use crate::extract::{
+        kea_reservations_v4, kea_reservations_v6, kea_subnet4_elements, kea_subnet6_elements,
+    };
+    use crate::model::{Subnet4Node, Subnet6Node};
+
+    let mut subnet_cidrs = HashMap::new();
+    let mut options = Vec::new();
+    let subnets4: Vec<Vec<String>> = kea_subnet4_elements(root)
+        .filter_map(|el| Some((el, Subnet4Node::from_element(el)?)))
+        .map(|(el, node)| {
+            options.extend(option_rows(&node.subnet, el));
+            subnet_cidrs.insert(node.uuid.clone(), node.subnet.clone());
+            vec![
+                node.subnet,
+                node.interface,
+                node.pools.join(","),
+                node.description,
+                node.uuid,
+            ]
+        })
+        .collect();
+    let subnets6: Vec<Vec<String>> = kea_subnet6_elements(root)
+        .filter_map(|el| Some((el, Subnet6Node::from_element(el)?)))
+        .map(|(el, node)| {
+            options.extend(option_rows(&node.subnet, el));
+            subnet_cidrs.insert(node.uuid.clone(), node.subnet.clone());
+            vec![
+                node.subnet,
+                node.interface,
+                node.pools.join(","),
+                node.description,
+                node.uuid,
+            ]
+        })
+        .collect();
+    // Reservations show the CIDR of their subnet, or its uuid when dangling
+    let cidr = |uuid: &str| subnet_cidrs.get(uuid).cloned().unwrap_or(uuid.to_string());
+
+    let reservations4 = kea_reservations_v4(root)
+        .map(|r| {
+            // Per-host overrides of the subnet's options
+            for (option, value) in [
+                ("domain_name_servers", &r.domain_name_servers),
+                ("routers", &r.routers),
+            ] {
+                if !value.is_empty() {
+                    options.push(vec![
+                        r.ip_address.clone(),
+                        option.to_string(),
+                        value.clone(),
+                    ]);
+                }
+            }
+            vec![
+                r.ip_address,
+                r.hw_address,
+                r.client_id.unwrap_or_default(),
+                r.hostname.unwrap_or_default(),
+                cidr(&r.subnet),
+                r.description.unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let reservations6 = kea_reservations_v6(root)
+        .map(|r| {
+            vec![
+                r.ip_address,
+                r.duid,
+                r.prefixes.unwrap_or_default(),
+                r.hostname.unwrap_or_default(),
+                cidr(&r.subnet),
+                r.description.unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    vec![
+        InspectTable {
+            name: "kea_subnet4",
+            title: "Kea subnet4",
+            columns: &["subnet", "interface", "pools", "description", "uuid"],
+            rows: subnets4,
+        },
+        InspectTable {
+            name: "kea_subnet6",
+            title: "Kea subnet6",
+            columns: &["subnet", "interface", "pools", "description", "uuid"],
+            rows: subnets6,
+        },
+        InspectTable {
+            name: "kea_reservations_v4",
+            title: "Kea reservations (v4)",
+            columns: &[
+                "ip_address",
+                "hw_address",
+                "client_id",
+                "hostname",
+                "subnet",
+                "description",
+            ],
+            rows: reservations4,
+        },
+        InspectTable {
+            name: "kea_reservations_v6",
+            title: "Kea reservations (v6)",
+            columns: &[
+                "ip_address",
+                "duid",
+                "prefixes",
+                "hostname",
+                "subnet",
+                "description",
+            ],
+            rows: reservations6,
+        },
+        InspectTable {
+            name: "kea_options",
+            title: "Kea options",
+            columns: &["scope", "option", "value"],
+            rows: options,
+        },
+    ]
+}
+
+/// Set `<option_data>` fields of a Kea subnet, scoped to `scope`.
+#[cfg(feature = "kea")]
+fn option_rows(scope: &str, el: &Element) -> Vec<Vec<String>> {
+    crate::xml_helpers::get_child_ci(el, "option_data")
+        .into_iter()
+        .flat_map(|option_data| option_data.children.iter())
+        .filter_map(|child| child.as_element())
+        .filter_map(|field| {
+            let value = field.get_text()?.trim().to_string();
+            if value.is_empty() {
+                return None;
+            }
+            Some(vec![scope.to_string(), field.name.clone(), value])
+        })
+        .collect()
+}
+
+#[cfg(feature = "dnsmasq")]
+fn dnsmasq_tables(root: &Element) -> Vec<InspectTable> {
+    use crate::extract_dnsmasq::dnsmasq_children;
+    use crate::model::{
+        DnsmasqBootNode, DnsmasqHostNode, DnsmasqOptionNode, DnsmasqRangeNode, DnsmasqTagNode,
+    };
+
+    // Hosts and options refer to tags by uuid
+    let tags: HashMap<String, String> = dnsmasq_children(root)
+        .filter_map(DnsmasqTagNode::from_element)
+        .map(|tag| (tag.uuid, tag.tag))
+        .collect();
+    let tag_names = |uuids: &str| -> String {
+        uuids
+            .split(',')
+            .filter(|uuid| !uuid.is_empty())
+            .map(|uuid| tags.get(uuid).map_or(uuid, String::as_str))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    let ranges = dnsmasq_children(root)
+        .filter_map(DnsmasqRangeNode::from_element)
+        .map(|r| {
+            let size = if r.prefix_len.is_empty() {
+                r.subnet_mask
+            } else {
+                format!("/{}", r.prefix_len)
+            };
+            vec![
+                r.interface,
+                r.start_addr,
+                r.end_addr,
+                size,
+                r.mode,
+                r.description,
+            ]
+        })
+        .collect();
+    let hosts = dnsmasq_children(root)
+        .filter_map(DnsmasqHostNode::from_element)
+        .map(|h| {
+            vec![
+                h.ip,
+                h.hwaddr,
+                h.client_id.unwrap_or_default(),
+                h.host,
+                h.domain,
+                tag_names(&h.set_tag),
+                h.descr.unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let options = dnsmasq_children(root)
+        .filter_map(DnsmasqOptionNode::from_element)
+        .map(|o| {
+            let option = if o.option.is_empty() {
+                format!("v6:{}", o.option6)
+            } else {
+                o.option
+            };
+            vec![
+                o.option_type,
+                o.interface,
+                tag_names(&o.tag),
+                option,
+                o.value,
+                o.description,
+            ]
+        })
+        .collect();
+    let boot = dnsmasq_children(root)
+        .filter_map(DnsmasqBootNode::from_element)
+        .map(|b| {
+            vec![
+                b.interface,
+                tag_names(&b.tag),
+                b.filename,
+                b.servername,
+                b.address,
+                b.description,
+            ]
+        })
+        .collect();
+
+    vec![
+        InspectTable {
+            name: "dnsmasq_ranges",
+            title: "dnsmasq ranges",
+            columns: &["interface", "start", "end", "mask", "mode", "description"],
+            rows: ranges,
+        },
+        InspectTable {
+            name: "dnsmasq_hosts",
+            title: "dnsmasq hosts",
+            columns: &[
+                "ip",
+                "hwaddr",
+                "client_id",
+                "host",
+                "domain",
+                "set_tag",
+                "descr",
+            ],
+            rows: hosts,
+        },
+        InspectTable {
+            name: "dnsmasq_options",
+            title: "dnsmasq options",
+            columns: &["type", "interface", "tag", "option", "value", "description"],
+            rows: options,
+        },
+        InspectTable {
+            name: "dnsmasq_boot",
+            title: "dnsmasq boot",
+            columns: &[
+                "interface",
+                "tag",
+                "filename",
+                "servername",
+                "address",
+                "description",
+            ],
+            rows: boot,
+        },
+    ]
+}
+
+/// Each table under a `title (count)` heading, columns padded to their
+/// widest value.
+pub(crate) fn render_tables(tables: &[InspectTable]) -> String {
+    let mut out = String::new();
+    for (idx, table) in tables.iter().enumerate() {
+        if idx > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("{} ({})\n", table.title, table.rows.len()));
+        if table.rows.is_empty() {
+            out.push_str("  (none)\n");
+            continue;
+        }
+        let mut widths: Vec<usize> = table.columns.iter().map(|c| c.len()).collect();
+        for row in &table.rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+        let line = |values: Vec<&str>| {
+            let cells: Vec<String> = values
+                .iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{:<width$}", value, width = width))
+                .collect();
+            format!("  {}\n", cells.join("  ").trim_end())
+        };
+        out.push_str(&line(table.columns.to_vec()));
+        for row in &table.rows {
+            out.push_str(&line(row.iter().map(String::as_str).collect()));
+        }
+    }
+    out
+}
+
+/// One array of objects per table, keyed by the column names.
+pub(crate) fn render_json(tables: &[InspectTable]) -> String {
+    let members: Vec<String> = tables
+        .iter()
+        .map(|table| {
+            let rows: Vec<String> = table
+                .rows
+                .iter()
+                .map(|row| {
+                    let fields: Vec<String> = table
+                        .columns
+                        .iter()
+                        .zip(row)
+                        .map(|(column, value)| {
+                            format!("{}: {}", json_string(column), json_string(value))
+                        })
+                        .collect();
+                    format!("    {{{}}}", fields.join(", "))
+                })
+                .collect();
+            if rows.is_empty() {
+                format!("  {}: []", json_string(table.name))
+            } else {
+                format!(
+                    "  {}: [\n{}\n  ]",
+                    json_string(table.name),
+                    rows.join(",\n")
+                )
+            }
+        })
+        .collect();
+    format!("{{\n{}\n}}\n", members.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: Vec<Vec<&str>>) -> InspectTable {
+        InspectTable {
+            name: "kea_subnet4",
+            title: "Kea subnet4",
+            columns: &["subnet", "interface"],
+            rows: rows
+                .into_iter()
+                .map(|row| row.into_iter().map(str::to_string).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_render_tables() {
+        let tables = [
+            table(vec![vec!["192.168.1.0/24", "lan"], vec!["10.0.0.0/8", ""]]),
+            table(Vec::new()),
+        ];
+        assert_eq!(
+            render_tables(&tables),
+            "Kea subnet4 (2)\n\
+             \x20 subnet          interface\n\
+             \x20 192.168.1.0/24  lan\n\
+             \x20 10.0.0.0/8\n\
+             \n\
+             Kea subnet4 (0)\n\
+             \x20 (none)\n"
+        );
+        assert_eq!(
+            render_json(&tables),
+            "{\n  \"kea_subnet4\": [\n    \
+             {\"subnet\": \"192.168.1.0/24\", \"interface\": \"lan\"},\n    \
+             {\"subnet\": \"10.0.0.0/8\", \"interface\": \"\"}\n  ],\n  \
+             \"kea_subnet4\": []\n}\n"
+        );
+    }
+}
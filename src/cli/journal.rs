@@ -0,0 +1,203 @@
+//! Resume journal for `convert --resume`.
+//!
+//! The journal is a small `key=value` file recording how far batched runs
+//! have got and the totals across them, so each run picks up at the next
+//! `--offset`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::export::write_file;
+use crate::{Backend, MappingBatch, MigrationStats};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Journal {
+    pub(crate) backend: String,
+    pub(crate) offset: usize,
+    pub(crate) total: usize,
+    pub(crate) runs: usize,
+    pub(crate) reservations_created: usize,
+    pub(crate) reservations_v6_created: usize,
+    pub(crate) reservations_skipped: usize,
+    pub(crate) reservations_v6_skipped: usize,
+}
+
+impl Journal {
+    /// Read the journal at `path`, or start a new one if it does not exist.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read resume journal: {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Invalid resume journal: {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let mut journal = Self::default();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got '{}'", line))?;
+            let (key, value) = (key.trim(), value.trim());
+            if key == "backend" {
+                journal.backend = value.to_string();
+                continue;
+            }
+            let count: usize = value
+                .parse()
+                .map_err(|_| anyhow!("'{}' is not a count for {}", value, key))?;
+            match key {
+                "offset" => journal.offset = count,
+                "total" => journal.total = count,
+                "runs" => journal.runs = count,
+                "reservations_created" => journal.reservations_created = count,
+                "reservations_v6_created" => journal.reservations_v6_created = count,
+                "reservations_skipped" => journal.reservations_skipped = count,
+                "reservations_v6_skipped" => journal.reservations_v6_skipped = count,
+                _ => bail!("unknown key '{}'", key),
+            }
+        }
+        Ok(journal)
+    }
+
+    /// Add a finished run. Fails if the set of ISC mappings or the backend
+    /// changed since earlier runs, since the offset would no longer line up.
+    pub(crate) fn record(&mut self, stats: &MigrationStats, backend: &Backend) -> Result<()> {
+        // Without --limit or an offset every mapping was migrated
+        let batch = stats.batch.unwrap_or_else(|| {
+            let total = stats.isc_mappings_found + stats.isc_mappings_v6_found;
+            MappingBatch {
+                offset: 0,
+                selected: total,
+                total,
+            }
+        });
+        let backend = backend.to_string();
+        if self.runs > 0 {
+            if self.backend != backend {
+                bail!(
+                    "Resume journal was written for backend {}, not {}. Use a new journal.",
+                    self.backend,
+                    backend
+                );
+            }
+            if self.total != batch.total {
+                bail!(
+                    "ISC static mappings changed since the resume journal was written \
+                     ({} then, {} now). Use a new journal.",
+                    self.total,
+                    batch.total
+                );
+            }
+        }
+        self.backend = backend;
+        self.offset = batch.next_offset();
+        self.total = batch.total;
+        self.runs += 1;
+        self.reservations_created += stats.reservations_to_create;
+        self.reservations_v6_created += stats.reservations_v6_to_create;
+        self.reservations_skipped += stats.reservations_skipped;
+        self.reservations_v6_skipped += stats.reservations_v6_skipped;
+        Ok(())
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        write_file(path, &self.render())
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::from("# isc2kea convert --resume journal\n");
+        let _ = writeln!(out, "backend={}", self.backend);
+        for (key, value) in [
+            ("offset", self.offset),
+            ("total", self.total),
+            ("runs", self.runs),
+            ("reservations_created", self.reservations_created),
+            ("reservations_v6_created", self.reservations_v6_created),
+            ("reservations_skipped", self.reservations_skipped),
+            ("reservations_v6_skipped", self.reservations_v6_skipped),
+        ] {
+            let _ = writeln!(out, "{}={}", key, value);
+        }
+        out
+    }
+
+    /// Mappings not yet covered by a run
+    pub(crate) fn remaining(&self) -> usize {
+        self.total.saturating_sub(self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_round_trip_and_accumulate() {
+        let mut journal = Journal::default();
+        let stats = MigrationStats {
+            reservations_to_create: 2,
+            reservations_skipped: 1,
+            batch: Some(MappingBatch {
+                offset: 0,
+                selected: 3,
+                total: 5,
+            }),
+            ..Default::default()
+        };
+        journal.record(&stats, &Backend::Kea).unwrap();
+
+        let mut journal = Journal::parse(&journal.render()).unwrap();
+        assert_eq!(journal.offset, 3);
+        assert_eq!(journal.remaining(), 2);
+
+        let stats = MigrationStats {
+            reservations_v6_to_create: 2,
+            batch: Some(MappingBatch {
+                offset: 3,
+                selected: 2,
+                total: 5,
+            }),
+            ..Default::default()
+        };
+        journal.record(&stats, &Backend::Kea).unwrap();
+        assert_eq!(
+            journal,
+            Journal {
+                backend: "Kea".to_string(),
+                offset: 5,
+                total: 5,
+                runs: 2,
+                reservations_created: 2,
+                reservations_v6_created: 2,
+                reservations_skipped: 1,
+                reservations_v6_skipped: 0,
+            }
+        );
+
+        let err = journal.record(&stats, &Backend::Dnsmasq).unwrap_err();
+        assert!(err.to_string().contains("backend Kea, not dnsmasq"));
+    }
+
+    #[test]
+    fn test_journal_rejects_changed_mappings() {
+        let mut journal = Journal::parse("backend=Kea\noffset=3\ntotal=5\nruns=1\n").unwrap();
+        let stats = MigrationStats {
+            batch: Some(MappingBatch {
+                offset: 3,
+                selected: 2,
+                total: 6,
+            }),
+            ..Default::default()
+        };
+        let err = journal.record(&stats, &Backend::Kea).unwrap_err();
+        assert!(err.to_string().contains("5 then, 6 now"));
+        assert!(Journal::parse("offset=three").is_err());
+    }
+}
@@ -1,50 +1,456 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use std::collections::HashMap;
 use std::ffi::OsString;
 
-use crate::{Backend, MigrationStats};
+use crate::clock::utc_date;
+use crate::extract::{iface_display, parse_isc_leases};
+use crate::term::{self, ADDED, SKIPPED};
+use crate::{
+    AttributeOrder, Backend, Clock, ColorChoice, ConfigLimits, DnsmasqOptionConflict,
+    DnsmasqV6Mode, EmitMode, ExportFormat, FieldOverrides, FindingCategory, FirewallRulesStatus,
+    FixtureSpec, HostnamePolicy, InspectFormat, KeaInterfacePolicy, LeaseStatus, MigrationOptions,
+    MigrationStats, MigrationTiming, OptionsMerge, OrphanedIfacePolicy, OutputStyle,
+    RangeOverlapPolicy, ReservationOrder, Severity, Source, VerifyFormat,
+};
+use summary::Summary;
 
+#[cfg(feature = "kea")]
+mod api_import;
+mod audit;
 mod convert;
+mod cutover;
+mod diag;
+mod export;
+mod gen_fixture;
+mod inspect;
+mod journal;
+mod plan;
+mod preview;
+mod redact;
 mod scan;
+#[cfg(feature = "serve")]
+mod serve;
+mod stats_log;
+mod summary;
 mod verify;
 
 pub(crate) struct ScanArgs {
     pub(crate) r#in: std::path::PathBuf,
-    pub(crate) backend: Backend,
-    pub(crate) fail_if_existing: bool,
-    pub(crate) create_subnets: bool,
-    pub(crate) force_subnets: bool,
-    pub(crate) create_options: bool,
-    pub(crate) force_options: bool,
-    pub(crate) enable_backend: bool,
-    pub(crate) verbose: bool,
+    pub(crate) migration: MigrationArgs,
+    pub(crate) redact: bool,
 }
 
 pub(crate) struct ConvertArgs {
     pub(crate) r#in: std::path::PathBuf,
-    pub(crate) backend: Backend,
     pub(crate) out: std::path::PathBuf,
-    pub(crate) fail_if_existing: bool,
-    pub(crate) create_subnets: bool,
-    pub(crate) force_subnets: bool,
-    pub(crate) create_options: bool,
-    pub(crate) force_options: bool,
-    pub(crate) enable_backend: bool,
-    pub(crate) verbose: bool,
+    pub(crate) migration: MigrationArgs,
+    pub(crate) force: bool,
+    pub(crate) merge_into: bool,
+    pub(crate) resume: Option<std::path::PathBuf>,
+    pub(crate) emit: EmitMode,
+    pub(crate) emit_cutover_script: Option<std::path::PathBuf>,
+    pub(crate) output_style: OutputStyle,
+    pub(crate) peer_out: Option<std::path::PathBuf>,
+    pub(crate) emit_preview_dir: Option<std::path::PathBuf>,
+    pub(crate) plan: Option<std::path::PathBuf>,
+}
+
+pub(crate) struct PlanArgs {
+    pub(crate) r#in: std::path::PathBuf,
+    pub(crate) out: std::path::PathBuf,
+    pub(crate) migration: MigrationArgs,
     pub(crate) force: bool,
 }
 
 pub(crate) struct VerifyArgs {
     pub(crate) r#in: std::path::PathBuf,
+    pub(crate) migration: MigrationArgs,
+    pub(crate) quiet: bool,
+    pub(crate) format: VerifyFormat,
+    pub(crate) fail_on: Severity,
+    pub(crate) redact: bool,
+    pub(crate) only: Vec<FindingCategory>,
+}
+
+pub(crate) struct DiagArgs {
+    pub(crate) r#in: std::path::PathBuf,
+    pub(crate) out: Option<std::path::PathBuf>,
+    pub(crate) migration: MigrationArgs,
+}
+
+pub(crate) struct InspectArgs {
+    pub(crate) r#in: std::path::PathBuf,
+    pub(crate) backend: Backend,
+    pub(crate) format: InspectFormat,
+}
+
+pub(crate) struct AuditArgs {
+    pub(crate) r#in: std::path::PathBuf,
+    pub(crate) leases: std::path::PathBuf,
+    pub(crate) backend: Backend,
+}
+
+pub(crate) struct GenFixtureArgs {
+    pub(crate) out: Option<std::path::PathBuf>,
+    pub(crate) spec: FixtureSpec,
+}
+
+#[cfg(feature = "serve")]
+pub(crate) struct ServeArgs {
+    pub(crate) listen: std::net::SocketAddr,
+    pub(crate) migration: MigrationArgs,
+}
+
+#[cfg(feature = "kea")]
+pub(crate) struct ApiImportArgs {
+    pub(crate) r#in: std::path::PathBuf,
+    pub(crate) url: String,
+    pub(crate) api_key_file: std::path::PathBuf,
+    pub(crate) insecure: bool,
+    pub(crate) delay_ms: u64,
+    pub(crate) retries: u32,
+    pub(crate) journal: Option<std::path::PathBuf>,
+    pub(crate) no_reconfigure: bool,
+    pub(crate) migration: MigrationArgs,
+}
+
+pub(crate) struct ExportArgs {
+    pub(crate) r#in: std::path::PathBuf,
+    pub(crate) out: std::path::PathBuf,
+    pub(crate) format: ExportFormat,
+    pub(crate) force: bool,
+    pub(crate) include_disabled_interfaces: bool,
+    pub(crate) orphaned_interfaces: OrphanedIfacePolicy,
+    pub(crate) source: Source,
+    pub(crate) sort_reservations: ReservationOrder,
+    pub(crate) split_output_per_subnet: bool,
+    pub(crate) merge_ranges: bool,
+    pub(crate) verbose: bool,
+}
+
+/// Migration flags shared by the commands that run a migration: `scan`,
+/// `convert`, `plan`, `apply`, `verify`, `diag`, `serve`, and `api-import`.
+#[derive(Args, Clone)]
+pub(crate) struct MigrationArgs {
+    /// Target DHCP backend
+    #[arg(short, long, value_enum, default_value_t = Backend::default(), env = "ISC2KEA_BACKEND")]
     pub(crate) backend: Backend,
+
+    /// Abort if any existing reservations/hosts are found
+    #[arg(long, env = "ISC2KEA_FAIL_IF_EXISTING")]
     pub(crate) fail_if_existing: bool,
+
+    /// Create missing subnets/ranges in the target backend
+    #[arg(long, env = "ISC2KEA_CREATE_SUBNETS")]
     pub(crate) create_subnets: bool,
+
+    /// Overwrite existing subnets/ranges when creating them
+    #[arg(long, requires = "create_subnets", env = "ISC2KEA_FORCE_SUBNETS")]
     pub(crate) force_subnets: bool,
+
+    /// Add ISC ranges as pools of Kea subnets that already exist for them (Kea only)
+    #[arg(long, conflicts_with = "force_subnets", env = "ISC2KEA_CREATE_POOLS")]
+    pub(crate) create_pools: bool,
+
+    /// Skip static mappings that fall outside every existing or created subnet
+    #[arg(long, requires = "create_subnets", env = "ISC2KEA_SKIP_UNMATCHED")]
+    pub(crate) skip_unmatched: bool,
+
+    /// What to do when Kea does not listen on an interface with migrated reservations
+    #[arg(long, value_enum, default_value_t = KeaInterfacePolicy::Warn, env = "ISC2KEA_KEA_INTERFACES")]
+    pub(crate) kea_interfaces: KeaInterfacePolicy,
+
+    /// How to handle created dnsmasq ranges that overlap existing ones
+    #[arg(long, value_enum, default_value_t = RangeOverlapPolicy::Skip, requires = "create_subnets", env = "ISC2KEA_RANGE_OVERLAP")]
+    pub(crate) range_overlap: RangeOverlapPolicy,
+
+    /// How to write an interface's differing DHCPv4 and DHCPv6 domain search lists to dnsmasq
+    #[arg(long, value_enum, default_value_t = DnsmasqOptionConflict::KeepBothTagged, requires = "create_options", env = "ISC2KEA_DNSMASQ_OPTION_CONFLICT")]
+    pub(crate) dnsmasq_option_conflict: DnsmasqOptionConflict,
+
+    /// Mode for created dnsmasq DHCPv6 ranges (auto derives it from ISC ramode)
+    #[arg(long, value_enum, default_value_t = DnsmasqV6Mode::Auto, requires = "create_subnets", env = "ISC2KEA_DNSMASQ_V6_MODE")]
+    pub(crate) dnsmasq_v6_mode: DnsmasqV6Mode,
+
+    /// Set the interface as constructor on created dnsmasq DHCPv6 ranges
+    #[arg(long, requires = "create_subnets", env = "ISC2KEA_DNSMASQ_CONSTRUCTOR")]
+    pub(crate) dnsmasq_constructor: bool,
+
+    /// `local` flag on created dnsmasq hosts
+    #[arg(
+        long,
+        value_name = "0|1",
+        default_value = "0",
+        action = clap::ArgAction::Set,
+        value_parser = clap::builder::BoolishValueParser::new(),
+        env = "ISC2KEA_DNSMASQ_HOSTS_LOCAL",
+    )]
+    pub(crate) dnsmasq_hosts_local: bool,
+
+    /// Read [no-dns], [ignore], [local] and [no-local] tags in ISC mapping
+    /// descriptions into the created dnsmasq hosts
+    #[arg(long, env = "ISC2KEA_DNSMASQ_HOST_TAGS")]
+    pub(crate) dnsmasq_host_tags: bool,
+
+    /// Split created Kea pools so they exclude migrated reservation IPs
+    #[arg(
+        long,
+        requires = "create_subnets",
+        env = "ISC2KEA_CARVE_POOLS_AROUND_RESERVATIONS"
+    )]
+    pub(crate) carve_pools_around_reservations: bool,
+
+    /// Merge overlapping and adjacent ISC ranges of an interface into minimal pools
+    #[arg(long, requires = "create_subnets", env = "ISC2KEA_MERGE_RANGES")]
+    pub(crate) merge_ranges: bool,
+
+    /// Only write non-empty fields on created dnsmasq hosts, ranges and options
+    #[arg(long, env = "ISC2KEA_MINIMAL_ELEMENTS")]
+    pub(crate) minimal_elements: bool,
+
+    /// Also migrate static mappings on interfaces where ISC DHCP is disabled
+    #[arg(long, env = "ISC2KEA_INCLUDE_DISABLED_INTERFACES")]
+    pub(crate) include_disabled_interfaces: bool,
+
+    /// How to handle static mappings under DHCP sections of interfaces that
+    /// no longer exist
+    #[arg(long, value_enum, default_value_t = OrphanedIfacePolicy::Fail, env = "ISC2KEA_ORPHANED_INTERFACES")]
+    pub(crate) orphaned_interfaces: OrphanedIfacePolicy,
+
+    /// Create DHCP options in the target backend
+    #[arg(long, env = "ISC2KEA_CREATE_OPTIONS")]
     pub(crate) create_options: bool,
+
+    /// Overwrite existing DHCP options when creating them
+    #[arg(long, requires = "create_options", env = "ISC2KEA_FORCE_OPTIONS")]
     pub(crate) force_options: bool,
+
+    /// How to treat subnets that already have some options set
+    #[arg(long, value_enum, default_value_t = OptionsMerge::FillMissing, requires = "create_options", env = "ISC2KEA_OPTIONS_MERGE")]
+    pub(crate) options_merge: OptionsMerge,
+
+    /// Look up hostnames in option server lists (DNS, NTP, WINS, SIP) with
+    /// the system resolver
+    #[arg(long, requires = "create_options", env = "ISC2KEA_RESOLVE_HOSTNAMES")]
+    pub(crate) resolve_hostnames: bool,
+
+    /// Also create dnsmasq DNS host entries for mappings with hostnames (convert only)
+    #[arg(long, env = "ISC2KEA_CREATE_DNS_HOSTS")]
+    pub(crate) create_dns_hosts: bool,
+
+    /// Also create Unbound host overrides for mappings with hostnames (convert only)
+    #[arg(long, env = "ISC2KEA_REGISTER_UNBOUND")]
+    pub(crate) register_unbound: bool,
+
+    /// Enable target backend and disable ISC DHCP on migrated interfaces
+    #[arg(long, env = "ISC2KEA_ENABLE_BACKEND")]
     pub(crate) enable_backend: bool,
+
+    /// Order in which new reservations/hosts are appended
+    #[arg(long, value_enum, default_value_t = ReservationOrder::Discovery, env = "ISC2KEA_SORT_RESERVATIONS")]
+    pub(crate) sort_reservations: ReservationOrder,
+
+    /// How hostnames are written into created reservations and hosts
+    #[arg(long, value_enum, default_value_t = HostnamePolicy::Keep, env = "ISC2KEA_HOSTNAME_POLICY")]
+    pub(crate) hostname_policy: HostnamePolicy,
+
+    /// Domain joined to hostnames with --hostname-policy fqdn
+    #[arg(
+        long,
+        value_name = "DOMAIN",
+        required_if_eq("hostname_policy", "fqdn"),
+        env = "ISC2KEA_DOMAIN"
+    )]
+    pub(crate) domain: Option<String>,
+
+    /// Stamp created elements with an isc2kea-origin attribute
+    #[arg(long, env = "ISC2KEA_MARK_CREATED")]
+    pub(crate) mark_created: bool,
+
+    /// Remove isc2kea-origin attributes left by earlier runs
+    #[arg(long, conflicts_with = "mark_created", env = "ISC2KEA_STRIP_MARKERS")]
+    pub(crate) strip_markers: bool,
+
+    /// Fail before writing when an element the migration writes is not in
+    /// the bundled OPNsense Kea/dnsmasq schema
+    #[arg(long, env = "ISC2KEA_VALIDATE_SCHEMA")]
+    pub(crate) validate_schema: bool,
+
+    /// Regenerate duplicate uuids in the target backend instead of failing
+    #[arg(long, env = "ISC2KEA_FIX_DUPLICATE_UUIDS")]
+    pub(crate) fix_duplicate_uuids: bool,
+
+    /// Note the paired address in the descriptions of hosts with both a
+    /// DHCPv4 and a DHCPv6 mapping
+    #[arg(long, env = "ISC2KEA_LINK_DUAL_STACK")]
+    pub(crate) link_dual_stack: bool,
+
+    /// Convert an HA primary whose DHCP settings sync to a backup node
+    #[arg(long, env = "ISC2KEA_ALLOW_HA")]
+    pub(crate) allow_ha: bool,
+
+    /// Create Kea reservations without an address for static mappings that
+    /// only have a MAC
+    #[arg(long, env = "ISC2KEA_MIGRATE_PLACEHOLDERS")]
+    pub(crate) migrate_placeholders: bool,
+
+    /// Skip DHCPv6 mappings whose DUID or address is reserved in any Kea
+    /// subnet, not just their own
+    #[arg(long, env = "ISC2KEA_GLOBAL_DUIDS")]
+    pub(crate) global_duids: bool,
+
+    /// TOML file redirecting where created hostnames and descriptions come from
+    #[arg(long, value_name = "FILE", env = "ISC2KEA_FIELD_OVERRIDES")]
+    pub(crate) field_overrides: Option<std::path::PathBuf>,
+
+    /// Where to read static mappings from
+    #[arg(long, value_enum, default_value_t = Source::Auto, env = "ISC2KEA_SOURCE")]
+    pub(crate) source: Source,
+
+    /// Read ISC mappings from legacy <installedpackages> locations when none are found
+    #[arg(long, env = "ISC2KEA_LEGACY_PATHS")]
+    pub(crate) legacy_paths: bool,
+
+    /// ISC dhcpd.leases file to report static mapping activity against
+    #[arg(long, value_name = "FILE", env = "ISC2KEA_LEASES")]
+    pub(crate) leases: Option<std::path::PathBuf>,
+
+    /// Append a JSON line with the time, input hash, options and stats of
+    /// each scan or convert to this file
+    #[arg(long, value_name = "FILE", env = "ISC2KEA_STATS_LOG")]
+    pub(crate) stats_log: Option<std::path::PathBuf>,
+
+    /// Skip static mappings with no lease in this many days
+    #[arg(
+        long,
+        value_name = "DAYS",
+        requires = "leases",
+        env = "ISC2KEA_SKIP_STALE_DAYS"
+    )]
+    pub(crate) skip_stale_days: Option<u64>,
+
+    /// Migrate at most this many ISC mappings (DHCPv4 first, then DHCPv6)
+    #[arg(long, value_name = "N", env = "ISC2KEA_LIMIT")]
+    pub(crate) limit: Option<usize>,
+
+    /// Skip this many ISC mappings before migrating (see --limit)
+    #[arg(long, value_name = "N", default_value_t = 0, env = "ISC2KEA_OFFSET")]
+    pub(crate) offset: usize,
+
+    /// Warn when a Kea subnet has more reservations than this (0 disables)
+    #[arg(long, default_value_t = ConfigLimits::default().max_reservations_per_subnet, env = "ISC2KEA_MAX_RESERVATIONS_PER_SUBNET")]
+    pub(crate) max_reservations_per_subnet: usize,
+
+    /// Warn about reservation/host descriptions longer than this (0 disables)
+    #[arg(long, default_value_t = ConfigLimits::default().max_description_len, env = "ISC2KEA_MAX_DESCRIPTION_LEN")]
+    pub(crate) max_description_len: usize,
+
+    /// Print at most this many warnings of each repeated kind (existing
+    /// subnets, options, skipped mappings) and summarize the rest
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 10,
+        env = "ISC2KEA_MAX_WARNINGS"
+    )]
+    pub(crate) max_warnings: usize,
+
+    /// Print every warning (see --max-warnings)
+    #[arg(long, env = "ISC2KEA_SHOW_ALL_WARNINGS")]
+    pub(crate) show_all_warnings: bool,
+
+    /// Show detailed progress for each mapping
+    #[arg(short, long, env = "ISC2KEA_VERBOSE")]
     pub(crate) verbose: bool,
-    pub(crate) quiet: bool,
+
+    /// Print time spent per phase and XML element counts
+    #[arg(long, env = "ISC2KEA_TIMING")]
+    pub(crate) timing: bool,
+}
+
+impl MigrationArgs {
+    pub(crate) fn to_options(&self) -> Result<MigrationOptions> {
+        let leases = match &self.leases {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read lease file: {}", path.display()))?;
+                parse_isc_leases(&text)
+            }
+            None => Vec::new(),
+        };
+        Ok(MigrationOptions {
+            fail_if_existing: self.fail_if_existing,
+            verbose: self.verbose,
+            backend: self.backend.clone(),
+            create_subnets: self.create_subnets,
+            force_subnets: self.force_subnets,
+            create_pools: self.create_pools,
+            create_options: self.create_options,
+            force_options: self.force_options,
+            options_merge: self.options_merge,
+            resolve_hostnames: self.resolve_hostnames,
+            enable_backend: self.enable_backend,
+            reservation_order: self.sort_reservations,
+            skip_unmatched: self.skip_unmatched,
+            include_disabled_interfaces: self.include_disabled_interfaces,
+            orphaned_ifaces: self.orphaned_interfaces,
+            kea_interfaces: self.kea_interfaces,
+            range_overlap: self.range_overlap,
+            dnsmasq_option_conflict: self.dnsmasq_option_conflict,
+            dnsmasq_v6_mode: self.dnsmasq_v6_mode,
+            dnsmasq_constructor: self.dnsmasq_constructor,
+            dnsmasq_hosts_local: self.dnsmasq_hosts_local,
+            dnsmasq_host_tags: self.dnsmasq_host_tags,
+            carve_pools: self.carve_pools_around_reservations,
+            merge_ranges: self.merge_ranges,
+            minimal_elements: self.minimal_elements,
+            create_dns_hosts: self.create_dns_hosts,
+            register_unbound: self.register_unbound,
+            limits: ConfigLimits {
+                max_reservations_per_subnet: self.max_reservations_per_subnet,
+                max_description_len: self.max_description_len,
+            },
+            offset: self.offset,
+            limit: self.limit,
+            mark_created: self.mark_created,
+            strip_markers: self.strip_markers,
+            validate_schema: self.validate_schema,
+            source: self.source,
+            legacy_paths: self.legacy_paths,
+            emit: EmitMode::default(),
+            leases,
+            skip_stale_days: self.skip_stale_days,
+            clock: clock_from_env()?,
+            hostname_policy: self.hostname_policy,
+            hostname_domain: self.domain.clone(),
+            fix_duplicate_uuids: self.fix_duplicate_uuids,
+            link_dual_stack: self.link_dual_stack,
+            field_overrides: match &self.field_overrides {
+                Some(path) => FieldOverrides::load(path)?,
+                None => FieldOverrides::default(),
+            },
+            output_style: OutputStyle::default(),
+            allow_ha: self.allow_ha,
+            max_warnings: (!self.show_all_warnings).then_some(self.max_warnings),
+            migrate_placeholders: self.migrate_placeholders,
+            global_duids: self.global_duids,
+            plan: None,
+        })
+    }
+}
+
+/// [`Clock::Fixed`] at `SOURCE_DATE_EPOCH` when it is set, so markers and
+/// reports can be reproduced; the system clock otherwise.
+fn clock_from_env() -> Result<Clock> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Clock::Fixed)
+            .with_context(|| format!("Invalid SOURCE_DATE_EPOCH: {}", value)),
+        Err(_) => Ok(Clock::System),
+    }
 }
 
 #[derive(Parser)]
@@ -57,6 +463,16 @@ pub(crate) struct VerifyArgs {
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Colour tags and summaries: auto colours a terminal unless NO_COLOR is set
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = ColorChoice::default(),
+        env = "ISC2KEA_COLOR"
+    )]
+    color: ColorChoice,
 }
 
 #[derive(Subcommand)]
@@ -67,80 +483,109 @@ enum Commands {
         #[arg(short, long, default_value = "/conf/config.xml")]
         r#in: std::path::PathBuf,
 
-        /// Target DHCP backend
-        #[arg(short, long, value_enum, default_value_t = Backend::Kea)]
-        backend: Backend,
+        #[command(flatten)]
+        migration: MigrationArgs,
 
-        /// Abort if any existing reservations/hosts are found
+        /// Replace MACs, DUIDs, client IDs and hostnames with per-run pseudonyms
         #[arg(long)]
-        fail_if_existing: bool,
+        redact: bool,
+    },
 
-        /// Create missing subnets/ranges in the target backend
-        #[arg(long)]
-        create_subnets: bool,
+    /// Convert ISC mappings to target backend format and write to output file
+    Convert {
+        /// Input config.xml file path
+        #[arg(short, long, default_value = "/conf/config.xml")]
+        r#in: std::path::PathBuf,
+
+        /// Output file path for converted XML
+        #[arg(short, long)]
+        out: std::path::PathBuf,
 
-        /// Overwrite existing subnets/ranges when creating them
-        #[arg(long, requires = "create_subnets")]
-        force_subnets: bool,
+        #[command(flatten)]
+        migration: MigrationArgs,
 
-        /// Create DHCP options in the target backend
+        /// Overwrite output file if it exists
         #[arg(long)]
-        create_options: bool,
+        force: bool,
+
+        /// Treat the output file as a partially-migrated config and add only
+        /// the missing reservations/subnets to it
+        #[arg(long, conflicts_with = "force")]
+        merge_into: bool,
 
-        /// Overwrite existing DHCP options when creating them
-        #[arg(long, requires = "create_options")]
-        force_options: bool,
+        /// Journal file that records the --offset reached and running totals,
+        /// so repeated runs migrate consecutive --limit batches
+        #[arg(long, value_name = "FILE", conflicts_with = "offset")]
+        resume: Option<std::path::PathBuf>,
 
-        /// Enable target backend and disable ISC DHCP on migrated interfaces
+        /// Write the whole config, or only the changed sections as XML fragments
+        #[arg(long, value_enum, default_value_t = EmitMode::Full, conflicts_with = "merge_into")]
+        emit: EmitMode,
+
+        /// Also write a shell script that installs the output on the firewall,
+        /// switches DHCP services over and rolls back if health checks fail
+        #[arg(long, value_name = "PATH", requires = "enable_backend")]
+        emit_cutover_script: Option<std::path::PathBuf>,
+
+        /// Spaces per indentation level in the written XML
+        #[arg(long, value_name = "N", default_value_t = OutputStyle::default().indent)]
+        indent: usize,
+
+        /// Leave out the `<?xml ...?>` declaration
         #[arg(long)]
-        enable_backend: bool,
+        no_declaration: bool,
 
-        /// Show detailed progress for each mapping
-        #[arg(short, long)]
-        verbose: bool,
+        /// Keep attributes in input order, or sort them by name
+        #[arg(long, value_enum, default_value_t = AttributeOrder::Keep)]
+        attribute_order: AttributeOrder,
+
+        /// Also write the converted config for the HA backup, with the
+        /// interface addresses of the ISC failover peer
+        #[arg(long, value_name = "PATH", requires = "allow_ha")]
+        peer_out: Option<std::path::PathBuf>,
+
+        /// Also write the backend subtrees and reservations of the output,
+        /// for review, to this directory
+        #[arg(long, value_name = "DIR")]
+        emit_preview_dir: Option<std::path::PathBuf>,
     },
 
-    /// Convert ISC mappings to target backend format and write to output file
-    Convert {
+    /// Write the mappings a convert would migrate to a plan file for
+    /// review (read-only)
+    Plan {
         /// Input config.xml file path
         #[arg(short, long, default_value = "/conf/config.xml")]
         r#in: std::path::PathBuf,
 
-        /// Target DHCP backend
-        #[arg(short, long, value_enum, default_value_t = Backend::Kea)]
-        backend: Backend,
-
-        /// Output file path for converted XML
+        /// Plan file to write
         #[arg(short, long)]
         out: std::path::PathBuf,
 
-        /// Abort if any existing reservations/hosts are found
-        #[arg(long)]
-        fail_if_existing: bool,
+        #[command(flatten)]
+        migration: MigrationArgs,
 
-        /// Create missing subnets/ranges in the target backend
+        /// Overwrite the plan file if it exists
         #[arg(long)]
-        create_subnets: bool,
-
-        /// Overwrite existing subnets/ranges when creating them
-        #[arg(long, requires = "create_subnets")]
-        force_subnets: bool,
+        force: bool,
+    },
 
-        /// Create DHCP options in the target backend
-        #[arg(long)]
-        create_options: bool,
+    /// Convert only the mappings a reviewed plan lists, into the subnets it
+    /// names
+    Apply {
+        /// Input config.xml file path
+        #[arg(short, long, default_value = "/conf/config.xml")]
+        r#in: std::path::PathBuf,
 
-        /// Overwrite existing DHCP options when creating them
-        #[arg(long, requires = "create_options")]
-        force_options: bool,
+        /// Output file path for converted XML
+        #[arg(short, long)]
+        out: std::path::PathBuf,
 
-        /// Enable target backend and disable ISC DHCP on migrated interfaces
-        #[arg(long)]
-        enable_backend: bool,
+        /// Plan file written by `isc2kea plan`
+        #[arg(long, value_name = "FILE")]
+        plan: std::path::PathBuf,
 
-        /// Show detailed progress for each mapping
-        #[arg(short, long)]
-        verbose: bool,
+        #[command(flatten)]
+        migration: MigrationArgs,
 
         /// Overwrite output file if it exists
         #[arg(long)]
@@ -153,41 +598,196 @@ enum Commands {
         #[arg(short, long, default_value = "/conf/config.xml")]
         r#in: std::path::PathBuf,
 
-        /// Target DHCP backend
-        #[arg(short, long, value_enum, default_value_t = Backend::Kea)]
-        backend: Backend,
+        #[command(flatten)]
+        migration: MigrationArgs,
 
-        /// Abort if any existing reservations/hosts are found
+        /// Suppress diff output (exit code still indicates changes)
         #[arg(long)]
-        fail_if_existing: bool,
+        quiet: bool,
+
+        /// Output: unified diff, or the findings as JSON or CSV
+        #[arg(long, value_enum, default_value_t = VerifyFormat::Diff)]
+        format: VerifyFormat,
+
+        /// Lowest finding severity that makes verify exit non-zero
+        #[arg(long, value_enum, default_value_t = Severity::Info)]
+        fail_on: Severity,
 
-        /// Create missing subnets/ranges in the target backend
+        /// Replace MACs, DUIDs, client IDs and hostnames with per-run pseudonyms
         #[arg(long)]
-        create_subnets: bool,
+        redact: bool,
+
+        /// Only show and count changes in these parts of the config
+        #[arg(long, value_enum, value_delimiter = ',')]
+        only: Vec<FindingCategory>,
+    },
+
+    /// Write a redacted diagnostics bundle to attach to bug reports
+    Diag {
+        /// Input config.xml file path
+        #[arg(short, long, default_value = "/conf/config.xml")]
+        r#in: std::path::PathBuf,
+
+        /// Bundle file to write (default: stdout)
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+
+        #[command(flatten)]
+        migration: MigrationArgs,
+    },
+
+    /// Export ISC mappings, ranges and options for another DHCP server
+    Export {
+        /// Input config.xml file path
+        #[arg(short, long, default_value = "/conf/config.xml")]
+        r#in: std::path::PathBuf,
+
+        /// Output file path for the exported configuration
+        #[arg(short, long)]
+        out: std::path::PathBuf,
+
+        /// Output format
+        #[arg(short, long, value_enum)]
+        format: ExportFormat,
 
-        /// Overwrite existing subnets/ranges when creating them
-        #[arg(long, requires = "create_subnets")]
-        force_subnets: bool,
+        /// Overwrite output file if it exists
+        #[arg(long)]
+        force: bool,
 
-        /// Create DHCP options in the target backend
+        /// Also export static mappings on interfaces where ISC DHCP is disabled
         #[arg(long)]
-        create_options: bool,
+        include_disabled_interfaces: bool,
 
-        /// Overwrite existing DHCP options when creating them
-        #[arg(long, requires = "create_options")]
-        force_options: bool,
+        /// How to handle static mappings under DHCP sections of interfaces
+        /// that no longer exist
+        #[arg(long, value_enum, default_value_t = OrphanedIfacePolicy::Fail)]
+        orphaned_interfaces: OrphanedIfacePolicy,
 
-        /// Enable target backend and disable ISC DHCP on migrated interfaces
+        /// Where to read static mappings from
+        #[arg(long, value_enum, default_value_t = Source::Auto)]
+        source: Source,
+
+        /// Order in which reservations are written
+        #[arg(long, value_enum, default_value_t = ReservationOrder::Discovery)]
+        sort_reservations: ReservationOrder,
+
+        /// Write each subnet to its own include file next to --out (Kea formats)
+        #[arg(long)]
+        split_output_per_subnet: bool,
+
+        /// Merge overlapping and adjacent ISC ranges of an interface into minimal pools
         #[arg(long)]
-        enable_backend: bool,
+        merge_ranges: bool,
 
         /// Show detailed progress for each mapping
         #[arg(short, long)]
         verbose: bool,
+    },
 
-        /// Suppress diff output (exit code still indicates changes)
+    /// List the subnets, reservations and options the target backend already
+    /// has (read-only, no ISC data needed)
+    Inspect {
+        /// Input config.xml file path
+        #[arg(short, long, default_value = "/conf/config.xml")]
+        r#in: std::path::PathBuf,
+
+        /// Backend whose configuration to list
+        #[arg(short, long, value_enum, default_value_t = Backend::default())]
+        backend: Backend,
+
+        /// Output: aligned tables or JSON
+        #[arg(long, value_enum, default_value_t = InspectFormat::Table)]
+        format: InspectFormat,
+    },
+
+    /// Compare active leases with the reservations of a converted config
+    /// and report devices leasing another address (read-only)
+    Audit {
+        /// Converted config.xml file path
+        #[arg(short, long, default_value = "/conf/config.xml")]
+        r#in: std::path::PathBuf,
+
+        /// Kea lease CSV (kea-leases4.csv) or ISC dhcpd.leases file
+        #[arg(long, value_name = "FILE")]
+        leases: std::path::PathBuf,
+
+        /// Backend whose reservations to compare
+        #[arg(short, long, value_enum, default_value_t = Backend::default())]
+        backend: Backend,
+    },
+
+    /// Write a synthetic config.xml for testing and reproducing issues
+    GenFixture {
+        /// Output file path (default: stdout)
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+
+        /// Interfaces with ISC DHCP enabled
+        #[arg(long, default_value_t = 2)]
+        interfaces: usize,
+
+        /// Static mappings on each interface
+        #[arg(long, default_value_t = 10)]
+        maps_per_iface: usize,
+
+        /// Mappings on each interface that already exist as Kea reservations
+        /// and dnsmasq hosts
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        existing: usize,
+
+        /// Seed for the generated addresses and names
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Serve POST /scan and POST /convert over HTTP
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8553")]
+        listen: std::net::SocketAddr,
+
+        #[command(flatten)]
+        migration: MigrationArgs,
+    },
+
+    /// Create the planned Kea reservations through the OPNsense API
+    #[cfg(feature = "kea")]
+    ApiImport {
+        /// Input config.xml the reservations are planned from
+        #[arg(short, long, default_value = "/conf/config.xml")]
+        r#in: std::path::PathBuf,
+
+        /// Base URL of the OPNsense GUI, e.g. https://192.168.1.1
         #[arg(long)]
-        quiet: bool,
+        url: String,
+
+        /// OPNsense apikey.txt holding key= and secret= lines
+        #[arg(long, value_name = "FILE")]
+        api_key_file: std::path::PathBuf,
+
+        /// Accept a self-signed GUI certificate
+        #[arg(long)]
+        insecure: bool,
+
+        /// Pause between API requests, in milliseconds
+        #[arg(long, value_name = "MS", default_value_t = 250)]
+        delay_ms: u64,
+
+        /// Retries per reservation on connection errors, 429 and 5xx
+        #[arg(long, value_name = "N", default_value_t = 5)]
+        retries: u32,
+
+        /// Record created reservations here and skip them on rerun
+        #[arg(long, value_name = "FILE")]
+        journal: Option<std::path::PathBuf>,
+
+        /// Do not apply the Kea configuration after creating reservations
+        #[arg(long)]
+        no_reconfigure: bool,
+
+        #[command(flatten)]
+        migration: MigrationArgs,
     },
 }
 
@@ -197,170 +797,814 @@ where
     T: Into<OsString> + Clone,
 {
     let cli = Cli::parse_from(args);
+    term::set_color(cli.color);
 
     match cli.command {
         Commands::Scan {
             r#in,
-            backend,
-            fail_if_existing,
-            create_subnets,
-            force_subnets,
-            create_options,
-            force_options,
-            enable_backend,
-            verbose,
+            migration,
+            redact,
         } => scan::run_scan(ScanArgs {
             r#in,
-            backend,
-            fail_if_existing,
-            create_subnets,
-            force_subnets,
-            create_options,
-            force_options,
-            enable_backend,
-            verbose,
+            migration,
+            redact,
         }),
         Commands::Convert {
             r#in,
-            backend,
             out,
-            fail_if_existing,
-            create_subnets,
-            force_subnets,
-            create_options,
-            force_options,
-            enable_backend,
-            verbose,
+            migration,
             force,
+            merge_into,
+            resume,
+            emit,
+            emit_cutover_script,
+            indent,
+            no_declaration,
+            attribute_order,
+            peer_out,
+            emit_preview_dir,
         } => convert::run_convert(ConvertArgs {
             r#in,
-            backend,
             out,
-            fail_if_existing,
-            create_subnets,
-            force_subnets,
-            create_options,
-            force_options,
-            enable_backend,
-            verbose,
+            migration,
+            force,
+            merge_into,
+            resume,
+            emit,
+            emit_cutover_script,
+            output_style: OutputStyle {
+                indent,
+                declaration: !no_declaration,
+                attribute_order,
+            },
+            peer_out,
+            emit_preview_dir,
+            plan: None,
+        }),
+        Commands::Plan {
+            r#in,
+            out,
+            migration,
+            force,
+        } => plan::run_plan(PlanArgs {
+            r#in,
+            out,
+            migration,
+            force,
+        }),
+        Commands::Apply {
+            r#in,
+            out,
+            plan,
+            migration,
+            force,
+        } => convert::run_convert(ConvertArgs {
+            r#in,
+            out,
+            migration,
             force,
+            merge_into: false,
+            resume: None,
+            emit: EmitMode::Full,
+            emit_cutover_script: None,
+            output_style: OutputStyle::default(),
+            peer_out: None,
+            emit_preview_dir: None,
+            plan: Some(plan),
         }),
         Commands::Verify {
             r#in,
-            backend,
-            fail_if_existing,
-            create_subnets,
-            force_subnets,
-            create_options,
-            force_options,
-            enable_backend,
-            verbose,
+            migration,
             quiet,
+            format,
+            fail_on,
+            redact,
+            only,
         } => verify::run_verify(VerifyArgs {
             r#in,
-            backend,
-            fail_if_existing,
-            create_subnets,
-            force_subnets,
-            create_options,
-            force_options,
-            enable_backend,
-            verbose,
+            migration,
             quiet,
+            format,
+            fail_on,
+            redact,
+            only,
+        }),
+        Commands::Diag {
+            r#in,
+            out,
+            migration,
+        } => diag::run_diag(DiagArgs {
+            r#in,
+            out,
+            migration,
+        }),
+        Commands::Export {
+            r#in,
+            out,
+            format,
+            force,
+            include_disabled_interfaces,
+            orphaned_interfaces,
+            source,
+            sort_reservations,
+            split_output_per_subnet,
+            merge_ranges,
+            verbose,
+        } => export::run_export(ExportArgs {
+            r#in,
+            out,
+            format,
+            force,
+            include_disabled_interfaces,
+            orphaned_interfaces,
+            source,
+            sort_reservations,
+            split_output_per_subnet,
+            merge_ranges,
+            verbose,
+        }),
+        Commands::Inspect {
+            r#in,
+            backend,
+            format,
+        } => inspect::run_inspect(InspectArgs {
+            r#in,
+            backend,
+            format,
         }),
+        Commands::Audit {
+            r#in,
+            leases,
+            backend,
+        } => audit::run_audit(AuditArgs {
+            r#in,
+            leases,
+            backend,
+        }),
+        Commands::GenFixture {
+            out,
+            interfaces,
+            maps_per_iface,
+            existing,
+            seed,
+        } => gen_fixture::run_gen_fixture(GenFixtureArgs {
+            out,
+            spec: FixtureSpec {
+                interfaces,
+                maps_per_iface,
+                existing_per_iface: existing,
+                seed,
+            },
+        }),
+        #[cfg(feature = "serve")]
+        Commands::Serve { listen, migration } => serve::run_serve(ServeArgs { listen, migration }),
+        #[cfg(feature = "kea")]
+        Commands::ApiImport {
+            r#in,
+            url,
+            api_key_file,
+            insecure,
+            delay_ms,
+            retries,
+            journal,
+            no_reconfigure,
+            migration,
+        } => api_import::run_api_import(ApiImportArgs {
+            r#in,
+            url,
+            api_key_file,
+            insecure,
+            delay_ms,
+            retries,
+            journal,
+            no_reconfigure,
+            migration,
+        }),
+    }
+}
+
+/// `value` as a quoted JSON string.
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
     }
+    out.push('"');
+    out
+}
+
+/// Scan counts and warnings as a JSON object.
+pub(crate) fn stats_json(stats: &MigrationStats) -> String {
+    let members: Vec<String> = stats_members(stats)
+        .iter()
+        .map(|(name, value)| format!("  {}: {}", json_string(name), value))
+        .collect();
+    format!("{{\n{}\n}}\n", members.join(",\n"))
+}
+
+/// Names and JSON values of the stats in [`stats_json`], shared with the
+/// one-line form written by `--stats-log`.
+pub(crate) fn stats_members(stats: &MigrationStats) -> Vec<(&'static str, String)> {
+    let counts = [
+        ("isc_mappings_found", stats.isc_mappings_found),
+        ("isc_mappings_v6_found", stats.isc_mappings_v6_found),
+        ("isc_ranges_found", stats.isc_ranges_found),
+        ("isc_ranges_v6_found", stats.isc_ranges_v6_found),
+        ("ranges_merged", stats.ranges_merged),
+        ("subnets_created", stats.subnets_created),
+        ("pools_created", stats.pools_created),
+        ("ranges_created", stats.ranges_created),
+        ("options_created", stats.options_created),
+        ("options_skipped", stats.options_skipped),
+        ("target_subnets_found", stats.target_subnets_found),
+        ("target_subnets_v6_found", stats.target_subnets_v6_found),
+        ("reservations_to_create", stats.reservations_to_create),
+        ("reservations_v6_to_create", stats.reservations_v6_to_create),
+        ("reservations_skipped", stats.reservations_skipped),
+        ("reservations_v6_skipped", stats.reservations_v6_skipped),
+        ("prefix_reservations_v6", stats.prefix_reservations_v6),
+        ("prefixes_v6_unmigrated", stats.prefixes_v6_unmigrated),
+        ("uuids_regenerated", stats.uuids_regenerated),
+        ("hostname_collisions", stats.hostname_collisions.len()),
+        ("option_values_resolved", stats.option_values_resolved),
+        (
+            "unresolved_option_values",
+            stats.unresolved_option_values.len(),
+        ),
+        ("isc_placeholders", stats.isc_placeholders.len()),
+        ("placeholders_migrated", stats.placeholders_migrated),
+    ];
+    let mut members: Vec<(&'static str, String)> = counts
+        .iter()
+        .map(|(name, count)| (*name, count.to_string()))
+        .collect();
+    let collisions: Vec<String> = stats
+        .hostname_collisions
+        .iter()
+        .map(|c| {
+            let ips: Vec<String> = c.ips.iter().map(|ip| json_string(ip)).collect();
+            format!(
+                "{{\"hostname\": {}, \"ips\": [{}]}}",
+                json_string(&c.hostname),
+                ips.join(", ")
+            )
+        })
+        .collect();
+    members.push((
+        "hostname_collision_details",
+        format!("[{}]", collisions.join(", ")),
+    ));
+    let unresolved: Vec<String> = stats
+        .unresolved_option_values
+        .iter()
+        .map(|v| {
+            format!(
+                "{{\"section\": {}, \"iface\": {}, \"field\": {}, \"value\": {}, \"reason\": {}}}",
+                json_string(&v.section),
+                json_string(&v.iface),
+                json_string(&v.field),
+                json_string(&v.value),
+                json_string(&v.reason)
+            )
+        })
+        .collect();
+    members.push((
+        "unresolved_option_value_details",
+        format!("[{}]", unresolved.join(", ")),
+    ));
+    let placeholders: Vec<String> = stats
+        .isc_placeholders
+        .iter()
+        .map(|p| {
+            format!(
+                "{{\"iface\": {}, \"mac\": {}, \"hostname\": {}}}",
+                json_string(&p.iface),
+                json_string(&p.mac),
+                p.hostname
+                    .as_deref()
+                    .map_or_else(|| "null".to_string(), json_string)
+            )
+        })
+        .collect();
+    members.push((
+        "isc_placeholder_details",
+        format!("[{}]", placeholders.join(", ")),
+    ));
+    let warnings: Vec<String> = stats.warnings.iter().map(|w| json_string(w)).collect();
+    members.push(("warnings", format!("[{}]", warnings.join(", "))));
+    let actions: Vec<String> = stats
+        .manual_actions
+        .iter()
+        .map(|a| json_string(a))
+        .collect();
+    members.push(("manual_actions", format!("[{}]", actions.join(", "))));
+    members
 }
 
 pub(crate) fn print_scan_stats(stats: &MigrationStats, backend: &Backend) {
-    println!(
-        "ISC DHCP static mappings found: {}",
-        stats.isc_mappings_found
+    let mut summary = Summary::default();
+    summary.row("ISC DHCP static mappings found", stats.isc_mappings_found);
+    summary.row(
+        "ISC DHCPv6 static mappings found",
+        stats.isc_mappings_v6_found,
     );
-    println!(
-        "ISC DHCPv6 static mappings found: {}",
-        stats.isc_mappings_v6_found
+    disabled_iface_rows(&mut summary, stats);
+    placeholder_rows(&mut summary, stats, "that would be created");
+    lease_activity_rows(&mut summary, stats);
+    batch_rows(&mut summary, stats);
+    summary.row("ISC DHCP ranges found", stats.isc_ranges_found);
+    summary.row("ISC DHCPv6 ranges found", stats.isc_ranges_v6_found);
+    if stats.per_backend.is_empty() {
+        scan_backend_rows(&mut summary, stats, backend);
+    } else {
+        for (backend, backend_stats) in &stats.per_backend {
+            summary.heading(format!("[{}]", backend));
+            scan_backend_rows(&mut summary, backend_stats, backend);
+        }
+    }
+    if stats.uuids_regenerated > 0 {
+        summary.row(
+            "Duplicate uuids that would be regenerated",
+            stats.uuids_regenerated,
+        );
+    }
+    if stats.ranges_merged > 0 {
+        summary.row("ISC ranges merged", stats.ranges_merged);
+    }
+    if stats.dual_stack_linked > 0 {
+        summary.row("Dual-stack hosts linked", stats.dual_stack_linked);
+    }
+    ha_sync_rows(&mut summary, stats);
+    if !stats.manual_actions.is_empty() {
+        summary.heading("Manual steps after migrating:");
+        for action in &stats.manual_actions {
+            summary.detail(format!("  [ ] {}", action));
+        }
+    }
+    summary.print();
+}
+
+fn scan_backend_rows(summary: &mut Summary, stats: &MigrationStats, backend: &Backend) {
+    summary.row(
+        format!("{} subnet4 entries found", backend),
+        stats.target_subnets_found,
+    );
+    summary.row(
+        format!("{} subnet6 entries found", backend),
+        stats.target_subnets_v6_found,
     );
-    println!("ISC DHCP ranges found: {}", stats.isc_ranges_found);
-    println!("ISC DHCPv6 ranges found: {}", stats.isc_ranges_v6_found);
-    println!(
-        "{} subnet4 entries found: {}",
-        backend, stats.target_subnets_found
+    summary.count(
+        "Reservations that would be created",
+        stats.reservations_to_create,
+        ADDED,
     );
-    println!(
-        "{} subnet6 entries found: {}",
-        backend, stats.target_subnets_v6_found
+    summary.count(
+        "Reservations (v6) that would be created",
+        stats.reservations_v6_to_create,
+        ADDED,
     );
-    println!(
-        "Reservations that would be created: {}",
-        stats.reservations_to_create
+    summary.count(
+        "Reservations skipped (already exist)",
+        stats.reservations_skipped,
+        SKIPPED,
     );
-    println!(
-        "Reservations (v6) that would be created: {}",
-        stats.reservations_v6_to_create
+    summary.count(
+        "Reservations skipped (v6)",
+        stats.reservations_v6_skipped,
+        SKIPPED,
     );
-    println!(
-        "Reservations skipped (already exist): {}",
-        stats.reservations_skipped
+    prefix_rows(summary, stats, "that would be created");
+    hostname_collision_rows(summary, stats);
+    option_value_rows(summary, stats);
+}
+
+fn hostname_collision_rows(summary: &mut Summary, stats: &MigrationStats) {
+    if stats.hostname_collisions.is_empty() {
+        return;
+    }
+    summary.count(
+        "Hostname collisions (same name, different IPs)",
+        stats.hostname_collisions.len(),
+        SKIPPED,
     );
-    println!(
-        "Reservations skipped (v6): {}",
-        stats.reservations_v6_skipped
+    for collision in &stats.hostname_collisions {
+        summary.detail(format!(
+            "  {}: {}",
+            collision.hostname,
+            collision.ips.join(", ")
+        ));
+    }
+}
+
+fn option_value_rows(summary: &mut Summary, stats: &MigrationStats) {
+    if stats.option_values_resolved > 0 {
+        summary.count(
+            "Option values resolved to addresses",
+            stats.option_values_resolved,
+            ADDED,
+        );
+    }
+    if stats.unresolved_option_values.is_empty() {
+        return;
+    }
+    summary.count(
+        "Option values left out (no address)",
+        stats.unresolved_option_values.len(),
+        SKIPPED,
     );
+    for value in &stats.unresolved_option_values {
+        summary.detail(format!(
+            "  {}/{}/{}: {} ({})",
+            value.section, value.iface, value.field, value.value, value.reason
+        ));
+    }
+}
+
+fn prefix_rows(summary: &mut Summary, stats: &MigrationStats, verb: &str) {
+    if stats.prefix_reservations_v6 > 0 {
+        summary.count(
+            format!("Prefix delegation reservations (v6) {}", verb),
+            stats.prefix_reservations_v6,
+            ADDED,
+        );
+    }
+    if stats.prefixes_v6_unmigrated > 0 {
+        summary.count(
+            "Delegated prefixes not migrated (v6)",
+            stats.prefixes_v6_unmigrated,
+            SKIPPED,
+        );
+    }
 }
 
 pub(crate) fn print_convert_stats(stats: &MigrationStats, backend: &Backend) {
-    println!(
-        "ISC DHCP static mappings found: {}",
-        stats.isc_mappings_found
+    let mut summary = Summary::default();
+    summary.row("ISC DHCP static mappings found", stats.isc_mappings_found);
+    summary.row(
+        "ISC DHCPv6 static mappings found",
+        stats.isc_mappings_v6_found,
     );
-    println!(
-        "ISC DHCPv6 static mappings found: {}",
-        stats.isc_mappings_v6_found
+    disabled_iface_rows(&mut summary, stats);
+    placeholder_rows(&mut summary, stats, "created");
+    lease_activity_rows(&mut summary, stats);
+    batch_rows(&mut summary, stats);
+    if stats.per_backend.is_empty() {
+        convert_backend_rows(&mut summary, stats, backend);
+    } else {
+        for (backend, backend_stats) in &stats.per_backend {
+            summary.heading(format!("[{}]", backend));
+            convert_backend_rows(&mut summary, backend_stats, backend);
+        }
+    }
+    if stats.uuids_regenerated > 0 {
+        summary.row("Duplicate uuids regenerated", stats.uuids_regenerated);
+    }
+    if stats.ranges_merged > 0 {
+        summary.row("ISC ranges merged", stats.ranges_merged);
+    }
+    if stats.dual_stack_linked > 0 {
+        summary.row("Dual-stack hosts linked", stats.dual_stack_linked);
+    }
+    if stats.markers_added > 0 {
+        summary.row("Origin markers added", stats.markers_added);
+    }
+    if stats.markers_stripped > 0 {
+        summary.row("Origin markers removed", stats.markers_stripped);
+    }
+    ha_sync_rows(&mut summary, stats);
+    summary.print();
+}
+
+/// HA sync of DHCP settings, with what converting one node means for both.
+fn ha_sync_rows(summary: &mut Summary, stats: &MigrationStats) {
+    let Some(ha) = &stats.ha_sync else {
+        return;
+    };
+    summary.row(
+        format!("HA config sync to {}", ha.peer),
+        ha.sections.join(", "),
     );
-    println!(
-        "{} subnet4 entries found: {}",
-        backend, stats.target_subnets_found
+    summary.detail("  Convert this node only, then add the new backend to the sync items");
+    summary.detail("  (System > High Availability > Settings) and sync, so both nodes switch");
+    summary.detail("  together; run the backend's own HA (Kea HA peers) or split pools so the");
+    summary.detail("  nodes do not hand out the same addresses. To prepare the backup's config");
+    summary.detail("  offline, use convert --allow-ha --peer-out.");
+}
+
+fn convert_backend_rows(summary: &mut Summary, stats: &MigrationStats, backend: &Backend) {
+    summary.row(
+        format!("{} subnet4 entries found", backend),
+        stats.target_subnets_found,
     );
-    println!(
-        "{} subnet6 entries found: {}",
-        backend, stats.target_subnets_v6_found
+    summary.row(
+        format!("{} subnet6 entries found", backend),
+        stats.target_subnets_v6_found,
     );
-    println!("Reservations created: {}", stats.reservations_to_create);
-    println!(
-        "Reservations created (v6): {}",
-        stats.reservations_v6_to_create
+    summary.count("Reservations created", stats.reservations_to_create, ADDED);
+    summary.count(
+        "Reservations created (v6)",
+        stats.reservations_v6_to_create,
+        ADDED,
     );
-    println!(
-        "Reservations skipped (already exist): {}",
-        stats.reservations_skipped
+    summary.count(
+        "Reservations skipped (already exist)",
+        stats.reservations_skipped,
+        SKIPPED,
     );
-    println!(
-        "Reservations skipped (v6): {}",
-        stats.reservations_v6_skipped
+    summary.count(
+        "Reservations skipped (v6)",
+        stats.reservations_v6_skipped,
+        SKIPPED,
     );
+    prefix_rows(summary, stats, "created");
+    hostname_collision_rows(summary, stats);
+    option_value_rows(summary, stats);
+    if stats.subnets_created + stats.pools_created > 0 {
+        summary.count("Subnets created", stats.subnets_created, ADDED);
+        summary.count("Pools created", stats.pools_created, ADDED);
+    }
+    if stats.ranges_created > 0 {
+        summary.count("DHCP ranges created", stats.ranges_created, ADDED);
+    }
+    if stats.options_created + stats.options_skipped > 0 {
+        summary.count("Options created", stats.options_created, ADDED);
+        summary.count(
+            "Options skipped (already set)",
+            stats.options_skipped,
+            SKIPPED,
+        );
+    }
 
+    if stats.dns_hosts_created + stats.dns_hosts_updated + stats.dns_hosts_skipped > 0 {
+        summary.count("DNS host entries created", stats.dns_hosts_created, ADDED);
+        if stats.dns_hosts_updated > 0 {
+            summary.count(
+                "DNS host entries updated (domain set)",
+                stats.dns_hosts_updated,
+                ADDED,
+            );
+        }
+        summary.count("DNS host entries skipped", stats.dns_hosts_skipped, SKIPPED);
+    }
+    if stats.unbound_hosts_created + stats.unbound_hosts_skipped > 0 {
+        summary.count(
+            "Unbound host overrides created",
+            stats.unbound_hosts_created,
+            ADDED,
+        );
+        summary.count(
+            "Unbound host overrides skipped",
+            stats.unbound_hosts_skipped,
+            SKIPPED,
+        );
+    }
     if !stats.interfaces_configured.is_empty() {
-        println!(
-            "Interfaces configured: {}",
-            stats.interfaces_configured.join(", ")
+        summary.row(
+            "Interfaces configured",
+            labeled_ifaces(&stats.interfaces_configured, &stats.interface_labels),
+        );
+    }
+    if !stats.no_dhcp_cleared.is_empty() {
+        summary.row(
+            "dnsmasq DHCP re-enabled on",
+            labeled_ifaces(&stats.no_dhcp_cleared, &stats.interface_labels),
+        );
+    }
+    if !stats.interfaces_not_listening.is_empty() {
+        summary.row(
+            "Interfaces Kea does not listen on",
+            labeled_ifaces(&stats.interfaces_not_listening, &stats.interface_labels),
         );
     }
     if !stats.isc_disabled_v4.is_empty() {
-        println!(
-            "ISC DHCP disabled (v4): {}",
-            stats.isc_disabled_v4.join(", ")
+        summary.row(
+            "ISC DHCP disabled (v4)",
+            labeled_ifaces(&stats.isc_disabled_v4, &stats.interface_labels),
         );
     }
     if !stats.isc_disabled_v6.is_empty() {
-        println!(
-            "ISC DHCP disabled (v6): {}",
-            stats.isc_disabled_v6.join(", ")
+        summary.row(
+            "ISC DHCP disabled (v6)",
+            labeled_ifaces(&stats.isc_disabled_v6, &stats.interface_labels),
         );
     }
     if stats.backend_enabled_v4 {
-        println!("Backend DHCP enabled (v4): yes");
+        summary.row("Backend DHCP enabled (v4)", "yes");
     }
     if stats.backend_enabled_v6 {
-        println!("Backend DHCP enabled (v6): yes");
+        summary.row("Backend DHCP enabled (v6)", "yes");
+    }
+    if let Some(status) = stats.fwrules_v4 {
+        summary.row("Kea firewall rules (v4)", fwrules_label(status));
+    }
+    if let Some(status) = stats.fwrules_v6 {
+        summary.row("Kea firewall rules (v6)", fwrules_label(status));
+    }
+}
+
+pub(crate) fn print_timing(timing: &MigrationTiming) {
+    let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    eprintln!(
+        "Timing: parse {:.1} ms, extract {:.1} ms, convert {:.1} ms, write {:.1} ms \
+         (total {:.1} ms)",
+        ms(timing.parse),
+        ms(timing.extract),
+        ms(timing.convert),
+        ms(timing.write),
+        ms(timing.total())
+    );
+    eprintln!(
+        "XML elements: {} in, {} out",
+        timing.input_elements, timing.output_elements
+    );
+}
+
+pub(crate) fn print_limit_warnings(stats: &MigrationStats) {
+    for warning in &stats.warnings {
+        term::warning(format_args!("{}", warning));
+    }
+}
+
+/// Counts of the repeated warnings left out by --max-warnings.
+pub(crate) fn print_suppressed_warnings(stats: &MigrationStats) {
+    for suppressed in &stats.warnings_suppressed {
+        term::warning(format_args!(
+            "{} {} ({} shown); run with --show-all-warnings for details.",
+            suppressed.count, suppressed.summary, suppressed.shown
+        ));
+    }
+}
+
+pub(crate) fn disabled_iface_rows(summary: &mut Summary, stats: &MigrationStats) {
+    if stats.isc_mappings_orphaned_iface > 0 {
+        summary.count(
+            "ISC DHCP static mappings on removed interfaces",
+            stats.isc_mappings_orphaned_iface,
+            SKIPPED,
+        );
+    }
+    if stats.isc_mappings_v6_orphaned_iface > 0 {
+        summary.count(
+            "ISC DHCPv6 static mappings on removed interfaces",
+            stats.isc_mappings_v6_orphaned_iface,
+            SKIPPED,
+        );
+    }
+    if stats.isc_mappings_disabled_iface > 0 {
+        summary.count(
+            "ISC DHCP static mappings ignored (interface disabled)",
+            stats.isc_mappings_disabled_iface,
+            SKIPPED,
+        );
+    }
+    if stats.isc_mappings_v6_disabled_iface > 0 {
+        summary.count(
+            "ISC DHCPv6 static mappings ignored (interface disabled)",
+            stats.isc_mappings_v6_disabled_iface,
+            SKIPPED,
+        );
+    }
+}
+
+/// Static mappings without an IP address, and how many became Kea
+/// reservations with `--migrate-placeholders`.
+fn placeholder_rows(summary: &mut Summary, stats: &MigrationStats, verb: &str) {
+    if stats.isc_placeholders.is_empty() {
+        return;
+    }
+    summary.row(
+        "ISC DHCP placeholder mappings (MAC only, no IP)",
+        stats.isc_placeholders.len(),
+    );
+    for placeholder in &stats.isc_placeholders {
+        match placeholder.hostname.as_deref().filter(|h| !h.is_empty()) {
+            Some(hostname) => summary.detail(format!(
+                "  {}: {} ({})",
+                placeholder.iface, placeholder.mac, hostname
+            )),
+            None => summary.detail(format!("  {}: {}", placeholder.iface, placeholder.mac)),
+        }
+    }
+    if stats.placeholders_migrated > 0 {
+        summary.count(
+            format!("Placeholder reservations (no IP) {}", verb),
+            stats.placeholders_migrated,
+            ADDED,
+        );
+    }
+}
+
+fn lease_activity_rows(summary: &mut Summary, stats: &MigrationStats) {
+    if stats.lease_activity.is_empty() {
+        return;
+    }
+    let count = |status| {
+        stats
+            .lease_activity
+            .iter()
+            .filter(|a| a.status == status)
+            .count()
+    };
+    summary.row(
+        "Lease activity (v4)",
+        format!(
+            "{} online, {} recent, {} stale, {} never seen",
+            count(LeaseStatus::Online),
+            count(LeaseStatus::Recent),
+            count(LeaseStatus::Stale),
+            count(LeaseStatus::NeverSeen)
+        ),
+    );
+    if stats.isc_mappings_stale > 0 {
+        summary.count(
+            "ISC DHCP static mappings skipped (stale)",
+            stats.isc_mappings_stale,
+            SKIPPED,
+        );
+    }
+}
+
+/// Per-mapping lease activity, least active first, as a cutover order.
+pub(crate) fn print_lease_schedule(stats: &MigrationStats) {
+    if stats.lease_activity.is_empty() {
+        return;
+    }
+    println!("Cutover order by lease activity (least active first):");
+    for activity in &stats.lease_activity {
+        let status = match activity.status {
+            LeaseStatus::Online => "ONLINE",
+            LeaseStatus::Recent => "RECENT",
+            LeaseStatus::Stale => "STALE",
+            LeaseStatus::NeverSeen => "NEVER",
+        };
+        let last_seen = activity
+            .last_seen
+            .map(utc_date)
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {}: {} ({}) on {}{}, last seen {}",
+            status,
+            activity.ipaddr,
+            activity.mac,
+            iface_display(&activity.iface, &stats.interface_labels),
+            activity
+                .hostname
+                .as_deref()
+                .map(|h| format!(" [{}]", h))
+                .unwrap_or_default(),
+            last_seen
+        );
+    }
+}
+
+fn batch_rows(summary: &mut Summary, stats: &MigrationStats) {
+    let Some(batch) = stats.batch else {
+        return;
+    };
+    if batch.selected == 0 {
+        summary.row(
+            "Batch",
+            format!(
+                "no mappings left after offset {} (of {})",
+                batch.offset, batch.total
+            ),
+        );
+        return;
+    }
+    summary.row(
+        "Batch",
+        format!(
+            "mappings {}-{} of {} ({} remaining, next --offset {})",
+            batch.offset + 1,
+            batch.next_offset(),
+            batch.total,
+            batch.remaining(),
+            batch.next_offset()
+        ),
+    );
+}
+
+pub(crate) fn labeled_ifaces(ifaces: &[String], labels: &HashMap<String, String>) -> String {
+    ifaces
+        .iter()
+        .map(|iface| iface_display(iface, labels))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn fwrules_label(status: FirewallRulesStatus) -> &'static str {
+    match status {
+        FirewallRulesStatus::AlreadyEnabled => "already enabled",
+        FirewallRulesStatus::Enabled => "enabled",
+        FirewallRulesStatus::Missing => "setting not found (check firewall rules manually)",
     }
 }
@@ -0,0 +1,38 @@
+//! `plan`: write the mappings a convert would migrate, for review and
+//! editing before `apply` converts them.
+
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+
+use super::export::write_file;
+use super::PlanArgs;
+use crate::plan_config;
+
+pub(crate) fn run_plan(args: PlanArgs) -> Result<()> {
+    let input_file = File::open(&args.r#in)
+        .with_context(|| format!("Failed to open input file: {}", args.r#in.display()))?;
+    if !args.force && args.out.exists() {
+        bail!(
+            "Plan file already exists: {} (use --force to overwrite)",
+            args.out.display()
+        );
+    }
+
+    let options = args.migration.to_options()?;
+    let plan = plan_config(input_file, &options)?;
+    write_file(&args.out, &plan.to_text())?;
+
+    println!(
+        "Planned {} DHCPv4 and {} DHCPv6 reservation(s) for {}.",
+        plan.v4.len(),
+        plan.v6.len(),
+        plan.backend
+    );
+    println!("Plan written to: {}", args.out.display());
+    println!(
+        "Review or edit it, then run: isc2kea apply --plan {} --in {} --out <file>",
+        args.out.display(),
+        args.r#in.display()
+    );
+    Ok(())
+}
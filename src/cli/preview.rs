@@ -0,0 +1,82 @@
+//! `convert --emit-preview-dir`: per-backend slices of the converted config.
+//!
+//! Reviewers who only sign off on the DHCP changes get the backend's
+//! subtree pretty-printed on its own (`kea.xml`, `dnsmasq.xml`) and its
+//! reservations and hosts as JSON (`reservations.json`, in the `inspect`
+//! format), next to the full config.xml written to `--out`.
+
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use super::export::write_file;
+use super::inspect::{inspect_tables, render_json};
+use crate::xml_helpers::{find_descendant_ci, write_element, XmlDocument};
+use crate::{Backend, OutputStyle};
+
+const RESERVATIONS: &str = "reservations.json";
+
+/// Subtree preview files for `backend`, with the element each holds.
+fn subtree_files(backend: &Backend) -> Vec<(&'static str, &'static str)> {
+    match backend {
+        Backend::Kea => vec![("kea.xml", "Kea")],
+        Backend::Dnsmasq => vec![("dnsmasq.xml", "dnsmasq")],
+        Backend::Both => vec![("kea.xml", "Kea"), ("dnsmasq.xml", "dnsmasq")],
+    }
+}
+
+/// Refuse to overwrite preview files in `dir` unless `force`.
+pub(crate) fn check_preview_dir(dir: &Path, backend: &Backend, force: bool) -> Result<()> {
+    if dir.exists() && !dir.is_dir() {
+        bail!("Preview path is not a directory: {}", dir.display());
+    }
+    if force {
+        return Ok(());
+    }
+    let names = subtree_files(backend).into_iter().map(|(name, _)| name);
+    for name in names.chain([RESERVATIONS]) {
+        let path = dir.join(name);
+        if path.exists() {
+            bail!(
+                "Preview file already exists: {} (use --force to overwrite)",
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Write the preview files of the converted config at `out` to `dir`,
+/// returning their paths. A subtree missing from the output is left out.
+pub(crate) fn write_preview(
+    out: &Path,
+    dir: &Path,
+    backend: &Backend,
+    style: &OutputStyle,
+) -> Result<Vec<PathBuf>> {
+    let file = File::open(out)
+        .with_context(|| format!("Failed to read converted config: {}", out.display()))?;
+    let doc = XmlDocument::parse(file).context("Failed to parse converted config")?;
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create preview directory: {}", dir.display()))?;
+
+    let mut written = Vec::new();
+    for (name, element) in subtree_files(backend) {
+        let Some(subtree) = find_descendant_ci(&doc.root, element) else {
+            continue;
+        };
+        let mut xml = Vec::new();
+        write_element(subtree, &mut xml, style)?;
+        xml.push(b'\n');
+        let path = dir.join(name);
+        write_file(&path, &String::from_utf8(xml)?)?;
+        written.push(path);
+    }
+
+    let mut tables = inspect_tables(&doc.root, backend);
+    tables.retain(|table| table.name.contains("reservations") || table.name.contains("hosts"));
+    let path = dir.join(RESERVATIONS);
+    write_file(&path, &render_json(&tables))?;
+    written.push(path);
+    Ok(written)
+}
@@ -0,0 +1,198 @@
+//! `--redact`: pseudonymize identifying values before a report is built.
+//!
+//! Hardware addresses, DUIDs, client IDs and hostnames in the DHCP sections
+//! of the input (and in `--leases`) are replaced with pseudonyms derived
+//! from a keyed hash. The key is random and lives only for this run, so a
+//! value always maps to the same pseudonym within a report, and duplicate
+//! detection still sees duplicates, but pseudonyms cannot be reversed or
+//! matched across runs. Hex identifiers keep their octet count so checks on
+//! hardware address and DUID shape behave as with the real values.
+
+use anyhow::{Context, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::Cursor;
+use xmltree::{Element, XMLNode};
+
+use crate::parse;
+use crate::xml_helpers::XmlDocument;
+use crate::{IscLease, OutputStyle};
+
+/// Sections whose values are pseudonymized.
+const SECTIONS: &[&str] = &[
+    "interfaces",
+    "dhcpd",
+    "dhcpdv6",
+    "Kea",
+    "dnsmasq",
+    "unboundplus",
+];
+/// Elements holding a hardware address, DUID or client ID.
+const ID_TAGS: &[&str] = &[
+    "mac",
+    "spoofmac",
+    "hw_address",
+    "hwaddr",
+    "duid",
+    "cid",
+    "client_id",
+];
+/// Elements holding a hostname.
+const NAME_TAGS: &[&str] = &["hostname", "host"];
+
+pub(crate) struct Redactor {
+    key: RandomState,
+}
+
+impl Redactor {
+    /// A redactor with a fresh random key.
+    pub(crate) fn new() -> Self {
+        Redactor {
+            key: RandomState::new(),
+        }
+    }
+
+    /// `input` with the DHCP sections pseudonymized, re-serialized.
+    pub(crate) fn redact_config(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut doc = XmlDocument::parse(Cursor::new(input)).context("Failed to parse XML")?;
+        self.redact_root(&mut doc.root);
+        let mut out = Vec::new();
+        doc.write(&mut out, &OutputStyle::default())?;
+        Ok(out)
+    }
+
+    /// Pseudonymize the DHCP sections of `root` in place.
+    pub(crate) fn redact_root(&self, root: &mut Element) {
+        for child in root.children.iter_mut().filter_map(|n| n.as_mut_element()) {
+            if SECTIONS.iter().any(|s| child.name.eq_ignore_ascii_case(s)) {
+                self.redact_element(child);
+            } else {
+                // Kea and dnsmasq may sit under <OPNsense>
+                self.redact_root(child);
+            }
+        }
+    }
+
+    pub(crate) fn redact_leases(&self, leases: &mut [IscLease]) {
+        for lease in leases {
+            lease.mac = lease.mac.as_deref().map(|mac| self.id(mac));
+            lease.hostname = lease.hostname.as_deref().map(|name| self.name(name));
+        }
+    }
+
+    fn redact_element(&self, el: &mut Element) {
+        let redact: Option<fn(&Self, &str) -> String> =
+            if ID_TAGS.iter().any(|t| el.name.eq_ignore_ascii_case(t)) {
+                Some(Self::id)
+            } else if NAME_TAGS.iter().any(|t| el.name.eq_ignore_ascii_case(t)) {
+                Some(Self::name)
+            } else {
+                None
+            };
+        for child in &mut el.children {
+            match child {
+                XMLNode::Element(child) => self.redact_element(child),
+                XMLNode::Text(text) => {
+                    if let Some(redact) = redact.filter(|_| !text.trim().is_empty()) {
+                        *text = redact(self, text.trim());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Hex identifiers become random hex of the same octet count, joined
+    /// like the original; anything else becomes `id-` and 8 hex digits.
+    fn id(&self, value: &str) -> String {
+        let Ok(octets) = parse::hwaddr(value) else {
+            return format!("id-{:08x}", self.digest("id", value, 0) as u32);
+        };
+        let canonical: Vec<String> = octets.iter().map(|o| format!("{:02x}", o)).collect();
+        let canonical = canonical.join(":");
+        let separator = if value.contains('-') { "-" } else { ":" };
+        let pseudo: Vec<String> = (0..octets.len())
+            .map(|i| {
+                let word = self.digest("hex", &canonical, i / 8);
+                format!("{:02x}", (word >> ((i % 8) * 8)) as u8)
+            })
+            .collect();
+        pseudo.join(separator)
+    }
+
+    /// `host-` and 8 hex digits; case-insensitive like DNS.
+    fn name(&self, value: &str) -> String {
+        let digest = self.digest("name", &value.to_ascii_lowercase(), 0);
+        format!("host-{:08x}", digest as u32)
+    }
+
+    fn digest(&self, kind: &str, value: &str, block: usize) -> u64 {
+        self.key.hash_one((kind, value, block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_config_is_consistent() {
+        let input = br#"<?xml version="1.0"?>
+<opnsense>
+    <system><hostname>firewall</hostname></system>
+    <dhcpd>
+        <lan>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>Laptop</hostname>
+                <cid>laptop-cid</cid>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <OPNsense>
+        <Kea>
+            <dhcp4>
+                <reservations>
+                    <reservation uuid="r1">
+                        <hw_address>00:11:22:33:44:55</hw_address>
+                        <hostname>laptop</hostname>
+                    </reservation>
+                </reservations>
+            </dhcp4>
+        </Kea>
+    </OPNsense>
+</opnsense>
+"#;
+        let redactor = Redactor::new();
+        let output = redactor.redact_config(input).unwrap();
+        let root = Element::parse(Cursor::new(output)).unwrap();
+        let text = |path: &[&str]| {
+            let mut el = &root;
+            for name in path {
+                el = el.get_child(*name).unwrap();
+            }
+            el.get_text().unwrap().to_string()
+        };
+
+        let staticmap = ["dhcpd", "lan", "staticmap"];
+        let reservation = ["OPNsense", "Kea", "dhcp4", "reservations", "reservation"];
+        let mac = text(&[&staticmap[..], &["mac"]].concat());
+        assert_ne!(mac, "00:11:22:33:44:55");
+        assert_eq!(parse::hwaddr(&mac).ok().map(|o| o.len()), Some(6));
+        assert_eq!(mac, text(&[&reservation[..], &["hw_address"]].concat()));
+
+        let hostname = text(&[&staticmap[..], &["hostname"]].concat());
+        assert!(hostname.starts_with("host-"));
+        assert_eq!(hostname, text(&[&reservation[..], &["hostname"]].concat()));
+        assert!(text(&[&staticmap[..], &["cid"]].concat()).starts_with("id-"));
+        assert_eq!(
+            text(&[&staticmap[..], &["ipaddr"]].concat()),
+            "192.168.1.10"
+        );
+        assert_eq!(text(&["system", "hostname"]), "firewall");
+
+        // A new key gives new pseudonyms
+        assert_ne!(Redactor::new().id("00:11:22:33:44:55"), mac);
+    }
+}
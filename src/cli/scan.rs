@@ -1,12 +1,16 @@
 use crate::migrate::services::isc_enabled_ifaces_v4;
 use crate::migrate::services::isc_enabled_ifaces_v6;
-use crate::{scan_config, scan_counts, MigrationError, MigrationOptions};
+use crate::{MigrationError, ParsedConfig};
 use anyhow::{Context, Result};
 use std::fs::File;
 use std::io::{Cursor, Read};
 
-use super::print_scan_stats;
+use super::redact::Redactor;
+use super::stats_log::append_stats_log;
 use super::ScanArgs;
+use super::{
+    labeled_ifaces, print_lease_schedule, print_scan_stats, print_suppressed_warnings, print_timing,
+};
 
 pub(crate) fn run_scan(args: ScanArgs) -> Result<()> {
     let mut file = File::open(&args.r#in)
@@ -15,18 +19,15 @@ pub(crate) fn run_scan(args: ScanArgs) -> Result<()> {
     file.read_to_end(&mut buffer)
         .with_context(|| format!("Failed to read input file: {}", args.r#in.display()))?;
 
-    let options = MigrationOptions {
-        fail_if_existing: args.fail_if_existing,
-        verbose: args.verbose,
-        backend: args.backend.clone(),
-        create_subnets: args.create_subnets,
-        force_subnets: args.force_subnets,
-        create_options: args.create_options,
-        force_options: args.force_options,
-        enable_backend: args.enable_backend,
-    };
+    let mut options = args.migration.to_options()?;
+    if args.redact {
+        let redactor = Redactor::new();
+        buffer = redactor.redact_config(&buffer)?;
+        redactor.redact_leases(&mut options.leases);
+    }
 
-    let stats = match scan_config(Cursor::new(&buffer), &options) {
+    let parsed = ParsedConfig::parse(Cursor::new(&buffer))?;
+    let stats = match parsed.scan(&options) {
         Ok(stats) => stats,
         Err(e) => {
             if let Some(migration_error) = e.downcast_ref::<MigrationError>() {
@@ -37,8 +38,8 @@ pub(crate) fn run_scan(args: ScanArgs) -> Result<()> {
                         | MigrationError::BackendV6NotConfigured { .. }
                         | MigrationError::NoBackendSubnetsV6 { .. }
                 ) {
-                    if let Ok(stats) = scan_counts(Cursor::new(&buffer), &args.backend) {
-                        print_scan_stats(&stats, &args.backend);
+                    if let Ok(stats) = parsed.scan_counts(&args.migration.backend) {
+                        print_scan_stats(&stats, &args.migration.backend);
                     }
                 }
             }
@@ -47,19 +48,33 @@ pub(crate) fn run_scan(args: ScanArgs) -> Result<()> {
         }
     };
 
-    if args.verbose {
-        if let Ok(root) = xmltree::Element::parse(Cursor::new(&buffer)) {
-            let ifaces_v4 = isc_enabled_ifaces_v4(&root);
-            let ifaces_v6 = isc_enabled_ifaces_v6(&root);
-            if !ifaces_v4.is_empty() {
-                println!("ISC DHCP enabled interfaces (v4): {}", ifaces_v4.join(", "));
-            }
-            if !ifaces_v6.is_empty() {
-                println!("ISC DHCP enabled interfaces (v6): {}", ifaces_v6.join(", "));
-            }
+    if args.migration.verbose {
+        let ifaces_v4 = isc_enabled_ifaces_v4(parsed.root());
+        let ifaces_v6 = isc_enabled_ifaces_v6(parsed.root());
+        if !ifaces_v4.is_empty() {
+            println!(
+                "ISC DHCP enabled interfaces (v4): {}",
+                labeled_ifaces(&ifaces_v4, &stats.interface_labels)
+            );
+        }
+        if !ifaces_v6.is_empty() {
+            println!(
+                "ISC DHCP enabled interfaces (v6): {}",
+                labeled_ifaces(&ifaces_v6, &stats.interface_labels)
+            );
         }
     }
 
-    print_scan_stats(&stats, &args.backend);
+    print_scan_stats(&stats, &args.migration.backend);
+    if args.migration.verbose {
+        print_lease_schedule(&stats);
+    }
+    print_suppressed_warnings(&stats);
+    if let Some(log) = &args.migration.stats_log {
+        append_stats_log(log, "scan", &args.r#in, &options, &stats)?;
+    }
+    if args.migration.timing {
+        print_timing(&stats.timing);
+    }
     Ok(())
 }
@@ -0,0 +1,423 @@
+//! `serve`: scan and convert over HTTP.
+//!
+//! `POST /scan` takes a config.xml body and returns the scan stats as JSON;
+//! `POST /convert` returns the converted config.xml. Each connection gets
+//! its own thread and carries one request. Requests share nothing but the
+//! migration options given on the command line, so they can run
+//! concurrently, up to [`MAX_CONNECTIONS`] at a time; connections beyond
+//! that get a 503 straight away.
+
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{json_string, stats_json, ServeArgs};
+use crate::term;
+use crate::{convert_config, scan_config, MigrationError, MigrationOptions};
+
+/// Largest accepted request body; real config.xml files are a few MiB.
+const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+/// Largest accepted request line or header line.
+const MAX_HEADER_LINE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+/// Drop clients that stop sending mid-request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Drop clients that keep sending, slowly, for longer than this.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Connections handled at once; each may hold a body of up to
+/// [`MAX_BODY_BYTES`] and the converted config.
+const MAX_CONNECTIONS: usize = 16;
+
+pub(crate) fn run_serve(args: ServeArgs) -> Result<()> {
+    let options = Arc::new(args.migration.to_options()?);
+    let listener = TcpListener::bind(args.listen)
+        .with_context(|| format!("Failed to listen on {}", args.listen))?;
+    println!("Listening on http://{}", listener.local_addr()?);
+
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                term::warning(format_args!("Failed to accept connection: {}", e));
+                continue;
+            }
+        };
+        let Some(slot) = ConnectionSlot::acquire(&active) else {
+            let mut response =
+                Response::error(503, "Service Unavailable", "too many requests in progress");
+            response.headers.push(("Retry-After", "1".to_string()));
+            if let Err(e) = stream
+                .set_write_timeout(Some(READ_TIMEOUT))
+                .context("Failed to set write timeout")
+                .and_then(|_| response.write_to(&stream))
+            {
+                term::warning(format_args!("{:#}", e));
+            }
+            continue;
+        };
+        let options = Arc::clone(&options);
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(e) = handle_connection(&stream, &options, REQUEST_TIMEOUT) {
+                term::warning(format_args!("{:#}", e));
+            }
+        });
+    }
+    Ok(())
+}
+
+/// One of the [`MAX_CONNECTIONS`] counted in `active`, given back on drop.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()?;
+        Some(ConnectionSlot(Arc::clone(active)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Reads from a stream until `deadline`, so a client trickling bytes cannot
+/// hold a connection past the request timeout.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left.min(READ_TIMEOUT)))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: u16, reason: &'static str, content_type: &'static str, body: Vec<u8>) -> Self {
+        Response {
+            status,
+            reason,
+            content_type,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    fn error(status: u16, reason: &'static str, message: &str) -> Self {
+        let body = format!("{{\"error\": {}}}\n", json_string(message));
+        Response::new(status, reason, "application/json", body.into_bytes())
+    }
+
+    fn write_to(&self, mut stream: &TcpStream) -> Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            self.reason,
+            self.content_type,
+            self.body.len()
+        );
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        stream
+            .write_all(head.as_bytes())
+            .and_then(|_| stream.write_all(&self.body))
+            .and_then(|_| stream.flush())
+            .context("Failed to write HTTP response")
+    }
+}
+
+fn handle_connection(
+    stream: &TcpStream,
+    options: &MigrationOptions,
+    timeout: Duration,
+) -> Result<()> {
+    stream.set_write_timeout(Some(READ_TIMEOUT))?;
+    let deadline = Instant::now() + timeout;
+    let response = match read_request(stream, deadline) {
+        Ok(request) => route(&request, options),
+        Err(_) if Instant::now() >= deadline => Response::error(
+            408,
+            "Request Timeout",
+            &format!("request took longer than {} seconds", timeout.as_secs()),
+        ),
+        Err(response) => response,
+    };
+    response.write_to(stream)
+}
+
+fn read_request(stream: &TcpStream, deadline: Instant) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::error(400, "Bad Request", message);
+    let mut reader = BufReader::new(DeadlineReader { stream, deadline });
+
+    let request_line = read_line(&mut reader).ok_or_else(|| bad_request("missing request line"))?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad_request("malformed request line"));
+    };
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    let method = method.to_string();
+
+    let mut content_length = None;
+    let mut expect_continue = false;
+    for _ in 0..=MAX_HEADERS {
+        let line = read_line(&mut reader).ok_or_else(|| bad_request("truncated headers"))?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(bad_request("malformed header"));
+        };
+        let value = value.trim();
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            let length = value
+                .parse::<usize>()
+                .map_err(|_| bad_request("invalid Content-Length"))?;
+            content_length = Some(length);
+        } else if name.trim().eq_ignore_ascii_case("expect") {
+            expect_continue = value.eq_ignore_ascii_case("100-continue");
+        }
+    }
+
+    let body = if method == "POST" {
+        let length = content_length
+            .ok_or_else(|| Response::error(411, "Length Required", "missing Content-Length"))?;
+        if length > MAX_BODY_BYTES {
+            return Err(Response::error(
+                413,
+                "Payload Too Large",
+                &format!("body is larger than {} bytes", MAX_BODY_BYTES),
+            ));
+        }
+        if expect_continue {
+            let mut writer = stream;
+            writer
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .map_err(|_| bad_request("failed to continue"))?;
+        }
+        // Read what arrives rather than allocating the announced length
+        let mut body = Vec::new();
+        reader
+            .take(length as u64)
+            .read_to_end(&mut body)
+            .map_err(|_| bad_request("truncated body"))?;
+        if body.len() < length {
+            return Err(bad_request("truncated body"));
+        }
+        body
+    } else {
+        Vec::new()
+    };
+
+    Ok(Request { method, path, body })
+}
+
+/// One CRLF- or LF-terminated line without its terminator; `None` on EOF,
+/// I/O errors or lines over [`MAX_HEADER_LINE`].
+fn read_line(reader: &mut impl BufRead) -> Option<String> {
+    let mut line = String::new();
+    let read = reader
+        .by_ref()
+        .take(MAX_HEADER_LINE)
+        .read_line(&mut line)
+        .ok()?;
+    if read == 0 || !line.ends_with('\n') {
+        return None;
+    }
+    Some(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn route(request: &Request, options: &MigrationOptions) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/scan") => match scan_config(Cursor::new(&request.body), options) {
+            Ok(stats) => Response::new(
+                200,
+                "OK",
+                "application/json",
+                stats_json(&stats).into_bytes(),
+            ),
+            Err(e) => migration_error(&e),
+        },
+        ("POST", "/convert") => {
+            let mut output = Vec::new();
+            match convert_config(Cursor::new(&request.body), &mut output, options) {
+                Ok(stats) => {
+                    let mut response = Response::new(200, "OK", "application/xml", output);
+                    response.headers.push((
+                        "X-Reservations-Created",
+                        stats.reservations_to_create.to_string(),
+                    ));
+                    response.headers.push((
+                        "X-Reservations-V6-Created",
+                        stats.reservations_v6_to_create.to_string(),
+                    ));
+                    response
+                }
+                Err(e) => migration_error(&e),
+            }
+        }
+        (_, "/scan" | "/convert") => {
+            let mut response = Response::error(405, "Method Not Allowed", "use POST");
+            response.headers.push(("Allow", "POST".to_string()));
+            response
+        }
+        _ => Response::error(404, "Not Found", "use POST /scan or POST /convert"),
+    }
+}
+
+/// 422 for configs the migration refuses, 400 for bodies that are not XML.
+fn migration_error(e: &anyhow::Error) -> Response {
+    let message = format!("{:#}", e);
+    if e.downcast_ref::<MigrationError>().is_some() {
+        Response::error(422, "Unprocessable Entity", &message)
+    } else {
+        Response::error(400, "Bad Request", &message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Shutdown;
+
+    /// Send `request` to a single-connection server and return the response.
+    fn exchange(request: &str) -> String {
+        exchange_within(request, REQUEST_TIMEOUT)
+    }
+
+    fn exchange_within(request: &str, timeout: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle_connection(&stream, &MigrationOptions::default(), timeout).unwrap();
+        });
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        server.join().unwrap();
+        response
+    }
+
+    fn post(path: &str, body: &str) -> String {
+        exchange(&format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            body.len(),
+            body
+        ))
+    }
+
+    #[test]
+    fn test_serve_routes() {
+        let response = exchange("GET /scan HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("\r\nAllow: POST\r\n"));
+
+        let response = post("/status", "");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let response = post("/scan", "<pfsense><version>23.3</version></pfsense>");
+        assert!(response.starts_with("HTTP/1.1 422 Unprocessable Entity\r\n"));
+        assert!(response.contains("does not look like an OPNsense config.xml"));
+
+        let response = post("/scan", "not xml");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn test_serve_limits() {
+        let response = exchange("POST /scan HTTP/1.1\r\nContent-Length: 67108864\r\n\r\n<opn");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.contains("truncated body"));
+
+        let response = exchange_within("", Duration::ZERO);
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+
+        let active = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| ConnectionSlot::acquire(&active).unwrap())
+            .collect();
+        assert!(ConnectionSlot::acquire(&active).is_none());
+        drop(slots);
+        assert_eq!(active.load(Ordering::Acquire), 0);
+        assert!(ConnectionSlot::acquire(&active).is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "kea")]
+    fn test_serve_scan_and_convert() {
+        let config = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+        let response = post("/scan?verbose=0", config);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        assert!(response.contains("\"reservations_to_create\": 1,"));
+
+        let response = post("/convert", config);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("X-Reservations-Created: 1\r\n"));
+        assert!(response.contains("<ip_address>192.168.1.10</ip_address>"));
+    }
+}
@@ -0,0 +1,159 @@
+//! `--stats-log`: one JSON line per `scan`/`convert` run, appended to a file.
+//!
+//! Each line records when the run happened, which input it read, the
+//! options that shape the result and the stats, so the log of a fleet of
+//! migrations can be audited or compared afterwards. The input is
+//! identified by a 64-bit FNV-1a hash of its bytes, which tells repeated
+//! runs on the same file apart from runs on a changed one but is not a
+//! cryptographic digest.
+
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use super::{json_string, stats_members};
+use crate::{MigrationOptions, MigrationStats};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Append the log line for a `command` run on `input` to `log`.
+pub(crate) fn append_stats_log(
+    log: &Path,
+    command: &str,
+    input: &Path,
+    options: &MigrationOptions,
+    stats: &MigrationStats,
+) -> Result<()> {
+    let bytes = std::fs::read(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+    let line = stats_log_line(command, input, &bytes, options, stats);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .with_context(|| format!("Failed to open stats log: {}", log.display()))?;
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write stats log: {}", log.display()))
+}
+
+fn stats_log_line(
+    command: &str,
+    input: &Path,
+    bytes: &[u8],
+    options: &MigrationOptions,
+    stats: &MigrationStats,
+) -> String {
+    let members = [
+        ("timestamp", json_string(&options.clock.now_rfc3339())),
+        ("command", json_string(command)),
+        ("input", json_string(&input.display().to_string())),
+        (
+            "input_hash",
+            json_string(&format!("fnv1a64:{:016x}", fnv1a(bytes))),
+        ),
+        ("options", options_json(options)),
+        ("stats", json_object(&stats_members(stats))),
+    ];
+    let mut line = json_object(&members);
+    line.push('\n');
+    line
+}
+
+/// The backend, batch and switched-on flags of `options`.
+fn options_json(options: &MigrationOptions) -> String {
+    let flags = [
+        ("fail_if_existing", options.fail_if_existing),
+        ("create_subnets", options.create_subnets),
+        ("force_subnets", options.force_subnets),
+        ("create_options", options.create_options),
+        ("force_options", options.force_options),
+        ("enable_backend", options.enable_backend),
+        ("skip_unmatched", options.skip_unmatched),
+        (
+            "include_disabled_interfaces",
+            options.include_disabled_interfaces,
+        ),
+        ("dnsmasq_constructor", options.dnsmasq_constructor),
+        ("dnsmasq_hosts_local", options.dnsmasq_hosts_local),
+        ("dnsmasq_host_tags", options.dnsmasq_host_tags),
+        ("carve_pools", options.carve_pools),
+        ("merge_ranges", options.merge_ranges),
+        ("minimal_elements", options.minimal_elements),
+        ("create_dns_hosts", options.create_dns_hosts),
+        ("register_unbound", options.register_unbound),
+        ("mark_created", options.mark_created),
+        ("strip_markers", options.strip_markers),
+        ("validate_schema", options.validate_schema),
+        ("legacy_paths", options.legacy_paths),
+        ("fix_duplicate_uuids", options.fix_duplicate_uuids),
+        ("link_dual_stack", options.link_dual_stack),
+        ("allow_ha", options.allow_ha),
+        ("migrate_placeholders", options.migrate_placeholders),
+        ("global_duids", options.global_duids),
+        ("field_overrides", !options.field_overrides.is_empty()),
+    ];
+    let enabled: Vec<String> = flags
+        .iter()
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| json_string(name))
+        .collect();
+    let limit = options
+        .limit
+        .map_or_else(|| "null".to_string(), |limit| limit.to_string());
+    json_object(&[
+        ("backend", json_string(&options.backend.to_string())),
+        ("offset", options.offset.to_string()),
+        ("limit", limit),
+        ("enabled", format!("[{}]", enabled.join(", "))),
+    ])
+}
+
+fn json_object(members: &[(&str, String)]) -> String {
+    let members: Vec<String> = members
+        .iter()
+        .map(|(name, value)| format!("{}: {}", json_string(name), value))
+        .collect();
+    format!("{{{}}}", members.join(", "))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, Clock};
+
+    #[test]
+    fn test_stats_log_line() {
+        assert_eq!(fnv1a(b""), FNV_OFFSET);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let options = MigrationOptions {
+            backend: Backend::Dnsmasq,
+            create_subnets: true,
+            limit: Some(5),
+            clock: Clock::Fixed(1_767_261_600),
+            ..Default::default()
+        };
+        let stats = MigrationStats {
+            reservations_to_create: 3,
+            ..Default::default()
+        };
+        let line = stats_log_line("convert", Path::new("in.xml"), b"a", &options, &stats);
+        assert!(line.ends_with("}}\n"));
+        assert_eq!(line.lines().count(), 1);
+        assert!(line.starts_with(
+            "{\"timestamp\": \"2026-01-01T10:00:00Z\", \"command\": \"convert\", \
+             \"input\": \"in.xml\", \"input_hash\": \"fnv1a64:af63dc4c8601ec8c\", \
+             \"options\": {\"backend\": \"dnsmasq\", \"offset\": 0, \"limit\": 5, \
+             \"enabled\": [\"create_subnets\"]}, \"stats\": {"
+        ));
+        assert!(line.contains("\"reservations_to_create\": 3"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Scan, convert and export summaries as aligned `label: value` tables.
+//!
+//! Rows are collected first so the values of each section can be lined up;
+//! with colour on, counts of added entries are green and counts of entries
+//! left out are yellow once they are not zero.
+
+use anstyle::Style;
+use std::fmt::Display;
+
+use crate::term::{self, HEADING};
+
+/// Summary lines, printed with the values of each section lined up.
+#[derive(Debug, Default)]
+pub(crate) struct Summary {
+    lines: Vec<Line>,
+}
+
+#[derive(Debug)]
+enum Line {
+    Row {
+        label: String,
+        value: String,
+        style: Option<Style>,
+    },
+    /// Printed as is, under the row before it
+    Detail(String),
+    /// Starts a section after a blank line
+    Heading(String),
+}
+
+impl Summary {
+    pub(crate) fn row(&mut self, label: impl Into<String>, value: impl Display) {
+        self.lines.push(Line::Row {
+            label: label.into(),
+            value: value.to_string(),
+            style: None,
+        });
+    }
+
+    /// A count in `style` unless it is zero.
+    pub(crate) fn count(&mut self, label: impl Into<String>, count: usize, style: Style) {
+        self.lines.push(Line::Row {
+            label: label.into(),
+            value: count.to_string(),
+            style: (count > 0).then_some(style),
+        });
+    }
+
+    pub(crate) fn detail(&mut self, text: impl Into<String>) {
+        self.lines.push(Line::Detail(text.into()));
+    }
+
+    pub(crate) fn heading(&mut self, title: impl Into<String>) {
+        self.lines.push(Line::Heading(title.into()));
+    }
+
+    pub(crate) fn print(&self) {
+        print!("{}", self.render());
+    }
+
+    /// Each section's labels padded to its longest.
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+        for (idx, section) in self.sections().into_iter().enumerate() {
+            let width = section
+                .iter()
+                .filter_map(|line| match line {
+                    Line::Row { label, .. } => Some(label.chars().count() + 1),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0);
+            for line in section {
+                match line {
+                    Line::Row {
+                        label,
+                        value,
+                        style,
+                    } => {
+                        let label = format!("{}:", label);
+                        let value = match style {
+                            Some(style) => term::paint(*style, value).to_string(),
+                            None => value.clone(),
+                        };
+                        out.push_str(&format!("{:<width$} {}\n", label, value, width = width));
+                    }
+                    Line::Detail(text) => {
+                        out.push_str(text);
+                        out.push('\n');
+                    }
+                    Line::Heading(title) => {
+                        if idx > 0 {
+                            out.push('\n');
+                        }
+                        out.push_str(&format!("{}\n", term::paint(HEADING, title)));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// The lines split before each heading.
+    fn sections(&self) -> Vec<&[Line]> {
+        let mut sections = Vec::new();
+        let mut start = 0;
+        for (idx, line) in self.lines.iter().enumerate() {
+            if matches!(line, Line::Heading(_)) && idx > start {
+                sections.push(&self.lines[start..idx]);
+                start = idx;
+            }
+        }
+        if start < self.lines.len() {
+            sections.push(&self.lines[start..]);
+        }
+        sections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::term::ADDED;
+
+    #[test]
+    fn test_render_aligns_each_section() {
+        let mut summary = Summary::default();
+        summary.row("ISC DHCP static mappings found", 3);
+        summary.count("Reservations created", 2, ADDED);
+        summary.detail("  lan: 00:11:22:33:44:55");
+        summary.heading("[kea]");
+        summary.row("Subnets", 1);
+        summary.row("Pools created", 0);
+        assert_eq!(
+            summary.render(),
+            "ISC DHCP static mappings found: 3\n\
+             Reservations created:           2\n  \
+             lan: 00:11:22:33:44:55\n\
+             \n\
+             [kea]\n\
+             Subnets:       1\n\
+             Pools created: 0\n"
+        );
+    }
+}
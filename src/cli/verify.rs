@@ -1,10 +1,16 @@
-use crate::{convert_config, MigrationOptions};
+use crate::xml_helpers::XmlDocument;
+use crate::{
+    verify_findings, FindingCategory, FindingChange, ParsedConfig, Severity, VerifyFinding,
+    VerifyFormat,
+};
 use anyhow::{Context, Result};
 use std::fs::File;
 use std::io::{self, Cursor, Read, Write};
 use xmltree::{Element, XMLNode};
 
-use super::VerifyArgs;
+use super::redact::Redactor;
+use super::{json_string, print_suppressed_warnings, print_timing, VerifyArgs};
+use crate::migrate::retain_categories;
 
 pub(crate) fn run_verify(args: VerifyArgs) -> Result<()> {
     let mut file = File::open(&args.r#in)
@@ -13,51 +19,195 @@ pub(crate) fn run_verify(args: VerifyArgs) -> Result<()> {
     file.read_to_end(&mut input_buf)
         .with_context(|| format!("Failed to read input file: {}", args.r#in.display()))?;
 
-    let options = MigrationOptions {
-        fail_if_existing: args.fail_if_existing,
-        verbose: args.verbose,
-        backend: args.backend.clone(),
-        create_subnets: args.create_subnets,
-        force_subnets: args.force_subnets,
-        create_options: args.create_options,
-        force_options: args.force_options,
-        enable_backend: args.enable_backend,
-    };
+    let mut options = args.migration.to_options()?;
+    if args.redact {
+        let redactor = Redactor::new();
+        input_buf = redactor.redact_config(&input_buf)?;
+        redactor.redact_leases(&mut options.leases);
+    }
 
+    // The input is parsed once, for both converting and comparing
+    let parsed = ParsedConfig::parse(Cursor::new(&input_buf))?;
     let mut output_buf = Vec::new();
-    let _stats = convert_config(Cursor::new(&input_buf), &mut output_buf, &options)?;
-
-    let input_str = normalize_xml(&input_buf)
-        .with_context(|| format!("Failed to normalize input: {}", args.r#in.display()))?;
-    let output_str = normalize_xml(&output_buf).context("Failed to normalize converted output")?;
+    let stats = parsed.convert(&mut output_buf, &options)?;
+    print_suppressed_warnings(&stats);
+    if args.migration.timing {
+        print_timing(&stats.timing);
+    }
 
-    if input_str == output_str {
-        if !args.quiet {
-            println!("No changes.");
-        }
-        return Ok(());
+    let mut input_root = parsed.root().clone();
+    let mut output_root = XmlDocument::parse(Cursor::new(&output_buf))
+        .context("Failed to normalize converted output")?
+        .root;
+    let mut findings = verify_findings(&input_root, &output_root);
+    if !args.only.is_empty() {
+        findings.retain(|f| args.only.contains(&f.category));
+        retain_categories(&mut input_root, &args.only);
+        retain_categories(&mut output_root, &args.only);
     }
+    let max_severity = findings.iter().map(|f| f.severity).max();
 
     if !args.quiet {
-        let diff = similar::TextDiff::from_lines(&input_str, &output_str);
         let mut out = io::stdout().lock();
-        let unified = diff
-            .unified_diff()
-            .context_radius(3)
-            .header("original", "converted")
-            .to_string();
-        write!(out, "{}", unified)?;
+        match args.format {
+            VerifyFormat::Diff => {
+                let input_str = normalize_xml(&input_root);
+                let output_str = normalize_xml(&output_root);
+                if input_str == output_str {
+                    println!("No changes.");
+                } else {
+                    let diff = similar::TextDiff::from_lines(&input_str, &output_str);
+                    let unified = diff
+                        .unified_diff()
+                        .context_radius(3)
+                        .header("original", "converted")
+                        .to_string();
+                    write!(out, "{}", unified)?;
+                }
+                if args.migration.verbose && !findings.is_empty() {
+                    write_summary(&findings, &mut out)?;
+                }
+            }
+            VerifyFormat::Json => write_json(&findings, max_severity, &mut out)?,
+            VerifyFormat::Csv => write_csv(&findings, &mut out)?,
+        }
     }
 
-    Err(anyhow::anyhow!("verify: changes detected"))
+    let failing = findings
+        .iter()
+        .filter(|f| f.severity >= args.fail_on)
+        .count();
+    if failing > 0 {
+        return Err(anyhow::anyhow!(
+            "verify: {} finding(s) at or above {}",
+            failing,
+            severity_label(args.fail_on)
+        ));
+    }
+    Ok(())
+}
+
+/// `{"max_severity": ..., "findings": [...]}`, one finding per line.
+fn write_json<W: Write>(
+    findings: &[VerifyFinding],
+    max_severity: Option<Severity>,
+    out: &mut W,
+) -> Result<()> {
+    let max = max_severity.map_or("null".to_string(), |s| json_string(severity_label(s)));
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"max_severity\": {},", max)?;
+    if findings.is_empty() {
+        writeln!(out, "  \"findings\": []")?;
+    } else {
+        writeln!(out, "  \"findings\": [")?;
+        for (i, finding) in findings.iter().enumerate() {
+            let comma = if i + 1 < findings.len() { "," } else { "" };
+            writeln!(
+                out,
+                "    {{\"severity\": {}, \"category\": {}, \"change\": {}, \"path\": {}, \"detail\": {}}}{}",
+                json_string(severity_label(finding.severity)),
+                json_string(category_label(finding.category)),
+                json_string(change_label(finding.change)),
+                json_string(&finding.path),
+                json_string(&finding.detail),
+                comma
+            )?;
+        }
+        writeln!(out, "  ]")?;
+    }
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+/// Finding counts per category, for reviewing each kind of change apart.
+fn write_summary<W: Write>(findings: &[VerifyFinding], out: &mut W) -> Result<()> {
+    writeln!(out, "Changes by category:")?;
+    for category in [
+        FindingCategory::Reservation,
+        FindingCategory::Subnet,
+        FindingCategory::Option,
+        FindingCategory::Service,
+    ] {
+        let count = |change| {
+            findings
+                .iter()
+                .filter(|f| f.category == category && f.change == change)
+                .count()
+        };
+        let (added, removed, changed) = (
+            count(FindingChange::Added),
+            count(FindingChange::Removed),
+            count(FindingChange::Changed),
+        );
+        if added + removed + changed > 0 {
+            writeln!(
+                out,
+                "  {}: {} added, {} removed, {} changed",
+                category_label(category),
+                added,
+                removed,
+                changed
+            )?;
+        }
+    }
+    Ok(())
 }
 
-fn normalize_xml(input: &[u8]) -> Result<String> {
-    let root = Element::parse(Cursor::new(input)).context("Failed to parse XML")?;
+fn write_csv<W: Write>(findings: &[VerifyFinding], out: &mut W) -> Result<()> {
+    writeln!(out, "severity,category,change,path,detail")?;
+    for finding in findings {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            severity_label(finding.severity),
+            category_label(finding.category),
+            change_label(finding.change),
+            csv_field(&finding.path),
+            csv_field(&finding.detail)
+        )?;
+    }
+    Ok(())
+}
+
+fn severity_label(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+fn category_label(category: FindingCategory) -> &'static str {
+    match category {
+        FindingCategory::Reservation => "reservation",
+        FindingCategory::Subnet => "subnet",
+        FindingCategory::Option => "option",
+        FindingCategory::Service => "service",
+    }
+}
+
+fn change_label(change: FindingChange) -> &'static str {
+    match change {
+        FindingChange::Added => "added",
+        FindingChange::Removed => "removed",
+        FindingChange::Changed => "changed",
+    }
+}
+
+/// Quote a CSV field when it holds a comma, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn normalize_xml(root: &Element) -> String {
     let mut out = String::new();
     out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
-    write_element(&root, 0, &mut out);
-    Ok(out)
+    write_element(root, 0, &mut out);
+    out
 }
 
 fn write_element(el: &Element, indent: usize, out: &mut String) {
@@ -0,0 +1,84 @@
+//! Wall-clock time for lease staleness and created-at markers.
+//!
+//! Timestamps are written as RFC 3339 in UTC, computed from seconds since
+//! the Unix epoch without the system time zone or locale, so output only
+//! depends on the instant. [`Clock::Fixed`] pins that instant, keeping
+//! output stable in tests.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of the current time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Clock {
+    /// The system clock (default)
+    #[default]
+    System,
+    /// This many seconds since the Unix epoch
+    Fixed(u64),
+}
+
+impl Clock {
+    /// Seconds since the Unix epoch.
+    pub fn now(&self) -> u64 {
+        match self {
+            Clock::System => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            Clock::Fixed(secs) => *secs,
+        }
+    }
+
+    /// The current time as `YYYY-MM-DDTHH:MM:SSZ`.
+    pub fn now_rfc3339(&self) -> String {
+        rfc3339_utc(self.now())
+    }
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` for seconds since the Unix epoch.
+pub(crate) fn rfc3339_utc(secs: u64) -> String {
+    let time = secs % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        utc_date(secs),
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// `YYYY-MM-DD` (UTC) for seconds since the Unix epoch.
+pub(crate) fn utc_date(secs: u64) -> String {
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_date() {
+        assert_eq!(utc_date(1_767_261_600), "2026-01-01");
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(951_782_400), "2000-02-29");
+    }
+
+    #[test]
+    fn test_rfc3339_utc() {
+        assert_eq!(rfc3339_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339_utc(1_767_261_600), "2026-01-01T10:00:00Z");
+        assert_eq!(
+            Clock::Fixed(951_868_799).now_rfc3339(),
+            "2000-02-29T23:59:59Z"
+        );
+    }
+}
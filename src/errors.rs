@@ -1,9 +1,134 @@
+use std::fmt;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+use crate::PlanEntry;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum MigrationError {
-    #[error("IP address {0} does not match any configured subnet")]
-    NoMatchingSubnet(String),
+    #[error("IP address {ip} does not match any configured subnet")]
+    SubnetNotFound { ip: String },
+
+    #[error("IP address {ip} is already used by an existing entry")]
+    DuplicateIp { ip: String },
+
+    #[error("{ip}: hardware address {mac} is already used by an existing entry")]
+    DuplicateMac { ip: String, mac: String },
+
+    #[error("{ip}: client ID {client_id} is already used by an existing entry")]
+    DuplicateClientId { ip: String, client_id: String },
+
+    #[error("{ip}: DUID {duid} is already used by an existing entry")]
+    DuplicateDuid { ip: String, duid: String },
+
+    #[error("{ip} on {iface}: {reason}")]
+    UnusableIpv6Address {
+        iface: String,
+        ip: String,
+        reason: String,
+    },
+
+    #[error(
+        "{} static mapping(s) do not match any configured or created subnet:\n{}\n\
+         Add the missing subnets or use --skip-unmatched to skip these mappings.",
+        mappings.len(),
+        format_lines(mappings)
+    )]
+    UnmatchedMappings { mappings: Vec<UnmatchedMapping> },
+
+    #[error(
+        "{} static mapping(s) fall outside every Kea subnet, and OPNsense rejects Kea \
+         reservations outside a subnet:\n{}\n\
+         Add or resize the Kea subnets, or use --create-subnets.",
+        mappings.len(),
+        format_lines(mappings)
+    )]
+    MappingsOutsideSubnets { mappings: Vec<UnmatchedMapping> },
+
+    #[error(
+        "{} static mapping(s) are under DHCP sections for interfaces missing from <interfaces>:\n{}\n\
+         Remove the stale sections or use --orphaned-interfaces skip|subnet-match.",
+        mappings.len(),
+        format_lines(mappings)
+    )]
+    OrphanedInterfaceMappings { mappings: Vec<OrphanedMapping> },
+
+    #[error(
+        "{} plan entr(ies) match no ISC static mapping:\n{}\n\
+         The config changed since the plan was made; run `isc2kea plan` again.",
+        entries.len(),
+        format_lines(entries)
+    )]
+    StalePlan { entries: Vec<PlanEntry> },
+
+    #[error(
+        "{} dnsmasq range(s) overlap existing ranges:\n{}\n\
+         Adjust the existing ranges or use --range-overlap skip|force.",
+        overlaps.len(),
+        format_lines(overlaps)
+    )]
+    OverlappingRanges { overlaps: Vec<RangeOverlap> },
+
+    #[error(
+        "{} ISC range(s) are invalid:\n{}\n\
+         Fix the ranges in the ISC DHCP settings before migrating.",
+        ranges.len(),
+        format_lines(ranges)
+    )]
+    InvalidRanges { ranges: Vec<InvalidIscRange> },
+
+    #[error(
+        "{} created dnsmasq host(s) would be rejected:\n{}\n\
+         Fix the ISC static mappings or the existing dnsmasq hosts and ranges.",
+        issues.len(),
+        format_lines(issues)
+    )]
+    InvalidDnsmasqHosts { issues: Vec<DnsmasqHostIssue> },
+
+    #[error(
+        "{} uuid(s) are used by more than one element:\n{}\n\
+         Fix them in the GUI or use --fix-duplicate-uuids to regenerate them.",
+        duplicates.len(),
+        format_lines(duplicates)
+    )]
+    DuplicateUuids { duplicates: Vec<DuplicateUuid> },
+
+    #[error(
+        "{} element(s) written by the migration do not match the OPNsense schema:\n{}\n\
+         This is a bug in isc2kea; please report it with the output of `isc2kea diag`.",
+        violations.len(),
+        format_lines(violations)
+    )]
+    SchemaViolations { violations: Vec<SchemaViolation> },
+
+    #[error(
+        "This node syncs its DHCP settings ({sections}) to HA peer {peer}.\n\
+         Converting one node leaves the peer on ISC DHCP or overwrites it with the next sync. \
+         Plan the migration of both nodes and use --allow-ha to convert anyway."
+    )]
+    HaSyncEnabled { peer: String, sections: String },
+
+    #[error(
+        "{family} range {from}-{to} is not contained within interface subnet {iface} ({cidr})"
+    )]
+    RangeOutsideSubnet {
+        family: String,
+        iface: String,
+        from: String,
+        to: String,
+        cidr: String,
+    },
+
+    #[error("No interface CIDR found for {family} interface '{iface}'")]
+    MissingInterfaceCidr { family: String, iface: String },
+
+    #[error("Existing reservations found ({count} IPs) and --fail-if-existing is set. Aborting.")]
+    ExistingReservations { count: usize },
+
+    #[error("Input does not look like an OPNsense config.xml: {reason}.\n{hint}")]
+    NotOpnsenseConfig { reason: String, hint: String },
+
+    #[error("Converted output failed validation: {reason}")]
+    InvalidOutput { reason: String },
 
     #[error("Invalid IP address: {0}")]
     InvalidIpAddress(String),
@@ -11,6 +136,15 @@ pub enum MigrationError {
     #[error("Invalid CIDR notation: {0}")]
     InvalidCidr(String),
 
+    #[error("Invalid address range: {0}")]
+    InvalidRange(String),
+
+    #[error("Invalid hardware address '{value}': {reason}")]
+    InvalidHwaddr { value: String, reason: String },
+
+    #[error("Invalid DUID '{value}': {reason}")]
+    InvalidDuid { value: String, reason: String },
+
     #[error("IP address {0} does not match any configured interface subnet")]
     NoMatchingInterface(String),
 
@@ -43,3 +177,156 @@ pub enum MigrationError {
     )]
     NoBackendSubnetsV6 { backend: String },
 }
+
+/// A problem with a dnsmasq host created by the migration.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DnsmasqHostIssue {
+    #[error("{fqdn} ({ip}) duplicates the host name already used by {other_ip}")]
+    DuplicateName {
+        fqdn: String,
+        ip: String,
+        other_ip: String,
+    },
+
+    #[error("{host} has an invalid hardware address '{hwaddr}'")]
+    InvalidHwaddr { host: String, hwaddr: String },
+
+    #[error("{host} uses {ip}, which is outside every dnsmasq DHCP range network")]
+    OutsideRanges { host: String, ip: String },
+}
+
+/// An element written by the migration that the OPNsense schema does not
+/// allow.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SchemaViolation {
+    #[error("{path}: <{element}> is not a field")]
+    UnknownField { path: String, element: String },
+
+    #[error("{path}: holds text, not <{element}>")]
+    ChildOfLeaf { path: String, element: String },
+}
+
+/// A static mapping whose address falls outside every Kea subnet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmatchedMapping {
+    /// Interface key, with its description when it has one
+    pub iface: String,
+    pub ip: String,
+    /// Network of the mapping's interface, when it has one of that family
+    pub iface_cidr: Option<String>,
+    /// Subnets sharing the longest prefix with `ip`, nearest first
+    pub candidates: Vec<String>,
+}
+
+impl fmt::Display for UnmatchedMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} (", self.iface, self.ip)?;
+        if let Some(cidr) = &self.iface_cidr {
+            write!(f, "interface network {}; ", cidr)?;
+        }
+        match self.candidates.as_slice() {
+            [] => write!(f, "no subnets configured)"),
+            [cidr] => write!(f, "nearest subnet {})", cidr),
+            cidrs => write!(f, "nearest subnets {})", cidrs.join(", ")),
+        }
+    }
+}
+
+/// A created dnsmasq range that overlaps existing ranges on its interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeOverlap {
+    /// Interface key, with its description when it has one
+    pub iface: String,
+    pub from: String,
+    pub to: String,
+    /// Existing ranges it overlaps, as `start-end`
+    pub conflicts: Vec<String>,
+}
+
+impl fmt::Display for RangeOverlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}-{} overlaps {}",
+            self.iface,
+            self.from,
+            self.to,
+            self.conflicts.join(", ")
+        )
+    }
+}
+
+/// An ISC range that cannot be converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidIscRange {
+    /// Interface key, with its description when it has one
+    pub iface: String,
+    /// `DHCPv4` or `DHCPv6`
+    pub family: String,
+    pub from: String,
+    pub to: String,
+    /// What is wrong with it, e.g. "ends before it starts"
+    pub problem: String,
+}
+
+impl fmt::Display for InvalidIscRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} range {}-{} {}",
+            self.iface, self.family, self.from, self.to, self.problem
+        )
+    }
+}
+
+/// A `uuid` attribute shared by several elements of one section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateUuid {
+    pub uuid: String,
+    /// Backend section holding the elements, e.g. `Kea`
+    pub section: String,
+    /// Names of the elements using it, in document order
+    pub elements: Vec<String>,
+}
+
+impl fmt::Display for DuplicateUuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {}",
+            self.uuid,
+            self.section,
+            self.elements.join(", ")
+        )
+    }
+}
+
+/// A static mapping under the DHCP section of an interface missing from
+/// `<interfaces>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedMapping {
+    /// `dhcpd` or `dhcpdv6`
+    pub section: String,
+    pub iface: String,
+    pub ip: String,
+    /// Hardware address (v4) or DUID (v6)
+    pub client: String,
+}
+
+impl fmt::Display for OrphanedMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}: {} ({})",
+            self.section, self.iface, self.ip, self.client
+        )
+    }
+}
+
+fn format_lines<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| format!("  {}", item))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,11 +1,46 @@
 use anyhow::Result;
 use std::collections::HashMap;
-use std::net::{Ipv4Addr, Ipv6Addr};
-use std::str::FromStr;
 use xmltree::Element;
 
+use crate::parse;
 use crate::xml_helpers::get_child_ci;
 
+/// Extract interface descriptions from the XML tree (interface name -> `<descr>`)
+///
+/// Interfaces without a description are omitted.
+pub fn extract_interface_labels(root: &Element) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+
+    if let Some(interfaces) = get_child_ci(root, "interfaces") {
+        for iface_elem in interfaces.children.iter().filter_map(|c| c.as_element()) {
+            let descr = get_child_ci(iface_elem, "descr")
+                .and_then(|e| e.get_text())
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default();
+            if !descr.is_empty() {
+                labels.insert(iface_elem.name.clone(), descr);
+            }
+        }
+    }
+
+    labels
+}
+
+/// Format an interface key for display, adding its description when it differs
+/// from the key (e.g. `opt3 (IoT VLAN)`).
+pub fn iface_with_descr(iface: &str, descr: Option<&str>) -> String {
+    match descr {
+        Some(d) if !d.eq_ignore_ascii_case(iface) => format!("{} ({})", iface, d),
+        _ => iface.to_string(),
+    }
+}
+
+/// Format an interface key for display using a label map from
+/// [`extract_interface_labels`].
+pub fn iface_display(iface: &str, labels: &HashMap<String, String>) -> String {
+    iface_with_descr(iface, labels.get(iface).map(String::as_str))
+}
+
 /// Extract interface IPv4 CIDRs from the XML tree (interface name -> CIDR)
 pub fn extract_interface_cidrs(root: &Element) -> Result<HashMap<String, String>> {
     let mut cidrs = HashMap::new();
@@ -30,13 +65,11 @@ pub fn extract_interface_cidrs(root: &Element) -> Result<HashMap<String, String>
                     continue;
                 }
 
-                let prefix: u8 = match subnet.parse() {
-                    Ok(v) => v,
-                    Err(_) => continue,
+                let Ok(prefix) = parse::prefix_v4(&subnet) else {
+                    continue;
                 };
-                let ip = match Ipv4Addr::from_str(&ipaddr) {
-                    Ok(ip) => ip,
-                    Err(_) => continue,
+                let Ok(ip) = parse::ipv4(&ipaddr) else {
+                    continue;
                 };
 
                 let net = ipnet::Ipv4Net::new(ip, prefix)
@@ -75,13 +108,11 @@ pub fn extract_interface_cidrs_v6(root: &Element) -> Result<HashMap<String, Stri
                     continue;
                 }
 
-                let prefix: u8 = match subnet.parse() {
-                    Ok(v) => v,
-                    Err(_) => continue,
+                let Ok(prefix) = parse::prefix_v6(&subnet) else {
+                    continue;
                 };
-                let ip = match Ipv6Addr::from_str(&ipaddr) {
-                    Ok(ip) => ip,
-                    Err(_) => continue,
+                let Ok(ip) = parse::ipv6(&ipaddr) else {
+                    continue;
                 };
 
                 let net = ipnet::Ipv6Net::new(ip, prefix)
@@ -1,242 +1,552 @@
 use anyhow::Result;
+use std::collections::{HashMap, HashSet};
 use xmltree::Element;
 
-use crate::xml_helpers::get_child_ci;
+use crate::term;
+use crate::xml_helpers::{child_text, get_child_ci, get_children_ci};
 use crate::{
-    IscDhcpOptionsV4, IscDhcpOptionsV6, IscRangeV4, IscRangeV6, IscStaticMap, IscStaticMapV6,
+    IscDhcpOptionsV4, IscDhcpOptionsV6, IscNumberOption, IscRangeV4, IscRangeV6, IscStaticMap,
+    IscStaticMapV6,
 };
 
-/// Extract ISC static mappings from the XML tree
-pub fn extract_isc_mappings(root: &Element) -> Result<Vec<IscStaticMap>> {
-    let mut mappings = Vec::new();
-
-    // Navigate to <dhcpd> (case-insensitive)
-    if let Some(dhcpd) = get_child_ci(root, "dhcpd") {
-        // Iterate over all interface nodes (lan, wan, opt1, etc.)
-        for iface_node in dhcpd.children.iter() {
-            if let Some(iface_elem) = iface_node.as_element() {
-                let iface_name = iface_elem.name.clone();
-                // Look for staticmap children (case-insensitive)
-                for child in iface_elem.children.iter() {
-                    if let Some(staticmap) = child.as_element() {
-                        if staticmap.name.eq_ignore_ascii_case("staticmap") {
-                            let mac = get_child_ci(staticmap, "mac")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-
-                            let ipaddr = get_child_ci(staticmap, "ipaddr")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-
-                            // Skip entries without essential fields
-                            if mac.is_empty() || ipaddr.is_empty() {
-                                continue;
-                            }
-
-                            let hostname = get_child_ci(staticmap, "hostname")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string());
-
-                            let cid = get_child_ci(staticmap, "cid")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string());
-
-                            let descr = get_child_ci(staticmap, "descr")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string());
-
-                            mappings.push(IscStaticMap {
-                                iface: iface_name.clone(),
-                                mac,
-                                ipaddr,
-                                hostname,
-                                cid,
-                                descr,
-                            });
-                        }
-                    }
+/// `<dhcpd>` or `<dhcpdv6>` sections to read, in document order: every one
+/// under the root, then any copies under `<installedpackages>`. Configs
+/// synced over XMLRPC can carry several.
+fn isc_sections<'a>(root: &'a Element, name: &'a str) -> Vec<&'a Element> {
+    let mut sections: Vec<&Element> = get_children_ci(root, name).collect();
+    if let Some(packages) = get_child_ci(root, "installedpackages") {
+        sections.extend(get_children_ci(packages, name));
+    }
+    sections
+}
+
+/// Legacy `<installedpackages><{name}><config>` wrappers, each holding
+/// interface nodes as in a top-level section.
+pub(crate) fn legacy_sections<'a>(root: &'a Element, name: &'a str) -> Vec<&'a Element> {
+    get_children_ci(root, "installedpackages")
+        .flat_map(|packages| get_children_ci(packages, name))
+        .flat_map(|section| get_children_ci(section, "config"))
+        .collect()
+}
+
+/// Streaming merge of static mappings read from each section. Entries in
+/// later sections that exactly copy an earlier one are dropped; ones that
+/// reuse an earlier entry's `keys` (interface + MAC/DUID, interface + IP)
+/// with different fields are dropped with a warning, keeping the first.
+struct MappingMerge<T> {
+    /// Whether more than one section is read; with one, nothing is tracked
+    multi: bool,
+    kept: Vec<T>,
+    seen: HashMap<String, usize>,
+    keys: fn(&T) -> [String; 2],
+    describe: fn(&T) -> String,
+}
+
+impl<T: Clone + PartialEq> MappingMerge<T> {
+    fn new(sections: usize, keys: fn(&T) -> [String; 2], describe: fn(&T) -> String) -> Self {
+        MappingMerge {
+            multi: sections > 1,
+            kept: Vec::new(),
+            seen: HashMap::new(),
+            keys,
+            describe,
+        }
+    }
+
+    /// Whether `entry`, read from `section`, should be kept.
+    fn accept(&mut self, section: usize, entry: &T) -> bool {
+        if !self.multi {
+            return true;
+        }
+        let keys = (self.keys)(entry);
+        if section > 0 {
+            if let Some(idx) = keys.iter().find_map(|key| self.seen.get(key).copied()) {
+                if self.kept[idx] != *entry {
+                    term::warning(format_args!(
+                        "{} in a duplicated ISC section conflicts with {}; keeping the first.",
+                        (self.describe)(entry),
+                        (self.describe)(&self.kept[idx])
+                    ));
                 }
+                return false;
             }
         }
+        for key in keys {
+            self.seen.entry(key).or_insert(self.kept.len());
+        }
+        self.kept.push(entry.clone());
+        true
     }
+}
 
-    Ok(mappings)
+/// Streaming merge of per-interface settings read from each section: an
+/// interface takes its entries from the first section that has any, with a
+/// warning when a later section disagrees.
+struct IfaceMerge<T> {
+    /// Whether more than one section is read; with one, nothing is tracked
+    multi: bool,
+    owner: HashMap<String, usize>,
+    kept: Vec<T>,
+    warned: HashSet<(usize, String)>,
+    iface: fn(&T) -> &str,
+    what: &'static str,
 }
 
-/// Extract ISC DHCPv6 static mappings from the XML tree
-pub fn extract_isc_mappings_v6(root: &Element) -> Result<Vec<IscStaticMapV6>> {
-    let mut mappings = Vec::new();
-
-    // Navigate to <dhcpdv6> (case-insensitive)
-    if let Some(dhcpdv6) = get_child_ci(root, "dhcpdv6") {
-        // Iterate over all interface nodes (lan, wan, opt1, etc.)
-        for iface_node in dhcpdv6.children.iter() {
-            if let Some(iface_elem) = iface_node.as_element() {
-                let iface_name = iface_elem.name.clone();
-                // Look for staticmap children (case-insensitive)
-                for child in iface_elem.children.iter() {
-                    if let Some(staticmap) = child.as_element() {
-                        if staticmap.name.eq_ignore_ascii_case("staticmap") {
-                            let duid = get_child_ci(staticmap, "duid")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-
-                            let ipaddr = get_child_ci(staticmap, "ipaddrv6")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-
-                            // Skip entries without essential fields
-                            if duid.is_empty() || ipaddr.is_empty() {
-                                continue;
-                            }
-
-                            let hostname = get_child_ci(staticmap, "hostname")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string());
-
-                            let descr = get_child_ci(staticmap, "descr")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string());
-
-                            let domain_search = get_child_ci(staticmap, "domainsearchlist")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string());
-
-                            mappings.push(IscStaticMapV6 {
-                                iface: iface_name.clone(),
-                                duid,
-                                ipaddr,
-                                hostname,
-                                descr,
-                                domain_search,
-                            });
-                        }
-                    }
+impl<T: Clone + PartialEq> IfaceMerge<T> {
+    fn new(sections: usize, iface: fn(&T) -> &str, what: &'static str) -> Self {
+        IfaceMerge {
+            multi: sections > 1,
+            owner: HashMap::new(),
+            kept: Vec::new(),
+            warned: HashSet::new(),
+            iface,
+            what,
+        }
+    }
+
+    /// Whether `entry`, read from `section`, should be kept.
+    fn accept(&mut self, section: usize, entry: &T) -> bool {
+        if !self.multi {
+            return true;
+        }
+        let name = (self.iface)(entry).to_string();
+        match self.owner.get(&name) {
+            Some(&owner) if owner != section => {
+                if !self.kept.contains(entry) && self.warned.insert((section, name.clone())) {
+                    term::warning(format_args!(
+                        "duplicated ISC section has different {} for {}; keeping the first.",
+                        self.what, name
+                    ));
                 }
+                false
+            }
+            _ => {
+                self.owner.entry(name).or_insert(section);
+                self.kept.push(entry.clone());
+                true
             }
         }
     }
+}
+
+/// Interface nodes (lan, wan, opt1, etc.) of each section, tagged with the
+/// section's position.
+fn section_ifaces(sections: Vec<&Element>) -> impl Iterator<Item = (usize, &Element)> {
+    sections.into_iter().enumerate().flat_map(|(idx, section)| {
+        section
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .map(move |iface| (idx, iface))
+    })
+}
+
+/// `tag` children (case-insensitive) of each interface node, parsed with
+/// `parse(iface_name, child)`.
+fn section_entries<'a, T: 'a>(
+    sections: Vec<&'a Element>,
+    tag: &'static str,
+    parse: fn(&str, &Element) -> Option<T>,
+) -> impl Iterator<Item = (usize, T)> + 'a {
+    section_ifaces(sections).flat_map(move |(idx, iface)| {
+        iface
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .filter(move |child| child.name.eq_ignore_ascii_case(tag))
+            .filter_map(move |child| parse(&iface.name, child))
+            .map(move |entry| (idx, entry))
+    })
+}
+
+fn mapping_keys_v4(m: &IscStaticMap) -> [String; 2] {
+    [
+        format!("{}|{}", m.iface, m.mac.to_ascii_lowercase()),
+        format!("{}|{}", m.iface, m.ipaddr),
+    ]
+}
+
+fn describe_mapping_v4(m: &IscStaticMap) -> String {
+    format!("{} ({}) on {}", m.ipaddr, m.mac, m.iface)
+}
+
+fn mapping_keys_v6(m: &IscStaticMapV6) -> [String; 2] {
+    [
+        format!("{}|{}", m.iface, m.duid.to_ascii_lowercase()),
+        format!("{}|{}", m.iface, m.ipaddr),
+    ]
+}
+
+fn describe_mapping_v6(m: &IscStaticMapV6) -> String {
+    format!("{} ({}) on {}", m.ipaddr, m.duid, m.iface)
+}
+
+/// Iterate ISC static mappings in document order without collecting them.
+///
+/// Duplicated `<dhcpd>` sections are merged as they are read, so mappings
+/// repeated in a later section are skipped.
+pub fn iter_isc_mappings(root: &Element) -> impl Iterator<Item = IscStaticMap> + '_ {
+    mappings_from_sections(isc_sections(root, "dhcpd"))
+}
+
+/// Extract ISC static mappings from the XML tree
+pub fn extract_isc_mappings(root: &Element) -> Result<Vec<IscStaticMap>> {
+    Ok(iter_isc_mappings(root).collect())
+}
+
+/// Extract ISC static mappings from legacy pfSense-era package paths
+/// (`<installedpackages><dhcpd><config>`)
+pub(crate) fn extract_legacy_isc_mappings(root: &Element) -> Result<Vec<IscStaticMap>> {
+    Ok(mappings_from_sections(legacy_sections(root, "dhcpd")).collect())
+}
+
+fn mappings_from_sections(sections: Vec<&Element>) -> impl Iterator<Item = IscStaticMap> + '_ {
+    let mut merge = MappingMerge::new(sections.len(), mapping_keys_v4, describe_mapping_v4);
+    section_entries(sections, "staticmap", staticmap_v4)
+        .filter(move |(idx, mapping)| merge.accept(*idx, mapping))
+        .map(|(_, mapping)| mapping)
+}
+
+/// Iterate ISC static mappings that have a MAC but no IP address, in
+/// document order. OPNsense allows these as placeholders that document a
+/// device; [`iter_isc_mappings`] skips them. Copies in duplicated `<dhcpd>`
+/// sections are listed once.
+pub fn iter_isc_placeholder_mappings(root: &Element) -> impl Iterator<Item = IscStaticMap> + '_ {
+    let mut seen = HashSet::new();
+    section_entries(
+        isc_sections(root, "dhcpd"),
+        "staticmap",
+        staticmap_fields_v4,
+    )
+    .map(|(_, mapping)| mapping)
+    .filter(|mapping| mapping.ipaddr.trim().is_empty())
+    .filter(move |mapping| {
+        seen.insert(format!(
+            "{}|{}",
+            mapping.iface,
+            mapping.mac.to_ascii_lowercase()
+        ))
+    })
+}
+
+fn staticmap_v4(iface: &str, staticmap: &Element) -> Option<IscStaticMap> {
+    // Skip entries without essential fields
+    staticmap_fields_v4(iface, staticmap).filter(|mapping| !mapping.ipaddr.is_empty())
+}
+
+/// Fields of a DHCPv4 static mapping with a MAC, whether or not it has an
+/// IP address.
+fn staticmap_fields_v4(iface: &str, staticmap: &Element) -> Option<IscStaticMap> {
+    let mac = child_text(staticmap, "mac").unwrap_or_default();
+    let ipaddr = child_text(staticmap, "ipaddr").unwrap_or_default();
+
+    if mac.is_empty() {
+        return None;
+    }
+
+    // As for the interface, "none" sends no router option at all
+    let gateway = child_text(staticmap, "gateway")
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    let gateway_suppressed = gateway
+        .as_deref()
+        .is_some_and(|v| v.eq_ignore_ascii_case("none"));
+
+    Some(IscStaticMap {
+        iface: iface.to_string(),
+        mac,
+        ipaddr,
+        hostname: child_text(staticmap, "hostname"),
+        cid: child_text(staticmap, "cid"),
+        descr: child_text(staticmap, "descr"),
+        domain: child_text(staticmap, "domain").filter(|s| !s.is_empty()),
+        domain_search: child_text(staticmap, "domainsearchlist").filter(|s| !s.is_empty()),
+        dns_servers: get_children_ci(staticmap, "dnsserver")
+            .filter_map(|e| e.get_text())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect(),
+        gateway: gateway.filter(|_| !gateway_suppressed),
+        gateway_suppressed,
+    })
+}
+
+/// Iterate ISC DHCPv6 static mappings in document order without collecting
+/// them, merging duplicated `<dhcpdv6>` sections as for
+/// [`iter_isc_mappings`].
+pub fn iter_isc_mappings_v6(root: &Element) -> impl Iterator<Item = IscStaticMapV6> + '_ {
+    mappings_v6_from_sections(isc_sections(root, "dhcpdv6"))
+}
+
+/// Extract ISC DHCPv6 static mappings from the XML tree
+pub fn extract_isc_mappings_v6(root: &Element) -> Result<Vec<IscStaticMapV6>> {
+    Ok(iter_isc_mappings_v6(root).collect())
+}
+
+/// Extract ISC DHCPv6 static mappings from legacy pfSense-era package paths
+/// (`<installedpackages><dhcpdv6><config>`)
+pub(crate) fn extract_legacy_isc_mappings_v6(root: &Element) -> Result<Vec<IscStaticMapV6>> {
+    Ok(mappings_v6_from_sections(legacy_sections(root, "dhcpdv6")).collect())
+}
+
+fn mappings_v6_from_sections(sections: Vec<&Element>) -> impl Iterator<Item = IscStaticMapV6> + '_ {
+    let mut merge = MappingMerge::new(sections.len(), mapping_keys_v6, describe_mapping_v6);
+    section_entries(sections, "staticmap", staticmap_v6)
+        .filter(move |(idx, mapping)| merge.accept(*idx, mapping))
+        .map(|(_, mapping)| mapping)
+}
+
+fn staticmap_v6(iface: &str, staticmap: &Element) -> Option<IscStaticMapV6> {
+    let duid = child_text(staticmap, "duid").unwrap_or_default();
+    let ipaddr = child_text(staticmap, "ipaddrv6").unwrap_or_default();
+
+    // Skip entries without essential fields
+    if duid.is_empty() || ipaddr.is_empty() {
+        return None;
+    }
 
-    Ok(mappings)
+    // The prefix may carry its length (`2001:db8:100::/56`) or leave it to
+    // `<prefixlen>`
+    let (prefix, prefix_len) = match child_text(staticmap, "prefix").filter(|p| !p.is_empty()) {
+        Some(prefix) => match prefix.split_once('/') {
+            Some((addr, len)) => (Some(addr.trim().to_string()), len.trim().parse().ok()),
+            None => (
+                Some(prefix),
+                child_text(staticmap, "prefixlen").and_then(|l| l.parse().ok()),
+            ),
+        },
+        None => (None, None),
+    };
+
+    Some(IscStaticMapV6 {
+        iface: iface.to_string(),
+        duid,
+        ipaddr,
+        hostname: child_text(staticmap, "hostname"),
+        descr: child_text(staticmap, "descr"),
+        domain_search: child_text(staticmap, "domainsearchlist"),
+        prefix,
+        prefix_len,
+    })
+}
+
+/// Iterate ISC DHCPv4 options per interface without collecting them; with
+/// duplicated sections an interface keeps the first options found.
+pub fn iter_isc_options_v4(root: &Element) -> impl Iterator<Item = IscDhcpOptionsV4> + '_ {
+    let sections = isc_sections(root, "dhcpd");
+    let mut merge = IfaceMerge::new(
+        sections.len(),
+        |o: &IscDhcpOptionsV4| &o.iface,
+        "DHCPv4 options",
+    );
+    section_ifaces(sections)
+        .filter_map(|(idx, iface)| Some((idx, options_v4(iface)?)))
+        .filter(move |(idx, options)| merge.accept(*idx, options))
+        .map(|(_, options)| options)
 }
 
 /// Extract ISC DHCPv4 options per interface
 pub fn extract_isc_options_v4(root: &Element) -> Result<Vec<IscDhcpOptionsV4>> {
-    let mut options = Vec::new();
-
-    if let Some(dhcpd) = get_child_ci(root, "dhcpd") {
-        for iface_node in dhcpd.children.iter() {
-            if let Some(iface_elem) = iface_node.as_element() {
-                let iface_name = iface_elem.name.clone();
-                let mut dns_servers = Vec::new();
-                let mut ntp_servers = Vec::new();
-                let mut routers = None;
-                let mut domain_name = None;
-                let mut domain_search = None;
-
-                for child in iface_elem.children.iter().filter_map(|c| c.as_element()) {
-                    if child.name.eq_ignore_ascii_case("dnsserver") {
-                        if let Some(val) = child.get_text() {
-                            let v = val.to_string();
-                            if !v.is_empty() {
-                                dns_servers.push(v);
-                            }
-                        }
-                    }
-                    if child.name.eq_ignore_ascii_case("ntpserver") {
-                        if let Some(val) = child.get_text() {
-                            let v = val.to_string();
-                            if !v.is_empty() {
-                                ntp_servers.push(v);
-                            }
-                        }
-                    }
-                    if child.name.eq_ignore_ascii_case("gateway") {
-                        routers = child
-                            .get_text()
-                            .map(|v| v.to_string())
-                            .filter(|v| !v.is_empty());
-                    }
-                    if child.name.eq_ignore_ascii_case("domain") {
-                        domain_name = child
-                            .get_text()
-                            .map(|v| v.to_string())
-                            .filter(|v| !v.is_empty());
-                    }
-                    if child.name.eq_ignore_ascii_case("domainsearchlist") {
-                        domain_search = child
-                            .get_text()
-                            .map(|v| v.to_string())
-                            .filter(|v| !v.is_empty());
-                    }
-                }
+    Ok(iter_isc_options_v4(root).collect())
+}
+
+/// Iterate custom numbered ISC DHCPv4 options (`<numberoptions>`) per
+/// interface; with duplicated sections an interface keeps the first found.
+pub fn iter_isc_number_options_v4(root: &Element) -> impl Iterator<Item = IscNumberOption> + '_ {
+    let sections = isc_sections(root, "dhcpd");
+    let mut merge = IfaceMerge::new(
+        sections.len(),
+        |o: &IscNumberOption| &o.iface,
+        "DHCPv4 custom options",
+    );
+    section_entries(sections, "numberoptions", |iface, list| {
+        Some(number_options(iface, list))
+    })
+    .flat_map(|(idx, options)| options.into_iter().map(move |option| (idx, option)))
+    .filter(move |(idx, option)| merge.accept(*idx, option))
+    .map(|(_, option)| option)
+}
 
-                if !dns_servers.is_empty()
-                    || !ntp_servers.is_empty()
-                    || routers.is_some()
-                    || domain_name.is_some()
-                    || domain_search.is_some()
-                {
-                    options.push(IscDhcpOptionsV4 {
-                        iface: iface_name,
-                        dns_servers,
-                        routers,
-                        domain_name,
-                        domain_search: domain_search.map(normalize_domain_search),
-                        ntp_servers,
-                    });
+fn number_options(iface: &str, list: &Element) -> Vec<IscNumberOption> {
+    get_children_ci(list, "item")
+        .filter_map(|item| {
+            let number = child_text(item, "number")?.trim().parse().ok()?;
+            let value = child_text(item, "value")?.trim().to_string();
+            if value.is_empty() {
+                return None;
+            }
+            Some(IscNumberOption {
+                iface: iface.to_string(),
+                number,
+                kind: child_text(item, "type")
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty()),
+                value,
+            })
+        })
+        .collect()
+}
+
+fn options_v4(iface_elem: &Element) -> Option<IscDhcpOptionsV4> {
+    let mut dns_servers = Vec::new();
+    let mut ntp_servers = Vec::new();
+    let mut wins_servers = Vec::new();
+    let mut routers = None;
+    let mut routers_suppressed = false;
+    let mut domain_name = None;
+    let mut domain_search = None;
+
+    for child in iface_elem.children.iter().filter_map(|c| c.as_element()) {
+        if child.name.eq_ignore_ascii_case("dnsserver") {
+            if let Some(val) = child.get_text() {
+                let v = val.to_string();
+                if !v.is_empty() {
+                    dns_servers.push(v);
+                }
+            }
+        }
+        if child.name.eq_ignore_ascii_case("ntpserver") {
+            if let Some(val) = child.get_text() {
+                let v = val.to_string();
+                if !v.is_empty() {
+                    ntp_servers.push(v);
                 }
             }
         }
+        if child.name.eq_ignore_ascii_case("winsserver") {
+            if let Some(val) = child.get_text() {
+                let v = val.to_string();
+                if !v.is_empty() {
+                    wins_servers.push(v);
+                }
+            }
+        }
+        if child.name.eq_ignore_ascii_case("gateway") {
+            // A blank gateway sends the interface address, "none" nothing
+            let gateway = child.get_text().map(|v| v.trim().to_string());
+            routers_suppressed = gateway
+                .as_deref()
+                .is_some_and(|v| v.eq_ignore_ascii_case("none"));
+            routers = gateway.filter(|v| !v.is_empty() && !routers_suppressed);
+        }
+        if child.name.eq_ignore_ascii_case("domain") {
+            domain_name = child
+                .get_text()
+                .map(|v| v.to_string())
+                .filter(|v| !v.is_empty());
+        }
+        if child.name.eq_ignore_ascii_case("domainsearchlist") {
+            domain_search = child
+                .get_text()
+                .map(|v| v.to_string())
+                .filter(|v| !v.is_empty());
+        }
+    }
+
+    if dns_servers.is_empty()
+        && ntp_servers.is_empty()
+        && wins_servers.is_empty()
+        && routers.is_none()
+        && !routers_suppressed
+        && domain_name.is_none()
+        && domain_search.is_none()
+    {
+        return None;
     }
+    Some(IscDhcpOptionsV4 {
+        iface: iface_elem.name.clone(),
+        dns_servers,
+        routers,
+        routers_suppressed,
+        domain_name,
+        domain_search: domain_search.map(normalize_domain_search),
+        ntp_servers,
+        wins_servers,
+    })
+}
 
-    Ok(options)
+/// Iterate ISC DHCPv6 options per interface without collecting them; with
+/// duplicated sections an interface keeps the first options found.
+pub fn iter_isc_options_v6(root: &Element) -> impl Iterator<Item = IscDhcpOptionsV6> + '_ {
+    let sections = isc_sections(root, "dhcpdv6");
+    let mut merge = IfaceMerge::new(
+        sections.len(),
+        |o: &IscDhcpOptionsV6| &o.iface,
+        "DHCPv6 options",
+    );
+    section_ifaces(sections)
+        .filter_map(|(idx, iface)| Some((idx, options_v6(iface)?)))
+        .filter(move |(idx, options)| merge.accept(*idx, options))
+        .map(|(_, options)| options)
 }
 
 /// Extract ISC DHCPv6 options per interface
 pub fn extract_isc_options_v6(root: &Element) -> Result<Vec<IscDhcpOptionsV6>> {
-    let mut options = Vec::new();
-
-    if let Some(dhcpdv6) = get_child_ci(root, "dhcpdv6") {
-        for iface_node in dhcpdv6.children.iter() {
-            if let Some(iface_elem) = iface_node.as_element() {
-                let iface_name = iface_elem.name.clone();
-                let mut dns_servers = Vec::new();
-                let mut domain_search = None;
-
-                for child in iface_elem.children.iter().filter_map(|c| c.as_element()) {
-                    if child.name.eq_ignore_ascii_case("dnsserver") {
-                        if let Some(val) = child.get_text() {
-                            let v = val.to_string();
-                            if !v.is_empty() {
-                                dns_servers.push(v);
-                            }
-                        }
-                    }
-                    if child.name.eq_ignore_ascii_case("domainsearchlist") {
-                        domain_search = child
-                            .get_text()
-                            .map(|v| v.to_string())
-                            .filter(|v| !v.is_empty());
-                    }
-                }
+    Ok(iter_isc_options_v6(root).collect())
+}
 
-                if !dns_servers.is_empty() || domain_search.is_some() {
-                    options.push(IscDhcpOptionsV6 {
-                        iface: iface_name,
-                        dns_servers,
-                        domain_search: domain_search.map(normalize_domain_search),
-                    });
+fn options_v6(iface_elem: &Element) -> Option<IscDhcpOptionsV6> {
+    let mut dns_servers = Vec::new();
+    let mut domain_search = None;
+    let mut ntp_servers = Vec::new();
+    let mut sip_servers = Vec::new();
+    let mut bootfile_url = None;
+
+    for child in iface_elem.children.iter().filter_map(|c| c.as_element()) {
+        if child.name.eq_ignore_ascii_case("dnsserver") {
+            if let Some(val) = child.get_text() {
+                let v = val.to_string();
+                if !v.is_empty() {
+                    dns_servers.push(v);
+                }
+            }
+        }
+        if child.name.eq_ignore_ascii_case("domainsearchlist") {
+            domain_search = child
+                .get_text()
+                .map(|v| v.to_string())
+                .filter(|v| !v.is_empty());
+        }
+        if child.name.eq_ignore_ascii_case("ntpserver") {
+            if let Some(val) = child.get_text() {
+                let v = val.to_string();
+                if !v.is_empty() {
+                    ntp_servers.push(v);
                 }
             }
         }
+        if child.name.eq_ignore_ascii_case("sipserver") {
+            if let Some(val) = child.get_text() {
+                let v = val.to_string();
+                if !v.is_empty() {
+                    sip_servers.push(v);
+                }
+            }
+        }
+        if child.name.eq_ignore_ascii_case("bootfile_url") {
+            bootfile_url = child
+                .get_text()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty());
+        }
     }
 
-    Ok(options)
+    if dns_servers.is_empty()
+        && domain_search.is_none()
+        && ntp_servers.is_empty()
+        && sip_servers.is_empty()
+        && bootfile_url.is_none()
+    {
+        return None;
+    }
+    Some(IscDhcpOptionsV6 {
+        iface: iface_elem.name.clone(),
+        dns_servers,
+        domain_search: domain_search.map(normalize_domain_search),
+        ntp_servers,
+        sip_servers,
+        bootfile_url,
+    })
 }
 
 fn normalize_domain_search(raw: String) -> String {
@@ -246,76 +556,105 @@ fn normalize_domain_search(raw: String) -> String {
         .join(" ")
 }
 
-/// Extract ISC DHCPv4 ranges from the XML tree
-pub fn extract_isc_ranges(root: &Element) -> Result<Vec<IscRangeV4>> {
-    let mut ranges = Vec::new();
-
-    if let Some(dhcpd) = get_child_ci(root, "dhcpd") {
-        for iface_node in dhcpd.children.iter() {
-            if let Some(iface_elem) = iface_node.as_element() {
-                let iface_name = iface_elem.name.clone();
-                for child in iface_elem.children.iter() {
-                    if let Some(range) = child.as_element() {
-                        if range.name.eq_ignore_ascii_case("range") {
-                            let from = get_child_ci(range, "from")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-                            let to = get_child_ci(range, "to")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-                            if from.is_empty() || to.is_empty() {
-                                continue;
-                            }
-                            ranges.push(IscRangeV4 {
-                                iface: iface_name.clone(),
-                                from,
-                                to,
-                            });
-                        }
-                    }
-                }
+/// Iterate ISC DHCPv4 ranges without collecting them; with duplicated
+/// sections an interface keeps the ranges of the first section that has any.
+pub fn iter_isc_ranges(root: &Element) -> impl Iterator<Item = IscRangeV4> + '_ {
+    let sections = isc_sections(root, "dhcpd");
+    let mut merge = IfaceMerge::new(sections.len(), |r: &IscRangeV4| &r.iface, "DHCPv4 ranges");
+    section_entries(sections, "range", |iface, range| {
+        let (from, to) = range_bounds(range)?;
+        Some(IscRangeV4 {
+            iface: iface.to_string(),
+            from,
+            to,
+        })
+    })
+    .filter(move |(idx, range)| merge.accept(*idx, range))
+    .map(|(_, range)| range)
+}
+
+/// Iterate ISC DHCPv6 ranges without collecting them; with duplicated
+/// sections an interface keeps the ranges of the first section that has any.
+pub fn iter_isc_ranges_v6(root: &Element) -> impl Iterator<Item = IscRangeV6> + '_ {
+    let sections = isc_sections(root, "dhcpdv6");
+    let mut merge = IfaceMerge::new(sections.len(), |r: &IscRangeV6| &r.iface, "DHCPv6 ranges");
+    section_entries(sections, "range", |iface, range| {
+        let (from, to) = range_bounds(range)?;
+        Some(IscRangeV6 {
+            iface: iface.to_string(),
+            from,
+            to,
+        })
+    })
+    .filter(move |(idx, range)| merge.accept(*idx, range))
+    .map(|(_, range)| range)
+}
+
+/// `<from>`/`<to>` of a range, skipping ranges missing either.
+fn range_bounds(range: &Element) -> Option<(String, String)> {
+    let from = child_text(range, "from").unwrap_or_default();
+    let to = child_text(range, "to").unwrap_or_default();
+    if from.is_empty() || to.is_empty() {
+        return None;
+    }
+    Some((from, to))
+}
+
+/// ISC network boot settings of an interface: `<nextserver>` and
+/// `<filename>` when `<netboot>` is on, plus the TFTP server (`<tftp>`,
+/// option 66) and boot file name (`<bootfilename>`, option 67).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct IscNetbootV4 {
+    pub(crate) next_server: Option<String>,
+    /// Option 67, falling back to the netboot `<filename>`
+    pub(crate) boot_file_name: Option<String>,
+    pub(crate) tftp_server_name: Option<String>,
+}
+
+/// Extract ISC network boot settings per interface, leaving out interfaces
+/// without any; with duplicated sections the first settings found win
+pub(crate) fn extract_isc_netboot_v4(root: &Element) -> HashMap<String, IscNetbootV4> {
+    let mut netboot = HashMap::new();
+
+    for dhcpd in isc_sections(root, "dhcpd") {
+        for iface_elem in dhcpd.children.iter().filter_map(|c| c.as_element()) {
+            let text = |name| {
+                child_text(iface_elem, name)
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+            };
+            let enabled = text("netboot").is_some_and(|v| v != "0");
+            let settings = IscNetbootV4 {
+                next_server: text("nextserver").filter(|_| enabled),
+                boot_file_name: text("bootfilename")
+                    .or_else(|| text("filename").filter(|_| enabled)),
+                tftp_server_name: text("tftp"),
+            };
+            if settings != IscNetbootV4::default() {
+                netboot.entry(iface_elem.name.clone()).or_insert(settings);
             }
         }
     }
 
-    Ok(ranges)
-}
-
-/// Extract ISC DHCPv6 ranges from the XML tree
-pub fn extract_isc_ranges_v6(root: &Element) -> Result<Vec<IscRangeV6>> {
-    let mut ranges = Vec::new();
-
-    if let Some(dhcpdv6) = get_child_ci(root, "dhcpdv6") {
-        for iface_node in dhcpdv6.children.iter() {
-            if let Some(iface_elem) = iface_node.as_element() {
-                let iface_name = iface_elem.name.clone();
-                for child in iface_elem.children.iter() {
-                    if let Some(range) = child.as_element() {
-                        if range.name.eq_ignore_ascii_case("range") {
-                            let from = get_child_ci(range, "from")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-                            let to = get_child_ci(range, "to")
-                                .and_then(|e| e.get_text())
-                                .map(|s| s.to_string())
-                                .unwrap_or_default();
-                            if from.is_empty() || to.is_empty() {
-                                continue;
-                            }
-                            ranges.push(IscRangeV6 {
-                                iface: iface_name.clone(),
-                                from,
-                                to,
-                            });
-                        }
-                    }
-                }
+    netboot
+}
+
+/// Extract ISC router advertisement modes (`<dhcpdv6><iface><ramode>`) per interface;
+/// with duplicated sections the first mode found for an interface wins
+pub fn extract_isc_ramodes_v6(root: &Element) -> HashMap<String, String> {
+    let mut modes = HashMap::new();
+
+    for dhcpdv6 in isc_sections(root, "dhcpdv6") {
+        for iface_elem in dhcpdv6.children.iter().filter_map(|c| c.as_element()) {
+            let ramode = get_child_ci(iface_elem, "ramode")
+                .and_then(|e| e.get_text())
+                .map(|s| s.trim().to_ascii_lowercase())
+                .unwrap_or_default();
+            if !ramode.is_empty() {
+                modes.entry(iface_elem.name.clone()).or_insert(ramode);
             }
         }
     }
 
-    Ok(ranges)
+    modes
 }
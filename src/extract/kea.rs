@@ -2,10 +2,12 @@ use anyhow::Result;
 use std::collections::HashSet;
 use xmltree::Element;
 
+use crate::model::{ReservationNode, ReservationV6Node, Subnet4Node, Subnet6Node};
 use crate::xml_helpers::{find_descendant_ci, get_child_ci};
 use crate::{Subnet, SubnetV6};
 
 /// Check if Kea DHCPv4 is configured (recursive search)
+#[cfg(feature = "kea")]
 pub fn has_kea_dhcp4(root: &Element) -> bool {
     find_descendant_ci(root, "Kea")
         .and_then(|kea| find_descendant_ci(kea, "dhcp4"))
@@ -13,6 +15,7 @@ pub fn has_kea_dhcp4(root: &Element) -> bool {
 }
 
 /// Check if Kea DHCPv6 is configured (recursive search)
+#[cfg(feature = "kea")]
 pub fn has_kea_dhcp6(root: &Element) -> bool {
     find_descendant_ci(root, "Kea")
         .and_then(|kea| find_descendant_ci(kea, "dhcp6"))
@@ -24,156 +27,89 @@ pub fn has_kea_dhcp6(root: &Element) -> bool {
 /// 1. <Kea><dhcp4><subnets><subnet4>... (standard OPNsense)
 /// 2. <Kea><dhcp4><subnet4>... (fallback for plugin variations)
 pub fn extract_kea_subnets(root: &Element) -> Result<Vec<Subnet>> {
-    let mut subnets = Vec::new();
-
-    // Navigate to <Kea>/<kea> (case-insensitive) -> <dhcp4>
-    if let Some(kea) = find_descendant_ci(root, "Kea") {
-        if let Some(dhcp4) = find_descendant_ci(kea, "dhcp4") {
-            // Try standard path: <dhcp4><subnets><subnet4>
-            let subnet_container = if let Some(subnets_node) = get_child_ci(dhcp4, "subnets") {
-                Some(subnets_node)
-            } else {
-                // Fallback: <subnet4> directly under <dhcp4>
-                Some(dhcp4)
-            };
-
-            if let Some(container) = subnet_container {
-                for child in container.children.iter() {
-                    if let Some(subnet4) = child.as_element() {
-                        if subnet4.name.eq_ignore_ascii_case("subnet4") {
-                            if let Some(uuid) = subnet4.attributes.get("uuid") {
-                                if let Some(subnet_elem) = get_child_ci(subnet4, "subnet") {
-                                    if let Some(cidr) = subnet_elem.get_text() {
-                                        let iface = get_child_ci(subnet4, "interface")
-                                            .and_then(|e| e.get_text())
-                                            .map(|s| s.to_string())
-                                            .filter(|s| !s.is_empty());
-                                        subnets.push(Subnet {
-                                            uuid: uuid.to_string(),
-                                            cidr: cidr.to_string(),
-                                            iface,
-                                        });
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(subnets)
+    Ok(kea_subnet4_elements(root)
+        .filter_map(Subnet4Node::from_element)
+        .map(|node| Subnet {
+            iface: Some(node.interface).filter(|s| !s.is_empty()),
+            uuid: node.uuid,
+            cidr: node.subnet,
+        })
+        .collect())
 }
 
 /// Extract Kea subnet6 entries from the XML tree
 /// Supports <Kea><dhcp6><subnets><subnet6>
 pub fn extract_kea_subnets_v6(root: &Element) -> Result<Vec<SubnetV6>> {
-    let mut subnets = Vec::new();
+    Ok(kea_subnet6_elements(root)
+        .filter_map(Subnet6Node::from_element)
+        .map(|node| SubnetV6 {
+            iface: Some(node.interface).filter(|s| !s.is_empty()),
+            uuid: node.uuid,
+            cidr: node.subnet,
+        })
+        .collect())
+}
 
-    if let Some(kea) = find_descendant_ci(root, "Kea") {
-        if let Some(dhcp6) = find_descendant_ci(kea, "dhcp6") {
-            if let Some(subnets_node) = get_child_ci(dhcp6, "subnets") {
-                for child in subnets_node.children.iter() {
-                    if let Some(subnet6) = child.as_element() {
-                        if subnet6.name.eq_ignore_ascii_case("subnet6") {
-                            if let Some(uuid) = subnet6.attributes.get("uuid") {
-                                if let Some(subnet_elem) = get_child_ci(subnet6, "subnet") {
-                                    if let Some(cidr) = subnet_elem.get_text() {
-                                        let iface = get_child_ci(subnet6, "interface")
-                                            .and_then(|e| e.get_text())
-                                            .map(|s| s.to_string())
-                                            .filter(|s| !s.is_empty());
-                                        subnets.push(SubnetV6 {
-                                            uuid: uuid.to_string(),
-                                            cidr: cidr.to_string(),
-                                            iface,
-                                        });
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
+/// The elements under `<dhcp4><subnets>`, or directly under `<dhcp4>`
+/// when there is no `<subnets>`
+pub(crate) fn kea_subnet4_elements(root: &Element) -> impl Iterator<Item = &Element> {
+    find_descendant_ci(root, "Kea")
+        .and_then(|kea| find_descendant_ci(kea, "dhcp4"))
+        .map(|dhcp4| get_child_ci(dhcp4, "subnets").unwrap_or(dhcp4))
+        .into_iter()
+        .flat_map(|container| container.children.iter())
+        .filter_map(|child| child.as_element())
+}
 
-    Ok(subnets)
+/// The elements under `<dhcp6><subnets>`
+pub(crate) fn kea_subnet6_elements(root: &Element) -> impl Iterator<Item = &Element> {
+    find_descendant_ci(root, "Kea")
+        .and_then(|kea| find_descendant_ci(kea, "dhcp6"))
+        .and_then(|dhcp6| get_child_ci(dhcp6, "subnets"))
+        .into_iter()
+        .flat_map(|subnets| subnets.children.iter())
+        .filter_map(|child| child.as_element())
 }
 
 /// Extract existing Kea reservation IP addresses for duplicate detection
 pub fn extract_existing_reservation_ips(root: &Element) -> Result<HashSet<String>> {
-    let mut ips = HashSet::new();
-
-    // Navigate to <Kea>/<kea> (case-insensitive) -> <dhcp4> -> <reservations>
-    if let Some(kea) = find_descendant_ci(root, "Kea") {
-        if let Some(dhcp4) = find_descendant_ci(kea, "dhcp4") {
-            if let Some(reservations) = find_descendant_ci(dhcp4, "reservations") {
-                for child in reservations.children.iter() {
-                    if let Some(reservation) = child.as_element() {
-                        if reservation.name.eq_ignore_ascii_case("reservation") {
-                            if let Some(ip_elem) = get_child_ci(reservation, "ip_address") {
-                                if let Some(ip) = ip_elem.get_text() {
-                                    ips.insert(ip.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(ips)
+    Ok(kea_reservations_v4(root)
+        .map(|r| r.ip_address)
+        .filter(|ip| !ip.is_empty())
+        .collect())
 }
 
 /// Extract existing Kea DHCPv6 reservation IP addresses for duplicate detection
 pub fn extract_existing_reservation_ips_v6(root: &Element) -> Result<HashSet<String>> {
-    let mut ips = HashSet::new();
-
-    if let Some(kea) = find_descendant_ci(root, "Kea") {
-        if let Some(dhcp6) = find_descendant_ci(kea, "dhcp6") {
-            if let Some(reservations) = find_descendant_ci(dhcp6, "reservations") {
-                for child in reservations.children.iter() {
-                    if let Some(reservation) = child.as_element() {
-                        if reservation.name.eq_ignore_ascii_case("reservation") {
-                            if let Some(ip_elem) = get_child_ci(reservation, "ip_address") {
-                                if let Some(ip) = ip_elem.get_text() {
-                                    ips.insert(ip.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(ips)
+    Ok(kea_reservations_v6(root)
+        .map(|r| r.ip_address)
+        .filter(|ip| !ip.is_empty())
+        .collect())
 }
 
 /// Extract existing Kea DHCPv6 reservation DUIDs for duplicate detection
 pub fn extract_existing_reservation_duids_v6(root: &Element) -> Result<HashSet<String>> {
-    let mut duids = HashSet::new();
+    Ok(kea_reservations_v6(root)
+        .map(|r| r.duid)
+        .filter(|duid| !duid.is_empty())
+        .collect())
+}
 
-    if let Some(kea) = find_descendant_ci(root, "Kea") {
-        if let Some(dhcp6) = find_descendant_ci(kea, "dhcp6") {
-            if let Some(reservations) = find_descendant_ci(dhcp6, "reservations") {
-                for child in reservations.children.iter() {
-                    if let Some(reservation) = child.as_element() {
-                        if reservation.name.eq_ignore_ascii_case("reservation") {
-                            if let Some(duid_elem) = get_child_ci(reservation, "duid") {
-                                if let Some(duid) = duid_elem.get_text() {
-                                    duids.insert(duid.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
+/// The `<dhcp4><reservations>` entries
+pub(crate) fn kea_reservations_v4(root: &Element) -> impl Iterator<Item = ReservationNode> + '_ {
+    reservation_elements(root, "dhcp4").filter_map(ReservationNode::from_element)
+}
+
+/// The `<dhcp6><reservations>` entries
+pub(crate) fn kea_reservations_v6(root: &Element) -> impl Iterator<Item = ReservationV6Node> + '_ {
+    reservation_elements(root, "dhcp6").filter_map(ReservationV6Node::from_element)
+}
 
-    Ok(duids)
+fn reservation_elements<'a>(root: &'a Element, dhcp: &str) -> impl Iterator<Item = &'a Element> {
+    find_descendant_ci(root, "Kea")
+        .and_then(|kea| find_descendant_ci(kea, dhcp))
+        .and_then(|dhcp| find_descendant_ci(dhcp, "reservations"))
+        .into_iter()
+        .flat_map(|reservations| reservations.children.iter())
+        .filter_map(|child| child.as_element())
 }
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use crate::IscLease;
+
+/// Parse DHCPv4 leases from an ISC `dhcpd.leases` file.
+///
+/// Only `lease <ip> { ... }` blocks are read; `starts`, `ends` and `cltt`
+/// accept both the default `<weekday> YYYY/MM/DD HH:MM:SS` (UTC) format and
+/// `epoch <seconds>`. Later blocks for the same IP appear later in the file
+/// and are kept as separate entries.
+pub fn parse_isc_leases(text: &str) -> Vec<IscLease> {
+    let mut leases = Vec::new();
+    let mut current: Option<IscLease> = None;
+
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("lease ") {
+            let ip = rest.trim_end_matches('{').trim();
+            current = Some(IscLease {
+                ip: ip.to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        if line == "}" {
+            if let Some(lease) = current.take() {
+                leases.push(lease);
+            }
+            continue;
+        }
+        let Some(lease) = current.as_mut() else {
+            continue;
+        };
+        let statement = line.trim_end_matches(';').trim();
+        let mut words = statement.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("starts"), Some(_)) => lease.starts = parse_lease_time(statement),
+            (Some("ends"), Some(_)) => lease.ends = parse_lease_time(statement),
+            (Some("cltt"), Some(_)) => lease.cltt = parse_lease_time(statement),
+            (Some("binding"), Some("state")) => {
+                lease.binding_state = words.next().map(str::to_string);
+            }
+            (Some("hardware"), Some(_)) => {
+                lease.mac = words.next().map(str::to_ascii_lowercase);
+            }
+            (Some("client-hostname"), Some(name)) => {
+                lease.hostname = Some(name.trim_matches('"').to_string());
+            }
+            _ => {}
+        }
+    }
+
+    leases
+}
+
+/// Parse DHCPv4 leases from a Kea `kea-leases4.csv` file.
+///
+/// Kea appends a line each time a lease changes, so only the last line for
+/// each address is kept. Columns are found by the header; `state` 0 is
+/// read as an `active` binding, 1 as `declined` and 2 as `expired`.
+pub fn parse_kea_leases(text: &str) -> Vec<IscLease> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| columns.iter().position(|c| *c == name);
+    let (Some(address), hwaddr, lifetime, expire, hostname, state) = (
+        column("address"),
+        column("hwaddr"),
+        column("valid_lifetime"),
+        column("expire"),
+        column("hostname"),
+        column("state"),
+    ) else {
+        return Vec::new();
+    };
+
+    let mut leases: Vec<IscLease> = Vec::new();
+    let mut by_ip: HashMap<String, usize> = HashMap::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |idx: Option<usize>| {
+            idx.and_then(|idx| fields.get(idx))
+                .filter(|value| !value.is_empty())
+                // Kea escapes commas inside values
+                .map(|value| value.replace("&#x2c", ","))
+        };
+        let Some(ip) = field(Some(address)) else {
+            continue;
+        };
+        let ends = field(expire).and_then(|e| e.parse::<u64>().ok());
+        let starts = ends.zip(field(lifetime).and_then(|l| l.parse::<u64>().ok()));
+        let starts = starts.map(|(ends, lifetime)| ends.saturating_sub(lifetime));
+        let binding_state = match field(state).as_deref().unwrap_or("0") {
+            "0" => "active",
+            "1" => "declined",
+            _ => "expired",
+        };
+        let lease = IscLease {
+            ip: ip.clone(),
+            mac: field(hwaddr).map(|mac| mac.to_ascii_lowercase()),
+            starts,
+            ends,
+            cltt: starts,
+            binding_state: Some(binding_state.to_string()),
+            hostname: field(hostname),
+        };
+        match by_ip.get(&ip) {
+            Some(&idx) => leases[idx] = lease,
+            None => {
+                by_ip.insert(ip, leases.len());
+                leases.push(lease);
+            }
+        }
+    }
+    leases
+}
+
+/// Parse a Kea lease CSV, recognised by its `address,` header, or else an
+/// ISC `dhcpd.leases` file.
+pub fn parse_leases(text: &str) -> Vec<IscLease> {
+    let first = text.lines().map(str::trim).find(|line| !line.is_empty());
+    if first.is_some_and(|line| line.starts_with("address,")) {
+        parse_kea_leases(text)
+    } else {
+        parse_isc_leases(text)
+    }
+}
+
+/// Seconds since the Unix epoch from a `starts`/`ends`/`cltt` statement;
+/// `never` and unreadable values give `None`.
+fn parse_lease_time(statement: &str) -> Option<u64> {
+    let mut words = statement.split_whitespace().skip(1);
+    let first = words.next()?;
+    if first == "epoch" {
+        return words.next()?.parse().ok();
+    }
+    let date = words.next()?;
+    let time = words.next()?;
+
+    let mut date_parts = date.split('/').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+    let mut time_parts = time.split(':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (
+        time_parts.next()??,
+        time_parts.next()??,
+        time_parts.next()??,
+    );
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(secs).ok()
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_isc_leases() {
+        let leases = parse_isc_leases(
+            r#"# The format of this file is documented in the dhcpd.leases(5) manual page.
+lease 192.168.1.10 {
+  starts 4 2026/01/01 10:00:00;
+  ends 4 2026/01/01 12:00:00;
+  cltt epoch 1767261600; # Thu Jan 01 10:00:00 2026
+  binding state active;
+  hardware ethernet 00:11:22:AA:BB:CC;
+  client-hostname "laptop";
+}
+lease 192.168.1.11 {
+  ends never;
+}
+"#,
+        );
+        assert_eq!(leases.len(), 2);
+        assert_eq!(leases[0].ip, "192.168.1.10");
+        assert_eq!(leases[0].starts, Some(1_767_261_600));
+        assert_eq!(leases[0].cltt, Some(1_767_261_600));
+        assert_eq!(leases[0].ends, Some(1_767_268_800));
+        assert_eq!(leases[0].binding_state.as_deref(), Some("active"));
+        assert_eq!(leases[0].mac.as_deref(), Some("00:11:22:aa:bb:cc"));
+        assert_eq!(leases[0].hostname.as_deref(), Some("laptop"));
+        assert_eq!(leases[1].ends, None);
+    }
+
+    #[test]
+    fn test_parse_kea_leases() {
+        let text = "address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state,user_context,pool_id
+192.168.1.10,00:11:22:AA:BB:CC,,3600,1767268800,1,0,0,laptop&#x2c1,0,,0
+192.168.1.11,00:11:22:aa:bb:dd,,3600,1767268800,1,0,0,,0,,0
+192.168.1.11,00:11:22:aa:bb:dd,,0,1767265200,1,0,0,,2,,0
+";
+        let leases = parse_leases(text);
+        assert_eq!(leases.len(), 2);
+        assert_eq!(leases[0].ip, "192.168.1.10");
+        assert_eq!(leases[0].mac.as_deref(), Some("00:11:22:aa:bb:cc"));
+        assert_eq!(leases[0].starts, Some(1_767_265_200));
+        assert_eq!(leases[0].ends, Some(1_767_268_800));
+        assert_eq!(leases[0].binding_state.as_deref(), Some("active"));
+        assert_eq!(leases[0].hostname.as_deref(), Some("laptop,1"));
+        // The release line replaces the earlier lease
+        assert_eq!(leases[1].binding_state.as_deref(), Some("expired"));
+        assert_eq!(parse_leases("lease 10.0.0.1 {\n}\n").len(), 1);
+    }
+}
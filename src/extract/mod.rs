@@ -1,14 +1,30 @@
 mod interfaces;
 mod isc;
 mod kea;
+mod leases;
+mod static_leases;
 
-pub use interfaces::{extract_interface_cidrs, extract_interface_cidrs_v6};
+pub use interfaces::{
+    extract_interface_cidrs, extract_interface_cidrs_v6, extract_interface_labels, iface_display,
+    iface_with_descr,
+};
 pub use isc::{
     extract_isc_mappings, extract_isc_mappings_v6, extract_isc_options_v4, extract_isc_options_v6,
-    extract_isc_ranges, extract_isc_ranges_v6,
+    extract_isc_ramodes_v6, iter_isc_mappings, iter_isc_mappings_v6, iter_isc_number_options_v4,
+    iter_isc_options_v4, iter_isc_options_v6, iter_isc_placeholder_mappings, iter_isc_ranges,
+    iter_isc_ranges_v6,
+};
+pub(crate) use isc::{
+    extract_isc_netboot_v4, extract_legacy_isc_mappings, extract_legacy_isc_mappings_v6,
+    legacy_sections, IscNetbootV4,
 };
 pub use kea::{
     extract_existing_reservation_duids_v6, extract_existing_reservation_ips,
     extract_existing_reservation_ips_v6, extract_kea_subnets, extract_kea_subnets_v6,
-    has_kea_dhcp4, has_kea_dhcp6,
 };
+#[cfg(feature = "kea")]
+pub(crate) use kea::{has_kea_dhcp4, has_kea_dhcp6, kea_reservations_v4, kea_reservations_v6};
+#[cfg(all(feature = "cli", feature = "kea"))]
+pub(crate) use kea::{kea_subnet4_elements, kea_subnet6_elements};
+pub use leases::{parse_isc_leases, parse_kea_leases, parse_leases};
+pub(crate) use static_leases::{extract_static_leases, has_static_leases};
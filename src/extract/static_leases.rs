@@ -0,0 +1,87 @@
+//! Static leases kept by the legacy `dhcp-static-leases` plugin.
+//!
+//! The plugin stored DHCPv4 static leases in its own model instead of the
+//! ISC sections:
+//!
+//! ```xml
+//! <OPNsense><dhcpleases><leases>
+//!   <lease uuid="…">
+//!     <enabled>1</enabled>
+//!     <interface>lan</interface>
+//!     <mac>00:11:22:33:44:55</mac>
+//!     <ipaddr>192.168.1.10</ipaddr>
+//!     <hostname>printer</hostname>
+//!     <descr>Office printer</descr>
+//!   </lease>
+//! </leases></dhcpleases></OPNsense>
+//! ```
+//!
+//! Leases without an interface are placed on the interface whose subnet
+//! holds their address.
+
+use xmltree::Element;
+
+use crate::extract::extract_interface_cidrs;
+use crate::subnet::iface_for_ip;
+use crate::term;
+use crate::xml_helpers::{child_text, find_descendant_ci, get_child_ci, get_children_ci};
+use crate::IscStaticMap;
+
+fn lease_elements(root: &Element) -> impl Iterator<Item = &Element> {
+    find_descendant_ci(root, "dhcpleases")
+        .and_then(|plugin| get_child_ci(plugin, "leases"))
+        .into_iter()
+        .flat_map(|leases| get_children_ci(leases, "lease"))
+}
+
+/// Whether the config holds any plugin static lease.
+pub(crate) fn has_static_leases(root: &Element) -> bool {
+    lease_elements(root).next().is_some()
+}
+
+/// The plugin's enabled static leases as ISC static mappings. Leases
+/// without a MAC or IP, or whose interface cannot be found, are skipped.
+pub(crate) fn extract_static_leases(root: &Element) -> Vec<IscStaticMap> {
+    let cidrs = extract_interface_cidrs(root).unwrap_or_default();
+    lease_elements(root)
+        .filter(|lease| lease_text(lease, "enabled").is_none_or(|e| e != "0"))
+        .filter_map(|lease| {
+            let mac = lease_text(lease, "mac")?;
+            let ipaddr = lease_text(lease, "ipaddr")?;
+            let iface = match lease_text(lease, "interface") {
+                Some(iface) => iface,
+                None => match iface_for_ip(&ipaddr, &cidrs) {
+                    Ok(iface) => iface,
+                    Err(_) => {
+                        term::warning(format_args!(
+                            "Static lease {} ({}) has no interface and is outside every interface \
+                             subnet. Skipping.",
+                            ipaddr, mac
+                        ));
+                        return None;
+                    }
+                },
+            };
+            Some(IscStaticMap {
+                iface,
+                mac,
+                ipaddr,
+                hostname: lease_text(lease, "hostname"),
+                cid: None,
+                descr: lease_text(lease, "descr"),
+                domain: None,
+                domain_search: None,
+                dns_servers: Vec::new(),
+                gateway: None,
+                gateway_suppressed: false,
+            })
+        })
+        .collect()
+}
+
+/// Trimmed text of a lease field; `None` when missing or blank.
+fn lease_text(lease: &Element, name: &str) -> Option<String> {
+    child_text(lease, name)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
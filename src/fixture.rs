@@ -0,0 +1,308 @@
+//! Synthetic OPNsense config.xml files for tests, benchmarks and bug
+//! reproduction.
+//!
+//! [`generate_fixture`] writes interfaces with ISC DHCP ranges and static
+//! mappings, a Kea subnet per interface and, optionally, mappings that are
+//! already migrated to Kea reservations and dnsmasq hosts. Every value comes
+//! from a small PRNG seeded with [`FixtureSpec::seed`], so the same spec
+//! always gives the same file.
+
+use anyhow::{bail, Result};
+use std::net::Ipv4Addr;
+
+/// Shape of a generated config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureSpec {
+    /// Interfaces with ISC DHCP enabled: `lan`, then `opt1`, `opt2`, ...
+    pub interfaces: usize,
+    /// Static mappings on each interface
+    pub maps_per_iface: usize,
+    /// Mappings on each interface that also exist as Kea reservations and
+    /// dnsmasq hosts, as after an earlier partial migration
+    pub existing_per_iface: usize,
+    pub seed: u64,
+}
+
+impl Default for FixtureSpec {
+    fn default() -> Self {
+        FixtureSpec {
+            interfaces: 2,
+            maps_per_iface: 10,
+            existing_per_iface: 0,
+            seed: 0,
+        }
+    }
+}
+
+/// Device kinds hostnames are built from.
+const DEVICES: &[&str] = &[
+    "laptop", "desktop", "printer", "nas", "camera", "phone", "tv", "ap", "switch", "sensor",
+];
+
+/// First static mapping address, from the start of each interface subnet.
+const FIRST_HOST: u32 = 10;
+/// Addresses kept clear of static mappings above the range.
+const RANGE_MARGIN: u32 = 20;
+
+/// A config.xml for `spec`.
+pub fn generate_fixture(spec: &FixtureSpec) -> Result<String> {
+    if spec.existing_per_iface > spec.maps_per_iface {
+        bail!(
+            "Existing entries per interface ({}) exceed mappings per interface ({})",
+            spec.existing_per_iface,
+            spec.maps_per_iface
+        );
+    }
+    // Each interface gets a subnet of 10.0.0.0/8 with room for its static
+    // mappings in the lower half and the dynamic range in the upper half
+    let block = (spec.maps_per_iface as u64)
+        .checked_add(u64::from(FIRST_HOST + RANGE_MARGIN))
+        .and_then(|hosts| hosts.checked_mul(2))
+        .and_then(u64::checked_next_power_of_two)
+        .map(|block| block.max(256))
+        .filter(|&block| {
+            block <= 1 << 24
+                && (spec.interfaces as u64)
+                    .checked_mul(block)
+                    .is_some_and(|total| total <= 1 << 24)
+        });
+    let Some(block) = block else {
+        bail!(
+            "{} interfaces with {} mappings each do not fit in 10.0.0.0/8",
+            spec.interfaces,
+            spec.maps_per_iface
+        );
+    };
+    // At most 2^24, so every address below fits in a u32
+    let block = block as u32;
+    let prefix = 32 - block.trailing_zeros();
+    let mut rng = Rng::new(spec.seed);
+
+    let ifaces: Vec<Iface> = (0..spec.interfaces)
+        .map(|i| {
+            let base = u32::from(Ipv4Addr::new(10, 0, 0, 0)) + i as u32 * block;
+            let maps = (0..spec.maps_per_iface)
+                .map(|j| Mapping {
+                    mac: rng.mac(),
+                    ip: Ipv4Addr::from(base + FIRST_HOST + j as u32),
+                    hostname: format!("{}-{}-{}", DEVICES[rng.below(DEVICES.len())], i, j),
+                    descr: rng.below(4) != 0,
+                })
+                .collect();
+            Iface {
+                name: if i == 0 {
+                    "lan".to_string()
+                } else {
+                    format!("opt{}", i)
+                },
+                base,
+                subnet_uuid: rng.uuid(),
+                maps,
+            }
+        })
+        .collect();
+
+    let mut xml = String::new();
+    let mut line = |indent: usize, text: &str| {
+        xml.push_str(&"    ".repeat(indent));
+        xml.push_str(text);
+        xml.push('\n');
+    };
+    line(0, r#"<?xml version="1.0"?>"#);
+    line(0, "<opnsense>");
+    line(1, "<version>24.7</version>");
+
+    line(1, "<interfaces>");
+    for (i, iface) in ifaces.iter().enumerate() {
+        line(2, &format!("<{}>", iface.name));
+        line(3, "<enable>1</enable>");
+        line(3, &format!("<if>igb{}</if>", i));
+        line(3, &format!("<descr>{}</descr>", iface.name.to_uppercase()));
+        line(3, &format!("<ipaddr>{}</ipaddr>", iface.addr(1)));
+        line(3, &format!("<subnet>{}</subnet>", prefix));
+        line(2, &format!("</{}>", iface.name));
+    }
+    line(1, "</interfaces>");
+
+    line(1, "<dhcpd>");
+    for iface in &ifaces {
+        line(2, &format!("<{}>", iface.name));
+        line(3, "<enable>1</enable>");
+        line(3, "<range>");
+        line(4, &format!("<from>{}</from>", iface.addr(block / 2)));
+        line(4, &format!("<to>{}</to>", iface.addr(block - 2)));
+        line(3, "</range>");
+        for map in &iface.maps {
+            line(3, "<staticmap>");
+            line(4, &format!("<mac>{}</mac>", map.mac));
+            line(4, &format!("<ipaddr>{}</ipaddr>", map.ip));
+            line(4, &format!("<hostname>{}</hostname>", map.hostname));
+            if map.descr {
+                line(4, &format!("<descr>Generated {}</descr>", map.hostname));
+            }
+            line(3, "</staticmap>");
+        }
+        line(2, &format!("</{}>", iface.name));
+    }
+    line(1, "</dhcpd>");
+
+    line(1, "<OPNsense>");
+    line(2, "<Kea>");
+    line(3, "<dhcp4>");
+    line(4, "<general>");
+    line(5, "<enabled>0</enabled>");
+    line(5, "<interfaces/>");
+    line(4, "</general>");
+    line(4, "<subnets>");
+    for iface in &ifaces {
+        line(5, &format!(r#"<subnet4 uuid="{}">"#, iface.subnet_uuid));
+        line(6, &format!("<subnet>{}/{}</subnet>", iface.addr(0), prefix));
+        line(
+            6,
+            &format!(
+                "<pools>{}-{}</pools>",
+                iface.addr(block / 2),
+                iface.addr(block - 2)
+            ),
+        );
+        line(6, &format!("<description>{}</description>", iface.name));
+        line(5, "</subnet4>");
+    }
+    line(4, "</subnets>");
+    line(4, "<reservations>");
+    for iface in &ifaces {
+        for map in iface.maps.iter().take(spec.existing_per_iface) {
+            line(5, &format!(r#"<reservation uuid="{}">"#, rng.uuid()));
+            line(6, &format!("<subnet>{}</subnet>", iface.subnet_uuid));
+            line(6, &format!("<ip_address>{}</ip_address>", map.ip));
+            line(6, &format!("<hw_address>{}</hw_address>", map.mac));
+            line(6, &format!("<hostname>{}</hostname>", map.hostname));
+            line(5, "</reservation>");
+        }
+    }
+    line(4, "</reservations>");
+    line(3, "</dhcp4>");
+    line(2, "</Kea>");
+    line(1, "</OPNsense>");
+
+    line(1, "<dnsmasq>");
+    line(2, "<enable>0</enable>");
+    let existing = ifaces
+        .iter()
+        .flat_map(|iface| iface.maps.iter().take(spec.existing_per_iface));
+    for map in existing {
+        line(2, &format!(r#"<hosts uuid="{}">"#, rng.uuid()));
+        line(3, &format!("<host>{}</host>", map.hostname));
+        line(3, &format!("<ip>{}</ip>", map.ip));
+        line(3, &format!("<hwaddr>{}</hwaddr>", map.mac));
+        line(2, "</hosts>");
+    }
+    line(1, "</dnsmasq>");
+    line(0, "</opnsense>");
+    Ok(xml)
+}
+
+struct Iface {
+    name: String,
+    base: u32,
+    subnet_uuid: String,
+    maps: Vec<Mapping>,
+}
+
+impl Iface {
+    fn addr(&self, offset: u32) -> Ipv4Addr {
+        Ipv4Addr::from(self.base + offset)
+    }
+}
+
+struct Mapping {
+    mac: String,
+    ip: Ipv4Addr,
+    hostname: String,
+    descr: bool,
+}
+
+/// SplitMix64, enough for varied but reproducible values.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A locally administered unicast MAC.
+    fn mac(&mut self) -> String {
+        let mut octets = self.next_u64().to_le_bytes();
+        octets[0] = (octets[0] & 0xfc) | 0x02;
+        let octets: Vec<String> = octets[..6].iter().map(|o| format!("{:02x}", o)).collect();
+        octets.join(":")
+    }
+
+    fn uuid(&mut self) -> String {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.next_u64().to_le_bytes());
+        bytes[8..].copy_from_slice(&self.next_u64().to_le_bytes());
+        uuid::Builder::from_random_bytes(bytes)
+            .into_uuid()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_fixture_is_reproducible() {
+        let spec = FixtureSpec {
+            interfaces: 3,
+            maps_per_iface: 200,
+            existing_per_iface: 5,
+            seed: 42,
+        };
+        let xml = generate_fixture(&spec).unwrap();
+        assert_eq!(xml, generate_fixture(&spec).unwrap());
+        assert_ne!(
+            xml,
+            generate_fixture(&FixtureSpec { seed: 43, ..spec }).unwrap()
+        );
+        assert_eq!(xml.matches("<staticmap>").count(), 600);
+        assert_eq!(xml.matches("<reservation uuid").count(), 15);
+        // 200 mappings need more than a /24
+        assert!(xml.contains("<subnet>10.0.2.0/23</subnet>"));
+    }
+
+    #[test]
+    fn test_generate_fixture_rejects_bad_specs() {
+        let too_many = FixtureSpec {
+            existing_per_iface: 11,
+            ..Default::default()
+        };
+        assert!(generate_fixture(&too_many).is_err());
+        let too_large = FixtureSpec {
+            interfaces: 70_000,
+            ..Default::default()
+        };
+        assert!(generate_fixture(&too_large).is_err());
+        for maps_per_iface in [3_000_000_000, usize::MAX] {
+            let too_many_maps = FixtureSpec {
+                maps_per_iface,
+                ..Default::default()
+            };
+            let err = generate_fixture(&too_many_maps).unwrap_err();
+            assert!(err.to_string().contains("do not fit in 10.0.0.0/8"));
+        }
+    }
+}
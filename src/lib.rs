@@ -1,28 +1,82 @@
+// Without a built-in backend most of the migration plumbing has no caller
+#![cfg_attr(
+    not(any(feature = "kea", feature = "dnsmasq")),
+    allow(dead_code, unused_imports)
+)]
+
 pub mod backend;
+#[cfg(feature = "cli")]
 pub mod cli;
+mod clock;
 mod errors;
 mod extract;
+#[cfg(feature = "dnsmasq")]
 mod extract_dnsmasq;
+mod fixture;
 mod migrate;
+#[cfg(feature = "dnsmasq")]
 mod migrate_dnsmasq;
+mod migrate_unbound;
+#[cfg(feature = "kea")]
 mod migrate_v4;
+#[cfg(feature = "kea")]
 mod migrate_v6;
-mod subnet;
+mod model;
+mod overrides;
+mod parse;
+mod plan;
+#[cfg(feature = "backend-api")]
+pub mod registry;
+#[cfg(not(feature = "backend-api"))]
+mod registry;
+mod source;
+pub mod subnet;
+mod term;
 mod types;
+mod warnings;
+pub mod workspace;
 mod xml_helpers;
 
 pub use backend::Backend;
-pub use errors::MigrationError;
+pub use clock::Clock;
+pub use errors::{
+    DnsmasqHostIssue, DuplicateUuid, InvalidIscRange, MigrationError, OrphanedMapping,
+    RangeOverlap, SchemaViolation, UnmatchedMapping,
+};
 pub use extract::{
     extract_existing_reservation_duids_v6, extract_existing_reservation_ips,
-    extract_existing_reservation_ips_v6, extract_isc_mappings, extract_isc_mappings_v6,
-    extract_isc_options_v4, extract_isc_options_v6, extract_kea_subnets, extract_kea_subnets_v6,
+    extract_existing_reservation_ips_v6, extract_interface_labels, extract_isc_mappings,
+    extract_isc_mappings_v6, extract_isc_options_v4, extract_isc_options_v6, extract_kea_subnets,
+    extract_kea_subnets_v6, iface_display, iter_isc_mappings, iter_isc_mappings_v6,
+    iter_isc_number_options_v4, iter_isc_options_v4, iter_isc_options_v6,
+    iter_isc_placeholder_mappings, iter_isc_ranges, iter_isc_ranges_v6, parse_isc_leases,
+    parse_kea_leases, parse_leases,
+};
+pub use fixture::{generate_fixture, FixtureSpec};
+pub use migrate::{
+    audit_config, convert_config, export_config, export_config_split, merge_config, plan_config,
+    scan_config, scan_counts, validate_input, validate_output, verify_findings, ExportFile,
+    ParsedConfig, CREATED_ATTR, ORIGIN_ATTR,
 };
-pub use migrate::{convert_config, scan_config, scan_counts};
+#[cfg(feature = "backend-api")]
+pub use migrate::{convert_config_with_backend, scan_config_with_backend};
+pub use overrides::{FieldOverrides, FieldRule, MappingField};
+pub use plan::{MigrationPlan, PlanEntry};
+#[cfg(feature = "backend-api")]
+pub use registry::{BackendRegistry, MigrationBackend};
 pub use subnet::{
-    find_subnet_for_ip, find_subnet_for_ip_v6, ip_in_subnet, ip_in_subnet_v6, prefix_to_netmask,
+    clamp_range, clamp_range_v6, covering_cidr, covering_cidr_v6, find_subnet_for_ip,
+    find_subnet_for_ip_v6, ip_in_subnet, ip_in_subnet_v6, netmask_to_prefix, netmask_to_prefix_v6,
+    prefix_to_netmask, prefix_to_netmask_v6, range_in_subnet, range_in_subnet_v6, range_to_cidrs,
+    range_to_cidrs_v6, ranges_overlap, ranges_overlap_v6, usable_range, usable_range_v6,
 };
 pub use types::{
-    IscDhcpOptionsV4, IscDhcpOptionsV6, IscRangeV4, IscRangeV6, IscStaticMap, IscStaticMapV6,
-    KeaSubnet, KeaSubnetV6, MigrationOptions, MigrationStats, Subnet, SubnetV6,
+    AttributeOrder, ColorChoice, ConfigLimits, DnsmasqOptionConflict, DnsmasqV6Mode, EmitMode,
+    ExportFormat, FindingCategory, FindingChange, FirewallRulesStatus, HaSync, HostnameCollision,
+    HostnamePolicy, InspectFormat, IscDhcpOptionsV4, IscDhcpOptionsV6, IscLease, IscNumberOption,
+    IscRangeV4, IscRangeV6, IscStaticMap, IscStaticMapV6, KeaInterfacePolicy, KeaSubnet,
+    KeaSubnetV6, LeaseActivity, LeaseAudit, LeaseMismatch, LeaseStatus, MappingBatch,
+    MigrationOptions, MigrationStats, MigrationTiming, OptionsMerge, OrphanedIfacePolicy,
+    OutputStyle, RangeOverlapPolicy, ReservationOrder, Severity, Source, Subnet, SubnetV6,
+    SuppressedWarnings, UnresolvedOptionValue, VerifyFinding, VerifyFormat,
 };
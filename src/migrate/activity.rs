@@ -0,0 +1,100 @@
+//! Static mapping activity from ISC leases (`--leases`, `--skip-stale-days`).
+
+use crate::term;
+use crate::{IscLease, IscStaticMap, LeaseActivity, LeaseStatus, MigrationOptions};
+
+/// Days without a lease after which a mapping is reported stale when
+/// `skip_stale_days` is not set.
+const DEFAULT_STALE_DAYS: u64 = 180;
+
+/// Report lease activity for `mappings`, least active first, and drop the
+/// stale ones when `skip_stale_days` is set. Returns the report and the
+/// number of mappings dropped.
+///
+/// A mapping matches leases with its MAC or IP. Mappings never seen in the
+/// leases are kept: ISC does not write leases for fixed addresses, so a
+/// device that only ever used its reservation has none.
+pub(crate) fn apply_lease_activity(
+    mappings: &mut Vec<IscStaticMap>,
+    options: &MigrationOptions,
+) -> (Vec<LeaseActivity>, usize) {
+    if options.leases.is_empty() {
+        return (Vec::new(), 0);
+    }
+    let now = options.clock.now();
+    let stale_days = options.skip_stale_days.unwrap_or(DEFAULT_STALE_DAYS);
+    let stale_before = now.saturating_sub(stale_days * 86_400);
+
+    let mut report: Vec<LeaseActivity> = mappings
+        .iter()
+        .map(|mapping| activity(mapping, &options.leases, now, stale_before))
+        .collect();
+
+    let mut dropped = 0;
+    if options.skip_stale_days.is_some() {
+        let total = mappings.len();
+        let mut statuses = report.iter().map(|a| a.status);
+        mappings.retain(|mapping| {
+            let stale = statuses.next() == Some(LeaseStatus::Stale);
+            if stale && options.verbose {
+                term::action(
+                    "STALE",
+                    format_args!(
+                        "{} ({}) - no lease in {} days",
+                        mapping.ipaddr, mapping.mac, stale_days
+                    ),
+                );
+            }
+            !stale
+        });
+        dropped = total - mappings.len();
+        if dropped > 0 {
+            term::warning(format_args!(
+                "Skipping {} static mapping(s) with no lease in {} days (--skip-stale-days).",
+                dropped, stale_days
+            ));
+        }
+    }
+
+    report.sort_by(|a, b| a.status.cmp(&b.status).then(a.last_seen.cmp(&b.last_seen)));
+    (report, dropped)
+}
+
+fn activity(
+    mapping: &IscStaticMap,
+    leases: &[IscLease],
+    now: u64,
+    stale_before: u64,
+) -> LeaseActivity {
+    let mac = mapping.mac.to_ascii_lowercase();
+    let matching: Vec<&IscLease> = leases
+        .iter()
+        .filter(|lease| lease.mac.as_deref() == Some(mac.as_str()) || lease.ip == mapping.ipaddr)
+        .collect();
+
+    let last_seen = matching
+        .iter()
+        .filter_map(|lease| lease.starts.max(lease.cltt))
+        .max();
+    let online = matching.iter().any(|lease| {
+        lease.binding_state.as_deref() == Some("active") && lease.ends.is_none_or(|end| end > now)
+    });
+    let status = if online {
+        LeaseStatus::Online
+    } else {
+        match last_seen {
+            None if matching.is_empty() => LeaseStatus::NeverSeen,
+            Some(seen) if seen >= stale_before => LeaseStatus::Recent,
+            _ => LeaseStatus::Stale,
+        }
+    };
+
+    LeaseActivity {
+        iface: mapping.iface.clone(),
+        mac: mapping.mac.clone(),
+        ipaddr: mapping.ipaddr.clone(),
+        hostname: mapping.hostname.clone(),
+        status,
+        last_seen,
+    }
+}
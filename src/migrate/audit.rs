@@ -0,0 +1,195 @@
+//! Active leases against the reservations of a converted config.
+//!
+//! A static map that no longer matches its device shows up once the new
+//! backend is live: the device keeps leasing a pool address while the
+//! reservation sits unused. Comparing the current leases with the migrated
+//! reservations by hardware address finds these before cutover.
+
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "dnsmasq")]
+use std::net::Ipv4Addr;
+use xmltree::Element;
+
+use crate::backend::Backend;
+#[cfg(feature = "kea")]
+use crate::extract::kea_reservations_v4;
+#[cfg(feature = "dnsmasq")]
+use crate::extract_dnsmasq::dnsmasq_children;
+#[cfg(feature = "dnsmasq")]
+use crate::model::DnsmasqHostNode;
+use crate::{IscLease, LeaseAudit, LeaseMismatch};
+
+/// A DHCPv4 reservation with a hardware address.
+struct Reservation {
+    mac: String,
+    ip: String,
+    hostname: Option<String>,
+}
+
+/// Compare the leases active at `now` with the DHCPv4 reservations
+/// `backend` has in `root`.
+///
+/// A reservation is matched when its hardware address leases an address
+/// reserved for it, and mismatched when it only leases others. Reservations
+/// of both backends for the same address and hardware address count once.
+pub(crate) fn audit_leases(
+    root: &Element,
+    backend: &Backend,
+    leases: &[IscLease],
+    now: u64,
+) -> LeaseAudit {
+    let reservations = reservations(root, backend);
+    let mut reserved: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for r in &reservations {
+        reserved.entry(&r.mac).or_default().insert(&r.ip);
+    }
+
+    let active: Vec<&IscLease> = leases
+        .iter()
+        .filter(|lease| {
+            lease.binding_state.as_deref() == Some("active")
+                && lease.ends.is_none_or(|end| end > now)
+        })
+        .collect();
+    let mut by_mac: HashMap<String, Vec<&IscLease>> = HashMap::new();
+    for lease in &active {
+        if let Some(mac) = lease.mac.as_deref() {
+            by_mac.entry(mac_key(mac)).or_default().push(lease);
+        }
+    }
+
+    let mut audit = LeaseAudit {
+        reservations: reservations.len(),
+        active_leases: active.len(),
+        ..Default::default()
+    };
+    for r in &reservations {
+        let Some(leased) = by_mac.get(&r.mac) else {
+            audit.not_leasing += 1;
+            continue;
+        };
+        let own = &reserved[r.mac.as_str()];
+        if leased.iter().any(|lease| own.contains(lease.ip.as_str())) {
+            audit.matched += 1;
+            continue;
+        }
+        // The lease that lasts longest is the address the device now uses
+        let Some(lease) = leased
+            .iter()
+            .max_by_key(|lease| lease.ends.unwrap_or(u64::MAX))
+        else {
+            continue;
+        };
+        audit.mismatched.push(LeaseMismatch {
+            mac: r.mac.clone(),
+            hostname: r.hostname.clone().or_else(|| lease.hostname.clone()),
+            reserved_ip: r.ip.clone(),
+            leased_ip: lease.ip.clone(),
+            lease_ends: lease.ends,
+        });
+    }
+    audit
+}
+
+/// Lowercase `:`-separated hardware address.
+fn mac_key(mac: &str) -> String {
+    mac.trim().to_ascii_lowercase().replace('-', ":")
+}
+
+#[cfg_attr(
+    not(any(feature = "kea", feature = "dnsmasq")),
+    allow(unused_variables, unused_mut)
+)]
+fn reservations(root: &Element, backend: &Backend) -> Vec<Reservation> {
+    let mut found: Vec<Reservation> = Vec::new();
+    #[cfg(feature = "kea")]
+    if matches!(backend, Backend::Kea | Backend::Both) {
+        found.extend(kea_reservations_v4(root).map(|r| Reservation {
+            mac: mac_key(&r.hw_address),
+            ip: r.ip_address,
+            hostname: r.hostname,
+        }));
+    }
+    #[cfg(feature = "dnsmasq")]
+    if matches!(backend, Backend::Dnsmasq | Backend::Both) {
+        for host in dnsmasq_children(root).filter_map(DnsmasqHostNode::from_element) {
+            // One host may carry an IPv4 and an IPv6 address
+            let ip = host
+                .ip
+                .split(',')
+                .map(str::trim)
+                .find(|ip| ip.parse::<Ipv4Addr>().is_ok());
+            if let Some(ip) = ip {
+                found.push(Reservation {
+                    mac: mac_key(&host.hwaddr),
+                    ip: ip.to_string(),
+                    hostname: Some(host.host).filter(|h| !h.is_empty()),
+                });
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    found.retain(|r| {
+        !r.mac.is_empty() && !r.ip.is_empty() && seen.insert((r.mac.clone(), r.ip.clone()))
+    });
+    found
+}
+
+#[cfg(all(test, feature = "kea"))]
+mod tests {
+    use super::*;
+
+    fn lease(ip: &str, mac: &str, state: &str, ends: u64) -> IscLease {
+        IscLease {
+            ip: ip.to_string(),
+            mac: Some(mac.to_string()),
+            ends: Some(ends),
+            binding_state: Some(state.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_audit_leases() {
+        let root = Element::parse(
+            r#"<opnsense><Kea><dhcp4><reservations>
+                <reservation uuid="r1"><ip_address>10.0.0.10</ip_address><hw_address>00-11-22-33-44-01</hw_address><hostname>printer</hostname></reservation>
+                <reservation uuid="r2"><ip_address>10.0.0.11</ip_address><hw_address>00:11:22:33:44:02</hw_address></reservation>
+                <reservation uuid="r3"><ip_address>10.0.0.12</ip_address><hw_address>00:11:22:33:44:03</hw_address></reservation>
+                <reservation uuid="r4"><ip_address>10.0.0.13</ip_address><hw_address>00:11:22:33:44:04</hw_address></reservation>
+                <reservation uuid="r5"><ip_address>10.0.0.14</ip_address><hw_address>00:11:22:33:44:04</hw_address></reservation>
+            </reservations></dhcp4></Kea></opnsense>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let leases = [
+            lease("10.0.0.150", "00:11:22:33:44:01", "active", 2000),
+            lease("10.0.0.11", "00:11:22:33:44:02", "active", 2000),
+            // Ended, and released
+            lease("10.0.0.151", "00:11:22:33:44:03", "active", 500),
+            lease("10.0.0.152", "00:11:22:33:44:03", "free", 2000),
+            // Leases the second of its two reservations
+            lease("10.0.0.14", "00:11:22:33:44:04", "active", 2000),
+        ];
+        let audit = audit_leases(&root, &Backend::Kea, &leases, 1000);
+        assert_eq!(
+            (
+                audit.reservations,
+                audit.active_leases,
+                audit.matched,
+                audit.not_leasing
+            ),
+            (5, 3, 3, 1)
+        );
+        assert_eq!(
+            audit.mismatched,
+            vec![LeaseMismatch {
+                mac: "00:11:22:33:44:01".to_string(),
+                hostname: Some("printer".to_string()),
+                reserved_ip: "10.0.0.10".to_string(),
+                leased_ip: "10.0.0.150".to_string(),
+                lease_ends: Some(2000),
+            }]
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! DNS host records for migrated static mappings (`--create-dns-hosts`,
+//! `--register-unbound`).
+
+use anyhow::Result;
+use std::collections::HashMap;
+use xmltree::{Element, XMLNode};
+
+use crate::extract::{iter_isc_options_v4, iter_isc_options_v6};
+use crate::migrate::utils::split_policy_fqdn;
+#[cfg(feature = "dnsmasq")]
+use crate::migrate_dnsmasq::{create_dnsmasq_dns_host, get_dnsmasq_node};
+use crate::migrate_unbound::{create_unbound_host_element, get_unbound_hosts_node};
+#[cfg(feature = "dnsmasq")]
+use crate::model::HostLayout;
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::xml_helpers::{child_text, get_child_ci};
+#[cfg(feature = "dnsmasq")]
+use crate::xml_helpers::{get_mut_child_ci, set_text_preserving_comments};
+use crate::{IscStaticMap, IscStaticMapV6, MigrationOptions};
+
+/// Hostname to address record derived from a static mapping.
+#[derive(Debug, Clone)]
+pub(crate) struct DnsHostRecord {
+    pub(crate) hostname: String,
+    pub(crate) domain: String,
+    pub(crate) ip: String,
+    pub(crate) descr: String,
+}
+
+impl DnsHostRecord {
+    fn is_v6(&self) -> bool {
+        self.ip.contains(':')
+    }
+
+    pub(crate) fn fqdn(&self) -> String {
+        if self.domain.is_empty() {
+            self.hostname.clone()
+        } else {
+            format!("{}.{}", self.hostname, self.domain)
+        }
+    }
+}
+
+/// Counts from applying DNS host records.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct DnsHostCounts {
+    pub(crate) created: usize,
+    /// Existing dnsmasq hosts that were given a domain
+    #[cfg_attr(not(feature = "dnsmasq"), allow(dead_code))]
+    pub(crate) updated: usize,
+    pub(crate) skipped: usize,
+}
+
+/// Build DNS records for mappings that have a hostname.
+///
+/// The domain is taken from `--hostname-policy fqdn`, then the mapping, then
+/// the interface's ISC domain, then the system domain.
+pub(crate) fn dns_host_records(
+    root: &Element,
+    mappings: &[IscStaticMap],
+    mappings_v6: &[IscStaticMapV6],
+    options: &MigrationOptions,
+) -> Result<Vec<DnsHostRecord>> {
+    let system_domain = get_child_ci(root, "system")
+        .and_then(|system| get_child_ci(system, "domain"))
+        .and_then(|e| e.get_text())
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    let iface_domains: HashMap<String, String> = iter_isc_options_v4(root)
+        .filter_map(|opt| opt.domain_name.map(|domain| (opt.iface, domain)))
+        .collect();
+    let iface_domains_v6: HashMap<String, String> = iter_isc_options_v6(root)
+        .filter_map(|opt| {
+            let domain = first_domain(opt.domain_search.as_deref()?);
+            (!domain.is_empty()).then_some((opt.iface, domain))
+        })
+        .collect();
+
+    let mut records = Vec::new();
+    for mapping in mappings {
+        let Some(hostname) = mapping.hostname.as_deref().filter(|h| !h.is_empty()) else {
+            continue;
+        };
+        let (hostname, policy_domain) = split_policy_fqdn(hostname, options);
+        let domain = policy_domain
+            .map(str::to_string)
+            .or_else(|| mapping.domain.clone())
+            .or_else(|| iface_domains.get(&mapping.iface).cloned())
+            .unwrap_or_else(|| system_domain.clone());
+        records.push(DnsHostRecord {
+            hostname: hostname.to_string(),
+            domain,
+            ip: mapping.ipaddr.clone(),
+            descr: mapping.descr.clone().unwrap_or_default(),
+        });
+    }
+    for mapping in mappings_v6 {
+        let Some(hostname) = mapping.hostname.as_deref().filter(|h| !h.is_empty()) else {
+            continue;
+        };
+        let (hostname, policy_domain) = split_policy_fqdn(hostname, options);
+        let domain = policy_domain
+            .map(str::to_string)
+            .or_else(|| mapping.domain_search.as_deref().map(first_domain))
+            .filter(|d| !d.is_empty())
+            .or_else(|| iface_domains_v6.get(&mapping.iface).cloned())
+            .or_else(|| iface_domains.get(&mapping.iface).cloned())
+            .unwrap_or_else(|| system_domain.clone());
+        records.push(DnsHostRecord {
+            hostname: hostname.to_string(),
+            domain,
+            ip: mapping.ipaddr.clone(),
+            descr: mapping.descr.clone().unwrap_or_default(),
+        });
+    }
+
+    Ok(records)
+}
+
+/// First entry of a space- or comma-separated domain search list.
+pub(crate) fn first_domain(domain_search: &str) -> String {
+    domain_search
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .find(|s| !s.is_empty())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Add dnsmasq DNS host entries for `records`.
+///
+/// A name that already resolves to a different address of the same family
+/// is skipped with a warning. A record whose IP already has a dnsmasq host
+/// is skipped too, except that a host with the same name and an empty domain
+/// gets the record's domain (this covers hosts just created by
+/// `--backend dnsmasq`).
+#[cfg(feature = "dnsmasq")]
+pub(crate) fn apply_dnsmasq_dns_hosts(
+    root: &mut Element,
+    records: &[DnsHostRecord],
+    options: &MigrationOptions,
+) -> Result<DnsHostCounts> {
+    let dnsmasq = get_dnsmasq_node(root)?;
+    let mut counts = DnsHostCounts::default();
+    let mut known: Vec<DnsHostRecord> = dnsmasq
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(|e| e.name.eq_ignore_ascii_case("hosts"))
+        .map(|host| DnsHostRecord {
+            hostname: child_text(host, "host").unwrap_or_default(),
+            domain: child_text(host, "domain").unwrap_or_default(),
+            ip: child_text(host, "ip").unwrap_or_default(),
+            descr: String::new(),
+        })
+        .collect();
+
+    for record in records {
+        if let Some(existing) = known.iter().find(|k| {
+            k.ip != record.ip
+                && k.is_v6() == record.is_v6()
+                && k.fqdn().eq_ignore_ascii_case(&record.fqdn())
+        }) {
+            warn(
+                WarningKind::DnsNameTaken,
+                format_args!(
+                    "DNS name {} already points to {} in dnsmasq; not adding {}.",
+                    record.fqdn(),
+                    existing.ip,
+                    record.ip
+                ),
+            );
+            counts.skipped += 1;
+            continue;
+        }
+
+        if let Some(idx) = known.iter().position(|k| k.ip == record.ip) {
+            let existing = &known[idx];
+            if existing.hostname.eq_ignore_ascii_case(&record.hostname)
+                && existing.domain.is_empty()
+                && !record.domain.is_empty()
+            {
+                set_dnsmasq_host_domain(dnsmasq, &record.ip, &record.domain);
+                known[idx].domain = record.domain.clone();
+                counts.updated += 1;
+                if options.verbose {
+                    term::action(
+                        "DNS",
+                        format_args!("{} -> {} (domain set)", record.fqdn(), record.ip),
+                    );
+                }
+            } else {
+                counts.skipped += 1;
+                if options.verbose {
+                    term::action(
+                        "SKIP DNS",
+                        format_args!(
+                            "{} -> {} - IP already has a dnsmasq host",
+                            record.fqdn(),
+                            record.ip
+                        ),
+                    );
+                }
+            }
+            continue;
+        }
+
+        if options.verbose {
+            term::action("DNS", format_args!("{} -> {}", record.fqdn(), record.ip));
+        }
+        let host =
+            create_dnsmasq_dns_host(&record.hostname, &record.domain, &record.ip, &record.descr);
+        dnsmasq
+            .children
+            .push(XMLNode::Element(host.to_element(HostLayout::Dns)));
+        known.push(record.clone());
+        counts.created += 1;
+    }
+
+    Ok(counts)
+}
+
+/// Add Unbound host overrides for `records`.
+///
+/// An override for the same name and record type is never duplicated; if
+/// it points to a different address a warning is printed. Records without
+/// a domain are skipped because Unbound overrides require one.
+pub(crate) fn apply_unbound_dns_hosts(
+    root: &mut Element,
+    records: &[DnsHostRecord],
+    options: &MigrationOptions,
+) -> Result<DnsHostCounts> {
+    let hosts = get_unbound_hosts_node(root)?;
+    let mut counts = DnsHostCounts::default();
+    let mut known: Vec<(String, String)> = hosts
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(|e| e.name.eq_ignore_ascii_case("host"))
+        .map(|host| {
+            let fqdn = format!(
+                "{}.{}/{}",
+                child_text(host, "hostname").unwrap_or_default(),
+                child_text(host, "domain").unwrap_or_default(),
+                child_text(host, "rr")
+                    .unwrap_or_default()
+                    .to_ascii_uppercase()
+            );
+            (
+                fqdn.to_ascii_lowercase(),
+                child_text(host, "server").unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    for record in records {
+        if record.domain.is_empty() {
+            term::warning(format_args!(
+                "No domain for {} ({}); Unbound overrides need one. Skipping.",
+                record.hostname, record.ip
+            ));
+            counts.skipped += 1;
+            continue;
+        }
+        let rr = if record.is_v6() { "AAAA" } else { "A" };
+        let key = format!("{}/{}", record.fqdn(), rr).to_ascii_lowercase();
+        if let Some((_, server)) = known.iter().find(|(k, _)| *k == key) {
+            if *server != record.ip {
+                warn(
+                    WarningKind::DnsNameTaken,
+                    format_args!(
+                        "Unbound override {} already points to {}; not adding {}.",
+                        record.fqdn(),
+                        server,
+                        record.ip
+                    ),
+                );
+            } else if options.verbose {
+                term::action(
+                    "SKIP UNBOUND",
+                    format_args!(
+                        "{} -> {} - override already exists",
+                        record.fqdn(),
+                        record.ip
+                    ),
+                );
+            }
+            counts.skipped += 1;
+            continue;
+        }
+
+        if options.verbose {
+            term::action(
+                "UNBOUND",
+                format_args!("{} {} {}", record.fqdn(), rr, record.ip),
+            );
+        }
+        let elem = create_unbound_host_element(
+            &record.hostname,
+            &record.domain,
+            rr,
+            &record.ip,
+            &record.descr,
+        );
+        hosts.children.push(XMLNode::Element(elem));
+        known.push((key, record.ip.clone()));
+        counts.created += 1;
+    }
+
+    Ok(counts)
+}
+
+#[cfg(feature = "dnsmasq")]
+fn set_dnsmasq_host_domain(dnsmasq: &mut Element, ip: &str, domain: &str) {
+    let host = dnsmasq
+        .children
+        .iter_mut()
+        .filter_map(|n| n.as_mut_element())
+        .filter(|e| e.name.eq_ignore_ascii_case("hosts"))
+        .find(|host| child_text(host, "ip").as_deref() == Some(ip));
+    let Some(host) = host else {
+        return;
+    };
+    match get_mut_child_ci(host, "domain") {
+        Some(elem) => set_text_preserving_comments(elem, domain),
+        None => {
+            let mut elem = Element::new("domain");
+            elem.children.push(XMLNode::Text(domain.to_string()));
+            host.children.push(XMLNode::Element(elem));
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! dnsmasq network boot entries (`<dhcp_boot>`) from ISC netboot settings.
+//!
+//! ISC sets the boot file, TFTP server and next server per interface, and
+//! Kea takes them on the `subnet4` `--create-subnets` creates. dnsmasq keeps
+//! them apart from ranges, as one `dhcp-boot` entry per interface or tag,
+//! so each interface with a created range gets its own entry. An entry for
+//! an interface and tag that dnsmasq already has is left alone.
+
+use std::collections::HashSet;
+use xmltree::Element;
+
+use crate::extract_dnsmasq::dnsmasq_children;
+use crate::migrate::subnets::DesiredSubnetV4;
+use crate::model::DnsmasqBootNode;
+use crate::parse;
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::MigrationOptions;
+
+/// Fields OPNsense requires on a `<dhcp_boot>` entry.
+pub(super) const DNSMASQ_BOOT_REQUIRED: &[&str] = &["interface", "filename"];
+
+/// `interface|tag` keys of the existing `<dhcp_boot>` entries.
+pub(super) fn existing_boot_keys(root: &Element) -> HashSet<String> {
+    dnsmasq_children(root)
+        .filter_map(DnsmasqBootNode::from_element)
+        .map(|boot| boot_key(&boot.interface, &boot.tag))
+        .collect()
+}
+
+fn boot_key(interface: &str, tag: &str) -> String {
+    format!("{}|{}", interface, tag)
+}
+
+/// Boot entries for the netboot settings of `subnets`, leaving out those
+/// whose interface already has one. `existing` gets the keys of the new
+/// entries, so an interface with several subnets gets a single entry.
+pub(super) fn boot_entries(
+    subnets: &[DesiredSubnetV4],
+    existing: &mut HashSet<String>,
+    options: &MigrationOptions,
+) -> Vec<DnsmasqBootNode> {
+    let mut entries = Vec::new();
+    for subnet in subnets {
+        let Some(netboot) = &subnet.netboot else {
+            continue;
+        };
+        let Some(filename) = netboot.boot_file_name.clone() else {
+            term::warning(format_args!(
+                "ISC network boot on {} has no boot file name; dnsmasq needs one. Skipping.",
+                subnet.display_iface()
+            ));
+            continue;
+        };
+        if !existing.insert(boot_key(&subnet.iface, "")) {
+            warn(
+                WarningKind::DnsmasqBootExists,
+                format_args!(
+                    "dnsmasq boot entry already exists (iface {}). Skipping.",
+                    subnet.display_iface()
+                ),
+            );
+            continue;
+        }
+        let address = netboot.next_server.clone().filter(|ip| {
+            let valid = parse::ipv4(ip).is_ok();
+            if !valid {
+                term::warning(format_args!(
+                    "ISC next-server '{}' on {} is not an IPv4 address. Skipping it.",
+                    ip,
+                    subnet.display_iface()
+                ));
+            }
+            valid
+        });
+        let entry = DnsmasqBootNode {
+            uuid: uuid::Uuid::new_v4().to_string(),
+            interface: subnet.iface.clone(),
+            tag: String::new(),
+            filename,
+            servername: netboot.tftp_server_name.clone().unwrap_or_default(),
+            address: address.unwrap_or_default(),
+            description: subnet.descr.clone().unwrap_or_default(),
+        };
+        if options.verbose {
+            term::action(
+                "BOOT",
+                format_args!(
+                    "{} -> {} (server {})",
+                    subnet.display_iface(),
+                    entry.filename,
+                    if entry.address.is_empty() {
+                        "dnsmasq"
+                    } else {
+                        entry.address.as_str()
+                    }
+                ),
+            );
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::IscNetbootV4;
+
+    fn subnet(iface: &str, netboot: IscNetbootV4) -> DesiredSubnetV4 {
+        DesiredSubnetV4 {
+            iface: iface.to_string(),
+            cidr: "192.168.1.0/24".to_string(),
+            ranges: Vec::new(),
+            descr: None,
+            netboot: Some(netboot),
+        }
+    }
+
+    #[test]
+    fn test_boot_entries() {
+        let root = Element::parse(
+            r#"<opnsense><dnsmasq>
+                <dhcp_boot><interface>opt1</interface><tag/><filename>a</filename></dhcp_boot>
+            </dnsmasq></opnsense>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let mut existing = existing_boot_keys(&root);
+        let pxe = IscNetbootV4 {
+            next_server: Some("192.168.1.5".to_string()),
+            boot_file_name: Some("pxelinux.0".to_string()),
+            tftp_server_name: Some("tftp.lan".to_string()),
+        };
+        let subnets = [
+            subnet("lan", pxe.clone()),
+            subnet("lan", pxe.clone()),
+            subnet("opt1", pxe),
+            subnet(
+                "opt2",
+                IscNetbootV4 {
+                    next_server: Some("192.168.1.5".to_string()),
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        let entries = boot_entries(&subnets, &mut existing, &MigrationOptions::default());
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.interface, "lan");
+        assert_eq!(entry.filename, "pxelinux.0");
+        assert_eq!(entry.servername, "tftp.lan");
+        assert_eq!(entry.address, "192.168.1.5");
+        assert!(existing.contains("lan|"));
+    }
+}
@@ -2,33 +2,52 @@ use anyhow::{anyhow, Result};
 use xmltree::{Element, XMLNode};
 
 use crate::extract::{
-    extract_interface_cidrs, extract_interface_cidrs_v6, extract_isc_options_v4,
-    extract_isc_options_v6,
+    extract_interface_cidrs, extract_interface_cidrs_v6, iter_isc_number_options_v4,
 };
 use crate::extract_dnsmasq::{
-    extract_existing_dnsmasq_client_ids, extract_existing_dnsmasq_ips,
-    extract_existing_dnsmasq_macs, extract_existing_dnsmasq_options,
-    extract_existing_dnsmasq_ranges, has_dnsmasq,
+    extract_existing_dnsmasq_options, extract_existing_dnsmasq_ranges, has_dnsmasq,
 };
 use crate::migrate_dnsmasq::{
-    create_dnsmasq_host_element, create_dnsmasq_host_element_v6, create_dnsmasq_option_element,
-    create_dnsmasq_range_element_v4, create_dnsmasq_range_element_v6, get_dnsmasq_node,
+    create_dnsmasq_host, create_dnsmasq_host_v6, create_dnsmasq_option, create_dnsmasq_range_v4,
+    create_dnsmasq_range_v6, get_dnsmasq_node, strip_empty_elements, DNSMASQ_HOST_REQUIRED,
+    DNSMASQ_OPTION_REQUIRED, DNSMASQ_RANGE_REQUIRED,
 };
+use crate::model::{DnsmasqHostNode, DnsmasqOptionNode, HostLayout};
 use crate::subnet::prefix_to_netmask;
-use crate::{IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions, MigrationStats};
+use crate::term;
+use crate::{
+    IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions, MigrationStats, OptionsMerge,
+    RangeOverlapPolicy,
+};
 
-use super::{option_key_for_spec, range_key};
-use crate::migrate::options::{dnsmasq_option_key_from_elem, dnsmasq_option_specs_from_isc};
+use super::boot::{boot_entries, existing_boot_keys, DNSMASQ_BOOT_REQUIRED};
+use super::host_options::{existing_tag_names, host_option_entries, DNSMASQ_TAG_REQUIRED};
+use super::{
+    cidr_prefix_v4, cidr_prefix_v6, conflicting_ranges, dnsmasq_v6_mode, ensure_no_range_overlaps,
+    option_key_for_spec, range_key, range_overlap_policy, remove_dnsmasq_ranges, spec_tags,
+    v6_range_bounds, warn_range_conflict,
+};
+use crate::migrate::existing::ExistingState;
+use crate::migrate::hwaddr::drop_non_ethernet_hwaddrs;
+use crate::migrate::option_values::checked_isc_options;
+use crate::migrate::options::{
+    classless_route_specs, dnsmasq_option_key_from_elem, dnsmasq_option_specs_from_isc,
+    options_merge_policy, reconcile_dnsmasq_option_specs,
+};
 use crate::migrate::services::{
-    disable_isc_dhcp_from_config, enable_dnsmasq, ensure_isc_was_enabled, verify_isc_disabled,
+    allow_dnsmasq_dhcp, disable_isc_dhcp_from_config, enable_dnsmasq, ensure_isc_was_enabled,
+    verify_isc_disabled,
 };
 use crate::migrate::subnets::{
-    cidr_prefix_v4, cidr_prefix_v6, desired_subnets_v4, desired_subnets_v6, DesiredSubnetV4,
-    DesiredSubnetV6,
+    desired_subnets_v4, desired_subnets_v6, merge_desired_ranges, DesiredSubnetV4, DesiredSubnetV6,
+};
+use crate::migrate::utils::{
+    conflict_v4, conflict_v6, split_policy_fqdn, validate_mapping_ifaces_v4,
+    validate_mapping_ifaces_v6, warn_unmigrated_prefixes,
 };
-use crate::migrate::utils::{validate_mapping_ifaces_v4, validate_mapping_ifaces_v6};
+use crate::warnings::{warn, WarningKind};
 
-/// Convert an input configuration into dnsmasq hosts/ranges/options.
+/// Convert an input configuration into dnsmasq hosts/ranges/boot entries/options.
 pub(crate) fn convert_dnsmasq(
     root: &mut Element,
     isc_mappings: &[IscStaticMap],
@@ -36,28 +55,24 @@ pub(crate) fn convert_dnsmasq(
     options: &MigrationOptions,
 ) -> Result<MigrationStats> {
     let want_desired = options.create_subnets || options.enable_backend;
-    let desired_v4 = if want_desired {
+    let mut desired_v4 = if want_desired {
         desired_subnets_v4(root)?
     } else {
         Vec::new()
     };
-    let desired_v6 = if want_desired {
+    let mut desired_v6 = if want_desired {
         desired_subnets_v6(root)?
     } else {
         Vec::new()
     };
-    let options_v4 = if options.create_options {
-        extract_isc_options_v4(root)?
-    } else {
-        Vec::new()
-    };
-    let options_v6 = if options.create_options {
-        extract_isc_options_v6(root)?
-    } else {
-        Vec::new()
-    };
+    let ranges_merged = merge_desired_ranges(&mut desired_v4, &mut desired_v6, options);
+    let (options_v4, options_v6, option_values) = checked_isc_options(root, options);
     let desired_options = if options.create_options {
-        dnsmasq_option_specs_from_isc(&options_v4, &options_v6)
+        let number_options: Vec<_> = iter_isc_number_options_v4(root).collect();
+        let mut specs = dnsmasq_option_specs_from_isc(&options_v4, &options_v6);
+        reconcile_dnsmasq_option_specs(&mut specs, options);
+        specs.extend(classless_route_specs(&number_options));
+        specs
     } else {
         Vec::new()
     };
@@ -77,10 +92,12 @@ pub(crate) fn convert_dnsmasq(
         .into());
     }
 
-    let existing_ips = extract_existing_dnsmasq_ips(root)?;
-    let existing_macs = extract_existing_dnsmasq_macs(root)?;
-    let existing_client_ids = extract_existing_dnsmasq_client_ids(root)?;
+    let existing = ExistingState::dnsmasq(root);
     let existing_ranges = extract_existing_dnsmasq_ranges(root)?;
+    let mut existing_boot = existing_boot_keys(root);
+    let mut tag_names = existing_tag_names(root);
+    let mut option_tags = spec_tags(root, &desired_options);
+    tag_names.extend(option_tags.keys().map(|name| name.to_ascii_lowercase()));
     let existing_options = if options.create_options {
         extract_existing_dnsmasq_options(root)?
     } else {
@@ -88,27 +105,41 @@ pub(crate) fn convert_dnsmasq(
     };
 
     if options.fail_if_existing
-        && (!existing_ips.is_empty()
-            || !existing_macs.is_empty()
-            || !existing_client_ids.is_empty()
+        && (!existing.ips.is_empty()
+            || !existing.macs.is_empty()
+            || !existing.client_ids.is_empty()
             || (options.create_subnets && !existing_ranges.is_empty()))
     {
         return Err(anyhow!(
             "Existing dnsmasq hosts found ({} entries) and --fail-if-existing is set. Aborting.",
-            existing_ips.len()
+            existing.ips.len()
         ));
     }
 
+    if options.create_subnets {
+        ensure_no_range_overlaps(&existing_ranges, &desired_v4, &desired_v6, options)?;
+    }
+
     let mut to_create = 0;
-    let mut skipped = 0;
+    let total_v4 = isc_mappings.len();
+    let mut isc_mappings = isc_mappings.to_vec();
+    let mut skipped = drop_non_ethernet_hwaddrs(&mut isc_mappings);
     let mut to_create_v6 = 0;
     let mut skipped_v6 = 0;
-    let mut reserved_ips = existing_ips;
-    let mut reserved_macs = existing_macs;
-    let mut reserved_client_ids = existing_client_ids;
-
-    validate_mapping_ifaces_v4(isc_mappings, &iface_cidrs_v4)?;
+    let mut skips = Vec::new();
+    let ExistingState {
+        ips: mut reserved_ips,
+        macs: mut reserved_macs,
+        client_ids: mut reserved_client_ids,
+        ..
+    } = existing;
+    let mut ranges_created = 0;
+    let (mut options_created, mut options_skipped) = (0, 0);
+
+    validate_mapping_ifaces_v4(&isc_mappings, &iface_cidrs_v4)?;
     validate_mapping_ifaces_v6(isc_mappings_v6, &iface_cidrs_v6)?;
+    let prefixes_v6_unmigrated =
+        warn_unmigrated_prefixes(isc_mappings_v6, "dnsmasq", false, options);
 
     if options.verbose {
         println!(
@@ -125,135 +156,129 @@ pub(crate) fn convert_dnsmasq(
         let dnsmasq_node = get_dnsmasq_node(root)?;
 
         if options.create_subnets {
+            let policy = range_overlap_policy(options);
             for subnet in &desired_v4 {
                 let prefix = cidr_prefix_v4(&subnet.cidr)?;
                 let mask = prefix_to_netmask(prefix)?;
                 for range in &subnet.ranges {
                     let key = range_key(&subnet.iface, &range.from, &range.to, "", &mask);
-                    if existing_ranges.contains(&key) {
-                        if options.force_subnets {
-                            dnsmasq_node.children.retain(|child| {
-                                let Some(elem) = child.as_element() else {
-                                    return true;
-                                };
-                                if !elem.name.eq_ignore_ascii_case("dhcp_ranges") {
-                                    return true;
-                                }
-                                let iface = crate::xml_helpers::get_child_ci(elem, "interface")
-                                    .and_then(|e| e.get_text())
-                                    .map(|s| s.to_string())
-                                    .unwrap_or_default();
-                                let start = crate::xml_helpers::get_child_ci(elem, "start_addr")
-                                    .and_then(|e| e.get_text())
-                                    .map(|s| s.to_string())
-                                    .unwrap_or_default();
-                                let end = crate::xml_helpers::get_child_ci(elem, "end_addr")
-                                    .and_then(|e| e.get_text())
-                                    .map(|s| s.to_string())
-                                    .unwrap_or_default();
-                                let prefix_len =
-                                    crate::xml_helpers::get_child_ci(elem, "prefix_len")
-                                        .and_then(|e| e.get_text())
-                                        .map(|s| s.to_string())
-                                        .unwrap_or_default();
-                                let subnet_mask =
-                                    crate::xml_helpers::get_child_ci(elem, "subnet_mask")
-                                        .and_then(|e| e.get_text())
-                                        .map(|s| s.to_string())
-                                        .unwrap_or_default();
-                                let existing_key =
-                                    range_key(&iface, &start, &end, &prefix_len, &subnet_mask);
-                                existing_key != key
-                            });
+                    let conflicts = conflicting_ranges(
+                        &existing_ranges,
+                        &subnet.iface,
+                        &range.from,
+                        &range.to,
+                        &key,
+                    );
+                    if !conflicts.is_empty() {
+                        if policy == RangeOverlapPolicy::Force {
+                            remove_dnsmasq_ranges(dnsmasq_node, &conflicts);
                         } else {
-                            eprintln!(
-                                "Warning: dnsmasq range {}-{} already exists (iface {}). Skipping.",
-                                range.from, range.to, subnet.iface
+                            warn_range_conflict(
+                                &range.from,
+                                &range.to,
+                                &subnet.display_iface(),
+                                &key,
+                                &conflicts,
                             );
                             continue;
                         }
                     }
 
-                    let elem = create_dnsmasq_range_element_v4(
+                    let range = create_dnsmasq_range_v4(
                         &subnet.iface,
                         &range.from,
                         &range.to,
                         &mask,
+                        subnet.descr.as_deref().unwrap_or(""),
                     );
-                    dnsmasq_node.children.push(XMLNode::Element(elem));
+                    dnsmasq_node.children.push(created_node(
+                        range.to_element(),
+                        DNSMASQ_RANGE_REQUIRED,
+                        options,
+                    ));
+                    ranges_created += 1;
                 }
             }
 
             for subnet in &desired_v6 {
                 let prefix = cidr_prefix_v6(&subnet.cidr)?;
+                let mode = dnsmasq_v6_mode(subnet, options);
+                let constructor = if options.dnsmasq_constructor {
+                    subnet.iface.as_str()
+                } else {
+                    ""
+                };
                 for range in &subnet.ranges {
-                    let key = range_key(
-                        &subnet.iface,
-                        &range.from,
-                        &range.to,
-                        &prefix.to_string(),
-                        "",
-                    );
-                    if existing_ranges.contains(&key) {
-                        if options.force_subnets {
-                            dnsmasq_node.children.retain(|child| {
-                                let Some(elem) = child.as_element() else {
-                                    return true;
-                                };
-                                if !elem.name.eq_ignore_ascii_case("dhcp_ranges") {
-                                    return true;
-                                }
-                                let iface = crate::xml_helpers::get_child_ci(elem, "interface")
-                                    .and_then(|e| e.get_text())
-                                    .map(|s| s.to_string())
-                                    .unwrap_or_default();
-                                let start = crate::xml_helpers::get_child_ci(elem, "start_addr")
-                                    .and_then(|e| e.get_text())
-                                    .map(|s| s.to_string())
-                                    .unwrap_or_default();
-                                let end = crate::xml_helpers::get_child_ci(elem, "end_addr")
-                                    .and_then(|e| e.get_text())
-                                    .map(|s| s.to_string())
-                                    .unwrap_or_default();
-                                let prefix_len =
-                                    crate::xml_helpers::get_child_ci(elem, "prefix_len")
-                                        .and_then(|e| e.get_text())
-                                        .map(|s| s.to_string())
-                                        .unwrap_or_default();
-                                let subnet_mask =
-                                    crate::xml_helpers::get_child_ci(elem, "subnet_mask")
-                                        .and_then(|e| e.get_text())
-                                        .map(|s| s.to_string())
-                                        .unwrap_or_default();
-                                let existing_key =
-                                    range_key(&iface, &start, &end, &prefix_len, &subnet_mask);
-                                existing_key != key
-                            });
+                    let (start, end) = v6_range_bounds(range, prefix, options)?;
+                    let key = range_key(&subnet.iface, &start, &end, &prefix.to_string(), "");
+                    let conflicts =
+                        conflicting_ranges(&existing_ranges, &subnet.iface, &start, &end, &key);
+                    if !conflicts.is_empty() {
+                        if policy == RangeOverlapPolicy::Force {
+                            remove_dnsmasq_ranges(dnsmasq_node, &conflicts);
                         } else {
-                            eprintln!(
-                                "Warning: dnsmasq range {}-{} already exists (iface {}). Skipping.",
-                                range.from, range.to, subnet.iface
+                            warn_range_conflict(
+                                &range.from,
+                                &range.to,
+                                &subnet.display_iface(),
+                                &key,
+                                &conflicts,
                             );
                             continue;
                         }
                     }
 
-                    let elem = create_dnsmasq_range_element_v6(
+                    let range = create_dnsmasq_range_v6(
                         &subnet.iface,
-                        &range.from,
-                        &range.to,
+                        &start,
+                        &end,
                         &prefix.to_string(),
+                        mode,
+                        constructor,
+                        subnet.descr.as_deref().unwrap_or(""),
                     );
-                    dnsmasq_node.children.push(XMLNode::Element(elem));
+                    dnsmasq_node.children.push(created_node(
+                        range.to_element(),
+                        DNSMASQ_RANGE_REQUIRED,
+                        options,
+                    ));
+                    ranges_created += 1;
                 }
             }
+
+            for entry in boot_entries(&desired_v4, &mut existing_boot, options) {
+                dnsmasq_node.children.push(created_node(
+                    entry.to_element(),
+                    DNSMASQ_BOOT_REQUIRED,
+                    options,
+                ));
+            }
         }
 
         if options.create_options {
+            let merge = options_merge_policy(options);
+            let configured = configured_option_families(&existing_options);
             for spec in &desired_options {
-                let key = option_key_for_spec(spec);
+                let family = (spec.iface.clone(), !spec.option6.is_empty());
+                if merge == OptionsMerge::Skip && configured.contains(&family) {
+                    options_skipped += 1;
+                    warn(
+                        WarningKind::OptionsSet,
+                        format_args!(
+                            "dnsmasq {} options already set (iface {}). Skipping.",
+                            if family.1 { "DHCPv6" } else { "DHCPv4" },
+                            spec.iface
+                        ),
+                    );
+                    continue;
+                }
+                let tag = option_tags
+                    .get(&spec.tag)
+                    .map(|(uuid, _)| uuid.clone())
+                    .unwrap_or_default();
+                let key = option_key_for_spec(spec, &tag);
                 if existing_options.contains(&key) {
-                    if options.force_options {
+                    if merge == OptionsMerge::Overwrite {
                         dnsmasq_node.children.retain(|child| {
                             let Some(elem) = child.as_element() else {
                                 return true;
@@ -264,36 +289,63 @@ pub(crate) fn convert_dnsmasq(
                             existing_key != key
                         });
                     } else {
-                        eprintln!(
-                            "Warning: dnsmasq option {} already exists (iface {}). Skipping.",
-                            if spec.option.is_empty() {
-                                format!("v6:{}", spec.option6)
-                            } else {
-                                spec.option.clone()
-                            },
-                            spec.iface
+                        options_skipped += 1;
+                        warn(
+                            WarningKind::OptionsSet,
+                            format_args!(
+                                "dnsmasq option {} already exists (iface {}). Skipping.",
+                                if spec.option.is_empty() {
+                                    format!("v6:{}", spec.option6)
+                                } else {
+                                    spec.option.clone()
+                                },
+                                spec.iface
+                            ),
                         );
                         continue;
                     }
                 }
 
-                let elem = create_dnsmasq_option_element(
-                    &spec.iface,
-                    &spec.option,
-                    &spec.option6,
-                    &spec.value,
-                );
-                dnsmasq_node.children.push(XMLNode::Element(elem));
+                if let Some(new_tag) = option_tags
+                    .get_mut(&spec.tag)
+                    .and_then(|(_, new)| new.take())
+                {
+                    dnsmasq_node.children.push(created_node(
+                        new_tag.to_element(),
+                        DNSMASQ_TAG_REQUIRED,
+                        options,
+                    ));
+                }
+                let option = DnsmasqOptionNode {
+                    tag,
+                    ..create_dnsmasq_option(&spec.iface, &spec.option, &spec.option6, &spec.value)
+                };
+                dnsmasq_node.children.push(created_node(
+                    option.to_element(),
+                    DNSMASQ_OPTION_REQUIRED,
+                    options,
+                ));
+                options_created += 1;
             }
         }
 
-        for mapping in isc_mappings {
-            if reserved_ips.contains(&mapping.ipaddr) || reserved_macs.contains(&mapping.mac) {
+        for mapping in &isc_mappings {
+            if let Some(conflict) = conflict_v4(
+                mapping,
+                &reserved_ips,
+                &reserved_macs,
+                &reserved_client_ids,
+                true,
+            ) {
                 skipped += 1;
+                skips.push(conflict);
                 if options.verbose {
-                    println!(
-                        "  SKIP: {} ({}) - IP or MAC already exists in dnsmasq",
-                        mapping.ipaddr, mapping.mac
+                    term::action(
+                        "SKIP",
+                        format_args!(
+                            "{} ({}) - IP, MAC or client ID already exists in dnsmasq",
+                            mapping.ipaddr, mapping.mac
+                        ),
                     );
                 }
                 continue;
@@ -306,24 +358,62 @@ pub(crate) fn convert_dnsmasq(
                     .or(mapping.cid.as_ref())
                     .map(|s| s.as_str())
                     .unwrap_or("<no hostname>");
-                println!("  ADD: {} ({}) [{}]", mapping.ipaddr, mapping.mac, hostname);
+                term::action(
+                    "ADD",
+                    format_args!("{} ({}) [{}]", mapping.ipaddr, mapping.mac, hostname),
+                );
             }
 
-            let host_elem = create_dnsmasq_host_element(mapping);
-            dnsmasq_node.children.push(XMLNode::Element(host_elem));
+            let mut host = create_dnsmasq_host(mapping);
+            split_fqdn_host(&mut host, mapping.hostname.as_deref(), options);
+            apply_host_flags(&mut host, mapping.descr.as_deref(), options);
+            let host_options = if options.create_options {
+                host_option_entries(&mut host, mapping, &mut tag_names)
+            } else {
+                None
+            };
+            dnsmasq_node.children.push(created_node(
+                host.to_element(HostLayout::Dhcp4),
+                DNSMASQ_HOST_REQUIRED,
+                options,
+            ));
+            if let Some((tag, host_options)) = host_options {
+                if options.verbose {
+                    term::action(
+                        "HOST OPTIONS",
+                        format_args!("{} [tag {}]", mapping.ipaddr, tag.tag),
+                    );
+                }
+                dnsmasq_node.children.push(created_node(
+                    tag.to_element(),
+                    DNSMASQ_TAG_REQUIRED,
+                    options,
+                ));
+                for option in host_options {
+                    dnsmasq_node.children.push(created_node(
+                        option.to_element(),
+                        DNSMASQ_OPTION_REQUIRED,
+                        options,
+                    ));
+                }
+            }
             reserved_ips.insert(mapping.ipaddr.clone());
             reserved_macs.insert(mapping.mac.clone());
+            reserved_client_ids.extend(mapping.cid.clone());
             to_create += 1;
         }
 
         for mapping in isc_mappings_v6 {
-            if reserved_ips.contains(&mapping.ipaddr) || reserved_client_ids.contains(&mapping.duid)
-            {
+            if let Some(conflict) = conflict_v6(mapping, &reserved_ips, &reserved_client_ids) {
                 skipped_v6 += 1;
+                skips.push(conflict);
                 if options.verbose {
-                    println!(
-                        "  SKIP6: {} ({}) - IP or DUID already exists in dnsmasq",
-                        mapping.ipaddr, mapping.duid
+                    term::action(
+                        "SKIP6",
+                        format_args!(
+                            "{} ({}) - IP or DUID already exists in dnsmasq",
+                            mapping.ipaddr, mapping.duid
+                        ),
                     );
                 }
                 continue;
@@ -331,14 +421,20 @@ pub(crate) fn convert_dnsmasq(
 
             if options.verbose {
                 let hostname = mapping.hostname.as_deref().unwrap_or("<no hostname>");
-                println!(
-                    "  ADD6: {} ({}) [{}]",
-                    mapping.ipaddr, mapping.duid, hostname
+                term::action(
+                    "ADD6",
+                    format_args!("{} ({}) [{}]", mapping.ipaddr, mapping.duid, hostname),
                 );
             }
 
-            let host_elem = create_dnsmasq_host_element_v6(mapping);
-            dnsmasq_node.children.push(XMLNode::Element(host_elem));
+            let mut host = create_dnsmasq_host_v6(mapping);
+            split_fqdn_host(&mut host, mapping.hostname.as_deref(), options);
+            apply_host_flags(&mut host, mapping.descr.as_deref(), options);
+            dnsmasq_node.children.push(created_node(
+                host.to_element(HostLayout::Dhcp6),
+                DNSMASQ_HOST_REQUIRED,
+                options,
+            ));
             reserved_ips.insert(mapping.ipaddr.clone());
             reserved_client_ids.insert(mapping.duid.clone());
             to_create_v6 += 1;
@@ -375,22 +471,45 @@ pub(crate) fn convert_dnsmasq(
         verify_isc_disabled(root, &isc_disabled_v4, &isc_disabled_v6)?;
     }
 
+    // Interfaces this run serves must not stay on the "disable DHCP on"
+    // list, or dnsmasq answers only DNS there
+    let mut dhcp_ifaces = Vec::new();
+    if options.create_subnets {
+        dhcp_ifaces.extend(desired_v4.iter().map(|s| s.iface.clone()));
+        dhcp_ifaces.extend(desired_v6.iter().map(|s| s.iface.clone()));
+    }
+    dhcp_ifaces.extend(isc_disabled_v4.iter().cloned());
+    dhcp_ifaces.extend(isc_disabled_v6.iter().cloned());
+    let no_dhcp_cleared = allow_dnsmasq_dhcp(root, &dhcp_ifaces);
+
     Ok(MigrationStats {
-        isc_mappings_found: isc_mappings.len(),
+        option_values_resolved: option_values.resolved,
+        unresolved_option_values: option_values.unresolved,
+        isc_mappings_found: total_v4,
         isc_mappings_v6_found: isc_mappings_v6.len(),
         isc_ranges_found: 0,
         isc_ranges_v6_found: 0,
+        ranges_merged,
+        ranges_created,
+        options_created,
+        options_skipped,
         target_subnets_found: 0,
         target_subnets_v6_found: 0,
         reservations_to_create: to_create,
         reservations_v6_to_create: to_create_v6,
         reservations_skipped: skipped,
         reservations_v6_skipped: skipped_v6,
+        skips,
+        prefixes_v6_unmigrated,
         interfaces_configured,
+        no_dhcp_cleared,
         isc_disabled_v4,
         isc_disabled_v6,
         backend_enabled_v4,
         backend_enabled_v6,
+        fwrules_v4: None,
+        fwrules_v6: None,
+        ..Default::default()
     })
 }
 
@@ -428,19 +547,88 @@ pub(crate) fn apply_dnsmasq_interfaces(
         ifaces.insert(iface.to_string());
     }
 
-    // Remove existing interface element if present
-    dnsmasq_node
-        .children
-        .retain(|c| c.as_element().is_none_or(|e| e.name != "interface"));
-
-    // Add merged interfaces
-    let mut ifaces_elem = Element::new("interface");
+    // Update the existing interface element in place, or add one
     let mut sorted_ifaces: Vec<_> = ifaces.into_iter().collect();
     sorted_ifaces.sort();
-    ifaces_elem
-        .children
-        .push(XMLNode::Text(sorted_ifaces.join(",")));
-    dnsmasq_node.children.push(XMLNode::Element(ifaces_elem));
+    match crate::xml_helpers::get_mut_child_ci(dnsmasq_node, "interface") {
+        Some(ifaces_elem) => {
+            crate::xml_helpers::set_text_preserving_comments(ifaces_elem, &sorted_ifaces.join(","))
+        }
+        None => {
+            let mut ifaces_elem = Element::new("interface");
+            ifaces_elem
+                .children
+                .push(XMLNode::Text(sorted_ifaces.join(",")));
+            dnsmasq_node.children.push(XMLNode::Element(ifaces_elem));
+        }
+    }
 
     Ok(sorted_ifaces)
 }
+
+/// Wrap a created element for insertion, dropping empty fields first with
+/// `--minimal-elements`.
+fn created_node(mut elem: Element, required: &[&str], options: &MigrationOptions) -> XMLNode {
+    if options.minimal_elements {
+        strip_empty_elements(&mut elem, required);
+    }
+    XMLNode::Element(elem)
+}
+
+/// Move the domain of a `--hostname-policy fqdn` hostname into the host's
+/// `domain` field; dnsmasq hosts take a bare name.
+fn split_fqdn_host(host: &mut DnsmasqHostNode, hostname: Option<&str>, options: &MigrationOptions) {
+    let Some((short, Some(domain))) = hostname.map(|h| split_policy_fqdn(h, options)) else {
+        return;
+    };
+    host.host = short.to_string();
+    host.domain = domain.to_string();
+}
+
+/// Set the `local` and `ignore` flags of a created host from
+/// `--dnsmasq-hosts-local` and, with `--dnsmasq-host-tags`, from tags in the
+/// ISC description. `[no-dns]` leaves the host unnamed, so dnsmasq answers
+/// no DNS queries for it; the DHCP reservation is unchanged.
+fn apply_host_flags(host: &mut DnsmasqHostNode, descr: Option<&str>, options: &MigrationOptions) {
+    host.local = options.dnsmasq_hosts_local;
+    let Some(descr) = descr.filter(|_| options.dnsmasq_host_tags) else {
+        return;
+    };
+    let descr = descr.to_ascii_lowercase();
+    let tagged = |tag: &str| descr.contains(&format!("[{}]", tag));
+    let mut applied = Vec::new();
+    if tagged("no-dns") {
+        host.host.clear();
+        host.domain.clear();
+        applied.push("no-dns");
+    }
+    if tagged("ignore") {
+        host.ignore = true;
+        applied.push("ignore");
+    }
+    if tagged("local") {
+        host.local = true;
+        applied.push("local");
+    } else if tagged("no-local") {
+        host.local = false;
+        applied.push("no-local");
+    }
+    if options.verbose && !applied.is_empty() {
+        term::action("TAGS", format_args!("{} [{}]", host.ip, applied.join(", ")));
+    }
+}
+
+/// `(interface, is_v6)` pairs that already have a `set` option, from the
+/// keys of [`extract_existing_dnsmasq_options`].
+fn configured_option_families(
+    existing: &std::collections::HashSet<String>,
+) -> std::collections::HashSet<(String, bool)> {
+    existing
+        .iter()
+        .filter_map(|key| {
+            let parts: Vec<&str> = key.split('|').collect();
+            let (option6, iface) = (parts.get(2)?, parts.get(3)?);
+            Some((iface.to_string(), !option6.is_empty()))
+        })
+        .collect()
+}
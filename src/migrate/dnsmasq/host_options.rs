@@ -0,0 +1,149 @@
+//! Per-host DNS servers and gateway for dnsmasq hosts.
+//!
+//! ISC staticmaps can override the interface's DNS servers and gateway, and
+//! Kea keeps these in the reservation's `option_data`. dnsmasq only attaches
+//! options to tags, so each host with overrides sets a tag of its own
+//! (`<dhcp_tags>`) and gets `<dhcp_options>` matching that tag. Tag names
+//! come from the hostname or address and never reuse an existing tag.
+
+use std::collections::HashSet;
+use xmltree::Element;
+
+use crate::extract_dnsmasq::dnsmasq_children;
+use crate::migrate_dnsmasq::create_dnsmasq_option;
+use crate::model::{DnsmasqHostNode, DnsmasqOptionNode, DnsmasqTagNode};
+use crate::IscStaticMap;
+
+/// Fields OPNsense requires on a `<dhcp_tags>` entry.
+pub(super) const DNSMASQ_TAG_REQUIRED: &[&str] = &["tag"];
+
+/// Names of the existing `<dhcp_tags>` entries, lowercased.
+pub(super) fn existing_tag_names(root: &Element) -> HashSet<String> {
+    dnsmasq_children(root)
+        .filter_map(DnsmasqTagNode::from_element)
+        .map(|tag| tag.tag.to_ascii_lowercase())
+        .collect()
+}
+
+/// The tag and options carrying the per-host overrides of `mapping`, with
+/// `host` set to the tag; `None` when the mapping has none. `taken` gets the
+/// new tag's name.
+pub(super) fn host_option_entries(
+    host: &mut DnsmasqHostNode,
+    mapping: &IscStaticMap,
+    taken: &mut HashSet<String>,
+) -> Option<(DnsmasqTagNode, Vec<DnsmasqOptionNode>)> {
+    let mut values: Vec<(&str, String)> = [
+        ("6", mapping.dns_servers.join(",")),
+        ("3", mapping.gateway.clone().unwrap_or_default()),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .collect();
+    // An empty option 3 stops dnsmasq sending the interface's router
+    if mapping.gateway_suppressed {
+        values.push(("3", String::new()));
+    }
+    if values.is_empty() {
+        return None;
+    }
+
+    let tag = DnsmasqTagNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        tag: unique_tag_name(mapping, taken),
+    };
+    host.set_tag = tag.uuid.clone();
+    let options = values
+        .into_iter()
+        .map(|(option, value)| DnsmasqOptionNode {
+            tag: tag.uuid.clone(),
+            description: format!("{} ({})", mapping.ipaddr, tag.tag),
+            ..create_dnsmasq_option("", option, "", &value)
+        })
+        .collect();
+    Some((tag, options))
+}
+
+/// `host_<hostname>`, or `host_<address>` without a usable hostname, with a
+/// numeric suffix when the name is taken.
+fn unique_tag_name(mapping: &IscStaticMap, taken: &mut HashSet<String>) -> String {
+    let sanitize = |value: &str| -> String {
+        value
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    };
+    let base = match mapping.hostname.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => format!("host_{}", sanitize(name)),
+        _ => format!("host_{}", sanitize(&mapping.ipaddr)),
+    };
+    let mut name = base.clone();
+    let mut suffix = 2;
+    while !taken.insert(name.to_ascii_lowercase()) {
+        name = format!("{}_{}", base, suffix);
+        suffix += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(hostname: Option<&str>, ip: &str) -> IscStaticMap {
+        IscStaticMap {
+            iface: "lan".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            ipaddr: ip.to_string(),
+            hostname: hostname.map(str::to_string),
+            cid: None,
+            descr: None,
+            domain: None,
+            domain_search: None,
+            dns_servers: vec!["9.9.9.9".to_string(), "1.1.1.1".to_string()],
+            gateway: Some("192.168.1.254".to_string()),
+            gateway_suppressed: false,
+        }
+    }
+
+    #[test]
+    fn test_host_option_entries() {
+        let mut taken = HashSet::from(["host_printer".to_string()]);
+        let mut host = DnsmasqHostNode::default();
+        let (tag, options) = host_option_entries(
+            &mut host,
+            &mapping(Some("printer"), "192.168.1.10"),
+            &mut taken,
+        )
+        .expect("mapping has overrides");
+        assert_eq!(tag.tag, "host_printer_2");
+        assert_eq!(host.set_tag, tag.uuid);
+        let values: Vec<(&str, &str, &str, &str)> = options
+            .iter()
+            .map(|o| {
+                (
+                    o.option.as_str(),
+                    o.value.as_str(),
+                    o.tag.as_str(),
+                    o.interface.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("6", "9.9.9.9,1.1.1.1", tag.uuid.as_str(), ""),
+                ("3", "192.168.1.254", tag.uuid.as_str(), ""),
+            ]
+        );
+
+        let (tag, _) =
+            host_option_entries(&mut host, &mapping(None, "192.168.1.11"), &mut taken).unwrap();
+        assert_eq!(tag.tag, "host_192_168_1_11");
+
+        let mut plain = mapping(None, "192.168.1.12");
+        plain.dns_servers.clear();
+        plain.gateway = None;
+        assert!(host_option_entries(&mut host, &plain, &mut taken).is_none());
+    }
+}
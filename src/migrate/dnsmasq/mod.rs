@@ -1,22 +1,318 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv6Addr;
+use xmltree::Element;
+
 use super::options::DnsmasqOptionSpec;
+use super::subnets::{DesiredSubnetV4, DesiredSubnetV6};
+use crate::extract_dnsmasq::{dnsmasq_children, DnsmasqRange};
+use crate::model::DnsmasqTagNode;
+use crate::parse;
+use crate::registry::MigrationBackend;
+use crate::subnet::{prefix_to_netmask, ranges_overlap, ranges_overlap_v6};
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::{
+    DnsmasqV6Mode, IscRangeV6, IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions,
+    MigrationStats, RangeOverlap, RangeOverlapPolicy,
+};
 
 pub(crate) use convert::convert_dnsmasq;
+pub(crate) use order::order_created_children;
 pub(crate) use scan::scan_dnsmasq;
+pub(crate) use validate::{dnsmasq_host_uuids, validate_dnsmasq_hosts};
 
+mod boot;
 mod convert;
+mod host_options;
+mod order;
 mod scan;
+mod validate;
+
+/// Built-in dnsmasq DHCP backend.
+pub(crate) struct DnsmasqBackend;
+
+impl MigrationBackend for DnsmasqBackend {
+    fn name(&self) -> &str {
+        "dnsmasq"
+    }
+
+    fn scan(
+        &self,
+        root: &Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        scan_dnsmasq(root, mappings, mappings_v6, options)
+    }
+
+    fn convert(
+        &self,
+        root: &mut Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        convert_dnsmasq(root, mappings, mappings_v6, options)
+    }
+}
+
+fn cidr_prefix_v4(cidr: &str) -> Result<u8> {
+    Ok(parse::cidr_v4(cidr)?.prefix_len())
+}
+
+fn cidr_prefix_v6(cidr: &str) -> Result<u8> {
+    Ok(parse::cidr_v6(cidr)?.prefix_len())
+}
 
 fn range_key(iface: &str, start: &str, end: &str, prefix_len: &str, mask: &str) -> String {
     format!("{}|{}|{}|{}|{}", iface, start, end, prefix_len, mask)
 }
 
-fn option_key_for_spec(spec: &DnsmasqOptionSpec) -> String {
+fn option_key_for_spec(spec: &DnsmasqOptionSpec, tag: &str) -> String {
     crate::extract_dnsmasq::dnsmasq_option_key(
         "set",
         &spec.option,
         &spec.option6,
         &spec.iface,
-        "",
+        tag,
         "",
     )
 }
+
+/// `uuid`s of the `<dhcp_tags>` that `specs` match, by name: the existing
+/// entry with that name, else a new entry to create along with it.
+fn spec_tags(
+    root: &Element,
+    specs: &[DnsmasqOptionSpec],
+) -> HashMap<String, (String, Option<DnsmasqTagNode>)> {
+    let existing: Vec<DnsmasqTagNode> = dnsmasq_children(root)
+        .filter_map(DnsmasqTagNode::from_element)
+        .collect();
+    let mut tags = HashMap::new();
+    for name in specs.iter().map(|spec| &spec.tag).filter(|t| !t.is_empty()) {
+        tags.entry(name.clone()).or_insert_with(|| {
+            match existing
+                .iter()
+                .find(|tag| tag.tag.eq_ignore_ascii_case(name))
+            {
+                Some(tag) => (tag.uuid.clone(), None),
+                None => {
+                    let tag = DnsmasqTagNode {
+                        uuid: uuid::Uuid::new_v4().to_string(),
+                        tag: name.clone(),
+                    };
+                    (tag.uuid.clone(), Some(tag))
+                }
+            }
+        });
+    }
+    tags
+}
+
+/// Effective overlap policy; `--force-subnets` always replaces.
+fn range_overlap_policy(options: &MigrationOptions) -> RangeOverlapPolicy {
+    if options.force_subnets {
+        RangeOverlapPolicy::Force
+    } else {
+        options.range_overlap
+    }
+}
+
+/// Existing ranges on `iface` that equal `key` or whose address interval
+/// overlaps `from`-`to` (inclusive at both ends).
+fn conflicting_ranges<'a>(
+    existing: &'a [DnsmasqRange],
+    iface: &str,
+    from: &str,
+    to: &str,
+    key: &str,
+) -> Vec<&'a DnsmasqRange> {
+    existing
+        .iter()
+        .filter(|range| {
+            if range.key() == key {
+                return true;
+            }
+            if !range.iface.eq_ignore_ascii_case(iface) {
+                return false;
+            }
+            let overlap = if from.contains(':') {
+                ranges_overlap_v6(from, to, &range.start, &range.end)
+            } else {
+                ranges_overlap(from, to, &range.start, &range.end)
+            };
+            // Unparseable or mixed-family ranges never overlap
+            overlap.unwrap_or(false)
+        })
+        .collect()
+}
+
+/// [`conflicting_ranges`] without an existing range equal to `key`, which
+/// is skipped as already migrated rather than reported as an overlap.
+fn overlapping_ranges<'a>(
+    existing: &'a [DnsmasqRange],
+    iface: &str,
+    from: &str,
+    to: &str,
+    key: &str,
+) -> Vec<&'a DnsmasqRange> {
+    let mut conflicts = conflicting_ranges(existing, iface, from, to, key);
+    conflicts.retain(|range| range.key() != key);
+    conflicts
+}
+
+fn format_ranges(ranges: &[&DnsmasqRange]) -> String {
+    ranges
+        .iter()
+        .map(|r| format!("{}-{}", r.start, r.end))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Warn that a range is skipped because of existing ranges.
+fn warn_range_conflict(from: &str, to: &str, iface: &str, key: &str, conflicts: &[&DnsmasqRange]) {
+    if conflicts.len() == 1 && conflicts[0].key() == key {
+        warn(
+            WarningKind::DnsmasqRangeExists,
+            format_args!(
+                "dnsmasq range {}-{} already exists (iface {}). Skipping.",
+                from, to, iface
+            ),
+        );
+    } else {
+        warn(
+            WarningKind::DnsmasqRangeExists,
+            format_args!(
+                "dnsmasq range {}-{} (iface {}) overlaps existing range(s) {}. Skipping.",
+                from,
+                to,
+                iface,
+                format_ranges(conflicts)
+            ),
+        );
+    }
+}
+
+/// Remove existing `<dhcp_ranges>` entries matching any of the given ranges.
+fn remove_dnsmasq_ranges(dnsmasq_node: &mut Element, ranges: &[&DnsmasqRange]) {
+    let keys: HashSet<String> = ranges.iter().map(|r| r.key()).collect();
+    dnsmasq_node.children.retain(|child| {
+        let Some(elem) = child.as_element() else {
+            return true;
+        };
+        if !elem.name.eq_ignore_ascii_case("dhcp_ranges") {
+            return true;
+        }
+        DnsmasqRange::from_element(elem).is_none_or(|r| !keys.contains(&r.key()))
+    });
+}
+
+/// With `--range-overlap fail`, report every created range that overlaps an
+/// existing one.
+fn ensure_no_range_overlaps(
+    existing: &[DnsmasqRange],
+    desired_v4: &[DesiredSubnetV4],
+    desired_v6: &[DesiredSubnetV6],
+    options: &MigrationOptions,
+) -> Result<()> {
+    if range_overlap_policy(options) != RangeOverlapPolicy::Fail {
+        return Ok(());
+    }
+
+    let mut overlaps = Vec::new();
+    for subnet in desired_v4 {
+        let mask = prefix_to_netmask(cidr_prefix_v4(&subnet.cidr)?)?;
+        for range in &subnet.ranges {
+            let key = range_key(&subnet.iface, &range.from, &range.to, "", &mask);
+            let conflicts =
+                overlapping_ranges(existing, &subnet.iface, &range.from, &range.to, &key);
+            if !conflicts.is_empty() {
+                overlaps.push(RangeOverlap {
+                    iface: subnet.display_iface(),
+                    from: range.from.clone(),
+                    to: range.to.clone(),
+                    conflicts: conflicts
+                        .iter()
+                        .map(|r| format!("{}-{}", r.start, r.end))
+                        .collect(),
+                });
+            }
+        }
+    }
+    for subnet in desired_v6 {
+        let prefix = cidr_prefix_v6(&subnet.cidr)?;
+        for range in &subnet.ranges {
+            let (start, end) = v6_range_bounds(range, prefix, options)?;
+            let key = range_key(&subnet.iface, &start, &end, &prefix.to_string(), "");
+            let conflicts = overlapping_ranges(existing, &subnet.iface, &start, &end, &key);
+            if !conflicts.is_empty() {
+                overlaps.push(RangeOverlap {
+                    iface: subnet.display_iface(),
+                    from: range.from.clone(),
+                    to: range.to.clone(),
+                    conflicts: conflicts
+                        .iter()
+                        .map(|r| format!("{}-{}", r.start, r.end))
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    if overlaps.is_empty() {
+        return Ok(());
+    }
+    Err(MigrationError::OverlappingRanges { overlaps }.into())
+}
+
+/// `mode` for created DHCPv6 ranges on a subnet: the explicit
+/// `--dnsmasq-v6-mode`, or one derived from the interface's ISC `ramode`.
+fn dnsmasq_v6_mode(subnet: &DesiredSubnetV6, options: &MigrationOptions) -> &'static str {
+    match options.dnsmasq_v6_mode {
+        DnsmasqV6Mode::Auto => match subnet.ramode.as_deref() {
+            Some("assist") => "slaac",
+            Some("stateless") => "ra-stateless",
+            Some("unmanaged") => "ra-only",
+            Some("managed") | Some("router") | Some("disabled") | None => "",
+            Some(other) => {
+                term::warning(format_args!(
+                    "Unknown ISC ramode '{}' on {}; leaving dnsmasq range mode empty.",
+                    other,
+                    subnet.display_iface()
+                ));
+                ""
+            }
+        },
+        DnsmasqV6Mode::Stateful => "",
+        DnsmasqV6Mode::Static => "static",
+        DnsmasqV6Mode::Slaac => "slaac",
+        DnsmasqV6Mode::RaOnly => "ra-only",
+        DnsmasqV6Mode::RaNames => "ra-names",
+        DnsmasqV6Mode::RaStateless => "ra-stateless",
+        DnsmasqV6Mode::RaAdvrouter => "ra-advrouter",
+    }
+}
+
+/// Start and end addresses written for a created DHCPv6 range. With
+/// `--dnsmasq-constructor` these are host suffixes below `prefix`.
+fn v6_range_bounds(
+    range: &IscRangeV6,
+    prefix: u8,
+    options: &MigrationOptions,
+) -> Result<(String, String)> {
+    if !options.dnsmasq_constructor {
+        return Ok((range.from.clone(), range.to.clone()));
+    }
+    Ok((
+        v6_host_suffix(&range.from, prefix)?,
+        v6_host_suffix(&range.to, prefix)?,
+    ))
+}
+
+fn v6_host_suffix(addr: &str, prefix: u8) -> Result<String> {
+    let ip = parse::ipv6(addr)?;
+    let host_mask = u128::MAX.checked_shr(u32::from(prefix)).unwrap_or(0);
+    Ok(Ipv6Addr::from(u128::from(ip) & host_mask).to_string())
+}
@@ -0,0 +1,114 @@
+//! Positions of created `<dnsmasq>` children.
+//!
+//! The OPNsense dnsmasq model lists its settings first, then hosts, domain
+//! overrides, tags, ranges, options and boot entries, and the GUI expects
+//! the section in that order. Entries are appended as they are created;
+//! this pass moves each one after the last sibling that comes no later in
+//! the model (`opnsense.schema`), leaving existing children where they are.
+
+use std::collections::HashSet;
+use xmltree::{Element, XMLNode};
+
+use crate::migrate::schema::child_order;
+use crate::xml_helpers::find_mut_descendant_ci;
+
+/// Move `<dnsmasq>` children whose `uuid` is not in `existing` into their
+/// model position, keeping the order they were created in.
+pub(crate) fn order_created_children(root: &mut Element, existing: &HashSet<String>) {
+    let Some(dnsmasq) = find_mut_descendant_ci(root, "dnsmasq") else {
+        return;
+    };
+    let order = child_order("dnsmasq");
+    let rank = |node: &XMLNode| {
+        node.as_element().and_then(|el| {
+            order
+                .iter()
+                .position(|name| el.name.eq_ignore_ascii_case(name))
+        })
+    };
+    let is_created = |node: &XMLNode| {
+        node.as_element()
+            .and_then(|el| el.attributes.get("uuid"))
+            .is_some_and(|uuid| !existing.contains(uuid))
+            && rank(node).is_some()
+    };
+    if !dnsmasq.children.iter().any(is_created) {
+        return;
+    }
+
+    let (created, kept): (Vec<XMLNode>, Vec<XMLNode>) = std::mem::take(&mut dnsmasq.children)
+        .into_iter()
+        .partition(is_created);
+    dnsmasq.children = kept;
+    for node in created {
+        let node_rank = rank(&node);
+        let position = match dnsmasq
+            .children
+            .iter()
+            .rposition(|child| rank(child).is_some_and(|r| Some(r) <= node_rank))
+        {
+            Some(last) => last + 1,
+            None => dnsmasq
+                .children
+                .iter()
+                .position(|child| rank(child).is_some())
+                .unwrap_or(dnsmasq.children.len()),
+        };
+        dnsmasq.children.insert(position, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn child_names(root: &Element) -> Vec<String> {
+        root.get_child("dnsmasq")
+            .unwrap()
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .map(|el| match el.attributes.get("uuid") {
+                Some(uuid) => format!("{}:{}", el.name, uuid),
+                None => el.name.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_order_created_children() {
+        let mut root = Element::parse(Cursor::new(
+            r#"<opnsense><dnsmasq>
+                <enable>1</enable>
+                <interface>lan</interface>
+                <hosts uuid="h1"/>
+                <dhcp_ranges uuid="r1"/>
+                <custom_field>x</custom_field>
+                <dhcp_options uuid="n1"/>
+                <hosts uuid="h2"/>
+                <dhcp_ranges uuid="r2"/>
+                <dhcp_tags uuid="t1"/>
+                <hosts uuid="h3"/>
+            </dnsmasq></opnsense>"#,
+        ))
+        .unwrap();
+        let existing = HashSet::from(["h1".to_string(), "r1".to_string()]);
+        order_created_children(&mut root, &existing);
+        assert_eq!(
+            child_names(&root),
+            vec![
+                "enable",
+                "interface",
+                "hosts:h1",
+                "hosts:h2",
+                "hosts:h3",
+                "dhcp_tags:t1",
+                "dhcp_ranges:r1",
+                "dhcp_ranges:r2",
+                "dhcp_options:n1",
+                "custom_field",
+            ]
+        );
+    }
+}
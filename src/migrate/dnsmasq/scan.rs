@@ -2,22 +2,31 @@ use anyhow::{anyhow, Result};
 use xmltree::Element;
 
 use crate::extract::{
-    extract_interface_cidrs, extract_interface_cidrs_v6, extract_isc_options_v4,
-    extract_isc_options_v6,
-};
-use crate::extract_dnsmasq::{
-    extract_existing_dnsmasq_client_ids, extract_existing_dnsmasq_ips,
-    extract_existing_dnsmasq_macs, extract_existing_dnsmasq_ranges, has_dnsmasq,
+    extract_interface_cidrs, extract_interface_cidrs_v6, iter_isc_number_options_v4,
 };
+use crate::extract_dnsmasq::{extract_existing_dnsmasq_ranges, has_dnsmasq};
 use crate::subnet::prefix_to_netmask;
-use crate::{IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions, MigrationStats};
+use crate::term;
+use crate::{
+    IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions, MigrationStats,
+    RangeOverlapPolicy,
+};
 
-use super::range_key;
-use crate::migrate::options::dnsmasq_option_specs_from_isc;
-use crate::migrate::subnets::{
-    cidr_prefix_v4, cidr_prefix_v6, desired_subnets_v4, desired_subnets_v6,
+use super::{
+    cidr_prefix_v4, cidr_prefix_v6, conflicting_ranges, dnsmasq_v6_mode, ensure_no_range_overlaps,
+    range_key, range_overlap_policy, v6_range_bounds, warn_range_conflict,
+};
+use crate::migrate::existing::ExistingState;
+use crate::migrate::hwaddr::drop_non_ethernet_hwaddrs;
+use crate::migrate::option_values::checked_isc_options;
+use crate::migrate::options::{
+    classless_route_specs, dnsmasq_option_specs_from_isc, reconcile_dnsmasq_option_specs,
+};
+use crate::migrate::subnets::{desired_subnets_v4, desired_subnets_v6, merge_desired_ranges};
+use crate::migrate::utils::{
+    conflict_v4, conflict_v6, validate_mapping_ifaces_v4, validate_mapping_ifaces_v6,
+    warn_unmigrated_prefixes,
 };
-use crate::migrate::utils::{validate_mapping_ifaces_v4, validate_mapping_ifaces_v6};
 
 /// Scan an input configuration for dnsmasq migration stats.
 pub(crate) fn scan_dnsmasq(
@@ -26,28 +35,24 @@ pub(crate) fn scan_dnsmasq(
     isc_mappings_v6: &[IscStaticMapV6],
     options: &MigrationOptions,
 ) -> Result<MigrationStats> {
-    let desired_v4 = if options.create_subnets {
+    let mut desired_v4 = if options.create_subnets {
         desired_subnets_v4(root)?
     } else {
         Vec::new()
     };
-    let desired_v6 = if options.create_subnets {
+    let mut desired_v6 = if options.create_subnets {
         desired_subnets_v6(root)?
     } else {
         Vec::new()
     };
-    let options_v4 = if options.create_options {
-        extract_isc_options_v4(root)?
-    } else {
-        Vec::new()
-    };
-    let options_v6 = if options.create_options {
-        extract_isc_options_v6(root)?
-    } else {
-        Vec::new()
-    };
+    let ranges_merged = merge_desired_ranges(&mut desired_v4, &mut desired_v6, options);
+    let (options_v4, options_v6, option_values) = checked_isc_options(root, options);
     let desired_options = if options.create_options {
-        dnsmasq_option_specs_from_isc(&options_v4, &options_v6)
+        let number_options: Vec<_> = iter_isc_number_options_v4(root).collect();
+        let mut specs = dnsmasq_option_specs_from_isc(&options_v4, &options_v6);
+        reconcile_dnsmasq_option_specs(&mut specs, options);
+        specs.extend(classless_route_specs(&number_options));
+        specs
     } else {
         Vec::new()
     };
@@ -67,33 +72,43 @@ pub(crate) fn scan_dnsmasq(
         .into());
     }
 
-    let existing_ips = extract_existing_dnsmasq_ips(root)?;
-    let existing_macs = extract_existing_dnsmasq_macs(root)?;
-    let existing_client_ids = extract_existing_dnsmasq_client_ids(root)?;
+    let existing = ExistingState::dnsmasq(root);
     let existing_ranges = extract_existing_dnsmasq_ranges(root)?;
 
     if options.fail_if_existing
-        && (!existing_ips.is_empty()
-            || !existing_macs.is_empty()
-            || !existing_client_ids.is_empty()
+        && (!existing.ips.is_empty()
+            || !existing.macs.is_empty()
+            || !existing.client_ids.is_empty()
             || (options.create_subnets && !existing_ranges.is_empty()))
     {
         return Err(anyhow!(
             "Existing dnsmasq hosts found ({} entries) and --fail-if-existing is set. Aborting.",
-            existing_ips.len()
+            existing.ips.len()
         ));
     }
 
+    if options.create_subnets {
+        ensure_no_range_overlaps(&existing_ranges, &desired_v4, &desired_v6, options)?;
+    }
+
     let mut to_create = 0;
-    let mut skipped = 0;
+    let total_v4 = isc_mappings.len();
+    let mut isc_mappings = isc_mappings.to_vec();
+    let mut skipped = drop_non_ethernet_hwaddrs(&mut isc_mappings);
     let mut to_create_v6 = 0;
     let mut skipped_v6 = 0;
-    let mut reserved_ips = existing_ips;
-    let mut reserved_macs = existing_macs;
-    let mut reserved_client_ids = existing_client_ids;
+    let mut skips = Vec::new();
+    let ExistingState {
+        ips: mut reserved_ips,
+        macs: mut reserved_macs,
+        client_ids: mut reserved_client_ids,
+        ..
+    } = existing;
 
-    validate_mapping_ifaces_v4(isc_mappings, &iface_cidrs_v4)?;
+    validate_mapping_ifaces_v4(&isc_mappings, &iface_cidrs_v4)?;
     validate_mapping_ifaces_v6(isc_mappings_v6, &iface_cidrs_v6)?;
+    let prefixes_v6_unmigrated =
+        warn_unmigrated_prefixes(isc_mappings_v6, "dnsmasq", false, options);
 
     if options.verbose {
         println!(
@@ -102,18 +117,29 @@ pub(crate) fn scan_dnsmasq(
         );
     }
 
-    for mapping in isc_mappings {
-        if reserved_ips.contains(&mapping.ipaddr) || reserved_macs.contains(&mapping.mac) {
+    for mapping in &isc_mappings {
+        if let Some(conflict) = conflict_v4(
+            mapping,
+            &reserved_ips,
+            &reserved_macs,
+            &reserved_client_ids,
+            true,
+        ) {
             skipped += 1;
+            skips.push(conflict);
             if options.verbose {
-                println!(
-                    "  SKIP: {} ({}) - IP or MAC already exists in dnsmasq",
-                    mapping.ipaddr, mapping.mac
+                term::action(
+                    "SKIP",
+                    format_args!(
+                        "{} ({}) - IP, MAC or client ID already exists in dnsmasq",
+                        mapping.ipaddr, mapping.mac
+                    ),
                 );
             }
         } else {
             reserved_ips.insert(mapping.ipaddr.clone());
             reserved_macs.insert(mapping.mac.clone());
+            reserved_client_ids.extend(mapping.cid.clone());
             to_create += 1;
             if options.verbose {
                 let hostname = mapping
@@ -122,26 +148,42 @@ pub(crate) fn scan_dnsmasq(
                     .or(mapping.cid.as_ref())
                     .map(|s| s.as_str())
                     .unwrap_or("<no hostname>");
-                println!("  ADD: {} ({}) [{}]", mapping.ipaddr, mapping.mac, hostname);
+                term::action(
+                    "ADD",
+                    format_args!("{} ({}) [{}]", mapping.ipaddr, mapping.mac, hostname),
+                );
             }
         }
     }
 
     if options.create_subnets {
+        let policy = range_overlap_policy(options);
         for subnet in &desired_v4 {
             let prefix = cidr_prefix_v4(&subnet.cidr)?;
             let mask = prefix_to_netmask(prefix)?;
             for range in &subnet.ranges {
                 let key = range_key(&subnet.iface, &range.from, &range.to, "", &mask);
-                if existing_ranges.contains(&key) {
-                    eprintln!(
-                        "Warning: dnsmasq range {}-{} already exists (iface {}). Skipping.",
-                        range.from, range.to, subnet.iface
+                let conflicts = conflicting_ranges(
+                    &existing_ranges,
+                    &subnet.iface,
+                    &range.from,
+                    &range.to,
+                    &key,
+                );
+                if !conflicts.is_empty() && policy != RangeOverlapPolicy::Force {
+                    warn_range_conflict(
+                        &range.from,
+                        &range.to,
+                        &subnet.display_iface(),
+                        &key,
+                        &conflicts,
                     );
                 } else if options.verbose {
                     println!(
                         "  ADD-RANGE: {}-{} (iface {})",
-                        range.from, range.to, subnet.iface
+                        range.from,
+                        range.to,
+                        subnet.display_iface()
                     );
                 }
             }
@@ -149,23 +191,27 @@ pub(crate) fn scan_dnsmasq(
 
         for subnet in &desired_v6 {
             let prefix = cidr_prefix_v6(&subnet.cidr)?;
+            let mode = dnsmasq_v6_mode(subnet, options);
             for range in &subnet.ranges {
-                let key = range_key(
-                    &subnet.iface,
-                    &range.from,
-                    &range.to,
-                    &prefix.to_string(),
-                    "",
-                );
-                if existing_ranges.contains(&key) {
-                    eprintln!(
-                        "Warning: dnsmasq range {}-{} already exists (iface {}). Skipping.",
-                        range.from, range.to, subnet.iface
+                let (start, end) = v6_range_bounds(range, prefix, options)?;
+                let key = range_key(&subnet.iface, &start, &end, &prefix.to_string(), "");
+                let conflicts =
+                    conflicting_ranges(&existing_ranges, &subnet.iface, &start, &end, &key);
+                if !conflicts.is_empty() && policy != RangeOverlapPolicy::Force {
+                    warn_range_conflict(
+                        &range.from,
+                        &range.to,
+                        &subnet.display_iface(),
+                        &key,
+                        &conflicts,
                     );
                 } else if options.verbose {
                     println!(
-                        "  ADD-RANGE6: {}-{} (iface {})",
-                        range.from, range.to, subnet.iface
+                        "  ADD-RANGE6: {}-{} (iface {}, mode {})",
+                        range.from,
+                        range.to,
+                        subnet.display_iface(),
+                        if mode.is_empty() { "stateful" } else { mode }
                     );
                 }
             }
@@ -180,12 +226,16 @@ pub(crate) fn scan_dnsmasq(
     }
 
     for mapping in isc_mappings_v6 {
-        if reserved_ips.contains(&mapping.ipaddr) || reserved_client_ids.contains(&mapping.duid) {
+        if let Some(conflict) = conflict_v6(mapping, &reserved_ips, &reserved_client_ids) {
             skipped_v6 += 1;
+            skips.push(conflict);
             if options.verbose {
-                println!(
-                    "  SKIP6: {} ({}) - IP or DUID already exists in dnsmasq",
-                    mapping.ipaddr, mapping.duid
+                term::action(
+                    "SKIP6",
+                    format_args!(
+                        "{} ({}) - IP or DUID already exists in dnsmasq",
+                        mapping.ipaddr, mapping.duid
+                    ),
                 );
             }
         } else {
@@ -194,25 +244,30 @@ pub(crate) fn scan_dnsmasq(
             to_create_v6 += 1;
             if options.verbose {
                 let hostname = mapping.hostname.as_deref().unwrap_or("<no hostname>");
-                println!(
-                    "  ADD6: {} ({}) [{}]",
-                    mapping.ipaddr, mapping.duid, hostname
+                term::action(
+                    "ADD6",
+                    format_args!("{} ({}) [{}]", mapping.ipaddr, mapping.duid, hostname),
                 );
             }
         }
     }
 
     Ok(MigrationStats {
-        isc_mappings_found: isc_mappings.len(),
+        option_values_resolved: option_values.resolved,
+        unresolved_option_values: option_values.unresolved,
+        isc_mappings_found: total_v4,
         isc_mappings_v6_found: isc_mappings_v6.len(),
         isc_ranges_found: 0,
         isc_ranges_v6_found: 0,
+        ranges_merged,
         target_subnets_found: 0,
         target_subnets_v6_found: 0,
         reservations_to_create: to_create,
         reservations_v6_to_create: to_create_v6,
         reservations_skipped: skipped,
         reservations_v6_skipped: skipped_v6,
+        skips,
+        prefixes_v6_unmigrated,
         ..Default::default()
     })
 }
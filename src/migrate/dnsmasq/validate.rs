@@ -0,0 +1,212 @@
+//! Checks that created dnsmasq hosts will be accepted by OPNsense.
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use xmltree::Element;
+
+use crate::extract::{extract_interface_cidrs, extract_interface_cidrs_v6};
+use crate::extract_dnsmasq::{extract_existing_dnsmasq_ranges, DnsmasqRange};
+use crate::parse;
+use crate::subnet::{ip_in_subnet, ip_in_subnet_v6, netmask_to_prefix};
+use crate::xml_helpers::{find_descendant_ci, get_child_ci};
+use crate::{DnsmasqHostIssue, MigrationError};
+
+/// `uuid`s of the dnsmasq hosts currently in the config.
+pub(crate) fn dnsmasq_host_uuids(root: &Element) -> HashSet<String> {
+    dnsmasq_hosts(root)
+        .filter_map(|host| host.attributes.get("uuid").cloned())
+        .collect()
+}
+
+/// Validate dnsmasq hosts whose `uuid` is not in `existing`.
+///
+/// Each created host must have a unique host+domain among all hosts, a
+/// well-formed `hwaddr`, and (when it is a DHCP host and ranges of its
+/// address family exist) an IP inside the network of some dnsmasq range.
+/// Every problem is reported in one [`MigrationError::InvalidDnsmasqHosts`].
+pub(crate) fn validate_dnsmasq_hosts(root: &Element, existing: &HashSet<String>) -> Result<()> {
+    let networks = range_networks(root)?;
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut created = Vec::new();
+    for host in dnsmasq_hosts(root) {
+        let is_new = host
+            .attributes
+            .get("uuid")
+            .is_none_or(|uuid| !existing.contains(uuid));
+        if is_new {
+            created.push(host);
+        } else if let Some(fqdn) = fqdn(host) {
+            names.entry(fqdn).or_insert_with(|| text(host, "ip"));
+        }
+    }
+
+    let mut issues = Vec::new();
+    for host in created {
+        let ip = text(host, "ip");
+        let name = fqdn(host).unwrap_or_else(|| ip.clone());
+
+        if let Some(fqdn) = fqdn(host) {
+            match names.get(&fqdn) {
+                Some(other_ip) => issues.push(DnsmasqHostIssue::DuplicateName {
+                    fqdn,
+                    ip: ip.clone(),
+                    other_ip: other_ip.clone(),
+                }),
+                None => {
+                    names.insert(fqdn, ip.clone());
+                }
+            }
+        }
+
+        let hwaddr = text(host, "hwaddr");
+        if !hwaddr.is_empty() && !valid_hwaddr(&hwaddr) {
+            issues.push(DnsmasqHostIssue::InvalidHwaddr {
+                host: name.clone(),
+                hwaddr: hwaddr.clone(),
+            });
+        }
+
+        let is_dhcp = !hwaddr.is_empty() || !text(host, "client_id").is_empty();
+        if is_dhcp {
+            for addr in ip.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+                if !networks.covers(addr) {
+                    issues.push(DnsmasqHostIssue::OutsideRanges {
+                        host: name.clone(),
+                        ip: addr.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+    Err(MigrationError::InvalidDnsmasqHosts { issues }.into())
+}
+
+fn dnsmasq_hosts(root: &Element) -> impl Iterator<Item = &Element> {
+    find_descendant_ci(root, "dnsmasq")
+        .into_iter()
+        .flat_map(|dnsmasq| dnsmasq.children.iter())
+        .filter_map(|n| n.as_element())
+        .filter(|e| e.name.eq_ignore_ascii_case("hosts"))
+}
+
+/// Lowercase `host.domain` for hosts registered in DNS (non-empty host).
+fn fqdn(host: &Element) -> Option<String> {
+    let name = text(host, "host");
+    if name.is_empty() {
+        return None;
+    }
+    let domain = text(host, "domain");
+    let fqdn = if domain.is_empty() {
+        name
+    } else {
+        format!("{}.{}", name, domain)
+    };
+    Some(fqdn.to_ascii_lowercase())
+}
+
+/// One or more comma-separated MACs of six `:`-separated hex octets; `*`
+/// is accepted as a dnsmasq wildcard octet.
+fn valid_hwaddr(hwaddr: &str) -> bool {
+    hwaddr.split(',').map(str::trim).all(|mac| {
+        let octets: Vec<&str> = mac.split(':').collect();
+        octets.len() == 6
+            && octets.iter().all(|octet| {
+                *octet == "*" || (octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()))
+            })
+    })
+}
+
+/// Networks served by the dnsmasq ranges, per address family.
+struct RangeNetworks {
+    v4: Vec<String>,
+    v6: Vec<String>,
+}
+
+impl RangeNetworks {
+    /// Whether `ip` lies in a range network. A family with no ranges is not
+    /// checked, since its hosts may be served once ranges are added.
+    fn covers(&self, ip: &str) -> bool {
+        if ip.contains(':') {
+            self.v6.is_empty()
+                || self
+                    .v6
+                    .iter()
+                    .any(|cidr| ip_in_subnet_v6(ip, cidr).unwrap_or(false))
+        } else {
+            self.v4.is_empty()
+                || self
+                    .v4
+                    .iter()
+                    .any(|cidr| ip_in_subnet(ip, cidr).unwrap_or(false))
+        }
+    }
+}
+
+/// The network of each range: its interface subnet, or failing that its
+/// start address with `subnet_mask`/`prefix_len`.
+fn range_networks(root: &Element) -> Result<RangeNetworks> {
+    let cidrs_v4 = extract_interface_cidrs(root)?;
+    let cidrs_v6 = extract_interface_cidrs_v6(root)?;
+    let mut networks = RangeNetworks {
+        v4: Vec::new(),
+        v6: Vec::new(),
+    };
+    for range in extract_existing_dnsmasq_ranges(root)? {
+        if range.start.contains(':') {
+            if let Some(cidr) = cidrs_v6
+                .get(&range.iface)
+                .cloned()
+                .or_else(|| range_cidr_v6(&range))
+            {
+                networks.v6.push(cidr);
+            }
+        } else if let Some(cidr) = cidrs_v4
+            .get(&range.iface)
+            .cloned()
+            .or_else(|| range_cidr_v4(&range))
+        {
+            networks.v4.push(cidr);
+        }
+    }
+    Ok(networks)
+}
+
+fn range_cidr_v4(range: &DnsmasqRange) -> Option<String> {
+    let start = parse::ipv4(&range.start).ok()?;
+    let prefix = netmask_to_prefix(&range.subnet_mask).ok()?;
+    let net = ipnet::Ipv4Net::new(start, prefix).ok()?;
+    Some(format!("{}/{}", net.network(), prefix))
+}
+
+fn range_cidr_v6(range: &DnsmasqRange) -> Option<String> {
+    let start = parse::ipv6(&range.start).ok()?;
+    let prefix: u8 = range.prefix_len.parse().ok()?;
+    let net = ipnet::Ipv6Net::new(start, prefix).ok()?;
+    Some(format!("{}/{}", net.network(), prefix))
+}
+
+fn text(el: &Element, name: &str) -> String {
+    get_child_ci(el, name)
+        .and_then(|e| e.get_text())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_hwaddr() {
+        assert!(valid_hwaddr("00:11:22:aa:BB:cc"));
+        assert!(valid_hwaddr("00:11:22:*:*:*"));
+        assert!(valid_hwaddr("00:11:22:33:44:55,00:11:22:33:44:56"));
+        assert!(!valid_hwaddr("00-11-22-33-44-55"));
+        assert!(!valid_hwaddr("00:11:22:33:44"));
+        assert!(!valid_hwaddr("00:11:22:33:44:5g"));
+    }
+}
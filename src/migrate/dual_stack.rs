@@ -0,0 +1,114 @@
+//! Dual-stack hosts (`--link-dual-stack`).
+//!
+//! ISC keeps DHCPv4 and DHCPv6 static mappings apart, so a host with both
+//! ends up as two unrelated reservations. A DHCPv6 mapping is paired with
+//! the DHCPv4 mapping of the same host: the one whose MAC is embedded in
+//! its DUID (DUID-LLT or DUID-LL), or failing that the one with the same
+//! hostname. Both mappings are still migrated; each description names the
+//! other address so the pair stays recognisable afterwards.
+
+use crate::parse;
+use crate::term;
+use crate::{IscStaticMap, IscStaticMapV6, MigrationOptions};
+
+/// DUID types that carry a link-layer address, and its offset.
+const DUID_LLT: (u16, usize) = (1, 8);
+const DUID_LL: (u16, usize) = (3, 4);
+/// Ethernet hardware type in a DUID.
+const HW_ETHERNET: u16 = 1;
+
+/// Note the paired address in the descriptions of every dual-stack host
+/// and return how many DHCPv6 mappings were paired.
+pub(crate) fn link_dual_stack(
+    mappings: &mut [IscStaticMap],
+    mappings_v6: &mut [IscStaticMapV6],
+    options: &MigrationOptions,
+) -> usize {
+    let mut linked = 0;
+    for mapping_v6 in mappings_v6.iter_mut() {
+        let by_mac = duid_mac(&mapping_v6.duid).and_then(|mac| {
+            mappings
+                .iter()
+                .position(|m| parse::hwaddr(&m.mac).ok().as_deref() == Some(&mac[..]))
+        });
+        let by_name = || {
+            let name = short_name(mapping_v6.hostname.as_deref()?);
+            mappings.iter().position(|m| {
+                m.hostname
+                    .as_deref()
+                    .is_some_and(|h| short_name(h).eq_ignore_ascii_case(name))
+            })
+        };
+        let (idx, how) = match by_mac {
+            Some(idx) => (idx, "MAC"),
+            None => match by_name() {
+                Some(idx) => (idx, "hostname"),
+                None => continue,
+            },
+        };
+        let mapping = &mut mappings[idx];
+        if options.verbose {
+            term::action(
+                "DUAL",
+                format_args!(
+                    "{} <-> {} (same {})",
+                    mapping.ipaddr, mapping_v6.ipaddr, how
+                ),
+            );
+        }
+        append_note(&mut mapping.descr, &mapping_v6.ipaddr);
+        append_note(&mut mapping_v6.descr, &mapping.ipaddr);
+        linked += 1;
+    }
+    linked
+}
+
+/// The Ethernet address in a DUID-LLT or DUID-LL.
+fn duid_mac(duid: &str) -> Option<Vec<u8>> {
+    let octets = parse::duid(duid).ok()?;
+    let kind = u16::from_be_bytes([*octets.first()?, *octets.get(1)?]);
+    let hw_type = u16::from_be_bytes([*octets.get(2)?, *octets.get(3)?]);
+    let offset = [DUID_LLT, DUID_LL]
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map(|(_, offset)| *offset)?;
+    let mac = octets.get(offset..)?;
+    (hw_type == HW_ETHERNET && mac.len() == 6).then(|| mac.to_vec())
+}
+
+fn short_name(hostname: &str) -> &str {
+    hostname.split('.').next().unwrap_or(hostname)
+}
+
+fn append_note(descr: &mut Option<String>, other_ip: &str) {
+    let note = format!("dual-stack: {}", other_ip);
+    *descr = Some(match descr.take().filter(|d| !d.trim().is_empty()) {
+        Some(existing) => format!("{}; {}", existing, note),
+        None => note,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duid_mac() {
+        let mac = Some(vec![0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        assert_eq!(duid_mac("00:01:00:01:2a:3b:4c:5d:00:11:22:33:44:55"), mac);
+        assert_eq!(duid_mac("00:03:00:01:00:11:22:33:44:55"), mac);
+        // DUID-EN carries no link-layer address
+        assert_eq!(duid_mac("00:02:00:00:ab:11:00:11:22:33"), None);
+        assert_eq!(duid_mac("not-a-duid"), None);
+    }
+
+    #[test]
+    fn test_append_note() {
+        let mut descr = None;
+        append_note(&mut descr, "192.168.1.10");
+        assert_eq!(descr.as_deref(), Some("dual-stack: 192.168.1.10"));
+        let mut descr = Some("Printer".to_string());
+        append_note(&mut descr, "2001:db8::10");
+        assert_eq!(descr.as_deref(), Some("Printer; dual-stack: 2001:db8::10"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Entries a backend already has, indexed once per run.
+//!
+//! Duplicate checks compare every ISC mapping against the existing Kea
+//! reservations or dnsmasq hosts. An [`ExistingState`] reads a backend's
+//! entries in one pass and indexes them by address, hardware address,
+//! client identifier, DUID, hostname and subnet, so each check is a lookup
+//! however many entries the config holds.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use xmltree::Element;
+
+#[cfg(feature = "kea")]
+use crate::extract::{kea_reservations_v4, kea_reservations_v6};
+#[cfg(feature = "dnsmasq")]
+use crate::extract_dnsmasq::dnsmasq_children;
+#[cfg(feature = "kea")]
+use crate::migrate::hwaddr::normalize_hwaddr;
+#[cfg(feature = "dnsmasq")]
+use crate::model::DnsmasqHostNode;
+#[cfg(feature = "kea")]
+use crate::{IscStaticMapV6, MigrationError};
+
+/// Lowercase hostname to the name as first written and its addresses, in
+/// the order they were found.
+pub(crate) type HostnameIndex = BTreeMap<String, (String, Vec<String>)>;
+
+/// The entries of one backend, by the values duplicates are found on.
+#[derive(Debug, Default)]
+pub(crate) struct ExistingState {
+    /// Kea DHCPv4 reservation addresses, or the address of every dnsmasq host
+    pub(crate) ips: HashSet<String>,
+    /// Hardware addresses, in Kea's form for Kea
+    pub(crate) macs: HashSet<String>,
+    /// DHCPv4 client identifiers; dnsmasq keeps DUIDs here too
+    pub(crate) client_ids: HashSet<String>,
+    /// Kea DHCPv6 reservations by subnet `uuid`
+    #[cfg_attr(not(feature = "kea"), allow(dead_code))]
+    pub(crate) subnets_v6: HashMap<String, SubnetEntries>,
+    pub(crate) hostnames: HostnameIndex,
+}
+
+/// Addresses and DUIDs reserved in one Kea DHCPv6 subnet.
+#[cfg_attr(not(feature = "kea"), allow(dead_code))]
+#[derive(Debug, Default)]
+pub(crate) struct SubnetEntries {
+    pub(crate) ips: HashSet<String>,
+    pub(crate) duids: HashSet<String>,
+}
+
+impl ExistingState {
+    /// The Kea DHCPv4 and DHCPv6 reservations of `root`.
+    #[cfg(feature = "kea")]
+    pub(crate) fn kea(root: &Element) -> Self {
+        let mut state = ExistingState::default();
+        for reservation in kea_reservations_v4(root) {
+            if !reservation.ip_address.is_empty() {
+                state.add_hostname(reservation.hostname.as_deref(), &reservation.ip_address);
+                state.ips.insert(reservation.ip_address);
+            }
+            let mac = reservation.hw_address.trim();
+            if !mac.is_empty() {
+                state.macs.insert(normalize_hwaddr(mac));
+            }
+            if let Some(cid) = reservation.client_id.as_deref().map(str::trim) {
+                if !cid.is_empty() {
+                    state.client_ids.insert(cid.to_string());
+                }
+            }
+        }
+        for reservation in kea_reservations_v6(root) {
+            let subnet = state.subnets_v6.entry(reservation.subnet).or_default();
+            if !reservation.duid.is_empty() {
+                subnet.duids.insert(reservation.duid);
+            }
+            if !reservation.ip_address.is_empty() {
+                subnet.ips.insert(reservation.ip_address.clone());
+                state.add_hostname(reservation.hostname.as_deref(), &reservation.ip_address);
+            }
+        }
+        state
+    }
+
+    /// The dnsmasq hosts of `root`.
+    #[cfg(feature = "dnsmasq")]
+    pub(crate) fn dnsmasq(root: &Element) -> Self {
+        let mut state = ExistingState::default();
+        for host in dnsmasq_children(root).filter_map(DnsmasqHostNode::from_element) {
+            // One host may carry an IPv4 and an IPv6 address
+            for ip in host.ip.split(',') {
+                state.add_hostname(Some(&host.host), ip);
+            }
+            if !host.ip.is_empty() {
+                state.ips.insert(host.ip);
+            }
+            if !host.hwaddr.is_empty() {
+                state.macs.insert(host.hwaddr);
+            }
+            if let Some(cid) = host.client_id.filter(|cid| !cid.is_empty()) {
+                state.client_ids.insert(cid);
+            }
+        }
+        state
+    }
+
+    fn add_hostname(&mut self, hostname: Option<&str>, ip: &str) {
+        let (Some(hostname), ip) = (hostname.map(str::trim), ip.trim()) else {
+            return;
+        };
+        if hostname.is_empty() || ip.is_empty() {
+            return;
+        }
+        let (_, ips) = self
+            .hostnames
+            .entry(hostname.to_ascii_lowercase())
+            .or_insert_with(|| (hostname.to_string(), Vec::new()));
+        if !ips.iter().any(|seen| seen == ip) {
+            ips.push(ip.to_string());
+        }
+    }
+}
+
+/// Kea DHCPv6 reservations keyed for duplicate detection.
+///
+/// Kea scopes host reservations to their subnet, so one DUID may be
+/// reserved on several networks. Entries are keyed by subnet `uuid` and
+/// DUID, and by subnet `uuid` and address; a `global` registry leaves the
+/// subnet out, so a DUID or address reserved anywhere is taken.
+#[cfg(feature = "kea")]
+#[derive(Debug, Default)]
+pub(crate) struct DuidRegistryV6 {
+    global: bool,
+    ips: HashSet<(String, String)>,
+    duids: HashSet<(String, String)>,
+}
+
+#[cfg(feature = "kea")]
+impl DuidRegistryV6 {
+    /// Registry of the reservations in `existing`, scoped to their subnet
+    /// unless `global`.
+    pub(crate) fn new(existing: &ExistingState, global: bool) -> Self {
+        let mut registry = DuidRegistryV6 {
+            global,
+            ..Default::default()
+        };
+        for (subnet, entries) in &existing.subnets_v6 {
+            let scope = registry.scope(subnet);
+            registry
+                .ips
+                .extend(entries.ips.iter().map(|ip| (scope.clone(), ip.clone())));
+            registry.duids.extend(
+                entries
+                    .duids
+                    .iter()
+                    .map(|duid| (scope.clone(), duid.clone())),
+            );
+        }
+        registry
+    }
+
+    fn scope(&self, subnet: &str) -> String {
+        if self.global {
+            String::new()
+        } else {
+            subnet.to_string()
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ips.is_empty() && self.duids.is_empty()
+    }
+
+    /// Reserved addresses, counting each once per scope
+    pub(crate) fn ip_count(&self) -> usize {
+        self.ips.len()
+    }
+
+    /// Why `mapping` cannot be reserved in `subnet`: its IP, then its DUID
+    /// already taken there.
+    pub(crate) fn conflict(
+        &self,
+        mapping: &IscStaticMapV6,
+        subnet: &str,
+    ) -> Option<MigrationError> {
+        let scope = self.scope(subnet);
+        if self.ips.contains(&(scope.clone(), mapping.ipaddr.clone())) {
+            return Some(MigrationError::DuplicateIp {
+                ip: mapping.ipaddr.clone(),
+            });
+        }
+        self.duids
+            .contains(&(scope, mapping.duid.clone()))
+            .then(|| MigrationError::DuplicateDuid {
+                ip: mapping.ipaddr.clone(),
+                duid: mapping.duid.clone(),
+            })
+    }
+
+    /// Record `mapping` as reserved in `subnet`.
+    pub(crate) fn insert(&mut self, mapping: &IscStaticMapV6, subnet: &str) {
+        let scope = self.scope(subnet);
+        self.ips.insert((scope.clone(), mapping.ipaddr.clone()));
+        self.duids.insert((scope, mapping.duid.clone()));
+    }
+}
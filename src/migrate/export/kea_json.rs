@@ -0,0 +1,575 @@
+//! Kea JSON configuration (`kea-dhcp4.conf` / `kea-dhcp6.conf`) renderer.
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+use xmltree::Element;
+
+use super::option_defs::{option_data, option_defs, option_type, OptionDef};
+use super::{duid_hex_digits, mapping_name, ExportCounts, ExportData, ExportFile};
+use crate::extract::{
+    extract_interface_cidrs, extract_interface_cidrs_v6, iter_isc_options_v6, IscNetbootV4,
+};
+use crate::migrate::options::{dnsmasq_option_specs_from_isc, DnsmasqOptionSpec};
+use crate::migrate::subnets::{desired_subnets_v6, merge_ranges_v6};
+use crate::subnet::{ip_in_subnet, ip_in_subnet_v6};
+use crate::term;
+use crate::{IscNumberOption, MigrationOptions};
+
+/// Rendered Kea config: the main file and, when split, one include per subnet.
+pub(crate) struct KeaRendered {
+    pub(crate) main: String,
+    pub(crate) includes: Vec<ExportFile>,
+    pub(crate) counts: ExportCounts,
+}
+
+/// Render a `Dhcp4` (or with `v6`, `Dhcp6`) config holding one subnet per
+/// ISC interface with ranges or static mappings.
+///
+/// Subnets use the interface CIDR, its ISC ranges as pools, its ISC options
+/// as `option-data`, and its mappings as reservations. Custom numbered
+/// DHCPv4 options Kea has no standard definition for get a global
+/// `option-def` so Kea accepts their data. With `split_out`,
+/// each subnet goes to its own file next to it and the main config
+/// `<?include?>`s them in order.
+pub(crate) fn render(
+    root: &Element,
+    data: &ExportData,
+    v6: bool,
+    split_out: Option<&Path>,
+    options: &MigrationOptions,
+) -> Result<KeaRendered> {
+    let mut counts = ExportCounts::default();
+    let defs = if v6 {
+        Vec::new()
+    } else {
+        option_defs(&data.number_options)
+    };
+    let subnets = if v6 {
+        subnets_v6(root, data, options, &mut counts)?
+    } else {
+        subnets_v4(root, data, &defs, options, &mut counts)?
+    };
+    counts.subnets = subnets.len();
+
+    let mut includes = Vec::new();
+    let entries = subnets
+        .into_iter()
+        .map(|(iface, subnet)| match split_out {
+            Some(out) => {
+                let path = include_path(out, includes.len() + 1, &iface);
+                includes.push(ExportFile {
+                    path: path.clone(),
+                    contents: format!("{}\n", subnet.render(0)),
+                });
+                Json::Raw(format!("<?include \"{}\"?>", path.display()))
+            }
+            None => subnet,
+        })
+        .collect();
+
+    let (daemon, key) = if v6 {
+        ("Dhcp6", "subnet6")
+    } else {
+        ("Dhcp4", "subnet4")
+    };
+    let mut fields = Vec::new();
+    if !defs.is_empty() {
+        counts.option_defs = defs.len();
+        fields.push(field(
+            "option-def",
+            Json::Arr(defs.iter().map(option_def).collect()),
+        ));
+    }
+    fields.push(field(key, Json::Arr(entries)));
+    let doc = Json::Obj(vec![(daemon.to_string(), Json::Obj(fields))]);
+    let main = format!(
+        "// Kea {} configuration exported by isc2kea {} from ISC DHCP.\n\
+         // Add interfaces-config and lease-database before use.\n{}\n",
+        daemon,
+        env!("CARGO_PKG_VERSION"),
+        doc.render(0)
+    );
+
+    Ok(KeaRendered {
+        main,
+        includes,
+        counts,
+    })
+}
+
+/// Subnets keyed by interface, in interface order, with ids assigned.
+type Subnets = Vec<(String, Json)>;
+
+struct SubnetBuilder {
+    cidr: String,
+    descr: Option<String>,
+    netboot: Option<IscNetbootV4>,
+    pools: Vec<String>,
+    reservations: Vec<Json>,
+}
+
+fn subnets_v4(
+    root: &Element,
+    data: &ExportData,
+    defs: &[OptionDef],
+    options: &MigrationOptions,
+    counts: &mut ExportCounts,
+) -> Result<Subnets> {
+    let cidrs = extract_interface_cidrs(root)?;
+    let mut builders: BTreeMap<String, SubnetBuilder> = BTreeMap::new();
+    for subnet in &data.subnets {
+        builders.insert(
+            subnet.iface.clone(),
+            SubnetBuilder {
+                cidr: subnet.cidr.clone(),
+                descr: subnet.descr.clone(),
+                netboot: subnet.netboot.clone(),
+                pools: subnet
+                    .ranges
+                    .iter()
+                    .map(|r| format!("{} - {}", r.from, r.to))
+                    .collect(),
+                reservations: Vec::new(),
+            },
+        );
+    }
+
+    for mapping in &data.mappings {
+        let Some(builder) = builder_for(&mut builders, &mapping.iface, &cidrs, data) else {
+            term::warning(format_args!(
+                "No IPv4 subnet for interface {}; not exporting {}.",
+                mapping.iface, mapping.ipaddr
+            ));
+            counts.skipped += 1;
+            continue;
+        };
+        if !ip_in_subnet(&mapping.ipaddr, &builder.cidr).unwrap_or(false) {
+            term::warning(format_args!(
+                "{} is outside {} on {}; not exporting it.",
+                mapping.ipaddr, builder.cidr, mapping.iface
+            ));
+            counts.skipped += 1;
+            continue;
+        }
+        let mut fields = vec![
+            field("hw-address", Json::str(mapping.mac.to_ascii_lowercase())),
+            field("ip-address", Json::str(&mapping.ipaddr)),
+        ];
+        if let Some(name) = mapping_name(mapping) {
+            fields.push(field("hostname", Json::str(name)));
+        }
+        push_description(&mut fields, mapping.descr.as_deref());
+        let host_options: Vec<Json> = [
+            ("domain-name-servers", mapping.dns_servers.join(",")),
+            ("routers", mapping.gateway.clone().unwrap_or_default()),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| {
+            Json::Obj(vec![
+                field("name", Json::str(name)),
+                field("data", Json::str(value)),
+            ])
+        })
+        .collect();
+        if !host_options.is_empty() {
+            fields.push(field("option-data", Json::Arr(host_options)));
+        }
+        if options.verbose {
+            term::action(
+                "EXPORT",
+                format_args!("{} -> {}", mapping.mac, mapping.ipaddr),
+            );
+        }
+        builder.reservations.push(Json::Obj(fields));
+        counts.reservations += 1;
+    }
+
+    let specs = dnsmasq_option_specs_from_isc(&data.options, &[]);
+    let custom = custom_option_data(&data.number_options, &specs, defs, counts);
+    Ok(finish(builders, &specs, &custom, data, false))
+}
+
+fn subnets_v6(
+    root: &Element,
+    data: &ExportData,
+    options: &MigrationOptions,
+    counts: &mut ExportCounts,
+) -> Result<Subnets> {
+    let cidrs = extract_interface_cidrs_v6(root)?;
+    let mut builders: BTreeMap<String, SubnetBuilder> = BTreeMap::new();
+    let mut subnets = desired_subnets_v6(root)?;
+    if options.merge_ranges {
+        counts.ranges_merged = merge_ranges_v6(&mut subnets, options);
+    }
+    for subnet in subnets {
+        builders.insert(
+            subnet.iface.clone(),
+            SubnetBuilder {
+                cidr: subnet.cidr.clone(),
+                descr: subnet.descr.clone(),
+                netboot: None,
+                pools: subnet
+                    .ranges
+                    .iter()
+                    .map(|r| format!("{} - {}", r.from, r.to))
+                    .collect(),
+                reservations: Vec::new(),
+            },
+        );
+    }
+
+    for mapping in &data.mappings_v6 {
+        if duid_hex_digits(&mapping.duid).is_none() {
+            term::warning(format_args!(
+                "DUID '{}' for {} is not hex; not exporting it.",
+                mapping.duid, mapping.ipaddr
+            ));
+            counts.skipped_v6 += 1;
+            continue;
+        }
+        let Some(builder) = builder_for(&mut builders, &mapping.iface, &cidrs, data) else {
+            term::warning(format_args!(
+                "No IPv6 subnet for interface {}; not exporting {}.",
+                mapping.iface, mapping.ipaddr
+            ));
+            counts.skipped_v6 += 1;
+            continue;
+        };
+        if !ip_in_subnet_v6(&mapping.ipaddr, &builder.cidr).unwrap_or(false) {
+            term::warning(format_args!(
+                "{} is outside {} on {}; not exporting it.",
+                mapping.ipaddr, builder.cidr, mapping.iface
+            ));
+            counts.skipped_v6 += 1;
+            continue;
+        }
+        let mut fields = vec![
+            field("duid", Json::str(&mapping.duid)),
+            field("ip-addresses", Json::Arr(vec![Json::str(&mapping.ipaddr)])),
+        ];
+        if let Some(prefix) = mapping.delegated_prefix() {
+            fields.push(field("prefixes", Json::Arr(vec![Json::str(&prefix)])));
+        }
+        if let Some(name) = mapping.hostname.as_deref().filter(|h| !h.is_empty()) {
+            fields.push(field("hostname", Json::str(name)));
+        }
+        push_description(&mut fields, mapping.descr.as_deref());
+        if options.verbose {
+            term::action(
+                "EXPORT",
+                format_args!("{} -> {}", mapping.duid, mapping.ipaddr),
+            );
+        }
+        builder.reservations.push(Json::Obj(fields));
+        counts.reservations_v6 += 1;
+    }
+
+    let options_v6: Vec<_> = iter_isc_options_v6(root).collect();
+    let specs = dnsmasq_option_specs_from_isc(&[], &options_v6);
+    Ok(finish(builders, &specs, &[], data, true))
+}
+
+/// The subnet for `iface`, adding one from the interface CIDR if it has
+/// mappings but no ranges.
+fn builder_for<'a>(
+    builders: &'a mut BTreeMap<String, SubnetBuilder>,
+    iface: &str,
+    cidrs: &std::collections::HashMap<String, String>,
+    data: &ExportData,
+) -> Option<&'a mut SubnetBuilder> {
+    if !builders.contains_key(iface) {
+        let cidr = cidrs.get(iface)?;
+        builders.insert(
+            iface.to_string(),
+            SubnetBuilder {
+                cidr: cidr.clone(),
+                descr: data.labels.get(iface).cloned(),
+                netboot: None,
+                pools: Vec::new(),
+                reservations: Vec::new(),
+            },
+        );
+    }
+    builders.get_mut(iface)
+}
+
+/// `option-data` for custom numbered ISC options, keyed by interface.
+/// Options for a code the standard ISC fields already set, or whose type
+/// differs from the code's definition, are left out with a warning.
+fn custom_option_data(
+    options: &[IscNumberOption],
+    specs: &[DnsmasqOptionSpec],
+    defs: &[OptionDef],
+    counts: &mut ExportCounts,
+) -> Vec<(String, Json)> {
+    let mut entries = Vec::new();
+    for option in options {
+        let code = option.number.to_string();
+        if specs
+            .iter()
+            .any(|spec| spec.iface == option.iface && spec.option == code)
+        {
+            term::warning(format_args!(
+                "Custom option {} on {} duplicates a standard ISC option; not exporting it.",
+                code, option.iface
+            ));
+            continue;
+        }
+        let (kind, _) = option_type(option);
+        let mut fields = match defs.iter().find(|def| def.code == option.number) {
+            Some(def) if def.kind != kind => {
+                term::warning(format_args!(
+                    "Custom option {} on {} is {} but defined as {}; not exporting it.",
+                    code,
+                    option.iface,
+                    kind.as_str(),
+                    def.kind.as_str()
+                ));
+                continue;
+            }
+            Some(def) => vec![field("name", Json::str(&def.name))],
+            None => Vec::new(),
+        };
+        fields.push(field("code", Json::Num(option.number.into())));
+        let (value, csv) = option_data(option, kind);
+        if !csv {
+            fields.push(field("csv-format", Json::Bool(false)));
+        }
+        fields.push(field("data", Json::str(value)));
+        entries.push((option.iface.clone(), Json::Obj(fields)));
+        counts.custom_options += 1;
+    }
+    entries
+}
+
+fn option_def(def: &OptionDef) -> Json {
+    let mut fields = vec![
+        field("name", Json::str(&def.name)),
+        field("code", Json::Num(def.code.into())),
+        field("type", Json::str(def.kind.as_str())),
+    ];
+    if def.array {
+        fields.push(field("array", Json::Bool(true)));
+    }
+    fields.push(field("space", Json::str("dhcp4")));
+    Json::Obj(fields)
+}
+
+fn finish(
+    builders: BTreeMap<String, SubnetBuilder>,
+    specs: &[DnsmasqOptionSpec],
+    custom: &[(String, Json)],
+    data: &ExportData,
+    v6: bool,
+) -> Subnets {
+    builders
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (iface, builder))| {
+            let mut fields = vec![
+                field("id", Json::Num(idx as u64 + 1)),
+                field("subnet", Json::str(&builder.cidr)),
+            ];
+            let netboot = builder.netboot.unwrap_or_default();
+            if let Some(next_server) = &netboot.next_server {
+                fields.push(field("next-server", Json::str(next_server)));
+            }
+            if !builder.pools.is_empty() {
+                let pools = builder
+                    .pools
+                    .into_iter()
+                    .map(|pool| Json::Obj(vec![field("pool", Json::str(pool))]))
+                    .collect();
+                fields.push(field("pools", Json::Arr(pools)));
+            }
+            // Kea sends no routers unless configured, so a suppressed
+            // (empty) option 3 is left out
+            let option_data: Vec<Json> = specs
+                .iter()
+                .filter(|spec| spec.iface == iface && !spec.value.is_empty())
+                .map(|spec| {
+                    let code = if v6 { &spec.option6 } else { &spec.option };
+                    let name = kea_option_name(code, v6).unwrap_or(code);
+                    Json::Obj(vec![
+                        field("name", Json::str(name)),
+                        field("data", Json::str(&spec.value)),
+                    ])
+                })
+                .chain(
+                    [
+                        ("tftp-server-name", &netboot.tftp_server_name),
+                        ("boot-file-name", &netboot.boot_file_name),
+                    ]
+                    .into_iter()
+                    .filter_map(|(name, value)| {
+                        let value = value.as_ref()?;
+                        Some(Json::Obj(vec![
+                            field("name", Json::str(name)),
+                            field("data", Json::str(value)),
+                        ]))
+                    }),
+                )
+                .chain(
+                    custom
+                        .iter()
+                        .filter(|(custom_iface, _)| *custom_iface == iface)
+                        .map(|(_, entry)| entry.clone()),
+                )
+                .collect();
+            if !option_data.is_empty() {
+                fields.push(field("option-data", Json::Arr(option_data)));
+            }
+            if !builder.reservations.is_empty() {
+                fields.push(field("reservations", Json::Arr(builder.reservations)));
+            }
+            let label = builder
+                .descr
+                .or_else(|| data.labels.get(&iface).cloned())
+                .map(|descr| format!("{} ({})", iface, descr))
+                .unwrap_or_else(|| iface.clone());
+            fields.push(field(
+                "user-context",
+                Json::Obj(vec![field("isc-interface", Json::str(label))]),
+            ));
+            (iface, Json::Obj(fields))
+        })
+        .collect()
+}
+
+/// Kea option name for a DHCP option code from the dnsmasq option mapping.
+fn kea_option_name(code: &str, v6: bool) -> Option<&'static str> {
+    let name = match (v6, code) {
+        (false, "3") => "routers",
+        (false, "6") => "domain-name-servers",
+        (false, "15") => "domain-name",
+        (false, "42") => "ntp-servers",
+        (false, "44") => "netbios-name-servers",
+        (false, "46") => "netbios-node-type",
+        (false, "119") => "domain-search",
+        (true, "22") => "sip-server-addr",
+        (true, "23") => "dns-servers",
+        (true, "24") => "domain-search",
+        (true, "31") => "sntp-servers",
+        (true, "59") => "bootfile-url",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn push_description(fields: &mut Vec<(String, Json)>, descr: Option<&str>) {
+    if let Some(descr) = descr.filter(|d| !d.is_empty()) {
+        fields.push(field(
+            "user-context",
+            Json::Obj(vec![field("description", Json::str(descr))]),
+        ));
+    }
+}
+
+/// `<stem>-subnet<id>-<iface>.json` next to `out`.
+fn include_path(out: &Path, id: usize, iface: &str) -> std::path::PathBuf {
+    let stem = out
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "kea".to_string());
+    out.with_file_name(format!("{}-subnet{}-{}.json", stem, id, iface))
+}
+
+fn field(key: &str, value: Json) -> (String, Json) {
+    (key.to_string(), value)
+}
+
+/// Minimal JSON value with pretty printing (4-space indent).
+#[derive(Clone)]
+enum Json {
+    Str(String),
+    Num(u64),
+    Bool(bool),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+    /// Written verbatim, for Kea `<?include?>` directives
+    Raw(String),
+}
+
+impl Json {
+    fn str(value: impl Into<String>) -> Self {
+        Json::Str(value.into())
+    }
+
+    fn render(&self, indent: usize) -> String {
+        let pad = " ".repeat(indent + 4);
+        match self {
+            Json::Str(s) => json_string(s),
+            Json::Num(n) => n.to_string(),
+            Json::Bool(b) => b.to_string(),
+            Json::Raw(s) => s.clone(),
+            Json::Arr(items) if items.is_empty() => "[]".to_string(),
+            Json::Obj(fields) if fields.is_empty() => "{}".to_string(),
+            Json::Arr(items) => {
+                let body = items
+                    .iter()
+                    .map(|item| format!("{}{}", pad, item.render(indent + 4)))
+                    .collect::<Vec<_>>()
+                    .join(",\n");
+                format!("[\n{}\n{}]", body, " ".repeat(indent))
+            }
+            Json::Obj(fields) => {
+                let body = fields
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}{}: {}", pad, json_string(key), value.render(indent + 4))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",\n");
+                format!("{{\n{}\n{}}}", body, " ".repeat(indent))
+            }
+        }
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_render() {
+        let value = Json::Obj(vec![
+            field("a", Json::Num(1)),
+            field(
+                "b",
+                Json::Arr(vec![Json::str("x\"y"), Json::Arr(Vec::new())]),
+            ),
+        ]);
+        assert_eq!(
+            value.render(0),
+            "{\n    \"a\": 1,\n    \"b\": [\n        \"x\\\"y\",\n        []\n    ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_include_path() {
+        assert_eq!(
+            include_path(Path::new("/etc/kea/kea-dhcp4.conf"), 2, "opt1"),
+            Path::new("/etc/kea/kea-dhcp4-subnet2-opt1.json")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Export ISC DHCP data to other DHCP servers' native formats.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use xmltree::Element;
+
+use super::subnets::{desired_subnets_v4, merge_ranges_v4, DesiredSubnetV4};
+use super::{extract_ordered_mappings, validate_input, IscMappingSet};
+use crate::extract::{
+    extract_interface_labels, iter_isc_number_options_v4, iter_isc_options_v4, iter_isc_ranges_v6,
+};
+use crate::parse;
+use crate::xml_helpers::count_elements;
+use crate::{
+    ExportFormat, IscDhcpOptionsV4, IscNumberOption, IscStaticMap, IscStaticMapV6,
+    MigrationOptions, MigrationStats, MigrationTiming,
+};
+
+mod kea_json;
+mod option_defs;
+mod routeros;
+mod uci;
+
+/// ISC data selected for export.
+pub(crate) struct ExportData {
+    pub(crate) mappings: Vec<IscStaticMap>,
+    pub(crate) mappings_v6: Vec<IscStaticMapV6>,
+    /// Interface subnets with their ISC ranges
+    pub(crate) subnets: Vec<DesiredSubnetV4>,
+    /// ISC DHCPv4 options per interface
+    pub(crate) options: Vec<IscDhcpOptionsV4>,
+    /// Custom numbered ISC DHCPv4 options
+    pub(crate) number_options: Vec<IscNumberOption>,
+    /// Interface key to `<descr>` label
+    pub(crate) labels: HashMap<String, String>,
+    /// ISC DHCPv4 ranges merged away with `merge_ranges`
+    pub(crate) ranges_merged: usize,
+}
+
+impl ExportData {
+    fn from_root(root: &Element, options: &MigrationOptions) -> Result<(Self, usize, usize)> {
+        let IscMappingSet {
+            v4,
+            v6,
+            disabled_v4,
+            disabled_v6,
+            ..
+        } = extract_ordered_mappings(root, options)?;
+        let mut subnets = desired_subnets_v4(root)?;
+        subnets.sort_by(|a, b| a.iface.cmp(&b.iface));
+        let ranges_merged = if options.merge_ranges {
+            merge_ranges_v4(&mut subnets, options)
+        } else {
+            0
+        };
+        let data = ExportData {
+            mappings: v4,
+            mappings_v6: v6,
+            subnets,
+            options: iter_isc_options_v4(root).collect(),
+            number_options: iter_isc_number_options_v4(root).collect(),
+            labels: extract_interface_labels(root),
+            ranges_merged,
+        };
+        Ok((data, disabled_v4, disabled_v6))
+    }
+
+    /// ISC DHCPv4 options for `iface`, if any are set.
+    pub(crate) fn options_for(&self, iface: &str) -> Option<&IscDhcpOptionsV4> {
+        self.options.iter().find(|opt| opt.iface == iface)
+    }
+}
+
+/// Export ISC static mappings, ranges and options from an OPNsense config
+/// in `format`, writing the rendered file and reporting what it contains.
+///
+/// The input config is never modified. Mappings on interfaces where ISC DHCP
+/// is disabled are left out unless `options.include_disabled_interfaces` is
+/// set, and `options.reservation_order` controls their order.
+pub fn export_config<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    format: ExportFormat,
+    options: &MigrationOptions,
+) -> Result<MigrationStats> {
+    let (main, _, mut stats) = run_export(reader, format, options, None)?;
+    let started = Instant::now();
+    writer
+        .write_all(main.as_bytes())
+        .context("Failed to write export")?;
+    stats.timing.write = started.elapsed();
+    Ok(stats)
+}
+
+/// A file produced by [`export_config_split`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Export like [`export_config`], but with each subnet and its reservations
+/// in its own include file next to `out`.
+///
+/// The first file returned is the main config for `out`; it pulls in the
+/// others with Kea `<?include?>` directives using their paths as given, so
+/// pass an absolute `out` if Kea runs from another directory. Only the Kea
+/// formats can be split.
+pub fn export_config_split<R: Read>(
+    reader: R,
+    out: &Path,
+    format: ExportFormat,
+    options: &MigrationOptions,
+) -> Result<(Vec<ExportFile>, MigrationStats)> {
+    if !matches!(format, ExportFormat::KeaDhcp4 | ExportFormat::KeaDhcp6) {
+        return Err(anyhow!(
+            "Per-subnet split output is only supported for the kea-dhcp4 and kea-dhcp6 formats"
+        ));
+    }
+    let (main, includes, stats) = run_export(reader, format, options, Some(out))?;
+    let mut files = vec![ExportFile {
+        path: out.to_path_buf(),
+        contents: main,
+    }];
+    files.extend(includes);
+    Ok((files, stats))
+}
+
+/// Parse, extract and render; returns the main file, any include files for
+/// `split_out`, and stats without write timing.
+fn run_export<R: Read>(
+    reader: R,
+    format: ExportFormat,
+    options: &MigrationOptions,
+    split_out: Option<&Path>,
+) -> Result<(String, Vec<ExportFile>, MigrationStats)> {
+    let started = Instant::now();
+    let root = Element::parse(reader).context("Failed to parse XML")?;
+    validate_input(&root)?;
+    let mut timing = MigrationTiming {
+        parse: started.elapsed(),
+        input_elements: count_elements(&root),
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    let (data, disabled_v4, disabled_v6) = ExportData::from_root(&root, options)?;
+    timing.extract = started.elapsed();
+
+    let started = Instant::now();
+    let mut includes = Vec::new();
+    let (main, exported) = match format {
+        ExportFormat::Routeros => routeros::render(&data, options),
+        ExportFormat::Uci => uci::render(&data, options)?,
+        ExportFormat::KeaDhcp4 | ExportFormat::KeaDhcp6 => {
+            let v6 = format == ExportFormat::KeaDhcp6;
+            let rendered = kea_json::render(&root, &data, v6, split_out, options)?;
+            includes = rendered.includes;
+            (rendered.main, rendered.counts)
+        }
+    };
+    timing.convert = started.elapsed();
+
+    let stats = MigrationStats {
+        isc_mappings_found: data.mappings.len(),
+        isc_mappings_v6_found: data.mappings_v6.len(),
+        isc_mappings_disabled_iface: disabled_v4,
+        isc_mappings_v6_disabled_iface: disabled_v6,
+        isc_ranges_found: data.subnets.iter().map(|s| s.ranges.len()).sum::<usize>()
+            + data.ranges_merged,
+        isc_ranges_v6_found: iter_isc_ranges_v6(&root).count(),
+        ranges_merged: data.ranges_merged + exported.ranges_merged,
+        target_subnets_found: exported.subnets,
+        reservations_to_create: exported.reservations,
+        reservations_v6_to_create: exported.reservations_v6,
+        reservations_skipped: exported.skipped,
+        reservations_v6_skipped: exported.skipped_v6,
+        custom_options_exported: exported.custom_options,
+        option_defs_exported: exported.option_defs,
+        interface_labels: data.labels,
+        timing,
+        ..Default::default()
+    };
+    Ok((main, includes, stats))
+}
+
+/// What a renderer wrote.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ExportCounts {
+    pub(crate) subnets: usize,
+    pub(crate) reservations: usize,
+    pub(crate) reservations_v6: usize,
+    /// Mappings that could not be represented in the format
+    pub(crate) skipped: usize,
+    pub(crate) skipped_v6: usize,
+    /// Custom numbered options exported, and `option-def` entries for them
+    pub(crate) custom_options: usize,
+    pub(crate) option_defs: usize,
+    /// ISC DHCPv6 ranges merged away with `merge_ranges`
+    pub(crate) ranges_merged: usize,
+}
+
+/// Hostname used for a mapping: its hostname, falling back to the ISC client
+/// identifier as the Kea backend does.
+pub(crate) fn mapping_name(mapping: &IscStaticMap) -> Option<&str> {
+    mapping
+        .hostname
+        .as_deref()
+        .or(mapping.cid.as_deref())
+        .filter(|name| !name.is_empty())
+}
+
+/// Lowercase hex digits of a DUID, or `None` if it does not parse.
+pub(crate) fn duid_hex_digits(duid: &str) -> Option<String> {
+    let octets = parse::duid(duid).ok()?;
+    Some(
+        octets
+            .iter()
+            .map(|octet| format!("{:02x}", octet))
+            .collect(),
+    )
+}
@@ -0,0 +1,317 @@
+//! Kea types and `option-def` entries for custom numbered ISC DHCPv4
+//! options.
+//!
+//! Kea only accepts `option-data` for a code it has a definition for, so
+//! codes without a standard definition (site-specific ones such as 252,
+//! or vendor ones such as 150) get an `option-def` in the `dhcp4` space.
+//! The type comes from the ISC option type when it maps to a Kea type,
+//! and is otherwise guessed from the shape of the value. Classless static
+//! routes (121, and 249 which Kea does not define) are re-encoded whatever
+//! shape ISC had them in.
+
+use std::net::Ipv4Addr;
+
+use crate::migrate::routes::{
+    encode_wire, kea_routes, parse_classless_routes, ClasslessRoute, CLASSLESS_ROUTE_CODES,
+};
+use crate::parse;
+use crate::IscNumberOption;
+
+/// Kea option data type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum KeaOptionType {
+    String,
+    Binary,
+    Boolean,
+    Uint8,
+    Uint16,
+    Uint32,
+    Int8,
+    Int16,
+    Int32,
+    Ipv4Address,
+}
+
+impl KeaOptionType {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            KeaOptionType::String => "string",
+            KeaOptionType::Binary => "binary",
+            KeaOptionType::Boolean => "boolean",
+            KeaOptionType::Uint8 => "uint8",
+            KeaOptionType::Uint16 => "uint16",
+            KeaOptionType::Uint32 => "uint32",
+            KeaOptionType::Int8 => "int8",
+            KeaOptionType::Int16 => "int16",
+            KeaOptionType::Int32 => "int32",
+            KeaOptionType::Ipv4Address => "ipv4-address",
+        }
+    }
+}
+
+/// An `option-def` for a code Kea does not define.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct OptionDef {
+    pub(super) code: u8,
+    pub(super) name: String,
+    pub(super) kind: KeaOptionType,
+    pub(super) array: bool,
+}
+
+/// Whether Kea ships a standard DHCPv4 definition for `code`.
+pub(super) fn is_standard_v4(code: u8) -> bool {
+    matches!(
+        code,
+        1..=77
+            | 81
+            | 82
+            | 85..=94
+            | 97..=101
+            | 108
+            | 112..=114
+            | 116..=119
+            | 121
+            | 124
+            | 125
+            | 136..=138
+            | 141
+            | 146
+            | 159
+            | 162
+            | 212
+            | 213
+    )
+}
+
+/// Kea type of `option`, and whether it is a list.
+pub(super) fn option_type(option: &IscNumberOption) -> (KeaOptionType, bool) {
+    if classless_routes(option).is_some() {
+        return (KeaOptionType::Binary, false);
+    }
+    let value = unquote(&option.value);
+    let declared = match option
+        .kind
+        .as_deref()
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("text") => Some(KeaOptionType::String),
+        Some("string") => Some(if is_hex_octets(value) {
+            KeaOptionType::Binary
+        } else {
+            KeaOptionType::String
+        }),
+        Some("boolean") => Some(KeaOptionType::Boolean),
+        Some("unsigned integer 8") => Some(KeaOptionType::Uint8),
+        Some("unsigned integer 16") => Some(KeaOptionType::Uint16),
+        Some("unsigned integer 32") => Some(KeaOptionType::Uint32),
+        Some("signed integer 8") => Some(KeaOptionType::Int8),
+        Some("signed integer 16") => Some(KeaOptionType::Int16),
+        Some("signed integer 32") => Some(KeaOptionType::Int32),
+        Some("ip-address") => Some(KeaOptionType::Ipv4Address),
+        _ => None,
+    };
+    let kind = declared.unwrap_or_else(|| guess_type(value));
+    let array = kind == KeaOptionType::Ipv4Address && value.contains(',');
+    (kind, array)
+}
+
+/// Type guessed from the value: addresses, booleans, the smallest integer
+/// type holding it, colon-separated hex, and otherwise a string.
+fn guess_type(value: &str) -> KeaOptionType {
+    if value
+        .split(',')
+        .all(|part| part.trim().parse::<Ipv4Addr>().is_ok())
+    {
+        return KeaOptionType::Ipv4Address;
+    }
+    if ["true", "false", "on", "off"]
+        .iter()
+        .any(|b| value.eq_ignore_ascii_case(b))
+    {
+        return KeaOptionType::Boolean;
+    }
+    if let Ok(n) = value.parse::<u32>() {
+        return match n {
+            0..=0xff => KeaOptionType::Uint8,
+            0x100..=0xffff => KeaOptionType::Uint16,
+            _ => KeaOptionType::Uint32,
+        };
+    }
+    if let Ok(n) = value.parse::<i32>() {
+        return match n {
+            -0x80..=-1 => KeaOptionType::Int8,
+            -0x8000..=-0x81 => KeaOptionType::Int16,
+            _ => KeaOptionType::Int32,
+        };
+    }
+    if is_hex_octets(value) {
+        return KeaOptionType::Binary;
+    }
+    KeaOptionType::String
+}
+
+/// Definitions for the codes in `options` Kea has no standard definition
+/// for, in code order. A code set with different types on several
+/// interfaces is defined by its first use.
+pub(super) fn option_defs(options: &[IscNumberOption]) -> Vec<OptionDef> {
+    let mut defs: Vec<OptionDef> = Vec::new();
+    for option in options.iter().filter(|o| !is_standard_v4(o.number)) {
+        if defs.iter().any(|def| def.code == option.number) {
+            continue;
+        }
+        let (kind, array) = option_type(option);
+        defs.push(OptionDef {
+            code: option.number,
+            name: format!("isc-option-{}", option.number),
+            kind,
+            array,
+        });
+    }
+    defs.sort_by_key(|def| def.code);
+    defs
+}
+
+/// `data` for `option` written as `kind`, and whether Kea should read it in
+/// CSV format (everything but binary data).
+pub(super) fn option_data(option: &IscNumberOption, kind: KeaOptionType) -> (String, bool) {
+    if let Some(routes) = classless_routes(option) {
+        // Kea reads its own 121 as text; 249 goes out in wire format
+        if option.number == 121 {
+            return (kea_routes(&routes), true);
+        }
+        let hex = encode_wire(&routes)
+            .iter()
+            .map(|o| format!("{:02x}", o))
+            .collect();
+        return (hex, false);
+    }
+    let value = unquote(&option.value);
+    match kind {
+        KeaOptionType::Binary => {
+            let hex = parse::hwaddr(value)
+                .ok()
+                .map(|octets| octets.iter().map(|o| format!("{:02x}", o)).collect())
+                .unwrap_or_else(|| value.to_string());
+            (hex, false)
+        }
+        // A CSV string value has its commas escaped
+        KeaOptionType::String => (value.replace(',', "\\,"), true),
+        KeaOptionType::Boolean => {
+            let on = value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("on");
+            (on.to_string(), true)
+        }
+        KeaOptionType::Ipv4Address => {
+            let list: Vec<&str> = value.split(',').map(str::trim).collect();
+            (list.join(", "), true)
+        }
+        _ => (value.to_string(), true),
+    }
+}
+
+fn classless_routes(option: &IscNumberOption) -> Option<Vec<ClasslessRoute>> {
+    if !CLASSLESS_ROUTE_CODES.contains(&option.number) {
+        return None;
+    }
+    parse_classless_routes(&option.value)
+}
+
+/// `value` without the double quotes ISC puts around text.
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn is_hex_octets(value: &str) -> bool {
+    value.contains(':') && parse::hwaddr(value).ok().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(number: u8, kind: Option<&str>, value: &str) -> IscNumberOption {
+        IscNumberOption {
+            iface: "lan".to_string(),
+            number,
+            kind: kind.map(str::to_string),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_option_type() {
+        let cases = [
+            (
+                option(252, Some("text"), "\"http://wpad/wpad.dat\""),
+                "string",
+            ),
+            (option(224, Some("string"), "01:02:0a"), "binary"),
+            (option(224, Some("unsigned integer 16"), "5"), "uint16"),
+            (option(150, None, "10.0.0.1, 10.0.0.2"), "ipv4-address"),
+            (option(224, None, "300"), "uint16"),
+            (option(224, None, "-5"), "int8"),
+            (option(224, None, "on"), "boolean"),
+            (option(224, None, "a:b:c"), "binary"),
+            (option(224, None, "some text"), "string"),
+        ];
+        for (option, expected) in cases {
+            assert_eq!(option_type(&option).0.as_str(), expected, "{:?}", option);
+        }
+        assert!(option_type(&option(150, None, "10.0.0.1,10.0.0.2")).1);
+    }
+
+    #[test]
+    fn test_option_defs_skip_standard_codes() {
+        assert!(is_standard_v4(66));
+        assert!(!is_standard_v4(80));
+        assert!(!is_standard_v4(150));
+        let defs = option_defs(&[
+            option(252, Some("text"), "http://wpad/wpad.dat"),
+            option(66, Some("text"), "tftp.example"),
+            option(150, Some("ip-address"), "10.0.0.1"),
+            option(252, Some("boolean"), "true"),
+        ]);
+        let defs: Vec<(u8, &str)> = defs.iter().map(|d| (d.code, d.kind.as_str())).collect();
+        assert_eq!(defs, [(150, "ipv4-address"), (252, "string")]);
+    }
+
+    #[test]
+    fn test_option_data() {
+        let data = |value: &str, kind| option_data(&option(224, None, value), kind);
+        assert_eq!(
+            data("\"a,b\"", KeaOptionType::String),
+            ("a\\,b".to_string(), true)
+        );
+        assert_eq!(
+            data("1:2:ab", KeaOptionType::Binary),
+            ("0102ab".to_string(), false)
+        );
+        assert_eq!(
+            data("on", KeaOptionType::Boolean),
+            ("true".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_classless_route_options() {
+        let wire = "18:0a:01:02:c0:a8:01:01";
+        let routes = option(121, Some("string"), wire);
+        assert_eq!(option_type(&routes), (KeaOptionType::Binary, false));
+        assert_eq!(
+            option_data(&routes, KeaOptionType::Binary),
+            ("10.1.2.0/24 - 192.168.1.1".to_string(), true)
+        );
+        let windows = option(249, Some("text"), "10.1.2.0/24 192.168.1.1");
+        assert_eq!(
+            option_data(&windows, option_type(&windows).0),
+            ("180a0102c0a80101".to_string(), false)
+        );
+        // Not a route list: handled like any other custom option
+        let other = option(249, Some("text"), "routes");
+        assert_eq!(option_type(&other).0, KeaOptionType::String);
+    }
+}
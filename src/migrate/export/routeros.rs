@@ -0,0 +1,181 @@
+//! MikroTik RouterOS script (`.rsc`) renderer.
+
+use std::fmt::Write;
+
+use super::{duid_hex_digits, mapping_name, ExportCounts, ExportData};
+use crate::term;
+use crate::MigrationOptions;
+
+/// Render `data` as RouterOS CLI commands for `/import`.
+///
+/// Each interface with ISC ranges gets an `/ip pool` and an
+/// `/ip dhcp-server network`. Leases and IPv6 bindings are added without a
+/// `server`, so they apply to every DHCP server until assigned to one.
+pub(crate) fn render(data: &ExportData, options: &MigrationOptions) -> (String, ExportCounts) {
+    let mut out = String::new();
+    let mut counts = ExportCounts::default();
+
+    out.push_str(&format!(
+        "# RouterOS DHCP configuration exported by isc2kea {} from ISC DHCP\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    out.push_str(
+        "# Create an /ip dhcp-server for each pool on the matching interface, then\n\
+         # set server= on the leases before relying on them.\n",
+    );
+
+    for subnet in &data.subnets {
+        let label = subnet.display_iface();
+        let pool = pool_name(&subnet.iface);
+        let ranges = subnet
+            .ranges
+            .iter()
+            .map(|r| format!("{}-{}", r.from, r.to))
+            .collect::<Vec<_>>()
+            .join(",");
+        let _ = writeln!(out, "\n# {}", label);
+        let _ = writeln!(
+            out,
+            "/ip pool add name={} ranges={} comment={}",
+            quote(&pool),
+            ranges,
+            quote(&label)
+        );
+
+        let mut network = format!("/ip dhcp-server network add address={}", subnet.cidr);
+        if let Some(opt) = data.options_for(&subnet.iface) {
+            if let Some(routers) = opt.routers.as_deref().filter(|r| !r.is_empty()) {
+                let _ = write!(network, " gateway={}", routers);
+            }
+            for (key, values) in [
+                ("dns-server", &opt.dns_servers),
+                ("ntp-server", &opt.ntp_servers),
+                ("wins-server", &opt.wins_servers),
+            ] {
+                if !values.is_empty() {
+                    let _ = write!(network, " {}={}", key, values.join(","));
+                }
+            }
+            if let Some(domain) = opt.domain_name.as_deref().filter(|d| !d.is_empty()) {
+                let _ = write!(network, " domain={}", quote(domain));
+            }
+        }
+        let _ = writeln!(out, "{} comment={}", network, quote(&label));
+        counts.subnets += 1;
+    }
+
+    if !data.mappings.is_empty() {
+        out.push_str("\n# Static leases\n");
+    }
+    for mapping in &data.mappings {
+        let mut line = format!(
+            "/ip dhcp-server lease add address={} mac-address={}",
+            mapping.ipaddr,
+            mapping.mac.to_ascii_uppercase()
+        );
+        let comment = lease_comment(mapping_name(mapping), mapping.descr.as_deref());
+        if !comment.is_empty() {
+            let _ = write!(line, " comment={}", quote(&comment));
+        }
+        if options.verbose {
+            term::action(
+                "EXPORT",
+                format_args!("{} -> {}", mapping.mac, mapping.ipaddr),
+            );
+        }
+        let _ = writeln!(out, "{}", line);
+        counts.reservations += 1;
+    }
+
+    if !data.mappings_v6.is_empty() {
+        out.push_str("\n# DHCPv6 bindings\n");
+    }
+    for mapping in &data.mappings_v6 {
+        let Some(duid) = duid_hex(&mapping.duid) else {
+            term::warning(format_args!(
+                "DUID '{}' for {} is not hex; skipping RouterOS binding.",
+                mapping.duid, mapping.ipaddr
+            ));
+            counts.skipped_v6 += 1;
+            continue;
+        };
+        let mut line = format!(
+            "/ipv6 dhcp-server binding add address={}/128 duid={}",
+            mapping.ipaddr, duid
+        );
+        let comment = lease_comment(mapping.hostname.as_deref(), mapping.descr.as_deref());
+        if !comment.is_empty() {
+            let _ = write!(line, " comment={}", quote(&comment));
+        }
+        if options.verbose {
+            term::action(
+                "EXPORT",
+                format_args!("{} -> {}", mapping.duid, mapping.ipaddr),
+            );
+        }
+        let _ = writeln!(out, "{}", line);
+        counts.reservations_v6 += 1;
+    }
+
+    (out, counts)
+}
+
+fn pool_name(iface: &str) -> String {
+    format!("{}-pool", iface)
+}
+
+/// `hostname - description`, leaving out whichever is empty.
+fn lease_comment(hostname: Option<&str>, descr: Option<&str>) -> String {
+    [hostname, descr]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" - ")
+}
+
+/// DUID in the `0x...` form RouterOS expects, from ISC's colon-separated hex.
+fn duid_hex(duid: &str) -> Option<String> {
+    duid_hex_digits(duid).map(|hex| format!("0x{}", hex))
+}
+
+/// Double-quote a RouterOS string, escaping characters the parser treats
+/// specially.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' | '$' | '?' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' | '\r' | '\t' => quoted.push(' '),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_escapes_special_characters() {
+        assert_eq!(quote("plain"), "\"plain\"");
+        assert_eq!(quote("say \"hi\" $x?"), "\"say \\\"hi\\\" \\$x\\?\"");
+        assert_eq!(quote("a\\b"), "\"a\\\\b\"");
+    }
+
+    #[test]
+    fn test_duid_hex() {
+        assert_eq!(
+            duid_hex("00:01:00:01:AA:bb").as_deref(),
+            Some("0x00010001aabb")
+        );
+        assert_eq!(duid_hex("not-a-duid"), None);
+        assert_eq!(duid_hex(""), None);
+    }
+}
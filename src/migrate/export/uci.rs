@@ -0,0 +1,193 @@
+//! OpenWrt `/etc/config/dhcp` (UCI) renderer.
+
+use anyhow::{anyhow, Result};
+use std::fmt::Write;
+
+use super::{duid_hex_digits, mapping_name, ExportCounts, ExportData};
+use crate::migrate::options::{classless_route_specs, dnsmasq_option_specs_from_isc};
+use crate::migrate::subnets::DesiredSubnetV4;
+use crate::parse;
+use crate::term;
+use crate::{MigrationError, MigrationOptions};
+
+/// Render `data` as UCI `config dhcp` and `config host` sections.
+///
+/// Each interface with ISC ranges gets a `config dhcp` section named after
+/// the ISC interface key, with `start`/`limit` taken from its first range
+/// and DHCPv4 options from the same mapping as the dnsmasq backend. Static
+/// mappings become `config host` sections; DHCPv6 ones carry `duid` and
+/// `hostid` for odhcpd.
+pub(crate) fn render(
+    data: &ExportData,
+    options: &MigrationOptions,
+) -> Result<(String, ExportCounts)> {
+    let mut out = String::new();
+    let mut counts = ExportCounts::default();
+
+    let _ = writeln!(
+        out,
+        "# OpenWrt DHCP configuration exported by isc2kea {} from ISC DHCP",
+        env!("CARGO_PKG_VERSION")
+    );
+    out.push_str(
+        "# Rename the dhcp sections and their interface options to match\n\
+         # /etc/config/network before merging into /etc/config/dhcp.\n",
+    );
+
+    let mut specs = dnsmasq_option_specs_from_isc(&data.options, &[]);
+    specs.extend(classless_route_specs(&data.number_options));
+    for subnet in &data.subnets {
+        let Some((start, limit)) = range_offsets(subnet)? else {
+            continue;
+        };
+        let _ = writeln!(out, "\n# {}", subnet.display_iface());
+        let _ = writeln!(out, "config dhcp {}", quote(&subnet.iface));
+        let _ = writeln!(out, "\toption interface {}", quote(&subnet.iface));
+        let _ = writeln!(out, "\toption start {}", quote(&start.to_string()));
+        let _ = writeln!(out, "\toption limit {}", quote(&limit.to_string()));
+        for spec in specs.iter().filter(|spec| spec.iface == subnet.iface) {
+            // A bare option number sends the option empty (no router for 3)
+            let value = if spec.value.is_empty() {
+                spec.option.clone()
+            } else {
+                format!("{},{}", spec.option, spec.value)
+            };
+            let _ = writeln!(out, "\tlist dhcp_option {}", quote(&value));
+        }
+        counts.subnets += 1;
+    }
+
+    for mapping in &data.mappings {
+        out.push('\n');
+        if let Some(descr) = mapping.descr.as_deref().filter(|d| !d.is_empty()) {
+            let _ = writeln!(out, "# {}", single_line(descr));
+        }
+        out.push_str("config host\n");
+        if let Some(name) = mapping_name(mapping) {
+            let _ = writeln!(out, "\toption name {}", quote(name));
+        }
+        let _ = writeln!(
+            out,
+            "\toption mac {}",
+            quote(&mapping.mac.to_ascii_lowercase())
+        );
+        let _ = writeln!(out, "\toption ip {}", quote(&mapping.ipaddr));
+        if options.verbose {
+            term::action(
+                "EXPORT",
+                format_args!("{} -> {}", mapping.mac, mapping.ipaddr),
+            );
+        }
+        counts.reservations += 1;
+    }
+
+    for mapping in &data.mappings_v6 {
+        let Some(duid) = duid_hex_digits(&mapping.duid) else {
+            term::warning(format_args!(
+                "DUID '{}' for {} is not hex; skipping UCI host.",
+                mapping.duid, mapping.ipaddr
+            ));
+            counts.skipped_v6 += 1;
+            continue;
+        };
+        let Some(hostid) = host_id(&mapping.ipaddr) else {
+            term::warning(format_args!(
+                "'{}' is not an IPv6 address; skipping UCI host.",
+                mapping.ipaddr
+            ));
+            counts.skipped_v6 += 1;
+            continue;
+        };
+        out.push('\n');
+        if let Some(descr) = mapping.descr.as_deref().filter(|d| !d.is_empty()) {
+            let _ = writeln!(out, "# {}", single_line(descr));
+        }
+        out.push_str("config host\n");
+        if let Some(name) = mapping.hostname.as_deref().filter(|h| !h.is_empty()) {
+            let _ = writeln!(out, "\toption name {}", quote(name));
+        }
+        let _ = writeln!(out, "\toption duid {}", quote(&duid));
+        let _ = writeln!(out, "\toption hostid {}", quote(&hostid));
+        if options.verbose {
+            term::action(
+                "EXPORT",
+                format_args!("{} -> {}", mapping.duid, mapping.ipaddr),
+            );
+        }
+        counts.reservations_v6 += 1;
+    }
+
+    Ok((out, counts))
+}
+
+/// `start` as an offset from the network address and `limit` as the number
+/// of addresses, from the subnet's first range. UCI allows one range per
+/// interface, so any further ranges are dropped with a warning.
+fn range_offsets(subnet: &DesiredSubnetV4) -> Result<Option<(u32, u32)>> {
+    let Some(first) = subnet.ranges.first() else {
+        return Ok(None);
+    };
+    for extra in &subnet.ranges[1..] {
+        term::warning(format_args!(
+            "UCI allows one DHCP range per interface; not exporting {}-{} on {}.",
+            extra.from,
+            extra.to,
+            subnet.display_iface()
+        ));
+    }
+
+    let network = subnet
+        .cidr
+        .split('/')
+        .next()
+        .and_then(|ip| parse::ipv4(ip).ok())
+        .ok_or_else(|| MigrationError::InvalidCidr(subnet.cidr.clone()))?;
+    let from = u32::from(parse::ipv4(&first.from)?);
+    let to = u32::from(parse::ipv4(&first.to)?);
+    if to < from {
+        return Err(anyhow!(
+            "DHCPv4 range {}-{} on {} ends before it starts",
+            first.from,
+            first.to,
+            subnet.display_iface()
+        ));
+    }
+    Ok(Some((from - u32::from(network), to - from + 1)))
+}
+
+/// Interface identifier (low 64 bits) of an IPv6 address in hex, as odhcpd
+/// expects for `hostid`.
+fn host_id(addr: &str) -> Option<String> {
+    let ip = parse::ipv6(addr).ok()?;
+    Some(format!("{:x}", u128::from(ip) as u64))
+}
+
+/// Single-quote a UCI value; embedded quotes are closed, escaped and reopened.
+fn quote(value: &str) -> String {
+    format!("'{}'", single_line(value).replace('\'', "'\\''"))
+}
+
+fn single_line(value: &str) -> String {
+    value.replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_single_quotes() {
+        assert_eq!(quote("lan"), "'lan'");
+        assert_eq!(quote("bob's pc"), "'bob'\\''s pc'");
+    }
+
+    #[test]
+    fn test_host_id() {
+        assert_eq!(host_id("2001:db8:30::50").as_deref(), Some("50"));
+        assert_eq!(
+            host_id("2001:db8::1:2:3:4").as_deref(),
+            Some("1000200030004")
+        );
+        assert_eq!(host_id("10.0.0.1"), None);
+    }
+}
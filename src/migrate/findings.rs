@@ -0,0 +1,251 @@
+//! Element-level differences between a config and its converted form, for
+//! `verify`.
+
+use xmltree::{Element, XMLNode};
+
+use super::markers::{CREATED_ATTR, ORIGIN_ATTR};
+use crate::{FindingCategory, FindingChange, Severity, VerifyFinding};
+
+/// Path segments that place an element in a category, checked in order so
+/// options nested in a Kea subnet, and the autocollect switch
+/// `--create-options` turns off, count as options.
+const CATEGORIES: &[(FindingCategory, &[&str])] = &[
+    (
+        FindingCategory::Option,
+        &["option_data", "option_data_autocollect", "dhcp_options"],
+    ),
+    (FindingCategory::Reservation, &["reservations", "hosts"]),
+    (
+        FindingCategory::Subnet,
+        &["subnets", "subnet4", "subnet6", "dhcp_ranges"],
+    ),
+];
+
+/// Compare `before` with `after` and list what was added, removed or changed.
+///
+/// Children are matched by name and `uuid`, or by name and position among
+/// siblings without one, so reservations appended by a conversion show up
+/// as additions rather than as changes to whatever followed them. An added
+/// or removed element with a `uuid` is reported once with its fields;
+/// containers without one are reported child by child.
+pub fn verify_findings(before: &Element, after: &Element) -> Vec<VerifyFinding> {
+    let mut findings = Vec::new();
+    let mut path = vec![before.name.clone()];
+    compare(before, after, &mut path, &mut findings);
+    findings
+}
+
+/// Drop the parts of `root` outside `only`, so a diff of two pruned trees
+/// shows just those categories. Elements are placed like findings, by the
+/// names on their path; text, comments and the root itself are kept.
+#[cfg(feature = "cli")]
+pub(crate) fn retain_categories(root: &mut Element, only: &[FindingCategory]) {
+    let mut path = vec![root.name.clone()];
+    retain(root, only, &mut path);
+}
+
+/// Whether `el` holds anything in `only` after pruning its children.
+#[cfg(feature = "cli")]
+fn retain(el: &mut Element, only: &[FindingCategory], path: &mut Vec<String>) -> bool {
+    let own = only.contains(&category(path));
+    if !el.children.iter().any(|n| n.as_element().is_some()) {
+        return own;
+    }
+    el.children.retain_mut(|node| match node {
+        XMLNode::Element(child) => {
+            path.push(child.name.clone());
+            let keep = retain(child, only, path);
+            path.pop();
+            keep
+        }
+        _ => true,
+    });
+    own || el.children.iter().any(|n| n.as_element().is_some())
+}
+
+fn compare(
+    old: &Element,
+    new: &Element,
+    path: &mut Vec<String>,
+    findings: &mut Vec<VerifyFinding>,
+) {
+    let (old_text, new_text) = (text_of(old), text_of(new));
+    if old_text != new_text {
+        push(
+            findings,
+            path,
+            FindingChange::Changed,
+            format!("{} -> {}", shown(&old_text), shown(&new_text)),
+            None,
+        );
+    }
+
+    let mut names: Vec<&String> = old.attributes.keys().chain(new.attributes.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let (old_value, new_value) = (old.attributes.get(name), new.attributes.get(name));
+        if old_value == new_value {
+            continue;
+        }
+        let severity = (name == ORIGIN_ATTR || name == CREATED_ATTR).then_some(Severity::Info);
+        push(
+            findings,
+            path,
+            FindingChange::Changed,
+            format!(
+                "@{}: {} -> {}",
+                name,
+                shown(old_value.map_or("", String::as_str)),
+                shown(new_value.map_or("", String::as_str))
+            ),
+            severity,
+        );
+    }
+
+    let old_children = keyed_children(old);
+    let mut new_children = keyed_children(new);
+    for (key, old_child) in old_children {
+        path.push(key.clone());
+        match new_children.iter().position(|(k, _)| *k == key) {
+            Some(pos) => {
+                let (_, new_child) = new_children.remove(pos);
+                compare(old_child, new_child, path, findings);
+            }
+            None => report(old_child, FindingChange::Removed, path, findings),
+        }
+        path.pop();
+    }
+    for (key, new_child) in new_children {
+        path.push(key);
+        report(new_child, FindingChange::Added, path, findings);
+        path.pop();
+    }
+}
+
+/// Report an element that exists on one side only.
+fn report(
+    el: &Element,
+    change: FindingChange,
+    path: &mut Vec<String>,
+    findings: &mut Vec<VerifyFinding>,
+) {
+    let children = keyed_children(el);
+    if el.attributes.contains_key("uuid") || children.is_empty() {
+        let detail = if children.is_empty() {
+            text_of(el)
+        } else {
+            children
+                .iter()
+                .filter(|(_, child)| keyed_children(child).is_empty())
+                .filter_map(|(_, child)| {
+                    let text = text_of(child);
+                    (!text.is_empty()).then(|| format!("{}={}", child.name, text))
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        push(findings, path, change, detail, None);
+        return;
+    }
+    for (key, child) in children {
+        path.push(key);
+        report(child, change, path, findings);
+        path.pop();
+    }
+}
+
+fn push(
+    findings: &mut Vec<VerifyFinding>,
+    path: &[String],
+    change: FindingChange,
+    detail: String,
+    severity: Option<Severity>,
+) {
+    let category = category(path);
+    findings.push(VerifyFinding {
+        severity: severity.unwrap_or_else(|| severity_for(category, change)),
+        category,
+        change,
+        path: path.join("/"),
+        detail,
+    });
+}
+
+fn category(path: &[String]) -> FindingCategory {
+    CATEGORIES
+        .iter()
+        .find(|(_, names)| {
+            path.iter().any(|segment| {
+                let name = segment.split('[').next().unwrap_or_default();
+                names.iter().any(|n| n.eq_ignore_ascii_case(name))
+            })
+        })
+        .map_or(FindingCategory::Service, |(category, _)| *category)
+}
+
+fn severity_for(category: FindingCategory, change: FindingChange) -> Severity {
+    match (category, change) {
+        (FindingCategory::Reservation, FindingChange::Added | FindingChange::Removed) => {
+            Severity::Error
+        }
+        (FindingCategory::Subnet | FindingCategory::Option, FindingChange::Removed) => {
+            Severity::Error
+        }
+        _ => Severity::Warning,
+    }
+}
+
+/// Element children with their path segment: `name[uuid]`, `name[n]` for
+/// repeated names without a `uuid`, or just `name`.
+fn keyed_children(el: &Element) -> Vec<(String, &Element)> {
+    let children: Vec<&Element> = el.children.iter().filter_map(|n| n.as_element()).collect();
+    let mut seen: Vec<(&str, usize)> = Vec::new();
+    children
+        .iter()
+        .map(|child| {
+            if let Some(uuid) = child.attributes.get("uuid") {
+                return (format!("{}[{}]", child.name, uuid), *child);
+            }
+            let repeated = children
+                .iter()
+                .filter(|c| c.name == child.name && !c.attributes.contains_key("uuid"))
+                .count()
+                > 1;
+            if !repeated {
+                return (child.name.clone(), *child);
+            }
+            let index = match seen.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    seen.push((&child.name, 1));
+                    1
+                }
+            };
+            (format!("{}[{}]", child.name, index), *child)
+        })
+        .collect()
+}
+
+/// Trimmed text and CDATA directly inside `el`.
+fn text_of(el: &Element) -> String {
+    el.children
+        .iter()
+        .filter_map(|node| match node {
+            XMLNode::Text(text) | XMLNode::CData(text) => Some(text.trim()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn shown(value: &str) -> &str {
+    if value.is_empty() {
+        "(empty)"
+    } else {
+        value
+    }
+}
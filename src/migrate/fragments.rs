@@ -0,0 +1,88 @@
+//! Changed-section output for `EmitMode::ChangesOnly`.
+
+use anyhow::Result;
+use std::io::Write;
+use xmltree::Element;
+
+use crate::xml_helpers::{get_child_ci, write_element};
+use crate::OutputStyle;
+
+/// Sections a migration may change: the first descendant named like the
+/// first entry, then children named like the rest.
+const SECTIONS: &[&[&str]] = &[
+    &["dhcpd"],
+    &["dhcpdv6"],
+    &["Kea", "dhcp4"],
+    &["Kea", "dhcp6"],
+    &["dnsmasq"],
+    &["unboundplus"],
+];
+
+/// A copy of each section, taken before conversion.
+pub(crate) struct SectionSnapshot(Vec<Option<Element>>);
+
+impl SectionSnapshot {
+    pub(crate) fn take(root: &Element) -> Self {
+        SectionSnapshot(
+            SECTIONS
+                .iter()
+                .map(|names| find_section(root, names).map(|(_, el)| el.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// Write each section of `root` that differs from `before` as a standalone
+/// XML fragment in `style`, preceded by a comment with its path from the root. Returns
+/// the paths written.
+pub(crate) fn write_changed_sections<W: Write>(
+    before: &SectionSnapshot,
+    root: &Element,
+    mut writer: W,
+    style: &OutputStyle,
+) -> Result<Vec<String>> {
+    let mut written = Vec::new();
+    for (names, old) in SECTIONS.iter().zip(&before.0) {
+        let Some((path, section)) = find_section(root, names) else {
+            continue;
+        };
+        if old.as_ref() == Some(section) {
+            continue;
+        }
+        if !written.is_empty() {
+            writer.write_all(b"\n")?;
+        }
+        writeln!(writer, "<!-- {} -->", path)?;
+        write_element(section, &mut writer, style)?;
+        writer.write_all(b"\n")?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// The section at `names` with its `/`-separated path from `root`.
+fn find_section<'a>(root: &'a Element, names: &[&str]) -> Option<(String, &'a Element)> {
+    let (first, rest) = names.split_first()?;
+    let mut path = vec![root.name.clone()];
+    let mut section = find_path(root, first, &mut path)?;
+    for name in rest {
+        section = get_child_ci(section, name)?;
+        path.push(section.name.clone());
+    }
+    Some((path.join("/"), section))
+}
+
+/// Depth-first search for `name`, recording the element names on the way.
+fn find_path<'a>(el: &'a Element, name: &str, path: &mut Vec<String>) -> Option<&'a Element> {
+    for child in el.children.iter().filter_map(|n| n.as_element()) {
+        path.push(child.name.clone());
+        if child.name.eq_ignore_ascii_case(name) {
+            return Some(child);
+        }
+        if let Some(found) = find_path(child, name, path) {
+            return Some(found);
+        }
+        path.pop();
+    }
+    None
+}
@@ -0,0 +1,133 @@
+//! HA config sync (`<hasync>`) checks and configs for the backup node.
+//!
+//! A CARP primary with XMLRPC sync pushes its DHCP sections to the backup.
+//! Converting only the primary leaves the backup on ISC DHCP until the next
+//! sync, and the sync carries nothing when the new backend is not a sync
+//! item, so converting a sync primary needs `allow_ha`.
+
+use anyhow::Result;
+#[cfg(feature = "cli")]
+use std::collections::HashMap;
+use xmltree::Element;
+
+use crate::xml_helpers::get_child_ci;
+#[cfg(feature = "cli")]
+use crate::xml_helpers::{get_children_ci, get_mut_child_ci, set_text_preserving_comments};
+use crate::{HaSync, MigrationError, MigrationOptions};
+
+/// Sync items that carry DHCP settings.
+const DHCP_SYNC_ITEMS: &[&str] = &["dhcpd", "dhcpdv6", "kea", "dnsmasq"];
+
+fn text(el: &Element, name: &str) -> Option<String> {
+    get_child_ci(el, name)
+        .and_then(|e| e.get_text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// The DHCP config sync of `root`, when it syncs DHCP sections to a peer.
+/// Sync items are read from `<syncitems>` (comma-separated) and from the
+/// older `<synchronize{item}>` flags.
+pub(crate) fn ha_sync(root: &Element) -> Option<HaSync> {
+    let hasync = get_child_ci(root, "hasync")?;
+    let peer = text(hasync, "synchronizetoip")?;
+    let items = text(hasync, "syncitems").unwrap_or_default();
+    let sections: Vec<String> = DHCP_SYNC_ITEMS
+        .iter()
+        .filter(|item| {
+            items
+                .split(',')
+                .any(|i| i.trim().eq_ignore_ascii_case(item))
+                || text(hasync, &format!("synchronize{}", item)).is_some()
+        })
+        .map(|item| item.to_string())
+        .collect();
+    if sections.is_empty() {
+        return None;
+    }
+    Some(HaSync { peer, sections })
+}
+
+/// Refuse to convert a sync primary unless `allow_ha` is set.
+pub(crate) fn ensure_ha_allowed(ha: Option<&HaSync>, options: &MigrationOptions) -> Result<()> {
+    match ha {
+        Some(ha) if !options.allow_ha => Err(MigrationError::HaSyncEnabled {
+            peer: ha.peer.clone(),
+            sections: ha.sections.join(", "),
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Turn the converted config of a sync primary into one for its backup.
+///
+/// Each interface with an ISC failover peer (`<failover_peerip>`) takes the
+/// peer's address, and the failover peer becomes the primary's address. The
+/// backup stops syncing to the primary, and its pfsync peer becomes the
+/// primary's address on the pfsync interface. Returns the ISC DHCP
+/// interfaces without a failover peer, which keep the primary's address.
+#[cfg(feature = "cli")]
+pub(crate) fn peer_config(root: &mut Element) -> Vec<String> {
+    let own_addrs: HashMap<String, String> = get_child_ci(root, "interfaces")
+        .map(|interfaces| {
+            interfaces
+                .children
+                .iter()
+                .filter_map(|n| n.as_element())
+                .filter_map(|iface| {
+                    text(iface, "ipaddr").map(|ip| (iface.name.to_ascii_lowercase(), ip))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut unchanged = Vec::new();
+    let mut peer_addrs = Vec::new();
+    for dhcpd in get_children_ci(root, "dhcpd") {
+        for iface in dhcpd.children.iter().filter_map(|n| n.as_element()) {
+            match text(iface, "failover_peerip") {
+                Some(peer) => peer_addrs.push((iface.name.clone(), peer)),
+                None => unchanged.push(iface.name.clone()),
+            }
+        }
+    }
+
+    for (iface, peer) in &peer_addrs {
+        let key = iface.to_ascii_lowercase();
+        if let Some(el) = get_mut_child_ci(root, "interfaces")
+            .and_then(|interfaces| get_mut_child_ci(interfaces, iface))
+            .and_then(|el| get_mut_child_ci(el, "ipaddr"))
+        {
+            set_text_preserving_comments(el, peer);
+        }
+        if let Some(own) = own_addrs.get(&key) {
+            for dhcpd in root
+                .children
+                .iter_mut()
+                .filter_map(|n| n.as_mut_element())
+                .filter(|el| el.name.eq_ignore_ascii_case("dhcpd"))
+            {
+                if let Some(el) = get_mut_child_ci(dhcpd, iface)
+                    .and_then(|el| get_mut_child_ci(el, "failover_peerip"))
+                {
+                    set_text_preserving_comments(el, own);
+                }
+            }
+        }
+    }
+
+    if let Some(hasync) = get_mut_child_ci(root, "hasync") {
+        let pfsync_addr = text(hasync, "pfsyncinterface")
+            .and_then(|iface| own_addrs.get(&iface.to_ascii_lowercase()))
+            .cloned();
+        if let Some(el) = get_mut_child_ci(hasync, "synchronizetoip") {
+            set_text_preserving_comments(el, "");
+        }
+        if let (Some(addr), Some(el)) = (pfsync_addr, get_mut_child_ci(hasync, "pfsyncpeerip")) {
+            set_text_preserving_comments(el, &addr);
+        }
+    }
+
+    unchanged
+}
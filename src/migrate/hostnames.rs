@@ -0,0 +1,165 @@
+//! Hostnames shared by entries with different addresses.
+//!
+//! dnsmasq answers DNS for its hosts and Kea DDNS registers its
+//! reservations, so one name on several addresses of a family gives
+//! answers that change with every lease. The check covers the target
+//! backend's existing entries and the ISC mappings migrated to it. Names are
+//! compared case-insensitively as written; a dual-stack host with one IPv4
+//! and one IPv6 address is not a collision.
+
+use std::collections::BTreeMap;
+use xmltree::Element;
+
+use crate::backend::Backend;
+use crate::migrate::existing::ExistingState;
+use crate::{HostnameCollision, IscStaticMap, IscStaticMapV6};
+
+/// Addresses seen per lowercase name: the name as first written, then the
+/// IPv4 and IPv6 addresses in the order they were found.
+type Names = BTreeMap<String, (String, Vec<String>, Vec<String>)>;
+
+/// Hostnames that `backend` would have on more than one address of a family
+/// once `mappings` and `mappings_v6` are migrated.
+#[cfg_attr(
+    not(any(feature = "kea", feature = "dnsmasq")),
+    allow(unused_variables)
+)]
+pub(crate) fn hostname_collisions(
+    root: &Element,
+    backend: &Backend,
+    mappings: &[IscStaticMap],
+    mappings_v6: &[IscStaticMapV6],
+) -> Vec<HostnameCollision> {
+    let mut names = Names::new();
+    #[cfg(feature = "kea")]
+    if matches!(backend, Backend::Kea | Backend::Both) {
+        add_existing(&ExistingState::kea(root), &mut names);
+    }
+    #[cfg(feature = "dnsmasq")]
+    if matches!(backend, Backend::Dnsmasq | Backend::Both) {
+        add_existing(&ExistingState::dnsmasq(root), &mut names);
+    }
+    for m in mappings {
+        add(&mut names, m.hostname.as_deref(), &m.ipaddr);
+    }
+    for m in mappings_v6 {
+        add(&mut names, m.hostname.as_deref(), &m.ipaddr);
+    }
+
+    let mut collisions = Vec::new();
+    for (hostname, v4, v6) in names.into_values() {
+        for ips in [v4, v6] {
+            if ips.len() > 1 {
+                collisions.push(HostnameCollision {
+                    hostname: hostname.clone(),
+                    ips,
+                });
+            }
+        }
+    }
+    collisions
+}
+
+/// Fold the collisions of one backend into `into`, joining the addresses
+/// of a name both report for the same family.
+pub(crate) fn merge_collisions(into: &mut Vec<HostnameCollision>, from: &[HostnameCollision]) {
+    for collision in from {
+        let v6 = is_v6(&collision.ips[0]);
+        match into.iter_mut().find(|c| {
+            c.hostname.eq_ignore_ascii_case(&collision.hostname) && is_v6(&c.ips[0]) == v6
+        }) {
+            Some(existing) => {
+                for ip in &collision.ips {
+                    if !existing.ips.contains(ip) {
+                        existing.ips.push(ip.clone());
+                    }
+                }
+            }
+            None => into.push(collision.clone()),
+        }
+    }
+}
+
+/// Add the hostnames indexed in `existing`.
+fn add_existing(existing: &ExistingState, names: &mut Names) {
+    for (hostname, ips) in existing.hostnames.values() {
+        for ip in ips {
+            add(names, Some(hostname), ip);
+        }
+    }
+}
+
+fn add(names: &mut Names, hostname: Option<&str>, ip: &str) {
+    let (Some(hostname), ip) = (hostname.map(str::trim), ip.trim()) else {
+        return;
+    };
+    if hostname.is_empty() || ip.is_empty() {
+        return;
+    }
+    let (_, v4, v6) = names
+        .entry(hostname.to_ascii_lowercase())
+        .or_insert_with(|| (hostname.to_string(), Vec::new(), Vec::new()));
+    let ips = if is_v6(ip) { v6 } else { v4 };
+    if !ips.iter().any(|seen| seen == ip) {
+        ips.push(ip.to_string());
+    }
+}
+
+fn is_v6(ip: &str) -> bool {
+    ip.contains(':')
+}
+
+#[cfg(all(test, feature = "dnsmasq"))]
+mod tests {
+    use super::*;
+
+    fn mapping(hostname: &str, ip: &str) -> IscStaticMap {
+        IscStaticMap {
+            iface: "lan".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            ipaddr: ip.to_string(),
+            hostname: Some(hostname.to_string()),
+            cid: None,
+            descr: None,
+            domain: None,
+            domain_search: None,
+            dns_servers: Vec::new(),
+            gateway: None,
+            gateway_suppressed: false,
+        }
+    }
+
+    #[test]
+    fn test_hostname_collisions_per_family() {
+        let root = Element::parse(
+            br#"<opnsense><dnsmasq><hosts><host>nas</host><ip>10.0.0.5,fd00::5</ip></hosts></dnsmasq></opnsense>"#
+                .as_slice(),
+        )
+        .unwrap();
+        let mappings = [mapping("NAS", "10.0.0.5"), mapping("pc", "10.0.0.6")];
+        assert!(hostname_collisions(&root, &Backend::Dnsmasq, &mappings, &[]).is_empty());
+
+        let mappings = [mapping("nas", "10.0.0.7")];
+        let collisions = hostname_collisions(&root, &Backend::Dnsmasq, &mappings, &[]);
+        assert_eq!(
+            collisions,
+            [HostnameCollision {
+                hostname: "nas".to_string(),
+                ips: vec!["10.0.0.5".to_string(), "10.0.0.7".to_string()],
+            }]
+        );
+        // Kea does not see the dnsmasq host
+        assert!(hostname_collisions(&root, &Backend::Kea, &mappings, &[]).is_empty());
+
+        let mut merged = collisions.clone();
+        merge_collisions(
+            &mut merged,
+            &[HostnameCollision {
+                hostname: "NAS".to_string(),
+                ips: vec!["10.0.0.7".to_string(), "10.0.0.8".to_string()],
+            }],
+        );
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].ips, ["10.0.0.5", "10.0.0.7", "10.0.0.8"]);
+    }
+}
@@ -0,0 +1,183 @@
+//! Hardware addresses of ISC static mappings.
+//!
+//! ISC stores the address as typed, so besides 6-octet Ethernet MACs a
+//! mapping may carry a 20-octet InfiniBand address or another link layer's.
+//! Kea accepts any hardware address up to [`KEA_MAX_HWADDR_OCTETS`]; the
+//! dnsmasq GUI only takes Ethernet MACs.
+
+use crate::parse;
+#[cfg(feature = "kea")]
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::IscStaticMap;
+#[cfg(feature = "kea")]
+use crate::MigrationOptions;
+
+#[cfg(feature = "kea")]
+/// Longest hardware address Kea stores (`HWAddr::MAX_HWADDR_LEN`).
+const KEA_MAX_HWADDR_OCTETS: usize = 20;
+const ETHERNET_OCTETS: usize = 6;
+const INFINIBAND_OCTETS: usize = 20;
+
+#[cfg(feature = "kea")]
+/// Lowercase `:`-separated form of `hwaddr`, or `hwaddr` as written when
+/// it does not parse.
+pub(crate) fn normalize_hwaddr(hwaddr: &str) -> String {
+    match parse::hwaddr(hwaddr) {
+        Ok(octets) => format_octets(&octets),
+        Err(_) => hwaddr.trim().to_string(),
+    }
+}
+
+#[cfg(feature = "kea")]
+fn format_octets(octets: &[u8]) -> String {
+    octets
+        .iter()
+        .map(|octet| format!("{:02x}", octet))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Link layer guessed from the address length, for messages.
+fn hwaddr_kind(octets: usize) -> String {
+    match octets {
+        ETHERNET_OCTETS => "Ethernet".to_string(),
+        INFINIBAND_OCTETS => "InfiniBand".to_string(),
+        n => format!("{}-octet", n),
+    }
+}
+
+/// Rewrite mapping hardware addresses into Kea's form and drop those Kea
+/// cannot store. Returns how many mappings were dropped.
+#[cfg(feature = "kea")]
+pub(crate) fn prepare_kea_hwaddrs(
+    mappings: &mut Vec<IscStaticMap>,
+    options: &MigrationOptions,
+) -> usize {
+    let total = mappings.len();
+    mappings.retain_mut(|m| match parse::hwaddr(&m.mac) {
+        Ok(octets) if octets.len() <= KEA_MAX_HWADDR_OCTETS => {
+            if octets.len() != ETHERNET_OCTETS && options.verbose {
+                term::action(
+                    "HWADDR",
+                    format_args!(
+                        "{} ({}) - {} hardware address",
+                        m.ipaddr,
+                        m.mac,
+                        hwaddr_kind(octets.len())
+                    ),
+                );
+            }
+            m.mac = format_octets(&octets);
+            true
+        }
+        octets => {
+            let reason = match octets {
+                Ok(octets) => format!(
+                    "{} octets, Kea takes at most {}",
+                    octets.len(),
+                    KEA_MAX_HWADDR_OCTETS
+                ),
+                Err(err) => err.to_string(),
+            };
+            warn_dropped(m, "Kea", &reason);
+            false
+        }
+    });
+    total - mappings.len()
+}
+
+/// Drop mappings with a well-formed hardware address that is not an
+/// Ethernet MAC, which dnsmasq hosts cannot carry. Malformed addresses are
+/// kept for host validation to report. Returns how many were dropped.
+#[cfg(feature = "dnsmasq")]
+pub(crate) fn drop_non_ethernet_hwaddrs(mappings: &mut Vec<IscStaticMap>) -> usize {
+    let total = mappings.len();
+    mappings.retain(|m| match parse::hwaddr(&m.mac) {
+        Ok(octets) if octets.len() != ETHERNET_OCTETS => {
+            let reason = format!(
+                "{} hardware address, dnsmasq only takes Ethernet MACs",
+                hwaddr_kind(octets.len())
+            );
+            warn_dropped(m, "dnsmasq", &reason);
+            false
+        }
+        _ => true,
+    });
+    total - mappings.len()
+}
+
+fn warn_dropped(m: &IscStaticMap, backend: &str, reason: &str) {
+    warn(
+        WarningKind::MappingDropped,
+        format_args!(
+            "Cannot migrate {} ({}) on {} to {}: {}. Skipping.",
+            m.ipaddr, m.mac, m.iface, backend, reason
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(mac: &str) -> IscStaticMap {
+        IscStaticMap {
+            iface: "lan".to_string(),
+            mac: mac.to_string(),
+            ipaddr: "192.168.1.10".to_string(),
+            hostname: None,
+            cid: None,
+            descr: None,
+            domain: None,
+            domain_search: None,
+            dns_servers: Vec::new(),
+            gateway: None,
+            gateway_suppressed: false,
+        }
+    }
+
+    const INFINIBAND: &str = "80:00:02:08:FE:80:00:00:00:00:00:00:00:02:C9:03:00:0A:0B:0C";
+
+    #[test]
+    #[cfg(feature = "kea")]
+    fn test_prepare_kea_hwaddrs() {
+        assert_eq!(normalize_hwaddr("0-11-22-AA-bb-c"), "00:11:22:aa:bb:0c");
+        assert_eq!(normalize_hwaddr(" not-a-mac "), "not-a-mac");
+
+        let mut mappings = vec![
+            mapping("00-11-22-AA-BB-CC"),
+            mapping(INFINIBAND),
+            mapping(&format!("{}:0d", INFINIBAND)),
+            mapping("not-a-mac"),
+        ];
+        let dropped = prepare_kea_hwaddrs(&mut mappings, &MigrationOptions::default());
+        assert_eq!(dropped, 2);
+        let macs: Vec<&str> = mappings.iter().map(|m| m.mac.as_str()).collect();
+        assert_eq!(
+            macs,
+            [
+                "00:11:22:aa:bb:cc",
+                "80:00:02:08:fe:80:00:00:00:00:00:00:00:02:c9:03:00:0a:0b:0c"
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dnsmasq")]
+    fn test_drop_non_ethernet_hwaddrs() {
+        let mut mappings = vec![
+            mapping("00:11:22:33:44:55"),
+            mapping(INFINIBAND),
+            mapping("00-11-22-33-44-66"),
+            mapping("not-a-mac"),
+        ];
+        let dropped = drop_non_ethernet_hwaddrs(&mut mappings);
+        assert_eq!(dropped, 1);
+        let macs: Vec<&str> = mappings.iter().map(|m| m.mac.as_str()).collect();
+        assert_eq!(
+            macs,
+            ["00:11:22:33:44:55", "00-11-22-33-44-66", "not-a-mac"]
+        );
+    }
+}
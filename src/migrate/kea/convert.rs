@@ -1,25 +1,42 @@
 use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
 use xmltree::{Element, XMLNode};
 
 use crate::extract::{
-    extract_existing_reservation_duids_v6, extract_existing_reservation_ips,
-    extract_existing_reservation_ips_v6, extract_interface_cidrs, extract_interface_cidrs_v6,
-    extract_isc_options_v4, extract_isc_options_v6, extract_kea_subnets, extract_kea_subnets_v6,
-    has_kea_dhcp4, has_kea_dhcp6,
+    extract_interface_cidrs, extract_interface_cidrs_v6, extract_interface_labels,
+    extract_kea_subnets, extract_kea_subnets_v6, has_kea_dhcp4, has_kea_dhcp6,
+    iter_isc_number_options_v4,
 };
-use crate::migrate_v4::{create_reservation_element, get_reservations_node};
-use crate::migrate_v6::{create_reservation_element_v6, get_reservations_node_v6};
+use crate::migrate_v4::{create_reservation, get_reservations_node};
+use crate::migrate_v6::{create_reservation_v6, get_reservations_node_v6};
+use crate::plan::{planned_subnet_v4, planned_subnet_v6};
 use crate::subnet::{find_subnet_for_ip, find_subnet_for_ip_v6};
-use crate::{IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions, MigrationStats};
+use crate::term;
+use crate::{
+    FirewallRulesStatus, IscStaticMap, IscStaticMapV6, KeaInterfacePolicy, MigrationError,
+    MigrationOptions, MigrationStats,
+};
 
-use crate::migrate::options::apply_kea_options;
+use super::options::{apply_kea_options, OptionCounts};
+use super::subnets::{
+    apply_kea_interfaces, apply_kea_subnets, carve_pools_v4, carve_pools_v6, check_kea_listening,
+};
+use super::unmatched::{
+    drop_unmatched_mappings, is_subnet_not_found, unmatched_v4, unmatched_v6, IfaceContext,
+};
+use super::{served_iface, served_iface_v6, short_uuid};
+use crate::migrate::existing::{DuidRegistryV6, ExistingState};
+use crate::migrate::hwaddr::prepare_kea_hwaddrs;
+use crate::migrate::option_values::checked_isc_options;
+use crate::migrate::options::options_merge_policy;
 use crate::migrate::services::{
-    disable_isc_dhcp_from_config, enable_kea, ensure_isc_was_enabled, verify_isc_disabled,
+    disable_isc_dhcp_from_config, enable_kea, ensure_isc_was_enabled, ensure_kea_fwrules,
+    verify_isc_disabled,
 };
-use crate::migrate::subnets::{
-    apply_kea_interfaces, apply_kea_subnets, desired_subnets_v4, desired_subnets_v6,
+use crate::migrate::subnets::{desired_subnets_v4, desired_subnets_v6, merge_desired_ranges};
+use crate::migrate::utils::{
+    conflict_v4, validate_mapping_ifaces_v4, validate_mapping_ifaces_v6, warn_unmigrated_prefixes,
 };
-use crate::migrate::utils::{short_uuid, validate_mapping_ifaces_v4, validate_mapping_ifaces_v6};
 
 /// Convert an input configuration into Kea reservations.
 pub(crate) fn convert_kea(
@@ -29,35 +46,29 @@ pub(crate) fn convert_kea(
     options: &MigrationOptions,
 ) -> Result<MigrationStats> {
     let mut kea_subnets = extract_kea_subnets(root)?;
-    let existing_ips = extract_existing_reservation_ips(root)?;
+    let existing = ExistingState::kea(root);
     let mut kea_subnets_v6 = extract_kea_subnets_v6(root)?;
-    let existing_ips_v6 = extract_existing_reservation_ips_v6(root)?;
-    let existing_duids_v6 = extract_existing_reservation_duids_v6(root)?;
+    let existing_v6 = DuidRegistryV6::new(&existing, options.global_duids);
     let iface_cidrs_v4 = extract_interface_cidrs(root)?;
     let iface_cidrs_v6 = extract_interface_cidrs_v6(root)?;
-    let want_desired = options.create_subnets || options.enable_backend;
-    let desired_v4 = if want_desired {
+    let want_desired = options.create_subnets || options.create_pools || options.enable_backend;
+    let mut desired_v4 = if want_desired {
         desired_subnets_v4(root)?
     } else {
         Vec::new()
     };
-    let desired_v6 = if want_desired {
+    let mut desired_v6 = if want_desired {
         desired_subnets_v6(root)?
     } else {
         Vec::new()
     };
-    let options_v4 = if options.create_options {
-        extract_isc_options_v4(root)?
-    } else {
-        Vec::new()
-    };
-    let options_v6 = if options.create_options {
-        extract_isc_options_v6(root)?
-    } else {
-        Vec::new()
-    };
-    let mut interfaces_configured = Vec::new();
-    if options.create_subnets {
+    let ranges_merged = merge_desired_ranges(&mut desired_v4, &mut desired_v6, options);
+    if options.carve_pools {
+        carve_pools_v4(&mut desired_v4, isc_mappings, options);
+        carve_pools_v6(&mut desired_v6, isc_mappings_v6, options);
+    }
+    let (options_v4, options_v6, option_values) = checked_isc_options(root, options);
+    let (subnets_created, pools_created) = if options.create_subnets || options.create_pools {
         apply_kea_subnets(
             root,
             &mut kea_subnets,
@@ -65,17 +76,57 @@ pub(crate) fn convert_kea(
             &desired_v4,
             &desired_v6,
             options,
-        )?;
-        interfaces_configured = apply_kea_interfaces(root, &desired_v4, &desired_v6)?;
-    }
+        )?
+    } else {
+        (0, 0)
+    };
 
+    let mut option_counts = OptionCounts::default();
     if options.create_options {
-        apply_kea_options(root, &options_v4, &options_v6, options.force_options)?;
+        option_counts = apply_kea_options(
+            root,
+            &options_v4,
+            &options_v6,
+            options_merge_policy(options),
+        )?;
+        let custom = iter_isc_number_options_v4(root).count();
+        if custom > 0 {
+            term::warning(format_args!(
+                "{} custom numbered ISC DHCPv4 option(s) have no field in the OPNsense Kea model \
+                 and are not migrated. \
+                 `export --format kea-dhcp4` writes them with option-def entries.",
+                custom
+            ));
+        }
+        let unplaced_v6: usize = options_v6
+            .iter()
+            .map(|opt| {
+                usize::from(!opt.ntp_servers.is_empty())
+                    + usize::from(!opt.sip_servers.is_empty())
+                    + usize::from(opt.bootfile_url.is_some())
+            })
+            .sum();
+        if unplaced_v6 > 0 {
+            term::warning(format_args!(
+                "{} ISC DHCPv6 NTP, SIP server or boot file URL option(s) have no field in the \
+                 OPNsense Kea model and are not migrated. \
+                 dnsmasq carries them as option6 31, 22 and 59.",
+                unplaced_v6
+            ));
+        }
+        let no_router = isc_mappings
+            .iter()
+            .filter(|mapping| mapping.gateway_suppressed)
+            .count();
+        if no_router > 0 {
+            term::warning(format_args!(
+                "{} static mapping(s) send no gateway (gateway none), which a Kea reservation \
+                 cannot express; they get the subnet's router unless changed by hand.",
+                no_router
+            ));
+        }
     }
 
-    validate_mapping_ifaces_v4(isc_mappings, &iface_cidrs_v4)?;
-    validate_mapping_ifaces_v6(isc_mappings_v6, &iface_cidrs_v6)?;
-
     // Early check: differentiate between "Kea not configured" vs "no subnets"
     if !isc_mappings.is_empty() && kea_subnets.is_empty() && !options.create_subnets {
         if !has_kea_dhcp4(root) {
@@ -131,53 +182,106 @@ pub(crate) fn convert_kea(
         }
     }
 
+    let total_v4 = isc_mappings.len();
+    let total_v6 = isc_mappings_v6.len();
+    let mut skips = Vec::new();
+    let labels = extract_interface_labels(root);
+    let ifaces = IfaceContext {
+        labels: &labels,
+        cidrs_v4: &iface_cidrs_v4,
+        cidrs_v6: &iface_cidrs_v6,
+    };
+    let (mut isc_mappings, isc_mappings_v6) = if options.create_subnets {
+        drop_unmatched_mappings(
+            isc_mappings,
+            isc_mappings_v6,
+            &kea_subnets,
+            &kea_subnets_v6,
+            &ifaces,
+            options,
+            &mut skips,
+        )?
+    } else {
+        (isc_mappings.to_vec(), isc_mappings_v6.to_vec())
+    };
+    prepare_kea_hwaddrs(&mut isc_mappings, options);
+
+    validate_mapping_ifaces_v4(&isc_mappings, &iface_cidrs_v4)?;
+    validate_mapping_ifaces_v6(&isc_mappings_v6, &iface_cidrs_v6)?;
+    let prefixes_v6_unmigrated = warn_unmigrated_prefixes(&isc_mappings_v6, "Kea", true, options);
+
     // Check fail_if_existing flag
-    if options.fail_if_existing
-        && (!existing_ips.is_empty()
-            || !existing_ips_v6.is_empty()
-            || !existing_duids_v6.is_empty())
-    {
-        return Err(anyhow!(
-            "Existing reservations found ({} IPs) and --fail-if-existing is set. Aborting.",
-            existing_ips.len() + existing_ips_v6.len()
-        ));
+    if options.fail_if_existing && (!existing.ips.is_empty() || !existing_v6.is_empty()) {
+        return Err(MigrationError::ExistingReservations {
+            count: existing.ips.len() + existing_v6.ip_count(),
+        }
+        .into());
     }
 
     let mut to_create = 0;
-    let mut skipped = 0;
-    let mut reserved_ips = existing_ips;
+    let mut skipped = total_v4 - isc_mappings.len();
+    let ExistingState {
+        ips: mut reserved_ips,
+        macs: mut reserved_macs,
+        client_ids: mut reserved_client_ids,
+        ..
+    } = existing;
+    let mut migrated_ifaces_v4 = BTreeSet::new();
+    let mut migrated_ifaces_v6 = BTreeSet::new();
+    let mut served_v4 = BTreeSet::new();
+    let mut served_v6 = BTreeSet::new();
 
     if options.verbose {
-        println!("\nProcessing {} ISC static mappings:", isc_mappings.len());
+        println!("\nProcessing {} ISC static mappings:", total_v4);
         if !isc_mappings_v6.is_empty() {
-            println!(
-                "Processing {} ISC DHCPv6 static mappings:",
-                isc_mappings_v6.len()
-            );
+            println!("Processing {} ISC DHCPv6 static mappings:", total_v6);
         }
     }
 
     let mut to_create_v6 = 0;
-    let mut skipped_v6 = 0;
-    let mut reserved_ips_v6 = existing_ips_v6;
-    let mut reserved_duids_v6 = existing_duids_v6;
+    let mut skipped_v6 = total_v6 - isc_mappings_v6.len();
+    let mut prefix_reservations_v6 = 0;
+    let mut reserved_v6 = existing_v6;
+    let mut outside = Vec::new();
 
     if !isc_mappings.is_empty() {
         let reservations_node = get_reservations_node(root)?;
 
-        for mapping in isc_mappings {
-            if reserved_ips.contains(&mapping.ipaddr) {
+        for mapping in &isc_mappings {
+            if let Some(conflict) = conflict_v4(
+                mapping,
+                &reserved_ips,
+                &reserved_macs,
+                &reserved_client_ids,
+                mapping.cid.is_some(),
+            ) {
                 skipped += 1;
+                skips.push(conflict);
                 if options.verbose {
-                    println!(
-                        "  SKIP: {} ({}) - IP already reserved",
-                        mapping.ipaddr, mapping.mac
+                    term::action(
+                        "SKIP",
+                        format_args!(
+                            "{} ({}) - IP, MAC or client ID already reserved",
+                            mapping.ipaddr, mapping.mac
+                        ),
                     );
                 }
                 continue;
             }
 
-            let subnet_uuid = find_subnet_for_ip(&mapping.ipaddr, &kea_subnets)?;
+            let subnet_uuid = match planned_subnet_v4(options.plan.as_ref(), mapping, &kea_subnets)?
+            {
+                Some(uuid) => uuid,
+                None => match find_subnet_for_ip(&mapping.ipaddr, &kea_subnets) {
+                    Ok(uuid) => uuid,
+                    Err(err) if is_subnet_not_found(&err) => {
+                        outside.push(unmatched_v4(mapping, &kea_subnets, &ifaces));
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                },
+            };
+            served_v4.insert(served_iface(&subnet_uuid, &kea_subnets, &mapping.iface));
 
             if options.verbose {
                 let hostname = mapping
@@ -186,67 +290,110 @@ pub(crate) fn convert_kea(
                     .or(mapping.cid.as_ref())
                     .map(|s| s.as_str())
                     .unwrap_or("<no hostname>");
-                println!(
-                    "  ADD: {} ({}) -> subnet {} [{}]",
-                    mapping.ipaddr,
-                    mapping.mac,
-                    short_uuid(&subnet_uuid),
-                    hostname
+                term::action(
+                    "ADD",
+                    format_args!(
+                        "{} ({}) -> subnet {} [{}]",
+                        mapping.ipaddr,
+                        mapping.mac,
+                        short_uuid(&subnet_uuid),
+                        hostname
+                    ),
                 );
             }
 
-            let reservation = create_reservation_element(mapping, &subnet_uuid);
+            let mut reservation = create_reservation(mapping, &subnet_uuid);
+            if options.create_options {
+                // Per-host overrides of the subnet's options
+                reservation.domain_name_servers = mapping.dns_servers.join(",");
+                reservation.routers = mapping.gateway.clone().unwrap_or_default();
+            }
             reservations_node
                 .children
-                .push(XMLNode::Element(reservation));
+                .push(XMLNode::Element(reservation.to_element()));
             reserved_ips.insert(mapping.ipaddr.clone());
+            reserved_macs.insert(mapping.mac.clone());
+            reserved_client_ids.extend(mapping.cid.clone());
+            migrated_ifaces_v4.insert(mapping.iface.clone());
             to_create += 1;
         }
     }
 
     if !isc_mappings_v6.is_empty() {
         let reservations_node_v6 = get_reservations_node_v6(root)?;
-        for mapping in isc_mappings_v6 {
-            if reserved_ips_v6.contains(&mapping.ipaddr)
-                || reserved_duids_v6.contains(&mapping.duid)
-            {
+        for mapping in &isc_mappings_v6 {
+            // Duplicates are found within the mapping's subnet
+            let subnet_uuid =
+                match planned_subnet_v6(options.plan.as_ref(), mapping, &kea_subnets_v6)? {
+                    Some(uuid) => Ok(uuid),
+                    None => find_subnet_for_ip_v6(&mapping.ipaddr, &kea_subnets_v6),
+                };
+            let scope = subnet_uuid.as_deref().unwrap_or_default();
+            if let Some(conflict) = reserved_v6.conflict(mapping, scope) {
                 skipped_v6 += 1;
+                skips.push(conflict);
                 if options.verbose {
-                    println!(
-                        "  SKIP6: {} ({}) - IP or DUID already reserved",
-                        mapping.ipaddr, mapping.duid
+                    term::action(
+                        "SKIP6",
+                        format_args!(
+                            "{} ({}) - IP or DUID already reserved",
+                            mapping.ipaddr, mapping.duid
+                        ),
                     );
                 }
                 continue;
             }
 
-            let subnet_uuid = find_subnet_for_ip_v6(&mapping.ipaddr, &kea_subnets_v6)?;
+            let subnet_uuid = match subnet_uuid {
+                Ok(uuid) => uuid,
+                Err(err) if is_subnet_not_found(&err) => {
+                    outside.push(unmatched_v6(mapping, &kea_subnets_v6, &ifaces));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            served_v6.insert(served_iface_v6(
+                &subnet_uuid,
+                &kea_subnets_v6,
+                &mapping.iface,
+            ));
 
             if options.verbose {
                 let hostname = mapping.hostname.as_deref().unwrap_or("<no hostname>");
-                println!(
-                    "  ADD6: {} ({}) -> subnet {} [{}]",
-                    mapping.ipaddr,
-                    mapping.duid,
-                    short_uuid(&subnet_uuid),
-                    hostname
+                term::action(
+                    "ADD6",
+                    format_args!(
+                        "{} ({}) -> subnet {} [{}]",
+                        mapping.ipaddr,
+                        mapping.duid,
+                        short_uuid(&subnet_uuid),
+                        hostname
+                    ),
                 );
             }
 
-            let reservation = create_reservation_element_v6(mapping, &subnet_uuid);
+            let reservation = create_reservation_v6(mapping, &subnet_uuid);
             reservations_node_v6
                 .children
-                .push(XMLNode::Element(reservation));
-            reserved_ips_v6.insert(mapping.ipaddr.clone());
-            reserved_duids_v6.insert(mapping.duid.clone());
+                .push(XMLNode::Element(reservation.to_element()));
+            reserved_v6.insert(mapping, &subnet_uuid);
+            if mapping.delegated_prefix().is_some() {
+                prefix_reservations_v6 += 1;
+            }
+            migrated_ifaces_v6.insert(mapping.iface.clone());
             to_create_v6 += 1;
         }
     }
+    if !outside.is_empty() {
+        return Err(MigrationError::MappingsOutsideSubnets { mappings: outside }.into());
+    }
 
     let mut isc_disabled_v4 = Vec::new();
     let mut isc_disabled_v6 = Vec::new();
     let mut backend_enabled_v4 = false;
     let mut backend_enabled_v6 = false;
+    let mut fwrules_v4 = None;
+    let mut fwrules_v6 = None;
     if options.enable_backend {
         let (disabled_v4, disabled_v6) = disable_isc_dhcp_from_config(root)?;
         isc_disabled_v4 = disabled_v4;
@@ -268,24 +415,92 @@ pub(crate) fn convert_kea(
             ));
         }
 
+        if backend_enabled_v4 {
+            fwrules_v4 = Some(ensure_kea_fwrules(root, false));
+        }
+        if backend_enabled_v6 {
+            fwrules_v6 = Some(ensure_kea_fwrules(root, true));
+        }
+        for (status, family) in [(fwrules_v4, "DHCPv4"), (fwrules_v6, "DHCPv6")] {
+            if status == Some(FirewallRulesStatus::Missing) {
+                term::warning(format_args!(
+                    "Kea {} has no <fwrules> setting; make sure firewall rules allow DHCP on the \
+                     migrated interfaces.",
+                    family
+                ));
+            }
+        }
+
         verify_isc_disabled(root, &isc_disabled_v4, &isc_disabled_v6)?;
     }
 
+    // Make Kea listen on every interface it now serves: created subnets,
+    // migrated reservations, and interfaces taken over from ISC.
+    let mut interfaces_configured = Vec::new();
+    if options.create_subnets || options.enable_backend {
+        let mut ifaces_v4 = migrated_ifaces_v4;
+        let mut ifaces_v6 = migrated_ifaces_v6;
+        if options.create_subnets {
+            ifaces_v4.extend(desired_v4.iter().map(|s| s.iface.clone()));
+            ifaces_v6.extend(desired_v6.iter().map(|s| s.iface.clone()));
+        }
+        if options.enable_backend {
+            ifaces_v4.extend(isc_disabled_v4.iter().cloned());
+            ifaces_v6.extend(isc_disabled_v6.iter().cloned());
+        }
+        if kea_subnets.is_empty() {
+            ifaces_v4.clear();
+        }
+        if kea_subnets_v6.is_empty() {
+            ifaces_v6.clear();
+        }
+        interfaces_configured = apply_kea_interfaces(root, &ifaces_v4, &ifaces_v6)?;
+    }
+
+    // Otherwise Kea's interfaces are left alone, so check they cover the
+    // subnets the new reservations are in.
+    let mut interfaces_not_listening = Vec::new();
+    if !options.create_subnets && !options.enable_backend {
+        let (missing_v4, missing_v6) = check_kea_listening(root, &served_v4, &served_v6, options);
+        match options.kea_interfaces {
+            KeaInterfacePolicy::Append => {
+                interfaces_configured = apply_kea_interfaces(root, &missing_v4, &missing_v6)?;
+            }
+            KeaInterfacePolicy::Warn => {
+                interfaces_not_listening = missing_v4.union(&missing_v6).cloned().collect();
+            }
+        }
+    }
+
     Ok(MigrationStats {
-        isc_mappings_found: isc_mappings.len(),
-        isc_mappings_v6_found: isc_mappings_v6.len(),
+        option_values_resolved: option_values.resolved,
+        unresolved_option_values: option_values.unresolved,
+        isc_mappings_found: total_v4,
+        isc_mappings_v6_found: total_v6,
         isc_ranges_found: 0,
         isc_ranges_v6_found: 0,
+        ranges_merged,
+        subnets_created,
+        pools_created,
+        options_created: option_counts.created,
+        options_skipped: option_counts.skipped,
         target_subnets_found: kea_subnets.len(),
         target_subnets_v6_found: kea_subnets_v6.len(),
         reservations_to_create: to_create,
         reservations_v6_to_create: to_create_v6,
         reservations_skipped: skipped,
         reservations_v6_skipped: skipped_v6,
+        skips,
+        prefix_reservations_v6,
+        prefixes_v6_unmigrated,
         interfaces_configured,
+        interfaces_not_listening,
         isc_disabled_v4,
         isc_disabled_v6,
         backend_enabled_v4,
         backend_enabled_v6,
+        fwrules_v4,
+        fwrules_v6,
+        ..Default::default()
     })
 }
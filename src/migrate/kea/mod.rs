@@ -1,5 +1,73 @@
+use anyhow::Result;
+use xmltree::Element;
+
+use crate::extract::{extract_kea_subnets, extract_kea_subnets_v6};
+use crate::registry::MigrationBackend;
+use crate::{IscStaticMap, IscStaticMapV6, MigrationOptions, MigrationStats, Subnet, SubnetV6};
+
 pub(crate) use convert::convert_kea;
 pub(crate) use scan::scan_kea;
 
 mod convert;
+mod options;
 mod scan;
+mod subnets;
+mod unmatched;
+
+/// Built-in Kea DHCP backend.
+pub(crate) struct KeaBackend;
+
+impl MigrationBackend for KeaBackend {
+    fn name(&self) -> &str {
+        "kea"
+    }
+
+    fn scan(
+        &self,
+        root: &Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        scan_kea(root, mappings, mappings_v6, options)
+    }
+
+    fn convert(
+        &self,
+        root: &mut Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        convert_kea(root, mappings, mappings_v6, options)
+    }
+
+    fn stats(&self, root: &Element) -> Result<MigrationStats> {
+        Ok(MigrationStats {
+            target_subnets_found: extract_kea_subnets(root)?.len(),
+            target_subnets_v6_found: extract_kea_subnets_v6(root)?.len(),
+            ..Default::default()
+        })
+    }
+}
+
+pub(crate) fn short_uuid(uuid: &str) -> &str {
+    uuid.get(..8).unwrap_or(uuid)
+}
+
+/// Interface the subnet `uuid` is bound to, else the mapping's own.
+pub(crate) fn served_iface(uuid: &str, subnets: &[Subnet], mapping_iface: &str) -> String {
+    subnets
+        .iter()
+        .find(|s| s.uuid == uuid)
+        .and_then(|s| s.iface.clone())
+        .unwrap_or_else(|| mapping_iface.to_string())
+}
+
+pub(crate) fn served_iface_v6(uuid: &str, subnets: &[SubnetV6], mapping_iface: &str) -> String {
+    subnets
+        .iter()
+        .find(|s| s.uuid == uuid)
+        .and_then(|s| s.iface.clone())
+        .unwrap_or_else(|| mapping_iface.to_string())
+}
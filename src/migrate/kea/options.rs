@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Result};
+use xmltree::{Element, XMLNode};
+
+use crate::extract::{extract_interface_cidrs, extract_interface_cidrs_v6};
+use crate::migrate::options::join_list;
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::{IscDhcpOptionsV4, IscDhcpOptionsV6, OptionsMerge};
+
+/// `subnet4` option fields set from ISC netboot when the subnet is created.
+const NETBOOT_OPTION_DATA: &[&str] = &["tftp_server_name", "boot_file_name"];
+
+/// Option values written and left as they were by [`apply_kea_options`].
+#[derive(Debug, Default)]
+pub(crate) struct OptionCounts {
+    pub(crate) created: usize,
+    pub(crate) skipped: usize,
+}
+
+/// Apply ISC DHCP options into Kea option_data, per-interface.
+pub(crate) fn apply_kea_options(
+    root: &mut Element,
+    options_v4: &[IscDhcpOptionsV4],
+    options_v6: &[IscDhcpOptionsV6],
+    merge: OptionsMerge,
+) -> Result<OptionCounts> {
+    let force = merge == OptionsMerge::Overwrite;
+    let mut counts = OptionCounts::default();
+    let iface_cidrs_v4 = extract_interface_cidrs(root)?;
+    let iface_cidrs_v6 = extract_interface_cidrs_v6(root)?;
+
+    let mut v4_by_cidr = std::collections::HashMap::new();
+    for opt in options_v4 {
+        if let Some(cidr) = iface_cidrs_v4.get(&opt.iface) {
+            v4_by_cidr.insert(cidr.clone(), opt.clone());
+        } else {
+            term::warning(format_args!(
+                "No interface CIDR found for DHCPv4 options (iface {}). Skipping.",
+                opt.iface
+            ));
+        }
+    }
+
+    let mut v6_by_cidr = std::collections::HashMap::new();
+    for opt in options_v6 {
+        if let Some(cidr) = iface_cidrs_v6.get(&opt.iface) {
+            v6_by_cidr.insert(cidr.clone(), opt.clone());
+        } else {
+            term::warning(format_args!(
+                "No interface CIDR found for DHCPv6 options (iface {}). Skipping.",
+                opt.iface
+            ));
+        }
+    }
+
+    // DHCPv4 options
+    if let Some(kea) = crate::xml_helpers::find_mut_descendant_ci(root, "Kea") {
+        if let Some(dhcp4) = crate::xml_helpers::find_mut_descendant_ci(kea, "dhcp4") {
+            if let Some(subnets) = crate::xml_helpers::get_mut_child_ci(dhcp4, "subnets") {
+                for subnet in subnets
+                    .children
+                    .iter_mut()
+                    .filter_map(|n| n.as_mut_element())
+                    .filter(|e| e.name.eq_ignore_ascii_case("subnet4"))
+                {
+                    let cidr = crate::xml_helpers::get_child_ci(subnet, "subnet")
+                        .and_then(|e| e.get_text())
+                        .map(|s| s.to_string())
+                        .unwrap_or_default();
+                    let Some(opt) = v4_by_cidr.get(&cidr) else {
+                        continue;
+                    };
+                    let routers = if opt.routers_suppressed {
+                        None
+                    } else {
+                        opt.routers.clone()
+                    };
+                    let values = [
+                        ("domain_name_servers", join_list(&opt.dns_servers)),
+                        ("routers", routers),
+                        ("domain_name", opt.domain_name.clone()),
+                        ("domain_search", opt.domain_search.clone()),
+                        ("ntp_servers", join_list(&opt.ntp_servers)),
+                        ("netbios_name_servers", join_list(&opt.wins_servers)),
+                    ];
+                    if merge == OptionsMerge::Skip && has_option_data(subnet) {
+                        counts.skipped += count_values(&values);
+                        warn(
+                            WarningKind::OptionsSet,
+                            format_args!("Kea subnet {} already has options set. Skipping.", cidr),
+                        );
+                        continue;
+                    }
+
+                    if crate::xml_helpers::get_mut_child_ci(subnet, "option_data").is_none() {
+                        subnet
+                            .children
+                            .push(XMLNode::Element(Element::new("option_data")));
+                    }
+
+                    if let Some(auto) =
+                        crate::xml_helpers::get_mut_child_ci(subnet, "option_data_autocollect")
+                    {
+                        crate::xml_helpers::set_text_preserving_comments(auto, "0");
+                    } else {
+                        let mut auto = Element::new("option_data_autocollect");
+                        auto.children.push(XMLNode::Text("0".to_string()));
+                        subnet.children.push(XMLNode::Element(auto));
+                    }
+
+                    let option_data = crate::xml_helpers::get_mut_child_ci(subnet, "option_data")
+                        .ok_or_else(|| anyhow!("Failed to access Kea option_data"))?;
+
+                    for (tag, value) in values {
+                        if tag == "routers" && opt.routers_suppressed {
+                            clear_option_value(option_data, tag, force, &mut counts);
+                        } else {
+                            set_option_value(option_data, tag, value, force, &mut counts);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // DHCPv6 options
+    if let Some(kea) = crate::xml_helpers::find_mut_descendant_ci(root, "Kea") {
+        if let Some(dhcp6) = crate::xml_helpers::find_mut_descendant_ci(kea, "dhcp6") {
+            if let Some(subnets) = crate::xml_helpers::get_mut_child_ci(dhcp6, "subnets") {
+                for subnet in subnets
+                    .children
+                    .iter_mut()
+                    .filter_map(|n| n.as_mut_element())
+                    .filter(|e| e.name.eq_ignore_ascii_case("subnet6"))
+                {
+                    let cidr = crate::xml_helpers::get_child_ci(subnet, "subnet")
+                        .and_then(|e| e.get_text())
+                        .map(|s| s.to_string())
+                        .unwrap_or_default();
+                    let Some(opt) = v6_by_cidr.get(&cidr) else {
+                        continue;
+                    };
+                    let values = [
+                        ("dns_servers", join_list(&opt.dns_servers)),
+                        ("domain_search", opt.domain_search.clone()),
+                    ];
+                    if merge == OptionsMerge::Skip && has_option_data(subnet) {
+                        counts.skipped += count_values(&values);
+                        warn(
+                            WarningKind::OptionsSet,
+                            format_args!("Kea subnet {} already has options set. Skipping.", cidr),
+                        );
+                        continue;
+                    }
+
+                    if crate::xml_helpers::get_mut_child_ci(subnet, "option_data").is_none() {
+                        subnet
+                            .children
+                            .push(XMLNode::Element(Element::new("option_data")));
+                    }
+                    let option_data = crate::xml_helpers::get_mut_child_ci(subnet, "option_data")
+                        .ok_or_else(|| anyhow!("Failed to access Kea option_data"))?;
+
+                    for (tag, value) in values {
+                        set_option_value(option_data, tag, value, force, &mut counts);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(counts)
+}
+
+fn count_values(values: &[(&str, Option<String>)]) -> usize {
+    values
+        .iter()
+        .filter(|(_, value)| value.as_deref().is_some_and(|v| !v.is_empty()))
+        .count()
+}
+
+/// Whether any `<option_data>` field of `subnet` is non-empty, apart from
+/// the boot settings `--create-subnets` copies from ISC netboot.
+fn has_option_data(subnet: &Element) -> bool {
+    crate::xml_helpers::get_child_ci(subnet, "option_data").is_some_and(|option_data| {
+        option_data
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .filter(|e| {
+                !NETBOOT_OPTION_DATA
+                    .iter()
+                    .any(|f| e.name.eq_ignore_ascii_case(f))
+            })
+            .any(|e| e.get_text().is_some_and(|v| !v.trim().is_empty()))
+    })
+}
+
+/// Leave `tag` empty so Kea sends no such option; autocollection is already
+/// off for the subnet, so OPNsense does not fill it in either.
+fn clear_option_value(target: &mut Element, tag: &str, force: bool, counts: &mut OptionCounts) {
+    let Some(elem) = crate::xml_helpers::get_mut_child_ci(target, tag) else {
+        target.children.push(XMLNode::Element(Element::new(tag)));
+        return;
+    };
+    let existing = elem.get_text().map(|v| v.to_string()).unwrap_or_default();
+    if existing.is_empty() {
+        return;
+    }
+    if !force {
+        counts.skipped += 1;
+        warn(
+            WarningKind::OptionsSet,
+            format_args!(
+                "Kea option {} already set ({}) but ISC sends none. Skipping.",
+                tag, existing
+            ),
+        );
+        return;
+    }
+    crate::xml_helpers::set_text_preserving_comments(elem, "");
+    counts.created += 1;
+}
+
+fn set_option_value(
+    target: &mut Element,
+    tag: &str,
+    value: Option<String>,
+    force: bool,
+    counts: &mut OptionCounts,
+) {
+    let Some(val) = value.filter(|v| !v.is_empty()) else {
+        return;
+    };
+    let child = crate::xml_helpers::get_mut_child_ci(target, tag);
+    match child {
+        Some(elem) => {
+            let existing = elem.get_text().map(|v| v.to_string()).unwrap_or_default();
+            if !existing.is_empty() && !force {
+                counts.skipped += 1;
+                warn(
+                    WarningKind::OptionsSet,
+                    format_args!("Kea option {} already set ({}). Skipping.", tag, existing),
+                );
+                return;
+            }
+            crate::xml_helpers::set_text_preserving_comments(elem, &val);
+        }
+        None => {
+            let mut elem = Element::new(tag);
+            elem.children.push(XMLNode::Text(val));
+            target.children.push(XMLNode::Element(elem));
+        }
+    }
+    counts.created += 1;
+}
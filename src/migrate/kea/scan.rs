@@ -1,16 +1,31 @@
 use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
 use xmltree::Element;
 
 use crate::extract::{
-    extract_existing_reservation_duids_v6, extract_existing_reservation_ips,
-    extract_existing_reservation_ips_v6, extract_interface_cidrs, extract_interface_cidrs_v6,
+    extract_interface_cidrs, extract_interface_cidrs_v6, extract_interface_labels,
     extract_kea_subnets, extract_kea_subnets_v6, has_kea_dhcp4, has_kea_dhcp6,
 };
 use crate::subnet::{find_subnet_for_ip, find_subnet_for_ip_v6};
-use crate::{IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions, MigrationStats};
+use crate::term;
+use crate::{
+    IscStaticMap, IscStaticMapV6, KeaInterfacePolicy, MigrationError, MigrationOptions,
+    MigrationStats,
+};
 
-use crate::migrate::subnets::{desired_subnets_v4, desired_subnets_v6};
-use crate::migrate::utils::{short_uuid, validate_mapping_ifaces_v4, validate_mapping_ifaces_v6};
+use super::subnets::check_kea_listening;
+use super::unmatched::{
+    drop_unmatched_mappings, is_subnet_not_found, unmatched_v4, unmatched_v6, IfaceContext,
+};
+use super::{served_iface, served_iface_v6, short_uuid};
+use crate::migrate::existing::{DuidRegistryV6, ExistingState};
+use crate::migrate::hwaddr::prepare_kea_hwaddrs;
+use crate::migrate::option_values::checked_isc_options;
+use crate::migrate::subnets::{desired_subnets_v4, desired_subnets_v6, merge_desired_ranges};
+use crate::migrate::utils::{
+    conflict_v4, validate_mapping_ifaces_v4, validate_mapping_ifaces_v6, warn_unmigrated_prefixes,
+};
+use crate::warnings::{warn, WarningKind};
 
 /// Scan an input configuration for Kea migration stats.
 pub(crate) fn scan_kea(
@@ -20,22 +35,23 @@ pub(crate) fn scan_kea(
     options: &MigrationOptions,
 ) -> Result<MigrationStats> {
     let kea_subnets = extract_kea_subnets(root)?;
-    let existing_ips = extract_existing_reservation_ips(root)?;
+    let existing = ExistingState::kea(root);
     let kea_subnets_v6 = extract_kea_subnets_v6(root)?;
-    let existing_ips_v6 = extract_existing_reservation_ips_v6(root)?;
-    let existing_duids_v6 = extract_existing_reservation_duids_v6(root)?;
+    let existing_v6 = DuidRegistryV6::new(&existing, options.global_duids);
     let iface_cidrs_v4 = extract_interface_cidrs(root)?;
     let iface_cidrs_v6 = extract_interface_cidrs_v6(root)?;
-    let desired_v4 = if options.create_subnets {
+    let mut desired_v4 = if options.create_subnets {
         desired_subnets_v4(root)?
     } else {
         Vec::new()
     };
-    let desired_v6 = if options.create_subnets {
+    let mut desired_v6 = if options.create_subnets {
         desired_subnets_v6(root)?
     } else {
         Vec::new()
     };
+    let ranges_merged = merge_desired_ranges(&mut desired_v4, &mut desired_v6, options);
+    let (_, _, option_values) = checked_isc_options(root, options);
 
     // Early check: differentiate between "Kea not configured" vs "no subnets"
     if !isc_mappings.is_empty() && kea_subnets.is_empty() && !options.create_subnets {
@@ -97,12 +113,20 @@ pub(crate) fn scan_kea(
             kea_subnets.iter().map(|s| s.cidr.clone()).collect();
         for subnet in &desired_v4 {
             if existing_v4.contains(&subnet.cidr) {
-                eprintln!(
-                    "Warning: Kea subnet {} already exists (iface {}). Skipping.",
-                    subnet.cidr, subnet.iface
+                warn(
+                    WarningKind::KeaSubnetExists,
+                    format_args!(
+                        "Kea subnet {} already exists (iface {}). Skipping.",
+                        subnet.cidr,
+                        subnet.display_iface()
+                    ),
                 );
             } else {
-                println!("  ADD-SUBNET: {} (iface {})", subnet.cidr, subnet.iface);
+                println!(
+                    "  ADD-SUBNET: {} (iface {})",
+                    subnet.cidr,
+                    subnet.display_iface()
+                );
             }
         }
 
@@ -110,41 +134,24 @@ pub(crate) fn scan_kea(
             kea_subnets_v6.iter().map(|s| s.cidr.clone()).collect();
         for subnet in &desired_v6 {
             if existing_v6.contains(&subnet.cidr) {
-                eprintln!(
-                    "Warning: Kea subnet {} already exists (iface {}). Skipping.",
-                    subnet.cidr, subnet.iface
+                warn(
+                    WarningKind::KeaSubnetExists,
+                    format_args!(
+                        "Kea subnet {} already exists (iface {}). Skipping.",
+                        subnet.cidr,
+                        subnet.display_iface()
+                    ),
                 );
             } else {
-                println!("  ADD-SUBNET6: {} (iface {})", subnet.cidr, subnet.iface);
+                println!(
+                    "  ADD-SUBNET6: {} (iface {})",
+                    subnet.cidr,
+                    subnet.display_iface()
+                );
             }
         }
     }
 
-    validate_mapping_ifaces_v4(isc_mappings, &iface_cidrs_v4)?;
-    validate_mapping_ifaces_v6(isc_mappings_v6, &iface_cidrs_v6)?;
-
-    // Check fail_if_existing flag
-    if options.fail_if_existing
-        && (!existing_ips.is_empty()
-            || !existing_ips_v6.is_empty()
-            || !existing_duids_v6.is_empty())
-    {
-        return Err(anyhow!(
-            "Existing reservations found ({} IPs) and --fail-if-existing is set. Aborting.",
-            existing_ips.len() + existing_ips_v6.len()
-        ));
-    }
-
-    let mut to_create = 0;
-    let mut skipped = 0;
-    let mut to_create_v6 = 0;
-    let mut skipped_v6 = 0;
-
-    // Track reserved IPs including ones we're planning to add (to catch ISC duplicates)
-    let mut reserved_ips = existing_ips;
-    let mut reserved_ips_v6 = existing_ips_v6;
-    let mut reserved_duids_v6 = existing_duids_v6;
-
     let mut effective_subnets = kea_subnets.clone();
     if options.create_subnets {
         for subnet in &desired_v4 {
@@ -171,28 +178,103 @@ pub(crate) fn scan_kea(
         }
     }
 
+    let total_v4 = isc_mappings.len();
+    let total_v6 = isc_mappings_v6.len();
+    let mut skips = Vec::new();
+    let labels = extract_interface_labels(root);
+    let ifaces = IfaceContext {
+        labels: &labels,
+        cidrs_v4: &iface_cidrs_v4,
+        cidrs_v6: &iface_cidrs_v6,
+    };
+    let (mut isc_mappings, isc_mappings_v6) = if options.create_subnets {
+        drop_unmatched_mappings(
+            isc_mappings,
+            isc_mappings_v6,
+            &effective_subnets,
+            &effective_subnets_v6,
+            &ifaces,
+            options,
+            &mut skips,
+        )?
+    } else {
+        (isc_mappings.to_vec(), isc_mappings_v6.to_vec())
+    };
+    prepare_kea_hwaddrs(&mut isc_mappings, options);
+
+    validate_mapping_ifaces_v4(&isc_mappings, &iface_cidrs_v4)?;
+    validate_mapping_ifaces_v6(&isc_mappings_v6, &iface_cidrs_v6)?;
+    let prefixes_v6_unmigrated = warn_unmigrated_prefixes(&isc_mappings_v6, "Kea", true, options);
+
+    // Check fail_if_existing flag
+    if options.fail_if_existing && (!existing.ips.is_empty() || !existing_v6.is_empty()) {
+        return Err(MigrationError::ExistingReservations {
+            count: existing.ips.len() + existing_v6.ip_count(),
+        }
+        .into());
+    }
+
+    let mut to_create = 0;
+    let mut skipped = total_v4 - isc_mappings.len();
+    let mut to_create_v6 = 0;
+    let mut skipped_v6 = total_v6 - isc_mappings_v6.len();
+    let mut prefix_reservations_v6 = 0;
+
+    // Track reserved IPs including ones we're planning to add (to catch ISC duplicates)
+    let ExistingState {
+        ips: mut reserved_ips,
+        macs: mut reserved_macs,
+        client_ids: mut reserved_client_ids,
+        ..
+    } = existing;
+    let mut reserved_v6 = existing_v6;
+    let mut served_v4 = BTreeSet::new();
+    let mut served_v6 = BTreeSet::new();
+    let mut outside = Vec::new();
+
     if options.verbose {
-        println!("\nProcessing {} ISC static mappings:", isc_mappings.len());
+        println!("\nProcessing {} ISC static mappings:", total_v4);
         if !isc_mappings_v6.is_empty() {
-            println!(
-                "Processing {} ISC DHCPv6 static mappings:",
-                isc_mappings_v6.len()
-            );
+            println!("Processing {} ISC DHCPv6 static mappings:", total_v6);
         }
     }
 
-    for mapping in isc_mappings {
-        if reserved_ips.contains(&mapping.ipaddr) {
+    for mapping in &isc_mappings {
+        if let Some(conflict) = conflict_v4(
+            mapping,
+            &reserved_ips,
+            &reserved_macs,
+            &reserved_client_ids,
+            mapping.cid.is_some(),
+        ) {
             skipped += 1;
+            skips.push(conflict);
             if options.verbose {
-                println!(
-                    "  SKIP: {} ({}) - IP already reserved",
-                    mapping.ipaddr, mapping.mac
+                term::action(
+                    "SKIP",
+                    format_args!(
+                        "{} ({}) - IP, MAC or client ID already reserved",
+                        mapping.ipaddr, mapping.mac
+                    ),
                 );
             }
         } else {
-            let subnet_uuid = find_subnet_for_ip(&mapping.ipaddr, &effective_subnets)?;
+            let subnet_uuid = match find_subnet_for_ip(&mapping.ipaddr, &effective_subnets) {
+                Ok(uuid) => uuid,
+                Err(err) if is_subnet_not_found(&err) => {
+                    outside.push(unmatched_v4(mapping, &effective_subnets, &ifaces));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            served_v4.insert(served_iface(
+                &subnet_uuid,
+                &effective_subnets,
+                &mapping.iface,
+            ));
             reserved_ips.insert(mapping.ipaddr.clone());
+            reserved_macs.insert(mapping.mac.clone());
+            reserved_client_ids.extend(mapping.cid.clone());
             to_create += 1;
             if options.verbose {
                 let hostname = mapping
@@ -201,55 +283,101 @@ pub(crate) fn scan_kea(
                     .or(mapping.cid.as_ref())
                     .map(|s| s.as_str())
                     .unwrap_or("<no hostname>");
-                println!(
-                    "  ADD: {} ({}) -> subnet {} [{}]",
-                    mapping.ipaddr,
-                    mapping.mac,
-                    short_uuid(&subnet_uuid),
-                    hostname
+                term::action(
+                    "ADD",
+                    format_args!(
+                        "{} ({}) -> subnet {} [{}]",
+                        mapping.ipaddr,
+                        mapping.mac,
+                        short_uuid(&subnet_uuid),
+                        hostname
+                    ),
                 );
             }
         }
     }
 
-    for mapping in isc_mappings_v6 {
-        if reserved_ips_v6.contains(&mapping.ipaddr) || reserved_duids_v6.contains(&mapping.duid) {
+    for mapping in &isc_mappings_v6 {
+        // Duplicates are found within the mapping's subnet
+        let subnet_uuid = find_subnet_for_ip_v6(&mapping.ipaddr, &effective_subnets_v6);
+        let scope = subnet_uuid.as_deref().unwrap_or_default();
+        if let Some(conflict) = reserved_v6.conflict(mapping, scope) {
             skipped_v6 += 1;
+            skips.push(conflict);
             if options.verbose {
-                println!(
-                    "  SKIP6: {} ({}) - IP or DUID already reserved",
-                    mapping.ipaddr, mapping.duid
+                term::action(
+                    "SKIP6",
+                    format_args!(
+                        "{} ({}) - IP or DUID already reserved",
+                        mapping.ipaddr, mapping.duid
+                    ),
                 );
             }
         } else {
-            let subnet_uuid = find_subnet_for_ip_v6(&mapping.ipaddr, &effective_subnets_v6)?;
-            reserved_ips_v6.insert(mapping.ipaddr.clone());
-            reserved_duids_v6.insert(mapping.duid.clone());
+            let subnet_uuid = match subnet_uuid {
+                Ok(uuid) => uuid,
+                Err(err) if is_subnet_not_found(&err) => {
+                    outside.push(unmatched_v6(mapping, &effective_subnets_v6, &ifaces));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            served_v6.insert(served_iface_v6(
+                &subnet_uuid,
+                &effective_subnets_v6,
+                &mapping.iface,
+            ));
+            reserved_v6.insert(mapping, &subnet_uuid);
+            if mapping.delegated_prefix().is_some() {
+                prefix_reservations_v6 += 1;
+            }
             to_create_v6 += 1;
             if options.verbose {
                 let hostname = mapping.hostname.as_deref().unwrap_or("<no hostname>");
-                println!(
-                    "  ADD6: {} ({}) -> subnet {} [{}]",
-                    mapping.ipaddr,
-                    mapping.duid,
-                    short_uuid(&subnet_uuid),
-                    hostname
+                term::action(
+                    "ADD6",
+                    format_args!(
+                        "{} ({}) -> subnet {} [{}]",
+                        mapping.ipaddr,
+                        mapping.duid,
+                        short_uuid(&subnet_uuid),
+                        hostname
+                    ),
                 );
             }
         }
     }
+    if !outside.is_empty() {
+        return Err(MigrationError::MappingsOutsideSubnets { mappings: outside }.into());
+    }
+
+    // Subnet creation and takeover make Kea listen on what it serves
+    let mut interfaces_not_listening = Vec::new();
+    if !options.create_subnets && !options.enable_backend {
+        let (missing_v4, missing_v6) = check_kea_listening(root, &served_v4, &served_v6, options);
+        if options.kea_interfaces == KeaInterfacePolicy::Warn {
+            interfaces_not_listening = missing_v4.union(&missing_v6).cloned().collect();
+        }
+    }
 
     Ok(MigrationStats {
-        isc_mappings_found: isc_mappings.len(),
-        isc_mappings_v6_found: isc_mappings_v6.len(),
+        option_values_resolved: option_values.resolved,
+        unresolved_option_values: option_values.unresolved,
+        isc_mappings_found: total_v4,
+        isc_mappings_v6_found: total_v6,
         isc_ranges_found: 0,
         isc_ranges_v6_found: 0,
+        ranges_merged,
         target_subnets_found: kea_subnets.len(),
         target_subnets_v6_found: kea_subnets_v6.len(),
         reservations_to_create: to_create,
         reservations_v6_to_create: to_create_v6,
         reservations_skipped: skipped,
         reservations_v6_skipped: skipped_v6,
+        skips,
+        prefix_reservations_v6,
+        prefixes_v6_unmigrated,
+        interfaces_not_listening,
         ..Default::default()
     })
 }
@@ -0,0 +1,690 @@
+//! Kea subnets, pools and interfaces created from ISC ranges
+//! (`--create-subnets`, `--create-pools`,
+//! `--carve-pools-around-reservations`).
+
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use xmltree::{Element, XMLNode};
+
+use crate::extract::{extract_kea_subnets, extract_kea_subnets_v6};
+use crate::migrate::subnets::{DesiredSubnetV4, DesiredSubnetV6};
+use crate::model::{Subnet4Node, Subnet6Node};
+use crate::parse;
+use crate::subnet::{range_in_subnet, range_in_subnet_v6, ranges_overlap, ranges_overlap_v6};
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::{
+    IscRangeV4, IscRangeV6, IscStaticMap, IscStaticMapV6, KeaInterfacePolicy, MigrationOptions,
+};
+
+/// Split the ranges of `subnets` so reservation IPs from `mappings` that fall
+/// inside them are left out of the pools
+/// (`--carve-pools-around-reservations`). A range taken up entirely by
+/// reservations is dropped.
+pub(crate) fn carve_pools_v4(
+    subnets: &mut [DesiredSubnetV4],
+    mappings: &[IscStaticMap],
+    options: &MigrationOptions,
+) {
+    let reserved: BTreeSet<u128> = mappings
+        .iter()
+        .filter_map(|m| parse::ipv4(&m.ipaddr).ok())
+        .map(|ip| u128::from(u32::from(ip)))
+        .collect();
+    for subnet in subnets.iter_mut() {
+        let mut carved = Vec::new();
+        for range in subnet.ranges.drain(..) {
+            let bounds =
+                parse::ipv4(&range.from).and_then(|from| Ok((from, parse::ipv4(&range.to)?)));
+            let Ok((from, to)) = bounds else {
+                carved.push(range);
+                continue;
+            };
+            let (from, to) = (u128::from(u32::from(from)), u128::from(u32::from(to)));
+            let pieces: Vec<IscRangeV4> = carve_range(from, to, &reserved)
+                .into_iter()
+                .map(|(start, end)| IscRangeV4 {
+                    iface: range.iface.clone(),
+                    from: Ipv4Addr::from(start as u32).to_string(),
+                    to: Ipv4Addr::from(end as u32).to_string(),
+                })
+                .collect();
+            report_carve(
+                &range.from,
+                &range.to,
+                pieces.iter().map(|r| (&r.from, &r.to)),
+                options,
+            );
+            carved.extend(pieces);
+        }
+        subnet.ranges = carved;
+    }
+}
+
+/// DHCPv6 counterpart of [`carve_pools_v4`].
+pub(crate) fn carve_pools_v6(
+    subnets: &mut [DesiredSubnetV6],
+    mappings: &[IscStaticMapV6],
+    options: &MigrationOptions,
+) {
+    let reserved: BTreeSet<u128> = mappings
+        .iter()
+        .filter_map(|m| parse::ipv6(&m.ipaddr).ok())
+        .map(u128::from)
+        .collect();
+    for subnet in subnets.iter_mut() {
+        let mut carved = Vec::new();
+        for range in subnet.ranges.drain(..) {
+            let bounds =
+                parse::ipv6(&range.from).and_then(|from| Ok((from, parse::ipv6(&range.to)?)));
+            let Ok((from, to)) = bounds else {
+                carved.push(range);
+                continue;
+            };
+            let pieces: Vec<IscRangeV6> = carve_range(u128::from(from), u128::from(to), &reserved)
+                .into_iter()
+                .map(|(start, end)| IscRangeV6 {
+                    iface: range.iface.clone(),
+                    from: Ipv6Addr::from(start).to_string(),
+                    to: Ipv6Addr::from(end).to_string(),
+                })
+                .collect();
+            report_carve(
+                &range.from,
+                &range.to,
+                pieces.iter().map(|r| (&r.from, &r.to)),
+                options,
+            );
+            carved.extend(pieces);
+        }
+        subnet.ranges = carved;
+    }
+}
+
+/// `from..=to` with the `reserved` addresses removed, as inclusive pieces.
+fn carve_range(from: u128, to: u128, reserved: &BTreeSet<u128>) -> Vec<(u128, u128)> {
+    let mut pieces = Vec::new();
+    let mut start = from;
+    for &ip in reserved.range(from..=to) {
+        if ip > start {
+            pieces.push((start, ip - 1));
+        }
+        if ip == u128::MAX {
+            return pieces;
+        }
+        start = ip + 1;
+    }
+    if start <= to {
+        pieces.push((start, to));
+    }
+    pieces
+}
+
+fn report_carve<'a>(
+    from: &str,
+    to: &str,
+    pieces: impl Iterator<Item = (&'a String, &'a String)>,
+    options: &MigrationOptions,
+) {
+    let pieces: Vec<(&String, &String)> = pieces.collect();
+    let unchanged = matches!(pieces.as_slice(), [(f, t)] if f.as_str() == from && t.as_str() == to);
+    if unchanged {
+        return;
+    }
+    if pieces.is_empty() {
+        term::warning(format_args!(
+            "pool {}-{} only holds reservations; not creating it.",
+            from, to
+        ));
+    } else if options.verbose {
+        term::action(
+            "CARVE",
+            format_args!("{}-{} -> {}", from, to, pool_list(pieces.into_iter())),
+        );
+    }
+}
+
+fn get_kea_subnets_node_mut(root: &mut Element, v6: bool) -> Result<&mut Element> {
+    let kea = crate::xml_helpers::find_mut_descendant_ci(root, "Kea")
+        .ok_or_else(|| anyhow!("Kea not configured in config.xml"))?;
+    let dhcp_name = if v6 { "dhcp6" } else { "dhcp4" };
+    let dhcp = crate::xml_helpers::find_mut_descendant_ci(kea, dhcp_name)
+        .ok_or_else(|| anyhow!("Failed to access Kea {} node", dhcp_name))?;
+
+    if crate::xml_helpers::get_mut_child_ci(dhcp, "subnets").is_none() {
+        dhcp.children
+            .push(XMLNode::Element(Element::new("subnets")));
+    }
+
+    crate::xml_helpers::get_mut_child_ci(dhcp, "subnets")
+        .ok_or_else(|| anyhow!("Failed to access Kea subnets node"))
+}
+
+fn get_kea_general_node_mut(root: &mut Element, v6: bool) -> Result<&mut Element> {
+    let kea = crate::xml_helpers::find_mut_descendant_ci(root, "Kea")
+        .ok_or_else(|| anyhow!("Kea not configured in config.xml"))?;
+    let dhcp_name = if v6 { "dhcp6" } else { "dhcp4" };
+    let dhcp = crate::xml_helpers::find_mut_descendant_ci(kea, dhcp_name)
+        .ok_or_else(|| anyhow!("Failed to access Kea {} node", dhcp_name))?;
+
+    if crate::xml_helpers::get_mut_child_ci(dhcp, "general").is_none() {
+        dhcp.children
+            .push(XMLNode::Element(Element::new("general")));
+    }
+
+    crate::xml_helpers::get_mut_child_ci(dhcp, "general")
+        .ok_or_else(|| anyhow!("Failed to access Kea general node"))
+}
+
+/// A `subnet4` for `subnet`, carrying its interface's ISC network boot
+/// settings, since ISC applies them per interface.
+fn create_kea_subnet4(subnet: &DesiredSubnetV4) -> Subnet4Node {
+    let netboot = subnet.netboot.clone().unwrap_or_default();
+    let next_server = netboot.next_server.filter(|ip| {
+        let valid = parse::ipv4(ip).is_ok();
+        if !valid {
+            term::warning(format_args!(
+                "ISC next-server '{}' on {} is not an IPv4 address. Skipping it.",
+                ip,
+                subnet.display_iface()
+            ));
+        }
+        valid
+    });
+    Subnet4Node {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        subnet: subnet.cidr.clone(),
+        interface: subnet.iface.clone(),
+        pools: pools(subnet.ranges.iter().map(|r| (&r.from, &r.to))),
+        description: subnet.descr.clone().unwrap_or_default(),
+        next_server: next_server.unwrap_or_default(),
+        tftp_server_name: netboot.tftp_server_name.unwrap_or_default(),
+        boot_file_name: netboot.boot_file_name.unwrap_or_default(),
+    }
+}
+
+fn create_kea_subnet6(subnet: &DesiredSubnetV6) -> Subnet6Node {
+    Subnet6Node {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        subnet: subnet.cidr.clone(),
+        interface: subnet.iface.clone(),
+        pools: pools(subnet.ranges.iter().map(|r| (&r.from, &r.to))),
+        description: subnet.descr.clone().unwrap_or_default(),
+    }
+}
+
+fn pools<'a>(ranges: impl Iterator<Item = (&'a String, &'a String)>) -> Vec<String> {
+    ranges
+        .map(|(from, to)| format!("{}-{}", from, to))
+        .collect()
+}
+
+fn pool_list<'a>(ranges: impl Iterator<Item = (&'a String, &'a String)>) -> String {
+    pools(ranges).join(",")
+}
+
+/// Remove the subnets for `cidr`, returning their `uuid`s in document order.
+fn remove_kea_subnet_by_cidr(subnets_node: &mut Element, v6: bool, cidr: &str) -> Vec<String> {
+    let subnet_tag = if v6 { "subnet6" } else { "subnet4" };
+    let mut removed = Vec::new();
+    subnets_node.children.retain(|child| {
+        let Some(elem) = child.as_element() else {
+            return true;
+        };
+        if !elem.name.eq_ignore_ascii_case(subnet_tag) {
+            return true;
+        }
+        let subnet_val = elem
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .find(|e| e.name.eq_ignore_ascii_case("subnet"))
+            .and_then(|e| e.get_text())
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        if subnet_val != cidr {
+            return true;
+        }
+        if let Some(uuid) = elem.attributes.get("uuid").filter(|u| !u.is_empty()) {
+            removed.push(uuid.clone());
+        }
+        false
+    });
+    removed
+}
+
+/// `uuid` for the subnet replacing the `removed` ones: the first of them, so
+/// reservations elsewhere in the config keep pointing at it. Reservations on
+/// the others (duplicate subnets for one CIDR) are re-pointed to it through
+/// `repointed`.
+fn reuse_subnet_uuid(
+    removed: Vec<String>,
+    repointed: &mut HashMap<String, String>,
+) -> Option<String> {
+    let mut removed = removed.into_iter();
+    let kept = removed.next()?;
+    for old in removed.filter(|old| *old != kept) {
+        repointed.insert(old, kept.clone());
+    }
+    Some(kept)
+}
+
+/// Point reservations whose `<subnet>` is a key of `repointed` at its value.
+fn repoint_kea_reservations(
+    root: &mut Element,
+    v6: bool,
+    repointed: &HashMap<String, String>,
+    options: &MigrationOptions,
+) {
+    if repointed.is_empty() {
+        return;
+    }
+    let dhcp_name = if v6 { "dhcp6" } else { "dhcp4" };
+    let Some(reservations) = crate::xml_helpers::find_mut_descendant_ci(root, "Kea")
+        .and_then(|kea| crate::xml_helpers::get_mut_child_ci(kea, dhcp_name))
+        .and_then(|dhcp| crate::xml_helpers::get_mut_child_ci(dhcp, "reservations"))
+    else {
+        return;
+    };
+    for reservation in reservations.children.iter_mut().filter_map(|n| match n {
+        XMLNode::Element(e) => Some(e),
+        _ => None,
+    }) {
+        let ip = crate::xml_helpers::get_child_ci(reservation, "ip_address")
+            .and_then(|e| e.get_text())
+            .map(|t| t.trim().to_string())
+            .unwrap_or_default();
+        let Some(subnet_elem) = crate::xml_helpers::get_mut_child_ci(reservation, "subnet") else {
+            continue;
+        };
+        let current = subnet_elem
+            .get_text()
+            .map(|t| t.trim().to_string())
+            .unwrap_or_default();
+        if let Some(kept) = repointed.get(&current) {
+            if options.verbose {
+                term::action("REWIRE", format_args!("{} -> subnet {}", ip, kept));
+            }
+            crate::xml_helpers::set_text_preserving_comments(subnet_elem, kept);
+        }
+    }
+}
+
+/// Bounds of an existing Kea pool, written as `from-to` or as a CIDR.
+fn pool_bounds(pool: &str, v6: bool) -> Option<(String, String)> {
+    if let Some((from, to)) = pool.split_once('-') {
+        return Some((from.trim().to_string(), to.trim().to_string()));
+    }
+    if v6 {
+        let net = parse::cidr_v6(pool).ok()?.trunc();
+        Some((net.network().to_string(), net.broadcast().to_string()))
+    } else {
+        let net = parse::cidr_v4(pool).ok()?.trunc();
+        Some((net.network().to_string(), net.broadcast().to_string()))
+    }
+}
+
+/// The existing subnet for `cidr`, else the one bound to `iface` whose
+/// network holds every range of `ranges`.
+fn find_pool_subnet<'a>(
+    subnets_node: &'a mut Element,
+    v6: bool,
+    iface: &str,
+    cidr: &str,
+    ranges: &[(&String, &String)],
+) -> Option<&'a mut Element> {
+    let subnet_tag = if v6 { "subnet6" } else { "subnet4" };
+    let text = |el: &Element, name: &str| {
+        crate::xml_helpers::get_child_ci(el, name)
+            .and_then(|e| e.get_text())
+            .map(|t| t.trim().to_string())
+            .unwrap_or_default()
+    };
+    let holds = |subnet: &str| {
+        ranges.iter().all(|(from, to)| {
+            if v6 {
+                range_in_subnet_v6(from, to, subnet).unwrap_or(false)
+            } else {
+                range_in_subnet(from, to, subnet).unwrap_or(false)
+            }
+        })
+    };
+    let subnets = subnets_node.children.iter().filter_map(|n| n.as_element());
+    let index = subnets
+        .clone()
+        .position(|el| el.name.eq_ignore_ascii_case(subnet_tag) && text(el, "subnet") == cidr)
+        .or_else(|| {
+            subnets.clone().position(|el| {
+                el.name.eq_ignore_ascii_case(subnet_tag)
+                    && text(el, "interface").eq_ignore_ascii_case(iface)
+                    && holds(&text(el, "subnet"))
+            })
+        })?;
+    subnets_node
+        .children
+        .iter_mut()
+        .filter_map(|n| match n {
+            XMLNode::Element(e) => Some(e),
+            _ => None,
+        })
+        .nth(index)
+}
+
+/// Add `ranges` as pools of the existing Kea subnet for `cidr`, or of the
+/// one bound to `iface` (`--create-pools`). Ranges already pooled there
+/// are left alone, and ranges overlapping one of its pools are skipped with
+/// a warning. Returns how many pools were added, or `None` when no existing
+/// subnet matches.
+fn fill_kea_subnet_pools<'a>(
+    subnets_node: &mut Element,
+    v6: bool,
+    (iface, display_iface, cidr): (&str, &str, &str),
+    ranges: impl Iterator<Item = (&'a String, &'a String)>,
+    options: &MigrationOptions,
+) -> Option<usize> {
+    let ranges: Vec<(&String, &String)> = ranges.collect();
+    let subnet = find_pool_subnet(subnets_node, v6, iface, cidr, &ranges)?;
+    let subnet_cidr = crate::xml_helpers::get_child_ci(subnet, "subnet")
+        .and_then(|e| e.get_text())
+        .map(|t| t.trim().to_string())
+        .unwrap_or_default();
+    let mut pools: Vec<String> = crate::xml_helpers::get_child_ci(subnet, "pools")
+        .and_then(|e| e.get_text())
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|pool| !pool.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let mut added = 0;
+    for (from, to) in ranges {
+        let pool = format!("{}-{}", from, to);
+        let overlapping = pools.iter().find(|existing| {
+            let Some((start, end)) = pool_bounds(existing, v6) else {
+                return false;
+            };
+            if v6 {
+                ranges_overlap_v6(from, to, &start, &end).unwrap_or(false)
+            } else {
+                ranges_overlap(from, to, &start, &end).unwrap_or(false)
+            }
+        });
+        match overlapping {
+            Some(existing) if pool_bounds(existing, v6) == Some((from.clone(), to.clone())) => {
+                if options.verbose {
+                    term::action(
+                        "POOL",
+                        format_args!("{} already in Kea subnet {}", pool, subnet_cidr),
+                    );
+                }
+            }
+            Some(existing) => warn(
+                WarningKind::KeaPoolExists,
+                format_args!(
+                    "pool {} (iface {}) overlaps pool {} of Kea subnet {}. Skipping.",
+                    pool, display_iface, existing, subnet_cidr
+                ),
+            ),
+            None => {
+                if options.verbose {
+                    println!("  ADD-POOL: {} -> Kea subnet {}", pool, subnet_cidr);
+                }
+                pools.push(pool);
+                added += 1;
+            }
+        }
+    }
+
+    if added > 0 {
+        let joined = pools.join(",");
+        match crate::xml_helpers::get_mut_child_ci(subnet, "pools") {
+            Some(elem) => crate::xml_helpers::set_text_preserving_comments(elem, &joined),
+            None => {
+                let mut elem = Element::new("pools");
+                elem.children.push(XMLNode::Text(joined));
+                subnet.children.push(XMLNode::Element(elem));
+            }
+        }
+    }
+    Some(added)
+}
+
+/// Add the desired subnets to Kea, returning how many subnets and pools
+/// were created. With `force_subnets`, a subnet replacing an existing one
+/// for the same CIDR keeps its `uuid`. With `create_pools`, the ranges of a
+/// desired subnet that Kea already has go into that subnet as pools
+/// instead, and without `create_subnets` nothing else is created.
+pub(crate) fn apply_kea_subnets(
+    root: &mut Element,
+    kea_subnets: &mut Vec<crate::Subnet>,
+    kea_subnets_v6: &mut Vec<crate::SubnetV6>,
+    desired_v4: &[DesiredSubnetV4],
+    desired_v6: &[DesiredSubnetV6],
+    options: &MigrationOptions,
+) -> Result<(usize, usize)> {
+    let (mut subnets_created, mut pools_created) = (0, 0);
+    if !desired_v4.is_empty() {
+        let existing: std::collections::HashSet<_> =
+            kea_subnets.iter().map(|s| s.cidr.clone()).collect();
+        let mut repointed = HashMap::new();
+        let subnets_node = get_kea_subnets_node_mut(root, false)?;
+        for subnet in desired_v4 {
+            if options.create_pools {
+                let filled = fill_kea_subnet_pools(
+                    subnets_node,
+                    false,
+                    (&subnet.iface, &subnet.display_iface(), &subnet.cidr),
+                    subnet.ranges.iter().map(|r| (&r.from, &r.to)),
+                    options,
+                );
+                if let Some(added) = filled {
+                    pools_created += added;
+                    continue;
+                }
+            }
+            if !options.create_subnets {
+                term::warning(format_args!(
+                    "no Kea subnet for {} (iface {}) to add its pools to. Skipping.",
+                    subnet.cidr,
+                    subnet.display_iface()
+                ));
+                continue;
+            }
+            let mut reused = None;
+            if existing.contains(&subnet.cidr) {
+                if options.force_subnets {
+                    let removed = remove_kea_subnet_by_cidr(subnets_node, false, &subnet.cidr);
+                    reused = reuse_subnet_uuid(removed, &mut repointed);
+                } else {
+                    warn(
+                        WarningKind::KeaSubnetExists,
+                        format_args!(
+                            "Kea subnet {} already exists (iface {}). Skipping.",
+                            subnet.cidr,
+                            subnet.display_iface()
+                        ),
+                    );
+                    continue;
+                }
+            }
+            let mut node = create_kea_subnet4(subnet);
+            if let Some(uuid) = reused {
+                node.uuid = uuid;
+            }
+            subnets_created += 1;
+            pools_created += node.pools.len();
+            subnets_node
+                .children
+                .push(XMLNode::Element(node.to_element()));
+        }
+        repoint_kea_reservations(root, false, &repointed, options);
+    }
+
+    if !desired_v6.is_empty() {
+        let existing: std::collections::HashSet<_> =
+            kea_subnets_v6.iter().map(|s| s.cidr.clone()).collect();
+        let mut repointed = HashMap::new();
+        let subnets_node = get_kea_subnets_node_mut(root, true)?;
+        for subnet in desired_v6 {
+            if options.create_pools {
+                let filled = fill_kea_subnet_pools(
+                    subnets_node,
+                    true,
+                    (&subnet.iface, &subnet.display_iface(), &subnet.cidr),
+                    subnet.ranges.iter().map(|r| (&r.from, &r.to)),
+                    options,
+                );
+                if let Some(added) = filled {
+                    pools_created += added;
+                    continue;
+                }
+            }
+            if !options.create_subnets {
+                term::warning(format_args!(
+                    "no Kea subnet for {} (iface {}) to add its pools to. Skipping.",
+                    subnet.cidr,
+                    subnet.display_iface()
+                ));
+                continue;
+            }
+            let mut reused = None;
+            if existing.contains(&subnet.cidr) {
+                if options.force_subnets {
+                    let removed = remove_kea_subnet_by_cidr(subnets_node, true, &subnet.cidr);
+                    reused = reuse_subnet_uuid(removed, &mut repointed);
+                } else {
+                    warn(
+                        WarningKind::KeaSubnetExists,
+                        format_args!(
+                            "Kea subnet {} already exists (iface {}). Skipping.",
+                            subnet.cidr,
+                            subnet.display_iface()
+                        ),
+                    );
+                    continue;
+                }
+            }
+            let mut node = create_kea_subnet6(subnet);
+            if let Some(uuid) = reused {
+                node.uuid = uuid;
+            }
+            subnets_created += 1;
+            pools_created += node.pools.len();
+            subnets_node
+                .children
+                .push(XMLNode::Element(node.to_element()));
+        }
+        repoint_kea_reservations(root, true, &repointed, options);
+    }
+
+    *kea_subnets = extract_kea_subnets(root)?;
+    *kea_subnets_v6 = extract_kea_subnets_v6(root)?;
+    Ok((subnets_created, pools_created))
+}
+
+/// Merge interfaces into Kea `<general><interfaces>` for dhcp4/dhcp6.
+///
+/// Existing entries are preserved; the merged list is sorted and
+/// comma-separated. Returns the union of interfaces that were requested.
+pub(crate) fn apply_kea_interfaces(
+    root: &mut Element,
+    ifaces_v4: &BTreeSet<String>,
+    ifaces_v6: &BTreeSet<String>,
+) -> Result<Vec<String>> {
+    if !ifaces_v4.is_empty() {
+        let general = get_kea_general_node_mut(root, false)?;
+        merge_kea_interfaces(general, ifaces_v4);
+    }
+
+    if !ifaces_v6.is_empty() {
+        let general = get_kea_general_node_mut(root, true)?;
+        merge_kea_interfaces(general, ifaces_v6);
+    }
+
+    Ok(ifaces_v4.union(ifaces_v6).cloned().collect())
+}
+
+fn merge_kea_interfaces(general: &mut Element, ifaces: &BTreeSet<String>) {
+    let mut merged = ifaces.clone();
+
+    // Get existing interfaces and merge
+    let existing = crate::xml_helpers::get_child_ci(general, "interfaces")
+        .and_then(|e| e.get_text())
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    for iface in existing.split(',').filter(|s| !s.is_empty()) {
+        merged.insert(iface.to_string());
+    }
+
+    // Remove existing interfaces element if present
+    general
+        .children
+        .retain(|c| c.as_element().is_none_or(|e| e.name != "interfaces"));
+
+    let mut ifaces_elem = Element::new("interfaces");
+    ifaces_elem.children.push(XMLNode::Text(
+        merged.into_iter().collect::<Vec<_>>().join(","),
+    ));
+    general.children.push(XMLNode::Element(ifaces_elem));
+}
+
+/// Interfaces in `served` that Kea `<general><interfaces>` for dhcp4 (or
+/// dhcp6) leaves out. Without `<general><interfaces>` Kea listens on none.
+fn kea_interfaces_not_listening(
+    root: &Element,
+    v6: bool,
+    served: &BTreeSet<String>,
+) -> BTreeSet<String> {
+    let dhcp_name = if v6 { "dhcp6" } else { "dhcp4" };
+    let listening = crate::xml_helpers::find_descendant_ci(root, "Kea")
+        .and_then(|kea| crate::xml_helpers::get_child_ci(kea, dhcp_name))
+        .and_then(|dhcp| crate::xml_helpers::get_child_ci(dhcp, "general"))
+        .and_then(|general| crate::xml_helpers::get_child_ci(general, "interfaces"))
+        .and_then(|e| e.get_text())
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    let listening: BTreeSet<&str> = listening
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    served
+        .iter()
+        .filter(|iface| !listening.contains(iface.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Cross-check the interfaces migrated reservations are served on against
+/// the ones Kea listens on. Returns the missing interfaces per family; with
+/// [`KeaInterfacePolicy::Warn`] each family missing some is warned about.
+pub(crate) fn check_kea_listening(
+    root: &Element,
+    served_v4: &BTreeSet<String>,
+    served_v6: &BTreeSet<String>,
+    options: &MigrationOptions,
+) -> (BTreeSet<String>, BTreeSet<String>) {
+    let missing_v4 = kea_interfaces_not_listening(root, false, served_v4);
+    let missing_v6 = kea_interfaces_not_listening(root, true, served_v6);
+    for (missing, family) in [(&missing_v4, "DHCPv4"), (&missing_v6, "DHCPv6")] {
+        if missing.is_empty() {
+            continue;
+        }
+        let list = missing.iter().cloned().collect::<Vec<_>>().join(", ");
+        match options.kea_interfaces {
+            KeaInterfacePolicy::Warn => term::warning(format_args!(
+                "Kea {} does not listen on {}; reservations there will not be served. Add the \
+                 interfaces in Kea or use --kea-interfaces append.",
+                family, list
+            )),
+            KeaInterfacePolicy::Append => {
+                if options.verbose {
+                    term::action("LISTEN", format_args!("{} ({})", list, family));
+                }
+            }
+        }
+    }
+    (missing_v4, missing_v6)
+}
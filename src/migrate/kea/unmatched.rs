@@ -0,0 +1,157 @@
+//! Mappings outside every Kea subnet, reported together or skipped with
+//! `--skip-unmatched`.
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+use crate::extract::iface_display;
+use crate::subnet::{
+    find_subnet_for_ip, find_subnet_for_ip_v6, nearest_subnets, nearest_subnets_v6,
+};
+use crate::term;
+use crate::{
+    IscStaticMap, IscStaticMapV6, MigrationError, MigrationOptions, Subnet, SubnetV6,
+    UnmatchedMapping,
+};
+
+/// What reports of mappings outside every subnet say about interfaces.
+pub(crate) struct IfaceContext<'a> {
+    pub(crate) labels: &'a HashMap<String, String>,
+    pub(crate) cidrs_v4: &'a HashMap<String, String>,
+    pub(crate) cidrs_v6: &'a HashMap<String, String>,
+}
+
+/// Whether `err` is a [`MigrationError::SubnetNotFound`].
+pub(crate) fn is_subnet_not_found(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<MigrationError>(),
+        Some(MigrationError::SubnetNotFound { .. })
+    )
+}
+
+/// Report entry for a v4 mapping outside every one of `subnets`.
+pub(crate) fn unmatched_v4(
+    mapping: &IscStaticMap,
+    subnets: &[Subnet],
+    ifaces: &IfaceContext,
+) -> UnmatchedMapping {
+    UnmatchedMapping {
+        iface: iface_display(&mapping.iface, ifaces.labels),
+        ip: mapping.ipaddr.clone(),
+        iface_cidr: ifaces.cidrs_v4.get(&mapping.iface).cloned(),
+        candidates: nearest_subnets(&mapping.ipaddr, subnets),
+    }
+}
+
+/// Report entry for a v6 mapping outside every one of `subnets`.
+pub(crate) fn unmatched_v6(
+    mapping: &IscStaticMapV6,
+    subnets: &[SubnetV6],
+    ifaces: &IfaceContext,
+) -> UnmatchedMapping {
+    UnmatchedMapping {
+        iface: iface_display(&mapping.iface, ifaces.labels),
+        ip: mapping.ipaddr.clone(),
+        iface_cidr: ifaces.cidrs_v6.get(&mapping.iface).cloned(),
+        candidates: nearest_subnets_v6(&mapping.ipaddr, subnets),
+    }
+}
+
+/// Collect v4 mappings that do not fall inside any of `subnets`.
+fn find_unmatched_v4(
+    mappings: &[IscStaticMap],
+    subnets: &[Subnet],
+    ifaces: &IfaceContext,
+) -> Result<Vec<UnmatchedMapping>> {
+    let mut unmatched = Vec::new();
+    for mapping in mappings {
+        if let Err(err) = find_subnet_for_ip(&mapping.ipaddr, subnets) {
+            if !is_subnet_not_found(&err) {
+                return Err(err);
+            }
+            unmatched.push(unmatched_v4(mapping, subnets, ifaces));
+        }
+    }
+    Ok(unmatched)
+}
+
+/// Collect v6 mappings that do not fall inside any of `subnets`.
+fn find_unmatched_v6(
+    mappings: &[IscStaticMapV6],
+    subnets: &[SubnetV6],
+    ifaces: &IfaceContext,
+) -> Result<Vec<UnmatchedMapping>> {
+    let mut unmatched = Vec::new();
+    for mapping in mappings {
+        if let Err(err) = find_subnet_for_ip_v6(&mapping.ipaddr, subnets) {
+            if !is_subnet_not_found(&err) {
+                return Err(err);
+            }
+            unmatched.push(unmatched_v6(mapping, subnets, ifaces));
+        }
+    }
+    Ok(unmatched)
+}
+
+/// Pre-validate mappings against the subnets that will exist after
+/// `--create-subnets`, reporting every offending mapping in one error.
+///
+/// With `--skip-unmatched` the offending mappings are dropped instead, added
+/// to `skips`, and the remaining mappings are returned.
+pub(crate) fn drop_unmatched_mappings(
+    mappings: &[IscStaticMap],
+    mappings_v6: &[IscStaticMapV6],
+    subnets: &[Subnet],
+    subnets_v6: &[SubnetV6],
+    ifaces: &IfaceContext,
+    options: &MigrationOptions,
+    skips: &mut Vec<MigrationError>,
+) -> Result<(Vec<IscStaticMap>, Vec<IscStaticMapV6>)> {
+    let unmatched_v4 = find_unmatched_v4(mappings, subnets, ifaces)?;
+    let unmatched_v6 = find_unmatched_v6(mappings_v6, subnets_v6, ifaces)?;
+    if unmatched_v4.is_empty() && unmatched_v6.is_empty() {
+        return Ok((mappings.to_vec(), mappings_v6.to_vec()));
+    }
+
+    if !options.skip_unmatched {
+        return Err(MigrationError::UnmatchedMappings {
+            mappings: unmatched_v4.into_iter().chain(unmatched_v6).collect(),
+        }
+        .into());
+    }
+
+    if options.verbose {
+        for entry in &unmatched_v4 {
+            term::action(
+                "SKIP",
+                format_args!("{} ({}) - no matching subnet", entry.ip, entry.iface),
+            );
+        }
+        for entry in &unmatched_v6 {
+            term::action(
+                "SKIP6",
+                format_args!("{} ({}) - no matching subnet", entry.ip, entry.iface),
+            );
+        }
+    }
+
+    skips.extend(unmatched_v4.iter().chain(&unmatched_v6).map(|entry| {
+        MigrationError::SubnetNotFound {
+            ip: entry.ip.clone(),
+        }
+    }));
+    let skip_v4: HashSet<&str> = unmatched_v4.iter().map(|u| u.ip.as_str()).collect();
+    let skip_v6: HashSet<&str> = unmatched_v6.iter().map(|u| u.ip.as_str()).collect();
+    Ok((
+        mappings
+            .iter()
+            .filter(|m| !skip_v4.contains(m.ipaddr.as_str()))
+            .cloned()
+            .collect(),
+        mappings_v6
+            .iter()
+            .filter(|m| !skip_v6.contains(m.ipaddr.as_str()))
+            .cloned()
+            .collect(),
+    ))
+}
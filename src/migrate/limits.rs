@@ -0,0 +1,118 @@
+//! Scalability checks run against the converted config.
+
+use std::collections::BTreeMap;
+use xmltree::Element;
+
+use crate::xml_helpers::{find_descendant_ci, get_child_ci};
+use crate::ConfigLimits;
+
+/// Check the converted config against `limits`, returning one warning per
+/// subnet or entry that exceeds them.
+pub(crate) fn check_limits(root: &Element, limits: &ConfigLimits) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if let Some(kea) = find_descendant_ci(root, "Kea") {
+        for (section, subnet_tag, label) in [
+            ("dhcp4", "subnet4", "Kea"),
+            ("dhcp6", "subnet6", "Kea DHCPv6"),
+        ] {
+            if let Some(dhcp) = find_descendant_ci(kea, section) {
+                check_kea_section(dhcp, subnet_tag, label, limits, &mut warnings);
+            }
+        }
+    }
+
+    if let Some(dnsmasq) = find_descendant_ci(root, "dnsmasq") {
+        for host in child_elements(dnsmasq, "hosts") {
+            let name = text_of(host, "host")
+                .or_else(|| text_of(host, "ip"))
+                .unwrap_or("(unnamed)");
+            check_description(host, "descr", "dnsmasq host", name, limits, &mut warnings);
+        }
+    }
+
+    warnings
+}
+
+fn check_kea_section(
+    dhcp: &Element,
+    subnet_tag: &str,
+    label: &str,
+    limits: &ConfigLimits,
+    warnings: &mut Vec<String>,
+) {
+    let cidrs: BTreeMap<&str, &str> = get_child_ci(dhcp, "subnets")
+        .map(|subnets| {
+            child_elements(subnets, subnet_tag)
+                .filter_map(|subnet| {
+                    let uuid = subnet.attributes.get("uuid")?;
+                    Some((uuid.as_str(), text_of(subnet, "subnet").unwrap_or("")))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut per_subnet: BTreeMap<&str, usize> = BTreeMap::new();
+    if let Some(reservations) = get_child_ci(dhcp, "reservations") {
+        for reservation in child_elements(reservations, "reservation") {
+            if let Some(subnet) = text_of(reservation, "subnet") {
+                *per_subnet.entry(subnet).or_default() += 1;
+            }
+            let name = text_of(reservation, "hostname")
+                .or_else(|| text_of(reservation, "ip_address"))
+                .unwrap_or("(unnamed)");
+            let kind = format!("{} reservation", label);
+            check_description(reservation, "description", &kind, name, limits, warnings);
+        }
+    }
+
+    if limits.max_reservations_per_subnet == 0 {
+        return;
+    }
+    for (uuid, count) in per_subnet {
+        if count > limits.max_reservations_per_subnet {
+            let subnet = cidrs.get(uuid).copied().unwrap_or(uuid);
+            warnings.push(format!(
+                "{} subnet {} has {} reservations (limit {}); the OPNsense GUI gets slow with \
+                 this many, consider the Kea SQL hosts backend instead",
+                label, subnet, count, limits.max_reservations_per_subnet
+            ));
+        }
+    }
+}
+
+fn check_description(
+    entry: &Element,
+    field: &str,
+    kind: &str,
+    name: &str,
+    limits: &ConfigLimits,
+    warnings: &mut Vec<String>,
+) {
+    if limits.max_description_len == 0 {
+        return;
+    }
+    if let Some(descr) = text_of(entry, field) {
+        let len = descr.chars().count();
+        if len > limits.max_description_len {
+            warnings.push(format!(
+                "{} {} description is {} characters long (limit {})",
+                kind, name, len, limits.max_description_len
+            ));
+        }
+    }
+}
+
+fn child_elements<'a>(parent: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    parent
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(move |el| el.name.eq_ignore_ascii_case(name))
+}
+
+fn text_of<'a>(el: &'a Element, name: &str) -> Option<&'a str> {
+    get_child_ci(el, name)
+        .and_then(|child| child.children.iter().find_map(|n| n.as_text()))
+        .filter(|text| !text.is_empty())
+}
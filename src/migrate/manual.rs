@@ -0,0 +1,132 @@
+//! Follow-up steps a scan finds for the operator.
+//!
+//! Some ISC settings have no counterpart the migration can write: DHCP
+//! failover, prefix delegation ranges, custom numbered options without
+//! a field in the target backend, and option values naming no address. Each becomes one line of a checklist of
+//! things to re-create by hand after converting.
+
+use xmltree::Element;
+
+use crate::backend::Backend;
+use crate::extract::{iter_isc_mappings, iter_isc_number_options_v4};
+use crate::migrate::routes::CLASSLESS_ROUTE_CODES;
+use crate::xml_helpers::{get_child_ci, get_children_ci};
+use crate::MigrationStats;
+
+fn text(el: &Element, name: &str) -> Option<String> {
+    get_child_ci(el, name)
+        .and_then(|e| e.get_text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Interface sections of every top-level `name` section.
+fn iface_sections<'a>(root: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    get_children_ci(root, name)
+        .flat_map(|section| section.children.iter().filter_map(|n| n.as_element()))
+}
+
+/// Manual steps for migrating `root` to `backend`, given the scan's `stats`.
+pub(crate) fn manual_actions(
+    root: &Element,
+    backend: &Backend,
+    stats: &MigrationStats,
+) -> Vec<String> {
+    let (kea, dnsmasq) = match backend {
+        Backend::Kea => (true, false),
+        Backend::Dnsmasq => (false, true),
+        Backend::Both => (true, true),
+    };
+    let mut actions = Vec::new();
+
+    for iface in iface_sections(root, "dhcpd") {
+        if let Some(peer) = text(iface, "failover_peerip") {
+            if kea {
+                actions.push(format!(
+                    "Set up Kea HA for {}: ISC failover with peer {} is not migrated.",
+                    iface.name, peer
+                ));
+            }
+            if dnsmasq {
+                actions.push(format!(
+                    "Serve {} from one node or split its range: dnsmasq has no DHCP \
+                     failover (ISC peer {}).",
+                    iface.name, peer
+                ));
+            }
+        }
+    }
+
+    for iface in iface_sections(root, "dhcpdv6") {
+        let Some(range) = get_child_ci(iface, "prefixrange") else {
+            continue;
+        };
+        let (Some(from), Some(to)) = (text(range, "from"), text(range, "to")) else {
+            continue;
+        };
+        let length = text(range, "prefixlength").unwrap_or_else(|| "?".to_string());
+        if kea {
+            actions.push(format!(
+                "Create a Kea prefix delegation pool for {}-{} (/{}) on {}.",
+                from, to, length, iface.name
+            ));
+        }
+        if dnsmasq {
+            actions.push(format!(
+                "Delegate {}-{} (/{}) on {} another way: dnsmasq cannot delegate prefixes.",
+                from, to, length, iface.name
+            ));
+        }
+    }
+    if stats.prefixes_v6_unmigrated > 0 {
+        actions.push(format!(
+            "Reserve the delegated prefixes of {} DHCPv6 static mapping(s) by hand.",
+            stats.prefixes_v6_unmigrated
+        ));
+    }
+
+    for option in iter_isc_number_options_v4(root) {
+        let routes = CLASSLESS_ROUTE_CODES.contains(&option.number);
+        let targets: Vec<&str> = [(kea, "Kea"), (dnsmasq && !routes, "dnsmasq")]
+            .into_iter()
+            .filter(|(unmigrated, _)| *unmigrated)
+            .map(|(_, name)| name)
+            .collect();
+        if targets.is_empty() {
+            continue;
+        }
+        actions.push(format!(
+            "Re-create custom option {} ({}) on {} in {}.",
+            option.number,
+            option.value,
+            option.iface,
+            targets.join(" and ")
+        ));
+    }
+
+    if kea {
+        for mapping in iter_isc_mappings(root).filter(|m| m.gateway_suppressed) {
+            actions.push(format!(
+                "Keep the subnet's router from {} on {} by hand: its ISC gateway is none, \
+                 which a Kea reservation cannot express.",
+                mapping.ipaddr, mapping.iface
+            ));
+        }
+    }
+
+    for value in &stats.unresolved_option_values {
+        actions.push(format!(
+            "Enter an address for {} on {} in place of '{}', which {}.",
+            value.field, value.iface, value.value, value.reason
+        ));
+    }
+
+    if let Some(ha) = &stats.ha_sync {
+        actions.push(format!(
+            "Add the new backend to the HA sync items so {} receives it.",
+            ha.peer
+        ));
+    }
+
+    actions
+}
@@ -0,0 +1,76 @@
+//! Origin markers on elements created by a migration.
+
+use std::collections::HashSet;
+use xmltree::Element;
+
+use crate::Clock;
+
+/// Attribute stamped on created elements, valued with the tool version.
+pub const ORIGIN_ATTR: &str = "isc2kea-origin";
+/// Attribute stamped alongside [`ORIGIN_ATTR`], valued with the run's time
+/// as an RFC 3339 UTC timestamp.
+pub const CREATED_ATTR: &str = "isc2kea-created";
+
+/// `uuid`s of every element in the config.
+pub(crate) fn element_uuids(root: &Element) -> HashSet<String> {
+    let mut uuids = HashSet::new();
+    collect_uuids(root, &mut uuids);
+    uuids
+}
+
+fn collect_uuids(el: &Element, uuids: &mut HashSet<String>) {
+    if let Some(uuid) = el.attributes.get("uuid") {
+        uuids.insert(uuid.clone());
+    }
+    for child in el.children.iter().filter_map(|n| n.as_element()) {
+        collect_uuids(child, uuids);
+    }
+}
+
+/// Stamp [`ORIGIN_ATTR`] and [`CREATED_ATTR`] on elements whose `uuid` is
+/// not in `existing`, returning how many were marked. Every entry the migration creates
+/// (reservations, subnets, hosts, ranges, options, overrides) has a fresh
+/// `uuid`, so this covers them without each backend marking its own.
+pub(crate) fn mark_created(root: &mut Element, existing: &HashSet<String>, clock: &Clock) -> usize {
+    let stamp = Stamp {
+        origin: format!("v{}", env!("CARGO_PKG_VERSION")),
+        created: clock.now_rfc3339(),
+    };
+    let mut marked = 0;
+    mark(root, existing, &stamp, &mut marked);
+    marked
+}
+
+struct Stamp {
+    origin: String,
+    created: String,
+}
+
+fn mark(el: &mut Element, existing: &HashSet<String>, stamp: &Stamp, marked: &mut usize) {
+    if el
+        .attributes
+        .get("uuid")
+        .is_some_and(|uuid| !existing.contains(uuid))
+    {
+        el.attributes
+            .insert(ORIGIN_ATTR.to_string(), stamp.origin.clone());
+        el.attributes
+            .insert(CREATED_ATTR.to_string(), stamp.created.clone());
+        *marked += 1;
+    }
+    for child in el.children.iter_mut().filter_map(|n| n.as_mut_element()) {
+        mark(child, existing, stamp, marked);
+    }
+}
+
+/// Remove [`ORIGIN_ATTR`] and [`CREATED_ATTR`] from every element,
+/// returning how many had either.
+pub(crate) fn strip_markers(el: &mut Element) -> usize {
+    let origin = el.attributes.remove(ORIGIN_ATTR).is_some();
+    let created = el.attributes.remove(CREATED_ATTR).is_some();
+    let mut stripped = usize::from(origin || created);
+    for child in el.children.iter_mut().filter_map(|n| n.as_mut_element()) {
+        stripped += strip_markers(child);
+    }
+    stripped
+}
@@ -1,69 +1,201 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::io::{Read, Write};
-use xmltree::{Element, EmitterConfig};
+use std::time::Instant;
+use xmltree::Element;
 
 use crate::backend::Backend;
 use crate::extract::{
-    extract_isc_mappings, extract_isc_mappings_v6, extract_isc_ranges, extract_isc_ranges_v6,
-    extract_kea_subnets, extract_kea_subnets_v6,
+    extract_interface_labels, extract_legacy_isc_mappings, extract_legacy_isc_mappings_v6,
+    iter_isc_ranges, iter_isc_ranges_v6,
+};
+use crate::overrides::apply_field_overrides;
+use crate::plan::retain_planned;
+use crate::registry::MigrationBackend;
+use crate::source::{SourceRegistry, DHCPD_SOURCE};
+use crate::term;
+use crate::warnings::WarningScope;
+use crate::xml_helpers::{count_elements, XmlDocument};
+use crate::{
+    Clock, EmitMode, IscLease, IscStaticMap, IscStaticMapV6, LeaseActivity, LeaseAudit,
+    MappingBatch, MigrationError, MigrationOptions, MigrationPlan, MigrationStats, MigrationTiming,
 };
-use crate::{MigrationOptions, MigrationStats};
 
+mod activity;
+mod audit;
+mod dns_hosts;
+#[cfg(feature = "dnsmasq")]
 mod dnsmasq;
+mod dual_stack;
+mod existing;
+mod export;
+mod findings;
+mod fragments;
+mod ha;
+mod hostnames;
+pub(crate) mod hwaddr;
+#[cfg(feature = "kea")]
 mod kea;
+mod limits;
+mod manual;
+mod markers;
+mod option_values;
 mod options;
+mod parsed;
+mod placeholders;
+mod routes;
+mod schema;
 pub(crate) mod services;
 mod subnets;
+mod transaction;
 mod utils;
+mod uuids;
+mod validate;
+
+pub use export::{export_config, export_config_split, ExportFile};
+#[cfg(feature = "cli")]
+pub(crate) use findings::retain_categories;
+pub use findings::verify_findings;
+#[cfg(feature = "cli")]
+pub(crate) use ha::peer_config;
+pub use markers::{CREATED_ATTR, ORIGIN_ATTR};
+pub use parsed::ParsedConfig;
+pub use validate::{validate_input, validate_output};
+
+#[cfg(feature = "dnsmasq")]
+pub(crate) use dns_hosts::first_domain;
+#[cfg(feature = "dnsmasq")]
+pub(crate) use dnsmasq::DnsmasqBackend;
+#[cfg(feature = "kea")]
+pub(crate) use kea::KeaBackend;
+#[cfg(feature = "kea")]
+pub(crate) use options::domain_search_csv;
+
+/// Backends selected for a run, labelled for per-backend stats.
+type SelectedBackends<'a> = [(Backend, &'a dyn MigrationBackend)];
 
 /// Scan the configuration and return basic counts without validation
 pub fn scan_counts<R: Read>(reader: R, backend: &Backend) -> Result<MigrationStats> {
-    let root = Element::parse(reader).context("Failed to parse XML")?;
-
-    let isc_mappings = extract_isc_mappings(&root)?;
-    let isc_mappings_v6 = extract_isc_mappings_v6(&root)?;
-    let isc_ranges = extract_isc_ranges(&root)?;
-    let isc_ranges_v6 = extract_isc_ranges_v6(&root)?;
-
-    let (target_subnets_found, target_subnets_v6_found) = match backend {
-        Backend::Kea => {
-            let kea_subnets = extract_kea_subnets(&root)?;
-            let kea_subnets_v6 = extract_kea_subnets_v6(&root)?;
-            (kea_subnets.len(), kea_subnets_v6.len())
-        }
-        Backend::Dnsmasq => (0, 0),
-    };
-
-    Ok(MigrationStats {
-        isc_mappings_found: isc_mappings.len(),
-        isc_mappings_v6_found: isc_mappings_v6.len(),
-        isc_ranges_found: isc_ranges.len(),
-        isc_ranges_v6_found: isc_ranges_v6.len(),
-        target_subnets_found,
-        target_subnets_v6_found,
-        reservations_to_create: 0,
-        reservations_v6_to_create: 0,
-        reservations_skipped: 0,
-        reservations_v6_skipped: 0,
-        ..Default::default()
-    })
+    ParsedConfig::parse(reader)?.scan_counts(backend)
 }
 
 /// Scan the configuration and return statistics without modifying anything
 pub fn scan_config<R: Read>(reader: R, options: &MigrationOptions) -> Result<MigrationStats> {
-    let root = Element::parse(reader).context("Failed to parse XML")?;
-    let isc_mappings = extract_isc_mappings(&root)?;
-    let isc_mappings_v6 = extract_isc_mappings_v6(&root)?;
-    let isc_ranges = extract_isc_ranges(&root)?;
-    let isc_ranges_v6 = extract_isc_ranges_v6(&root)?;
+    ParsedConfig::parse(reader)?.scan(options)
+}
+
+/// Compare the leases active now with the DHCPv4 reservations `backend`
+/// has in a converted config.
+pub fn audit_config<R: Read>(
+    reader: R,
+    backend: &Backend,
+    leases: &[IscLease],
+    clock: &Clock,
+) -> Result<LeaseAudit> {
+    ParsedConfig::parse(reader)?.audit(backend, leases, clock)
+}
+
+/// Plan the mappings a convert with `options` would migrate, for review
+/// before `options.plan` applies it.
+pub fn plan_config<R: Read>(reader: R, options: &MigrationOptions) -> Result<MigrationPlan> {
+    ParsedConfig::parse(reader)?.plan(options)
+}
 
-    let mut stats = match options.backend {
-        Backend::Kea => kea::scan_kea(&root, &isc_mappings, &isc_mappings_v6, options),
-        Backend::Dnsmasq => dnsmasq::scan_dnsmasq(&root, &isc_mappings, &isc_mappings_v6, options),
-    }?;
+/// Scan the configuration with `backend` in place of `options.backend`.
+#[cfg(feature = "backend-api")]
+pub fn scan_config_with_backend<R: Read>(
+    reader: R,
+    options: &MigrationOptions,
+    backend: &dyn MigrationBackend,
+) -> Result<MigrationStats> {
+    ParsedConfig::parse(reader)?.scan_with_backend(options, backend)
+}
 
-    stats.isc_ranges_found = isc_ranges.len();
-    stats.isc_ranges_v6_found = isc_ranges_v6.len();
+fn scan_with_backends(
+    parsed: &ParsedConfig,
+    options: &MigrationOptions,
+    backends: &SelectedBackends,
+) -> Result<MigrationStats> {
+    let warnings = WarningScope::begin(options.max_warnings);
+    let mut timing = MigrationTiming {
+        parse: parsed.parse,
+        input_elements: parsed.input_elements,
+        ..Default::default()
+    };
+    // The shared document is only copied when its uuids are regenerated
+    let mut root = Cow::Borrowed(parsed.root());
+    let uuids_regenerated = if options.fix_duplicate_uuids {
+        uuids::check_duplicate_uuids(root.to_mut(), options)?
+    } else {
+        uuids::ensure_unique_uuids(&root, &options.backend)?;
+        0
+    };
+
+    let started = Instant::now();
+    let IscMappingSet {
+        v4: isc_mappings,
+        v6: isc_mappings_v6,
+        disabled_v4,
+        disabled_v6,
+        orphaned_v4,
+        orphaned_v6,
+        batch,
+        activity,
+        stale,
+        dual_stack,
+        rejected_v6,
+    } = extract_ordered_mappings(&root, options)?;
+    let isc_ranges_found = iter_isc_ranges(&root).count();
+    let isc_ranges_v6_found = iter_isc_ranges_v6(&root).count();
+    timing.extract = started.elapsed();
+
+    ensure_placeholders_allowed(options, backends)?;
+    let started = Instant::now();
+    let collisions = |kind: &Backend| {
+        hostnames::hostname_collisions(&root, kind, &isc_mappings, &isc_mappings_v6)
+    };
+    let mut stats = if let [(kind, backend)] = backends {
+        let mut stats = backend.scan(&root, &isc_mappings, &isc_mappings_v6, options)?;
+        stats.hostname_collisions = collisions(kind);
+        stats
+    } else {
+        ensure_both_allowed(options)?;
+        let mut results = Vec::new();
+        for (kind, backend) in backends {
+            let mut stats = backend.scan(&root, &isc_mappings, &isc_mappings_v6, options)?;
+            stats.hostname_collisions = collisions(kind);
+            results.push((kind.clone(), stats));
+        }
+        combine_backend_stats(results)
+    };
+
+    stats.isc_placeholders = placeholders::placeholder_mappings(&root);
+    #[cfg(feature = "kea")]
+    if options.migrate_placeholders {
+        stats.placeholders_migrated =
+            placeholders::plan_kea_placeholders(&root, &stats.isc_placeholders, &isc_mappings)?
+                .len();
+    }
+    stats.isc_ranges_found = isc_ranges_found;
+    stats.isc_ranges_v6_found = isc_ranges_v6_found;
+    stats.batch = batch;
+    stats.lease_activity = activity;
+    stats.isc_mappings_stale = stale;
+    stats.dual_stack_linked = dual_stack;
+    add_rejected_v6(&mut stats, rejected_v6);
+    stats.uuids_regenerated = uuids_regenerated;
+    stats.isc_mappings_disabled_iface = disabled_v4;
+    stats.isc_mappings_v6_disabled_iface = disabled_v6;
+    stats.isc_mappings_orphaned_iface = orphaned_v4;
+    stats.isc_mappings_v6_orphaned_iface = orphaned_v6;
+    stats.interface_labels = parsed.interface_labels().clone();
+    stats.ha_sync = ha::ha_sync(&root);
+    stats.manual_actions = manual::manual_actions(&root, &options.backend, &stats);
+    stats.warnings_suppressed = warnings.finish();
+    timing.convert = started.elapsed();
+    timing.output_elements = timing.input_elements;
+    stats.timing = timing;
 
     Ok(stats)
 }
@@ -75,33 +207,478 @@ pub fn convert_config<R: Read, W: Write>(
     writer: W,
     options: &MigrationOptions,
 ) -> Result<MigrationStats> {
-    let mut root = Element::parse(reader).context("Failed to parse XML")?;
-    let isc_mappings = extract_isc_mappings(&root)?;
-    let isc_mappings_v6 = extract_isc_mappings_v6(&root)?;
-
-    let stats = match options.backend {
-        Backend::Kea => kea::convert_kea(&mut root, &isc_mappings, &isc_mappings_v6, options)?,
-        Backend::Dnsmasq => {
-            dnsmasq::convert_dnsmasq(&mut root, &isc_mappings, &isc_mappings_v6, options)?
+    ParsedConfig::parse(reader)?.convert(writer, options)
+}
+
+/// Convert the configuration with `backend` in place of `options.backend`.
+#[cfg(feature = "backend-api")]
+pub fn convert_config_with_backend<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    options: &MigrationOptions,
+    backend: &dyn MigrationBackend,
+) -> Result<MigrationStats> {
+    ParsedConfig::parse(reader)?.convert_with_backend(writer, options, backend)
+}
+
+/// Merge ISC mappings from `source` into an already partially-migrated
+/// `target` config, writing the result and reporting what was added.
+///
+/// Mappings, ranges and options are read from the ISC DHCP sections
+/// (`dhcpd`, `dhcpdv6`) of `source`; everything else, including Kea/dnsmasq
+/// entries added since the first convert and the ISC sections themselves,
+/// comes from `target`. `enable_backend` disables the interfaces it migrates
+/// in the target's ISC sections. Reservations, hosts and subnets that already
+/// exist in `target` are skipped as duplicates.
+pub fn merge_config<S: Read, T: Read, W: Write>(
+    source: S,
+    target: T,
+    writer: W,
+    options: &MigrationOptions,
+) -> Result<MigrationStats> {
+    let started = Instant::now();
+    let source = XmlDocument::parse(source).context("Failed to parse input XML")?;
+    ParsedConfig::from_document(source, started)?.merge(target, writer, options)
+}
+
+fn convert_document<W: Write>(
+    parsed: ParsedConfig,
+    writer: W,
+    options: &MigrationOptions,
+    backends: &SelectedBackends,
+) -> Result<MigrationStats> {
+    let warnings = WarningScope::begin(options.max_warnings);
+    let mut timing = MigrationTiming {
+        parse: parsed.parse,
+        input_elements: parsed.input_elements,
+        ..Default::default()
+    };
+    let mut doc = parsed.doc;
+    let snapshot = (options.emit == EmitMode::ChangesOnly)
+        .then(|| fragments::SectionSnapshot::take(&doc.root));
+    // A merge converts the source's ISC sections and puts the target's back
+    // afterwards
+    let target_isc = parsed
+        .isc_source
+        .map(|source| utils::swap_isc_sections(&mut doc.root, source));
+
+    let started = Instant::now();
+    let IscMappingSet {
+        v4: isc_mappings,
+        v6: isc_mappings_v6,
+        disabled_v4,
+        disabled_v6,
+        orphaned_v4,
+        orphaned_v6,
+        batch,
+        activity,
+        stale,
+        dual_stack,
+        rejected_v6,
+    } = extract_ordered_mappings(&doc.root, options)?;
+    timing.extract = started.elapsed();
+    ensure_enable_allowed(options, batch)?;
+    let ha_sync = ha::ha_sync(&doc.root);
+    ha::ensure_ha_allowed(ha_sync.as_ref(), options)?;
+    ensure_placeholders_allowed(options, backends)?;
+    if options.create_dns_hosts && cfg!(not(feature = "dnsmasq")) {
+        return Err(anyhow!(
+            "--create-dns-hosts writes dnsmasq hosts; this build lacks the `dnsmasq` cargo feature"
+        ));
+    }
+
+    let started = Instant::now();
+    // Every section is changed on a staged copy and checked as a whole
+    // before the document sees any of it
+    let mut tx = transaction::Transaction::begin(&doc.root);
+    let uuids_regenerated = uuids::check_duplicate_uuids(tx.staged_mut(), options)?;
+    #[cfg(feature = "dnsmasq")]
+    let validate_hosts = options.create_dns_hosts
+        || backends
+            .iter()
+            .any(|(kind, _)| matches!(kind, Backend::Dnsmasq));
+    #[cfg(feature = "dnsmasq")]
+    let existing_hosts = dnsmasq::dnsmasq_host_uuids(tx.staged());
+    #[cfg(feature = "dnsmasq")]
+    let existing_dnsmasq = markers::element_uuids(tx.staged());
+    let existing_uuids = options
+        .mark_created
+        .then(|| markers::element_uuids(tx.staged()));
+    let schema_snapshot = options
+        .validate_schema
+        .then(|| schema::SchemaSnapshot::take(tx.staged()));
+    // Collisions are found before each backend converts, against the
+    // entries it already has
+    let mut stats = if let [(kind, backend)] = backends {
+        let collisions =
+            hostnames::hostname_collisions(tx.staged(), kind, &isc_mappings, &isc_mappings_v6);
+        let mut stats =
+            backend.convert(tx.staged_mut(), &isc_mappings, &isc_mappings_v6, options)?;
+        stats.hostname_collisions = collisions;
+        stats
+    } else {
+        ensure_both_allowed(options)?;
+        let mut results = Vec::new();
+        for (kind, backend) in backends {
+            let collisions =
+                hostnames::hostname_collisions(tx.staged(), kind, &isc_mappings, &isc_mappings_v6);
+            let mut stats =
+                backend.convert(tx.staged_mut(), &isc_mappings, &isc_mappings_v6, options)?;
+            stats.hostname_collisions = collisions;
+            results.push((kind.clone(), stats));
         }
+        combine_backend_stats(results)
     };
+    let isc_placeholders = placeholders::placeholder_mappings(tx.staged());
+    #[cfg(feature = "kea")]
+    if options.migrate_placeholders {
+        let planned =
+            placeholders::plan_kea_placeholders(tx.staged(), &isc_placeholders, &isc_mappings)?;
+        stats.placeholders_migrated =
+            placeholders::apply_kea_placeholders(tx.staged_mut(), &planned)?;
+    }
+    stats.isc_placeholders = isc_placeholders;
+    if options.create_dns_hosts || options.register_unbound {
+        let records =
+            dns_hosts::dns_host_records(tx.staged(), &isc_mappings, &isc_mappings_v6, options)?;
+        #[cfg(feature = "dnsmasq")]
+        if options.create_dns_hosts {
+            let counts = dns_hosts::apply_dnsmasq_dns_hosts(tx.staged_mut(), &records, options)?;
+            stats.dns_hosts_created = counts.created;
+            stats.dns_hosts_updated = counts.updated;
+            stats.dns_hosts_skipped = counts.skipped;
+        }
+        if options.register_unbound {
+            let counts = dns_hosts::apply_unbound_dns_hosts(tx.staged_mut(), &records, options)?;
+            stats.unbound_hosts_created = counts.created;
+            stats.unbound_hosts_skipped = counts.skipped;
+        }
+    }
+    #[cfg(feature = "dnsmasq")]
+    dnsmasq::order_created_children(tx.staged_mut(), &existing_dnsmasq);
+    if options.strip_markers {
+        stats.markers_stripped = markers::strip_markers(tx.staged_mut());
+    }
+    if let Some(existing) = &existing_uuids {
+        stats.markers_added = markers::mark_created(tx.staged_mut(), existing, &options.clock);
+    }
+    #[cfg(feature = "dnsmasq")]
+    if validate_hosts {
+        tx.check(move |staged| dnsmasq::validate_dnsmasq_hosts(staged, &existing_hosts));
+    }
+    if let Some(before) = schema_snapshot {
+        tx.check(move |staged| Ok(schema::check_schema(staged, &before)?));
+    }
+    tx.commit(&mut doc.root)?;
+    if let Some(sections) = target_isc {
+        utils::swap_isc_sections(&mut doc.root, sections);
+        services::disable_isc_dhcp_ifaces(
+            &mut doc.root,
+            &stats.isc_disabled_v4,
+            &stats.isc_disabled_v6,
+        )?;
+    }
+    stats.batch = batch;
+    stats.lease_activity = activity;
+    stats.isc_mappings_stale = stale;
+    stats.dual_stack_linked = dual_stack;
+    add_rejected_v6(&mut stats, rejected_v6);
+    stats.uuids_regenerated = uuids_regenerated;
+    stats.isc_mappings_disabled_iface = disabled_v4;
+    stats.isc_mappings_v6_disabled_iface = disabled_v6;
+    stats.isc_mappings_orphaned_iface = orphaned_v4;
+    stats.isc_mappings_v6_orphaned_iface = orphaned_v6;
+    stats.interface_labels = extract_interface_labels(&doc.root);
+    stats.ha_sync = ha_sync;
+    stats.warnings = limits::check_limits(&doc.root, &options.limits);
+    timing.convert = started.elapsed();
+    timing.output_elements = count_elements(&doc.root);
 
-    // Write the updated XML with human-readable indentation
-    let emitter_config = EmitterConfig::new()
-        .perform_indent(true)
-        .indent_string("  ")
-        .write_document_declaration(true);
-    root.write_with_config(writer, emitter_config)
-        .context("Failed to write XML")?;
+    // Write the updated XML in the requested style, keeping comments around
+    // the root element
+    let started = Instant::now();
+    match &snapshot {
+        Some(before) => {
+            stats.changed_sections =
+                fragments::write_changed_sections(before, &doc.root, writer, &options.output_style)
+                    .context("Failed to write XML fragments")?;
+        }
+        None => doc
+            .write(writer, &options.output_style)
+            .context("Failed to write XML")?,
+    }
+    timing.write = started.elapsed();
+    stats.warnings_suppressed = warnings.finish();
+    stats.timing = timing;
 
     Ok(stats)
 }
 
+/// Count DHCPv6 mappings dropped before the backends ran as found and
+/// skipped, with why.
+fn add_rejected_v6(stats: &mut MigrationStats, rejected: Vec<MigrationError>) {
+    stats.isc_mappings_v6_found += rejected.len();
+    stats.reservations_v6_skipped += rejected.len();
+    stats.skips.extend(rejected);
+}
+
+/// Reject options that cannot apply to Kea and dnsmasq at the same time.
+fn ensure_both_allowed(options: &MigrationOptions) -> Result<()> {
+    if options.enable_backend {
+        return Err(anyhow!(
+            "--enable-backend cannot be used with --backend both: only one DHCP server can \
+             take over from ISC. Enable the backends separately."
+        ));
+    }
+    Ok(())
+}
+
+/// Placeholders only become Kea reservations, so refuse to migrate them
+/// without Kea among the backends.
+fn ensure_placeholders_allowed(
+    options: &MigrationOptions,
+    backends: &SelectedBackends,
+) -> Result<()> {
+    if options.migrate_placeholders
+        && !backends
+            .iter()
+            .any(|(kind, _)| matches!(kind, Backend::Kea))
+    {
+        return Err(anyhow!(
+            "--migrate-placeholders creates Kea reservations without an address; \
+             use it with --backend kea or both."
+        ));
+    }
+    Ok(())
+}
+
+/// Enabling the backend disables ISC DHCP, so refuse while a batch leaves
+/// mappings for later runs.
+fn ensure_enable_allowed(options: &MigrationOptions, batch: Option<MappingBatch>) -> Result<()> {
+    if let Some(batch) = batch.filter(|b| options.enable_backend && b.remaining() > 0) {
+        return Err(anyhow!(
+            "--enable-backend would disable ISC DHCP with {} mapping(s) not yet migrated. \
+             Enable the backend with the last batch.",
+            batch.remaining()
+        ));
+    }
+    Ok(())
+}
+
+/// Merge stats from several backends run against the same ISC mappings.
+///
+/// ISC counts are shared; reservation and subnet counts are summed, except
+/// that a mapping several backends skip is counted and listed once. Each
+/// backend's own stats are kept in `per_backend`.
+fn combine_backend_stats(results: Vec<(Backend, MigrationStats)>) -> MigrationStats {
+    let mut combined = MigrationStats::default();
+    // A mapping every backend skips is one skipped mapping
+    let mut skipped_ips = HashSet::new();
+    for (_, stats) in &results {
+        let (mut repeated, mut repeated_v6) = (0, 0);
+        for skip in &stats.skips {
+            if let Some(ip) = skipped_ip(skip) {
+                if !skipped_ips.insert(ip) {
+                    if ip.contains(':') {
+                        repeated_v6 += 1;
+                    } else {
+                        repeated += 1;
+                    }
+                    continue;
+                }
+            }
+            combined.skips.push(skip.clone());
+        }
+        combined.isc_mappings_found = stats.isc_mappings_found;
+        combined.isc_mappings_v6_found = stats.isc_mappings_v6_found;
+        combined.ranges_merged = stats.ranges_merged;
+        combined.option_values_resolved = stats.option_values_resolved;
+        combined
+            .unresolved_option_values
+            .clone_from(&stats.unresolved_option_values);
+        combined.subnets_created += stats.subnets_created;
+        combined.pools_created += stats.pools_created;
+        combined.ranges_created += stats.ranges_created;
+        combined.options_created += stats.options_created;
+        combined.options_skipped += stats.options_skipped;
+        combined.target_subnets_found += stats.target_subnets_found;
+        combined.target_subnets_v6_found += stats.target_subnets_v6_found;
+        combined.reservations_to_create += stats.reservations_to_create;
+        combined.reservations_v6_to_create += stats.reservations_v6_to_create;
+        combined.reservations_skipped += stats.reservations_skipped.saturating_sub(repeated);
+        combined.reservations_v6_skipped +=
+            stats.reservations_v6_skipped.saturating_sub(repeated_v6);
+        combined.prefix_reservations_v6 += stats.prefix_reservations_v6;
+        combined.prefixes_v6_unmigrated += stats.prefixes_v6_unmigrated;
+        for iface in &stats.interfaces_configured {
+            if !combined.interfaces_configured.contains(iface) {
+                combined.interfaces_configured.push(iface.clone());
+            }
+        }
+        hostnames::merge_collisions(
+            &mut combined.hostname_collisions,
+            &stats.hostname_collisions,
+        );
+        for iface in &stats.interfaces_not_listening {
+            if !combined.interfaces_not_listening.contains(iface) {
+                combined.interfaces_not_listening.push(iface.clone());
+            }
+        }
+    }
+    combined.per_backend = results;
+    combined
+}
+
+/// Address of the ISC mapping a backend skipped.
+fn skipped_ip(skip: &MigrationError) -> Option<&str> {
+    match skip {
+        MigrationError::SubnetNotFound { ip }
+        | MigrationError::DuplicateIp { ip }
+        | MigrationError::DuplicateMac { ip, .. }
+        | MigrationError::DuplicateClientId { ip, .. }
+        | MigrationError::DuplicateDuid { ip, .. } => Some(ip),
+        _ => None,
+    }
+}
+
+/// ISC mappings selected for migration, with counts of mappings ignored
+/// because ISC DHCP is disabled on their interface.
+struct IscMappingSet {
+    v4: Vec<IscStaticMap>,
+    v6: Vec<IscStaticMapV6>,
+    disabled_v4: usize,
+    disabled_v6: usize,
+    /// Mappings under sections for interfaces missing from `<interfaces>`
+    orphaned_v4: usize,
+    orphaned_v6: usize,
+    batch: Option<MappingBatch>,
+    /// Lease activity of the DHCPv4 mappings, when leases are given
+    activity: Vec<LeaseActivity>,
+    /// DHCPv4 mappings dropped by `skip_stale_days`
+    stale: usize,
+    /// DHCPv6 mappings paired with a DHCPv4 mapping by `link_dual_stack`
+    dual_stack: usize,
+    /// DHCPv6 mappings dropped for a link-local or IPv4-mapped address
+    rejected_v6: Vec<MigrationError>,
+}
+
+/// Extract mappings from `--source` (with the `--legacy-paths` fallback for
+/// the ISC sections), drop ones on disabled interfaces (unless
+/// `--include-disabled-interfaces`), resolve ones on removed interfaces
+/// (`--orphaned-interfaces`), drop stale ones (`--skip-stale-days`), apply
+/// `--field-overrides` and the requested reservation order, and keep the
+/// `offset`/`limit` batch.
+fn extract_ordered_mappings(root: &Element, options: &MigrationOptions) -> Result<IscMappingSet> {
+    let registry = SourceRegistry::default();
+    let source = registry.resolve(options.source, root)?;
+    let mut isc_mappings = source.mappings(root);
+    let mut isc_mappings_v6 = source.mappings_v6(root);
+    let is_dhcpd = source.name() == DHCPD_SOURCE;
+    if is_dhcpd {
+        fallback_to_legacy(
+            &mut isc_mappings,
+            extract_legacy_isc_mappings(root)?,
+            "DHCPv4",
+            options,
+        );
+        fallback_to_legacy(
+            &mut isc_mappings_v6,
+            extract_legacy_isc_mappings_v6(root)?,
+            "DHCPv6",
+            options,
+        );
+    } else if options.verbose {
+        term::action(
+            "SOURCE",
+            format_args!(
+                "{} static mapping(s) from {}",
+                isc_mappings.len() + isc_mappings_v6.len(),
+                source.name()
+            ),
+        );
+    }
+    // Only the ISC sections say whether DHCP is enabled per interface
+    let (disabled_v4, disabled_v6) = if options.include_disabled_interfaces || !is_dhcpd {
+        (0, 0)
+    } else {
+        utils::drop_disabled_iface_mappings(root, &mut isc_mappings, &mut isc_mappings_v6, options)
+    };
+    let (orphaned_v4, orphaned_v6) = utils::resolve_orphaned_iface_mappings(
+        root,
+        &mut isc_mappings,
+        &mut isc_mappings_v6,
+        options,
+    )?;
+    let rejected_v6 = utils::normalize_v6_addresses(&mut isc_mappings_v6, options);
+    let (activity, stale) = activity::apply_lease_activity(&mut isc_mappings, options);
+    apply_field_overrides(
+        &mut isc_mappings,
+        &mut isc_mappings_v6,
+        &options.field_overrides,
+    );
+    utils::apply_hostname_policy(&mut isc_mappings, &mut isc_mappings_v6, options)?;
+    let dual_stack = if options.link_dual_stack {
+        dual_stack::link_dual_stack(&mut isc_mappings, &mut isc_mappings_v6, options)
+    } else {
+        0
+    };
+    if let Some(plan) = &options.plan {
+        retain_planned(plan, &mut isc_mappings, &mut isc_mappings_v6)?;
+    }
+    utils::sort_mappings_v4(&mut isc_mappings, options.reservation_order);
+    utils::sort_mappings_v6(&mut isc_mappings_v6, options.reservation_order);
+    let batch = utils::select_batch(&mut isc_mappings, &mut isc_mappings_v6, options);
+    Ok(IscMappingSet {
+        v4: isc_mappings,
+        v6: isc_mappings_v6,
+        disabled_v4,
+        disabled_v6,
+        orphaned_v4,
+        orphaned_v6,
+        batch,
+        activity,
+        stale,
+        dual_stack,
+        rejected_v6,
+    })
+}
+
+/// Use mappings from legacy `<installedpackages>` locations when the
+/// standard sections hold none and `legacy_paths` is set; otherwise point
+/// at the flag.
+fn fallback_to_legacy<T>(
+    mappings: &mut Vec<T>,
+    legacy: Vec<T>,
+    family: &str,
+    options: &MigrationOptions,
+) {
+    if !mappings.is_empty() || legacy.is_empty() {
+        return;
+    }
+    if options.legacy_paths {
+        term::warning(format_args!(
+            "no {} static mappings in the standard ISC sections; using {} from legacy \
+             <installedpackages> locations.",
+            family,
+            legacy.len()
+        ));
+        *mappings = legacy;
+    } else {
+        term::warning(format_args!(
+            "{} {} static mapping(s) found only in legacy <installedpackages> locations; rerun \
+             with --legacy-paths to migrate them.",
+            legacy.len(),
+            family
+        ));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "kea")]
     fn test_fail_if_existing_flag() {
         let xml_with_existing = r#"<?xml version="1.0"?>
 <opnsense>
@@ -113,6 +690,7 @@ mod tests {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -150,18 +728,20 @@ mod tests {
             result.is_err(),
             "Should fail when existing reservations found with --fail-if-existing"
         );
-        assert!(result
-            .unwrap_err()
-            .to_string()
-            .contains("Existing reservations found"));
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<MigrationError>(),
+            Some(&MigrationError::ExistingReservations { count: 1 })
+        );
     }
 
     #[test]
+    #[cfg(all(feature = "kea", feature = "dnsmasq"))]
     fn test_scan_counts_kea_and_dnsmasq() {
         let xml = r#"<?xml version="1.0"?>
 <opnsense>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -197,4 +777,59 @@ mod tests {
         assert_eq!(stats_dns.target_subnets_found, 0);
         assert_eq!(stats_dns.target_subnets_v6_found, 0);
     }
+
+    #[test]
+    fn test_select_batch_spans_v4_then_v6() {
+        let v4 = |ip: &str| IscStaticMap {
+            iface: "lan".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            ipaddr: ip.to_string(),
+            hostname: None,
+            cid: None,
+            descr: None,
+            domain: None,
+            domain_search: None,
+            dns_servers: Vec::new(),
+            gateway: None,
+            gateway_suppressed: false,
+        };
+        let v6 = |ip: &str| IscStaticMapV6 {
+            iface: "lan".to_string(),
+            duid: "00:01".to_string(),
+            ipaddr: ip.to_string(),
+            hostname: None,
+            descr: None,
+            domain_search: None,
+            prefix: None,
+            prefix_len: None,
+        };
+        let mut mappings = vec![v4("10.0.0.1"), v4("10.0.0.2")];
+        let mut mappings_v6 = vec![v6("2001:db8::1"), v6("2001:db8::2")];
+        let options = MigrationOptions {
+            offset: 1,
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let batch = utils::select_batch(&mut mappings, &mut mappings_v6, &options);
+        assert_eq!(
+            batch,
+            Some(MappingBatch {
+                offset: 1,
+                selected: 2,
+                total: 4,
+            })
+        );
+        assert_eq!(mappings, vec![v4("10.0.0.2")]);
+        assert_eq!(mappings_v6, vec![v6("2001:db8::1")]);
+        assert_eq!(batch.unwrap().remaining(), 1);
+
+        let options = MigrationOptions {
+            offset: 10,
+            ..Default::default()
+        };
+        let batch = utils::select_batch(&mut mappings, &mut mappings_v6, &options).unwrap();
+        assert_eq!((batch.offset, batch.selected), (2, 0));
+        assert!(mappings.is_empty() && mappings_v6.is_empty());
+    }
 }
@@ -0,0 +1,429 @@
+//! Addresses in ISC DHCP options written as names.
+//!
+//! ISC takes a hostname wherever an option lists servers (an NTP server of
+//! `ntp.example.com`), but the Kea fields and dnsmasq options they are
+//! written to take addresses only. A value naming an interface (its key or
+//! description) or a firewall host alias becomes that interface's or
+//! alias's addresses; other names are looked up with the system resolver
+//! when `resolve_hostnames` is set. Values still without an address are
+//! left out of the option and reported.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, ToSocketAddrs};
+use xmltree::Element;
+
+use crate::extract::{extract_interface_labels, iter_isc_options_v4, iter_isc_options_v6};
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::xml_helpers::{get_child_ci, get_children_ci};
+use crate::{IscDhcpOptionsV4, IscDhcpOptionsV6, MigrationOptions, UnresolvedOptionValue};
+
+/// Option values rewritten as addresses, and those left out.
+#[derive(Debug, Default)]
+pub(crate) struct OptionValueCheck {
+    /// Names replaced by their addresses
+    pub(crate) resolved: usize,
+    pub(crate) unresolved: Vec<UnresolvedOptionValue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    fn matches(self, ip: &IpAddr) -> bool {
+        matches!(
+            (self, ip),
+            (Family::V4, IpAddr::V4(_)) | (Family::V6, IpAddr::V6(_))
+        )
+    }
+
+    fn section(self) -> &'static str {
+        match self {
+            Family::V4 => "dhcpd",
+            Family::V6 => "dhcpdv6",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Family::V4 => "IPv4",
+            Family::V6 => "IPv6",
+        }
+    }
+}
+
+/// The ISC DHCPv4 and DHCPv6 options of `root` to migrate, checked with
+/// [`check_option_values`]; none unless `create_options` is set.
+pub(crate) fn checked_isc_options(
+    root: &Element,
+    options: &MigrationOptions,
+) -> (
+    Vec<IscDhcpOptionsV4>,
+    Vec<IscDhcpOptionsV6>,
+    OptionValueCheck,
+) {
+    if !options.create_options {
+        return (Vec::new(), Vec::new(), OptionValueCheck::default());
+    }
+    let mut options_v4: Vec<_> = iter_isc_options_v4(root).collect();
+    let mut options_v6: Vec<_> = iter_isc_options_v6(root).collect();
+    let check = check_option_values(root, &mut options_v4, &mut options_v6, options);
+    (options_v4, options_v6, check)
+}
+
+/// Replace names in the address-valued fields of `options_v4` and
+/// `options_v6` with their addresses, leaving out values that have none.
+pub(crate) fn check_option_values(
+    root: &Element,
+    options_v4: &mut [IscDhcpOptionsV4],
+    options_v6: &mut [IscDhcpOptionsV6],
+    options: &MigrationOptions,
+) -> OptionValueCheck {
+    let mut resolver = Resolver::new(root, options);
+    let mut check = OptionValueCheck::default();
+    for opt in options_v4 {
+        let mut field = |name, values: &mut Vec<String>| {
+            resolver.rewrite(&mut check, Family::V4, &opt.iface, name, values)
+        };
+        field("dnsserver", &mut opt.dns_servers);
+        field("ntpserver", &mut opt.ntp_servers);
+        field("winsserver", &mut opt.wins_servers);
+        if let Some(gateway) = opt.routers.take() {
+            let mut routers = vec![gateway];
+            field("gateway", &mut routers);
+            opt.routers = (!routers.is_empty()).then(|| routers.join(","));
+        }
+    }
+    for opt in options_v6 {
+        let mut field = |name, values: &mut Vec<String>| {
+            resolver.rewrite(&mut check, Family::V6, &opt.iface, name, values)
+        };
+        field("dnsserver", &mut opt.dns_servers);
+        field("ntpserver", &mut opt.ntp_servers);
+        field("sipserver", &mut opt.sip_servers);
+    }
+    check
+}
+
+struct Resolver {
+    resolve: bool,
+    verbose: bool,
+    /// Interface keys and descriptions, and firewall host alias names,
+    /// lowercase, to the addresses or names they stand for
+    aliases: HashMap<String, Vec<String>>,
+    lookups: HashMap<String, Result<Vec<IpAddr>, String>>,
+}
+
+impl Resolver {
+    fn new(root: &Element, options: &MigrationOptions) -> Self {
+        Resolver {
+            resolve: options.resolve_hostnames,
+            verbose: options.verbose,
+            aliases: aliases(root),
+            lookups: HashMap::new(),
+        }
+    }
+
+    fn rewrite(
+        &mut self,
+        check: &mut OptionValueCheck,
+        family: Family,
+        iface: &str,
+        field: &str,
+        values: &mut Vec<String>,
+    ) {
+        let mut addresses = Vec::new();
+        for value in std::mem::take(values) {
+            let value = value.trim();
+            match self.addresses(value, family) {
+                Ok(found) => {
+                    if found.len() != 1 || found[0] != value {
+                        check.resolved += 1;
+                        if self.verbose {
+                            term::action(
+                                "RESOLVE",
+                                format_args!(
+                                    "{}/{}/{} {} -> {}",
+                                    family.section(),
+                                    iface,
+                                    field,
+                                    value,
+                                    found.join(", ")
+                                ),
+                            );
+                        }
+                    }
+                    addresses.extend(found);
+                }
+                Err(reason) => {
+                    warn(
+                        WarningKind::OptionValueUnresolved,
+                        format_args!(
+                            "{}/{}/{} value {} {}; leaving it out.",
+                            family.section(),
+                            iface,
+                            field,
+                            value,
+                            reason
+                        ),
+                    );
+                    check.unresolved.push(UnresolvedOptionValue {
+                        section: family.section().to_string(),
+                        iface: iface.to_string(),
+                        field: field.to_string(),
+                        value: value.to_string(),
+                        reason,
+                    });
+                }
+            }
+        }
+        *values = addresses;
+    }
+
+    /// The `family` addresses `value` stands for, or why it has none.
+    fn addresses(&mut self, value: &str, family: Family) -> Result<Vec<String>, String> {
+        if let Ok(ip) = value.parse::<IpAddr>() {
+            return if family.matches(&ip) {
+                Ok(vec![value.to_string()])
+            } else {
+                Err(format!("is not an {} address", family.name()))
+            };
+        }
+        if let Some(targets) = self.aliases.get(&value.to_ascii_lowercase()).cloned() {
+            let mut found = Vec::new();
+            for target in targets {
+                match target.parse::<IpAddr>() {
+                    Ok(ip) if family.matches(&ip) => found.push(ip),
+                    Ok(_) => {}
+                    Err(_) if self.resolve && is_hostname(&target) => {
+                        found.extend(self.lookup(&target).unwrap_or_default())
+                    }
+                    Err(_) => {}
+                }
+            }
+            return match family_addresses(found, family) {
+                addresses if addresses.is_empty() => Err(format!(
+                    "names an interface or alias without an {} address",
+                    family.name()
+                )),
+                addresses => Ok(addresses),
+            };
+        }
+        if !is_hostname(value) {
+            return Err("is not an address or hostname".to_string());
+        }
+        if !self.resolve {
+            return Err("is a hostname (use --resolve-hostnames or enter an address)".to_string());
+        }
+        match self.lookup(value) {
+            Ok(found) => match family_addresses(found, family) {
+                addresses if addresses.is_empty() => {
+                    Err(format!("resolves to no {} address", family.name()))
+                }
+                addresses => Ok(addresses),
+            },
+            Err(e) => Err(format!("could not be resolved ({})", e)),
+        }
+    }
+
+    /// Addresses of `name` from the system resolver, looked up once.
+    fn lookup(&mut self, name: &str) -> Result<Vec<IpAddr>, String> {
+        self.lookups
+            .entry(name.to_ascii_lowercase())
+            .or_insert_with(|| {
+                (name, 0)
+                    .to_socket_addrs()
+                    .map(|addrs| addrs.map(|addr| addr.ip()).collect())
+                    .map_err(|e| e.to_string())
+            })
+            .clone()
+    }
+}
+
+/// The `family` addresses among `found`, each once, in order.
+fn family_addresses(found: Vec<IpAddr>, family: Family) -> Vec<String> {
+    let mut addresses: Vec<String> = Vec::new();
+    for ip in found.into_iter().filter(|ip| family.matches(ip)) {
+        let ip = ip.to_string();
+        if !addresses.contains(&ip) {
+            addresses.push(ip);
+        }
+    }
+    addresses
+}
+
+/// Whether `value` is a DNS name: dot-separated labels of letters, digits
+/// and hyphens.
+fn is_hostname(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 253
+        && value.trim_end_matches('.').split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Names an option value may use for addresses in the config: interfaces by
+/// key or description, then firewall host aliases.
+fn aliases(root: &Element) -> HashMap<String, Vec<String>> {
+    let mut aliases = HashMap::new();
+    let text = |el: &Element, name: &str| {
+        get_child_ci(el, name)
+            .and_then(|e| e.get_text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+
+    let labels = extract_interface_labels(root);
+    if let Some(interfaces) = get_child_ci(root, "interfaces") {
+        for iface in interfaces.children.iter().filter_map(|n| n.as_element()) {
+            let addresses: Vec<String> = ["ipaddr", "ipaddrv6"]
+                .iter()
+                .filter_map(|field| text(iface, field))
+                .filter(|addr| addr.parse::<IpAddr>().is_ok())
+                .collect();
+            if addresses.is_empty() {
+                continue;
+            }
+            if let Some(descr) = labels.get(&iface.name) {
+                aliases
+                    .entry(descr.to_ascii_lowercase())
+                    .or_insert_with(|| addresses.clone());
+            }
+            aliases.insert(iface.name.to_ascii_lowercase(), addresses);
+        }
+    }
+
+    let firewall_aliases = get_child_ci(root, "OPNsense")
+        .and_then(|el| get_child_ci(el, "Firewall"))
+        .and_then(|el| get_child_ci(el, "Alias"))
+        .and_then(|el| get_child_ci(el, "aliases"));
+    for alias in firewall_aliases
+        .into_iter()
+        .flat_map(|aliases| get_children_ci(aliases, "alias"))
+    {
+        let is_host = text(alias, "type").is_some_and(|t| t.eq_ignore_ascii_case("host"));
+        let (true, Some(name)) = (is_host, text(alias, "name")) else {
+            continue;
+        };
+        let content = text(alias, "content").unwrap_or_default();
+        aliases.entry(name.to_ascii_lowercase()).or_insert_with(|| {
+            content
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|entry| !entry.is_empty())
+                .map(str::to_string)
+                .collect()
+        });
+    }
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options_v4(ntp_servers: &[&str]) -> IscDhcpOptionsV4 {
+        IscDhcpOptionsV4 {
+            iface: "lan".to_string(),
+            dns_servers: Vec::new(),
+            routers: None,
+            routers_suppressed: false,
+            domain_name: None,
+            domain_search: None,
+            ntp_servers: ntp_servers.iter().map(|s| s.to_string()).collect(),
+            wins_servers: Vec::new(),
+        }
+    }
+
+    const CONFIG: &str = r#"<opnsense>
+        <interfaces>
+            <lan><ipaddr>192.168.1.1</ipaddr><subnet>24</subnet><descr>Office</descr></lan>
+        </interfaces>
+        <OPNsense><Firewall><Alias><aliases>
+            <alias><name>ntp_hosts</name><type>host</type><content>10.0.0.5
+fd00::5
+localhost</content></alias>
+            <alias><name>web</name><type>port</type><content>443</content></alias>
+        </aliases></Alias></Firewall></OPNsense>
+    </opnsense>"#;
+
+    #[test]
+    fn test_check_option_values() {
+        let root = Element::parse(CONFIG.as_bytes()).unwrap();
+        let mut v4 = vec![options_v4(&[
+            " 10.0.0.1 ",
+            "office",
+            "NTP_HOSTS",
+            "ntp.example.com",
+            "fd00::1",
+            "web",
+            "not a name",
+        ])];
+        let check = check_option_values(&root, &mut v4, &mut [], &MigrationOptions::default());
+        assert_eq!(
+            v4[0].ntp_servers,
+            vec!["10.0.0.1", "192.168.1.1", "10.0.0.5"]
+        );
+        assert_eq!(check.resolved, 2);
+        let unresolved: Vec<(&str, &str)> = check
+            .unresolved
+            .iter()
+            .map(|u| (u.value.as_str(), u.reason.as_str()))
+            .collect();
+        assert_eq!(
+            unresolved,
+            vec![
+                (
+                    "ntp.example.com",
+                    "is a hostname (use --resolve-hostnames or enter an address)"
+                ),
+                ("fd00::1", "is not an IPv4 address"),
+                (
+                    "web",
+                    "is a hostname (use --resolve-hostnames or enter an address)"
+                ),
+                ("not a name", "is not an address or hostname"),
+            ]
+        );
+        assert_eq!(check.unresolved[0].section, "dhcpd");
+        assert_eq!(check.unresolved[0].field, "ntpserver");
+    }
+
+    #[test]
+    fn test_check_option_values_resolves_hostnames() {
+        let root = Element::parse(CONFIG.as_bytes()).unwrap();
+        let options = MigrationOptions {
+            resolve_hostnames: true,
+            ..Default::default()
+        };
+        let mut v4 = vec![options_v4(&["localhost"])];
+        let mut v6 = vec![IscDhcpOptionsV6 {
+            iface: "lan".to_string(),
+            dns_servers: vec!["ntp_hosts".to_string()],
+            domain_search: None,
+            ntp_servers: Vec::new(),
+            sip_servers: Vec::new(),
+            bootfile_url: None,
+        }];
+        let check = check_option_values(&root, &mut v4, &mut v6, &options);
+        assert_eq!(v4[0].ntp_servers, vec!["127.0.0.1"]);
+        assert!(v6[0].dns_servers.starts_with(&["fd00::5".to_string()]));
+        assert_eq!(check.resolved, 2);
+        assert!(check.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_is_hostname() {
+        assert!(is_hostname("ntp.example.com"));
+        assert!(is_hostname("ntp1."));
+        assert!(!is_hostname("-bad.example"));
+        assert!(!is_hostname("a..b"));
+        assert!(!is_hostname("has space"));
+    }
+}
@@ -1,180 +1,24 @@
-use anyhow::{anyhow, Result};
-use xmltree::{Element, XMLNode};
+#[cfg(feature = "dnsmasq")]
+use xmltree::Element;
 
-use crate::extract::{extract_interface_cidrs, extract_interface_cidrs_v6};
+#[cfg(feature = "dnsmasq")]
 use crate::extract_dnsmasq::dnsmasq_option_key;
-use crate::{IscDhcpOptionsV4, IscDhcpOptionsV6};
+use crate::migrate::routes::{dnsmasq_routes, parse_classless_routes, CLASSLESS_ROUTE_CODES};
+use crate::term;
+#[cfg(feature = "dnsmasq")]
+use crate::DnsmasqOptionConflict;
+use crate::{IscDhcpOptionsV4, IscDhcpOptionsV6, IscNumberOption, MigrationOptions, OptionsMerge};
 
-/// Apply ISC DHCP options into Kea option_data, per-interface.
-pub(crate) fn apply_kea_options(
-    root: &mut Element,
-    options_v4: &[IscDhcpOptionsV4],
-    options_v6: &[IscDhcpOptionsV6],
-    force: bool,
-) -> Result<()> {
-    let iface_cidrs_v4 = extract_interface_cidrs(root)?;
-    let iface_cidrs_v6 = extract_interface_cidrs_v6(root)?;
-
-    let mut v4_by_cidr = std::collections::HashMap::new();
-    for opt in options_v4 {
-        if let Some(cidr) = iface_cidrs_v4.get(&opt.iface) {
-            v4_by_cidr.insert(cidr.clone(), opt.clone());
-        } else {
-            eprintln!(
-                "Warning: No interface CIDR found for DHCPv4 options (iface {}). Skipping.",
-                opt.iface
-            );
-        }
-    }
-
-    let mut v6_by_cidr = std::collections::HashMap::new();
-    for opt in options_v6 {
-        if let Some(cidr) = iface_cidrs_v6.get(&opt.iface) {
-            v6_by_cidr.insert(cidr.clone(), opt.clone());
-        } else {
-            eprintln!(
-                "Warning: No interface CIDR found for DHCPv6 options (iface {}). Skipping.",
-                opt.iface
-            );
-        }
-    }
-
-    // DHCPv4 options
-    if let Some(kea) = crate::xml_helpers::find_mut_descendant_ci(root, "Kea") {
-        if let Some(dhcp4) = crate::xml_helpers::find_mut_descendant_ci(kea, "dhcp4") {
-            if let Some(subnets) = crate::xml_helpers::get_mut_child_ci(dhcp4, "subnets") {
-                for subnet in subnets
-                    .children
-                    .iter_mut()
-                    .filter_map(|n| n.as_mut_element())
-                    .filter(|e| e.name.eq_ignore_ascii_case("subnet4"))
-                {
-                    let cidr = crate::xml_helpers::get_child_ci(subnet, "subnet")
-                        .and_then(|e| e.get_text())
-                        .map(|s| s.to_string())
-                        .unwrap_or_default();
-                    let Some(opt) = v4_by_cidr.get(&cidr) else {
-                        continue;
-                    };
-
-                    if crate::xml_helpers::get_mut_child_ci(subnet, "option_data").is_none() {
-                        subnet
-                            .children
-                            .push(XMLNode::Element(Element::new("option_data")));
-                    }
-
-                    if let Some(auto) =
-                        crate::xml_helpers::get_mut_child_ci(subnet, "option_data_autocollect")
-                    {
-                        auto.children.clear();
-                        auto.children.push(XMLNode::Text("0".to_string()));
-                    } else {
-                        let mut auto = Element::new("option_data_autocollect");
-                        auto.children.push(XMLNode::Text("0".to_string()));
-                        subnet.children.push(XMLNode::Element(auto));
-                    }
-
-                    let option_data = crate::xml_helpers::get_mut_child_ci(subnet, "option_data")
-                        .ok_or_else(|| anyhow!("Failed to access Kea option_data"))?;
-
-                    set_option_value(
-                        option_data,
-                        "domain_name_servers",
-                        join_list(&opt.dns_servers),
-                        force,
-                    );
-                    set_option_value(option_data, "routers", opt.routers.clone(), force);
-                    set_option_value(option_data, "domain_name", opt.domain_name.clone(), force);
-                    set_option_value(
-                        option_data,
-                        "domain_search",
-                        opt.domain_search.clone(),
-                        force,
-                    );
-                    set_option_value(
-                        option_data,
-                        "ntp_servers",
-                        join_list(&opt.ntp_servers),
-                        force,
-                    );
-                }
-            }
-        }
-    }
-
-    // DHCPv6 options
-    if let Some(kea) = crate::xml_helpers::find_mut_descendant_ci(root, "Kea") {
-        if let Some(dhcp6) = crate::xml_helpers::find_mut_descendant_ci(kea, "dhcp6") {
-            if let Some(subnets) = crate::xml_helpers::get_mut_child_ci(dhcp6, "subnets") {
-                for subnet in subnets
-                    .children
-                    .iter_mut()
-                    .filter_map(|n| n.as_mut_element())
-                    .filter(|e| e.name.eq_ignore_ascii_case("subnet6"))
-                {
-                    let cidr = crate::xml_helpers::get_child_ci(subnet, "subnet")
-                        .and_then(|e| e.get_text())
-                        .map(|s| s.to_string())
-                        .unwrap_or_default();
-                    let Some(opt) = v6_by_cidr.get(&cidr) else {
-                        continue;
-                    };
-
-                    if crate::xml_helpers::get_mut_child_ci(subnet, "option_data").is_none() {
-                        subnet
-                            .children
-                            .push(XMLNode::Element(Element::new("option_data")));
-                    }
-                    let option_data = crate::xml_helpers::get_mut_child_ci(subnet, "option_data")
-                        .ok_or_else(|| anyhow!("Failed to access Kea option_data"))?;
-
-                    set_option_value(
-                        option_data,
-                        "dns_servers",
-                        join_list(&opt.dns_servers),
-                        force,
-                    );
-                    set_option_value(
-                        option_data,
-                        "domain_search",
-                        opt.domain_search.clone(),
-                        force,
-                    );
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
-
-fn set_option_value(target: &mut Element, tag: &str, value: Option<String>, force: bool) {
-    let Some(val) = value.filter(|v| !v.is_empty()) else {
-        return;
-    };
-    let child = crate::xml_helpers::get_mut_child_ci(target, tag);
-    match child {
-        Some(elem) => {
-            let existing = elem.get_text().map(|v| v.to_string()).unwrap_or_default();
-            if !existing.is_empty() && !force {
-                eprintln!(
-                    "Warning: Kea option {} already set ({}). Skipping.",
-                    tag, existing
-                );
-                return;
-            }
-            elem.children.clear();
-            elem.children.push(XMLNode::Text(val));
-        }
-        None => {
-            let mut elem = Element::new(tag);
-            elem.children.push(XMLNode::Text(val));
-            target.children.push(XMLNode::Element(elem));
-        }
+/// Effective options merge policy; `--force-options` always overwrites.
+pub(crate) fn options_merge_policy(options: &MigrationOptions) -> OptionsMerge {
+    if options.force_options {
+        OptionsMerge::Overwrite
+    } else {
+        options.options_merge
     }
 }
 
-fn join_list(values: &[String]) -> Option<String> {
+pub(crate) fn join_list(values: &[String]) -> Option<String> {
     let filtered = dedupe_preserve_order(values.iter().filter(|v| !v.is_empty()));
     if filtered.is_empty() {
         None
@@ -203,9 +47,13 @@ pub(crate) struct DnsmasqOptionSpec {
     pub(crate) option: String,
     pub(crate) option6: String,
     pub(crate) value: String,
+    /// Name of the dnsmasq tag the option matches; empty for none
+    #[cfg_attr(not(feature = "dnsmasq"), allow(dead_code))]
+    pub(crate) tag: String,
 }
 
-fn domain_search_csv(value: &str) -> Option<String> {
+/// A domain search list split on `;`, `,` or whitespace, comma-joined.
+pub(crate) fn domain_search_csv(value: &str) -> Option<String> {
     let parts: Vec<String> = value
         .split(|c: char| c == ';' || c == ',' || c.is_whitespace())
         .filter(|s| !s.is_empty())
@@ -231,14 +79,21 @@ pub(crate) fn dnsmasq_option_specs_from_isc(
                 option: "6".to_string(),
                 option6: String::new(),
                 value,
+                tag: String::new(),
             });
         }
-        if let Some(value) = opt.routers.clone().filter(|v| !v.is_empty()) {
+        // An empty option 3 stops dnsmasq sending its own address
+        let routers = match &opt.routers {
+            _ if opt.routers_suppressed => Some(String::new()),
+            routers => routers.clone().filter(|v| !v.is_empty()),
+        };
+        if let Some(value) = routers {
             specs.push(DnsmasqOptionSpec {
                 iface: opt.iface.clone(),
                 option: "3".to_string(),
                 option6: String::new(),
                 value,
+                tag: String::new(),
             });
         }
         if let Some(value) = opt.domain_name.clone().filter(|v| !v.is_empty()) {
@@ -247,6 +102,7 @@ pub(crate) fn dnsmasq_option_specs_from_isc(
                 option: "15".to_string(),
                 option6: String::new(),
                 value,
+                tag: String::new(),
             });
         }
         if let Some(value) = opt.domain_search.as_deref().and_then(domain_search_csv) {
@@ -255,6 +111,7 @@ pub(crate) fn dnsmasq_option_specs_from_isc(
                 option: "119".to_string(),
                 option6: String::new(),
                 value,
+                tag: String::new(),
             });
         }
         if let Some(value) = join_list(&opt.ntp_servers) {
@@ -263,6 +120,24 @@ pub(crate) fn dnsmasq_option_specs_from_isc(
                 option: "42".to_string(),
                 option6: String::new(),
                 value,
+                tag: String::new(),
+            });
+        }
+        if let Some(value) = join_list(&opt.wins_servers) {
+            specs.push(DnsmasqOptionSpec {
+                iface: opt.iface.clone(),
+                option: "44".to_string(),
+                option6: String::new(),
+                value,
+                tag: String::new(),
+            });
+            // ISC pairs WINS servers with node type 8 (hybrid)
+            specs.push(DnsmasqOptionSpec {
+                iface: opt.iface.clone(),
+                option: "46".to_string(),
+                option6: String::new(),
+                value: "8".to_string(),
+                tag: String::new(),
             });
         }
     }
@@ -274,6 +149,7 @@ pub(crate) fn dnsmasq_option_specs_from_isc(
                 option: String::new(),
                 option6: "23".to_string(),
                 value,
+                tag: String::new(),
             });
         }
         if let Some(value) = opt.domain_search.as_deref().and_then(domain_search_csv) {
@@ -282,6 +158,34 @@ pub(crate) fn dnsmasq_option_specs_from_isc(
                 option: String::new(),
                 option6: "24".to_string(),
                 value,
+                tag: String::new(),
+            });
+        }
+        if let Some(value) = join_list(&opt.sip_servers) {
+            specs.push(DnsmasqOptionSpec {
+                iface: opt.iface.clone(),
+                option: String::new(),
+                option6: "22".to_string(),
+                value,
+                tag: String::new(),
+            });
+        }
+        if let Some(value) = join_list(&opt.ntp_servers) {
+            specs.push(DnsmasqOptionSpec {
+                iface: opt.iface.clone(),
+                option: String::new(),
+                option6: "31".to_string(),
+                value,
+                tag: String::new(),
+            });
+        }
+        if let Some(value) = opt.bootfile_url.clone() {
+            specs.push(DnsmasqOptionSpec {
+                iface: opt.iface.clone(),
+                option: String::new(),
+                option6: "59".to_string(),
+                value,
+                tag: String::new(),
             });
         }
     }
@@ -289,38 +193,122 @@ pub(crate) fn dnsmasq_option_specs_from_isc(
     specs
 }
 
-pub(crate) fn dnsmasq_option_key_from_elem(elem: &Element) -> Option<String> {
-    if !elem.name.eq_ignore_ascii_case("dhcp_options") {
-        return None;
+/// Tag dnsmasq sets on every DHCPv6 request.
+#[cfg(feature = "dnsmasq")]
+pub(crate) const DHCPV6_TAG: &str = "dhcpv6";
+
+/// DHCPv4 and DHCPv6 option codes carrying the same setting.
+#[cfg(feature = "dnsmasq")]
+const PAIRED_OPTIONS: &[(&str, &str, &str)] = &[("119", "24", "domain search list")];
+
+/// Settle interfaces whose DHCPv4 and DHCPv6 specs carry different values
+/// for the same setting, as `options.dnsmasq_option_conflict` says: one
+/// value replaces the other, or the DHCPv6 spec is tagged with
+/// [`DHCPV6_TAG`]. Returns how many conflicts were found.
+#[cfg(feature = "dnsmasq")]
+pub(crate) fn reconcile_dnsmasq_option_specs(
+    specs: &mut [DnsmasqOptionSpec],
+    options: &MigrationOptions,
+) -> usize {
+    let mut conflicts = 0;
+    for &(option, option6, setting) in PAIRED_OPTIONS {
+        let v4: Vec<(String, String)> = specs
+            .iter()
+            .filter(|spec| spec.option == option)
+            .map(|spec| (spec.iface.clone(), spec.value.clone()))
+            .collect();
+        for (iface, value) in v4 {
+            let Some(index) = specs
+                .iter()
+                .position(|spec| spec.iface == iface && spec.option6 == option6)
+            else {
+                continue;
+            };
+            if specs[index].value == value {
+                continue;
+            }
+            conflicts += 1;
+            let value6 = specs[index].value.clone();
+            match options.dnsmasq_option_conflict {
+                DnsmasqOptionConflict::PreferV4 => {
+                    term::warning(format_args!(
+                        "DHCPv4 and DHCPv6 {} differ on {} ({} vs {}); using the DHCPv4 \
+                         one for both.",
+                        setting, iface, value, value6
+                    ));
+                    specs[index].value = value;
+                }
+                DnsmasqOptionConflict::PreferV6 => {
+                    term::warning(format_args!(
+                        "DHCPv4 and DHCPv6 {} differ on {} ({} vs {}); using the DHCPv6 \
+                         one for both.",
+                        setting, iface, value, value6
+                    ));
+                    for spec in specs
+                        .iter_mut()
+                        .filter(|spec| spec.iface == iface && spec.option == option)
+                    {
+                        spec.value = value6.clone();
+                    }
+                }
+                DnsmasqOptionConflict::KeepBothTagged => {
+                    if options.verbose {
+                        term::action(
+                            "TAG",
+                            format_args!(
+                                "DHCPv6 {} on {} ({}) [tag {}]",
+                                setting, iface, value6, DHCPV6_TAG
+                            ),
+                        );
+                    }
+                    specs[index].tag = DHCPV6_TAG.to_string();
+                }
+            }
+        }
     }
-    let opt_type = crate::xml_helpers::get_child_ci(elem, "type")
-        .and_then(|e| e.get_text())
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-    if !opt_type.eq_ignore_ascii_case("set") {
+    conflicts
+}
+
+/// dnsmasq options for the classless static routes (121, and 249 for
+/// Windows clients) among custom numbered ISC options, whatever encoding
+/// ISC had them in. Values that are not a route list are left out with a
+/// warning.
+pub(crate) fn classless_route_specs(number_options: &[IscNumberOption]) -> Vec<DnsmasqOptionSpec> {
+    let mut specs = Vec::new();
+    for opt in number_options
+        .iter()
+        .filter(|o| CLASSLESS_ROUTE_CODES.contains(&o.number))
+    {
+        let Some(routes) = parse_classless_routes(&opt.value) else {
+            term::warning(format_args!(
+                "Option {} on {} is not a classless static route list ({}). Skipping.",
+                opt.number, opt.iface, opt.value
+            ));
+            continue;
+        };
+        specs.push(DnsmasqOptionSpec {
+            iface: opt.iface.clone(),
+            option: opt.number.to_string(),
+            option6: String::new(),
+            value: dnsmasq_routes(&routes),
+            tag: String::new(),
+        });
+    }
+    specs
+}
+
+#[cfg(feature = "dnsmasq")]
+pub(crate) fn dnsmasq_option_key_from_elem(elem: &Element) -> Option<String> {
+    let opt = crate::model::DnsmasqOptionNode::from_element(elem)?;
+    if !opt.option_type.eq_ignore_ascii_case("set") {
         return None;
     }
-    let option = crate::xml_helpers::get_child_ci(elem, "option")
-        .and_then(|e| e.get_text())
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-    let option6 = crate::xml_helpers::get_child_ci(elem, "option6")
-        .and_then(|e| e.get_text())
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-    let iface = crate::xml_helpers::get_child_ci(elem, "interface")
-        .and_then(|e| e.get_text())
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-    let tag = crate::xml_helpers::get_child_ci(elem, "tag")
-        .and_then(|e| e.get_text())
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-    let set_tag = crate::xml_helpers::get_child_ci(elem, "set_tag")
-        .and_then(|e| e.get_text())
-        .map(|s| s.to_string())
-        .unwrap_or_default();
     Some(dnsmasq_option_key(
-        &opt_type, &option, &option6, &iface, &tag, &set_tag,
+        &opt.option_type,
+        &opt.option,
+        &opt.option6,
+        &opt.interface,
+        &opt.tag,
+        &opt.set_tag,
     ))
 }
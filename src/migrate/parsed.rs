@@ -0,0 +1,173 @@
+//! A config parsed once and shared between runs.
+//!
+//! The `*_config` entry points each parse their reader. A caller that scans,
+//! verifies and converts one config parses it once into a [`ParsedConfig`]
+//! instead: it is validated as input when parsed, keeps the comments around
+//! its root, and caches what every run would otherwise extract again.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use xmltree::Element;
+
+use super::audit::audit_leases;
+use super::utils::{isc_sections, IscSections};
+use super::{
+    convert_document, extract_ordered_mappings, scan_with_backends, validate_input, IscMappingSet,
+};
+use crate::backend::Backend;
+use crate::extract::{
+    extract_interface_labels, iter_isc_mappings, iter_isc_mappings_v6, iter_isc_ranges,
+    iter_isc_ranges_v6,
+};
+use crate::plan::build_plan;
+use crate::registry::BackendRegistry;
+#[cfg(feature = "backend-api")]
+use crate::registry::MigrationBackend;
+use crate::xml_helpers::{count_elements, XmlDocument};
+use crate::{Clock, IscLease, LeaseAudit, MigrationOptions, MigrationPlan, MigrationStats};
+
+/// A validated config, ready to be scanned, planned or converted any number
+/// of times. Converting works on a copy, so the parsed document is never
+/// changed.
+#[derive(Clone)]
+pub struct ParsedConfig {
+    pub(super) doc: XmlDocument,
+    pub(super) parse: Duration,
+    pub(super) input_elements: usize,
+    interface_labels: HashMap<String, String>,
+    /// ISC DHCP sections of a merge source, read in place of this config's.
+    pub(super) isc_source: Option<IscSections>,
+}
+
+impl ParsedConfig {
+    /// Parse `reader` and validate it as an OPNsense config.
+    pub fn parse<R: Read>(reader: R) -> Result<Self> {
+        let started = Instant::now();
+        let doc = XmlDocument::parse(reader).context("Failed to parse XML")?;
+        Self::from_document(doc, started)
+    }
+
+    /// Validate a document parsed since `started`.
+    pub(super) fn from_document(doc: XmlDocument, started: Instant) -> Result<Self> {
+        validate_input(&doc.root)?;
+        Ok(ParsedConfig {
+            input_elements: count_elements(&doc.root),
+            interface_labels: extract_interface_labels(&doc.root),
+            parse: started.elapsed(),
+            doc,
+            isc_source: None,
+        })
+    }
+
+    /// The root `<opnsense>` element.
+    pub fn root(&self) -> &Element {
+        &self.doc.root
+    }
+
+    /// Interface names mapped to their descriptions.
+    pub fn interface_labels(&self) -> &HashMap<String, String> {
+        &self.interface_labels
+    }
+
+    /// Time spent parsing and validating the config.
+    pub fn parse_duration(&self) -> Duration {
+        self.parse
+    }
+
+    /// See [`scan_counts`](crate::scan_counts).
+    pub fn scan_counts(&self, backend: &Backend) -> Result<MigrationStats> {
+        let root = self.root();
+        let registry = BackendRegistry::default();
+        let (mut target_subnets_found, mut target_subnets_v6_found) = (0, 0);
+        for (_, selected) in registry.resolve(backend)? {
+            let counts = selected.stats(root)?;
+            target_subnets_found += counts.target_subnets_found;
+            target_subnets_v6_found += counts.target_subnets_v6_found;
+        }
+
+        Ok(MigrationStats {
+            isc_mappings_found: iter_isc_mappings(root).count(),
+            isc_mappings_v6_found: iter_isc_mappings_v6(root).count(),
+            isc_ranges_found: iter_isc_ranges(root).count(),
+            isc_ranges_v6_found: iter_isc_ranges_v6(root).count(),
+            target_subnets_found,
+            target_subnets_v6_found,
+            ..Default::default()
+        })
+    }
+
+    /// See [`scan_config`](crate::scan_config).
+    pub fn scan(&self, options: &MigrationOptions) -> Result<MigrationStats> {
+        let registry = BackendRegistry::default();
+        scan_with_backends(self, options, &registry.resolve(&options.backend)?)
+    }
+
+    /// See [`audit_config`](crate::audit_config).
+    pub fn audit(
+        &self,
+        backend: &Backend,
+        leases: &[IscLease],
+        clock: &Clock,
+    ) -> Result<LeaseAudit> {
+        BackendRegistry::default().resolve(backend)?;
+        Ok(audit_leases(self.root(), backend, leases, clock.now()))
+    }
+
+    /// See [`plan_config`](crate::plan_config).
+    pub fn plan(&self, options: &MigrationOptions) -> Result<MigrationPlan> {
+        let IscMappingSet { v4, v6, .. } = extract_ordered_mappings(self.root(), options)?;
+        Ok(build_plan(self.root(), &options.backend, &v4, &v6))
+    }
+
+    /// See [`convert_config`](crate::convert_config).
+    pub fn convert<W: Write>(
+        &self,
+        writer: W,
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        let registry = BackendRegistry::default();
+        let backends = registry.resolve(&options.backend)?;
+        convert_document(self.clone(), writer, options, &backends)
+    }
+
+    /// See [`merge_config`](crate::merge_config); this config is the source.
+    pub fn merge<T: Read, W: Write>(
+        &self,
+        target: T,
+        writer: W,
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        let started = Instant::now();
+        let doc = XmlDocument::parse(target).context("Failed to parse merge target XML")?;
+        let registry = BackendRegistry::default();
+        let backends = registry.resolve(&options.backend)?;
+        let mut merged = ParsedConfig::from_document(doc, started)?;
+        merged.parse += self.parse;
+        merged.isc_source = Some(isc_sections(self.root()));
+        convert_document(merged, writer, options, &backends)
+    }
+
+    /// Scan with `backend` in place of `options.backend`.
+    #[cfg(feature = "backend-api")]
+    pub fn scan_with_backend(
+        &self,
+        options: &MigrationOptions,
+        backend: &dyn MigrationBackend,
+    ) -> Result<MigrationStats> {
+        scan_with_backends(self, options, &[(options.backend.clone(), backend)])
+    }
+
+    /// Convert with `backend` in place of `options.backend`.
+    #[cfg(feature = "backend-api")]
+    pub fn convert_with_backend<W: Write>(
+        &self,
+        writer: W,
+        options: &MigrationOptions,
+        backend: &dyn MigrationBackend,
+    ) -> Result<MigrationStats> {
+        let backends = [(options.backend.clone(), backend)];
+        convert_document(self.clone(), writer, options, &backends)
+    }
+}
@@ -0,0 +1,134 @@
+//! Static mappings kept as placeholders.
+//!
+//! OPNsense lets a static mapping leave its IP address empty, which some
+//! setups use to document a device ahead of time. With nothing to reserve
+//! they are only listed, unless `migrate_placeholders` is set: then each
+//! becomes a Kea reservation with the MAC and hostname but no
+//! `ip_address`, in the subnet serving its interface.
+
+#[cfg(feature = "kea")]
+use anyhow::Result;
+#[cfg(feature = "kea")]
+use std::collections::{HashMap, HashSet};
+use xmltree::Element;
+#[cfg(feature = "kea")]
+use xmltree::XMLNode;
+
+use crate::extract::iter_isc_placeholder_mappings;
+#[cfg(feature = "kea")]
+use crate::extract::{extract_interface_cidrs, extract_kea_subnets};
+#[cfg(feature = "kea")]
+use crate::migrate::existing::ExistingState;
+#[cfg(feature = "kea")]
+use crate::migrate::hwaddr::normalize_hwaddr;
+#[cfg(feature = "kea")]
+use crate::migrate_v4::{create_reservation, get_reservations_node};
+#[cfg(feature = "kea")]
+use crate::parse;
+#[cfg(feature = "kea")]
+use crate::subnet::find_subnet_for_ip;
+#[cfg(feature = "kea")]
+use crate::warnings::{warn, WarningKind};
+use crate::IscStaticMap;
+#[cfg(feature = "kea")]
+use crate::Subnet;
+
+/// Placeholder mappings of `root`, in document order.
+pub(crate) fn placeholder_mappings(root: &Element) -> Vec<IscStaticMap> {
+    iter_isc_placeholder_mappings(root).collect()
+}
+
+/// Placeholders that would become Kea reservations, each with its MAC in
+/// Kea's form and the `uuid` of its subnet. Placeholders whose MAC is
+/// already reserved or among the `mappings` of this run, or whose
+/// interface no Kea subnet serves, are skipped with a warning.
+#[cfg(feature = "kea")]
+pub(crate) fn plan_kea_placeholders(
+    root: &Element,
+    placeholders: &[IscStaticMap],
+    mappings: &[IscStaticMap],
+) -> Result<Vec<(IscStaticMap, String)>> {
+    let subnets = extract_kea_subnets(root)?;
+    let iface_cidrs = extract_interface_cidrs(root)?;
+    let mut reserved: HashSet<String> = ExistingState::kea(root).macs;
+    reserved.extend(mappings.iter().map(|m| normalize_hwaddr(&m.mac)));
+
+    let mut planned = Vec::new();
+    for placeholder in placeholders {
+        if parse::hwaddr(&placeholder.mac).is_err() {
+            warn(
+                WarningKind::MappingDropped,
+                format_args!(
+                    "placeholder {} on {} is not a hardware address; skipping.",
+                    placeholder.mac, placeholder.iface
+                ),
+            );
+            continue;
+        }
+        let mac = normalize_hwaddr(&placeholder.mac);
+        if !reserved.insert(mac.clone()) {
+            warn(
+                WarningKind::MappingDropped,
+                format_args!(
+                    "placeholder {} on {} is already reserved; skipping.",
+                    placeholder.mac, placeholder.iface
+                ),
+            );
+            continue;
+        }
+        let Some(subnet) = iface_subnet(&placeholder.iface, &subnets, &iface_cidrs) else {
+            warn(
+                WarningKind::MappingDropped,
+                format_args!(
+                    "no Kea subnet serves {} for placeholder {}; skipping.",
+                    placeholder.iface, placeholder.mac
+                ),
+            );
+            continue;
+        };
+        planned.push((
+            IscStaticMap {
+                mac,
+                ..placeholder.clone()
+            },
+            subnet,
+        ));
+    }
+    Ok(planned)
+}
+
+/// Add the planned placeholders as Kea reservations without an address.
+#[cfg(feature = "kea")]
+pub(crate) fn apply_kea_placeholders(
+    root: &mut Element,
+    planned: &[(IscStaticMap, String)],
+) -> Result<usize> {
+    if planned.is_empty() {
+        return Ok(0);
+    }
+    let reservations = get_reservations_node(root)?;
+    for (placeholder, subnet) in planned {
+        reservations.children.push(XMLNode::Element(
+            create_reservation(placeholder, subnet).to_element(),
+        ));
+    }
+    Ok(planned.len())
+}
+
+/// The Kea subnet bound to `iface`, else the one holding its address.
+#[cfg(feature = "kea")]
+fn iface_subnet(
+    iface: &str,
+    subnets: &[Subnet],
+    iface_cidrs: &HashMap<String, String>,
+) -> Option<String> {
+    if let Some(subnet) = subnets.iter().find(|s| {
+        s.iface
+            .as_deref()
+            .is_some_and(|bound| bound.eq_ignore_ascii_case(iface))
+    }) {
+        return Some(subnet.uuid.clone());
+    }
+    let (network, _) = iface_cidrs.get(iface)?.split_once('/')?;
+    find_subnet_for_ip(network, subnets).ok()
+}
@@ -0,0 +1,188 @@
+//! Classless static routes (DHCPv4 option 121, and 249 for older Windows
+//! clients) set as ISC custom numbered options.
+//!
+//! ISC only takes these as custom options, so their values come in several
+//! shapes: the RFC 3442 wire encoding as `:`-separated hex (type `string`)
+//! or comma-separated decimal octets, or routes written out as text. The
+//! targets want them re-encoded: Kea as `prefix - router` pairs, dnsmasq as
+//! a flat `prefix,router` list, and Kea option 249 (which has no standard
+//! definition) as binary.
+
+use ipnet::Ipv4Net;
+use std::net::Ipv4Addr;
+
+use crate::parse;
+
+/// Option codes holding classless static routes.
+pub(crate) const CLASSLESS_ROUTE_CODES: [u8; 2] = [121, 249];
+
+/// One route: traffic for `destination` goes via `router`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ClasslessRoute {
+    pub(crate) destination: Ipv4Net,
+    pub(crate) router: Ipv4Addr,
+}
+
+/// Routes in `value`, in any of the accepted shapes; `None` when it is not
+/// a route list.
+pub(crate) fn parse_classless_routes(value: &str) -> Option<Vec<ClasslessRoute>> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    if value.contains(':') {
+        return decode_wire(&parse::hwaddr(value).ok()?);
+    }
+    let tokens: Vec<&str> = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty() && *t != "-")
+        .collect();
+    if tokens.is_empty() {
+        return None;
+    }
+    if tokens.iter().all(|t| !t.contains('.')) {
+        let octets: Option<Vec<u8>> = tokens.iter().map(|t| t.parse().ok()).collect();
+        return decode_wire(&octets?);
+    }
+    if tokens.len() % 2 != 0 {
+        return None;
+    }
+    tokens
+        .chunks(2)
+        .map(|pair| {
+            Some(ClasslessRoute {
+                destination: pair[0].parse::<Ipv4Net>().ok()?.trunc(),
+                router: pair[1].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// RFC 3442: each route is the prefix length, the significant octets of
+/// the destination, then the router.
+fn decode_wire(octets: &[u8]) -> Option<Vec<ClasslessRoute>> {
+    let mut routes = Vec::new();
+    let mut rest = octets;
+    while let Some((&len, tail)) = rest.split_first() {
+        if len > 32 {
+            return None;
+        }
+        let significant = usize::from(len).div_ceil(8);
+        if tail.len() < significant + 4 {
+            return None;
+        }
+        let mut destination = [0u8; 4];
+        destination[..significant].copy_from_slice(&tail[..significant]);
+        let router: [u8; 4] = tail[significant..significant + 4].try_into().ok()?;
+        routes.push(ClasslessRoute {
+            destination: Ipv4Net::new(destination.into(), len).ok()?.trunc(),
+            router: router.into(),
+        });
+        rest = &tail[significant + 4..];
+    }
+    (!routes.is_empty()).then_some(routes)
+}
+
+/// The RFC 3442 wire encoding of `routes`.
+pub(crate) fn encode_wire(routes: &[ClasslessRoute]) -> Vec<u8> {
+    let mut octets = Vec::new();
+    for route in routes {
+        let len = route.destination.prefix_len();
+        octets.push(len);
+        let significant = usize::from(len).div_ceil(8);
+        octets.extend_from_slice(&route.destination.addr().octets()[..significant]);
+        octets.extend_from_slice(&route.router.octets());
+    }
+    octets
+}
+
+/// Kea `classless-static-route` data: `10.0.0.0/8 - 192.168.1.1, ...`.
+pub(crate) fn kea_routes(routes: &[ClasslessRoute]) -> String {
+    let routes: Vec<String> = routes
+        .iter()
+        .map(|r| format!("{} - {}", r.destination, r.router))
+        .collect();
+    routes.join(", ")
+}
+
+/// dnsmasq option value: `10.0.0.0/8,192.168.1.1,...`.
+pub(crate) fn dnsmasq_routes(routes: &[ClasslessRoute]) -> String {
+    let routes: Vec<String> = routes
+        .iter()
+        .map(|r| format!("{},{}", r.destination, r.router))
+        .collect();
+    routes.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, router: &str) -> ClasslessRoute {
+        ClasslessRoute {
+            destination: destination.parse().unwrap(),
+            router: router.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_classless_routes() {
+        let expected = vec![
+            route("10.0.0.0/8", "192.168.1.1"),
+            route("0.0.0.0/0", "192.168.1.254"),
+            route("172.16.5.0/24", "192.168.1.2"),
+        ];
+        let shapes = [
+            "08:0a:c0:a8:01:01:00:c0:a8:01:fe:18:ac:10:05:c0:a8:01:02",
+            "\"8:a:c0:a8:1:1:0:c0:a8:1:fe:18:ac:10:5:c0:a8:1:2\"",
+            "8, 10, 192, 168, 1, 1, 0, 192, 168, 1, 254, 24, 172, 16, 5, 192, 168, 1, 2",
+            "10.0.0.0/8 - 192.168.1.1, 0.0.0.0/0 - 192.168.1.254, 172.16.5.0/24 - 192.168.1.2",
+            "10.0.0.0/8,192.168.1.1,0.0.0.0/0,192.168.1.254,172.16.5.0/24,192.168.1.2",
+        ];
+        for shape in shapes {
+            assert_eq!(
+                parse_classless_routes(shape).as_ref(),
+                Some(&expected),
+                "{}",
+                shape
+            );
+        }
+        // Host bits past the prefix are dropped
+        assert_eq!(
+            parse_classless_routes("10.1.2.3/16 192.168.1.1"),
+            Some(vec![route("10.1.0.0/16", "192.168.1.1")])
+        );
+        for bad in [
+            "",
+            "08:0a:c0:a8",
+            "33:0a:00:00:00:00:c0:a8:01:01",
+            "10.0.0.0/8",
+            "text",
+        ] {
+            assert_eq!(parse_classless_routes(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_encode_classless_routes() {
+        let routes = [
+            route("10.0.0.0/8", "192.168.1.1"),
+            route("172.16.5.128/25", "192.168.1.2"),
+        ];
+        let wire = encode_wire(&routes);
+        assert_eq!(
+            wire,
+            [8, 10, 192, 168, 1, 1, 25, 172, 16, 5, 128, 192, 168, 1, 2]
+        );
+        assert_eq!(decode_wire(&wire), Some(routes.to_vec()));
+        assert_eq!(
+            kea_routes(&routes),
+            "10.0.0.0/8 - 192.168.1.1, 172.16.5.128/25 - 192.168.1.2"
+        );
+        assert_eq!(
+            dnsmasq_routes(&routes),
+            "10.0.0.0/8,192.168.1.1,172.16.5.128/25,192.168.1.2"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! `validate_schema`: check what a migration wrote against the OPNsense
+//! models.
+//!
+//! The bundled schema (`opnsense.schema`) lists, for each element of the
+//! Kea, dnsmasq and Unbound models the migration writes into, the child
+//! elements OPNsense defines. Created elements are checked in full, and
+//! elsewhere only children that were not in the input, so fields an
+//! existing config carries from a newer OPNsense are left alone. A
+//! misspelled field is reported at convert time instead of breaking the GUI
+//! after import.
+
+use std::collections::{HashMap, HashSet};
+use xmltree::Element;
+
+use super::markers::element_uuids;
+use crate::xml_helpers::find_descendant_ci;
+use crate::{MigrationError, SchemaViolation};
+
+const SCHEMA: &str = include_str!("opnsense.schema");
+/// Sections the schema covers.
+const SECTIONS: &[&str] = &["Kea", "dnsmasq", "unboundplus"];
+
+/// The element paths and `uuid`s of the input, before migrating.
+pub(crate) struct SchemaSnapshot {
+    paths: HashSet<String>,
+    uuids: HashSet<String>,
+}
+
+impl SchemaSnapshot {
+    pub(crate) fn take(root: &Element) -> Self {
+        let mut paths = HashSet::new();
+        for (section, el) in sections(root) {
+            collect_paths(el, &section.to_lowercase(), &mut paths);
+        }
+        SchemaSnapshot {
+            paths,
+            uuids: element_uuids(root),
+        }
+    }
+}
+
+/// Fail when `root` holds elements written since `before` that the schema
+/// does not allow.
+pub(crate) fn check_schema(root: &Element, before: &SchemaSnapshot) -> Result<(), MigrationError> {
+    let rules = parse_schema(SCHEMA);
+    let mut violations = Vec::new();
+    for (section, el) in sections(root) {
+        check_element(el, section, false, before, &rules, &mut violations);
+    }
+    violations.sort_by_key(SchemaViolation::to_string);
+    violations.dedup();
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(MigrationError::SchemaViolations { violations })
+}
+
+/// Child names the schema lists for the element at `path`, in the model's
+/// order; empty for paths without a rule.
+#[cfg(feature = "dnsmasq")]
+pub(crate) fn child_order(path: &str) -> Vec<String> {
+    parse_schema(SCHEMA)
+        .remove(&path.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Allowed child names by lowercase element path.
+fn parse_schema(schema: &str) -> HashMap<String, Vec<String>> {
+    schema
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(path, children)| {
+            let children = children.split_whitespace().map(str::to_lowercase);
+            (path.trim().to_lowercase(), children.collect())
+        })
+        .collect()
+}
+
+fn sections(root: &Element) -> impl Iterator<Item = (&'static str, &Element)> {
+    SECTIONS
+        .iter()
+        .filter_map(|name| find_descendant_ci(root, name).map(|el| (*name, el)))
+}
+
+fn collect_paths(el: &Element, path: &str, paths: &mut HashSet<String>) {
+    for child in el.children.iter().filter_map(|n| n.as_element()) {
+        let child_path = format!("{}/{}", path, child.name.to_lowercase());
+        collect_paths(child, &child_path, paths);
+        paths.insert(child_path);
+    }
+}
+
+fn check_element(
+    el: &Element,
+    path: &str,
+    created: bool,
+    before: &SchemaSnapshot,
+    rules: &HashMap<String, Vec<String>>,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    let key = path.to_lowercase();
+    for child in el.children.iter().filter_map(|n| n.as_element()) {
+        let name = child.name.to_lowercase();
+        let child_path = format!("{}/{}", path, child.name);
+        let created = created
+            || child
+                .attributes
+                .get("uuid")
+                .is_some_and(|uuid| !before.uuids.contains(uuid));
+        if created || !before.paths.contains(&format!("{}/{}", key, name)) {
+            match rules.get(&key) {
+                Some(allowed) if !allowed.contains(&name) => {
+                    violations.push(SchemaViolation::UnknownField {
+                        path: path.to_string(),
+                        element: child.name.clone(),
+                    });
+                }
+                None if is_leaf(&key, rules) => {
+                    violations.push(SchemaViolation::ChildOfLeaf {
+                        path: path.to_string(),
+                        element: child.name.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        check_element(child, &child_path, created, before, rules, violations);
+    }
+}
+
+/// Whether the schema lists `path` as a child without a rule of its own.
+fn is_leaf(path: &str, rules: &HashMap<String, Vec<String>>) -> bool {
+    path.rsplit_once('/').is_some_and(|(parent, name)| {
+        rules
+            .get(parent)
+            .is_some_and(|allowed| allowed.iter().any(|a| a == name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn parse(xml: &str) -> Element {
+        Element::parse(Cursor::new(xml)).unwrap()
+    }
+
+    const BEFORE: &str = r#"<opnsense>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="s1">
+          <subnet>192.168.1.0/24</subnet>
+          <option_data><routers/><future_field/></option_data>
+        </subnet4>
+      </subnets>
+    </dhcp4>
+  </Kea>
+</opnsense>"#;
+
+    #[test]
+    fn test_check_schema() {
+        let before = SchemaSnapshot::take(&parse(BEFORE));
+        // Unchanged input passes, even with a field the schema lacks
+        assert!(check_schema(&parse(BEFORE), &before).is_ok());
+
+        let after = BEFORE
+            .replace(
+                "<routers/>",
+                "<routers/><domain_name_servers>10.0.0.1</domain_name_servers>",
+            )
+            .replace(
+                "</subnets>",
+                r#"</subnets>
+      <reservations>
+        <reservation uuid="r1">
+          <subnet>s1</subnet>
+          <ip_address>192.168.1.10</ip_address>
+          <hw_adress>00:11:22:33:44:55</hw_adress>
+          <hostname><name>x</name></hostname>
+        </reservation>
+      </reservations>"#,
+            );
+        let err = check_schema(&parse(&after), &before).unwrap_err();
+        let MigrationError::SchemaViolations { violations } = &err else {
+            panic!("unexpected error: {}", err);
+        };
+        assert_eq!(
+            violations,
+            &[
+                SchemaViolation::ChildOfLeaf {
+                    path: "Kea/dhcp4/reservations/reservation/hostname".to_string(),
+                    element: "name".to_string(),
+                },
+                SchemaViolation::UnknownField {
+                    path: "Kea/dhcp4/reservations/reservation".to_string(),
+                    element: "hw_adress".to_string(),
+                },
+            ]
+        );
+        assert!(err
+            .to_string()
+            .contains("  Kea/dhcp4/reservations/reservation: <hw_adress> is not a field"));
+
+        // A DHCPv6 field name under a DHCPv4 subnet
+        let after = BEFORE.replace("<routers/>", "<routers/><dns_servers/>");
+        let err = check_schema(&parse(&after), &before).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("option_data: <dns_servers> is not a field"));
+    }
+}
@@ -5,6 +5,9 @@ use xmltree::{Element, XMLNode};
 
 use std::collections::HashSet;
 
+#[cfg(feature = "kea")]
+use crate::FirewallRulesStatus;
+
 /// Disable ISC DHCP on interfaces that are enabled in the current config.
 ///
 /// Removes `<dhcpd><{iface}><enable>` for each interface.
@@ -26,18 +29,25 @@ fn isc_enabled_ifaces(root: &Element, tag: &str) -> Vec<String> {
     };
 
     for iface_node in dhcp.children.iter().filter_map(|c| c.as_element()) {
-        let iface_name = iface_node.name.clone();
-        let enabled = match crate::xml_helpers::get_child_ci(iface_node, "enable")
-            .and_then(|e| e.get_text())
-        {
-            Some(v) => {
-                let val = v.trim();
-                !(val.is_empty() || val == "0")
+        if iface_enabled(iface_node) {
+            ifaces.insert(iface_node.name.clone());
+        }
+    }
+
+    let mut result: Vec<_> = ifaces.into_iter().collect();
+    result.sort();
+    result
+}
+
+/// Interfaces with `<enable>` set under legacy
+/// `<installedpackages><{tag}><config>` wrappers (`--legacy-paths`).
+pub(crate) fn legacy_enabled_ifaces(root: &Element, tag: &str) -> Vec<String> {
+    let mut ifaces = HashSet::new();
+    for section in crate::extract::legacy_sections(root, tag) {
+        for iface_node in section.children.iter().filter_map(|c| c.as_element()) {
+            if iface_enabled(iface_node) {
+                ifaces.insert(iface_node.name.clone());
             }
-            None => false,
-        };
-        if enabled {
-            ifaces.insert(iface_name);
         }
     }
 
@@ -46,7 +56,17 @@ fn isc_enabled_ifaces(root: &Element, tag: &str) -> Vec<String> {
     result
 }
 
-fn disable_isc_dhcp_ifaces(
+fn iface_enabled(iface_node: &Element) -> bool {
+    match crate::xml_helpers::get_child_ci(iface_node, "enable").and_then(|e| e.get_text()) {
+        Some(v) => {
+            let val = v.trim();
+            !(val.is_empty() || val == "0")
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn disable_isc_dhcp_ifaces(
     root: &mut Element,
     ifaces_v4: &[String],
     ifaces_v6: &[String],
@@ -140,18 +160,8 @@ fn isc_disabled_ifaces(root: &Element, tag: &str) -> Vec<String> {
     };
 
     for iface_node in dhcp.children.iter().filter_map(|c| c.as_element()) {
-        let iface_name = iface_node.name.clone();
-        let enabled = match crate::xml_helpers::get_child_ci(iface_node, "enable")
-            .and_then(|e| e.get_text())
-        {
-            Some(v) => {
-                let val = v.trim();
-                !(val.is_empty() || val == "0")
-            }
-            None => false,
-        };
-        if !enabled {
-            ifaces.insert(iface_name);
+        if !iface_enabled(iface_node) {
+            ifaces.insert(iface_node.name.clone());
         }
     }
 
@@ -172,6 +182,7 @@ pub(crate) fn isc_enabled_ifaces_v6(root: &Element) -> Vec<String> {
 ///
 /// Only enables dhcp4 if v4 subnets exist, dhcp6 if v6 subnets exist.
 /// Returns (enabled_v4, enabled_v6).
+#[cfg(feature = "kea")]
 pub(crate) fn enable_kea(
     root: &mut Element,
     has_v4_subnets: bool,
@@ -216,8 +227,34 @@ pub(crate) fn enable_kea(
     Ok((enabled_v4, enabled_v6))
 }
 
+/// Make sure Kea's automatic firewall rules are on for an enabled service.
+///
+/// Sets `<Kea><dhcp4|dhcp6><general><fwrules>` to `1` when present. When the
+/// setting is missing, nothing is written and the absence is reported so the
+/// operator can check firewall rules manually.
+#[cfg(feature = "kea")]
+pub(crate) fn ensure_kea_fwrules(root: &mut Element, v6: bool) -> FirewallRulesStatus {
+    let dhcp_name = if v6 { "dhcp6" } else { "dhcp4" };
+    let fwrules = crate::xml_helpers::find_mut_descendant_ci(root, "Kea")
+        .and_then(|kea| crate::xml_helpers::get_mut_child_ci(kea, dhcp_name))
+        .and_then(|dhcp| crate::xml_helpers::get_mut_child_ci(dhcp, "general"))
+        .and_then(|general| crate::xml_helpers::get_mut_child_ci(general, "fwrules"));
+
+    let Some(elem) = fwrules else {
+        return FirewallRulesStatus::Missing;
+    };
+
+    if elem.get_text().is_some_and(|v| v.trim() == "1") {
+        return FirewallRulesStatus::AlreadyEnabled;
+    }
+
+    crate::xml_helpers::set_text_preserving_comments(elem, "1");
+    FirewallRulesStatus::Enabled
+}
+
 /// Enable dnsmasq DHCP service.
 /// Returns true if dnsmasq was enabled.
+#[cfg(feature = "dnsmasq")]
 pub(crate) fn enable_dnsmasq(root: &mut Element) -> Result<bool> {
     let dnsmasq = match crate::xml_helpers::find_mut_descendant_ci(root, "dnsmasq") {
         Some(dnsmasq) => dnsmasq,
@@ -228,12 +265,39 @@ pub(crate) fn enable_dnsmasq(root: &mut Element) -> Result<bool> {
     Ok(true)
 }
 
+/// Remove `ifaces` from dnsmasq's "disable DHCP on" list
+/// (`<dnsmasq><dhcp><no_interface>`). dnsmasq still answers DNS on listed
+/// interfaces but hands out no leases there. Returns the interfaces
+/// removed, in list order.
+#[cfg(feature = "dnsmasq")]
+pub(crate) fn allow_dnsmasq_dhcp(root: &mut Element, ifaces: &[String]) -> Vec<String> {
+    let Some(no_interface) = crate::xml_helpers::find_mut_descendant_ci(root, "dnsmasq")
+        .and_then(|dnsmasq| crate::xml_helpers::get_mut_child_ci(dnsmasq, "dhcp"))
+        .and_then(|dhcp| crate::xml_helpers::get_mut_child_ci(dhcp, "no_interface"))
+    else {
+        return Vec::new();
+    };
+    let listed = no_interface
+        .get_text()
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+
+    let (removed, kept): (Vec<&str>, Vec<&str>) = listed
+        .split(',')
+        .map(str::trim)
+        .filter(|iface| !iface.is_empty())
+        .partition(|iface| ifaces.iter().any(|i| i.eq_ignore_ascii_case(iface)));
+    if !removed.is_empty() {
+        crate::xml_helpers::set_text_preserving_comments(no_interface, &kept.join(","));
+    }
+    removed.into_iter().map(str::to_string).collect()
+}
+
 /// Set the `<enable>` element within a node (ISC/dnsmasq).
 fn set_enable_element_generic(node: &mut Element, enabled: bool) {
     if enabled {
         if let Some(elem) = crate::xml_helpers::get_mut_child_ci(node, "enable") {
-            elem.children.clear();
-            elem.children.push(XMLNode::Text("1".to_string()));
+            crate::xml_helpers::set_text_preserving_comments(elem, "1");
             return;
         }
 
@@ -252,12 +316,12 @@ fn set_enable_element_generic(node: &mut Element, enabled: bool) {
 }
 
 /// Set the `<enabled>` element within a Kea general node.
+#[cfg(feature = "kea")]
 fn set_enable_element_kea(node: &mut Element, enabled: bool) {
     let value = if enabled { "1" } else { "" };
 
     if let Some(elem) = crate::xml_helpers::get_mut_child_ci(node, "enabled") {
-        elem.children.clear();
-        elem.children.push(XMLNode::Text(value.to_string()));
+        crate::xml_helpers::set_text_preserving_comments(elem, value);
         return;
     }
 
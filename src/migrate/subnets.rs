@@ -1,62 +1,187 @@
-use anyhow::{anyhow, Result};
-use std::str::FromStr;
-use xmltree::{Element, XMLNode};
+use anyhow::Result;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use xmltree::Element;
 
 use crate::extract::{
-    extract_interface_cidrs, extract_interface_cidrs_v6, extract_isc_ranges, extract_isc_ranges_v6,
-    extract_kea_subnets, extract_kea_subnets_v6,
+    extract_interface_cidrs, extract_interface_cidrs_v6, extract_interface_labels,
+    extract_isc_netboot_v4, extract_isc_ramodes_v6, iface_with_descr, iter_isc_ranges,
+    iter_isc_ranges_v6, IscNetbootV4,
 };
-use crate::subnet::{ip_in_subnet, ip_in_subnet_v6};
-use crate::{IscRangeV4, IscRangeV6, MigrationError, MigrationOptions};
+use crate::parse;
+use crate::subnet::{clamp_range, clamp_range_v6, ip_in_subnet, ip_in_subnet_v6};
+use crate::term;
+use crate::{InvalidIscRange, IscRangeV4, IscRangeV6, MigrationError, MigrationOptions};
 
 #[derive(Debug, Clone)]
 pub(crate) struct DesiredSubnetV4 {
     pub(crate) iface: String,
+    pub(crate) descr: Option<String>,
     pub(crate) cidr: String,
     pub(crate) ranges: Vec<IscRangeV4>,
+    /// ISC network boot settings for the interface, if any
+    pub(crate) netboot: Option<IscNetbootV4>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct DesiredSubnetV6 {
     pub(crate) iface: String,
+    pub(crate) descr: Option<String>,
     pub(crate) cidr: String,
     pub(crate) ranges: Vec<IscRangeV6>,
+    /// ISC router advertisement mode for the interface, if set
+    #[cfg_attr(not(feature = "dnsmasq"), allow(dead_code))]
+    pub(crate) ramode: Option<String>,
 }
 
-pub(crate) fn desired_subnets_v4(root: &Element) -> Result<Vec<DesiredSubnetV4>> {
-    let ranges = extract_isc_ranges(root)?;
+impl DesiredSubnetV4 {
+    /// Interface key with its description, for messages.
+    pub(crate) fn display_iface(&self) -> String {
+        iface_with_descr(&self.iface, self.descr.as_deref())
+    }
+}
+
+impl DesiredSubnetV6 {
+    /// Interface key with its description, for messages.
+    pub(crate) fn display_iface(&self) -> String {
+        iface_with_descr(&self.iface, self.descr.as_deref())
+    }
+}
+
+/// The usable part of the range `from`-`to`, with a warning when it
+/// reached into the network or broadcast address of `cidr` and is trimmed,
+/// or holds nothing else and is skipped.
+fn usable_part(
+    family: &str,
+    iface: &str,
+    (from, to): (&str, &str),
+    cidr: &str,
+    clamped: Option<(String, String)>,
+) -> Option<(String, String)> {
+    let Some((start, end)) = clamped else {
+        term::warning(format_args!(
+            "{} range {}-{} on {} holds no usable address of {}. Skipping it.",
+            family, from, to, iface, cidr
+        ));
+        return None;
+    };
+    if start != from || end != to {
+        term::warning(format_args!(
+            "{} range {}-{} on {} includes a reserved address of {}; using {}-{}.",
+            family, from, to, iface, cidr, start, end
+        ));
+    }
+    Some((start, end))
+}
+
+/// What is wrong with the range `from`-`to` of a `v6` or DHCPv4 section,
+/// if anything.
+fn range_problem(from: &str, to: &str, v6: bool) -> Option<String> {
+    let parse = |ip: &str| ip.trim().parse::<IpAddr>().ok();
+    let (start, end) = match (parse(from), parse(to)) {
+        (Some(start), Some(end)) => (start, end),
+        (start, end) => {
+            let bad: Vec<&str> = [(start, from), (end, to)]
+                .into_iter()
+                .filter(|(ip, _)| ip.is_none())
+                .map(|(_, text)| text)
+                .collect();
+            return Some(format!("'{}' is not an IP address", bad.join("' and '")));
+        }
+    };
+    if start.is_ipv6() != end.is_ipv6() {
+        return Some("mixes an IPv4 and an IPv6 address".to_string());
+    }
+    if start.is_ipv6() != v6 {
+        return Some(format!(
+            "holds IPv{} addresses",
+            if start.is_ipv6() { 6 } else { 4 }
+        ));
+    }
+    if start > end {
+        return Some("ends before it starts".to_string());
+    }
+    None
+}
+
+/// Check every ISC range of both families before any is converted: both
+/// bounds must be addresses of the section's family, and `from` must not
+/// come after `to`, so the range holds at least one address. All problems
+/// are reported at once.
+fn check_isc_ranges(root: &Element) -> Result<()> {
+    let labels = extract_interface_labels(root);
+    let ranges_v4 = iter_isc_ranges(root).map(|r| ("DHCPv4", false, r.iface, r.from, r.to));
+    let ranges_v6 = iter_isc_ranges_v6(root).map(|r| ("DHCPv6", true, r.iface, r.from, r.to));
+    let ranges: Vec<InvalidIscRange> = ranges_v4
+        .chain(ranges_v6)
+        .filter_map(|(family, v6, iface, from, to)| {
+            let problem = range_problem(&from, &to, v6)?;
+            Some(InvalidIscRange {
+                iface: iface_with_descr(&iface, labels.get(&iface).map(String::as_str)),
+                family: family.to_string(),
+                from,
+                to,
+                problem,
+            })
+        })
+        .collect();
+
     if ranges.is_empty() {
+        return Ok(());
+    }
+    Err(MigrationError::InvalidRanges { ranges }.into())
+}
+
+pub(crate) fn desired_subnets_v4(root: &Element) -> Result<Vec<DesiredSubnetV4>> {
+    let mut ranges = iter_isc_ranges(root).peekable();
+    if ranges.peek().is_none() {
         return Ok(Vec::new());
     }
+    check_isc_ranges(root)?;
 
     let iface_cidrs = extract_interface_cidrs(root)?;
+    let labels = extract_interface_labels(root);
+    let netboot = extract_isc_netboot_v4(root);
     let mut by_iface: std::collections::HashMap<String, DesiredSubnetV4> =
         std::collections::HashMap::new();
 
     for range in ranges {
         let cidr = iface_cidrs.get(&range.iface).cloned().ok_or_else(|| {
-            anyhow!(
-                "No interface CIDR found for DHCPv4 interface '{}'",
-                range.iface
-            )
+            MigrationError::MissingInterfaceCidr {
+                family: "DHCPv4".to_string(),
+                iface: range.iface.clone(),
+            }
         })?;
 
         if !ip_in_subnet(&range.from, &cidr)? || !ip_in_subnet(&range.to, &cidr)? {
-            return Err(anyhow!(
-                "DHCPv4 range {}-{} is not contained within interface subnet {} ({})",
-                range.from,
-                range.to,
-                range.iface,
-                cidr
-            ));
+            return Err(MigrationError::RangeOutsideSubnet {
+                family: "DHCPv4".to_string(),
+                iface: range.iface.clone(),
+                from: range.from.clone(),
+                to: range.to.clone(),
+                cidr,
+            }
+            .into());
         }
+        let clamped = clamp_range(&range.from, &range.to, &cidr)?;
+        let Some((from, to)) = usable_part(
+            "DHCPv4",
+            &range.iface,
+            (&range.from, &range.to),
+            &cidr,
+            clamped,
+        ) else {
+            continue;
+        };
+        let range = IscRangeV4 { from, to, ..range };
 
         by_iface
             .entry(range.iface.clone())
             .and_modify(|entry| entry.ranges.push(range.clone()))
             .or_insert(DesiredSubnetV4 {
                 iface: range.iface.clone(),
+                descr: labels.get(&range.iface).cloned(),
                 cidr,
+                netboot: netboot.get(&range.iface).cloned(),
                 ranges: vec![range],
             });
     }
@@ -65,39 +190,56 @@ pub(crate) fn desired_subnets_v4(root: &Element) -> Result<Vec<DesiredSubnetV4>>
 }
 
 pub(crate) fn desired_subnets_v6(root: &Element) -> Result<Vec<DesiredSubnetV6>> {
-    let ranges = extract_isc_ranges_v6(root)?;
-    if ranges.is_empty() {
+    let mut ranges = iter_isc_ranges_v6(root).peekable();
+    if ranges.peek().is_none() {
         return Ok(Vec::new());
     }
+    check_isc_ranges(root)?;
 
     let iface_cidrs = extract_interface_cidrs_v6(root)?;
+    let labels = extract_interface_labels(root);
+    let ramodes = extract_isc_ramodes_v6(root);
     let mut by_iface: std::collections::HashMap<String, DesiredSubnetV6> =
         std::collections::HashMap::new();
 
     for range in ranges {
         let cidr = iface_cidrs.get(&range.iface).cloned().ok_or_else(|| {
-            anyhow!(
-                "No interface CIDR found for DHCPv6 interface '{}'",
-                range.iface
-            )
+            MigrationError::MissingInterfaceCidr {
+                family: "DHCPv6".to_string(),
+                iface: range.iface.clone(),
+            }
         })?;
 
         if !ip_in_subnet_v6(&range.from, &cidr)? || !ip_in_subnet_v6(&range.to, &cidr)? {
-            return Err(anyhow!(
-                "DHCPv6 range {}-{} is not contained within interface subnet {} ({})",
-                range.from,
-                range.to,
-                range.iface,
-                cidr
-            ));
+            return Err(MigrationError::RangeOutsideSubnet {
+                family: "DHCPv6".to_string(),
+                iface: range.iface.clone(),
+                from: range.from.clone(),
+                to: range.to.clone(),
+                cidr,
+            }
+            .into());
         }
+        let clamped = clamp_range_v6(&range.from, &range.to, &cidr)?;
+        let Some((from, to)) = usable_part(
+            "DHCPv6",
+            &range.iface,
+            (&range.from, &range.to),
+            &cidr,
+            clamped,
+        ) else {
+            continue;
+        };
+        let range = IscRangeV6 { from, to, ..range };
 
         by_iface
             .entry(range.iface.clone())
             .and_modify(|entry| entry.ranges.push(range.clone()))
             .or_insert(DesiredSubnetV6 {
                 iface: range.iface.clone(),
+                descr: labels.get(&range.iface).cloned(),
                 cidr,
+                ramode: ramodes.get(&range.iface).cloned(),
                 ranges: vec![range],
             });
     }
@@ -105,259 +247,176 @@ pub(crate) fn desired_subnets_v6(root: &Element) -> Result<Vec<DesiredSubnetV6>>
     Ok(by_iface.into_values().collect())
 }
 
-fn get_kea_subnets_node_mut(root: &mut Element, v6: bool) -> Result<&mut Element> {
-    let kea = crate::xml_helpers::find_mut_descendant_ci(root, "Kea")
-        .ok_or_else(|| anyhow!("Kea not configured in config.xml"))?;
-    let dhcp_name = if v6 { "dhcp6" } else { "dhcp4" };
-    let dhcp = crate::xml_helpers::find_mut_descendant_ci(kea, dhcp_name)
-        .ok_or_else(|| anyhow!("Failed to access Kea {} node", dhcp_name))?;
-
-    if crate::xml_helpers::get_mut_child_ci(dhcp, "subnets").is_none() {
-        dhcp.children
-            .push(XMLNode::Element(Element::new("subnets")));
+/// Merge the ranges of the created subnets when `options.merge_ranges` is
+/// set. Returns how many ranges were merged away.
+pub(crate) fn merge_desired_ranges(
+    desired_v4: &mut [DesiredSubnetV4],
+    desired_v6: &mut [DesiredSubnetV6],
+    options: &MigrationOptions,
+) -> usize {
+    if !options.merge_ranges {
+        return 0;
     }
-
-    crate::xml_helpers::get_mut_child_ci(dhcp, "subnets")
-        .ok_or_else(|| anyhow!("Failed to access Kea subnets node"))
+    merge_ranges_v4(desired_v4, options) + merge_ranges_v6(desired_v6, options)
 }
 
-fn get_kea_general_node_mut(root: &mut Element, v6: bool) -> Result<&mut Element> {
-    let kea = crate::xml_helpers::find_mut_descendant_ci(root, "Kea")
-        .ok_or_else(|| anyhow!("Kea not configured in config.xml"))?;
-    let dhcp_name = if v6 { "dhcp6" } else { "dhcp4" };
-    let dhcp = crate::xml_helpers::find_mut_descendant_ci(kea, dhcp_name)
-        .ok_or_else(|| anyhow!("Failed to access Kea {} node", dhcp_name))?;
-
-    if crate::xml_helpers::get_mut_child_ci(dhcp, "general").is_none() {
-        dhcp.children
-            .push(XMLNode::Element(Element::new("general")));
+/// Merge the overlapping and adjacent ranges of each subnet into as few
+/// pools as cover the same addresses (`--merge-ranges`). Returns how many
+/// ranges were merged away.
+pub(crate) fn merge_ranges_v4(
+    subnets: &mut [DesiredSubnetV4],
+    options: &MigrationOptions,
+) -> usize {
+    let mut merged = 0;
+    for subnet in subnets.iter_mut() {
+        let before = subnet.ranges.len();
+        subnet.ranges = merge_spans(
+            std::mem::take(&mut subnet.ranges),
+            |r| {
+                let (from, to) = parse::range_v4(&r.from, &r.to).ok()?;
+                Some((u32::from(from).into(), u32::from(to).into()))
+            },
+            |r, start, end| IscRangeV4 {
+                iface: r.iface.clone(),
+                from: Ipv4Addr::from(start as u32).to_string(),
+                to: Ipv4Addr::from(end as u32).to_string(),
+            },
+        );
+        merged += before - subnet.ranges.len();
+        report_merge(
+            &subnet.display_iface(),
+            before,
+            subnet.ranges.iter().map(|r| (&r.from, &r.to)),
+            options,
+        );
     }
-
-    crate::xml_helpers::get_mut_child_ci(dhcp, "general")
-        .ok_or_else(|| anyhow!("Failed to access Kea general node"))
-}
-
-fn create_kea_subnet4_element(cidr: &str, ranges: &[IscRangeV4]) -> Element {
-    let mut subnet4 = Element::new("subnet4");
-    subnet4
-        .attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    let mut subnet_elem = Element::new("subnet");
-    subnet_elem.children.push(XMLNode::Text(cidr.to_string()));
-    subnet4.children.push(XMLNode::Element(subnet_elem));
-
-    let mut pools = Element::new("pools");
-    let pool_str = ranges
-        .iter()
-        .map(|r| format!("{}-{}", r.from, r.to))
-        .collect::<Vec<_>>()
-        .join(",");
-    pools.children.push(XMLNode::Text(pool_str));
-    subnet4.children.push(XMLNode::Element(pools));
-
-    subnet4
+    merged
 }
 
-fn create_kea_subnet6_element(cidr: &str, ranges: &[IscRangeV6], iface: &str) -> Element {
-    let mut subnet6 = Element::new("subnet6");
-    subnet6
-        .attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    let mut subnet_elem = Element::new("subnet");
-    subnet_elem.children.push(XMLNode::Text(cidr.to_string()));
-    subnet6.children.push(XMLNode::Element(subnet_elem));
-
-    let mut pools = Element::new("pools");
-    let pool_str = ranges
-        .iter()
-        .map(|r| format!("{}-{}", r.from, r.to))
-        .collect::<Vec<_>>()
-        .join(",");
-    pools.children.push(XMLNode::Text(pool_str));
-    subnet6.children.push(XMLNode::Element(pools));
-
-    let mut interface_elem = Element::new("interface");
-    interface_elem
-        .children
-        .push(XMLNode::Text(iface.to_string()));
-    subnet6.children.push(XMLNode::Element(interface_elem));
-
-    subnet6
-}
-
-fn remove_kea_subnet_by_cidr(subnets_node: &mut Element, v6: bool, cidr: &str) -> bool {
-    let subnet_tag = if v6 { "subnet6" } else { "subnet4" };
-    let before = subnets_node.children.len();
-    subnets_node.children.retain(|child| {
-        let Some(elem) = child.as_element() else {
-            return true;
-        };
-        if !elem.name.eq_ignore_ascii_case(subnet_tag) {
-            return true;
-        }
-        let subnet_val = elem
-            .children
-            .iter()
-            .filter_map(|c| c.as_element())
-            .find(|e| e.name.eq_ignore_ascii_case("subnet"))
-            .and_then(|e| e.get_text())
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-        subnet_val != cidr
-    });
-    before != subnets_node.children.len()
+/// DHCPv6 counterpart of [`merge_ranges_v4`].
+pub(crate) fn merge_ranges_v6(
+    subnets: &mut [DesiredSubnetV6],
+    options: &MigrationOptions,
+) -> usize {
+    let mut merged = 0;
+    for subnet in subnets.iter_mut() {
+        let before = subnet.ranges.len();
+        subnet.ranges = merge_spans(
+            std::mem::take(&mut subnet.ranges),
+            |r| {
+                let (from, to) = parse::range_v6(&r.from, &r.to).ok()?;
+                Some((from.into(), to.into()))
+            },
+            |r, start, end| IscRangeV6 {
+                iface: r.iface.clone(),
+                from: Ipv6Addr::from(start).to_string(),
+                to: Ipv6Addr::from(end).to_string(),
+            },
+        );
+        merged += before - subnet.ranges.len();
+        report_merge(
+            &subnet.display_iface(),
+            before,
+            subnet.ranges.iter().map(|r| (&r.from, &r.to)),
+            options,
+        );
+    }
+    merged
 }
 
-pub(crate) fn apply_kea_subnets(
-    root: &mut Element,
-    kea_subnets: &mut Vec<crate::Subnet>,
-    kea_subnets_v6: &mut Vec<crate::SubnetV6>,
-    desired_v4: &[DesiredSubnetV4],
-    desired_v6: &[DesiredSubnetV6],
-    options: &MigrationOptions,
-) -> Result<()> {
-    if !desired_v4.is_empty() {
-        let existing: std::collections::HashSet<_> =
-            kea_subnets.iter().map(|s| s.cidr.clone()).collect();
-        let subnets_node = get_kea_subnets_node_mut(root, false)?;
-        for subnet in desired_v4 {
-            if existing.contains(&subnet.cidr) {
-                if options.force_subnets {
-                    remove_kea_subnet_by_cidr(subnets_node, false, &subnet.cidr);
-                } else {
-                    eprintln!(
-                        "Warning: Kea subnet {} already exists (iface {}). Skipping.",
-                        subnet.cidr, subnet.iface
-                    );
-                    continue;
-                }
-            }
-            let elem = create_kea_subnet4_element(&subnet.cidr, &subnet.ranges);
-            subnets_node.children.push(XMLNode::Element(elem));
+/// `ranges` sorted by start, with each run of overlapping or adjacent
+/// ranges replaced by one range built with `build`. A range that merges
+/// with nothing is kept as written; one whose bounds do not parse is kept
+/// at the end.
+fn merge_spans<R>(
+    ranges: Vec<R>,
+    bounds: impl Fn(&R) -> Option<(u128, u128)>,
+    build: impl Fn(&R, u128, u128) -> R,
+) -> Vec<R> {
+    let mut spans = Vec::new();
+    let mut unparsed = Vec::new();
+    for range in ranges {
+        match bounds(&range) {
+            Some((start, end)) => spans.push((start, end, range)),
+            None => unparsed.push(range),
         }
     }
-
-    if !desired_v6.is_empty() {
-        let existing: std::collections::HashSet<_> =
-            kea_subnets_v6.iter().map(|s| s.cidr.clone()).collect();
-        let subnets_node = get_kea_subnets_node_mut(root, true)?;
-        for subnet in desired_v6 {
-            if existing.contains(&subnet.cidr) {
-                if options.force_subnets {
-                    remove_kea_subnet_by_cidr(subnets_node, true, &subnet.cidr);
-                } else {
-                    eprintln!(
-                        "Warning: Kea subnet {} already exists (iface {}). Skipping.",
-                        subnet.cidr, subnet.iface
-                    );
-                    continue;
+    spans.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut out = Vec::new();
+    let mut run: Option<(u128, u128, Vec<R>)> = None;
+    for (start, end, range) in spans {
+        match &mut run {
+            Some((_, run_end, members)) if start <= run_end.saturating_add(1) => {
+                *run_end = (*run_end).max(end);
+                members.push(range);
+            }
+            _ => {
+                if let Some(done) = run.take() {
+                    out.push(finish_run(done, &build));
                 }
+                run = Some((start, end, vec![range]));
             }
-            let elem = create_kea_subnet6_element(&subnet.cidr, &subnet.ranges, &subnet.iface);
-            subnets_node.children.push(XMLNode::Element(elem));
         }
     }
-
-    *kea_subnets = extract_kea_subnets(root)?;
-    *kea_subnets_v6 = extract_kea_subnets_v6(root)?;
-    Ok(())
-}
-
-pub(crate) fn cidr_prefix_v4(cidr: &str) -> Result<u8> {
-    let net = ipnet::Ipv4Net::from_str(cidr)
-        .map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))?;
-    Ok(net.prefix_len())
-}
-
-pub(crate) fn cidr_prefix_v6(cidr: &str) -> Result<u8> {
-    let net = ipnet::Ipv6Net::from_str(cidr)
-        .map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))?;
-    Ok(net.prefix_len())
-}
-
-/// Configure Kea listening interfaces from desired subnets.
-/// Returns list of interfaces that were configured.
-pub(crate) fn apply_kea_interfaces(
-    root: &mut Element,
-    desired_v4: &[DesiredSubnetV4],
-    desired_v6: &[DesiredSubnetV6],
-) -> Result<Vec<String>> {
-    // Collect unique interfaces from desired subnets (for return value)
-    let mut all_ifaces: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-    let mut ifaces_v4: std::collections::HashSet<String> = std::collections::HashSet::new();
-    for subnet in desired_v4 {
-        ifaces_v4.insert(subnet.iface.clone());
-        all_ifaces.insert(subnet.iface.clone());
+    if let Some(done) = run {
+        out.push(finish_run(done, &build));
     }
+    out.extend(unparsed);
+    out
+}
 
-    let mut ifaces_v6: std::collections::HashSet<String> = std::collections::HashSet::new();
-    for subnet in desired_v6 {
-        ifaces_v6.insert(subnet.iface.clone());
-        all_ifaces.insert(subnet.iface.clone());
+fn finish_run<R>(
+    (start, end, mut members): (u128, u128, Vec<R>),
+    build: impl Fn(&R, u128, u128) -> R,
+) -> R {
+    if members.len() == 1 {
+        return members.remove(0);
     }
+    build(&members[0], start, end)
+}
 
-    // Apply v4 interfaces
-    if !ifaces_v4.is_empty() {
-        let general = get_kea_general_node_mut(root, false)?;
-
-        // Get existing interfaces and merge
-        let existing = crate::xml_helpers::get_child_ci(general, "interfaces")
-            .and_then(|e| e.get_text())
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        for iface in existing.split(',').filter(|s| !s.is_empty()) {
-            ifaces_v4.insert(iface.to_string());
-        }
-
-        // Remove existing interfaces element if present
-        general
-            .children
-            .retain(|c| c.as_element().is_none_or(|e| e.name != "interfaces"));
-
-        // Add merged interfaces
-        let mut ifaces_elem = Element::new("interfaces");
-        let mut sorted_ifaces: Vec<_> = ifaces_v4.into_iter().collect();
-        sorted_ifaces.sort();
-        ifaces_elem
-            .children
-            .push(XMLNode::Text(sorted_ifaces.join(",")));
-        general.children.push(XMLNode::Element(ifaces_elem));
+fn report_merge<'a>(
+    iface: &str,
+    before: usize,
+    pools: impl Iterator<Item = (&'a String, &'a String)>,
+    options: &MigrationOptions,
+) {
+    let pools: Vec<String> = pools.map(|(from, to)| format!("{}-{}", from, to)).collect();
+    if options.verbose && pools.len() < before {
+        term::action(
+            "MERGE",
+            format_args!("{}: {} ranges -> {}", iface, before, pools.join(", ")),
+        );
     }
+}
 
-    // Apply v6 interfaces
-    if !ifaces_v6.is_empty() {
-        let general = get_kea_general_node_mut(root, true)?;
-
-        // Get existing interfaces and merge
-        let existing = crate::xml_helpers::get_child_ci(general, "interfaces")
-            .and_then(|e| e.get_text())
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        for iface in existing.split(',').filter(|s| !s.is_empty()) {
-            ifaces_v6.insert(iface.to_string());
-        }
-
-        // Remove existing interfaces element if present
-        general
-            .children
-            .retain(|c| c.as_element().is_none_or(|e| e.name != "interfaces"));
-
-        // Add merged interfaces
-        let mut ifaces_elem = Element::new("interfaces");
-        let mut sorted_ifaces: Vec<_> = ifaces_v6.into_iter().collect();
-        sorted_ifaces.sort();
-        ifaces_elem
-            .children
-            .push(XMLNode::Text(sorted_ifaces.join(",")));
-        general.children.push(XMLNode::Element(ifaces_elem));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_spans() {
+        let spans = vec![(30, 40), (10, 20), (21, 25), (15, 18), (50, 60), (0, 0)];
+        let merged = merge_spans(
+            spans.into_iter().map(Some).collect(),
+            |r: &Option<(u128, u128)>| *r,
+            |_, start, end| Some((start, end)),
+        );
+        assert_eq!(
+            merged,
+            [Some((0, 0)), Some((10, 25)), Some((30, 40)), Some((50, 60))]
+        );
+
+        let edge = merge_spans(
+            vec![
+                Some((u128::MAX - 1, u128::MAX)),
+                Some((u128::MAX, u128::MAX)),
+                None,
+            ],
+            |r: &Option<(u128, u128)>| *r,
+            |_, start, end| Some((start, end)),
+        );
+        assert_eq!(edge, [Some((u128::MAX - 1, u128::MAX)), None]);
     }
-
-    let mut result: Vec<_> = all_ifaces.into_iter().collect();
-    result.sort();
-    Ok(result)
 }
@@ -0,0 +1,91 @@
+//! Staged changes to a config tree.
+//!
+//! A convert touches several sections from different modules: backend
+//! reservations and hosts, DNS host records, services, markers. Each of
+//! them changes the working copy held by a [`Transaction`], and the checks
+//! on the result are registered alongside. [`Transaction::commit`] runs
+//! every check against the complete set of changes and only then replaces
+//! the document root, so a failed check, or an error while staging, leaves
+//! the document exactly as it was parsed.
+
+use anyhow::Result;
+use xmltree::Element;
+
+type Check<'a> = Box<dyn FnOnce(&Element) -> Result<()> + 'a>;
+
+pub(crate) struct Transaction<'a> {
+    staged: Element,
+    checks: Vec<Check<'a>>,
+}
+
+impl<'a> Transaction<'a> {
+    /// A transaction staging changes on a copy of `root`.
+    pub(crate) fn begin(root: &Element) -> Self {
+        Transaction {
+            staged: root.clone(),
+            checks: Vec::new(),
+        }
+    }
+
+    /// The staged tree, as changed so far.
+    pub(crate) fn staged(&self) -> &Element {
+        &self.staged
+    }
+
+    pub(crate) fn staged_mut(&mut self) -> &mut Element {
+        &mut self.staged
+    }
+
+    /// Run `check` on the staged tree once every change is in. Checks run
+    /// in the order they were added and the first failure aborts the
+    /// transaction.
+    pub(crate) fn check(&mut self, check: impl FnOnce(&Element) -> Result<()> + 'a) {
+        self.checks.push(Box::new(check));
+    }
+
+    /// Run the checks and, if all pass, replace `root` with the staged tree.
+    pub(crate) fn commit(self, root: &mut Element) -> Result<()> {
+        for check in self.checks {
+            check(&self.staged)?;
+        }
+        *root = self.staged;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use xmltree::XMLNode;
+
+    fn parse(xml: &str) -> Element {
+        Element::parse(xml.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_commit_applies_only_if_every_check_passes() {
+        let mut root = parse("<opnsense><dhcpd/></opnsense>");
+        let original = root.clone();
+
+        let mut tx = Transaction::begin(&root);
+        tx.staged_mut()
+            .children
+            .push(XMLNode::Element(Element::new("Kea")));
+        tx.check(|staged| {
+            assert!(staged.get_child("Kea").is_some());
+            Ok(())
+        });
+        tx.check(|_| bail!("rejected"));
+        assert!(tx.commit(&mut root).is_err());
+        assert_eq!(root, original);
+
+        let mut tx = Transaction::begin(&root);
+        tx.staged_mut()
+            .children
+            .push(XMLNode::Element(Element::new("Kea")));
+        tx.check(|_| Ok(()));
+        tx.commit(&mut root).unwrap();
+        assert!(root.get_child("Kea").is_some());
+    }
+}
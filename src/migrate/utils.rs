@@ -1,11 +1,61 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use xmltree::{Element, XMLNode};
 
+use crate::extract::{extract_interface_cidrs, extract_interface_cidrs_v6};
+use crate::migrate::services::{
+    isc_enabled_ifaces_v4, isc_enabled_ifaces_v6, legacy_enabled_ifaces,
+};
+use crate::parse;
 use crate::subnet::{iface_for_ip, iface_for_ip_v6};
-use crate::{IscStaticMap, IscStaticMapV6, MigrationError};
+use crate::term;
+use crate::warnings::{warn, WarningKind};
+use crate::xml_helpers::get_child_ci;
+use crate::{
+    HostnamePolicy, IscStaticMap, IscStaticMapV6, MappingBatch, MigrationError, MigrationOptions,
+    OrphanedIfacePolicy, OrphanedMapping, ReservationOrder,
+};
 
-pub(crate) fn short_uuid(uuid: &str) -> &str {
-    uuid.get(..8).unwrap_or(uuid)
+/// The ISC DHCP sections (`dhcpd`, `dhcpdv6`) of a config, by tag.
+pub(crate) type IscSections = Vec<(&'static str, Option<Element>)>;
+
+/// Copy the ISC DHCP sections of `root`.
+pub(crate) fn isc_sections(root: &Element) -> IscSections {
+    ["dhcpd", "dhcpdv6"]
+        .into_iter()
+        .map(|name| (name, get_child_ci(root, name).cloned()))
+        .collect()
+}
+
+/// Put `sections` in place of the ISC DHCP sections of `target`, keeping
+/// their position when `target` already has them, and return the sections
+/// replaced.
+pub(crate) fn swap_isc_sections(target: &mut Element, sections: IscSections) -> IscSections {
+    let mut replaced = Vec::new();
+    for (name, replacement) in sections {
+        let existing = target.children.iter().position(|node| {
+            node.as_element()
+                .is_some_and(|el| el.name.eq_ignore_ascii_case(name))
+        });
+        let previous = match (existing, replacement) {
+            (Some(idx), Some(section)) => Some(std::mem::replace(
+                &mut target.children[idx],
+                XMLNode::Element(section),
+            )),
+            (Some(idx), None) => Some(target.children.remove(idx)),
+            (None, Some(section)) => {
+                target.children.push(XMLNode::Element(section));
+                None
+            }
+            (None, None) => None,
+        };
+        let previous = match previous {
+            Some(XMLNode::Element(section)) => Some(section),
+            _ => None,
+        };
+        replaced.push((name, previous));
+    }
+    replaced
 }
 
 pub(crate) fn validate_mapping_ifaces_v4(
@@ -43,3 +93,449 @@ pub(crate) fn validate_mapping_ifaces_v6(
     }
     Ok(())
 }
+
+/// Why `mapping` cannot be created next to the existing entries: its IP,
+/// then its hardware address, then its client ID already in use. The
+/// hardware address is only compared when `match_mac` is set.
+pub(crate) fn conflict_v4(
+    mapping: &IscStaticMap,
+    ips: &HashSet<String>,
+    macs: &HashSet<String>,
+    client_ids: &HashSet<String>,
+    match_mac: bool,
+) -> Option<MigrationError> {
+    if ips.contains(&mapping.ipaddr) {
+        return Some(MigrationError::DuplicateIp {
+            ip: mapping.ipaddr.clone(),
+        });
+    }
+    if match_mac && macs.contains(&mapping.mac) {
+        return Some(MigrationError::DuplicateMac {
+            ip: mapping.ipaddr.clone(),
+            mac: mapping.mac.clone(),
+        });
+    }
+    let cid = mapping
+        .cid
+        .as_ref()
+        .filter(|cid| client_ids.contains(*cid))?;
+    Some(MigrationError::DuplicateClientId {
+        ip: mapping.ipaddr.clone(),
+        client_id: cid.clone(),
+    })
+}
+
+/// Why `mapping` cannot be created: its IP, then its DUID already in use.
+#[cfg(feature = "dnsmasq")]
+pub(crate) fn conflict_v6(
+    mapping: &IscStaticMapV6,
+    ips: &HashSet<String>,
+    duids: &HashSet<String>,
+) -> Option<MigrationError> {
+    if ips.contains(&mapping.ipaddr) {
+        return Some(MigrationError::DuplicateIp {
+            ip: mapping.ipaddr.clone(),
+        });
+    }
+    duids
+        .contains(&mapping.duid)
+        .then(|| MigrationError::DuplicateDuid {
+            ip: mapping.ipaddr.clone(),
+            duid: mapping.duid.clone(),
+        })
+}
+
+/// Reorder newly discovered v4 mappings according to `order`.
+///
+/// The sort is stable, so mappings that compare equal keep discovery order.
+/// Unparsable addresses sort after valid ones.
+pub(crate) fn sort_mappings_v4(mappings: &mut [IscStaticMap], order: ReservationOrder) {
+    let ip_key = |m: &IscStaticMap| {
+        let ip = parse::ipv4(&m.ipaddr).ok();
+        (ip.is_none(), ip)
+    };
+    match order {
+        ReservationOrder::Discovery => {}
+        ReservationOrder::Ip => mappings.sort_by_key(ip_key),
+        ReservationOrder::IfaceIp => {
+            mappings.sort_by_key(|m| (m.iface.to_ascii_lowercase(), ip_key(m)))
+        }
+    }
+}
+
+/// Reorder newly discovered v6 mappings according to `order`.
+pub(crate) fn sort_mappings_v6(mappings: &mut [IscStaticMapV6], order: ReservationOrder) {
+    let ip_key = |m: &IscStaticMapV6| {
+        let ip = parse::ipv6(&m.ipaddr).ok();
+        (ip.is_none(), ip)
+    };
+    match order {
+        ReservationOrder::Discovery => {}
+        ReservationOrder::Ip => mappings.sort_by_key(ip_key),
+        ReservationOrder::IfaceIp => {
+            mappings.sort_by_key(|m| (m.iface.to_ascii_lowercase(), ip_key(m)))
+        }
+    }
+}
+
+/// Keep only the mappings selected by `offset`/`limit`, counting DHCPv4
+/// mappings first and then DHCPv6. Returns `None` when neither is set.
+pub(crate) fn select_batch(
+    mappings: &mut Vec<IscStaticMap>,
+    mappings_v6: &mut Vec<IscStaticMapV6>,
+    options: &MigrationOptions,
+) -> Option<MappingBatch> {
+    if options.offset == 0 && options.limit.is_none() {
+        return None;
+    }
+    let n_v4 = mappings.len();
+    let total = n_v4 + mappings_v6.len();
+    let start = options.offset.min(total);
+    let end = options
+        .limit
+        .map_or(total, |limit| start.saturating_add(limit).min(total));
+
+    mappings.truncate(end.min(n_v4));
+    mappings.drain(..start.min(n_v4));
+    mappings_v6.truncate(end.saturating_sub(n_v4));
+    mappings_v6.drain(..start.saturating_sub(n_v4));
+
+    Some(MappingBatch {
+        offset: start,
+        selected: end - start,
+        total,
+    })
+}
+
+/// Remove mappings on interfaces where ISC DHCP is disabled (`<enable>`
+/// absent or `0`), returning how many were dropped per family.
+pub(crate) fn drop_disabled_iface_mappings(
+    root: &Element,
+    mappings: &mut Vec<IscStaticMap>,
+    mappings_v6: &mut Vec<IscStaticMapV6>,
+    options: &MigrationOptions,
+) -> (usize, usize) {
+    let mut enabled_v4 = isc_enabled_ifaces_v4(root);
+    let mut enabled_v6 = isc_enabled_ifaces_v6(root);
+    if options.legacy_paths {
+        enabled_v4.extend(legacy_enabled_ifaces(root, "dhcpd"));
+        enabled_v6.extend(legacy_enabled_ifaces(root, "dhcpdv6"));
+    }
+    let is_enabled =
+        |enabled: &[String], iface: &str| enabled.iter().any(|i| i.eq_ignore_ascii_case(iface));
+
+    let total_v4 = mappings.len();
+    mappings.retain(|m| {
+        let keep = is_enabled(&enabled_v4, &m.iface);
+        if !keep && options.verbose {
+            term::action(
+                "IGNORE",
+                format_args!(
+                    "{} ({}) - ISC DHCP disabled on {}",
+                    m.ipaddr, m.mac, m.iface
+                ),
+            );
+        }
+        keep
+    });
+
+    let total_v6 = mappings_v6.len();
+    mappings_v6.retain(|m| {
+        let keep = is_enabled(&enabled_v6, &m.iface);
+        if !keep && options.verbose {
+            term::action(
+                "IGNORE6",
+                format_args!(
+                    "{} ({}) - ISC DHCPv6 disabled on {}",
+                    m.ipaddr, m.duid, m.iface
+                ),
+            );
+        }
+        keep
+    });
+
+    let dropped = (total_v4 - mappings.len(), total_v6 - mappings_v6.len());
+    if dropped.0 + dropped.1 > 0 {
+        term::warning(format_args!(
+            "Ignoring {} static mapping(s) on interfaces where ISC DHCP is disabled. Use \
+             --include-disabled-interfaces to migrate them.",
+            dropped.0 + dropped.1
+        ));
+    }
+    dropped
+}
+
+/// Normalize the addresses of DHCPv6 mappings, dropping ones no backend can
+/// reserve.
+///
+/// A zone index (`2001:db8::10%igb0`) only matters on link-local addresses,
+/// so it is stripped from global ones. Link-local addresses and IPv4-mapped
+/// ones (`::ffff:192.0.2.1`) are dropped and returned as skips naming the
+/// interface. Addresses that do not parse are left for the backend to
+/// reject.
+pub(crate) fn normalize_v6_addresses(
+    mappings: &mut Vec<IscStaticMapV6>,
+    options: &MigrationOptions,
+) -> Vec<MigrationError> {
+    let mut rejected = Vec::new();
+    mappings.retain_mut(|m| {
+        let written = m.ipaddr.trim();
+        let (addr, zone) = match written.split_once('%') {
+            Some((addr, zone)) => (addr, Some(zone)),
+            None => (written, None),
+        };
+        let Ok(ip) = parse::ipv6(addr) else {
+            return true;
+        };
+        let reason = if let Some(v4) = ip.to_ipv4_mapped() {
+            format!(
+                "IPv4-mapped address; reserve {} as a DHCPv4 static mapping instead",
+                v4
+            )
+        } else if ip.segments()[0] & 0xffc0 == 0xfe80 {
+            "link-local addresses cannot be reserved".to_string()
+        } else {
+            if let Some(zone) = zone {
+                if options.verbose {
+                    term::action(
+                        "ZONE6",
+                        format_args!("{} ({}) - zone index {} dropped", written, m.duid, zone),
+                    );
+                }
+                m.ipaddr = addr.to_string();
+            }
+            return true;
+        };
+        warn(
+            WarningKind::MappingDropped,
+            format_args!("{} on {}: {}; skipping.", written, m.iface, reason),
+        );
+        rejected.push(MigrationError::UnusableIpv6Address {
+            iface: m.iface.clone(),
+            ip: written.to_string(),
+            reason,
+        });
+        false
+    });
+    rejected
+}
+
+/// Handle mappings under `dhcpd`/`dhcpdv6` sections for interfaces that no
+/// longer exist in `<interfaces>` according to `options.orphaned_ifaces`,
+/// returning how many were found per family. Nothing is orphaned when the
+/// config has no `<interfaces>` section at all.
+pub(crate) fn resolve_orphaned_iface_mappings(
+    root: &Element,
+    mappings: &mut Vec<IscStaticMap>,
+    mappings_v6: &mut Vec<IscStaticMapV6>,
+    options: &MigrationOptions,
+) -> Result<(usize, usize)> {
+    let Some(interfaces) = get_child_ci(root, "interfaces") else {
+        return Ok((0, 0));
+    };
+    let known: Vec<&str> = interfaces
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .map(|e| e.name.as_str())
+        .collect();
+    let is_orphaned = |iface: &str| !known.iter().any(|k| k.eq_ignore_ascii_case(iface));
+    let orphaned = (
+        mappings.iter().filter(|m| is_orphaned(&m.iface)).count(),
+        mappings_v6.iter().filter(|m| is_orphaned(&m.iface)).count(),
+    );
+    if orphaned == (0, 0) {
+        return Ok(orphaned);
+    }
+
+    let (cidrs_v4, cidrs_v6) = match options.orphaned_ifaces {
+        OrphanedIfacePolicy::Fail => {
+            let orphan = |section: &str, iface: &str, ip: &str, client: &str| OrphanedMapping {
+                section: section.to_string(),
+                iface: iface.to_string(),
+                ip: ip.to_string(),
+                client: client.to_string(),
+            };
+            let mappings = mappings
+                .iter()
+                .filter(|m| is_orphaned(&m.iface))
+                .map(|m| orphan("dhcpd", &m.iface, &m.ipaddr, &m.mac))
+                .chain(
+                    mappings_v6
+                        .iter()
+                        .filter(|m| is_orphaned(&m.iface))
+                        .map(|m| orphan("dhcpdv6", &m.iface, &m.ipaddr, &m.duid)),
+                )
+                .collect();
+            return Err(MigrationError::OrphanedInterfaceMappings { mappings }.into());
+        }
+        OrphanedIfacePolicy::Skip => (HashMap::new(), HashMap::new()),
+        OrphanedIfacePolicy::SubnetMatch => (
+            extract_interface_cidrs(root)?,
+            extract_interface_cidrs_v6(root)?,
+        ),
+    };
+
+    let total = mappings.len() + mappings_v6.len();
+    mappings.retain_mut(|m| {
+        if !is_orphaned(&m.iface) {
+            return true;
+        }
+        let derived = iface_for_ip(&m.ipaddr, &cidrs_v4).ok();
+        if options.verbose {
+            match &derived {
+                Some(iface) => term::action(
+                    "ORPHAN",
+                    format_args!(
+                        "{} ({}) - {} no longer exists, moved to {}",
+                        m.ipaddr, m.mac, m.iface, iface
+                    ),
+                ),
+                None => term::action(
+                    "ORPHAN",
+                    format_args!("{} ({}) - {} no longer exists", m.ipaddr, m.mac, m.iface),
+                ),
+            }
+        }
+        derived.map(|iface| m.iface = iface).is_some()
+    });
+    mappings_v6.retain_mut(|m| {
+        if !is_orphaned(&m.iface) {
+            return true;
+        }
+        let derived = iface_for_ip_v6(&m.ipaddr, &cidrs_v6).ok();
+        if options.verbose {
+            match &derived {
+                Some(iface) => term::action(
+                    "ORPHAN6",
+                    format_args!(
+                        "{} ({}) - {} no longer exists, moved to {}",
+                        m.ipaddr, m.duid, m.iface, iface
+                    ),
+                ),
+                None => term::action(
+                    "ORPHAN6",
+                    format_args!("{} ({}) - {} no longer exists", m.ipaddr, m.duid, m.iface),
+                ),
+            }
+        }
+        derived.map(|iface| m.iface = iface).is_some()
+    });
+
+    let dropped = total - mappings.len() - mappings_v6.len();
+    let moved = orphaned.0 + orphaned.1 - dropped;
+    if moved > 0 {
+        term::warning(format_args!(
+            "Moved {} static mapping(s) from DHCP sections of removed interfaces to the interface \
+             matching their subnet.",
+            moved
+        ));
+    }
+    if dropped > 0 {
+        term::warning(format_args!(
+            "Ignoring {} static mapping(s) under DHCP sections of interfaces missing from \
+             <interfaces>.",
+            dropped
+        ));
+    }
+    Ok(orphaned)
+}
+
+/// Warn about delegated prefixes on `mappings` that `backend` leaves out:
+/// all of them when it cannot reserve prefixes, otherwise those without a
+/// prefix length. Returns how many mappings lose their prefix.
+pub(crate) fn warn_unmigrated_prefixes(
+    mappings: &[IscStaticMapV6],
+    backend: &str,
+    prefixes_supported: bool,
+    options: &MigrationOptions,
+) -> usize {
+    let mut unmigrated = 0;
+    for m in mappings {
+        let Some(prefix) = &m.prefix else {
+            continue;
+        };
+        let reason = if !prefixes_supported {
+            "prefix reservations not supported"
+        } else if m.prefix_len.is_none() {
+            "no prefix length"
+        } else {
+            continue;
+        };
+        unmigrated += 1;
+        if options.verbose {
+            term::action(
+                "NOPREFIX6",
+                format_args!("{} ({}) - {} {}", m.ipaddr, m.duid, prefix, reason),
+            );
+        }
+    }
+    if unmigrated > 0 && prefixes_supported {
+        term::warning(format_args!(
+            "{} DHCPv6 static mapping(s) have a delegated prefix without a length; their {} \
+             reservations get the address only.",
+            unmigrated, backend
+        ));
+    } else if unmigrated > 0 {
+        term::warning(format_args!(
+            "{} cannot reserve delegated prefixes; {} DHCPv6 static mapping(s) get the address \
+             reservation only.",
+            backend, unmigrated
+        ));
+    }
+    unmigrated
+}
+
+/// Rewrite mapping hostnames according to `options.hostname_policy`.
+/// With [`HostnamePolicy::Fqdn`] the hostname becomes `short.domain`; the
+/// dnsmasq and DNS host writers split it back into host and domain.
+pub(crate) fn apply_hostname_policy(
+    mappings: &mut [IscStaticMap],
+    mappings_v6: &mut [IscStaticMapV6],
+    options: &MigrationOptions,
+) -> Result<()> {
+    let domain = match options.hostname_policy {
+        HostnamePolicy::Keep => return Ok(()),
+        HostnamePolicy::Short => None,
+        HostnamePolicy::Fqdn => {
+            let domain = options
+                .hostname_domain
+                .as_deref()
+                .map(|d| d.trim().trim_matches('.'))
+                .filter(|d| !d.is_empty())
+                .ok_or_else(|| anyhow!("--hostname-policy fqdn needs a domain (--domain)"))?;
+            Some(domain)
+        }
+    };
+    let rewrite = |hostname: &mut Option<String>| {
+        let Some(name) = hostname.as_mut().filter(|h| !h.is_empty()) else {
+            return;
+        };
+        let short = name.split('.').next().unwrap_or_default().to_string();
+        *name = match domain {
+            Some(domain) => format!("{}.{}", short, domain),
+            None => short,
+        };
+    };
+    for mapping in mappings.iter_mut() {
+        rewrite(&mut mapping.hostname);
+    }
+    for mapping in mappings_v6.iter_mut() {
+        rewrite(&mut mapping.hostname);
+    }
+    Ok(())
+}
+
+/// Split a hostname built by [`apply_hostname_policy`] into its short name
+/// and domain. Other policies leave the name whole.
+pub(crate) fn split_policy_fqdn<'a>(
+    hostname: &'a str,
+    options: &MigrationOptions,
+) -> (&'a str, Option<&'a str>) {
+    match hostname.split_once('.') {
+        Some((short, domain)) if options.hostname_policy == HostnamePolicy::Fqdn => {
+            (short, Some(domain))
+        }
+        _ => (hostname, None),
+    }
+}
@@ -0,0 +1,242 @@
+//! Duplicate `uuid` attributes in the target backend sections
+//! (`--fix-duplicate-uuids`).
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use xmltree::{Element, XMLNode};
+
+use crate::subnet::{ip_in_subnet, ip_in_subnet_v6};
+use crate::term;
+use crate::xml_helpers::{
+    find_descendant_ci, find_mut_descendant_ci, get_child_ci, get_mut_child_ci,
+    set_text_preserving_comments,
+};
+use crate::{Backend, DuplicateUuid, MigrationError, MigrationOptions};
+
+/// Kea families: DHCP section, subnet tag, and whether subnets are IPv6.
+const KEA_FAMILIES: &[(&str, &str, bool)] =
+    &[("dhcp4", "subnet4", false), ("dhcp6", "subnet6", true)];
+
+/// An element given a new `uuid` because an earlier one already used it.
+struct Regenerated {
+    name: String,
+    old: String,
+    new: String,
+}
+
+/// Fail listing the duplicate `uuid`s in the sections of `options.backend`,
+/// or regenerate them when `fix_duplicate_uuids` is set. Returns how many
+/// were regenerated.
+///
+/// Run before the backend looks anything up by `uuid`: with two Kea subnets
+/// sharing one, reservations would be matched against whichever comes first.
+pub(crate) fn check_duplicate_uuids(
+    root: &mut Element,
+    options: &MigrationOptions,
+) -> Result<usize> {
+    if options.fix_duplicate_uuids {
+        return Ok(fix_duplicate_uuids(
+            root,
+            sections(&options.backend),
+            options,
+        ));
+    }
+    ensure_unique_uuids(root, &options.backend)?;
+    Ok(0)
+}
+
+/// Fail listing the duplicate `uuid`s in the sections of `backend`, without
+/// touching `root`.
+pub(crate) fn ensure_unique_uuids(root: &Element, backend: &Backend) -> Result<()> {
+    let mut duplicates = Vec::new();
+    for section in sections(backend) {
+        let Some(el) = find_descendant_ci(root, section) else {
+            continue;
+        };
+        let mut uses: Vec<(String, Vec<String>)> = Vec::new();
+        collect_uuids(el, &mut uses, &mut HashMap::new());
+        duplicates.extend(uses.into_iter().filter(|(_, names)| names.len() > 1).map(
+            |(uuid, elements)| DuplicateUuid {
+                uuid,
+                section: section.to_string(),
+                elements,
+            },
+        ));
+    }
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+    Err(MigrationError::DuplicateUuids { duplicates }.into())
+}
+
+fn sections(backend: &Backend) -> &'static [&'static str] {
+    match backend {
+        Backend::Kea => &["Kea"],
+        Backend::Dnsmasq => &["dnsmasq"],
+        Backend::Both => &["Kea", "dnsmasq"],
+    }
+}
+
+/// Every `uuid` under `el` with the names of the elements using it, in
+/// document order; `index` maps each `uuid` to its position in `uses`.
+fn collect_uuids(
+    el: &Element,
+    uses: &mut Vec<(String, Vec<String>)>,
+    index: &mut HashMap<String, usize>,
+) {
+    for child in el.children.iter().filter_map(|n| n.as_element()) {
+        if let Some(uuid) = child.attributes.get("uuid") {
+            match index.get(uuid) {
+                Some(&i) => uses[i].1.push(child.name.clone()),
+                None => {
+                    index.insert(uuid.clone(), uses.len());
+                    uses.push((uuid.clone(), vec![child.name.clone()]));
+                }
+            }
+        }
+        collect_uuids(child, uses, index);
+    }
+}
+
+fn fix_duplicate_uuids(root: &mut Element, sections: &[&str], options: &MigrationOptions) -> usize {
+    let mut fixed = 0;
+    for section in sections {
+        let Some(el) = find_mut_descendant_ci(root, section) else {
+            continue;
+        };
+        let mut regenerated = Vec::new();
+        regenerate(el, &mut HashSet::new(), &mut regenerated);
+        for r in &regenerated {
+            if options.verbose {
+                term::action("UUID", format_args!("{} {} -> {}", r.name, r.old, r.new));
+            }
+        }
+        if section.eq_ignore_ascii_case("Kea") {
+            for (dhcp, subnet_tag, v6) in KEA_FAMILIES {
+                if let Some(dhcp) = get_mut_child_ci(el, dhcp) {
+                    rewire_reservations(dhcp, subnet_tag, *v6, &regenerated, options);
+                }
+            }
+        }
+        fixed += regenerated.len();
+    }
+    if fixed > 0 {
+        term::warning(format_args!(
+            "Regenerated {} duplicate uuid(s) (--fix-duplicate-uuids).",
+            fixed
+        ));
+    }
+    fixed
+}
+
+/// Give every element whose `uuid` was already seen a new one; the first
+/// element keeps it.
+fn regenerate(el: &mut Element, seen: &mut HashSet<String>, regenerated: &mut Vec<Regenerated>) {
+    for child in el.children.iter_mut().filter_map(|n| match n {
+        XMLNode::Element(e) => Some(e),
+        _ => None,
+    }) {
+        if let Some(uuid) = child.attributes.get("uuid").cloned() {
+            if !seen.insert(uuid.clone()) {
+                let new = uuid::Uuid::new_v4().to_string();
+                child.attributes.insert("uuid".to_string(), new.clone());
+                seen.insert(new.clone());
+                regenerated.push(Regenerated {
+                    name: child.name.clone(),
+                    old: uuid,
+                    new,
+                });
+            }
+        }
+        regenerate(child, seen, regenerated);
+    }
+}
+
+/// Point reservations that referenced a duplicated subnet `uuid` at the
+/// subnet holding their address. Reservations outside every subnet that
+/// shared the `uuid` keep pointing at the first one, with a warning.
+fn rewire_reservations(
+    dhcp: &mut Element,
+    subnet_tag: &str,
+    v6: bool,
+    regenerated: &[Regenerated],
+    options: &MigrationOptions,
+) {
+    // New subnet uuid to the one it duplicated
+    let renamed: HashMap<&str, &str> = regenerated
+        .iter()
+        .filter(|r| r.name.eq_ignore_ascii_case(subnet_tag))
+        .map(|r| (r.new.as_str(), r.old.as_str()))
+        .collect();
+    if renamed.is_empty() {
+        return;
+    }
+
+    // Subnets sharing each duplicated uuid, in document order
+    let mut groups: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    if let Some(subnets) = get_child_ci(dhcp, "subnets") {
+        for subnet in subnets.children.iter().filter_map(|n| n.as_element()) {
+            if !subnet.name.eq_ignore_ascii_case(subnet_tag) {
+                continue;
+            }
+            let Some(uuid) = subnet.attributes.get("uuid") else {
+                continue;
+            };
+            let original = renamed.get(uuid.as_str()).copied().unwrap_or(uuid.as_str());
+            if !renamed.values().any(|old| *old == original) {
+                continue;
+            }
+            let cidr = get_child_ci(subnet, "subnet")
+                .and_then(|e| e.get_text())
+                .map(|t| t.trim().to_string())
+                .unwrap_or_default();
+            groups
+                .entry(original.to_string())
+                .or_default()
+                .push((uuid.clone(), cidr));
+        }
+    }
+
+    let Some(reservations) = get_mut_child_ci(dhcp, "reservations") else {
+        return;
+    };
+    for reservation in reservations.children.iter_mut().filter_map(|n| match n {
+        XMLNode::Element(e) => Some(e),
+        _ => None,
+    }) {
+        let ip = get_child_ci(reservation, "ip_address")
+            .and_then(|e| e.get_text())
+            .map(|t| t.trim().to_string())
+            .unwrap_or_default();
+        let Some(subnet_elem) = get_mut_child_ci(reservation, "subnet") else {
+            continue;
+        };
+        let current = subnet_elem
+            .get_text()
+            .map(|t| t.trim().to_string())
+            .unwrap_or_default();
+        let Some(group) = groups.get(&current) else {
+            continue;
+        };
+        let contains = |cidr: &str| {
+            if v6 {
+                ip_in_subnet_v6(&ip, cidr).unwrap_or(false)
+            } else {
+                ip_in_subnet(&ip, cidr).unwrap_or(false)
+            }
+        };
+        match group.iter().find(|(_, cidr)| contains(cidr)) {
+            Some((uuid, _)) if *uuid != current => {
+                if options.verbose {
+                    term::action("REWIRE", format_args!("{} -> subnet {}", ip, uuid));
+                }
+                set_text_preserving_comments(subnet_elem, uuid);
+            }
+            Some(_) => {}
+            None => term::warning(format_args!(
+                "Reservation {} is outside every {} sharing uuid {}; left pointing at the first.",
+                ip, subnet_tag, current
+            )),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Structural sanity checks of the input config before migrating and of
+//! the converted output before it is written.
+
+use std::io::Read;
+use xmltree::Element;
+
+use super::schema::{check_schema, SchemaSnapshot};
+use crate::xml_helpers::get_child_ci;
+use crate::{Backend, MigrationError};
+
+/// Top-level sections of which an OPNsense config.xml has at least one.
+const EXPECTED_SECTIONS: &[&str] = &[
+    "version",
+    "system",
+    "interfaces",
+    "dhcpd",
+    "dhcpdv6",
+    "Kea",
+    "dnsmasq",
+];
+
+const BACKUP_HINT: &str =
+    "Use the firewall's /conf/config.xml, or download it from System > Configuration > Backups.";
+
+/// Check that `root` looks like an OPNsense config.xml: an `<opnsense>` root
+/// element holding at least one of the usual top-level sections
+/// (`<version>`, `<system>`, `<interfaces>`, the ISC, Kea or dnsmasq DHCP
+/// sections).
+///
+/// Only the shape is checked, so the scan and convert errors about missing
+/// backends or subnets still apply to files that pass. Those errors are
+/// confusing for a file that was never a config.xml, which is what this
+/// catches first.
+pub fn validate_input(root: &Element) -> Result<(), MigrationError> {
+    if root.name.eq_ignore_ascii_case("pfsense") {
+        return Err(MigrationError::NotOpnsenseConfig {
+            reason: "the root element is <pfsense>".into(),
+            hint: "pfSense configs are not supported. Import the file on OPNsense first \
+                   (System > Configuration > Backups converts it), then use the resulting \
+                   /conf/config.xml."
+                .into(),
+        });
+    }
+    if !root.name.eq_ignore_ascii_case("opnsense") {
+        return Err(MigrationError::NotOpnsenseConfig {
+            reason: format!("the root element is <{}>, expected <opnsense>", root.name),
+            hint: BACKUP_HINT.into(),
+        });
+    }
+    if !EXPECTED_SECTIONS
+        .iter()
+        .any(|name| get_child_ci(root, name).is_some())
+    {
+        return Err(MigrationError::NotOpnsenseConfig {
+            reason: format!(
+                "<opnsense> has none of {}",
+                EXPECTED_SECTIONS
+                    .iter()
+                    .map(|name| format!("<{}>", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            hint: format!(
+                "The file may be truncated or a partial export. {}",
+                BACKUP_HINT
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Check converted `output` before it replaces a file: it must parse back
+/// into a config that passes [`validate_input`], the Kea, dnsmasq and
+/// Unbound elements added since `before` must match the bundled OPNsense
+/// schema, and for a dnsmasq `backend` the added hosts must pass the same
+/// checks as during the convert.
+///
+/// `before` is the config the output was converted from, or the target it
+/// was merged into.
+#[cfg_attr(not(feature = "dnsmasq"), allow(unused_variables))]
+pub fn validate_output<R: Read>(
+    before: &Element,
+    output: R,
+    backend: &Backend,
+) -> Result<(), MigrationError> {
+    let invalid = |reason: String| MigrationError::InvalidOutput { reason };
+    let root = Element::parse(output).map_err(|e| invalid(format!("it does not parse ({})", e)))?;
+    validate_input(&root).map_err(|e| match e {
+        MigrationError::NotOpnsenseConfig { reason, .. } => invalid(reason),
+        e => invalid(e.to_string()),
+    })?;
+    check_schema(&root, &SchemaSnapshot::take(before)).map_err(|e| invalid(e.to_string()))?;
+    #[cfg(feature = "dnsmasq")]
+    if matches!(backend, Backend::Dnsmasq | Backend::Both) {
+        super::dnsmasq::validate_dnsmasq_hosts(&root, &super::dnsmasq::dnsmasq_host_uuids(before))
+            .map_err(|e| invalid(e.to_string()))?;
+    }
+    Ok(())
+}
@@ -1,139 +1,87 @@
 use anyhow::{anyhow, Result};
-use xmltree::{Element, XMLNode};
+use xmltree::Element;
 
+use crate::migrate::first_domain;
+use crate::model::{DnsmasqHostNode, DnsmasqOptionNode, DnsmasqRangeNode};
 use crate::xml_helpers::{find_mut_descendant_ci, get_child_ci};
 use crate::{IscStaticMap, IscStaticMapV6};
 
-/// Create a dnsmasq host XML element from an ISC static mapping.
+/// Host fields kept by [`strip_empty_elements`] even when empty.
+pub const DNSMASQ_HOST_REQUIRED: &[&str] = &["host", "ip"];
+/// Range fields kept by [`strip_empty_elements`] even when empty.
+pub const DNSMASQ_RANGE_REQUIRED: &[&str] = &["interface", "start_addr"];
+/// Option fields kept by [`strip_empty_elements`] even when empty; both
+/// `option` and `option6` are part of the duplicate-detection key.
+pub const DNSMASQ_OPTION_REQUIRED: &[&str] = &["type", "option", "option6", "interface", "value"];
+
+/// Remove empty child elements from a created dnsmasq element, keeping the
+/// `required` ones (`--minimal-elements`).
+pub fn strip_empty_elements(elem: &mut Element, required: &[&str]) {
+    elem.children.retain(|node| {
+        let Some(child) = node.as_element() else {
+            return true;
+        };
+        required.iter().any(|r| child.name.eq_ignore_ascii_case(r))
+            || child.get_text().is_some_and(|text| !text.is_empty())
+            || child.children.iter().any(|n| n.as_element().is_some())
+    });
+}
+
+/// Create a dnsmasq host from an ISC static mapping.
 ///
 /// dnsmasq hosts are flat under `<dnsmasq><hosts>` with no subnet association.
-pub fn create_dnsmasq_host_element(mapping: &IscStaticMap) -> Element {
-    let mut host = Element::new("hosts");
-    host.attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    let mut hwaddr = Element::new("hwaddr");
-    hwaddr.children.push(XMLNode::Text(mapping.mac.clone()));
-    host.children.push(XMLNode::Element(hwaddr));
-
-    let mut ip = Element::new("ip");
-    ip.children.push(XMLNode::Text(mapping.ipaddr.clone()));
-    host.children.push(XMLNode::Element(ip));
-
-    // hostname
-    let hostname_text = mapping
-        .hostname
-        .as_ref()
-        .or(mapping.cid.as_ref())
-        .cloned()
-        .unwrap_or_default();
-    let mut hostname = Element::new("host");
-    hostname.children.push(XMLNode::Text(hostname_text));
-    host.children.push(XMLNode::Element(hostname));
-
-    // client_id
-    if let Some(cid) = &mapping.cid {
-        let mut client_id = Element::new("client_id");
-        client_id.children.push(XMLNode::Text(cid.clone()));
-        host.children.push(XMLNode::Element(client_id));
-    }
-
-    // description
-    if let Some(d) = &mapping.descr {
-        let mut descr = Element::new("descr");
-        descr.children.push(XMLNode::Text(d.clone()));
-        host.children.push(XMLNode::Element(descr));
+pub fn create_dnsmasq_host(mapping: &IscStaticMap) -> DnsmasqHostNode {
+    DnsmasqHostNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        host: mapping
+            .hostname
+            .as_ref()
+            .or(mapping.cid.as_ref())
+            .cloned()
+            .unwrap_or_default(),
+        ip: mapping.ipaddr.clone(),
+        client_id: mapping.cid.clone(),
+        hwaddr: mapping.mac.clone(),
+        descr: mapping.descr.clone(),
+        ..Default::default()
     }
-
-    // Defaults for fields dnsmasq expects
-    for (tag, default) in [
-        ("domain", ""),
-        ("local", "0"),
-        ("ignore", "0"),
-        ("lease_time", ""),
-        ("cnames", ""),
-        ("set_tag", ""),
-        ("comments", ""),
-        ("aliases", ""),
-    ] {
-        let mut elem = Element::new(tag);
-        elem.children.push(XMLNode::Text(default.to_string()));
-        host.children.push(XMLNode::Element(elem));
-    }
-
-    host
 }
 
-/// Create a dnsmasq host XML element from an ISC DHCPv6 static mapping.
-pub fn create_dnsmasq_host_element_v6(mapping: &IscStaticMapV6) -> Element {
-    let mut host = Element::new("hosts");
-    host.attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    // hostname
-    let hostname_text = mapping.hostname.clone().unwrap_or_default();
-    let mut hostname = Element::new("host");
-    hostname.children.push(XMLNode::Text(hostname_text));
-    host.children.push(XMLNode::Element(hostname));
-
-    // domain (best-effort: first entry from domain search list)
-    let mut domain_elem = Element::new("domain");
-    let domain_value = mapping
-        .domain_search
-        .as_deref()
-        .map(first_domain)
-        .unwrap_or_default();
-    domain_elem.children.push(XMLNode::Text(domain_value));
-    host.children.push(XMLNode::Element(domain_elem));
-
-    let mut local = Element::new("local");
-    local.children.push(XMLNode::Text("0".to_string()));
-    host.children.push(XMLNode::Element(local));
-
-    let mut ip = Element::new("ip");
-    ip.children.push(XMLNode::Text(mapping.ipaddr.clone()));
-    host.children.push(XMLNode::Element(ip));
-
-    // client_id (DUID)
-    let mut client_id = Element::new("client_id");
-    client_id.children.push(XMLNode::Text(mapping.duid.clone()));
-    host.children.push(XMLNode::Element(client_id));
-
-    // hwaddr (not available for DHCPv6 mappings)
-    let mut hwaddr = Element::new("hwaddr");
-    hwaddr.children.push(XMLNode::Text("".to_string()));
-    host.children.push(XMLNode::Element(hwaddr));
-
-    // description
-    if let Some(d) = &mapping.descr {
-        let mut descr = Element::new("descr");
-        descr.children.push(XMLNode::Text(d.clone()));
-        host.children.push(XMLNode::Element(descr));
-    }
-
-    // Defaults for fields dnsmasq expects
-    for (tag, default) in [
-        ("lease_time", ""),
-        ("cnames", ""),
-        ("ignore", "0"),
-        ("set_tag", ""),
-        ("comments", ""),
-        ("aliases", ""),
-    ] {
-        let mut elem = Element::new(tag);
-        elem.children.push(XMLNode::Text(default.to_string()));
-        host.children.push(XMLNode::Element(elem));
+/// Create a dnsmasq host from an ISC DHCPv6 static mapping, identified by
+/// its DUID in `client_id`.
+pub fn create_dnsmasq_host_v6(mapping: &IscStaticMapV6) -> DnsmasqHostNode {
+    DnsmasqHostNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        host: mapping.hostname.clone().unwrap_or_default(),
+        // best-effort: first entry from domain search list
+        domain: mapping
+            .domain_search
+            .as_deref()
+            .map(first_domain)
+            .unwrap_or_default(),
+        ip: mapping.ipaddr.clone(),
+        client_id: Some(mapping.duid.clone()),
+        descr: mapping.descr.clone(),
+        ..Default::default()
     }
-
-    host
 }
 
-fn first_domain(domain_search: &str) -> String {
-    domain_search
-        .split(|c: char| c.is_whitespace() || c == ',')
-        .find(|s| !s.is_empty())
-        .unwrap_or("")
-        .to_string()
+/// Create a DNS-only dnsmasq host (no hardware address or client id).
+pub fn create_dnsmasq_dns_host(
+    hostname: &str,
+    domain: &str,
+    ip: &str,
+    description: &str,
+) -> DnsmasqHostNode {
+    DnsmasqHostNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        host: hostname.to_string(),
+        domain: domain.to_string(),
+        ip: ip.to_string(),
+        client_id: Some(String::new()),
+        descr: Some(description.to_string()),
+        ..Default::default()
+    }
 }
 
 /// Get the `<dnsmasq>` node, returning an error if it doesn't exist.
@@ -165,117 +113,65 @@ pub fn get_dnsmasq_node(root: &mut Element) -> Result<&mut Element> {
     find_mut_descendant_ci(root, "dnsmasq").ok_or_else(|| anyhow!("Failed to access dnsmasq node"))
 }
 
-/// Create a dnsmasq DHCP range element for IPv4.
-pub fn create_dnsmasq_range_element_v4(
+/// Create a dnsmasq DHCP range for IPv4.
+pub fn create_dnsmasq_range_v4(
     iface: &str,
     start: &str,
     end: &str,
     subnet_mask: &str,
-) -> Element {
-    let mut range = Element::new("dhcp_ranges");
-    range
-        .attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    let mut interface = Element::new("interface");
-    interface.children.push(XMLNode::Text(iface.to_string()));
-    range.children.push(XMLNode::Element(interface));
-
-    for (tag, value) in [
-        ("set_tag", ""),
-        ("start_addr", start),
-        ("end_addr", end),
-        ("subnet_mask", subnet_mask),
-        ("constructor", ""),
-        ("mode", ""),
-        ("lease_time", ""),
-        ("domain_type", "range"),
-        ("domain", ""),
-        ("nosync", "0"),
-        ("ra_mode", ""),
-        ("ra_priority", ""),
-        ("ra_mtu", ""),
-        ("ra_interval", ""),
-        ("ra_router_lifetime", ""),
-        ("description", ""),
-    ] {
-        let mut elem = Element::new(tag);
-        elem.children.push(XMLNode::Text(value.to_string()));
-        range.children.push(XMLNode::Element(elem));
+    description: &str,
+) -> DnsmasqRangeNode {
+    DnsmasqRangeNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        interface: iface.to_string(),
+        start_addr: start.to_string(),
+        end_addr: end.to_string(),
+        subnet_mask: subnet_mask.to_string(),
+        description: description.to_string(),
+        ..Default::default()
     }
-
-    range
 }
 
-/// Create a dnsmasq DHCP range element for IPv6.
-pub fn create_dnsmasq_range_element_v6(
+/// Create a dnsmasq DHCP range for IPv6.
+///
+/// With a `constructor` interface, `start` and `end` are host suffixes
+/// (e.g. `::1000`) applied to that interface's prefix.
+pub fn create_dnsmasq_range_v6(
     iface: &str,
     start: &str,
     end: &str,
     prefix_len: &str,
-) -> Element {
-    let mut range = Element::new("dhcp_ranges");
-    range
-        .attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    let mut interface = Element::new("interface");
-    interface.children.push(XMLNode::Text(iface.to_string()));
-    range.children.push(XMLNode::Element(interface));
-
-    for (tag, value) in [
-        ("set_tag", ""),
-        ("start_addr", start),
-        ("end_addr", end),
-        ("subnet_mask", ""),
-        ("constructor", ""),
-        ("mode", ""),
-        ("prefix_len", prefix_len),
-        ("lease_time", ""),
-        ("domain_type", "range"),
-        ("domain", ""),
-        ("nosync", "0"),
-        ("ra_mode", ""),
-        ("ra_priority", ""),
-        ("ra_mtu", ""),
-        ("ra_interval", ""),
-        ("ra_router_lifetime", ""),
-        ("description", ""),
-    ] {
-        let mut elem = Element::new(tag);
-        elem.children.push(XMLNode::Text(value.to_string()));
-        range.children.push(XMLNode::Element(elem));
+    mode: &str,
+    constructor: &str,
+    description: &str,
+) -> DnsmasqRangeNode {
+    DnsmasqRangeNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        interface: iface.to_string(),
+        start_addr: start.to_string(),
+        end_addr: end.to_string(),
+        constructor: constructor.to_string(),
+        mode: mode.to_string(),
+        prefix_len: prefix_len.to_string(),
+        description: description.to_string(),
+        ..Default::default()
     }
-
-    range
 }
 
-/// Create a dnsmasq DHCP option element (type=set).
-pub fn create_dnsmasq_option_element(
+/// Create a dnsmasq DHCP option (type=set).
+pub fn create_dnsmasq_option(
     iface: &str,
     option: &str,
     option6: &str,
     value: &str,
-) -> Element {
-    let mut opt = Element::new("dhcp_options");
-    opt.attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    for (tag, val) in [
-        ("type", "set"),
-        ("option", option),
-        ("option6", option6),
-        ("interface", iface),
-        ("tag", ""),
-        ("set_tag", ""),
-        ("value", value),
-        ("force", ""),
-        ("description", ""),
-    ] {
-        let mut elem = Element::new(tag);
-        elem.children.push(XMLNode::Text(val.to_string()));
-        opt.children.push(XMLNode::Element(elem));
+) -> DnsmasqOptionNode {
+    DnsmasqOptionNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        option_type: "set".to_string(),
+        option: option.to_string(),
+        option6: option6.to_string(),
+        interface: iface.to_string(),
+        value: value.to_string(),
+        ..Default::default()
     }
-
-    opt
 }
@@ -0,0 +1,55 @@
+use anyhow::{anyhow, Result};
+use xmltree::{Element, XMLNode};
+
+use crate::xml_helpers::{find_descendant_ci, find_mut_descendant_ci, get_mut_child_ci};
+
+/// Create an Unbound host override element (`<unboundplus><hosts><host>`).
+///
+/// `rr` is `A` or `AAAA` depending on the address family of `server`.
+pub fn create_unbound_host_element(
+    hostname: &str,
+    domain: &str,
+    rr: &str,
+    server: &str,
+    description: &str,
+) -> Element {
+    let mut host = Element::new("host");
+    host.attributes
+        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
+
+    for (tag, value) in [
+        ("enabled", "1"),
+        ("hostname", hostname),
+        ("domain", domain),
+        ("rr", rr),
+        ("mxprio", ""),
+        ("mx", ""),
+        ("server", server),
+        ("description", description),
+    ] {
+        let mut elem = Element::new(tag);
+        elem.children.push(XMLNode::Text(value.to_string()));
+        host.children.push(XMLNode::Element(elem));
+    }
+
+    host
+}
+
+/// Get the Unbound `<hosts>` override container, creating it under
+/// `<unboundplus>` when missing.
+pub fn get_unbound_hosts_node(root: &mut Element) -> Result<&mut Element> {
+    if find_descendant_ci(root, "unboundplus").is_none() {
+        return Err(anyhow!(
+            "Unbound not configured in config.xml. Open Services > Unbound DNS once to create \
+             its settings, then retry."
+        ));
+    }
+    let unbound = find_mut_descendant_ci(root, "unboundplus")
+        .ok_or_else(|| anyhow!("Failed to access Unbound node"))?;
+    if get_mut_child_ci(unbound, "hosts").is_none() {
+        unbound
+            .children
+            .push(XMLNode::Element(Element::new("hosts")));
+    }
+    get_mut_child_ci(unbound, "hosts").ok_or_else(|| anyhow!("Failed to access Unbound hosts"))
+}
@@ -1,48 +1,31 @@
 use anyhow::{anyhow, Result};
 use xmltree::{Element, XMLNode};
 
+use crate::migrate::domain_search_csv;
+use crate::model::ReservationNode;
 use crate::xml_helpers::{find_mut_descendant_ci, get_child_ci, get_mut_child_ci};
 use crate::{IscStaticMap, MigrationError};
 
-/// Create a reservation XML element from an ISC mapping
-pub fn create_reservation_element(mapping: &IscStaticMap, subnet_uuid: &str) -> Element {
-    let mut reservation = Element::new("reservation");
-    reservation
-        .attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    let mut subnet_elem = Element::new("subnet");
-    subnet_elem
-        .children
-        .push(XMLNode::Text(subnet_uuid.to_string()));
-    reservation.children.push(XMLNode::Element(subnet_elem));
-
-    let mut ip_elem = Element::new("ip_address");
-    ip_elem.children.push(XMLNode::Text(mapping.ipaddr.clone()));
-    reservation.children.push(XMLNode::Element(ip_elem));
-
-    let mut hw_elem = Element::new("hw_address");
-    hw_elem.children.push(XMLNode::Text(mapping.mac.clone()));
-    reservation.children.push(XMLNode::Element(hw_elem));
-
-    // hostname (prefer hostname over cid)
-    if let Some(hostname) = &mapping.hostname {
-        let mut hostname_elem = Element::new("hostname");
-        hostname_elem.children.push(XMLNode::Text(hostname.clone()));
-        reservation.children.push(XMLNode::Element(hostname_elem));
-    } else if let Some(cid) = &mapping.cid {
-        let mut hostname_elem = Element::new("hostname");
-        hostname_elem.children.push(XMLNode::Text(cid.clone()));
-        reservation.children.push(XMLNode::Element(hostname_elem));
+/// Create a reservation from an ISC mapping
+pub fn create_reservation(mapping: &IscStaticMap, subnet_uuid: &str) -> ReservationNode {
+    ReservationNode {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        subnet: subnet_uuid.to_string(),
+        ip_address: mapping.ipaddr.clone(),
+        hw_address: mapping.mac.clone(),
+        // prefer hostname over cid
+        hostname: mapping.hostname.clone().or_else(|| mapping.cid.clone()),
+        // client_id alongside hw_address when the mapping has both
+        client_id: mapping.cid.clone(),
+        description: mapping.descr.clone(),
+        domain_name: mapping.domain.clone().unwrap_or_default(),
+        domain_search: mapping
+            .domain_search
+            .as_deref()
+            .and_then(domain_search_csv)
+            .unwrap_or_default(),
+        ..Default::default()
     }
-
-    if let Some(descr) = &mapping.descr {
-        let mut descr_elem = Element::new("description");
-        descr_elem.children.push(XMLNode::Text(descr.clone()));
-        reservation.children.push(XMLNode::Element(descr_elem));
-    }
-
-    reservation
 }
 
 /// Get the <Kea>/<kea><dhcp4><reservations> node (case-insensitive)
@@ -1,51 +1,22 @@
 use anyhow::{anyhow, Result};
 use xmltree::{Element, XMLNode};
 
+use crate::model::ReservationV6Node;
 use crate::xml_helpers::{find_mut_descendant_ci, get_child_ci, get_mut_child_ci};
 use crate::{IscStaticMapV6, MigrationError};
 
-/// Create a DHCPv6 reservation XML element from an ISC mapping
-pub fn create_reservation_element_v6(mapping: &IscStaticMapV6, subnet_uuid: &str) -> Element {
-    let mut reservation = Element::new("reservation");
-    reservation
-        .attributes
-        .insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
-
-    let mut subnet_elem = Element::new("subnet");
-    subnet_elem
-        .children
-        .push(XMLNode::Text(subnet_uuid.to_string()));
-    reservation.children.push(XMLNode::Element(subnet_elem));
-
-    let mut ip_elem = Element::new("ip_address");
-    ip_elem.children.push(XMLNode::Text(mapping.ipaddr.clone()));
-    reservation.children.push(XMLNode::Element(ip_elem));
-
-    let mut duid_elem = Element::new("duid");
-    duid_elem.children.push(XMLNode::Text(mapping.duid.clone()));
-    reservation.children.push(XMLNode::Element(duid_elem));
-
-    if let Some(hostname) = &mapping.hostname {
-        let mut hostname_elem = Element::new("hostname");
-        hostname_elem.children.push(XMLNode::Text(hostname.clone()));
-        reservation.children.push(XMLNode::Element(hostname_elem));
+/// Create a DHCPv6 reservation from an ISC mapping
+pub fn create_reservation_v6(mapping: &IscStaticMapV6, subnet_uuid: &str) -> ReservationV6Node {
+    ReservationV6Node {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        subnet: subnet_uuid.to_string(),
+        ip_address: mapping.ipaddr.clone(),
+        duid: mapping.duid.clone(),
+        prefixes: mapping.delegated_prefix(),
+        hostname: mapping.hostname.clone(),
+        domain_search: mapping.domain_search.clone(),
+        description: mapping.descr.clone(),
     }
-
-    if let Some(domain_search) = &mapping.domain_search {
-        let mut domain_elem = Element::new("domain_search");
-        domain_elem
-            .children
-            .push(XMLNode::Text(domain_search.clone()));
-        reservation.children.push(XMLNode::Element(domain_elem));
-    }
-
-    if let Some(descr) = &mapping.descr {
-        let mut descr_elem = Element::new("description");
-        descr_elem.children.push(XMLNode::Text(descr.clone()));
-        reservation.children.push(XMLNode::Element(descr_elem));
-    }
-
-    reservation
 }
 
 /// Get the <Kea>/<kea><dhcp6><reservations> node (case-insensitive)
@@ -0,0 +1,344 @@
+use xmltree::Element;
+
+use super::{is_named, node_element, push_text, uuid_of};
+use crate::xml_helpers::child_text;
+
+/// `<dnsmasq><hosts>`: a DHCP reservation, a DNS-only host, or both
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DnsmasqHostNode {
+    pub uuid: String,
+    pub host: String,
+    pub domain: String,
+    pub local: bool,
+    pub ip: String,
+    pub cnames: String,
+    /// Client identifier, or the DUID for DHCPv6 hosts; left out when unset
+    pub client_id: Option<String>,
+    pub hwaddr: String,
+    pub lease_time: String,
+    pub ignore: bool,
+    pub set_tag: String,
+    /// Left out when unset
+    pub descr: Option<String>,
+    pub comments: String,
+    pub aliases: String,
+}
+
+impl DnsmasqHostNode {
+    /// Parse a `hosts` element; missing fields are left empty.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "hosts") {
+            return None;
+        }
+        let text = |tag: &str| child_text(el, tag).unwrap_or_default();
+        Some(Self {
+            uuid: uuid_of(el),
+            host: text("host"),
+            domain: text("domain"),
+            local: text("local") == "1",
+            ip: text("ip"),
+            cnames: text("cnames"),
+            client_id: child_text(el, "client_id"),
+            hwaddr: text("hwaddr"),
+            lease_time: text("lease_time"),
+            ignore: text("ignore") == "1",
+            set_tag: text("set_tag"),
+            descr: child_text(el, "descr"),
+            comments: text("comments"),
+            aliases: text("aliases"),
+        })
+    }
+
+    /// Serialize with the fields in `layout` order, leaving out unset
+    /// optional fields.
+    pub fn to_element(&self, layout: HostLayout) -> Element {
+        let fields = [
+            ("host", Some(self.host.as_str())),
+            ("domain", Some(&self.domain)),
+            ("local", Some(flag(self.local))),
+            ("ip", Some(&self.ip)),
+            ("cnames", Some(&self.cnames)),
+            ("client_id", self.client_id.as_deref()),
+            ("hwaddr", Some(&self.hwaddr)),
+            ("lease_time", Some(&self.lease_time)),
+            ("ignore", Some(flag(self.ignore))),
+            ("set_tag", Some(&self.set_tag)),
+            ("descr", self.descr.as_deref()),
+            ("comments", Some(&self.comments)),
+            ("aliases", Some(&self.aliases)),
+        ];
+        let mut el = node_element("hosts", &self.uuid);
+        for name in layout.fields() {
+            if let Some((_, Some(value))) = fields.iter().find(|(field, _)| field == name) {
+                push_text(&mut el, name, value);
+            }
+        }
+        el
+    }
+}
+
+/// Order of the fields in a created `<hosts>` element. dnsmasq reads them in
+/// any order; each kind of host keeps the order it has always been written
+/// in, so converting again gives the same file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HostLayout {
+    /// A DHCPv4 reservation
+    Dhcp4,
+    /// A DHCPv6 reservation
+    Dhcp6,
+    /// A DNS-only host, in OPNsense model order
+    Dns,
+}
+
+impl HostLayout {
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            HostLayout::Dhcp4 => &[
+                "hwaddr",
+                "ip",
+                "host",
+                "client_id",
+                "descr",
+                "domain",
+                "local",
+                "ignore",
+                "lease_time",
+                "cnames",
+                "set_tag",
+                "comments",
+                "aliases",
+            ],
+            HostLayout::Dhcp6 => &[
+                "host",
+                "domain",
+                "local",
+                "ip",
+                "client_id",
+                "hwaddr",
+                "descr",
+                "lease_time",
+                "cnames",
+                "ignore",
+                "set_tag",
+                "comments",
+                "aliases",
+            ],
+            HostLayout::Dns => &[
+                "host",
+                "domain",
+                "local",
+                "ip",
+                "cnames",
+                "client_id",
+                "hwaddr",
+                "lease_time",
+                "ignore",
+                "set_tag",
+                "descr",
+                "comments",
+                "aliases",
+            ],
+        }
+    }
+}
+
+/// `<dnsmasq><dhcp_tags>`: a tag name hosts set and options match on, both
+/// by the tag's `uuid`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DnsmasqTagNode {
+    pub uuid: String,
+    pub tag: String,
+}
+
+impl DnsmasqTagNode {
+    /// Parse a `dhcp_tags` element; a missing name is left empty.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "dhcp_tags") {
+            return None;
+        }
+        Some(Self {
+            uuid: uuid_of(el),
+            tag: child_text(el, "tag").unwrap_or_default(),
+        })
+    }
+
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("dhcp_tags", &self.uuid);
+        push_text(&mut el, "tag", &self.tag);
+        el
+    }
+}
+
+/// `<dnsmasq><dhcp_ranges>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DnsmasqRangeNode {
+    pub uuid: String,
+    pub interface: String,
+    pub start_addr: String,
+    pub end_addr: String,
+    /// IPv4 only
+    pub subnet_mask: String,
+    /// Interface whose prefix IPv6 host suffixes apply to
+    pub constructor: String,
+    /// IPv6 only, e.g. `ra-stateless`
+    pub mode: String,
+    /// IPv6 only; left out when empty
+    pub prefix_len: String,
+    pub description: String,
+}
+
+impl DnsmasqRangeNode {
+    /// Parse a `dhcp_ranges` element; missing fields are left empty.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "dhcp_ranges") {
+            return None;
+        }
+        let text = |tag: &str| child_text(el, tag).unwrap_or_default();
+        Some(Self {
+            uuid: uuid_of(el),
+            interface: text("interface"),
+            start_addr: text("start_addr"),
+            end_addr: text("end_addr"),
+            subnet_mask: text("subnet_mask"),
+            constructor: text("constructor"),
+            mode: text("mode"),
+            prefix_len: text("prefix_len"),
+            description: text("description"),
+        })
+    }
+
+    /// Serialize with a `range` domain type and router advertisement
+    /// settings left at their defaults.
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("dhcp_ranges", &self.uuid);
+        for (tag, value) in [
+            ("interface", self.interface.as_str()),
+            ("set_tag", ""),
+            ("start_addr", &self.start_addr),
+            ("end_addr", &self.end_addr),
+            ("subnet_mask", &self.subnet_mask),
+            ("constructor", &self.constructor),
+            ("mode", &self.mode),
+            ("lease_time", ""),
+            ("domain_type", "range"),
+            ("domain", ""),
+            ("nosync", "0"),
+            ("ra_mode", ""),
+            ("ra_priority", ""),
+            ("ra_mtu", ""),
+            ("ra_interval", ""),
+            ("ra_router_lifetime", ""),
+            ("description", &self.description),
+        ] {
+            push_text(&mut el, tag, value);
+            // IPv4 ranges have no prefix length field
+            if tag == "mode" && !self.prefix_len.is_empty() {
+                push_text(&mut el, "prefix_len", &self.prefix_len);
+            }
+        }
+        el
+    }
+}
+
+/// `<dnsmasq><dhcp_options>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DnsmasqOptionNode {
+    pub uuid: String,
+    /// `set` or `match`
+    pub option_type: String,
+    pub option: String,
+    pub option6: String,
+    pub interface: String,
+    pub tag: String,
+    pub set_tag: String,
+    pub value: String,
+    pub force: String,
+    pub description: String,
+}
+
+impl DnsmasqOptionNode {
+    /// Parse a `dhcp_options` element; missing fields are left empty.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "dhcp_options") {
+            return None;
+        }
+        let text = |tag: &str| child_text(el, tag).unwrap_or_default();
+        Some(Self {
+            uuid: uuid_of(el),
+            option_type: text("type"),
+            option: text("option"),
+            option6: text("option6"),
+            interface: text("interface"),
+            tag: text("tag"),
+            set_tag: text("set_tag"),
+            value: text("value"),
+            force: text("force"),
+            description: text("description"),
+        })
+    }
+
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("dhcp_options", &self.uuid);
+        push_text(&mut el, "type", &self.option_type);
+        push_text(&mut el, "option", &self.option);
+        push_text(&mut el, "option6", &self.option6);
+        push_text(&mut el, "interface", &self.interface);
+        push_text(&mut el, "tag", &self.tag);
+        push_text(&mut el, "set_tag", &self.set_tag);
+        push_text(&mut el, "value", &self.value);
+        push_text(&mut el, "force", &self.force);
+        push_text(&mut el, "description", &self.description);
+        el
+    }
+}
+
+/// `<dnsmasq><dhcp_boot>`: the `dhcp-boot` file, TFTP server name and
+/// address for clients on an interface or with a tag
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DnsmasqBootNode {
+    pub uuid: String,
+    pub interface: String,
+    pub tag: String,
+    pub filename: String,
+    pub servername: String,
+    pub address: String,
+    pub description: String,
+}
+
+impl DnsmasqBootNode {
+    /// Parse a `dhcp_boot` element; missing fields are left empty.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "dhcp_boot") {
+            return None;
+        }
+        let text = |tag: &str| child_text(el, tag).unwrap_or_default();
+        Some(Self {
+            uuid: uuid_of(el),
+            interface: text("interface"),
+            tag: text("tag"),
+            filename: text("filename"),
+            servername: text("servername"),
+            address: text("address"),
+            description: text("description"),
+        })
+    }
+
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("dhcp_boot", &self.uuid);
+        push_text(&mut el, "interface", &self.interface);
+        push_text(&mut el, "tag", &self.tag);
+        push_text(&mut el, "filename", &self.filename);
+        push_text(&mut el, "servername", &self.servername);
+        push_text(&mut el, "address", &self.address);
+        push_text(&mut el, "description", &self.description);
+        el
+    }
+}
+
+fn flag(value: bool) -> &'static str {
+    if value {
+        "1"
+    } else {
+        "0"
+    }
+}
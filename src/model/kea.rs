@@ -0,0 +1,272 @@
+use xmltree::Element;
+#[cfg(feature = "kea")]
+use xmltree::XMLNode;
+
+use super::{is_named, uuid_of};
+#[cfg(feature = "kea")]
+use super::{node_element, push_opt_text, push_text};
+use crate::xml_helpers::{child_text, get_child_ci};
+
+/// `<option_data>` fields of a `subnet4` or `reservation` created from the
+/// GUI
+#[cfg(feature = "kea")]
+const SUBNET4_OPTION_DATA: &[&str] = &[
+    "domain_name_servers",
+    "domain_search",
+    "routers",
+    "static_routes",
+    "domain_name",
+    "ntp_servers",
+    "time_servers",
+    "tftp_server_name",
+    "boot_file_name",
+];
+
+/// `<option_data>` fields of a `subnet6` created from the GUI
+#[cfg(feature = "kea")]
+const SUBNET6_OPTION_DATA: &[&str] = &["dns_servers", "domain_search"];
+
+/// `<Kea><dhcp4><subnets><subnet4>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Subnet4Node {
+    pub uuid: String,
+    pub subnet: String,
+    pub interface: String,
+    /// `from-to` address ranges
+    pub pools: Vec<String>,
+    pub description: String,
+    pub next_server: String,
+    /// `<option_data>` option 66
+    pub tftp_server_name: String,
+    /// `<option_data>` option 67
+    pub boot_file_name: String,
+}
+
+impl Subnet4Node {
+    /// Parse a `subnet4` element; `None` without a `uuid` or `<subnet>`,
+    /// since reservations could not reference it.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "subnet4") {
+            return None;
+        }
+        Some(Self {
+            uuid: el.attributes.get("uuid")?.clone(),
+            subnet: child_text(el, "subnet")?,
+            interface: child_text(el, "interface").unwrap_or_default(),
+            pools: parse_pools(el),
+            description: child_text(el, "description").unwrap_or_default(),
+            next_server: child_text(el, "next_server").unwrap_or_default(),
+            tftp_server_name: option_data_text(el, "tftp_server_name"),
+            boot_file_name: option_data_text(el, "boot_file_name"),
+        })
+    }
+
+    /// Serialize with `<option_data>` empty apart from the boot settings,
+    /// option autocollection and client-id matching on, matching subnets
+    /// created from the GUI.
+    #[cfg(feature = "kea")]
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("subnet4", &self.uuid);
+        push_text(&mut el, "subnet", &self.subnet);
+        push_text(&mut el, "interface", &self.interface);
+        push_text(&mut el, "next_server", &self.next_server);
+        push_text(&mut el, "option_data_autocollect", "1");
+        push_option_data(
+            &mut el,
+            SUBNET4_OPTION_DATA,
+            &[
+                ("tftp_server_name", &self.tftp_server_name),
+                ("boot_file_name", &self.boot_file_name),
+            ],
+        );
+        push_text(&mut el, "match-client-id", "1");
+        push_text(&mut el, "pools", &self.pools.join(","));
+        push_text(&mut el, "description", &self.description);
+        el
+    }
+}
+
+/// `<Kea><dhcp6><subnets><subnet6>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Subnet6Node {
+    pub uuid: String,
+    pub subnet: String,
+    pub interface: String,
+    /// `from-to` address ranges
+    pub pools: Vec<String>,
+    pub description: String,
+}
+
+impl Subnet6Node {
+    /// Parse a `subnet6` element; `None` without a `uuid` or `<subnet>`.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "subnet6") {
+            return None;
+        }
+        Some(Self {
+            uuid: el.attributes.get("uuid")?.clone(),
+            subnet: child_text(el, "subnet")?,
+            interface: child_text(el, "interface").unwrap_or_default(),
+            pools: parse_pools(el),
+            description: child_text(el, "description").unwrap_or_default(),
+        })
+    }
+
+    /// Serialize with empty `<option_data>`.
+    #[cfg(feature = "kea")]
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("subnet6", &self.uuid);
+        push_text(&mut el, "subnet", &self.subnet);
+        push_text(&mut el, "interface", &self.interface);
+        push_option_data(&mut el, SUBNET6_OPTION_DATA, &[]);
+        push_text(&mut el, "pools", &self.pools.join(","));
+        push_text(&mut el, "description", &self.description);
+        el
+    }
+}
+
+/// `<Kea><dhcp4><reservations><reservation>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ReservationNode {
+    pub uuid: String,
+    /// `uuid` of the `subnet4` holding `ip_address`
+    pub subnet: String,
+    pub ip_address: String,
+    pub hw_address: String,
+    pub hostname: Option<String>,
+    pub client_id: Option<String>,
+    pub description: Option<String>,
+    /// `<option_data>` option 6, overriding the subnet's
+    pub domain_name_servers: String,
+    /// `<option_data>` option 3, overriding the subnet's
+    pub routers: String,
+    /// `<option_data>` option 15, overriding the subnet's
+    pub domain_name: String,
+    /// `<option_data>` option 119, overriding the subnet's
+    pub domain_search: String,
+}
+
+impl ReservationNode {
+    /// Parse a `reservation` element; missing fields are left empty.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "reservation") {
+            return None;
+        }
+        Some(Self {
+            uuid: uuid_of(el),
+            subnet: child_text(el, "subnet").unwrap_or_default(),
+            ip_address: child_text(el, "ip_address").unwrap_or_default(),
+            hw_address: child_text(el, "hw_address").unwrap_or_default(),
+            hostname: child_text(el, "hostname"),
+            client_id: child_text(el, "client_id"),
+            description: child_text(el, "description"),
+            domain_name_servers: option_data_text(el, "domain_name_servers"),
+            routers: option_data_text(el, "routers"),
+            domain_name: option_data_text(el, "domain_name"),
+            domain_search: option_data_text(el, "domain_search"),
+        })
+    }
+
+    /// Serialize, leaving out unset optional fields, and `<option_data>`
+    /// unless the host overrides an option.
+    #[cfg(feature = "kea")]
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("reservation", &self.uuid);
+        push_text(&mut el, "subnet", &self.subnet);
+        push_text(&mut el, "ip_address", &self.ip_address);
+        push_text(&mut el, "hw_address", &self.hw_address);
+        push_opt_text(&mut el, "hostname", &self.hostname);
+        push_opt_text(&mut el, "client_id", &self.client_id);
+        push_opt_text(&mut el, "description", &self.description);
+        let overrides = [
+            ("domain_name_servers", self.domain_name_servers.as_str()),
+            ("routers", &self.routers),
+            ("domain_name", &self.domain_name),
+            ("domain_search", &self.domain_search),
+        ];
+        if overrides.iter().any(|(_, value)| !value.is_empty()) {
+            push_option_data(&mut el, SUBNET4_OPTION_DATA, &overrides);
+        }
+        el
+    }
+}
+
+/// `<Kea><dhcp6><reservations><reservation>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ReservationV6Node {
+    pub uuid: String,
+    /// `uuid` of the `subnet6` holding `ip_address`
+    pub subnet: String,
+    pub ip_address: String,
+    pub duid: String,
+    /// Delegated prefix as `prefix/len`
+    pub prefixes: Option<String>,
+    pub hostname: Option<String>,
+    pub domain_search: Option<String>,
+    pub description: Option<String>,
+}
+
+impl ReservationV6Node {
+    /// Parse a DHCPv6 `reservation` element; missing fields are left empty.
+    pub fn from_element(el: &Element) -> Option<Self> {
+        if !is_named(el, "reservation") {
+            return None;
+        }
+        Some(Self {
+            uuid: uuid_of(el),
+            subnet: child_text(el, "subnet").unwrap_or_default(),
+            ip_address: child_text(el, "ip_address").unwrap_or_default(),
+            duid: child_text(el, "duid").unwrap_or_default(),
+            prefixes: child_text(el, "prefixes"),
+            hostname: child_text(el, "hostname"),
+            domain_search: child_text(el, "domain_search"),
+            description: child_text(el, "description"),
+        })
+    }
+
+    /// Serialize, leaving out unset optional fields.
+    #[cfg(feature = "kea")]
+    pub fn to_element(&self) -> Element {
+        let mut el = node_element("reservation", &self.uuid);
+        push_text(&mut el, "subnet", &self.subnet);
+        push_text(&mut el, "ip_address", &self.ip_address);
+        push_text(&mut el, "duid", &self.duid);
+        push_opt_text(&mut el, "prefixes", &self.prefixes);
+        push_opt_text(&mut el, "hostname", &self.hostname);
+        push_opt_text(&mut el, "domain_search", &self.domain_search);
+        push_opt_text(&mut el, "description", &self.description);
+        el
+    }
+}
+
+fn parse_pools(el: &Element) -> Vec<String> {
+    child_text(el, "pools")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|pool| !pool.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `<option_data><{field}>` text of a subnet, or empty.
+fn option_data_text(el: &Element, field: &str) -> String {
+    get_child_ci(el, "option_data")
+        .and_then(|option_data| child_text(option_data, field))
+        .unwrap_or_default()
+}
+
+/// Append `<option_data>` with every field in `fields`, set from `values`
+/// or left empty.
+#[cfg(feature = "kea")]
+fn push_option_data(parent: &mut Element, fields: &[&str], values: &[(&str, &str)]) {
+    let mut option_data = Element::new("option_data");
+    for field in fields {
+        let value = values
+            .iter()
+            .find(|(name, _)| name == field)
+            .map_or("", |(_, value)| value);
+        push_text(&mut option_data, field, value);
+    }
+    parent.children.push(XMLNode::Element(option_data));
+}
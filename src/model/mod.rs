@@ -0,0 +1,146 @@
+//! Typed views of the Kea and dnsmasq sections of an OPNsense config.
+//!
+//! Each node parses from the `xmltree::Element` OPNsense stores and
+//! serializes back to one, writing its fields in the order they have always
+//! been written in. Only the fields the migration reads or writes are kept, so
+//! serializing a parsed node is not a round trip of the original element:
+//! builders use `to_element` for new entries only.
+
+#[cfg(feature = "dnsmasq")]
+mod dnsmasq;
+// Parsed for the subnet and reservation extractors with either backend
+#[cfg_attr(not(feature = "kea"), allow(dead_code))]
+mod kea;
+
+#[cfg(feature = "dnsmasq")]
+pub(crate) use dnsmasq::{
+    DnsmasqBootNode, DnsmasqHostNode, DnsmasqOptionNode, DnsmasqRangeNode, DnsmasqTagNode,
+    HostLayout,
+};
+pub(crate) use kea::{ReservationNode, ReservationV6Node, Subnet4Node, Subnet6Node};
+
+use xmltree::{Element, XMLNode};
+
+/// A new element named `name` carrying `uuid`.
+#[cfg(any(feature = "kea", feature = "dnsmasq"))]
+fn node_element(name: &str, uuid: &str) -> Element {
+    let mut el = Element::new(name);
+    el.attributes.insert("uuid".to_string(), uuid.to_string());
+    el
+}
+
+/// Append `<tag>value</tag>` to `parent`.
+#[cfg(any(feature = "kea", feature = "dnsmasq"))]
+fn push_text(parent: &mut Element, tag: &str, value: &str) {
+    let mut el = Element::new(tag);
+    el.children.push(XMLNode::Text(value.to_string()));
+    parent.children.push(XMLNode::Element(el));
+}
+
+/// Append `<tag>value</tag>` to `parent` when `value` is set.
+#[cfg(feature = "kea")]
+fn push_opt_text(parent: &mut Element, tag: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        push_text(parent, tag, value);
+    }
+}
+
+/// Whether `el` is named `name` (case-insensitive).
+fn is_named(el: &Element, name: &str) -> bool {
+    el.name.eq_ignore_ascii_case(name)
+}
+
+/// `el`'s `uuid` attribute, or empty.
+fn uuid_of(el: &Element) -> String {
+    el.attributes.get("uuid").cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "dnsmasq")]
+    use crate::xml_helpers::child_text;
+    use crate::xml_helpers::get_child_ci;
+
+    #[test]
+    #[cfg(feature = "kea")]
+    fn test_kea_nodes_round_trip() {
+        let subnet = Subnet4Node {
+            uuid: "subnet-uuid".into(),
+            subnet: "192.168.1.0/24".into(),
+            interface: "lan".into(),
+            pools: vec!["192.168.1.100-192.168.1.199".into()],
+            description: "LAN".into(),
+            next_server: "192.168.1.5".into(),
+            tftp_server_name: String::new(),
+            boot_file_name: "pxelinux.0".into(),
+        };
+        assert_eq!(
+            Subnet4Node::from_element(&subnet.to_element()),
+            Some(subnet)
+        );
+
+        let reservation = ReservationNode {
+            uuid: "res-uuid".into(),
+            subnet: "subnet-uuid".into(),
+            ip_address: "192.168.1.10".into(),
+            hw_address: "00:11:22:33:44:55".into(),
+            hostname: Some("host".into()),
+            ..Default::default()
+        };
+        let el = reservation.to_element();
+        assert!(get_child_ci(&el, "client_id").is_none());
+        assert!(get_child_ci(&el, "option_data").is_none());
+        assert_eq!(
+            ReservationNode::from_element(&el),
+            Some(reservation.clone())
+        );
+
+        let reservation = ReservationNode {
+            routers: "192.168.1.254".into(),
+            ..reservation
+        };
+        assert_eq!(
+            ReservationNode::from_element(&reservation.to_element()),
+            Some(reservation)
+        );
+        assert_eq!(
+            ReservationNode::from_element(&Element::new("subnet4")),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dnsmasq")]
+    fn test_dnsmasq_host_round_trip() {
+        let host = DnsmasqHostNode {
+            uuid: "host-uuid".into(),
+            host: "printer".into(),
+            domain: "example.com".into(),
+            ip: "192.168.1.20".into(),
+            hwaddr: "00:11:22:33:44:66".into(),
+            ignore: true,
+            ..Default::default()
+        };
+        let el = host.to_element(HostLayout::Dns);
+        assert_eq!(child_text(&el, "ignore").as_deref(), Some("1"));
+        assert!(get_child_ci(&el, "client_id").is_none());
+        assert_eq!(DnsmasqHostNode::from_element(&el), Some(host));
+    }
+
+    #[test]
+    #[cfg(feature = "dnsmasq")]
+    fn test_dnsmasq_boot_round_trip() {
+        let boot = DnsmasqBootNode {
+            uuid: "boot-uuid".into(),
+            interface: "lan".into(),
+            filename: "pxelinux.0".into(),
+            address: "192.168.1.5".into(),
+            ..Default::default()
+        };
+        let el = boot.to_element();
+        assert_eq!(child_text(&el, "tag").as_deref(), Some(""));
+        assert_eq!(DnsmasqBootNode::from_element(&el), Some(boot));
+        assert_eq!(DnsmasqBootNode::from_element(&Element::new("hosts")), None);
+    }
+}
@@ -0,0 +1,304 @@
+//! Field mapping overrides (`--field-overrides`).
+//!
+//! By default a created reservation or host takes its hostname and
+//! description from the same ISC fields. An override file redirects either
+//! one, for sites whose conventions differ: a description holding the
+//! hostname, or descriptions that should not be carried over at all. The
+//! file is a small subset of TOML, one table per emitted field:
+//!
+//! ```toml
+//! # Use the description when a mapping has no hostname
+//! [hostname]
+//! fallback = "descr"
+//!
+//! [description]
+//! drop = true
+//! ```
+//!
+//! `from` names the ISC field to read instead, `fallback` the one to read
+//! when that is empty, and `drop = true` leaves the field out. ISC fields
+//! are `hostname`, `descr`, `mac`, `cid`, `duid` and `ipaddr`.
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{IscStaticMap, IscStaticMapV6};
+
+/// A static mapping field an emitted field can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingField {
+    Hostname,
+    Descr,
+    /// DHCPv4 only
+    Mac,
+    /// DHCPv4 only
+    ClientId,
+    /// DHCPv6 only
+    Duid,
+    Ipaddr,
+}
+
+impl MappingField {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "hostname" => MappingField::Hostname,
+            "descr" => MappingField::Descr,
+            "mac" => MappingField::Mac,
+            "cid" => MappingField::ClientId,
+            "duid" => MappingField::Duid,
+            "ipaddr" => MappingField::Ipaddr,
+            _ => return None,
+        })
+    }
+}
+
+/// Where one emitted field comes from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldRule {
+    /// Read this field instead of the usual one
+    pub from: Option<MappingField>,
+    /// Read this field when the first one is empty
+    pub fallback: Option<MappingField>,
+    /// Leave the field out
+    pub drop: bool,
+}
+
+/// Overrides for the hostname and description of created reservations
+/// and hosts, on both backends.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldOverrides {
+    pub hostname: FieldRule,
+    pub description: FieldRule,
+}
+
+impl FieldOverrides {
+    /// Parse an override file.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut overrides = FieldOverrides::default();
+        let mut rule: Option<&mut FieldRule> = None;
+        for (idx, line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(table) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                rule = Some(match table.trim() {
+                    "hostname" => &mut overrides.hostname,
+                    "description" => &mut overrides.description,
+                    other => bail!(
+                        "line {}: unknown table [{}] (expected [hostname] or [description])",
+                        line_no,
+                        other
+                    ),
+                });
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected key = value", line_no))?;
+            let (key, value) = (key.trim(), value.trim());
+            let rule = rule
+                .as_deref_mut()
+                .ok_or_else(|| anyhow!("line {}: '{}' is outside a table", line_no, key))?;
+            match key {
+                "from" | "fallback" => {
+                    let field = string_value(value)
+                        .and_then(MappingField::parse)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "line {}: '{}' is not a quoted ISC field name",
+                                line_no,
+                                value
+                            )
+                        })?;
+                    if key == "from" {
+                        rule.from = Some(field);
+                    } else {
+                        rule.fallback = Some(field);
+                    }
+                }
+                "drop" => {
+                    rule.drop = match value {
+                        "true" => true,
+                        "false" => false,
+                        _ => bail!("line {}: drop must be true or false", line_no),
+                    }
+                }
+                _ => bail!("line {}: unknown key '{}'", line_no, key),
+            }
+        }
+        Ok(overrides)
+    }
+
+    /// Read and parse the override file at `path`.
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read field overrides: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid field overrides: {}", path.display()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == FieldOverrides::default()
+    }
+}
+
+/// `line` without a `#` comment outside double quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn string_value(value: &str) -> Option<&str> {
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
+impl FieldRule {
+    /// The value of the emitted field, given the value it usually takes and
+    /// a lookup for the other fields of the mapping.
+    fn resolve<'a>(
+        &self,
+        usual: Option<&'a str>,
+        field: impl Fn(MappingField) -> Option<&'a str>,
+    ) -> Option<String> {
+        if self.drop {
+            return None;
+        }
+        let primary = match self.from {
+            Some(from) => field(from),
+            None => usual,
+        };
+        primary
+            .filter(|v| !v.trim().is_empty())
+            .or_else(|| self.fallback.and_then(&field))
+            .filter(|v| !v.trim().is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// Rewrite the hostname and description of every mapping as `overrides`
+/// asks, before anything is built from them.
+pub(crate) fn apply_field_overrides(
+    mappings: &mut [IscStaticMap],
+    mappings_v6: &mut [IscStaticMapV6],
+    overrides: &FieldOverrides,
+) {
+    if overrides.is_empty() {
+        return;
+    }
+    for mapping in mappings.iter_mut() {
+        let field = |f| match f {
+            MappingField::Hostname => mapping.hostname.as_deref(),
+            MappingField::Descr => mapping.descr.as_deref(),
+            MappingField::Mac => Some(mapping.mac.as_str()),
+            MappingField::ClientId => mapping.cid.as_deref(),
+            MappingField::Duid => None,
+            MappingField::Ipaddr => Some(mapping.ipaddr.as_str()),
+        };
+        let hostname = overrides
+            .hostname
+            .resolve(mapping.hostname.as_deref(), field);
+        let descr = overrides
+            .description
+            .resolve(mapping.descr.as_deref(), field);
+        mapping.hostname = hostname;
+        mapping.descr = descr;
+    }
+    for mapping in mappings_v6.iter_mut() {
+        let field = |f| match f {
+            MappingField::Hostname => mapping.hostname.as_deref(),
+            MappingField::Descr => mapping.descr.as_deref(),
+            MappingField::Mac | MappingField::ClientId => None,
+            MappingField::Duid => Some(mapping.duid.as_str()),
+            MappingField::Ipaddr => Some(mapping.ipaddr.as_str()),
+        };
+        let hostname = overrides
+            .hostname
+            .resolve(mapping.hostname.as_deref(), field);
+        let descr = overrides
+            .description
+            .resolve(mapping.descr.as_deref(), field);
+        mapping.hostname = hostname;
+        mapping.descr = descr;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_field_overrides() {
+        let overrides = FieldOverrides::parse(
+            "# site conventions\n\
+             [hostname]\n\
+             fallback = \"descr\" # when unset\n\
+             \n\
+             [description]\n\
+             drop = true\n",
+        )
+        .unwrap();
+        assert_eq!(overrides.hostname.fallback, Some(MappingField::Descr));
+        assert!(overrides.description.drop);
+        assert!(FieldOverrides::parse("").unwrap().is_empty());
+
+        for bad in [
+            "drop = true",
+            "[hostname]\nfrom = descr",
+            "[hostname]\nfrom = \"name\"",
+            "[hostname]\ndrop = yes",
+            "[domain]",
+            "[description]\nrename = \"x\"",
+        ] {
+            assert!(FieldOverrides::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_apply_field_overrides() {
+        let mapping = |hostname: Option<&str>, descr: &str| IscStaticMap {
+            iface: "lan".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            ipaddr: "192.168.1.10".to_string(),
+            hostname: hostname.map(str::to_string),
+            cid: None,
+            descr: Some(descr.to_string()),
+            domain: None,
+            domain_search: None,
+            dns_servers: Vec::new(),
+            gateway: None,
+            gateway_suppressed: false,
+        };
+        let mut mappings = [mapping(None, "printer"), mapping(Some("nas"), "storage")];
+        let overrides = FieldOverrides {
+            hostname: FieldRule {
+                fallback: Some(MappingField::Descr),
+                ..Default::default()
+            },
+            description: FieldRule {
+                drop: true,
+                ..Default::default()
+            },
+        };
+        apply_field_overrides(&mut mappings, &mut [], &overrides);
+        assert_eq!(mappings[0].hostname.as_deref(), Some("printer"));
+        assert_eq!(mappings[1].hostname.as_deref(), Some("nas"));
+        assert!(mappings.iter().all(|m| m.descr.is_none()));
+
+        let overrides = FieldOverrides {
+            description: FieldRule {
+                from: Some(MappingField::Mac),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        apply_field_overrides(&mut mappings, &mut [], &overrides);
+        assert_eq!(mappings[0].descr.as_deref(), Some("00:11:22:33:44:55"));
+    }
+}
@@ -0,0 +1,249 @@
+//! Parsing of the addresses and identifiers found in config.xml.
+//!
+//! Every value is trimmed before parsing, and failures are
+//! [`MigrationError`] variants that name the value as written. Callers that
+//! skip malformed entries drop to an `Option` with `.ok()`.
+
+use ipnet::{Ipv4Net, Ipv6Net};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::MigrationError;
+
+/// Longest DUID (RFC 8415 §11.1): a 2-octet type and up to 128 octets.
+const MAX_DUID_OCTETS: usize = 130;
+
+/// Parse an IPv4 address.
+pub(crate) fn ipv4(ip: &str) -> Result<Ipv4Addr, MigrationError> {
+    ip.trim()
+        .parse()
+        .map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))
+}
+
+/// Parse an IPv6 address.
+pub(crate) fn ipv6(ip: &str) -> Result<Ipv6Addr, MigrationError> {
+    ip.trim()
+        .parse()
+        .map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))
+}
+
+/// Parse an IPv4 CIDR such as `192.168.1.0/24`; host bits may be set.
+pub(crate) fn cidr_v4(cidr: &str) -> Result<Ipv4Net, MigrationError> {
+    cidr.trim()
+        .parse()
+        .map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))
+}
+
+/// Parse an IPv6 CIDR such as `2001:db8::/64`; host bits may be set.
+pub(crate) fn cidr_v6(cidr: &str) -> Result<Ipv6Net, MigrationError> {
+    cidr.trim()
+        .parse()
+        .map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))
+}
+
+/// Parse an interface prefix written as a length (`24`, `/24`) or a netmask
+/// (`255.255.255.0`). Masks that are not contiguous are rejected.
+pub(crate) fn prefix_v4(prefix: &str) -> Result<u8, MigrationError> {
+    let invalid = || MigrationError::InvalidCidr(prefix.to_string());
+    let trimmed = prefix.trim().trim_start_matches('/');
+    match trimmed.parse::<u8>() {
+        Ok(len) if len <= 32 => Ok(len),
+        Ok(_) => Err(invalid()),
+        Err(_) => ipnet::ipv4_mask_to_prefix(ipv4(trimmed)?).map_err(|_| invalid()),
+    }
+}
+
+/// IPv6 counterpart of [`prefix_v4`].
+pub(crate) fn prefix_v6(prefix: &str) -> Result<u8, MigrationError> {
+    let invalid = || MigrationError::InvalidCidr(prefix.to_string());
+    let trimmed = prefix.trim().trim_start_matches('/');
+    match trimmed.parse::<u8>() {
+        Ok(len) if len <= 128 => Ok(len),
+        Ok(_) => Err(invalid()),
+        Err(_) => ipnet::ipv6_mask_to_prefix(ipv6(trimmed)?).map_err(|_| invalid()),
+    }
+}
+
+/// Parse an inclusive IPv4 range; it may not end before it starts.
+pub(crate) fn range_v4(start: &str, end: &str) -> Result<(Ipv4Addr, Ipv4Addr), MigrationError> {
+    let (s, e) = (ipv4(start)?, ipv4(end)?);
+    if s > e {
+        return Err(MigrationError::InvalidRange(format!("{}-{}", start, end)));
+    }
+    Ok((s, e))
+}
+
+/// IPv6 counterpart of [`range_v4`].
+pub(crate) fn range_v6(start: &str, end: &str) -> Result<(Ipv6Addr, Ipv6Addr), MigrationError> {
+    let (s, e) = (ipv6(start)?, ipv6(end)?);
+    if s > e {
+        return Err(MigrationError::InvalidRange(format!("{}-{}", start, end)));
+    }
+    Ok((s, e))
+}
+
+/// Octets of a hardware address written as `:`- or `-`-separated hex, each
+/// octet one or two digits.
+pub(crate) fn hwaddr(hwaddr: &str) -> Result<Vec<u8>, MigrationError> {
+    let invalid = |reason: String| MigrationError::InvalidHwaddr {
+        value: hwaddr.to_string(),
+        reason,
+    };
+    let trimmed = hwaddr.trim();
+    if trimmed.is_empty() {
+        return Err(invalid("empty".to_string()));
+    }
+    let separator = if trimmed.contains('-') { '-' } else { ':' };
+    trimmed
+        .split(separator)
+        .map(|octet| hex_octet(octet).ok_or_else(|| invalid(octet_reason(octet))))
+        .collect()
+}
+
+/// Octets of a DUID written as `:`- or `-`-separated hex octets, or as one
+/// run of hex digits.
+pub(crate) fn duid(duid: &str) -> Result<Vec<u8>, MigrationError> {
+    let invalid = |reason: String| MigrationError::InvalidDuid {
+        value: duid.to_string(),
+        reason,
+    };
+    let trimmed = duid.trim();
+    if trimmed.is_empty() {
+        return Err(invalid("empty".to_string()));
+    }
+    let octets: Vec<u8> = if trimmed.contains([':', '-']) {
+        let separator = if trimmed.contains('-') { '-' } else { ':' };
+        trimmed
+            .split(separator)
+            .map(|octet| hex_octet(octet).ok_or_else(|| invalid(octet_reason(octet))))
+            .collect::<Result<_, _>>()?
+    } else {
+        if !trimmed.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid("not hex digits".to_string()));
+        }
+        if trimmed.len() % 2 != 0 {
+            return Err(invalid("odd number of hex digits".to_string()));
+        }
+        (0..trimmed.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&trimmed[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid("not hex digits".to_string()))?
+    };
+    if octets.len() > MAX_DUID_OCTETS {
+        return Err(invalid(format!(
+            "{} octets, a DUID has at most {}",
+            octets.len(),
+            MAX_DUID_OCTETS
+        )));
+    }
+    Ok(octets)
+}
+
+fn hex_octet(octet: &str) -> Option<u8> {
+    if octet.is_empty() || octet.len() > 2 {
+        return None;
+    }
+    u8::from_str_radix(octet, 16).ok()
+}
+
+fn octet_reason(octet: &str) -> String {
+    if octet.is_empty() {
+        "empty octet".to_string()
+    } else {
+        format!("'{}' is not one or two hex digits", octet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addresses_and_cidrs() {
+        assert_eq!(ipv4(" 192.168.1.10 "), Ok(Ipv4Addr::new(192, 168, 1, 10)));
+        assert_eq!(
+            ipv4("192.168.1.300"),
+            Err(MigrationError::InvalidIpAddress(
+                "192.168.1.300".to_string()
+            ))
+        );
+        assert_eq!(ipv6("2001:db8::1"), Ok("2001:db8::1".parse().unwrap()));
+        assert!(ipv6("192.168.1.1").is_err());
+        assert_eq!(
+            cidr_v4("10.0.0.5/8").unwrap().network(),
+            Ipv4Addr::new(10, 0, 0, 0)
+        );
+        assert_eq!(
+            cidr_v6("2001:db8::/129"),
+            Err(MigrationError::InvalidCidr("2001:db8::/129".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_prefixes() {
+        assert_eq!(prefix_v4("24"), Ok(24));
+        assert_eq!(prefix_v4(" /31 "), Ok(31));
+        assert_eq!(prefix_v4("255.255.255.254"), Ok(31));
+        assert_eq!(prefix_v4("255.255.255.255"), Ok(32));
+        assert_eq!(prefix_v4("0.0.0.0"), Ok(0));
+        assert!(prefix_v4("33").is_err());
+        assert!(prefix_v4("255.0.255.0").is_err());
+        assert!(prefix_v4("wide").is_err());
+        assert_eq!(prefix_v6("64"), Ok(64));
+        assert_eq!(prefix_v6("ffff:ffff:ffff:ffff::"), Ok(64));
+        assert!(prefix_v6("129").is_err());
+        assert!(prefix_v6("ffff::ffff").is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert!(range_v4("10.0.0.1", "10.0.0.1").is_ok());
+        assert_eq!(
+            range_v4("10.0.0.9", "10.0.0.1"),
+            Err(MigrationError::InvalidRange(
+                "10.0.0.9-10.0.0.1".to_string()
+            ))
+        );
+        assert_eq!(
+            range_v6("2001:db8::1", "nope"),
+            Err(MigrationError::InvalidIpAddress("nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_hwaddr() {
+        assert_eq!(
+            hwaddr("00:11:22:AA:bb:cc"),
+            Ok(vec![0x00, 0x11, 0x22, 0xaa, 0xbb, 0xcc])
+        );
+        assert_eq!(hwaddr("0-1-2-3-4-5").map(|o| o.len()), Ok(6));
+        assert_eq!(
+            hwaddr("00:11:22:33:44:5g"),
+            Err(MigrationError::InvalidHwaddr {
+                value: "00:11:22:33:44:5g".to_string(),
+                reason: "'5g' is not one or two hex digits".to_string(),
+            })
+        );
+        assert!(hwaddr("001122334455").is_err());
+        assert!(hwaddr("00:11::33").is_err());
+        assert!(hwaddr("  ").is_err());
+    }
+
+    #[test]
+    fn test_duid() {
+        let octets = vec![0x00, 0x01, 0x00, 0x01, 0xaa, 0xbb];
+        assert_eq!(duid("00:01:00:01:AA:bb"), Ok(octets.clone()));
+        assert_eq!(duid("00-01-00-01-aa-bb"), Ok(octets.clone()));
+        assert_eq!(duid("00010001aabb"), Ok(octets));
+        assert_eq!(
+            duid("0001000"),
+            Err(MigrationError::InvalidDuid {
+                value: "0001000".to_string(),
+                reason: "odd number of hex digits".to_string(),
+            })
+        );
+        assert!(duid("not-a-duid").is_err());
+        assert!(duid("").is_err());
+        assert!(duid(&"ab".repeat(131)).is_err());
+    }
+}
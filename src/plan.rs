@@ -0,0 +1,482 @@
+//! Reviewable migration plans (`isc2kea plan` and `isc2kea apply`).
+//!
+//! A plan lists the ISC static mappings a convert would migrate, each with
+//! the subnet it would be reserved in, so the list can be reviewed and
+//! edited before anything is written. Applying a plan converts only the
+//! mappings still listed. The file is a small, stable subset of YAML:
+//!
+//! ```yaml
+//! version: 1
+//! backend: kea
+//! v4:
+//!   - iface: lan  # printer
+//!     ip: 192.168.1.10
+//!     mac: 00:11:22:33:44:55
+//!     subnet: 192.168.1.0/24
+//! v6:
+//!   - iface: opt2
+//!     ip: 2001:db8:42::10
+//!     duid: 00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55
+//!     subnet: 2001:db8:42::/64
+//! ```
+//!
+//! `backend` is `kea`, `dnsmasq` or `both`, and an empty list is written
+//! `v4: []`. An entry is matched to its mapping by `iface`, `ip` and `mac`
+//! or `duid`; delete it to leave the mapping out. `subnet` is the CIDR of
+//! the Kea subnet the reservation goes into: change it to another subnet
+//! holding the address, such as a narrower one, or leave it out to let the
+//! address pick. dnsmasq hosts have no subnet, so there it is only shown.
+//! Comments, such as the hostname written after `iface`, are ignored.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::net::IpAddr;
+use xmltree::Element;
+
+use crate::extract::{
+    extract_interface_cidrs, extract_interface_cidrs_v6, extract_kea_subnets,
+    extract_kea_subnets_v6,
+};
+use crate::parse;
+use crate::subnet::{find_subnet_for_ip, find_subnet_for_ip_v6, ip_in_subnet, ip_in_subnet_v6};
+use crate::{Backend, IscStaticMap, IscStaticMapV6, MigrationError};
+#[cfg(feature = "kea")]
+use crate::{Subnet, SubnetV6};
+
+/// Plan format version written and accepted.
+const PLAN_VERSION: u32 = 1;
+
+/// One planned reservation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanEntry {
+    pub iface: String,
+    pub ip: String,
+    /// MAC of a DHCPv4 mapping, DUID of a DHCPv6 one
+    pub id: String,
+    /// CIDR of the Kea subnet to reserve the address in; the one holding
+    /// the address when `None`
+    pub subnet: Option<String>,
+    /// ISC hostname, written as a comment for review and not read back
+    pub hostname: Option<String>,
+}
+
+impl std::fmt::Display for PlanEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ({})", self.iface, self.ip, self.id)
+    }
+}
+
+/// The mappings a run will migrate, per address family.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationPlan {
+    pub backend: Backend,
+    pub v4: Vec<PlanEntry>,
+    pub v6: Vec<PlanEntry>,
+}
+
+impl MigrationPlan {
+    /// Parse a plan file.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut plan = MigrationPlan::default();
+        let mut version = None;
+        let mut backend = None;
+        let mut list: Option<bool> = None;
+        let mut entry: Option<&mut PlanEntry> = None;
+        for (idx, line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = strip_comment(line).trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indent = line.len() - line.trim_start().len();
+            let item = line.trim_start().strip_prefix("- ");
+            let (key, value) = item
+                .unwrap_or(line.trim_start())
+                .split_once(':')
+                .ok_or_else(|| anyhow!("line {}: expected key: value", line_no))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            if indent == 0 {
+                entry = None;
+                match key {
+                    "version" => {
+                        version = Some(value.parse::<u32>().map_err(|_| {
+                            anyhow!("line {}: '{}' is not a version number", line_no, value)
+                        })?)
+                    }
+                    "backend" => {
+                        backend = Some(match value {
+                            "kea" => Backend::Kea,
+                            "dnsmasq" => Backend::Dnsmasq,
+                            "both" => Backend::Both,
+                            _ => bail!(
+                                "line {}: backend must be kea, dnsmasq or both, not '{}'",
+                                line_no,
+                                value
+                            ),
+                        })
+                    }
+                    "v4" | "v6" => {
+                        if !value.is_empty() && value != "[]" {
+                            bail!("line {}: {} must be a list of entries", line_no, key);
+                        }
+                        list = Some(key == "v6");
+                    }
+                    _ => bail!("line {}: unknown key '{}'", line_no, key),
+                }
+                continue;
+            }
+
+            let Some(v6) = list else {
+                bail!("line {}: entry outside the v4 or v6 list", line_no);
+            };
+            if item.is_some() {
+                let entries = if v6 { &mut plan.v6 } else { &mut plan.v4 };
+                entries.push(PlanEntry::default());
+                entry = entries.last_mut();
+            }
+            let entry = entry
+                .as_deref_mut()
+                .ok_or_else(|| anyhow!("line {}: '{}' is outside an entry", line_no, key))?;
+            let id_key = if v6 { "duid" } else { "mac" };
+            match key {
+                "iface" => entry.iface = value.to_string(),
+                "ip" => entry.ip = value.to_string(),
+                "subnet" => entry.subnet = Some(value.to_string()).filter(|s| !s.is_empty()),
+                _ if key == id_key => entry.id = value.to_string(),
+                _ => bail!("line {}: unknown key '{}'", line_no, key),
+            }
+        }
+
+        match version {
+            Some(PLAN_VERSION) => {}
+            Some(other) => bail!(
+                "plan version {} is not supported (expected {})",
+                other,
+                PLAN_VERSION
+            ),
+            None => bail!("missing version"),
+        }
+        plan.backend = backend.ok_or_else(|| anyhow!("missing backend"))?;
+        for (family, entries) in [("v4", &plan.v4), ("v6", &plan.v6)] {
+            for (idx, entry) in entries.iter().enumerate() {
+                if entry.iface.is_empty() || entry.ip.is_empty() || entry.id.is_empty() {
+                    bail!(
+                        "{} entry {} needs iface, ip and {}",
+                        family,
+                        idx + 1,
+                        if family == "v6" { "duid" } else { "mac" }
+                    );
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Read and parse the plan at `path`.
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read plan: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid plan: {}", path.display()))
+    }
+
+    /// The plan in its file format.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str("# isc2kea migration plan. Delete an entry to leave its mapping out of\n");
+        text.push_str("# `isc2kea apply`, or change `subnet` to another Kea subnet holding\n");
+        text.push_str("# the address.\n");
+        let _ = writeln!(text, "version: {}", PLAN_VERSION);
+        let backend = match self.backend {
+            Backend::Kea => "kea",
+            Backend::Dnsmasq => "dnsmasq",
+            Backend::Both => "both",
+        };
+        let _ = writeln!(text, "backend: {}", backend);
+        for (family, id_key, entries) in [("v4", "mac", &self.v4), ("v6", "duid", &self.v6)] {
+            if entries.is_empty() {
+                let _ = writeln!(text, "{}: []", family);
+                continue;
+            }
+            let _ = writeln!(text, "{}:", family);
+            for entry in entries {
+                let _ = write!(text, "  - iface: {}", entry.iface);
+                if let Some(hostname) = entry.hostname.as_deref().filter(|h| !h.is_empty()) {
+                    let _ = write!(text, "  # {}", single_line(hostname));
+                }
+                text.push('\n');
+                let _ = writeln!(text, "    ip: {}", entry.ip);
+                let _ = writeln!(text, "    {}: {}", id_key, entry.id);
+                if let Some(subnet) = &entry.subnet {
+                    let _ = writeln!(text, "    subnet: {}", subnet);
+                }
+            }
+        }
+        text
+    }
+}
+
+/// A plan migrating `mappings` and `mappings_v6` of `root` to `backend`.
+/// Each entry's subnet is the Kea subnet holding its address, else the
+/// network of its interface, which `create_subnets` would create.
+pub(crate) fn build_plan(
+    root: &Element,
+    backend: &Backend,
+    mappings: &[IscStaticMap],
+    mappings_v6: &[IscStaticMapV6],
+) -> MigrationPlan {
+    let kea = !matches!(backend, Backend::Dnsmasq);
+    let subnets = if kea {
+        extract_kea_subnets(root).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let subnets_v6 = if kea {
+        extract_kea_subnets_v6(root).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let iface_cidrs = extract_interface_cidrs(root).unwrap_or_default();
+    let iface_cidrs_v6 = extract_interface_cidrs_v6(root).unwrap_or_default();
+    let iface_network =
+        |cidrs: &HashMap<String, String>, iface: &str, contains: &dyn Fn(&str) -> bool| {
+            cidrs.get(iface).filter(|cidr| contains(cidr)).cloned()
+        };
+
+    let v4 = mappings
+        .iter()
+        .map(|m| PlanEntry {
+            iface: m.iface.clone(),
+            ip: m.ipaddr.clone(),
+            id: m.mac.clone(),
+            subnet: find_subnet_for_ip(&m.ipaddr, &subnets)
+                .ok()
+                .and_then(|uuid| subnets.iter().find(|s| s.uuid == uuid))
+                .map(|s| s.cidr.clone())
+                .or_else(|| {
+                    iface_network(&iface_cidrs, &m.iface, &|cidr| {
+                        ip_in_subnet(&m.ipaddr, cidr).unwrap_or(false)
+                    })
+                }),
+            hostname: m.hostname.clone(),
+        })
+        .collect();
+    let v6 = mappings_v6
+        .iter()
+        .map(|m| PlanEntry {
+            iface: m.iface.clone(),
+            ip: m.ipaddr.clone(),
+            id: m.duid.clone(),
+            subnet: find_subnet_for_ip_v6(&m.ipaddr, &subnets_v6)
+                .ok()
+                .and_then(|uuid| subnets_v6.iter().find(|s| s.uuid == uuid))
+                .map(|s| s.cidr.clone())
+                .or_else(|| {
+                    iface_network(&iface_cidrs_v6, &m.iface, &|cidr| {
+                        ip_in_subnet_v6(&m.ipaddr, cidr).unwrap_or(false)
+                    })
+                }),
+            hostname: m.hostname.clone(),
+        })
+        .collect();
+    MigrationPlan {
+        backend: backend.clone(),
+        v4,
+        v6,
+    }
+}
+
+/// `line` without a comment: a `#` at the start or after whitespace.
+fn strip_comment(line: &str) -> &str {
+    let mut prev_space = true;
+    for (idx, c) in line.char_indices() {
+        if c == '#' && prev_space {
+            return &line[..idx];
+        }
+        prev_space = c.is_whitespace();
+    }
+    line
+}
+
+fn single_line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn same_ip(a: &str, b: &str) -> bool {
+    match (a.trim().parse::<IpAddr>(), b.trim().parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.trim() == b.trim(),
+    }
+}
+
+fn same_hwaddr(a: &str, b: &str) -> bool {
+    match (parse::hwaddr(a), parse::hwaddr(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.trim().eq_ignore_ascii_case(b.trim()),
+    }
+}
+
+fn same_duid(a: &str, b: &str) -> bool {
+    match (parse::duid(a), parse::duid(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.trim().eq_ignore_ascii_case(b.trim()),
+    }
+}
+
+impl PlanEntry {
+    fn matches_v4(&self, mapping: &IscStaticMap) -> bool {
+        self.iface.eq_ignore_ascii_case(&mapping.iface)
+            && same_ip(&self.ip, &mapping.ipaddr)
+            && same_hwaddr(&self.id, &mapping.mac)
+    }
+
+    fn matches_v6(&self, mapping: &IscStaticMapV6) -> bool {
+        self.iface.eq_ignore_ascii_case(&mapping.iface)
+            && same_ip(&self.ip, &mapping.ipaddr)
+            && same_duid(&self.id, &mapping.duid)
+    }
+}
+
+/// Keep only the mappings `plan` lists. Fails when an entry matches no
+/// mapping, since the config changed after planning.
+pub(crate) fn retain_planned(
+    plan: &MigrationPlan,
+    mappings: &mut Vec<IscStaticMap>,
+    mappings_v6: &mut Vec<IscStaticMapV6>,
+) -> Result<(), MigrationError> {
+    let stale: Vec<PlanEntry> = plan
+        .v4
+        .iter()
+        .filter(|entry| !mappings.iter().any(|m| entry.matches_v4(m)))
+        .chain(
+            plan.v6
+                .iter()
+                .filter(|entry| !mappings_v6.iter().any(|m| entry.matches_v6(m))),
+        )
+        .cloned()
+        .collect();
+    if !stale.is_empty() {
+        return Err(MigrationError::StalePlan { entries: stale });
+    }
+    mappings.retain(|m| plan.v4.iter().any(|entry| entry.matches_v4(m)));
+    mappings_v6.retain(|m| plan.v6.iter().any(|entry| entry.matches_v6(m)));
+    Ok(())
+}
+
+/// `uuid` of the Kea subnet the plan puts `mapping` in, when it names one.
+/// The subnet must exist and hold the address.
+#[cfg(feature = "kea")]
+pub(crate) fn planned_subnet_v4(
+    plan: Option<&MigrationPlan>,
+    mapping: &IscStaticMap,
+    subnets: &[Subnet],
+) -> Result<Option<String>> {
+    let Some(cidr) = plan
+        .and_then(|plan| plan.v4.iter().find(|entry| entry.matches_v4(mapping)))
+        .and_then(|entry| entry.subnet.as_deref())
+    else {
+        return Ok(None);
+    };
+    let net = parse::cidr_v4(cidr)?.trunc();
+    let subnet = subnets
+        .iter()
+        .find(|s| parse::cidr_v4(&s.cidr).is_ok_and(|s| s.trunc() == net))
+        .ok_or_else(|| {
+            anyhow!(
+                "planned subnet {} for {} is not a Kea subnet",
+                cidr,
+                mapping.ipaddr
+            )
+        })?;
+    if !net.contains(&parse::ipv4(&mapping.ipaddr)?) {
+        bail!("planned subnet {} does not hold {}", cidr, mapping.ipaddr);
+    }
+    Ok(Some(subnet.uuid.clone()))
+}
+
+/// IPv6 counterpart of [`planned_subnet_v4`].
+#[cfg(feature = "kea")]
+pub(crate) fn planned_subnet_v6(
+    plan: Option<&MigrationPlan>,
+    mapping: &IscStaticMapV6,
+    subnets: &[SubnetV6],
+) -> Result<Option<String>> {
+    let Some(cidr) = plan
+        .and_then(|plan| plan.v6.iter().find(|entry| entry.matches_v6(mapping)))
+        .and_then(|entry| entry.subnet.as_deref())
+    else {
+        return Ok(None);
+    };
+    let net = parse::cidr_v6(cidr)?.trunc();
+    let subnet = subnets
+        .iter()
+        .find(|s| parse::cidr_v6(&s.cidr).is_ok_and(|s| s.trunc() == net))
+        .ok_or_else(|| {
+            anyhow!(
+                "planned subnet {} for {} is not a Kea subnet",
+                cidr,
+                mapping.ipaddr
+            )
+        })?;
+    if !net.contains(&parse::ipv6(&mapping.ipaddr)?) {
+        bail!("planned subnet {} does not hold {}", cidr, mapping.ipaddr);
+    }
+    Ok(Some(subnet.uuid.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_round_trip() {
+        let plan = MigrationPlan {
+            backend: Backend::Dnsmasq,
+            v4: vec![PlanEntry {
+                iface: "lan".to_string(),
+                ip: "192.168.1.10".to_string(),
+                id: "00:11:22:33:44:55".to_string(),
+                subnet: Some("192.168.1.0/24".to_string()),
+                hostname: Some("printer # office".to_string()),
+            }],
+            v6: Vec::new(),
+        };
+        let text = plan.to_text();
+        assert!(text.contains("  - iface: lan  # printer # office\n"));
+        assert!(text.contains("v6: []\n"));
+
+        let parsed = MigrationPlan::parse(&text).unwrap();
+        assert!(matches!(parsed.backend, Backend::Dnsmasq));
+        assert!(parsed.v6.is_empty());
+        assert_eq!(
+            parsed.v4,
+            vec![PlanEntry {
+                hostname: None,
+                ..plan.v4[0].clone()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_plan_parse_errors() {
+        let err = |text: &str| MigrationPlan::parse(text).unwrap_err().to_string();
+        assert_eq!(err("backend: kea\nv4: []\n"), "missing version");
+        assert_eq!(
+            err("version: 2\nbackend: kea\n"),
+            "plan version 2 is not supported (expected 1)"
+        );
+        assert_eq!(
+            err("version: 1\nbackend: kea\nv4:\n  - iface: lan\n    duid: 00:01\n"),
+            "line 5: unknown key 'duid'"
+        );
+        assert_eq!(
+            err("version: 1\nbackend: kea\nv4:\n  - iface: lan\n    ip: 10.0.0.1\n"),
+            "v4 entry 1 needs iface, ip and mac"
+        );
+        assert_eq!(
+            err("version: 1\n  ip: 10.0.0.1\n"),
+            "line 2: entry outside the v4 or v6 list"
+        );
+    }
+}
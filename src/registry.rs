@@ -0,0 +1,222 @@
+//! Migration backends and the registry used to look them up.
+//!
+//! Kea and dnsmasq are the built-in backends. With the `backend-api` feature
+//! the trait and registry are public, so other crates can add their own
+//! backends and run them with `scan_config_with_backend` and
+//! `convert_config_with_backend`.
+
+use anyhow::{anyhow, Result};
+use xmltree::Element;
+
+use crate::backend::Backend;
+use crate::{IscStaticMap, IscStaticMapV6, MigrationOptions, MigrationStats};
+
+/// A target that ISC static mappings can be migrated to.
+///
+/// Mappings are passed already filtered and ordered according to `options`.
+pub trait MigrationBackend {
+    /// Name used to look the backend up in a [`BackendRegistry`].
+    fn name(&self) -> &str;
+
+    /// Report what `convert` would do, without modifying the config.
+    fn scan(
+        &self,
+        root: &Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats>;
+
+    /// Write the mappings into the config.
+    fn convert(
+        &self,
+        root: &mut Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats>;
+
+    /// Basic target counts without validation (`target_subnets_found` and
+    /// `target_subnets_v6_found`). Defaults to none.
+    fn stats(&self, _root: &Element) -> Result<MigrationStats> {
+        Ok(MigrationStats::default())
+    }
+}
+
+/// Backends available for migration, keyed by name.
+pub struct BackendRegistry {
+    backends: Vec<Box<dyn MigrationBackend>>,
+}
+
+impl BackendRegistry {
+    /// Create a registry with no backends.
+    pub fn new() -> Self {
+        Self {
+            backends: Vec::new(),
+        }
+    }
+
+    /// Add a backend, replacing any registered under the same name.
+    pub fn register(&mut self, backend: Box<dyn MigrationBackend>) {
+        self.backends
+            .retain(|b| !b.name().eq_ignore_ascii_case(backend.name()));
+        self.backends.push(backend);
+    }
+
+    /// Look up a backend by name (case-insensitive).
+    pub fn get(&self, name: &str) -> Option<&dyn MigrationBackend> {
+        self.backends
+            .iter()
+            .find(|b| b.name().eq_ignore_ascii_case(name))
+            .map(|b| b.as_ref())
+    }
+
+    /// Names of the registered backends, in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.backends.iter().map(|b| b.name()).collect()
+    }
+
+    /// The backends selected by `--backend`, labelled for per-backend stats.
+    pub(crate) fn resolve(
+        &self,
+        backend: &Backend,
+    ) -> Result<Vec<(Backend, &dyn MigrationBackend)>> {
+        let selected = match backend {
+            Backend::Kea => vec![(Backend::Kea, "kea")],
+            Backend::Dnsmasq => vec![(Backend::Dnsmasq, "dnsmasq")],
+            Backend::Both => vec![(Backend::Kea, "kea"), (Backend::Dnsmasq, "dnsmasq")],
+        };
+        selected
+            .into_iter()
+            .map(|(kind, name)| {
+                let found = self.get(name).ok_or_else(|| {
+                    anyhow!(
+                        "Backend {} is not registered (available: {}); built-in backends \
+                         need the `{}` cargo feature",
+                        name,
+                        self.names().join(", "),
+                        name
+                    )
+                })?;
+                Ok((kind, found))
+            })
+            .collect()
+    }
+}
+
+impl Default for BackendRegistry {
+    /// A registry with the built-in backends enabled by cargo features
+    /// (`kea` and `dnsmasq`).
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut registry = Self::new();
+        #[cfg(feature = "kea")]
+        registry.register(Box::new(crate::migrate::KeaBackend));
+        #[cfg(feature = "dnsmasq")]
+        registry.register(Box::new(crate::migrate::DnsmasqBackend));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(all(feature = "kea", feature = "dnsmasq"))]
+    struct NullBackend(&'static str);
+
+    #[cfg(all(feature = "kea", feature = "dnsmasq"))]
+    impl MigrationBackend for NullBackend {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn scan(
+            &self,
+            _root: &Element,
+            mappings: &[IscStaticMap],
+            mappings_v6: &[IscStaticMapV6],
+            _options: &MigrationOptions,
+        ) -> Result<MigrationStats> {
+            Ok(MigrationStats {
+                isc_mappings_found: mappings.len(),
+                isc_mappings_v6_found: mappings_v6.len(),
+                ..Default::default()
+            })
+        }
+
+        fn convert(
+            &self,
+            root: &mut Element,
+            mappings: &[IscStaticMap],
+            mappings_v6: &[IscStaticMapV6],
+            options: &MigrationOptions,
+        ) -> Result<MigrationStats> {
+            self.scan(root, mappings, mappings_v6, options)
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "kea", feature = "dnsmasq"))]
+    fn test_default_registry_has_builtin_backends() {
+        let registry = BackendRegistry::default();
+        assert_eq!(registry.names(), vec!["kea", "dnsmasq"]);
+        assert!(registry.get("kea").is_some());
+        assert!(registry.get("DNSMASQ").is_some());
+        assert!(registry.get("routeros").is_none());
+
+        let both = registry.resolve(&Backend::Both).unwrap();
+        let names: Vec<&str> = both.iter().map(|(_, b)| b.name()).collect();
+        assert_eq!(names, vec!["kea", "dnsmasq"]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "kea", feature = "dnsmasq"))]
+    fn test_register_replaces_same_name() {
+        let mut registry = BackendRegistry::default();
+        registry.register(Box::new(NullBackend("kea")));
+        registry.register(Box::new(NullBackend("routeros")));
+        assert_eq!(registry.names(), vec!["dnsmasq", "kea", "routeros"]);
+
+        // The built-in Kea backend would fail here: there is no <Kea> section
+        let mapping = IscStaticMap {
+            iface: "lan".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            ipaddr: "192.168.1.10".to_string(),
+            hostname: None,
+            cid: None,
+            descr: None,
+            domain: None,
+            domain_search: None,
+            dns_servers: Vec::new(),
+            gateway: None,
+            gateway_suppressed: false,
+        };
+        let root = Element::new("opnsense");
+        let stats = registry
+            .get("Kea")
+            .unwrap()
+            .scan(&root, &[mapping], &[], &MigrationOptions::default())
+            .unwrap();
+        assert_eq!(stats.isc_mappings_found, 1);
+    }
+
+    #[test]
+    fn test_default_registry_matches_available_backends() {
+        let registry = BackendRegistry::default();
+        for backend in Backend::available() {
+            assert!(
+                registry.resolve(backend).is_ok(),
+                "{} is not registered",
+                backend
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_missing_backend() {
+        let registry = BackendRegistry::new();
+        let err = registry.resolve(&Backend::Dnsmasq).err().unwrap();
+        assert!(err.to_string().contains("dnsmasq is not registered"));
+    }
+}
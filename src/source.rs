@@ -0,0 +1,170 @@
+//! Where static mappings are read from, and the registry of source
+//! adapters used to look them up.
+//!
+//! `dhcpd` reads the ISC `<dhcpd>`/`<dhcpdv6>` sections. Other adapters
+//! read static leases that third-party plugins kept elsewhere in the
+//! config; each one turns them into ISC mappings, so the rest of the
+//! migration treats them the same way.
+
+use anyhow::{anyhow, Result};
+use xmltree::Element;
+
+use crate::extract::{
+    extract_legacy_isc_mappings, extract_legacy_isc_mappings_v6, extract_static_leases,
+    has_static_leases, iter_isc_mappings, iter_isc_mappings_v6,
+};
+use crate::{IscStaticMap, IscStaticMapV6, Source};
+
+/// Name of the ISC `<dhcpd>`/`<dhcpdv6>` source.
+pub(crate) const DHCPD_SOURCE: &str = "dhcpd";
+
+/// A location static mappings can be read from.
+pub(crate) trait MappingSource {
+    /// Name used to look the source up in a [`SourceRegistry`].
+    fn name(&self) -> &str;
+
+    /// Whether `root` holds static mappings in this source's layout.
+    fn detect(&self, root: &Element) -> bool;
+
+    /// DHCPv4 static mappings, in document order.
+    fn mappings(&self, root: &Element) -> Vec<IscStaticMap>;
+
+    /// DHCPv6 static mappings, in document order. Defaults to none.
+    fn mappings_v6(&self, _root: &Element) -> Vec<IscStaticMapV6> {
+        Vec::new()
+    }
+}
+
+/// The ISC DHCP sections.
+struct DhcpdSource;
+
+impl MappingSource for DhcpdSource {
+    fn name(&self) -> &str {
+        DHCPD_SOURCE
+    }
+
+    fn detect(&self, root: &Element) -> bool {
+        iter_isc_mappings(root).next().is_some()
+            || iter_isc_mappings_v6(root).next().is_some()
+            || extract_legacy_isc_mappings(root).is_ok_and(|m| !m.is_empty())
+            || extract_legacy_isc_mappings_v6(root).is_ok_and(|m| !m.is_empty())
+    }
+
+    fn mappings(&self, root: &Element) -> Vec<IscStaticMap> {
+        iter_isc_mappings(root).collect()
+    }
+
+    fn mappings_v6(&self, root: &Element) -> Vec<IscStaticMapV6> {
+        iter_isc_mappings_v6(root).collect()
+    }
+}
+
+/// The legacy `dhcp-static-leases` plugin (`<OPNsense><dhcpleases>`).
+struct StaticLeasesSource;
+
+impl MappingSource for StaticLeasesSource {
+    fn name(&self) -> &str {
+        "dhcp-static-leases"
+    }
+
+    fn detect(&self, root: &Element) -> bool {
+        has_static_leases(root)
+    }
+
+    fn mappings(&self, root: &Element) -> Vec<IscStaticMap> {
+        extract_static_leases(root)
+    }
+}
+
+/// Sources available for extraction, keyed by name.
+pub(crate) struct SourceRegistry {
+    sources: Vec<Box<dyn MappingSource>>,
+}
+
+impl SourceRegistry {
+    /// Create a registry with no sources.
+    pub(crate) fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+        }
+    }
+
+    /// Add a source, replacing any registered under the same name.
+    pub(crate) fn register(&mut self, source: Box<dyn MappingSource>) {
+        self.sources
+            .retain(|s| !s.name().eq_ignore_ascii_case(source.name()));
+        self.sources.push(source);
+    }
+
+    /// Look up a source by name (case-insensitive).
+    pub(crate) fn get(&self, name: &str) -> Option<&dyn MappingSource> {
+        self.sources
+            .iter()
+            .find(|s| s.name().eq_ignore_ascii_case(name))
+            .map(|s| s.as_ref())
+    }
+
+    /// The source selected by `--source`. [`Source::Auto`] takes the first
+    /// registered source that detects its layout in `root`, and `dhcpd`
+    /// when none does.
+    pub(crate) fn resolve(&self, source: Source, root: &Element) -> Result<&dyn MappingSource> {
+        let name = match source {
+            Source::Auto => self
+                .sources
+                .iter()
+                .find(|s| s.detect(root))
+                .map_or(DHCPD_SOURCE, |s| s.name()),
+            Source::Dhcpd => DHCPD_SOURCE,
+            Source::DhcpStaticLeases => "dhcp-static-leases",
+        };
+        self.get(name)
+            .ok_or_else(|| anyhow!("Mapping source {} is not registered", name))
+    }
+}
+
+impl Default for SourceRegistry {
+    /// A registry with the built-in sources, `dhcpd` first so it wins
+    /// detection when a config has both.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(DhcpdSource));
+        registry.register(Box::new(StaticLeasesSource));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_prefers_dhcpd() {
+        let registry = SourceRegistry::default();
+        let with_plugin = Element::parse(
+            br#"<opnsense><OPNsense><dhcpleases><leases><lease><interface>lan</interface><mac>00:11:22:33:44:55</mac><ipaddr>192.168.1.10</ipaddr></lease></leases></dhcpleases></OPNsense></opnsense>"#
+                .as_slice(),
+        )
+        .unwrap();
+        let source = registry.resolve(Source::Auto, &with_plugin).unwrap();
+        assert_eq!(source.name(), "dhcp-static-leases");
+        assert_eq!(source.mappings(&with_plugin).len(), 1);
+
+        let mut both = with_plugin.clone();
+        both.children.push(xmltree::XMLNode::Element(
+            Element::parse(
+                br#"<dhcpd><lan><staticmap><mac>00:11:22:33:44:66</mac><ipaddr>192.168.1.11</ipaddr></staticmap></lan></dhcpd>"#
+                    .as_slice(),
+            )
+            .unwrap(),
+        ));
+        let source = registry.resolve(Source::Auto, &both).unwrap();
+        assert_eq!(source.name(), DHCPD_SOURCE);
+
+        let empty = Element::new("opnsense");
+        let source = registry.resolve(Source::Auto, &empty).unwrap();
+        assert_eq!(source.name(), DHCPD_SOURCE);
+        assert!(SourceRegistry::new()
+            .resolve(Source::Dhcpd, &empty)
+            .is_err());
+    }
+}
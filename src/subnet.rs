@@ -1,35 +1,37 @@
+//! Subnet and address-range math for IPv4 and IPv6.
+//!
+//! Functions take addresses and CIDRs as strings, matching how they appear in
+//! config.xml, parse them with [`crate::parse`], and fail with its
+//! [`MigrationError`] variants on malformed input.
+//! IPv6 variants carry a `_v6` suffix.
+
+use crate::parse;
+use crate::{MigrationError, Subnet, SubnetV6};
 use anyhow::Result;
 use ipnet::{Ipv4Net, Ipv6Net};
 use std::collections::HashMap;
 use std::net::{Ipv4Addr, Ipv6Addr};
-use std::str::FromStr;
-
-use crate::{MigrationError, Subnet, SubnetV6};
 
 /// Check if an IP address is contained within a CIDR subnet
 pub fn ip_in_subnet(ip: &str, cidr: &str) -> Result<bool> {
-    let ip_addr =
-        Ipv4Addr::from_str(ip).map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))?;
+    let ip_addr = parse::ipv4(ip)?;
 
-    let network =
-        Ipv4Net::from_str(cidr).map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))?;
+    let network = parse::cidr_v4(cidr)?;
 
     Ok(network.contains(&ip_addr))
 }
 
 /// Find the matching subnet UUID for an IP address
 pub fn find_subnet_for_ip(ip: &str, subnets: &[Subnet]) -> Result<String> {
-    let ip_addr =
-        Ipv4Addr::from_str(ip).map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))?;
+    let ip_addr = parse::ipv4(ip)?;
 
     let mut parsed = Vec::with_capacity(subnets.len());
     for subnet in subnets {
-        let net = Ipv4Net::from_str(&subnet.cidr)
-            .map_err(|_| MigrationError::InvalidCidr(subnet.cidr.to_string()))?;
+        let net = parse::cidr_v4(&subnet.cidr)?;
         parsed.push((net.prefix_len(), subnet, net));
     }
 
-    parsed.sort_by(|a, b| b.0.cmp(&a.0));
+    parsed.sort_by_key(|b| std::cmp::Reverse(b.0));
 
     for (_, subnet, net) in parsed {
         if net.contains(&ip_addr) {
@@ -37,22 +39,20 @@ pub fn find_subnet_for_ip(ip: &str, subnets: &[Subnet]) -> Result<String> {
         }
     }
 
-    Err(MigrationError::NoMatchingSubnet(ip.to_string()).into())
+    Err(MigrationError::SubnetNotFound { ip: ip.to_string() }.into())
 }
 
 /// Find the interface name for an IPv4 address based on interface CIDRs
 pub fn iface_for_ip(ip: &str, iface_cidrs: &HashMap<String, String>) -> Result<String> {
-    let ip_addr =
-        Ipv4Addr::from_str(ip).map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))?;
+    let ip_addr = parse::ipv4(ip)?;
 
     let mut parsed = Vec::with_capacity(iface_cidrs.len());
     for (iface, cidr) in iface_cidrs {
-        let net =
-            Ipv4Net::from_str(cidr).map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))?;
+        let net = parse::cidr_v4(cidr)?;
         parsed.push((net.prefix_len(), iface, net));
     }
 
-    parsed.sort_by(|a, b| b.0.cmp(&a.0));
+    parsed.sort_by_key(|b| std::cmp::Reverse(b.0));
 
     for (_, iface, net) in parsed {
         if net.contains(&ip_addr) {
@@ -65,11 +65,9 @@ pub fn iface_for_ip(ip: &str, iface_cidrs: &HashMap<String, String>) -> Result<S
 
 /// Check if an IPv6 address is contained within a CIDR subnet
 pub fn ip_in_subnet_v6(ip: &str, cidr: &str) -> Result<bool> {
-    let ip_addr =
-        Ipv6Addr::from_str(ip).map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))?;
+    let ip_addr = parse::ipv6(ip)?;
 
-    let network =
-        Ipv6Net::from_str(cidr).map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))?;
+    let network = parse::cidr_v6(cidr)?;
 
     Ok(network.contains(&ip_addr))
 }
@@ -81,19 +79,223 @@ pub fn prefix_to_netmask(prefix: u8) -> Result<String> {
     Ok(net.netmask().to_string())
 }
 
+/// Convert an IPv6 prefix length to a netmask string (e.g. 64 -> ffff:ffff:ffff:ffff::)
+pub fn prefix_to_netmask_v6(prefix: u8) -> Result<String> {
+    let net = Ipv6Net::new(Ipv6Addr::UNSPECIFIED, prefix)
+        .map_err(|_| MigrationError::InvalidCidr(prefix.to_string()))?;
+    Ok(net.netmask().to_string())
+}
+
+/// Convert an IPv4 subnet mask to a prefix length (e.g. 255.255.255.0 -> 24)
+pub fn netmask_to_prefix(mask: &str) -> Result<u8> {
+    let addr = parse::ipv4(mask)?;
+    Ok(ipnet::ipv4_mask_to_prefix(addr)
+        .map_err(|_| MigrationError::InvalidCidr(mask.to_string()))?)
+}
+
+/// Convert an IPv6 netmask to a prefix length (e.g. ffff:ffff:ffff:ffff:: -> 64)
+pub fn netmask_to_prefix_v6(mask: &str) -> Result<u8> {
+    let addr = parse::ipv6(mask)?;
+    Ok(ipnet::ipv6_mask_to_prefix(addr)
+        .map_err(|_| MigrationError::InvalidCidr(mask.to_string()))?)
+}
+
+fn parse_range_v4(start: &str, end: &str) -> Result<(u32, u32)> {
+    let (s, e) = parse::range_v4(start, end)?;
+    Ok((u32::from(s), u32::from(e)))
+}
+
+fn parse_range_v6(start: &str, end: &str) -> Result<(u128, u128)> {
+    let (s, e) = parse::range_v6(start, end)?;
+    Ok((u128::from(s), u128::from(e)))
+}
+
+/// Split an inclusive address range into the minimal list of aligned blocks,
+/// as `(first address, prefix length)` pairs within a `bits`-wide space.
+fn range_blocks(start: u128, end: u128, bits: u32) -> Vec<(u128, u8)> {
+    let mut blocks = Vec::new();
+    let mut cur = start;
+    loop {
+        // Largest block aligned at `cur` that does not run past `end`
+        let mut size = if cur == 0 {
+            bits
+        } else {
+            cur.trailing_zeros().min(bits)
+        };
+        while size > 0 && block_last(cur, size) > end {
+            size -= 1;
+        }
+        blocks.push((cur, (bits - size) as u8));
+
+        let last = block_last(cur, size);
+        if last >= end {
+            break;
+        }
+        cur = last + 1;
+    }
+    blocks
+}
+
+fn block_last(first: u128, size: u32) -> u128 {
+    if size >= 128 {
+        u128::MAX
+    } else {
+        first | ((1u128 << size) - 1)
+    }
+}
+
+/// Convert an inclusive IPv4 range to the minimal set of CIDRs covering exactly
+/// that range (e.g. 10.0.0.0-10.0.0.5 -> 10.0.0.0/30, 10.0.0.4/31)
+pub fn range_to_cidrs(start: &str, end: &str) -> Result<Vec<String>> {
+    let (s, e) = parse_range_v4(start, end)?;
+    Ok(range_blocks(s as u128, e as u128, 32)
+        .into_iter()
+        .map(|(first, prefix)| format!("{}/{}", Ipv4Addr::from(first as u32), prefix))
+        .collect())
+}
+
+/// Convert an inclusive IPv6 range to the minimal set of CIDRs covering exactly
+/// that range
+pub fn range_to_cidrs_v6(start: &str, end: &str) -> Result<Vec<String>> {
+    let (s, e) = parse_range_v6(start, end)?;
+    Ok(range_blocks(s, e, 128)
+        .into_iter()
+        .map(|(first, prefix)| format!("{}/{}", Ipv6Addr::from(first), prefix))
+        .collect())
+}
+
+/// Smallest single IPv4 CIDR that contains the whole inclusive range
+/// (e.g. 10.0.0.100-10.0.0.200 -> 10.0.0.0/24)
+pub fn covering_cidr(start: &str, end: &str) -> Result<String> {
+    let (s, e) = parse_range_v4(start, end)?;
+    let prefix = (s ^ e).leading_zeros() as u8;
+    let net = Ipv4Net::new(Ipv4Addr::from(s), prefix)
+        .map_err(|_| MigrationError::InvalidRange(format!("{}-{}", start, end)))?;
+    Ok(net.trunc().to_string())
+}
+
+/// Smallest single IPv6 CIDR that contains the whole inclusive range
+pub fn covering_cidr_v6(start: &str, end: &str) -> Result<String> {
+    let (s, e) = parse_range_v6(start, end)?;
+    let prefix = (s ^ e).leading_zeros() as u8;
+    let net = Ipv6Net::new(Ipv6Addr::from(s), prefix)
+        .map_err(|_| MigrationError::InvalidRange(format!("{}-{}", start, end)))?;
+    Ok(net.trunc().to_string())
+}
+
+/// Check whether two inclusive IPv4 ranges share at least one address
+pub fn ranges_overlap(a_start: &str, a_end: &str, b_start: &str, b_end: &str) -> Result<bool> {
+    let (a_s, a_e) = parse_range_v4(a_start, a_end)?;
+    let (b_s, b_e) = parse_range_v4(b_start, b_end)?;
+    Ok(a_s <= b_e && b_s <= a_e)
+}
+
+/// Check whether two inclusive IPv6 ranges share at least one address
+pub fn ranges_overlap_v6(a_start: &str, a_end: &str, b_start: &str, b_end: &str) -> Result<bool> {
+    let (a_s, a_e) = parse_range_v6(a_start, a_end)?;
+    let (b_s, b_e) = parse_range_v6(b_start, b_end)?;
+    Ok(a_s <= b_e && b_s <= a_e)
+}
+
+/// First and last address a DHCP pool in an IPv4 subnet may hand out: all
+/// but the network and broadcast addresses, except that both addresses of
+/// a point-to-point /31 (RFC 3021) and the single address of a /32 are
+/// usable.
+pub fn usable_range(cidr: &str) -> Result<(String, String)> {
+    let (first, last) = usable_bounds(cidr)?;
+    Ok((
+        Ipv4Addr::from(first).to_string(),
+        Ipv4Addr::from(last).to_string(),
+    ))
+}
+
+fn usable_bounds(cidr: &str) -> Result<(u32, u32)> {
+    let net = parse::cidr_v4(cidr)?;
+    let (network, broadcast) = (u32::from(net.network()), u32::from(net.broadcast()));
+    Ok(if net.prefix_len() >= 31 {
+        (network, broadcast)
+    } else {
+        (network + 1, broadcast - 1)
+    })
+}
+
+/// First and last address a DHCP pool in an IPv6 subnet may hand out: all
+/// but the subnet-router anycast address (the first one, RFC 4291), except
+/// that both addresses of a point-to-point /127 (RFC 6164) and the single
+/// address of a /128 are usable.
+pub fn usable_range_v6(cidr: &str) -> Result<(String, String)> {
+    let (first, last) = usable_bounds_v6(cidr)?;
+    Ok((
+        Ipv6Addr::from(first).to_string(),
+        Ipv6Addr::from(last).to_string(),
+    ))
+}
+
+fn usable_bounds_v6(cidr: &str) -> Result<(u128, u128)> {
+    let net = parse::cidr_v6(cidr)?;
+    let (network, last) = (u128::from(net.network()), u128::from(net.broadcast()));
+    Ok(if net.prefix_len() >= 127 {
+        (network, last)
+    } else {
+        (network + 1, last)
+    })
+}
+
+/// The part of an inclusive IPv4 range a pool in `cidr` may hand out (see
+/// [`usable_range`]), or `None` when no usable address is left. Bounds that
+/// need no trimming are returned as written.
+pub fn clamp_range(start: &str, end: &str, cidr: &str) -> Result<Option<(String, String)>> {
+    let (s, e) = parse_range_v4(start, end)?;
+    let (first, last) = usable_bounds(cidr)?;
+    let bound = |orig: &str, value: u32, clamped: u32| {
+        if value == clamped {
+            orig.trim().to_string()
+        } else {
+            Ipv4Addr::from(clamped).to_string()
+        }
+    };
+    let (cs, ce) = (s.max(first), e.min(last));
+    Ok((cs <= ce).then(|| (bound(start, s, cs), bound(end, e, ce))))
+}
+
+/// IPv6 counterpart of [`clamp_range`], using [`usable_range_v6`].
+pub fn clamp_range_v6(start: &str, end: &str, cidr: &str) -> Result<Option<(String, String)>> {
+    let (s, e) = parse_range_v6(start, end)?;
+    let (first, last) = usable_bounds_v6(cidr)?;
+    let bound = |orig: &str, value: u128, clamped: u128| {
+        if value == clamped {
+            orig.trim().to_string()
+        } else {
+            Ipv6Addr::from(clamped).to_string()
+        }
+    };
+    let (cs, ce) = (s.max(first), e.min(last));
+    Ok((cs <= ce).then(|| (bound(start, s, cs), bound(end, e, ce))))
+}
+
+/// Check whether an inclusive IPv4 range lies entirely inside a CIDR
+pub fn range_in_subnet(start: &str, end: &str, cidr: &str) -> Result<bool> {
+    parse_range_v4(start, end)?;
+    Ok(ip_in_subnet(start.trim(), cidr)? && ip_in_subnet(end.trim(), cidr)?)
+}
+
+/// Check whether an inclusive IPv6 range lies entirely inside a CIDR
+pub fn range_in_subnet_v6(start: &str, end: &str, cidr: &str) -> Result<bool> {
+    parse_range_v6(start, end)?;
+    Ok(ip_in_subnet_v6(start.trim(), cidr)? && ip_in_subnet_v6(end.trim(), cidr)?)
+}
+
 /// Find the matching IPv6 subnet UUID for an IP address
 pub fn find_subnet_for_ip_v6(ip: &str, subnets: &[SubnetV6]) -> Result<String> {
-    let ip_addr =
-        Ipv6Addr::from_str(ip).map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))?;
+    let ip_addr = parse::ipv6(ip)?;
 
     let mut parsed = Vec::with_capacity(subnets.len());
     for subnet in subnets {
-        let net = Ipv6Net::from_str(&subnet.cidr)
-            .map_err(|_| MigrationError::InvalidCidr(subnet.cidr.to_string()))?;
+        let net = parse::cidr_v6(&subnet.cidr)?;
         parsed.push((net.prefix_len(), subnet, net));
     }
 
-    parsed.sort_by(|a, b| b.0.cmp(&a.0));
+    parsed.sort_by_key(|b| std::cmp::Reverse(b.0));
 
     for (_, subnet, net) in parsed {
         if net.contains(&ip_addr) {
@@ -101,22 +303,20 @@ pub fn find_subnet_for_ip_v6(ip: &str, subnets: &[SubnetV6]) -> Result<String> {
         }
     }
 
-    Err(MigrationError::NoMatchingSubnet(ip.to_string()).into())
+    Err(MigrationError::SubnetNotFound { ip: ip.to_string() }.into())
 }
 
 /// Find the interface name for an IPv6 address based on interface CIDRs
 pub fn iface_for_ip_v6(ip: &str, iface_cidrs: &HashMap<String, String>) -> Result<String> {
-    let ip_addr =
-        Ipv6Addr::from_str(ip).map_err(|_| MigrationError::InvalidIpAddress(ip.to_string()))?;
+    let ip_addr = parse::ipv6(ip)?;
 
     let mut parsed = Vec::with_capacity(iface_cidrs.len());
     for (iface, cidr) in iface_cidrs {
-        let net =
-            Ipv6Net::from_str(cidr).map_err(|_| MigrationError::InvalidCidr(cidr.to_string()))?;
+        let net = parse::cidr_v6(cidr)?;
         parsed.push((net.prefix_len(), iface, net));
     }
 
-    parsed.sort_by(|a, b| b.0.cmp(&a.0));
+    parsed.sort_by_key(|b| std::cmp::Reverse(b.0));
 
     for (_, iface, net) in parsed {
         if net.contains(&ip_addr) {
@@ -127,6 +327,60 @@ pub fn iface_for_ip_v6(ip: &str, iface_cidrs: &HashMap<String, String>) -> Resul
     Err(MigrationError::NoMatchingInterface(ip.to_string()).into())
 }
 
+/// Number of candidate subnets [`nearest_subnets`] returns.
+#[cfg(feature = "kea")]
+const NEAREST_SUBNETS: usize = 3;
+
+/// CIDRs of the subnets sharing the longest address prefix with an IPv4
+/// address, nearest first.
+///
+/// Used for diagnostics when an address does not fall inside any subnet.
+#[cfg(feature = "kea")]
+pub(crate) fn nearest_subnets(ip: &str, subnets: &[Subnet]) -> Vec<String> {
+    let Ok(ip) = parse::ipv4(ip) else {
+        return Vec::new();
+    };
+    let ip_bits = u32::from(ip);
+    let mut ranked: Vec<(u32, &Subnet)> = subnets
+        .iter()
+        .filter_map(|subnet| {
+            let net = parse::cidr_v4(&subnet.cidr).ok()?;
+            Some(((ip_bits ^ u32::from(net.network())).leading_zeros(), subnet))
+        })
+        .collect();
+    ranked.sort_by_key(|(common, _)| std::cmp::Reverse(*common));
+    ranked
+        .into_iter()
+        .take(NEAREST_SUBNETS)
+        .map(|(_, subnet)| subnet.cidr.clone())
+        .collect()
+}
+
+/// IPv6 counterpart of [`nearest_subnets`].
+#[cfg(feature = "kea")]
+pub(crate) fn nearest_subnets_v6(ip: &str, subnets: &[SubnetV6]) -> Vec<String> {
+    let Ok(ip) = parse::ipv6(ip) else {
+        return Vec::new();
+    };
+    let ip_bits = u128::from(ip);
+    let mut ranked: Vec<(u32, &SubnetV6)> = subnets
+        .iter()
+        .filter_map(|subnet| {
+            let net = parse::cidr_v6(&subnet.cidr).ok()?;
+            Some((
+                (ip_bits ^ u128::from(net.network())).leading_zeros(),
+                subnet,
+            ))
+        })
+        .collect();
+    ranked.sort_by_key(|(common, _)| std::cmp::Reverse(*common));
+    ranked
+        .into_iter()
+        .take(NEAREST_SUBNETS)
+        .map(|(_, subnet)| subnet.cidr.clone())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,6 +455,43 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "kea")]
+    fn test_nearest_subnets() {
+        let subnets = vec![
+            Subnet {
+                uuid: "subnet-1".to_string(),
+                cidr: "192.168.1.0/24".to_string(),
+                iface: None,
+            },
+            Subnet {
+                uuid: "subnet-2".to_string(),
+                cidr: "10.0.0.0/24".to_string(),
+                iface: None,
+            },
+        ];
+
+        assert_eq!(
+            nearest_subnets("192.168.5.10", &subnets),
+            vec!["192.168.1.0/24", "10.0.0.0/24"]
+        );
+        assert_eq!(
+            nearest_subnets("10.0.9.1", &subnets),
+            vec!["10.0.0.0/24", "192.168.1.0/24"]
+        );
+        assert!(nearest_subnets("10.0.9.1", &[]).is_empty());
+
+        let subnets_v6 = vec![SubnetV6 {
+            uuid: "subnet-6".to_string(),
+            cidr: "2001:db8:42::/64".to_string(),
+            iface: None,
+        }];
+        assert_eq!(
+            nearest_subnets_v6("2001:db8:43::1", &subnets_v6),
+            vec!["2001:db8:42::/64"]
+        );
+    }
+
     #[test]
     fn test_ip_in_subnet_v6() {
         assert!(ip_in_subnet_v6("2001:db8::1", "2001:db8::/64").unwrap());
@@ -289,4 +580,243 @@ mod tests {
         );
         assert!(iface_for_ip_v6("2001:db8:99::1", &iface_cidrs).is_err());
     }
+
+    #[test]
+    fn test_netmask_conversions() {
+        assert_eq!(prefix_to_netmask(24).unwrap(), "255.255.255.0");
+        assert_eq!(prefix_to_netmask_v6(64).unwrap(), "ffff:ffff:ffff:ffff::");
+        assert_eq!(prefix_to_netmask_v6(0).unwrap(), "::");
+        assert!(prefix_to_netmask_v6(129).is_err());
+
+        assert_eq!(netmask_to_prefix("255.255.255.0").unwrap(), 24);
+        assert_eq!(netmask_to_prefix("0.0.0.0").unwrap(), 0);
+        assert!(netmask_to_prefix("255.0.255.0").is_err());
+        assert_eq!(netmask_to_prefix_v6("ffff:ffff:ffff:ffff::").unwrap(), 64);
+        assert!(netmask_to_prefix_v6("ffff::ffff").is_err());
+    }
+
+    #[test]
+    fn test_range_to_cidrs() {
+        assert_eq!(
+            range_to_cidrs("10.0.0.0", "10.0.0.255").unwrap(),
+            vec!["10.0.0.0/24"]
+        );
+        assert_eq!(
+            range_to_cidrs("10.0.0.0", "10.0.0.5").unwrap(),
+            vec!["10.0.0.0/30", "10.0.0.4/31"]
+        );
+        assert_eq!(
+            range_to_cidrs("192.168.1.100", "192.168.1.200").unwrap(),
+            vec![
+                "192.168.1.100/30",
+                "192.168.1.104/29",
+                "192.168.1.112/28",
+                "192.168.1.128/26",
+                "192.168.1.192/29",
+                "192.168.1.200/32"
+            ]
+        );
+        assert_eq!(
+            range_to_cidrs("10.0.0.7", "10.0.0.7").unwrap(),
+            vec!["10.0.0.7/32"]
+        );
+        assert_eq!(
+            range_to_cidrs("0.0.0.0", "255.255.255.255").unwrap(),
+            vec!["0.0.0.0/0"]
+        );
+        assert!(range_to_cidrs("10.0.0.9", "10.0.0.1").is_err());
+
+        assert_eq!(
+            range_to_cidrs_v6("fd00::", "fd00::ffff").unwrap(),
+            vec!["fd00::/112"]
+        );
+        assert_eq!(
+            range_to_cidrs_v6("fd00::1", "fd00::2").unwrap(),
+            vec!["fd00::1/128", "fd00::2/128"]
+        );
+        assert_eq!(
+            range_to_cidrs_v6("::", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff").unwrap(),
+            vec!["::/0"]
+        );
+    }
+
+    #[test]
+    fn test_covering_cidr() {
+        assert_eq!(
+            covering_cidr("192.168.1.100", "192.168.1.200").unwrap(),
+            "192.168.1.0/24"
+        );
+        assert_eq!(
+            covering_cidr("10.0.0.4", "10.0.0.5").unwrap(),
+            "10.0.0.4/31"
+        );
+        assert_eq!(
+            covering_cidr("10.0.0.4", "10.0.0.4").unwrap(),
+            "10.0.0.4/32"
+        );
+        assert_eq!(
+            covering_cidr("10.0.0.255", "10.0.1.0").unwrap(),
+            "10.0.0.0/23"
+        );
+        assert_eq!(
+            covering_cidr_v6("fd00:abcd::100", "fd00:abcd::1ff").unwrap(),
+            "fd00:abcd::100/120"
+        );
+        assert!(covering_cidr_v6("fd00::2", "fd00::1").is_err());
+    }
+
+    #[test]
+    fn test_ranges_overlap() {
+        assert!(ranges_overlap("10.0.0.1", "10.0.0.10", "10.0.0.10", "10.0.0.20").unwrap());
+        assert!(ranges_overlap("10.0.0.5", "10.0.0.6", "10.0.0.1", "10.0.0.10").unwrap());
+        assert!(!ranges_overlap("10.0.0.1", "10.0.0.9", "10.0.0.10", "10.0.0.20").unwrap());
+        assert!(ranges_overlap("10.0.0.1", "10.0.0.9", "10.0.0.20", "10.0.0.10").is_err());
+        assert!(ranges_overlap_v6("fd00::1", "fd00::10", "fd00::f", "fd00::20").unwrap());
+        assert!(!ranges_overlap_v6("fd00::1", "fd00::10", "fd00::11", "fd00::20").unwrap());
+        assert!(ranges_overlap_v6("fd00::1", "fd00::10", "10.0.0.1", "10.0.0.2").is_err());
+    }
+
+    #[test]
+    fn test_range_in_subnet() {
+        assert!(range_in_subnet("10.0.0.10", "10.0.0.20", "10.0.0.0/24").unwrap());
+        assert!(!range_in_subnet("10.0.0.10", "10.0.1.20", "10.0.0.0/24").unwrap());
+        assert!(range_in_subnet_v6("fd00::10", "fd00::20", "fd00::/64").unwrap());
+        assert!(!range_in_subnet_v6("fd00::10", "fd01::20", "fd00::/64").unwrap());
+    }
+
+    #[test]
+    fn test_usable_range_edges() {
+        let usable = |cidr| usable_range(cidr).unwrap();
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            usable("192.168.1.0/24"),
+            pair("192.168.1.1", "192.168.1.254")
+        );
+        assert_eq!(usable("10.0.0.0/30"), pair("10.0.0.1", "10.0.0.2"));
+        assert_eq!(usable("10.0.0.4/31"), pair("10.0.0.4", "10.0.0.5"));
+        assert_eq!(usable("10.0.0.7/32"), pair("10.0.0.7", "10.0.0.7"));
+        assert_eq!(usable("0.0.0.0/0"), pair("0.0.0.1", "255.255.255.254"));
+        assert_eq!(
+            usable("255.255.255.254/31"),
+            pair("255.255.255.254", "255.255.255.255")
+        );
+        // Host bits in the CIDR are ignored
+        assert_eq!(usable("10.0.0.5/31"), pair("10.0.0.4", "10.0.0.5"));
+        assert!(usable_range("10.0.0.0/33").is_err());
+
+        let usable = |cidr| usable_range_v6(cidr).unwrap();
+        assert_eq!(
+            usable("fd00::/64"),
+            pair("fd00::1", "fd00::ffff:ffff:ffff:ffff")
+        );
+        assert_eq!(usable("fd00::/126"), pair("fd00::1", "fd00::3"));
+        assert_eq!(usable("fd00::2/127"), pair("fd00::2", "fd00::3"));
+        assert_eq!(usable("fd00::9/128"), pair("fd00::9", "fd00::9"));
+        assert_eq!(
+            usable("::/0"),
+            pair("::1", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")
+        );
+    }
+
+    #[test]
+    fn test_clamp_range_edges() {
+        let clamp = |s, e, cidr| clamp_range(s, e, cidr).unwrap();
+        let some = |a: &str, b: &str| Some((a.to_string(), b.to_string()));
+        assert_eq!(
+            clamp("192.168.1.0", "192.168.1.255", "192.168.1.0/24"),
+            some("192.168.1.1", "192.168.1.254")
+        );
+        assert_eq!(
+            clamp("192.168.1.10", "192.168.1.20", "192.168.1.0/24"),
+            some("192.168.1.10", "192.168.1.20")
+        );
+        assert_eq!(
+            clamp("10.0.0.0", "10.0.0.3", "10.0.0.0/30"),
+            some("10.0.0.1", "10.0.0.2")
+        );
+        assert_eq!(clamp("10.0.0.0", "10.0.0.0", "10.0.0.0/30"), None);
+        assert_eq!(clamp("10.0.0.3", "10.0.0.3", "10.0.0.0/30"), None);
+        assert_eq!(
+            clamp("10.0.0.4", "10.0.0.5", "10.0.0.4/31"),
+            some("10.0.0.4", "10.0.0.5")
+        );
+        assert_eq!(
+            clamp("10.0.0.7", "10.0.0.7", "10.0.0.7/32"),
+            some("10.0.0.7", "10.0.0.7")
+        );
+        // Outside the subnet altogether
+        assert_eq!(clamp("10.0.1.1", "10.0.1.9", "10.0.0.0/24"), None);
+        assert!(clamp_range("10.0.0.9", "10.0.0.1", "10.0.0.0/24").is_err());
+        assert!(clamp_range("10.0.0.1", "10.0.0.9", "10.0.0.0").is_err());
+
+        let clamp = |s, e, cidr| clamp_range_v6(s, e, cidr).unwrap();
+        assert_eq!(
+            clamp("fd00::", "fd00::ff", "fd00::/64"),
+            some("fd00::1", "fd00::ff")
+        );
+        assert_eq!(clamp("fd00::", "fd00::", "fd00::/64"), None);
+        assert_eq!(
+            clamp("fd00::2", "fd00::3", "fd00::2/127"),
+            some("fd00::2", "fd00::3")
+        );
+        assert_eq!(
+            clamp("fd00::9", "fd00::9", "fd00::9/128"),
+            some("fd00::9", "fd00::9")
+        );
+    }
+
+    #[test]
+    fn test_point_to_point_subnet_matching() {
+        let subnets = vec![
+            Subnet {
+                uuid: "p2p".to_string(),
+                cidr: "10.0.0.4/31".to_string(),
+                iface: None,
+            },
+            Subnet {
+                uuid: "host".to_string(),
+                cidr: "10.0.0.7/32".to_string(),
+                iface: None,
+            },
+            Subnet {
+                uuid: "wide".to_string(),
+                cidr: "10.0.0.0/24".to_string(),
+                iface: None,
+            },
+        ];
+        for (ip, uuid) in [
+            ("10.0.0.4", "p2p"),
+            ("10.0.0.5", "p2p"),
+            ("10.0.0.7", "host"),
+            ("10.0.0.6", "wide"),
+        ] {
+            assert_eq!(find_subnet_for_ip(ip, &subnets).unwrap(), uuid, "{}", ip);
+        }
+        assert!(range_in_subnet("10.0.0.4", "10.0.0.5", "10.0.0.4/31").unwrap());
+        assert!(!range_in_subnet("10.0.0.4", "10.0.0.6", "10.0.0.4/31").unwrap());
+        assert_eq!(
+            covering_cidr("10.0.0.4", "10.0.0.5").unwrap(),
+            "10.0.0.4/31"
+        );
+        assert_eq!(
+            covering_cidr("10.0.0.7", "10.0.0.7").unwrap(),
+            "10.0.0.7/32"
+        );
+
+        let subnets = vec![
+            SubnetV6 {
+                uuid: "p2p".to_string(),
+                cidr: "fd00::2/127".to_string(),
+                iface: None,
+            },
+            SubnetV6 {
+                uuid: "host".to_string(),
+                cidr: "fd00::9/128".to_string(),
+                iface: None,
+            },
+        ];
+        assert_eq!(find_subnet_for_ip_v6("fd00::3", &subnets).unwrap(), "p2p");
+        assert_eq!(find_subnet_for_ip_v6("fd00::9", &subnets).unwrap(), "host");
+        assert!(find_subnet_for_ip_v6("fd00::4", &subnets).is_err());
+    }
 }
@@ -0,0 +1,121 @@
+//! Colour for terminal output.
+//!
+//! Output is plain until the CLI turns colour on with `--color`, so library
+//! callers never see escape codes. Verbose per-mapping lines and warnings
+//! are printed through here so their tags can be coloured by what happened.
+
+use anstyle::{AnsiColor, Style};
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::ColorChoice;
+
+static STDOUT_COLOR: AtomicBool = AtomicBool::new(false);
+static STDERR_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Entries added
+pub(crate) const ADDED: Style = AnsiColor::Green.on_default();
+/// Entries left out
+pub(crate) const SKIPPED: Style = AnsiColor::Yellow.on_default();
+/// Other per-mapping notes
+pub(crate) const NOTE: Style = AnsiColor::Cyan.on_default();
+pub(crate) const WARNING: Style = AnsiColor::Yellow.on_default().bold();
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+pub(crate) const HEADING: Style = Style::new().bold();
+
+/// Colour stdout and stderr as `choice` says. `Auto` colours a stream that
+/// is a terminal unless `NO_COLOR` is set or `TERM` is `dumb`.
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+pub(crate) fn set_color(choice: ColorChoice) {
+    let (stdout, stderr) = match choice {
+        ColorChoice::Always => (true, true),
+        ColorChoice::Never => (false, false),
+        ColorChoice::Auto => {
+            let allowed = env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                && env::var_os("TERM").is_none_or(|term| term != "dumb");
+            (
+                allowed && io::stdout().is_terminal(),
+                allowed && io::stderr().is_terminal(),
+            )
+        }
+    };
+    STDOUT_COLOR.store(stdout, Ordering::Relaxed);
+    STDERR_COLOR.store(stderr, Ordering::Relaxed);
+}
+
+/// `text` in `style` when stdout is coloured.
+pub(crate) fn paint<T: fmt::Display>(style: Style, text: T) -> Painted<T> {
+    Painted {
+        style: STDOUT_COLOR.load(Ordering::Relaxed).then_some(style),
+        text,
+    }
+}
+
+/// `text` in `style` when stderr is coloured.
+fn paint_stderr<T: fmt::Display>(style: Style, text: T) -> Painted<T> {
+    Painted {
+        style: STDERR_COLOR.load(Ordering::Relaxed).then_some(style),
+        text,
+    }
+}
+
+/// Text wrapped in a style's escape codes, if any.
+pub(crate) struct Painted<T> {
+    style: Option<Style>,
+    text: T,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.style {
+            Some(style) => write!(f, "{}{}{:#}", style, self.text, style),
+            None => self.text.fmt(f),
+        }
+    }
+}
+
+/// Print a verbose `  TAG: message` line, the tag coloured by whether the
+/// entry was added or left out.
+pub(crate) fn action(tag: &str, message: fmt::Arguments) {
+    println!("  {}: {}", paint(tag_style(tag), tag), message);
+}
+
+/// Print `Warning: message` to stderr.
+pub(crate) fn warning(message: fmt::Arguments) {
+    eprintln!("{}: {}", paint_stderr(WARNING, "Warning"), message);
+}
+
+fn tag_style(tag: &str) -> Style {
+    match tag.split(' ').next().unwrap_or(tag) {
+        "ADD" | "ADD6" | "EXPORT" => ADDED,
+        "SKIP" | "SKIP6" | "IGNORE" | "IGNORE6" | "ORPHAN" | "ORPHAN6" | "STALE" | "NOPREFIX6" => {
+            SKIPPED
+        }
+        _ => NOTE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        let style = ADDED;
+        let colored = Painted {
+            style: Some(style),
+            text: "ADD",
+        };
+        assert_eq!(colored.to_string(), "\u{1b}[32mADD\u{1b}[0m");
+        let plain = Painted {
+            style: None,
+            text: "ADD",
+        };
+        assert_eq!(plain.to_string(), "ADD");
+        assert_eq!(tag_style("SKIP DNS"), SKIPPED);
+        assert_eq!(tag_style("ADD6"), ADDED);
+        assert_eq!(tag_style("UUID"), NOTE);
+    }
+}
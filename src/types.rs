@@ -1,4 +1,7 @@
-#[derive(Debug, Clone)]
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IscStaticMap {
     pub iface: String,
     pub mac: String,
@@ -6,9 +9,19 @@ pub struct IscStaticMap {
     pub hostname: Option<String>,
     pub cid: Option<String>,
     pub descr: Option<String>,
+    pub domain: Option<String>,
+    /// Per-host domain search list, overriding the interface's
+    pub domain_search: Option<String>,
+    /// Per-host DNS servers, overriding the interface's
+    pub dns_servers: Vec<String>,
+    /// Per-host gateway, overriding the interface's
+    pub gateway: Option<String>,
+    /// `<gateway>none</gateway>`: the host gets no router option (3), not
+    /// the interface's
+    pub gateway_suppressed: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IscStaticMapV6 {
     pub iface: String,
     pub duid: String,
@@ -16,39 +29,179 @@ pub struct IscStaticMapV6 {
     pub hostname: Option<String>,
     pub descr: Option<String>,
     pub domain_search: Option<String>,
+    /// Delegated prefix (IA_PD) reserved for the host, without the length
+    pub prefix: Option<String>,
+    pub prefix_len: Option<u8>,
 }
 
-#[derive(Debug, Clone)]
+impl IscStaticMapV6 {
+    /// The delegated prefix as `prefix/len`, when both parts are set.
+    pub fn delegated_prefix(&self) -> Option<String> {
+        Some(format!("{}/{}", self.prefix.as_ref()?, self.prefix_len?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IscDhcpOptionsV4 {
     pub iface: String,
     pub dns_servers: Vec<String>,
     pub routers: Option<String>,
+    /// `<gateway>none</gateway>`: the ISC server sends no router option (3)
+    /// instead of its own address
+    pub routers_suppressed: bool,
     pub domain_name: Option<String>,
     pub domain_search: Option<String>,
     pub ntp_servers: Vec<String>,
+    pub wins_servers: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IscDhcpOptionsV6 {
     pub iface: String,
     pub dns_servers: Vec<String>,
     pub domain_search: Option<String>,
+    pub ntp_servers: Vec<String>,
+    pub sip_servers: Vec<String>,
+    pub bootfile_url: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// A custom numbered ISC DHCPv4 option (`<numberoptions><item>`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IscNumberOption {
+    pub iface: String,
+    pub number: u8,
+    /// ISC option type as configured (`text`, `unsigned integer 8`, ...)
+    pub kind: Option<String>,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IscRangeV4 {
     pub iface: String,
     pub from: String,
     pub to: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IscRangeV6 {
     pub iface: String,
     pub from: String,
     pub to: String,
 }
 
+/// A DHCPv4 lease read from an ISC `dhcpd.leases` file. Times are seconds
+/// since the Unix epoch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IscLease {
+    pub ip: String,
+    /// Lowercase hardware address
+    pub mac: Option<String>,
+    pub starts: Option<u64>,
+    pub ends: Option<u64>,
+    /// Client's last transaction time
+    pub cltt: Option<u64>,
+    pub binding_state: Option<String>,
+    pub hostname: Option<String>,
+}
+
+/// A device holding an active lease on an address other than the one
+/// reserved for its hardware address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaseMismatch {
+    /// Lowercase hardware address
+    pub mac: String,
+    /// Hostname of the reservation, or else of the lease
+    pub hostname: Option<String>,
+    pub reserved_ip: String,
+    pub leased_ip: String,
+    /// End of the lease, in seconds since the Unix epoch
+    pub lease_ends: Option<u64>,
+}
+
+/// Active leases compared with the DHCPv4 reservations of a converted
+/// config (see [`audit_config`](crate::audit_config)).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeaseAudit {
+    /// Reservations with a hardware address
+    pub reservations: usize,
+    /// Leases active at the time of the audit
+    pub active_leases: usize,
+    /// Reservations whose device leases an address reserved for it
+    pub matched: usize,
+    /// Reservations with no active lease for their hardware address
+    pub not_leasing: usize,
+    pub mismatched: Vec<LeaseMismatch>,
+}
+
+/// A hostname given to several addresses of one family by the target
+/// backend's entries and the mappings migrated to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostnameCollision {
+    pub hostname: String,
+    /// Distinct addresses using the name, all IPv4 or all IPv6
+    pub ips: Vec<String>,
+}
+
+/// An ISC option value with no address for the option's family, left out
+/// of the migrated option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedOptionValue {
+    /// `dhcpd` or `dhcpdv6`
+    pub section: String,
+    pub iface: String,
+    /// ISC field, e.g. `ntpserver`
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+/// XMLRPC config sync (`<hasync>`) that carries DHCP sections to a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HaSync {
+    /// `synchronizetoip`: the backup the config is pushed to
+    pub peer: String,
+    /// DHCP sync items enabled, such as `dhcpd` or `kea`
+    pub sections: Vec<String>,
+}
+
+/// Repeated warnings of one kind beyond `max_warnings`, printed only as a
+/// count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuppressedWarnings {
+    /// What the warnings were about, e.g. "Kea subnets already existed and
+    /// were skipped"
+    pub summary: String,
+    /// Warnings of this kind, printed or not
+    pub count: usize,
+    /// Warnings of this kind that were printed
+    pub shown: usize,
+}
+
+/// How recently a static mapping's device held a lease.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LeaseStatus {
+    /// No lease matches the mapping's MAC or IP
+    NeverSeen,
+    /// Last seen longer ago than the stale threshold
+    Stale,
+    /// Seen within the stale threshold, with no active lease now
+    Recent,
+    /// Holds an active lease that has not ended
+    Online,
+}
+
+/// Lease activity of one ISC static mapping (see [`MigrationOptions::leases`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaseActivity {
+    pub iface: String,
+    pub mac: String,
+    pub ipaddr: String,
+    pub hostname: Option<String>,
+    pub status: LeaseStatus,
+    /// Latest lease start or client transaction time
+    pub last_seen: Option<u64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Subnet {
     pub uuid: String,
@@ -70,22 +223,484 @@ pub type KeaSubnetV6 = SubnetV6;
 pub struct MigrationStats {
     pub isc_mappings_found: usize,
     pub isc_mappings_v6_found: usize,
+    /// Static mappings ignored because ISC DHCP is disabled on their interface
+    pub isc_mappings_disabled_iface: usize,
+    pub isc_mappings_v6_disabled_iface: usize,
+    /// Static mappings under a section for an interface missing from
+    /// `<interfaces>` (see [`OrphanedIfacePolicy`])
+    pub isc_mappings_orphaned_iface: usize,
+    pub isc_mappings_v6_orphaned_iface: usize,
+    /// DHCPv4 static mappings with a MAC but no IP address, kept in ISC as
+    /// placeholders
+    pub isc_placeholders: Vec<IscStaticMap>,
+    /// Placeholders created (or, in a scan, that would be created) as Kea
+    /// reservations without an address by `migrate_placeholders`
+    pub placeholders_migrated: usize,
     pub isc_ranges_found: usize,
     pub isc_ranges_v6_found: usize,
+    /// ISC ranges merged into a neighbouring one (`merge_ranges`)
+    pub ranges_merged: usize,
+    /// Kea subnets created by `create_subnets`, and the pools on them
+    pub subnets_created: usize,
+    pub pools_created: usize,
+    /// dnsmasq DHCP ranges created by `create_subnets`
+    pub ranges_created: usize,
+    /// Option values written by `create_options`, and those left as they
+    /// were because the target already had them set
+    pub options_created: usize,
+    pub options_skipped: usize,
     pub target_subnets_found: usize,
     pub target_subnets_v6_found: usize,
     pub reservations_to_create: usize,
     pub reservations_v6_to_create: usize,
     pub reservations_skipped: usize,
     pub reservations_v6_skipped: usize,
+    /// Why each skipped mapping was skipped: a duplicate of an existing
+    /// entry, or outside every subnet with `skip_unmatched`
+    pub skips: Vec<MigrationError>,
+    /// DHCPv6 reservations created with a delegated prefix
+    pub prefix_reservations_v6: usize,
+    /// Custom numbered ISC DHCPv4 options written by a Kea JSON export, and
+    /// the `option-def` entries written for codes Kea does not define
+    pub custom_options_exported: usize,
+    pub option_defs_exported: usize,
+    /// DHCPv6 mappings whose delegated prefix is left out, because the
+    /// backend cannot reserve prefixes or the prefix has no length
+    pub prefixes_v6_unmigrated: usize,
     pub interfaces_configured: Vec<String>,
+    /// Interfaces removed from dnsmasq's "disable DHCP on" list
+    /// (`<dhcp><no_interface>`) because the migration serves them
+    pub no_dhcp_cleared: Vec<String>,
+    /// Interfaces with migrated Kea reservations that Kea does not listen on
+    pub interfaces_not_listening: Vec<String>,
     pub isc_disabled_v4: Vec<String>,
     pub isc_disabled_v6: Vec<String>,
     pub backend_enabled_v4: bool,
     pub backend_enabled_v6: bool,
+    pub fwrules_v4: Option<FirewallRulesStatus>,
+    pub fwrules_v6: Option<FirewallRulesStatus>,
+    /// Interface key to `<descr>` label, for readable reports
+    pub interface_labels: HashMap<String, String>,
+    /// Per-backend stats when running with `--backend both`
+    pub per_backend: Vec<(Backend, MigrationStats)>,
+    /// dnsmasq DNS host entries from `--create-dns-hosts`
+    pub dns_hosts_created: usize,
+    /// Existing dnsmasq hosts whose empty domain was filled in
+    pub dns_hosts_updated: usize,
+    pub dns_hosts_skipped: usize,
+    /// Unbound host overrides from `--register-unbound`
+    pub unbound_hosts_created: usize,
+    pub unbound_hosts_skipped: usize,
+    /// Scalability warnings for the converted config (see [`ConfigLimits`])
+    pub warnings: Vec<String>,
+    /// Mappings selected by `offset`/`limit`, when either is set
+    pub batch: Option<MappingBatch>,
+    /// Created elements stamped with an origin marker (`mark_created`)
+    pub markers_added: usize,
+    /// Origin markers removed from the config (`strip_markers`)
+    pub markers_stripped: usize,
+    /// Paths of the sections written with [`EmitMode::ChangesOnly`]
+    pub changed_sections: Vec<String>,
+    /// DHCPv4 static mapping activity when leases are given, least active first
+    pub lease_activity: Vec<LeaseActivity>,
+    /// Static mappings skipped by `skip_stale_days`
+    pub isc_mappings_stale: usize,
+    /// DHCPv6 mappings paired with a DHCPv4 mapping by `link_dual_stack`
+    pub dual_stack_linked: usize,
+    /// Duplicate `uuid`s regenerated by `fix_duplicate_uuids`
+    pub uuids_regenerated: usize,
+    /// Hostnames that would resolve to more than one address per family
+    pub hostname_collisions: Vec<HostnameCollision>,
+    /// Option values naming an interface, alias or host that were replaced
+    /// by its addresses
+    pub option_values_resolved: usize,
+    /// Option values left out for want of an address
+    pub unresolved_option_values: Vec<UnresolvedOptionValue>,
+    /// DHCP config sync to an HA peer, when this node is a sync primary
+    pub ha_sync: Option<HaSync>,
+    /// Repeated warnings left unprinted by `max_warnings`
+    pub warnings_suppressed: Vec<SuppressedWarnings>,
+    /// Settings a scan found that must be re-created by hand, such as ISC
+    /// failover, prefix delegation ranges and custom options
+    pub manual_actions: Vec<String>,
+    pub timing: MigrationTiming,
+}
+
+/// The slice of ISC static mappings migrated in one run with
+/// `offset`/`limit`.
+///
+/// Mappings are counted in migration order, DHCPv4 first and then DHCPv6,
+/// after disabled interfaces are dropped and `reservation_order` is applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MappingBatch {
+    /// Position of the first selected mapping
+    pub offset: usize,
+    /// Mappings in this batch
+    pub selected: usize,
+    /// Mappings available before batching
+    pub total: usize,
+}
+
+impl MappingBatch {
+    /// Offset to continue from in the next run
+    pub fn next_offset(&self) -> usize {
+        self.offset + self.selected
+    }
+
+    /// Mappings left after this batch
+    pub fn remaining(&self) -> usize {
+        self.total.saturating_sub(self.next_offset())
+    }
+}
+
+/// Time spent in each migration phase, plus XML element counts.
+///
+/// Measured locally for `--timing`; nothing is reported anywhere else.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationTiming {
+    /// Parsing the input XML
+    pub parse: Duration,
+    /// Extracting and filtering ISC mappings
+    pub extract: Duration,
+    /// Scanning or converting into the target backend
+    pub convert: Duration,
+    /// Writing the resulting XML (zero for scans)
+    pub write: Duration,
+    /// Elements in the parsed input config
+    pub input_elements: usize,
+    /// Elements in the resulting config
+    pub output_elements: usize,
+}
+
+impl MigrationTiming {
+    /// Sum of all measured phases
+    pub fn total(&self) -> Duration {
+        self.parse + self.extract + self.convert + self.write
+    }
+}
+
+/// State of the Kea `<general><fwrules>` setting after enabling the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirewallRulesStatus {
+    /// Automatic firewall rules were already enabled
+    AlreadyEnabled,
+    /// Automatic firewall rules were switched on by the migration
+    Enabled,
+    /// No `fwrules` setting exists in the config (older Kea plugin)
+    Missing,
 }
 
 use crate::backend::Backend;
+use crate::clock::Clock;
+use crate::errors::MigrationError;
+use crate::overrides::FieldOverrides;
+use crate::plan::MigrationPlan;
+
+/// Order in which newly created reservations/hosts are appended.
+///
+/// Existing entries in the target backend are never reordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ReservationOrder {
+    /// Keep ISC discovery order (default)
+    #[default]
+    Discovery,
+    /// Sort by IP address
+    Ip,
+    /// Sort by interface, then IP address
+    IfaceIp,
+}
+
+/// How ISC hostnames are written into created reservations and hosts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum HostnamePolicy {
+    /// Write hostnames as they appear in ISC (default)
+    #[default]
+    Keep,
+    /// Drop everything after the first dot
+    Short,
+    /// Join the short hostname with the chosen domain
+    Fqdn,
+}
+
+/// How created dnsmasq ranges that overlap an existing range on the same
+/// interface are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum RangeOverlapPolicy {
+    /// Keep the existing range and skip the new one (default)
+    #[default]
+    Skip,
+    /// Remove the overlapping existing ranges and add the new one
+    Force,
+    /// Abort and report every overlap
+    Fail,
+}
+
+/// How an interface's DHCPv4 and DHCPv6 domain search lists are written
+/// to dnsmasq when ISC has different ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DnsmasqOptionConflict {
+    /// Give DHCPv6 clients the DHCPv4 list
+    PreferV4,
+    /// Give DHCPv4 clients the DHCPv6 list
+    PreferV6,
+    /// Keep both lists, the DHCPv6 one matching the `dhcpv6` tag (default)
+    #[default]
+    KeepBothTagged,
+}
+
+/// How static mappings under a `dhcpd`/`dhcpdv6` section for an interface
+/// that no longer exists in `<interfaces>` are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OrphanedIfacePolicy {
+    /// Abort and list the mappings (default)
+    #[default]
+    Fail,
+    /// Leave the mappings out
+    Skip,
+    /// Move each mapping to the interface whose subnet holds its IP, and
+    /// leave it out when there is none
+    SubnetMatch,
+}
+
+/// Where static mappings are read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Source {
+    /// The ISC sections, or another source when they hold no mappings and
+    /// its data is present (default)
+    #[default]
+    Auto,
+    /// The ISC `<dhcpd>`/`<dhcpdv6>` sections
+    Dhcpd,
+    /// The legacy `dhcp-static-leases` plugin (`<OPNsense><dhcpleases>`),
+    /// DHCPv4 only
+    DhcpStaticLeases,
+}
+
+/// What to do when Kea `<general><interfaces>` leaves out an interface that
+/// migrated reservations are served on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum KeaInterfacePolicy {
+    /// Warn and leave `<interfaces>` as it is (default)
+    #[default]
+    Warn,
+    /// Add the missing interfaces
+    Append,
+}
+
+/// How `--create-options` treats a subnet that already has some options set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OptionsMerge {
+    /// Set only the options that are still empty (default)
+    #[default]
+    FillMissing,
+    /// Replace existing options with the ISC values
+    Overwrite,
+    /// Leave a subnet with any option already set untouched
+    Skip,
+}
+
+/// `mode` written on created dnsmasq DHCPv6 ranges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DnsmasqV6Mode {
+    /// Derive from the ISC router advertisement mode (default)
+    #[default]
+    Auto,
+    /// Stateful DHCPv6 only (empty mode)
+    Stateful,
+    /// Only hand out static host reservations
+    Static,
+    /// DHCPv6 plus SLAAC
+    Slaac,
+    /// Router advertisements only, no DHCPv6 addresses
+    RaOnly,
+    /// SLAAC with DNS names derived from DHCPv4 leases
+    RaNames,
+    /// SLAAC with stateless DHCPv6 for options
+    RaStateless,
+    /// Advertise the router address in router advertisements
+    RaAdvrouter,
+}
+
+/// File format written by `export`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ExportFormat {
+    /// MikroTik RouterOS script (.rsc)
+    #[default]
+    Routeros,
+    /// OpenWrt /etc/config/dhcp (UCI) for dnsmasq and odhcpd
+    Uci,
+    /// Kea DHCPv4 JSON configuration (kea-dhcp4.conf)
+    KeaDhcp4,
+    /// Kea DHCPv6 JSON configuration (kea-dhcp6.conf)
+    KeaDhcp6,
+}
+
+/// What `convert` writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum EmitMode {
+    /// The whole converted config.xml (default)
+    #[default]
+    Full,
+    /// Only the ISC, Kea, dnsmasq and Unbound sections that changed, each as
+    /// a standalone XML fragment
+    ChangesOnly,
+}
+
+/// Order of attributes on written elements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum AttributeOrder {
+    /// The order they had in the input (default)
+    #[default]
+    Keep,
+    /// Alphabetical by name
+    Sorted,
+}
+
+/// How the converted config.xml is formatted. OPNsense versions normalize
+/// config.xml slightly differently; matching the firewall's style keeps
+/// diffs of configs kept in git small.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputStyle {
+    /// Spaces per indentation level
+    pub indent: usize,
+    /// Start the document with `<?xml version="1.0" encoding="UTF-8"?>`
+    pub declaration: bool,
+    pub attribute_order: AttributeOrder,
+}
+
+impl Default for OutputStyle {
+    fn default() -> Self {
+        OutputStyle {
+            indent: 2,
+            declaration: true,
+            attribute_order: AttributeOrder::Keep,
+        }
+    }
+}
+
+/// When terminal output is coloured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ColorChoice {
+    /// Colour output written to a terminal, unless `NO_COLOR` is set
+    /// (default)
+    #[default]
+    Auto,
+    /// Always colour output
+    Always,
+    /// Never colour output
+    Never,
+}
+
+/// What `inspect` prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum InspectFormat {
+    /// Aligned tables, one per kind of entry (default)
+    #[default]
+    Table,
+    /// The same entries as a JSON document
+    Json,
+}
+
+/// What `verify` prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum VerifyFormat {
+    /// Unified diff of the normalized config (default)
+    #[default]
+    Diff,
+    /// Findings as a JSON document
+    Json,
+    /// Findings as CSV with a header row
+    Csv,
+}
+
+/// How serious a `verify` finding is, least serious first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Severity {
+    /// Bookkeeping only, such as `--mark-created` markers
+    #[default]
+    Info,
+    /// Settings that would change without touching reservations
+    Warning,
+    /// Reservations that would be added or removed, or removed subnets and
+    /// options
+    Error,
+}
+
+/// Part of the config a `verify` finding belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum FindingCategory {
+    /// Kea reservations, dnsmasq hosts and Unbound host overrides
+    #[cfg_attr(feature = "cli", value(name = "reservations", alias = "reservation"))]
+    Reservation,
+    /// Kea subnets and dnsmasq ranges
+    #[cfg_attr(feature = "cli", value(name = "subnets", alias = "subnet"))]
+    Subnet,
+    /// Kea option data and dnsmasq DHCP options
+    #[cfg_attr(feature = "cli", value(name = "options", alias = "option"))]
+    Option,
+    /// Everything else: enable flags, listening interfaces, firewall rules
+    #[cfg_attr(feature = "cli", value(name = "services", alias = "service"))]
+    Service,
+}
+
+/// What happened to the element behind a `verify` finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One difference between a config and its converted form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyFinding {
+    pub severity: Severity,
+    pub category: FindingCategory,
+    pub change: FindingChange,
+    /// `/`-separated element path; elements with a `uuid` carry it in
+    /// brackets, repeated siblings without one their 1-based position
+    pub path: String,
+    /// The element's non-empty fields for added and removed elements, or
+    /// `old -> new` for changed values
+    pub detail: String,
+}
+
+/// Practical limits of the OPNsense GUI checked against the converted config.
+///
+/// A value of 0 disables the corresponding check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigLimits {
+    /// Reservations in a single Kea subnet before the GUI gets sluggish
+    pub max_reservations_per_subnet: usize,
+    /// Longest reservation/host description shown cleanly in the GUI
+    pub max_description_len: usize,
+}
+
+impl Default for ConfigLimits {
+    fn default() -> Self {
+        ConfigLimits {
+            max_reservations_per_subnet: 1000,
+            max_description_len: 255,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct MigrationOptions {
@@ -94,7 +709,91 @@ pub struct MigrationOptions {
     pub backend: Backend,
     pub create_subnets: bool,
     pub force_subnets: bool,
+    /// Add ISC ranges as pools of the Kea subnets that already exist for
+    /// them, matched by CIDR or by interface
+    pub create_pools: bool,
     pub create_options: bool,
+    /// Look up hostnames in address-valued ISC options with the system
+    /// resolver instead of leaving them out
+    pub resolve_hostnames: bool,
     pub force_options: bool,
+    pub options_merge: OptionsMerge,
     pub enable_backend: bool,
+    pub reservation_order: ReservationOrder,
+    pub skip_unmatched: bool,
+    pub include_disabled_interfaces: bool,
+    pub orphaned_ifaces: OrphanedIfacePolicy,
+    pub kea_interfaces: KeaInterfacePolicy,
+    pub range_overlap: RangeOverlapPolicy,
+    pub dnsmasq_option_conflict: DnsmasqOptionConflict,
+    pub dnsmasq_v6_mode: DnsmasqV6Mode,
+    pub dnsmasq_constructor: bool,
+    /// `local` flag on created dnsmasq hosts
+    pub dnsmasq_hosts_local: bool,
+    /// Read `[no-dns]`, `[ignore]`, `[local]` and `[no-local]` tags in ISC
+    /// descriptions into the flags of created dnsmasq hosts
+    pub dnsmasq_host_tags: bool,
+    /// Leave reservation IPs out of the pools of created Kea subnets
+    pub carve_pools: bool,
+    /// Merge overlapping and adjacent ISC ranges of an interface into
+    /// minimal pools before creating them
+    pub merge_ranges: bool,
+    pub minimal_elements: bool,
+    pub create_dns_hosts: bool,
+    pub register_unbound: bool,
+    pub limits: ConfigLimits,
+    /// Skip this many ISC mappings (see [`MappingBatch`])
+    pub offset: usize,
+    /// Migrate at most this many ISC mappings, starting at `offset`
+    pub limit: Option<usize>,
+    /// Stamp created elements with an origin marker ([`crate::ORIGIN_ATTR`])
+    /// and the time they were created ([`crate::CREATED_ATTR`])
+    pub mark_created: bool,
+    /// Remove origin and created-at markers left by earlier runs
+    pub strip_markers: bool,
+    /// Check the Kea, dnsmasq and Unbound elements the migration writes
+    /// against the bundled OPNsense schema before writing
+    pub validate_schema: bool,
+    /// Where static mappings are read from
+    pub source: Source,
+    /// Fall back to legacy `<installedpackages>` locations when the standard
+    /// ISC sections hold no static mappings
+    pub legacy_paths: bool,
+    pub emit: EmitMode,
+    /// ISC DHCPv4 leases to report static mapping activity against
+    pub leases: Vec<IscLease>,
+    /// Skip static mappings whose device was last seen more than this many
+    /// days ago; without it, 180 days marks a mapping stale in the report
+    pub skip_stale_days: Option<u64>,
+    /// Time used for lease staleness and created-at markers
+    pub clock: Clock,
+    pub hostname_policy: HostnamePolicy,
+    /// Domain joined to hostnames with [`HostnamePolicy::Fqdn`]
+    pub hostname_domain: Option<String>,
+    /// Give elements that reuse an earlier element's `uuid` in the target
+    /// backend a new one instead of failing, rewiring Kea reservations
+    pub fix_duplicate_uuids: bool,
+    /// Pair DHCPv6 mappings with the DHCPv4 mapping of the same host (by
+    /// the MAC in the DUID, then hostname) and note each other's address
+    /// in their descriptions
+    pub link_dual_stack: bool,
+    /// Where created reservations and hosts take their hostname and
+    /// description from
+    pub field_overrides: FieldOverrides,
+    /// Formatting of the written config
+    pub output_style: OutputStyle,
+    /// Convert an HA sync primary whose DHCP sections sync to a backup
+    pub allow_ha: bool,
+    /// Print at most this many warnings of each repeated kind (such as
+    /// existing subnets or options), counting the rest; all when `None`
+    pub max_warnings: Option<usize>,
+    /// Create Kea reservations without an address for static mappings that
+    /// only have a MAC, in the subnet serving their interface
+    pub migrate_placeholders: bool,
+    /// Treat a DUID or IPv6 address reserved in any Kea subnet as taken,
+    /// instead of only within the mapping's subnet
+    pub global_duids: bool,
+    /// Migrate only the mappings this plan lists, into the Kea subnets it
+    /// names
+    pub plan: Option<MigrationPlan>,
 }
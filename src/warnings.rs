@@ -0,0 +1,160 @@
+//! Per-item warnings that repeat on large configs.
+//!
+//! Warnings about entries that already exist or cannot be migrated can come
+//! once per subnet, option or mapping. Within a [`WarningScope`] each kind
+//! is printed at most `max_warnings` times and the rest are only counted;
+//! [`WarningScope::finish`] returns the counts for the report. Outside a
+//! scope every warning is printed.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::term;
+use crate::SuppressedWarnings;
+
+/// Kinds of repeated warnings, in report order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum WarningKind {
+    #[cfg(feature = "kea")]
+    KeaSubnetExists,
+    #[cfg(feature = "kea")]
+    KeaPoolExists,
+    #[cfg(feature = "dnsmasq")]
+    DnsmasqRangeExists,
+    OptionsSet,
+    OptionValueUnresolved,
+    #[cfg(feature = "dnsmasq")]
+    DnsmasqBootExists,
+    DnsNameTaken,
+    MappingDropped,
+}
+
+impl WarningKind {
+    fn summary(self) -> &'static str {
+        match self {
+            #[cfg(feature = "kea")]
+            WarningKind::KeaSubnetExists => "Kea subnets already existed and were skipped",
+            #[cfg(feature = "kea")]
+            WarningKind::KeaPoolExists => "Kea pools overlapped existing ones and were skipped",
+            #[cfg(feature = "dnsmasq")]
+            WarningKind::DnsmasqRangeExists => {
+                "dnsmasq ranges already existed or overlapped and were skipped"
+            }
+            WarningKind::OptionsSet => "options were already set and were skipped",
+            WarningKind::OptionValueUnresolved => "option values had no address and were left out",
+            #[cfg(feature = "dnsmasq")]
+            WarningKind::DnsmasqBootExists => {
+                "dnsmasq boot entries already existed and were skipped"
+            }
+            WarningKind::DnsNameTaken => "DNS names already pointed elsewhere and were not added",
+            WarningKind::MappingDropped => "static mappings could not be migrated and were skipped",
+        }
+    }
+}
+
+struct Limiter {
+    max: Option<usize>,
+    counts: BTreeMap<WarningKind, usize>,
+}
+
+thread_local! {
+    static LIMITER: RefCell<Option<Limiter>> = const { RefCell::new(None) };
+}
+
+/// Print a `kind` warning unless the current scope has printed enough.
+pub(crate) fn warn(kind: WarningKind, message: fmt::Arguments) {
+    let print = LIMITER.with(|limiter| match limiter.borrow_mut().as_mut() {
+        Some(limiter) => {
+            let count = limiter.counts.entry(kind).or_default();
+            *count += 1;
+            limiter.max.is_none_or(|max| *count <= max)
+        }
+        None => true,
+    });
+    if print {
+        term::warning(message);
+    }
+}
+
+/// Caps repeated warnings until finished or dropped.
+pub(crate) struct WarningScope {
+    /// Whether this scope installed the limiter; nested scopes leave the
+    /// outer one counting
+    owner: bool,
+}
+
+impl WarningScope {
+    pub(crate) fn begin(max: Option<usize>) -> Self {
+        let owner = LIMITER.with(|limiter| {
+            let mut limiter = limiter.borrow_mut();
+            if limiter.is_some() {
+                return false;
+            }
+            *limiter = Some(Limiter {
+                max,
+                counts: BTreeMap::new(),
+            });
+            true
+        });
+        WarningScope { owner }
+    }
+
+    /// Kinds with warnings left unprinted, with how many there were.
+    pub(crate) fn finish(self) -> Vec<SuppressedWarnings> {
+        if !self.owner {
+            return Vec::new();
+        }
+        let Some(limiter) = LIMITER.with(|limiter| limiter.borrow_mut().take()) else {
+            return Vec::new();
+        };
+        let Some(max) = limiter.max else {
+            return Vec::new();
+        };
+        limiter
+            .counts
+            .into_iter()
+            .filter(|(_, count)| *count > max)
+            .map(|(kind, count)| SuppressedWarnings {
+                summary: kind.summary().to_string(),
+                count,
+                shown: max,
+            })
+            .collect()
+    }
+}
+
+impl Drop for WarningScope {
+    fn drop(&mut self) {
+        if self.owner {
+            LIMITER.with(|limiter| limiter.borrow_mut().take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_scope_caps_each_kind() {
+        let scope = WarningScope::begin(Some(2));
+        for i in 0..5 {
+            warn(WarningKind::OptionsSet, format_args!("option {}", i));
+        }
+        warn(WarningKind::MappingDropped, format_args!("mapping"));
+        let nested = WarningScope::begin(None);
+        warn(WarningKind::OptionsSet, format_args!("nested"));
+        assert!(nested.finish().is_empty());
+
+        assert_eq!(
+            scope.finish(),
+            vec![SuppressedWarnings {
+                summary: "options were already set and were skipped".to_string(),
+                count: 6,
+                shown: 2,
+            }]
+        );
+        assert!(LIMITER.with(|limiter| limiter.borrow().is_none()));
+    }
+}
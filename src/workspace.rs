@@ -0,0 +1,253 @@
+//! Temporary workspace for multi-file and remote operations.
+//!
+//! A [`Workspace`] owns a uniquely named directory with `inputs/`, `outputs/`
+//! and `reports/` subdirectories. The directory is removed on drop once the
+//! operation is marked successful; otherwise it is retained so the files can
+//! be inspected after a failure.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PREFIX: &str = "isc2kea-";
+const MAX_ATTEMPTS: u32 = 64;
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Kind of file stored in a workspace, mapped to a subdirectory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkspaceArea {
+    /// Downloaded or copied input configs (`inputs/`)
+    Inputs,
+    /// Generated configs (`outputs/`)
+    Outputs,
+    /// Reports, diffs, and logs (`reports/`)
+    Reports,
+}
+
+impl WorkspaceArea {
+    fn dir_name(self) -> &'static str {
+        match self {
+            WorkspaceArea::Inputs => "inputs",
+            WorkspaceArea::Outputs => "outputs",
+            WorkspaceArea::Reports => "reports",
+        }
+    }
+}
+
+/// A temporary directory that is cleaned up on success and kept on failure.
+#[derive(Debug)]
+pub struct Workspace {
+    root: PathBuf,
+    succeeded: bool,
+    keep: bool,
+}
+
+impl Workspace {
+    /// Create a new workspace under the system temporary directory.
+    pub fn new() -> io::Result<Self> {
+        Self::new_in(std::env::temp_dir())
+    }
+
+    /// Create a new workspace under `parent`.
+    ///
+    /// Directory names include the process id, a timestamp and a per-process
+    /// counter, and creation uses `create_dir` so concurrent callers never
+    /// share a directory.
+    pub fn new_in(parent: impl AsRef<Path>) -> io::Result<Self> {
+        let parent = parent.as_ref();
+        fs::create_dir_all(parent)?;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default();
+            let seq = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let root = parent.join(format!(
+                "{}{}-{:x}-{}",
+                PREFIX,
+                std::process::id(),
+                nanos,
+                seq
+            ));
+
+            match fs::create_dir(&root) {
+                Ok(()) => {
+                    for area in [
+                        WorkspaceArea::Inputs,
+                        WorkspaceArea::Outputs,
+                        WorkspaceArea::Reports,
+                    ] {
+                        fs::create_dir(root.join(area.dir_name()))?;
+                    }
+                    return Ok(Workspace {
+                        root,
+                        succeeded: false,
+                        keep: false,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "Failed to create a unique workspace directory in {}",
+                parent.display()
+            ),
+        ))
+    }
+
+    /// Root directory of the workspace.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path for a file named `name` in the given area.
+    ///
+    /// `name` must be a plain relative path without `..` components.
+    pub fn file_path(&self, area: WorkspaceArea, name: &str) -> io::Result<PathBuf> {
+        let rel = Path::new(name);
+        let valid = !name.is_empty() && rel.components().all(|c| matches!(c, Component::Normal(_)));
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid workspace file name: {}", name),
+            ));
+        }
+        Ok(self.root.join(area.dir_name()).join(rel))
+    }
+
+    /// Create a new file in the given area, failing if it already exists.
+    pub fn create_file(&self, area: WorkspaceArea, name: &str) -> io::Result<(PathBuf, File)> {
+        let path = self.file_path(area, name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok((path, file))
+    }
+
+    /// Write `contents` to a new file in the given area.
+    pub fn write_file(
+        &self,
+        area: WorkspaceArea,
+        name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> io::Result<PathBuf> {
+        let (path, mut file) = self.create_file(area, name)?;
+        io::Write::write_all(&mut file, contents.as_ref())?;
+        file.sync_all()?;
+        Ok(path)
+    }
+
+    /// Mark the operation as successful so the workspace is removed on drop.
+    pub fn mark_success(&mut self) {
+        self.succeeded = true;
+    }
+
+    /// Keep the workspace on drop even after success.
+    pub fn keep(&mut self) {
+        self.keep = true;
+    }
+
+    /// Whether the workspace will be left on disk when dropped.
+    pub fn is_retained(&self) -> bool {
+        self.keep || !self.succeeded
+    }
+
+    /// Remove the workspace now, returning any cleanup error.
+    pub fn cleanup(mut self) -> io::Result<()> {
+        self.keep = true;
+        fs::remove_dir_all(&self.root)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if !self.is_retained() {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_parent(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("isc2kea-ws-test-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_workspace_removed_on_success() {
+        let parent = test_parent("success");
+        let mut ws = Workspace::new_in(&parent).unwrap();
+        let path = ws
+            .write_file(WorkspaceArea::Outputs, "config.xml", "<opnsense/>")
+            .unwrap();
+        assert!(path.starts_with(ws.path().join("outputs")));
+        let root = ws.path().to_path_buf();
+
+        ws.mark_success();
+        drop(ws);
+        assert!(!root.exists());
+        let _ = fs::remove_dir_all(parent);
+    }
+
+    #[test]
+    fn test_workspace_retained_on_failure() {
+        let parent = test_parent("failure");
+        let ws = Workspace::new_in(&parent).unwrap();
+        ws.write_file(WorkspaceArea::Reports, "error.log", "boom")
+            .unwrap();
+        let root = ws.path().to_path_buf();
+
+        assert!(ws.is_retained());
+        drop(ws);
+        assert!(root.join("reports/error.log").exists());
+        let _ = fs::remove_dir_all(parent);
+    }
+
+    #[test]
+    fn test_workspace_unique_across_threads() {
+        let parent = test_parent("threads");
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let parent = parent.clone();
+                std::thread::spawn(move || {
+                    let mut ws = Workspace::new_in(parent).unwrap();
+                    ws.keep();
+                    ws.path().to_path_buf()
+                })
+            })
+            .collect();
+        let mut roots: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        roots.sort();
+        roots.dedup();
+        assert_eq!(roots.len(), 8);
+        let _ = fs::remove_dir_all(parent);
+    }
+
+    #[test]
+    fn test_workspace_rejects_escaping_names() {
+        let parent = test_parent("names");
+        let ws = Workspace::new_in(&parent).unwrap();
+        assert!(ws.file_path(WorkspaceArea::Inputs, "../x").is_err());
+        assert!(ws.file_path(WorkspaceArea::Inputs, "/etc/passwd").is_err());
+        assert!(ws.file_path(WorkspaceArea::Inputs, "").is_err());
+        assert!(ws
+            .file_path(WorkspaceArea::Inputs, "fw1/config.xml")
+            .is_ok());
+        ws.cleanup().unwrap();
+        let _ = fs::remove_dir_all(parent);
+    }
+}
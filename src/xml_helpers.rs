@@ -1,4 +1,7 @@
-use xmltree::Element;
+use std::io::{Read, Write};
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use crate::{AttributeOrder, OutputStyle};
 
 fn name_matches(raw_name: &str, target: &str) -> bool {
     if raw_name.eq_ignore_ascii_case(target) {
@@ -19,6 +22,24 @@ pub(crate) fn get_child_ci<'a>(el: &'a Element, name: &str) -> Option<&'a Elemen
         .find(|c| name_matches(&c.name, name))
 }
 
+/// Text of the child element `name` (case-insensitive), if present
+pub(crate) fn child_text(el: &Element, name: &str) -> Option<String> {
+    get_child_ci(el, name)
+        .and_then(|child| child.get_text())
+        .map(|text| text.to_string())
+}
+
+/// Get all child elements by name (case-insensitive)
+pub(crate) fn get_children_ci<'a>(
+    el: &'a Element,
+    name: &'a str,
+) -> impl Iterator<Item = &'a Element> + 'a {
+    el.children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(move |c| name_matches(&c.name, name))
+}
+
 /// Get mutable child element by name (case-insensitive)
 pub(crate) fn get_mut_child_ci<'a>(el: &'a mut Element, name: &str) -> Option<&'a mut Element> {
     el.children
@@ -55,3 +76,95 @@ pub(crate) fn find_mut_descendant_ci<'a>(
     }
     None
 }
+
+/// Count `el` and all of its descendant elements
+pub(crate) fn count_elements(el: &Element) -> usize {
+    1 + el
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .map(count_elements)
+        .sum::<usize>()
+}
+
+/// Replace the text of `el` with `value`, keeping any comments inside it
+pub(crate) fn set_text_preserving_comments(el: &mut Element, value: &str) {
+    el.children
+        .retain(|n| !matches!(n, XMLNode::Text(_) | XMLNode::CData(_)));
+    el.children.push(XMLNode::Text(value.to_string()));
+}
+
+/// A parsed config together with the comments around its root element,
+/// which `Element::parse` would drop.
+#[derive(Clone)]
+pub(crate) struct XmlDocument {
+    pub(crate) leading_comments: Vec<String>,
+    pub(crate) root: Element,
+    pub(crate) trailing_comments: Vec<String>,
+}
+
+impl XmlDocument {
+    pub(crate) fn parse<R: Read>(reader: R) -> Result<Self, xmltree::ParseError> {
+        let mut leading_comments = Vec::new();
+        let mut trailing_comments = Vec::new();
+        let mut root = None;
+        for node in Element::parse_all(reader)? {
+            match node {
+                XMLNode::Element(el) if root.is_none() => root = Some(el),
+                XMLNode::Comment(comment) if root.is_none() => leading_comments.push(comment),
+                XMLNode::Comment(comment) => trailing_comments.push(comment),
+                _ => {}
+            }
+        }
+        let root = root.ok_or(xmltree::ParseError::CannotParse)?;
+        Ok(XmlDocument {
+            leading_comments,
+            root,
+            trailing_comments,
+        })
+    }
+
+    /// Write the document in `style`, keeping the comments around the root
+    pub(crate) fn write<W: Write>(&self, mut writer: W, style: &OutputStyle) -> anyhow::Result<()> {
+        if style.declaration {
+            writer.write_all(br#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writer.write_all(b"\n")?;
+        }
+        for comment in &self.leading_comments {
+            writeln!(writer, "<!--{}-->", comment)?;
+        }
+        write_element(&self.root, &mut writer, style)?;
+        for comment in &self.trailing_comments {
+            write!(writer, "\n<!--{}-->", comment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `el` and its descendants in `style`, without a declaration
+pub(crate) fn write_element<W: Write>(
+    el: &Element,
+    writer: W,
+    style: &OutputStyle,
+) -> anyhow::Result<()> {
+    let emitter_config = EmitterConfig::new()
+        .perform_indent(true)
+        .indent_string(" ".repeat(style.indent))
+        .write_document_declaration(false);
+    match style.attribute_order {
+        AttributeOrder::Keep => el.write_with_config(writer, emitter_config)?,
+        AttributeOrder::Sorted => {
+            let mut sorted = el.clone();
+            sort_attributes(&mut sorted);
+            sorted.write_with_config(writer, emitter_config)?;
+        }
+    }
+    Ok(())
+}
+
+fn sort_attributes(el: &mut Element) {
+    el.attributes.sort_keys();
+    for child in el.children.iter_mut().filter_map(|n| n.as_mut_element()) {
+        sort_attributes(child);
+    }
+}
@@ -96,6 +96,86 @@ fn test_cli_convert_requires_force_for_existing_output() {
     assert!(stderr.contains("Output file already exists"));
 }
 
+#[test]
+fn test_cli_convert_keeps_workspace_on_failure() {
+    let config = |subnet: &str| {
+        format!(
+            r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <staticmap>
+        <mac>00:11:22:33:44:55</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+      </staticmap>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="test-subnet">
+          <subnet>{subnet}</subnet>
+        </subnet4>
+      </subnets>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#
+        )
+    };
+    let dir = temp_path("workspace_dir");
+    fs::create_dir(&dir).unwrap();
+    let workspaces = || -> Vec<PathBuf> {
+        fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect()
+    };
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+
+    // No Kea subnet holds the mapping
+    let input = write_temp_file("workspace_bad_in", &config("10.0.0.0/24"));
+    let out = dir.join("bad.xml");
+    let output = Command::new(exe)
+        .args(["convert", "--in"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .expect("run binary");
+    assert!(!output.status.success());
+    assert!(!out.exists());
+    let kept = workspaces();
+    assert_eq!(kept.len(), 1);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("the run is kept in {}", kept[0].display())));
+    let log = fs::read_to_string(kept[0].join("reports/error.log")).unwrap();
+    assert!(!log.is_empty());
+    fs::remove_dir_all(&kept[0]).unwrap();
+
+    let input = write_temp_file("workspace_good_in", &config("192.168.1.0/24"));
+    let out = dir.join("good.xml");
+    let output = Command::new(exe)
+        .args(["convert", "--in"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .expect("run binary");
+    assert!(output.status.success());
+    assert!(out.exists());
+    assert!(workspaces().is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
 #[test]
 fn test_cli_scan_missing_input() {
     let input = temp_path("missing_input");
@@ -126,6 +206,7 @@ fn test_cli_scan_success() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -158,3 +239,626 @@ fn test_cli_scan_success() {
     assert!(stdout.contains("ISC DHCP static mappings found"));
     assert!(stdout.contains("Kea subnet4 entries found"));
 }
+
+#[test]
+fn test_cli_export_routeros() {
+    let input = write_temp_file(
+        "export_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <staticmap>
+        <mac>00:11:22:33:44:55</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+        <hostname>testhost</hostname>
+      </staticmap>
+    </lan>
+  </dhcpd>
+</opnsense>
+"#,
+    );
+    let output_path = temp_path("export_out.rsc");
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let output = Command::new(exe)
+        .args(["export", "--format", "routeros", "--in"])
+        .arg(&input)
+        .args(["--out"])
+        .arg(&output_path)
+        .output()
+        .expect("run binary");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Reservations exported:            1\n"));
+    let rsc = fs::read_to_string(&output_path).expect("read export");
+    assert!(rsc.contains(
+        "/ip dhcp-server lease add address=192.168.1.10 mac-address=00:11:22:33:44:55 \
+         comment=\"testhost\""
+    ));
+
+    // A second run without --force must not overwrite the export
+    let output = Command::new(exe)
+        .args(["export", "--format", "routeros", "--in"])
+        .arg(&input)
+        .args(["--out"])
+        .arg(&output_path)
+        .output()
+        .expect("run binary");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Output file already exists"));
+}
+
+#[test]
+fn test_cli_convert_resume_journal() {
+    let input = write_temp_file(
+        "resume_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <staticmap>
+        <mac>00:11:22:33:44:01</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+      </staticmap>
+      <staticmap>
+        <mac>00:11:22:33:44:02</mac>
+        <ipaddr>192.168.1.11</ipaddr>
+      </staticmap>
+      <staticmap>
+        <mac>00:11:22:33:44:03</mac>
+        <ipaddr>192.168.1.12</ipaddr>
+      </staticmap>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="test-subnet">
+          <subnet>192.168.1.0/24</subnet>
+        </subnet4>
+      </subnets>
+      <reservations></reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let first = temp_path("resume_out1");
+    let second = temp_path("resume_out2");
+    let journal = temp_path("resume_journal");
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let run = |input: &PathBuf, out: &PathBuf| {
+        Command::new(exe)
+            .args(["convert", "--limit", "2", "--in"])
+            .arg(input)
+            .args(["--out"])
+            .arg(out)
+            .args(["--resume"])
+            .arg(&journal)
+            .output()
+            .expect("run binary")
+    };
+
+    let output = run(&input, &first);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(
+        "Batch:                                mappings 1-2 of 3 (1 remaining, next --offset 2)"
+    ));
+    assert!(fs::read_to_string(&journal)
+        .expect("read journal")
+        .contains("offset=2"));
+
+    let output = run(&first, &second);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Batch:                                mappings 3-3 of 3 (0 remaining"));
+    assert!(stdout.contains("Totals across runs: 3 reservations created"));
+    let converted = fs::read_to_string(&second).expect("read output");
+    assert_eq!(converted.matches("<reservation ").count(), 3);
+}
+
+#[test]
+fn test_cli_convert_emit_cutover_script() {
+    let input = write_temp_file(
+        "cutover_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <opt1>
+      <descr>IoT&#10;rm -rf /</descr>
+      <ipaddr>10.22.1.1</ipaddr>
+      <subnet>24</subnet>
+    </opt1>
+  </interfaces>
+  <dhcpd>
+    <opt1>
+      <enable>1</enable>
+      <range>
+        <from>10.22.1.100</from>
+        <to>10.22.1.200</to>
+      </range>
+    </opt1>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <general>
+        <enabled>0</enabled>
+      </general>
+      <subnets></subnets>
+      <reservations></reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    // Quotes and substitutions in the output name stay in the script as text
+    let out = temp_path("cutover_out_it's\"$(id)`id`");
+    let script = temp_path("cutover_script");
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let run = |extra: &[&str]| {
+        Command::new(exe)
+            .args(["convert", "--create-subnets", "--in"])
+            .arg(&input)
+            .args(["--out"])
+            .arg(&out)
+            .args(["--emit-cutover-script"])
+            .arg(&script)
+            .args(extra)
+            .output()
+            .expect("run binary")
+    };
+
+    // The script assumes the output switches ISC DHCP off
+    let output = run(&[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--enable-backend"));
+
+    let output = run(&["--enable-backend"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Cutover script written to:"));
+    let text = fs::read_to_string(&script).expect("read script");
+    assert!(text.contains("# Interfaces moved off ISC DHCP: opt1 (IoT rm -rf /)\n"));
+    assert!(!text.lines().any(|line| line.starts_with("rm ")));
+    assert!(text.contains("DEFAULT_CONFIG='/tmp/isc2kea_cutover_out_it'\\''s\"$(id)`id`_"));
+    assert!(text.contains("configctl kea restart || rollback"));
+    assert!(text.contains("pgrep -x kea-dhcp4"));
+    assert!(text.contains("for addr in 10.22.1.1; do"));
+
+    fs::remove_file(&out).expect("remove output");
+    let output = run(&["--enable-backend"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Cutover script already exists"));
+}
+
+#[test]
+fn test_cli_inspect_kea() {
+    let input = write_temp_file(
+        "inspect_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="lan-subnet">
+          <subnet>192.168.1.0/24</subnet>
+          <interface>lan</interface>
+          <pools>192.168.1.100-192.168.1.200</pools>
+          <option_data>
+            <domain_name_servers>9.9.9.9</domain_name_servers>
+            <routers/>
+          </option_data>
+        </subnet4>
+      </subnets>
+      <reservations>
+        <reservation uuid="res-1">
+          <subnet>lan-subnet</subnet>
+          <ip_address>192.168.1.10</ip_address>
+          <hw_address>00:11:22:33:44:55</hw_address>
+          <hostname>printer</hostname>
+        </reservation>
+      </reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let output = Command::new(exe)
+        .args(["inspect", "--in"])
+        .arg(&input)
+        .output()
+        .expect("run binary");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Kea subnet4 (1)"));
+    assert!(stdout.contains("192.168.1.0/24  lan        192.168.1.100-192.168.1.200"));
+    assert!(stdout.contains("Kea reservations (v6) (0)\n  (none)"));
+    assert!(stdout.contains("192.168.1.0/24  domain_name_servers  9.9.9.9"));
+    assert!(!stdout.contains("dnsmasq"));
+
+    let output = Command::new(exe)
+        .args(["inspect", "--format", "json", "--in"])
+        .arg(&input)
+        .output()
+        .expect("run binary");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(
+        r#"{"ip_address": "192.168.1.10", "hw_address": "00:11:22:33:44:55", "client_id": "", "hostname": "printer", "subnet": "192.168.1.0/24", "description": ""}"#
+    ));
+    assert!(stdout.contains(r#""kea_subnet6": []"#));
+}
+
+#[test]
+fn test_cli_convert_emit_preview_dir() {
+    let input = write_temp_file(
+        "preview_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <staticmap>
+        <mac>00:11:22:33:44:55</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+        <hostname>printer</hostname>
+      </staticmap>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="lan-subnet">
+          <subnet>192.168.1.0/24</subnet>
+        </subnet4>
+      </subnets>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let out = temp_path("preview_out");
+    let dir = temp_path("preview_dir");
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let output = Command::new(exe)
+        .args(["convert", "--in"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .arg("--emit-preview-dir")
+        .arg(&dir)
+        .output()
+        .expect("run binary");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Preview written to: "));
+
+    let kea = fs::read_to_string(dir.join("kea.xml")).expect("read kea preview");
+    assert!(kea.starts_with("<Kea>"));
+    assert!(kea.contains("<ip_address>192.168.1.10</ip_address>"));
+    assert!(!kea.contains("<dhcpd>"));
+    assert!(!dir.join("dnsmasq.xml").exists());
+    let json = fs::read_to_string(dir.join("reservations.json")).expect("read json preview");
+    assert!(json.contains(r#""ip_address": "192.168.1.10", "hw_address": "00:11:22:33:44:55""#));
+    assert!(!json.contains("kea_subnet4"));
+
+    // Existing preview files are kept without --force
+    let _ = fs::remove_file(&out);
+    let output = Command::new(exe)
+        .args(["convert", "--in"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .arg("--emit-preview-dir")
+        .arg(&dir)
+        .output()
+        .expect("run binary");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Preview file already exists"));
+
+    let _ = fs::remove_file(&out);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cli_audit() {
+    let input = write_temp_file(
+        "audit_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <Kea>
+    <dhcp4>
+      <reservations>
+        <reservation uuid="res-1">
+          <ip_address>192.168.1.10</ip_address>
+          <hw_address>00:11:22:33:44:55</hw_address>
+          <hostname>printer</hostname>
+        </reservation>
+        <reservation uuid="res-2">
+          <ip_address>192.168.1.11</ip_address>
+          <hw_address>00:11:22:33:44:66</hw_address>
+        </reservation>
+      </reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let leases = write_temp_file(
+        "audit_leases",
+        "address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state,user_context,pool_id\n\
+         192.168.1.150,00:11:22:33:44:55,,3600,4102444800,1,0,0,printer,0,,0\n\
+         192.168.1.11,00:11:22:33:44:66,,3600,4102444800,1,0,0,,0,,0\n",
+    );
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let output = Command::new(exe)
+        .args(["audit", "--in"])
+        .arg(&input)
+        .arg("--leases")
+        .arg(&leases)
+        .output()
+        .expect("run binary");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Leasing their reserved address: 1\n"));
+    assert!(stdout.contains(
+        "  00:11:22:33:44:55 (printer): reserved 192.168.1.10, leasing 192.168.1.150 until 2100-01-01T00:00:00Z\n"
+    ));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 device(s) lease an address other than their reservation"));
+}
+
+#[test]
+fn test_cli_convert_peer_out() {
+    let input = write_temp_file(
+        "peer_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.2</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <hasync>
+    <pfsyncinterface>lan</pfsyncinterface>
+    <pfsyncpeerip>192.168.1.3</pfsyncpeerip>
+    <synchronizetoip>192.168.1.3</synchronizetoip>
+    <syncitems>dhcpd,kea</syncitems>
+  </hasync>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <failover_peerip>192.168.1.3</failover_peerip>
+      <staticmap>
+        <mac>00:11:22:33:44:55</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+      </staticmap>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="lan-subnet">
+          <subnet>192.168.1.0/24</subnet>
+        </subnet4>
+      </subnets>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let out = temp_path("peer_out_primary");
+    let peer = temp_path("peer_out_backup");
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let output = Command::new(exe)
+        .args(["convert", "--in"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .expect("run binary");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--allow-ha"));
+
+    let output = Command::new(exe)
+        .args(["convert", "--allow-ha", "--in"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .arg("--peer-out")
+        .arg(&peer)
+        .output()
+        .expect("run binary");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("HA config sync to 192.168.1.3:        dhcpd, kea"));
+
+    let converted = fs::read_to_string(&peer).expect("read peer config");
+    assert!(converted.contains("<ipaddr>192.168.1.3</ipaddr>"));
+    assert!(converted.contains("<failover_peerip>192.168.1.2</failover_peerip>"));
+    assert!(converted.contains("<pfsyncpeerip>192.168.1.2</pfsyncpeerip>"));
+    assert!(!converted.contains("<synchronizetoip>192.168.1.3"));
+    assert_eq!(converted.matches("<reservation ").count(), 1);
+
+    let _ = fs::remove_file(&out);
+    let _ = fs::remove_file(&peer);
+}
+
+#[test]
+fn test_cli_env_options_below_flags() {
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let scan = |args: &[&str], env: &[(&str, &str)]| {
+        Command::new(exe)
+            .args(["scan", "--in", "fixtures/dnsmasq_minimal.xml"])
+            .args(args)
+            .envs(env.iter().copied())
+            .output()
+            .expect("run binary")
+    };
+
+    let output = scan(&[], &[("ISC2KEA_BACKEND", "dnsmasq")]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Reservations that would be created:      2\n"));
+
+    // Flags win over the environment
+    let output = scan(&["--backend", "kea"], &[("ISC2KEA_BACKEND", "dnsmasq")]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Kea DHCPv4 not configured"));
+
+    let output = scan(&[], &[("ISC2KEA_BACKEND", "bind")]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid value 'bind'"));
+
+    let output = scan(
+        &["--backend", "dnsmasq"],
+        &[("ISC2KEA_FAIL_IF_EXISTING", "true"), ("ISC2KEA_LIMIT", "1")],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Reservations that would be created:      1\n"));
+}
+
+#[test]
+fn test_cli_plan_and_apply() {
+    let input = write_temp_file(
+        "plan_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <staticmap>
+        <mac>00:11:22:33:44:01</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+        <hostname>printer</hostname>
+      </staticmap>
+      <staticmap>
+        <mac>00:11:22:33:44:02</mac>
+        <ipaddr>192.168.1.11</ipaddr>
+      </staticmap>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="test-subnet">
+          <subnet>192.168.1.0/24</subnet>
+        </subnet4>
+      </subnets>
+      <reservations></reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let plan = temp_path("plan_yaml");
+    let out = temp_path("plan_out");
+
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let output = Command::new(exe)
+        .args(["plan", "--in"])
+        .arg(&input)
+        .args(["--out"])
+        .arg(&plan)
+        .output()
+        .expect("run binary");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Planned 2 DHCPv4 and 0 DHCPv6"));
+    let text = fs::read_to_string(&plan).expect("read plan");
+    assert!(text.contains("  - iface: lan  # printer\n"));
+    let entry = "  - iface: lan\n    ip: 192.168.1.11\n    mac: 00:11:22:33:44:02\n    subnet: 192.168.1.0/24\n";
+    assert!(text.contains(entry));
+    fs::write(&plan, text.replace(entry, "")).expect("edit plan");
+
+    let apply = |backend: &str| {
+        Command::new(exe)
+            .args(["apply", "--force", "--backend", backend, "--in"])
+            .arg(&input)
+            .args(["--out"])
+            .arg(&out)
+            .args(["--plan"])
+            .arg(&plan)
+            .output()
+            .expect("run binary")
+    };
+    let output = apply("dnsmasq");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("was planned for Kea, not dnsmasq"));
+
+    let output = apply("kea");
+    assert!(output.status.success());
+    let converted = fs::read_to_string(&out).expect("read output");
+    assert_eq!(converted.matches("<reservation ").count(), 1);
+    assert!(converted.contains("<ip_address>192.168.1.10</ip_address>"));
+}
+
+#[test]
+fn test_cli_color() {
+    let exe = env!("CARGO_BIN_EXE_isc2kea");
+    let scan = |args: &[&str]| {
+        let output = Command::new(exe)
+            .args(["scan", "-v", "--backend", "dnsmasq"])
+            .args(["--in", "fixtures/dnsmasq_minimal.xml"])
+            .args(args)
+            .env_remove("NO_COLOR")
+            .output()
+            .expect("run binary");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    // Piped output is not a terminal
+    assert!(!scan(&[]).contains('\u{1b}'));
+    assert!(!scan(&["--color", "never"]).contains('\u{1b}'));
+    let colored = scan(&["--color", "always"]);
+    assert!(colored.contains("  \u{1b}[32mADD\u{1b}[0m: "));
+    assert!(colored.contains("Reservations that would be created:      \u{1b}[32m2\u{1b}[0m\n"));
+}
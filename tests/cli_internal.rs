@@ -100,6 +100,34 @@ fn run_with_args_requires_force_for_existing_output() {
     assert!(err.to_string().contains("Output file already exists"));
 }
 
+#[test]
+fn run_with_args_merge_into_requires_existing_output() {
+    let input = write_temp_file(
+        "merge_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <dhcpd>
+    <lan></lan>
+  </dhcpd>
+</opnsense>
+"#,
+    );
+    let output_path = temp_path("merge_missing_out");
+
+    let result = run_with_args([
+        "isc2kea",
+        "convert",
+        "--in",
+        input.to_str().unwrap(),
+        "--out",
+        output_path.to_str().unwrap(),
+        "--merge-into",
+    ]);
+
+    let err = result.expect_err("should fail when merge target is missing");
+    assert!(err.to_string().contains("Failed to read merge target"));
+}
+
 #[test]
 fn run_with_args_scan_missing_input() {
     let input = temp_path("missing_input");
@@ -123,6 +151,7 @@ fn run_with_args_verify_shows_diff() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -168,6 +197,7 @@ fn run_with_args_convert_writes_output() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -217,6 +247,7 @@ fn run_with_args_convert_cleans_temp_on_failure() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -264,6 +295,7 @@ fn run_with_args_convert_force_overwrites_output() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -314,6 +346,7 @@ fn run_with_args_scan_backend_not_configured() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -344,6 +377,7 @@ fn run_with_args_scan_backend_not_configured_with_options() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <range>
         <from>192.168.1.100</from>
         <to>192.168.1.200</to>
@@ -384,6 +418,7 @@ fn run_with_args_convert_dnsmasq_writes_output() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -406,10 +441,14 @@ fn run_with_args_convert_dnsmasq_writes_output() {
         output_path.to_str().unwrap(),
         "--backend",
         "dnsmasq",
+        "--dnsmasq-hosts-local",
+        "1",
     ]);
 
     assert!(result.is_ok());
     assert!(output_path.exists());
+    let output = fs::read_to_string(&output_path).expect("read output");
+    assert!(output.contains("<local>1</local>"));
 }
 
 #[test]
@@ -420,6 +459,7 @@ fn run_with_args_scan_dnsmasq_fail_if_existing() {
 <opnsense>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <staticmap>
         <mac>00:11:22:33:44:55</mac>
         <ipaddr>192.168.1.10</ipaddr>
@@ -466,6 +506,7 @@ fn run_with_args_convert_with_create_flags() {
   </interfaces>
   <dhcpd>
     <lan>
+      <enable>1</enable>
       <range>
         <from>192.168.1.100</from>
         <to>192.168.1.200</to>
@@ -510,3 +551,249 @@ fn run_with_args_convert_with_create_flags() {
     assert!(result.is_ok());
     assert!(output_path.exists());
 }
+
+#[test]
+fn run_with_args_verify_fail_on_threshold() {
+    let input = write_temp_file(
+        "verify_fail_on_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <dnsserver>192.168.1.53</dnsserver>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="test-subnet">
+          <subnet>192.168.1.0/24</subnet>
+        </subnet4>
+      </subnets>
+      <reservations></reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let verify = |fail_on: &str| {
+        run_with_args([
+            "isc2kea",
+            "verify",
+            "--in",
+            input.to_str().unwrap(),
+            "--create-options",
+            "--format",
+            "json",
+            "--quiet",
+            "--fail-on",
+            fail_on,
+        ])
+    };
+
+    // Copying options is a warning: it fails at warning but not at error
+    let err = verify("warning").expect_err("warning findings should fail");
+    assert!(err.to_string().contains("at or above warning"));
+    verify("error").expect("warning findings should pass --fail-on error");
+}
+
+#[test]
+fn run_with_args_verify_only_filters_categories() {
+    let input = write_temp_file(
+        "verify_only_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <dnsserver>192.168.1.53</dnsserver>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="test-subnet">
+          <subnet>192.168.1.0/24</subnet>
+        </subnet4>
+      </subnets>
+      <reservations></reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let verify = |only: &str| {
+        run_with_args([
+            "isc2kea",
+            "verify",
+            "--in",
+            input.to_str().unwrap(),
+            "--create-options",
+            "--quiet",
+            "--only",
+            only,
+        ])
+    };
+
+    // Only options change, so the other categories have nothing to fail on
+    verify("reservations,subnets").expect("no reservation or subnet findings");
+    let err = verify("options").expect_err("option findings should fail");
+    assert!(err.to_string().contains("at or above info"));
+}
+
+#[test]
+fn run_with_args_diag_writes_redacted_bundle() {
+    let input = write_temp_file(
+        "diag_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <staticmap>
+        <mac>00:11:22:33:44:55</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+        <hostname>alices-laptop</hostname>
+      </staticmap>
+    </lan>
+  </dhcpd>
+  <Kea>
+    <dhcp4>
+      <subnets>
+        <subnet4 uuid="test-subnet">
+          <subnet>192.168.1.0/24</subnet>
+        </subnet4>
+      </subnets>
+      <reservations></reservations>
+    </dhcp4>
+  </Kea>
+</opnsense>
+"#,
+    );
+    let out = temp_path("diag_out");
+
+    run_with_args([
+        "isc2kea",
+        "diag",
+        "--in",
+        input.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+    ])
+    .expect("diag should succeed");
+
+    let bundle = fs::read_to_string(&out).expect("read bundle");
+    assert!(bundle.contains("\"reservations_to_create\": 1,"));
+    assert!(bundle.contains("<ipaddr>192.168.1.10</ipaddr>"));
+    assert!(!bundle.contains("00:11:22:33:44:55"));
+    assert!(!bundle.contains("alices-laptop"));
+    assert!(bundle.contains("<hostname>host-"));
+}
+
+#[test]
+fn run_with_args_gen_fixture_writes_migratable_config() {
+    let out = temp_path("fixture_out");
+
+    run_with_args([
+        "isc2kea",
+        "gen-fixture",
+        "--interfaces",
+        "3",
+        "--maps-per-iface",
+        "20",
+        "--existing",
+        "2",
+        "--seed",
+        "42",
+        "--out",
+        out.to_str().unwrap(),
+    ])
+    .expect("gen-fixture should succeed");
+
+    let xml = fs::read_to_string(&out).expect("read fixture");
+    assert_eq!(xml.matches("<staticmap>").count(), 60);
+    assert_eq!(xml.matches("<hosts uuid").count(), 6);
+    assert!(xml.contains("<opt2>"));
+}
+
+#[test]
+fn run_with_args_stats_log_appends_a_line_per_run() {
+    let input = write_temp_file(
+        "stats_log_in",
+        r#"<?xml version="1.0"?>
+<opnsense>
+  <interfaces>
+    <lan>
+      <ipaddr>192.168.1.1</ipaddr>
+      <subnet>24</subnet>
+    </lan>
+  </interfaces>
+  <dhcpd>
+    <lan>
+      <enable>1</enable>
+      <staticmap>
+        <mac>00:11:22:33:44:55</mac>
+        <ipaddr>192.168.1.10</ipaddr>
+      </staticmap>
+    </lan>
+  </dhcpd>
+  <dnsmasq></dnsmasq>
+</opnsense>
+"#,
+    );
+    let output_path = temp_path("stats_log_out");
+    let log = temp_path("stats_log");
+    let common = [
+        "--in",
+        input.to_str().unwrap(),
+        "--backend",
+        "dnsmasq",
+        "--stats-log",
+        log.to_str().unwrap(),
+    ];
+
+    let scan = [&["isc2kea", "scan"][..], &common[..]].concat();
+    run_with_args(scan).expect("scan should succeed");
+    let convert = [
+        &["isc2kea", "convert", "--out", output_path.to_str().unwrap()][..],
+        &common[..],
+    ]
+    .concat();
+    run_with_args(convert).expect("convert should succeed");
+
+    let contents = fs::read_to_string(&log).expect("read stats log");
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"command\": \"scan\""));
+    assert!(lines[1].contains("\"command\": \"convert\""));
+    let hash = |line: &str| line.split("\"input_hash\": ").nth(1).unwrap()[..26].to_string();
+    assert_eq!(hash(lines[0]), hash(lines[1]));
+    for line in lines {
+        assert!(line.starts_with("{\"timestamp\": "));
+        assert!(line.contains("\"backend\": \"dnsmasq\""));
+        assert!(line.contains("\"reservations_to_create\": 1"));
+    }
+
+    let _ = fs::remove_file(input);
+    let _ = fs::remove_file(output_path);
+    let _ = fs::remove_file(log);
+}
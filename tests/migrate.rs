@@ -1,6 +1,8 @@
 mod migrate {
+    mod backend_api;
     mod common;
     mod dnsmasq;
+    mod export;
     mod kea;
     mod options;
     mod subnets;
@@ -0,0 +1,80 @@
+#![cfg(feature = "backend-api")]
+
+use super::common::*;
+use anyhow::Result;
+use isc2kea::{
+    convert_config_with_backend, scan_config_with_backend, BackendRegistry, IscStaticMap,
+    IscStaticMapV6, MigrationBackend, MigrationOptions, MigrationStats,
+};
+use std::io::Cursor;
+use xmltree::{Element, XMLNode};
+// Out-of-tree backend API tests
+// ---------------------------------------------------------------------------
+
+/// Writes one `<lease>` per v4 mapping under `<leases>`.
+struct LeaseListBackend;
+
+impl MigrationBackend for LeaseListBackend {
+    fn name(&self) -> &str {
+        "leases"
+    }
+
+    fn scan(
+        &self,
+        _root: &Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        _options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        Ok(MigrationStats {
+            isc_mappings_found: mappings.len(),
+            isc_mappings_v6_found: mappings_v6.len(),
+            reservations_to_create: mappings.len(),
+            ..Default::default()
+        })
+    }
+
+    fn convert(
+        &self,
+        root: &mut Element,
+        mappings: &[IscStaticMap],
+        mappings_v6: &[IscStaticMapV6],
+        options: &MigrationOptions,
+    ) -> Result<MigrationStats> {
+        let mut leases = Element::new("leases");
+        for mapping in mappings {
+            let mut lease = Element::new("lease");
+            lease.children.push(XMLNode::Text(mapping.ipaddr.clone()));
+            leases.children.push(XMLNode::Element(lease));
+        }
+        root.children.push(XMLNode::Element(leases));
+        self.scan(root, mappings, mappings_v6, options)
+    }
+}
+
+#[test]
+fn test_custom_backend_scan_and_convert() {
+    let mut registry = BackendRegistry::default();
+    registry.register(Box::new(LeaseListBackend));
+    let backend = registry.get("leases").expect("registered backend");
+    let options = MigrationOptions::default();
+
+    let stats = scan_config_with_backend(Cursor::new(TEST_XML), &options, backend)
+        .expect("scan should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+
+    let mut output = Vec::new();
+    let stats = convert_config_with_backend(Cursor::new(TEST_XML), &mut output, &options, backend)
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let leases = find_descendant_ci(&root, "leases").expect("Should have leases");
+    let ips: Vec<String> = leases
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter_map(|e| e.get_text().map(|t| t.to_string()))
+        .collect();
+    assert_eq!(ips, vec!["192.168.1.10"]);
+}
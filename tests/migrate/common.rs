@@ -142,6 +142,45 @@ pub fn dnsmasq_options_create_subnets() -> MigrationOptions {
     }
 }
 
+/// Config with ISC range 10.22.1.100-10.22.1.200 on opt1 and one existing
+/// dnsmasq range on `iface` from `start` to `end`.
+pub fn dnsmasq_overlap_xml(iface: &str, start: &str, end: &str) -> String {
+    format!(
+        r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+        <opt2>
+            <ipaddr>10.33.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt2>
+    </interfaces>
+    <dhcpd>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>10.22.1.100</from>
+                <to>10.22.1.200</to>
+            </range>
+        </opt1>
+    </dhcpd>
+    <dnsmasq>
+        <dhcp_ranges uuid="existing-range">
+            <interface>{iface}</interface>
+            <start_addr>{start}</start_addr>
+            <end_addr>{end}</end_addr>
+            <subnet_mask>255.255.255.0</subnet_mask>
+            <domain_type>range</domain_type>
+        </dhcp_ranges>
+    </dnsmasq>
+</opnsense>
+"#
+    )
+}
+
 pub const TEST_XML: &str = r#"<?xml version="1.0"?>
 <opnsense>
     <interfaces>
@@ -152,6 +191,7 @@ pub const TEST_XML: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -182,6 +222,7 @@ pub const TEST_XML_V6: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <opt2>
+            <enable>1</enable>
             <staticmap>
                 <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
                 <ipaddrv6>2001:db8:42::10</ipaddrv6>
@@ -213,6 +254,7 @@ pub const TEST_DNSMASQ_XML: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -236,6 +278,7 @@ pub const TEST_DNSMASQ_XML_WITH_EXISTING_IP: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -263,6 +306,7 @@ pub const TEST_DNSMASQ_XML_WITH_EXISTING_MAC: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -290,6 +334,7 @@ pub const TEST_DNSMASQ_XML_V6: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
                 <ipaddrv6>fd00:1234:5678:1::101</ipaddrv6>
@@ -314,6 +359,7 @@ pub const TEST_DNSMASQ_XML_V6_WITH_EXISTING_IP: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
                 <ipaddrv6>fd00:1234:5678:1::101</ipaddrv6>
@@ -342,6 +388,7 @@ pub const TEST_DNSMASQ_XML_V6_WITH_EXISTING_DUID: &str = r#"<?xml version="1.0"?
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
                 <ipaddrv6>fd00:1234:5678:1::101</ipaddrv6>
@@ -370,6 +417,7 @@ pub const TEST_DNSMASQ_XML_V6_WITH_EXISTING_CLIENT_ID_ONLY: &str = r#"<?xml vers
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
                 <ipaddrv6>fd00:1234:5678:1::101</ipaddrv6>
@@ -395,6 +443,7 @@ pub const TEST_XML_V6_WITH_EXISTING_DUID: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <opt2>
+            <enable>1</enable>
             <staticmap>
                 <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
                 <ipaddrv6>2001:db8:42::10</ipaddrv6>
@@ -431,6 +480,7 @@ pub const TEST_CREATE_SUBNETS_KEA_V4: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.22.1.100</from>
                 <to>10.22.1.200</to>
@@ -450,6 +500,49 @@ pub const TEST_CREATE_SUBNETS_KEA_V4: &str = r#"<?xml version="1.0"?>
 </opnsense>
 "#;
 
+/// Netboot on opt1 only; lan has just a TFTP server name.
+pub const TEST_CREATE_SUBNETS_KEA_NETBOOT: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+            <tftp>tftp.lan</tftp>
+            <nextserver>192.168.1.9</nextserver>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>10.22.1.100</from>
+                <to>10.22.1.200</to>
+            </range>
+            <netboot>1</netboot>
+            <nextserver>10.22.1.5</nextserver>
+            <filename>pxelinux.0</filename>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets></subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
 pub const TEST_CREATE_SUBNETS_KEA_V6: &str = r#"<?xml version="1.0"?>
 <opnsense>
     <interfaces>
@@ -460,6 +553,7 @@ pub const TEST_CREATE_SUBNETS_KEA_V6: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <range>
                 <from>fd00:1234:5678:1::100</from>
                 <to>fd00:1234:5678:1::200</to>
@@ -490,6 +584,7 @@ pub const TEST_CREATE_SUBNETS_DNSMASQ_V4: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.22.1.100</from>
                 <to>10.22.1.200</to>
@@ -510,6 +605,7 @@ pub const TEST_CREATE_SUBNETS_DNSMASQ_V6: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <range>
                 <from>fd00:1234:5678:1::100</from>
                 <to>fd00:1234:5678:1::200</to>
@@ -530,16 +626,20 @@ pub const TEST_CREATE_OPTIONS_DNSMASQ: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <dnsserver>8.8.8.8</dnsserver>
             <dnsserver>1.1.1.1</dnsserver>
             <gateway>10.22.1.1</gateway>
             <domain>example.com</domain>
             <domainsearchlist>example2.com; example3.com</domainsearchlist>
             <ntpserver>10.22.1.10</ntpserver>
+            <winsserver>10.22.1.20</winsserver>
+            <winsserver>10.22.1.21</winsserver>
         </opt1>
     </dhcpd>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <dnsserver>fd00:1234:5678:1::1</dnsserver>
             <dnsserver>fd00:1234:5678:1::2</dnsserver>
             <domainsearchlist>example.com</domainsearchlist>
@@ -559,6 +659,7 @@ pub const TEST_CREATE_OPTIONS_DNSMASQ_EXISTING: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <dnsserver>8.8.8.8</dnsserver>
             <dnsserver>1.1.1.1</dnsserver>
             <gateway>10.22.1.1</gateway>
@@ -593,6 +694,7 @@ pub const TEST_CREATE_SUBNETS_KEA_V4_EXISTING: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.22.1.100</from>
                 <to>10.22.1.200</to>
@@ -617,6 +719,96 @@ pub const TEST_CREATE_SUBNETS_KEA_V4_EXISTING: &str = r#"<?xml version="1.0"?>
 </opnsense>
 "#;
 
+pub const TEST_CREATE_POOLS_KEA_IFACE: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>10.22.1.10</from>
+                <to>10.22.1.20</to>
+            </range>
+            <range>
+                <from>10.22.1.30</from>
+                <to>10.22.1.40</to>
+            </range>
+            <range>
+                <from>10.22.1.100</from>
+                <to>10.22.1.200</to>
+            </range>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet">
+                    <subnet>10.22.0.0/16</subnet>
+                    <interface>opt1</interface>
+                    <pools>10.22.1.10-10.22.1.20,10.22.1.190-10.22.1.220</pools>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_SUBNETS_KEA_FORCE_REUSE_UUID: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>10.22.1.100</from>
+                <to>10.22.1.200</to>
+            </range>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:54</mac>
+                <ipaddr>10.22.1.100</ipaddr>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="existing-subnet">
+                    <subnet>10.22.1.0/24</subnet>
+                    <pools>10.22.1.50-10.22.1.60</pools>
+                </subnet4>
+                <subnet4 uuid="duplicate-subnet">
+                    <subnet>10.22.1.0/24</subnet>
+                    <pools>10.22.1.70-10.22.1.80</pools>
+                </subnet4>
+            </subnets>
+            <reservations>
+                <reservation uuid="existing-res">
+                    <subnet>existing-subnet</subnet>
+                    <ip_address>10.22.1.20</ip_address>
+                    <hw_address>00:11:22:33:44:20</hw_address>
+                </reservation>
+                <reservation uuid="duplicate-res">
+                    <subnet>duplicate-subnet</subnet>
+                    <ip_address>10.22.1.21</ip_address>
+                    <hw_address>00:11:22:33:44:21</hw_address>
+                </reservation>
+            </reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
 pub const TEST_CREATE_SUBNETS_DNSMASQ_V4_EXISTING: &str = r#"<?xml version="1.0"?>
 <opnsense>
     <interfaces>
@@ -627,6 +819,7 @@ pub const TEST_CREATE_SUBNETS_DNSMASQ_V4_EXISTING: &str = r#"<?xml version="1.0"
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.22.1.100</from>
                 <to>10.22.1.200</to>
@@ -661,6 +854,7 @@ pub const TEST_CREATE_SUBNETS_RANGE_OUTSIDE_CIDR: &str = r#"<?xml version="1.0"?
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.0.1.100</from>
                 <to>10.0.1.200</to>
@@ -685,6 +879,7 @@ pub const TEST_CREATE_SUBNETS_MISSING_INTERFACE: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.0.0.100</from>
                 <to>10.0.0.200</to>
@@ -709,6 +904,7 @@ pub const TEST_CREATE_SUBNETS_DHCP_INTERFACE: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.0.0.100</from>
                 <to>10.0.0.200</to>
@@ -733,6 +929,7 @@ pub const TEST_CREATE_SUBNETS_TRACK6_INTERFACE: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <range>
                 <from>fd00:1234:5678:1::100</from>
                 <to>fd00:1234:5678:1::200</to>
@@ -757,6 +954,7 @@ pub const TEST_CREATE_SUBNETS_MULTI_RANGE_V4: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <range>
                 <from>10.22.1.10</from>
                 <to>10.22.1.20</to>
@@ -785,6 +983,7 @@ pub const TEST_CREATE_SUBNETS_MULTI_RANGE_V6: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <range>
                 <from>fd00:1234:5678:1::10</from>
                 <to>fd00:1234:5678:1::20</to>
@@ -813,12 +1012,15 @@ pub const TEST_CREATE_OPTIONS_KEA_V4: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <dnsserver>8.8.8.8</dnsserver>
             <dnsserver>1.1.1.1</dnsserver>
             <gateway>10.22.1.1</gateway>
             <domain>example.com</domain>
             <domainsearchlist>example2.com; example3.com</domainsearchlist>
             <ntpserver>10.22.1.10</ntpserver>
+            <winsserver>10.22.1.20</winsserver>
+            <winsserver>10.22.1.21</winsserver>
         </opt1>
     </dhcpd>
     <Kea>
@@ -851,6 +1053,7 @@ pub const TEST_CREATE_OPTIONS_KEA_V6: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpdv6>
         <lan>
+            <enable>1</enable>
             <dnsserver>fd00:1234:5678:1::1</dnsserver>
             <dnsserver>fd00:1234:5678:1::2</dnsserver>
             <domainsearchlist>example.com</domainsearchlist>
@@ -882,6 +1085,7 @@ pub const TEST_CREATE_OPTIONS_KEA_V4_EXISTING: &str = r#"<?xml version="1.0"?>
     </interfaces>
     <dhcpd>
         <opt1>
+            <enable>1</enable>
             <dnsserver>8.8.8.8</dnsserver>
             <gateway>10.22.1.1</gateway>
         </opt1>
@@ -902,6 +1106,39 @@ pub const TEST_CREATE_OPTIONS_KEA_V4_EXISTING: &str = r#"<?xml version="1.0"?>
 </opnsense>
 "#;
 
+pub const TEST_CREATE_OPTIONS_KEA_V4_PARTIAL: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <opt1>
+            <enable>1</enable>
+            <dnsserver>8.8.8.8</dnsserver>
+            <gateway>10.22.1.1</gateway>
+            <domain>example.com</domain>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="s4">
+                    <subnet>10.22.1.0/24</subnet>
+                    <option_data>
+                        <domain_name_servers></domain_name_servers>
+                        <routers>10.22.1.254</routers>
+                        <domain_name></domain_name>
+                    </option_data>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
 pub const TEST_CREATE_SUBNETS_KEA_V4_EXISTING_INTERFACES: &str = r#"<?xml version="1.0"?>
 <opnsense>
     <interfaces>
@@ -916,6 +1153,7 @@ pub const TEST_CREATE_SUBNETS_KEA_V4_EXISTING_INTERFACES: &str = r#"<?xml versio
     </interfaces>
     <dhcpd>
         <opt2>
+            <enable>1</enable>
             <range>
                 <from>10.22.2.100</from>
                 <to>10.22.2.200</to>
@@ -952,6 +1190,7 @@ pub const TEST_CREATE_SUBNETS_DNSMASQ_V4_EXISTING_INTERFACES: &str = r#"<?xml ve
     </interfaces>
     <dhcpd>
         <opt2>
+            <enable>1</enable>
             <range>
                 <from>10.22.2.100</from>
                 <to>10.22.2.200</to>
@@ -1015,3 +1254,1827 @@ pub const TEST_ENABLE_BACKEND_DNSMASQ: &str = r#"<?xml version="1.0"?>
     </dnsmasq>
 </opnsense>
 "#;
+
+pub const TEST_SORT_RESERVATIONS_KEA: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.0.0.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <opt1>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:03</mac>
+                <ipaddr>10.0.0.20</ipaddr>
+            </staticmap>
+        </opt1>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:01</mac>
+                <ipaddr>192.168.1.100</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:02</mac>
+                <ipaddr>192.168.1.9</ipaddr>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+                <subnet4 uuid="opt1-subnet-uuid-5678">
+                    <subnet>10.0.0.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations>
+                <reservation uuid="existing-reservation">
+                    <subnet>lan-subnet-uuid-1234</subnet>
+                    <ip_address>192.168.1.200</ip_address>
+                    <hw_address>00:11:22:33:44:ff</hw_address>
+                </reservation>
+            </reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_SUBNETS_UNMATCHED_KEA_V4: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+            <staticmap>
+                <mac>00:11:22:33:44:01</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+            </staticmap>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:02</mac>
+                <ipaddr>10.22.1.10</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:03</mac>
+                <ipaddr>10.22.1.11</ipaddr>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets></subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_OPTIONS_V6_EXTENDED: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddrv6>fd00:1234:5678:1::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </lan>
+    </interfaces>
+    <dhcpdv6>
+        <lan>
+            <enable>1</enable>
+            <ntpserver>fd00:1234:5678:1::123</ntpserver>
+            <ntpserver>fd00:1234:5678:1::124</ntpserver>
+            <sipserver>fd00:1234:5678:1::50</sipserver>
+            <bootfile_url>http://[fd00:1234:5678:1::10]/boot.efi</bootfile_url>
+        </lan>
+    </dhcpdv6>
+    <Kea>
+        <dhcp6>
+            <subnets>
+                <subnet6 uuid="s6">
+                    <subnet>fd00:1234:5678:1::/64</subnet>
+                    <option_data/>
+                </subnet6>
+            </subnets>
+        </dhcp6>
+    </Kea>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_OPTIONS_DNSMASQ_SEARCH_CONFLICT: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+            <ipaddrv6>fd00:1234:5678:1::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <domainsearchlist>home.arpa;corp.example</domainsearchlist>
+        </lan>
+    </dhcpd>
+    <dhcpdv6>
+        <lan>
+            <enable>1</enable>
+            <domainsearchlist>v6.home.arpa</domainsearchlist>
+        </lan>
+    </dhcpdv6>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_ENABLE_BACKEND_KEA_EXISTING_SUBNETS: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+            </staticmap>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <general>
+                <enabled>0</enabled>
+                <interfaces>opt9</interfaces>
+            </general>
+            <subnets>
+                <subnet4 uuid="lan-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_SUBNETS_LABELED: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt3>
+            <descr>IoT VLAN</descr>
+            <ipaddr>10.30.0.1</ipaddr>
+            <subnet>24</subnet>
+        </opt3>
+    </interfaces>
+    <dhcpd>
+        <opt3>
+            <enable>1</enable>
+            <range>
+                <from>10.30.0.100</from>
+                <to>10.30.0.200</to>
+            </range>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:54</mac>
+                <ipaddr>10.30.0.50</ipaddr>
+            </staticmap>
+        </opt3>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets></subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_DISABLED_ISC_INTERFACE: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+            </staticmap>
+        </lan>
+        <opt1>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>10.22.1.10</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:77</mac>
+                <ipaddr>10.22.1.11</ipaddr>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+                <subnet4 uuid="opt1-subnet">
+                    <subnet>10.22.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_ORPHANED_ISC_INTERFACE: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+            </staticmap>
+        </lan>
+        <opt7>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>192.168.1.20</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:77</mac>
+                <ipaddr>10.77.0.10</ipaddr>
+            </staticmap>
+        </opt7>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_SUBNETS_DNSMASQ_V6_RAMODE: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddrv6>fd00:1234:5678:1::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </lan>
+    </interfaces>
+    <dhcpdv6>
+        <lan>
+            <enable>1</enable>
+            <ramode>assist</ramode>
+            <range>
+                <from>fd00:1234:5678:1::1000</from>
+                <to>fd00:1234:5678:1::2000</to>
+            </range>
+        </lan>
+    </dhcpdv6>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_BACKEND_BOTH: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>testhost</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>printer</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+    <dnsmasq>
+        <hosts uuid="existing-host-1">
+            <hwaddr>00:11:22:33:44:66</hwaddr>
+            <ip>192.168.1.11</ip>
+            <host>printer</host>
+        </hosts>
+    </dnsmasq>
+</opnsense>
+"#;
+
+/// TEST_XML after a first convert, with a second subnet added on the
+/// firewall afterwards.
+pub const TEST_MERGE_TARGET: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+                <subnet4 uuid="added-after-convert">
+                    <subnet>10.0.0.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations>
+                <reservation uuid="migrated-1">
+                    <subnet>test-subnet-uuid-1234</subnet>
+                    <ip_address>192.168.1.10</ip_address>
+                    <hw_address>00:11:22:33:44:55</hw_address>
+                </reservation>
+            </reservations>
+        </dhcp4>
+    </Kea>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <domain>target.lan</domain>
+        </lan>
+    </dhcpd>
+</opnsense>
+"#;
+
+pub const TEST_MERGE_SOURCE: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>testhost</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>192.168.1.20</ipaddr>
+                <hostname>newhost</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+</opnsense>
+"#;
+
+pub const TEST_XML_WITH_COMMENTS: &str = r#"<?xml version="1.0"?>
+<!-- top of file -->
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <!-- printer pool -->
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>testhost</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <!-- main lan -->
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                    <option_data>
+                        <!-- dns note -->
+                        <domain_name_servers/>
+                    </option_data>
+                </subnet4>
+            </subnets>
+            <reservations>
+                <!-- existing reservations -->
+            </reservations>
+            <general>
+                <enabled><!-- flipped at cutover -->0</enabled>
+            </general>
+        </dhcp4>
+    </Kea>
+</opnsense>
+<!-- end of file -->
+"#;
+
+pub const TEST_CREATE_DNS_HOSTS: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <system>
+        <domain>home.arpa</domain>
+    </system>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <domain>lan.example</domain>
+            <staticmap>
+                <mac>00:11:22:33:44:10</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>testhost</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:11</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>printer</hostname>
+                <domain>office.example</domain>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:12</mac>
+                <ipaddr>192.168.1.12</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:13</mac>
+                <ipaddr>192.168.1.13</ipaddr>
+                <hostname>nas</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+    <dnsmasq>
+        <hosts uuid="existing-dns-host">
+            <host>nas</host>
+            <domain>lan.example</domain>
+            <ip>192.168.1.99</ip>
+        </hosts>
+    </dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_REGISTER_UNBOUND: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <system>
+        <domain>home.arpa</domain>
+    </system>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+            <ipaddrv6>2001:db8:42::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:10</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>testhost</hostname>
+                <descr>Test Server</descr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:11</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>printer</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:12</mac>
+                <ipaddr>192.168.1.12</ipaddr>
+                <hostname>nas</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <dhcpdv6>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
+                <ipaddrv6>2001:db8:42::10</ipaddrv6>
+                <hostname>testhost</hostname>
+            </staticmap>
+        </lan>
+    </dhcpdv6>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+        <dhcp6>
+            <subnets>
+                <subnet6 uuid="v6-subnet-uuid-1234">
+                    <subnet>2001:db8:42::/64</subnet>
+                </subnet6>
+            </subnets>
+        </dhcp6>
+    </Kea>
+    <OPNsense>
+        <unboundplus>
+            <hosts>
+                <host uuid="existing-override-1">
+                    <enabled>1</enabled>
+                    <hostname>printer</hostname>
+                    <domain>home.arpa</domain>
+                    <rr>A</rr>
+                    <server>192.168.1.11</server>
+                </host>
+                <host uuid="existing-override-2">
+                    <enabled>1</enabled>
+                    <hostname>nas</hostname>
+                    <domain>home.arpa</domain>
+                    <rr>A</rr>
+                    <server>192.168.1.200</server>
+                </host>
+            </hosts>
+        </unboundplus>
+    </OPNsense>
+</opnsense>
+"#;
+
+pub const TEST_EXPORT: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt3>
+            <descr>IoT VLAN</descr>
+            <ipaddr>10.30.0.1</ipaddr>
+            <subnet>24</subnet>
+            <ipaddrv6>2001:db8:30::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </opt3>
+    </interfaces>
+    <dhcpd>
+        <opt3>
+            <enable>1</enable>
+            <range>
+                <from>10.30.0.100</from>
+                <to>10.30.0.200</to>
+            </range>
+            <dnsserver>10.30.0.1</dnsserver>
+            <dnsserver>1.1.1.1</dnsserver>
+            <gateway>10.30.0.1</gateway>
+            <domain>iot.example.com</domain>
+            <ntpserver>10.30.0.10</ntpserver>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:54</mac>
+                <ipaddr>10.30.0.50</ipaddr>
+                <hostname>camera</hostname>
+                <descr>Front "door" camera</descr>
+            </staticmap>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:55</mac>
+                <ipaddr>10.30.0.51</ipaddr>
+            </staticmap>
+        </opt3>
+    </dhcpd>
+    <dhcpdv6>
+        <opt3>
+            <enable>1</enable>
+            <staticmap>
+                <duid>00:01:00:01:AA:BB:CC:DD:00:11:22:33:44:55</duid>
+                <ipaddrv6>2001:db8:30::50</ipaddrv6>
+                <hostname>camera</hostname>
+            </staticmap>
+            <staticmap>
+                <duid>not-a-duid</duid>
+                <ipaddrv6>2001:db8:30::51</ipaddrv6>
+            </staticmap>
+        </opt3>
+    </dhcpdv6>
+</opnsense>
+"#;
+
+/// ISC custom numbered options: Kea defines 66, but not 150, 224 or 252.
+pub const TEST_EXPORT_CUSTOM_OPTIONS: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+            <numberoptions>
+                <item>
+                    <number>252</number>
+                    <type>text</type>
+                    <value>"http://wpad.lan/wpad.dat"</value>
+                </item>
+                <item>
+                    <number>66</number>
+                    <type>text</type>
+                    <value>tftp.lan</value>
+                </item>
+                <item>
+                    <number>150</number>
+                    <value>192.168.1.5,192.168.1.6</value>
+                </item>
+                <item>
+                    <number>224</number>
+                    <type>string</type>
+                    <value>01:02:0a</value>
+                </item>
+            </numberoptions>
+        </lan>
+    </dhcpd>
+</opnsense>
+"#;
+
+/// dnsmasq with a range on lan only and an existing "printer" host; the ISC
+/// mappings reuse that name, carry a malformed MAC and sit on opt1.
+pub const TEST_DNSMASQ_HOST_VALIDATION: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.0.0.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.20</ipaddr>
+                <hostname>Printer</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00-11-22-33-44-66</mac>
+                <ipaddr>192.168.1.21</ipaddr>
+                <hostname>laptop</hostname>
+            </staticmap>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:77</mac>
+                <ipaddr>10.0.0.50</ipaddr>
+                <hostname>camera</hostname>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <dnsmasq>
+        <hosts uuid="existing-printer">
+            <hwaddr>99:99:99:99:99:99</hwaddr>
+            <ip>192.168.1.5</ip>
+            <host>printer</host>
+            <domain></domain>
+        </hosts>
+        <dhcp_ranges uuid="lan-range">
+            <interface>lan</interface>
+            <start_addr>192.168.1.100</start_addr>
+            <end_addr>192.168.1.200</end_addr>
+            <subnet_mask>255.255.255.0</subnet_mask>
+        </dhcp_ranges>
+    </dnsmasq>
+</opnsense>
+"#;
+
+/// Two top-level `<dhcpd>` sections plus a copy under `<installedpackages>`,
+/// as left by XMLRPC sync: one exact copy, one conflicting entry for the same
+/// MAC and one mapping only present in the later sections.
+pub const TEST_DUPLICATED_DHCPD_SECTIONS: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>first</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>second</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>first</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>192.168.1.99</ipaddr>
+                <hostname>second-stale</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:77</mac>
+                <ipaddr>192.168.1.12</ipaddr>
+                <hostname>third</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <installedpackages>
+        <dhcpd>
+            <lan>
+                <range>
+                    <from>192.168.1.150</from>
+                    <to>192.168.1.250</to>
+                </range>
+                <staticmap>
+                    <mac>00:11:22:33:44:77</mac>
+                    <ipaddr>192.168.1.12</ipaddr>
+                    <hostname>third</hostname>
+                </staticmap>
+            </lan>
+        </dhcpd>
+    </installedpackages>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_LEGACY_INSTALLEDPACKAGES: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <installedpackages>
+        <dhcpd>
+            <config>
+                <lan>
+                    <enable>1</enable>
+                    <staticmap>
+                        <mac>00:11:22:33:44:55</mac>
+                        <ipaddr>192.168.1.10</ipaddr>
+                        <hostname>legacy</hostname>
+                    </staticmap>
+                </lan>
+            </config>
+        </dhcpd>
+    </installedpackages>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CARVE_POOLS_KEA_V4: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>10.22.1.100</from>
+                <to>10.22.1.200</to>
+            </range>
+            <range>
+                <from>10.22.1.220</from>
+                <to>10.22.1.220</to>
+            </range>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:54</mac>
+                <ipaddr>10.22.1.100</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:55</mac>
+                <ipaddr>10.22.1.150</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:56</mac>
+                <ipaddr>10.22.1.220</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>04:d9:f5:cb:9b:57</mac>
+                <ipaddr>10.22.1.10</ipaddr>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets></subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_LEASE_ACTIVITY: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:10</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>online</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:11</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>stale</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:12</mac>
+                <ipaddr>192.168.1.12</ipaddr>
+                <hostname>never</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:13</mac>
+                <ipaddr>192.168.1.13</ipaddr>
+                <hostname>recent</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+/// Leases for [`TEST_LEASE_ACTIVITY`]; `{recent}` is replaced with a
+/// timestamp from the last few days.
+pub const TEST_LEASE_ACTIVITY_LEASES: &str = r#"lease 192.168.1.10 {
+  starts 3 2026/01/07 08:00:00;
+  ends 4 2999/01/01 00:00:00;
+  binding state active;
+  hardware ethernet 00:11:22:33:44:10;
+}
+lease 192.168.1.150 {
+  starts 1 2001/01/01 08:00:00;
+  ends 1 2001/01/01 10:00:00;
+  binding state free;
+  hardware ethernet 00:11:22:33:44:11;
+}
+lease 192.168.1.13 {
+  starts epoch {recent};
+  ends epoch {recent};
+  binding state free;
+  hardware ethernet 00:11:22:33:44:13;
+}
+"#;
+
+pub const TEST_DUAL_ID_KEA_XML: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <cid>01:00:11:22:33:44:55</cid>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>dualhost</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <cid>01:aa:bb:cc:dd:ee:ff</cid>
+                <ipaddr>192.168.1.11</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>66:77:88:99:aa:bb</mac>
+                <cid>01:66:77:88:99:aa:bb</cid>
+                <ipaddr>192.168.1.12</ipaddr>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations>
+                <reservation uuid="existing-cid">
+                    <subnet>test-subnet-uuid-1234</subnet>
+                    <ip_address>192.168.1.50</ip_address>
+                    <client_id>01:aa:bb:cc:dd:ee:ff</client_id>
+                </reservation>
+                <reservation uuid="existing-mac">
+                    <subnet>test-subnet-uuid-1234</subnet>
+                    <ip_address>192.168.1.60</ip_address>
+                    <hw_address>66:77:88:99:aa:bb</hw_address>
+                </reservation>
+            </reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_DUAL_ID_DNSMASQ_XML: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <cid>01:00:11:22:33:44:55</cid>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>dualhost</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <cid>01:aa:bb:cc:dd:ee:ff</cid>
+                <ipaddr>192.168.1.11</ipaddr>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <dnsmasq>
+        <hosts uuid="existing-host-1">
+            <hwaddr></hwaddr>
+            <client_id>01:aa:bb:cc:dd:ee:ff</client_id>
+            <ip>192.168.1.50</ip>
+            <host>existing</host>
+        </hosts>
+    </dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_PREFIX_V6_XML: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <opt2>
+            <ipaddrv6>2001:db8:42::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </opt2>
+    </interfaces>
+    <dhcpdv6>
+        <opt2>
+            <enable>1</enable>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:01</duid>
+                <ipaddrv6>2001:db8:42::10</ipaddrv6>
+                <prefix>2001:db8:100::/56</prefix>
+            </staticmap>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:02</duid>
+                <ipaddrv6>2001:db8:42::11</ipaddrv6>
+                <prefix>2001:db8:200::</prefix>
+                <prefixlen>60</prefixlen>
+            </staticmap>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:03</duid>
+                <ipaddrv6>2001:db8:42::12</ipaddrv6>
+                <prefix>2001:db8:300::</prefix>
+            </staticmap>
+        </opt2>
+    </dhcpdv6>
+    <Kea>
+        <dhcp6>
+            <subnets>
+                <subnet6 uuid="v6-subnet-uuid-1234">
+                    <subnet>2001:db8:42::/64</subnet>
+                </subnet6>
+            </subnets>
+        </dhcp6>
+    </Kea>
+    <dnsmasq>
+    </dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_DUPLICATE_UUID_KEA_XML: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>192.168.2.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <opt1>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>192.168.2.10</ipaddr>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="dup-subnet-uuid">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+                <subnet4 uuid="dup-subnet-uuid">
+                    <subnet>192.168.2.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations>
+                <reservation uuid="existing-res-1">
+                    <subnet>dup-subnet-uuid</subnet>
+                    <ip_address>192.168.1.50</ip_address>
+                    <hw_address>00:11:22:33:44:01</hw_address>
+                </reservation>
+                <reservation uuid="existing-res-2">
+                    <subnet>dup-subnet-uuid</subnet>
+                    <ip_address>192.168.2.50</ip_address>
+                    <hw_address>00:11:22:33:44:02</hw_address>
+                </reservation>
+            </reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_HOSTNAME_POLICY_XML: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:01</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>nas</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:02</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>printer.old.lan</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="test-subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+    <dnsmasq>
+    </dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_KEA_INTERFACES_NOT_LISTENING: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.0.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+            </staticmap>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:66</mac>
+                <ipaddr>10.0.1.10</ipaddr>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <general>
+                <enabled>1</enabled>
+                <interfaces>lan</interfaces>
+            </general>
+            <subnets>
+                <subnet4 uuid="subnet-lan">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+                <subnet4 uuid="subnet-opt1">
+                    <subnet>10.0.1.0/24</subnet>
+                    <interface>opt1</interface>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_HOSTNAME_COLLISIONS: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:10</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>Printer</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:11</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>nas</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="subnet-lan">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations>
+                <reservation uuid="res-printer">
+                    <subnet>subnet-lan</subnet>
+                    <ip_address>192.168.1.20</ip_address>
+                    <hw_address>00:11:22:33:44:20</hw_address>
+                    <hostname>printer</hostname>
+                </reservation>
+            </reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_STATIC_LEASES_PLUGIN: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <OPNsense>
+        <dhcpleases>
+            <leases>
+                <lease uuid="lease-1">
+                    <enabled>1</enabled>
+                    <interface>lan</interface>
+                    <mac>00:11:22:33:44:10</mac>
+                    <ipaddr>192.168.1.10</ipaddr>
+                    <hostname>printer</hostname>
+                </lease>
+                <lease uuid="lease-2">
+                    <mac>00:11:22:33:44:11</mac>
+                    <ipaddr>192.168.1.11</ipaddr>
+                    <descr>No interface set</descr>
+                </lease>
+                <lease uuid="lease-3">
+                    <enabled>0</enabled>
+                    <interface>lan</interface>
+                    <mac>00:11:22:33:44:12</mac>
+                    <ipaddr>192.168.1.12</ipaddr>
+                </lease>
+            </leases>
+        </dhcpleases>
+    </OPNsense>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="subnet-lan">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+/// ISC gateway `none` on lan (no router option) and blank on opt1 (the
+/// interface address).
+pub const TEST_GATEWAY_NONE: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <dnsserver>9.9.9.9</dnsserver>
+            <gateway>none</gateway>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <dnsserver>8.8.8.8</dnsserver>
+            <gateway></gateway>
+            <range>
+                <from>10.22.1.100</from>
+                <to>10.22.1.200</to>
+            </range>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet">
+                    <subnet>192.168.1.0/24</subnet>
+                    <option_data_autocollect>1</option_data_autocollect>
+                    <option_data>
+                        <domain_name_servers/>
+                        <routers>192.168.1.254</routers>
+                    </option_data>
+                </subnet4>
+                <subnet4 uuid="opt1-subnet">
+                    <subnet>10.22.1.0/24</subnet>
+                    <option_data_autocollect>1</option_data_autocollect>
+                    <option_data>
+                        <domain_name_servers/>
+                        <routers/>
+                    </option_data>
+                </subnet4>
+            </subnets>
+        </dhcp4>
+    </Kea>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_DNSMASQ_HOST_TAGS: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:01</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <hostname>printer</hostname>
+                <descr>Office printer [no-dns]</descr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:02</mac>
+                <ipaddr>192.168.1.12</ipaddr>
+                <hostname>guest</hostname>
+                <descr>[IGNORE] old guest laptop</descr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:03</mac>
+                <ipaddr>192.168.1.13</ipaddr>
+                <hostname>nas</hostname>
+                <descr>NAS [no-local]</descr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:04</mac>
+                <ipaddr>192.168.1.14</ipaddr>
+                <hostname>desktop</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_DUAL_STACK_XML: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+            <ipaddrv6>2001:db8:42::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:55</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>printer</hostname>
+                <descr>Printer</descr>
+            </staticmap>
+            <staticmap>
+                <mac>00:aa:bb:cc:dd:ee</mac>
+                <ipaddr>192.168.1.20</ipaddr>
+                <hostname>laptop</hostname>
+            </staticmap>
+            <staticmap>
+                <mac>00:aa:bb:cc:dd:ff</mac>
+                <ipaddr>192.168.1.30</ipaddr>
+                <hostname>camera</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <dhcpdv6>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
+                <ipaddrv6>2001:db8:42::10</ipaddrv6>
+            </staticmap>
+            <staticmap>
+                <duid>00:02:00:00:ab:11:01:02:03:04</duid>
+                <ipaddrv6>2001:db8:42::20</ipaddrv6>
+                <hostname>Laptop.example.com</hostname>
+            </staticmap>
+        </lan>
+    </dhcpdv6>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="subnet-uuid-1234">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations/>
+        </dhcp4>
+        <dhcp6>
+            <subnets>
+                <subnet6 uuid="v6-subnet-uuid-1234">
+                    <subnet>2001:db8:42::/64</subnet>
+                </subnet6>
+            </subnets>
+            <reservations/>
+        </dhcp6>
+    </Kea>
+</opnsense>
+"#;
+
+/// Classless static routes as ISC custom options: 121 in wire format, 249
+/// as text, and a 121 on opt1 that is not a route list.
+pub const TEST_CLASSLESS_ROUTES: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+            <numberoptions>
+                <item>
+                    <number>121</number>
+                    <type>string</type>
+                    <value>08:0a:c0:a8:01:02:18:ac:10:05:c0:a8:01:03</value>
+                </item>
+                <item>
+                    <number>249</number>
+                    <type>text</type>
+                    <value>"10.0.0.0/8 192.168.1.2, 172.16.5.0/24 192.168.1.3"</value>
+                </item>
+            </numberoptions>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <numberoptions>
+                <item>
+                    <number>121</number>
+                    <type>text</type>
+                    <value>via the lan router</value>
+                </item>
+            </numberoptions>
+        </opt1>
+    </dhcpd>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+/// ISC netboot on lan (TFTP server only), opt1 (next server and filename)
+/// and opt2, which already has a dnsmasq boot entry.
+pub const TEST_CREATE_SUBNETS_DNSMASQ_NETBOOT: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+        <opt2>
+            <ipaddr>10.33.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt2>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.200</to>
+            </range>
+            <tftp>tftp.lan</tftp>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>10.22.1.100</from>
+                <to>10.22.1.200</to>
+            </range>
+            <netboot>1</netboot>
+            <nextserver>10.22.1.5</nextserver>
+            <filename>pxelinux.0</filename>
+            <tftp>tftp.opt1</tftp>
+        </opt1>
+        <opt2>
+            <enable>1</enable>
+            <range>
+                <from>10.33.1.100</from>
+                <to>10.33.1.200</to>
+            </range>
+            <bootfilename>ipxe.efi</bootfilename>
+        </opt2>
+    </dhcpd>
+    <dnsmasq>
+        <dhcp_boot uuid="boot-opt2">
+            <interface>opt2</interface>
+            <tag/>
+            <filename>undionly.kpxe</filename>
+        </dhcp_boot>
+    </dnsmasq>
+</opnsense>
+"#;
+
+/// Edge subnets: a /30 whose range spans network to broadcast, a /31
+/// point-to-point link written as a netmask, and a /30 range holding only
+/// the network address.
+pub const TEST_CREATE_SUBNETS_EDGE_PREFIXES: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>10.0.0.1</ipaddr>
+            <subnet>30</subnet>
+        </lan>
+        <opt1>
+            <ipaddr>10.0.0.4</ipaddr>
+            <subnet>255.255.255.254</subnet>
+        </opt1>
+        <opt2>
+            <ipaddr>10.0.0.9</ipaddr>
+            <subnet>30</subnet>
+        </opt2>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>10.0.0.0</from>
+                <to>10.0.0.3</to>
+            </range>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>10.0.0.4</from>
+                <to>10.0.0.5</to>
+            </range>
+        </opt1>
+        <opt2>
+            <enable>1</enable>
+            <range>
+                <from>10.0.0.8</from>
+                <to>10.0.0.8</to>
+            </range>
+        </opt2>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets></subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_SUBNETS_INVALID_RANGES: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <descr>LAN</descr>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+            <ipaddrv6>fd00::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </lan>
+        <opt1>
+            <descr>IoT</descr>
+            <ipaddr>192.168.2.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+        <opt2>
+            <ipaddr>192.168.3.1</ipaddr>
+            <subnet>24</subnet>
+        </opt2>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.200</from>
+                <to>192.168.1.100</to>
+            </range>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <range>
+                <from>192.168.2.100</from>
+                <to>fd00::200</to>
+            </range>
+        </opt1>
+        <opt2>
+            <enable>1</enable>
+            <range>
+                <from>192.168.3.100</from>
+                <to>192.168.3.300</to>
+            </range>
+        </opt2>
+    </dhcpd>
+    <dhcpdv6>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.10</from>
+                <to>192.168.1.20</to>
+            </range>
+        </lan>
+    </dhcpdv6>
+    <Kea>
+        <dhcp4>
+            <subnets></subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+pub const TEST_CREATE_SUBNETS_FRAGMENTED_RANGES: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <range>
+                <from>192.168.1.100</from>
+                <to>192.168.1.110</to>
+            </range>
+            <range>
+                <from>192.168.1.21</from>
+                <to>192.168.1.30</to>
+            </range>
+            <range>
+                <from>192.168.1.10</from>
+                <to>192.168.1.20</to>
+            </range>
+            <range>
+                <from>192.168.1.25</from>
+                <to>192.168.1.40</to>
+            </range>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets></subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+    <dnsmasq></dnsmasq>
+</opnsense>
+"#;
+
+pub const TEST_STATICMAP_OPTIONS: &str = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <dnsserver>192.168.1.1</dnsserver>
+            <staticmap>
+                <mac>00:11:22:33:44:01</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+                <hostname>kids-tablet</hostname>
+                <dnsserver>192.168.1.53</dnsserver>
+                <dnsserver>192.168.1.54</dnsserver>
+                <gateway>192.168.1.254</gateway>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:02</mac>
+                <ipaddr>192.168.1.11</ipaddr>
+                <gateway>192.168.1.253</gateway>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:03</mac>
+                <ipaddr>192.168.1.12</ipaddr>
+                <hostname>printer</hostname>
+            </staticmap>
+        </lan>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+    <dnsmasq>
+        <dhcp_tags uuid="existing-tag">
+            <tag>host_kids_tablet</tag>
+        </dhcp_tags>
+    </dnsmasq>
+</opnsense>
+"#;
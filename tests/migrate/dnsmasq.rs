@@ -1,5 +1,8 @@
 use super::common::*;
-use isc2kea::{convert_config, scan_config};
+use isc2kea::{
+    convert_config, generate_fixture, scan_config, Backend, DnsmasqHostIssue, FixtureSpec,
+    HostnamePolicy, MigrationError, MigrationOptions,
+};
 use std::fs;
 use std::io::Cursor;
 use xmltree::Element;
@@ -152,6 +155,10 @@ fn test_dnsmasq_skip_duplicate_ip() {
     assert_eq!(stats.isc_mappings_found, 1);
     assert_eq!(stats.reservations_to_create, 0);
     assert_eq!(stats.reservations_skipped, 1);
+    assert!(matches!(
+        stats.skips.as_slice(),
+        [MigrationError::DuplicateIp { .. }]
+    ));
 }
 
 #[test]
@@ -163,6 +170,10 @@ fn test_dnsmasq_skip_duplicate_mac() {
     assert_eq!(stats.isc_mappings_found, 1);
     assert_eq!(stats.reservations_to_create, 0);
     assert_eq!(stats.reservations_skipped, 1);
+    assert!(matches!(
+        stats.skips.as_slice(),
+        [MigrationError::DuplicateMac { .. }]
+    ));
 }
 
 #[test]
@@ -199,6 +210,7 @@ fn test_dnsmasq_error_when_not_configured() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -271,6 +283,78 @@ fn test_dnsmasq_convert_matches_golden_fixtures() {
     );
 }
 
+#[test]
+fn test_dnsmasq_convert_clears_no_dhcp_interface() {
+    let input = fs::read_to_string("fixtures/dnsmasq_no_dhcp_interface.xml")
+        .expect("no_interface fixture should be readable");
+    let dhcp_text = |output: &[u8], name: &str| {
+        let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+        let dnsmasq = find_descendant_ci(&root, "dnsmasq").expect("output should have dnsmasq");
+        let dhcp = dnsmasq
+            .get_child("dhcp")
+            .expect("dnsmasq should keep <dhcp>");
+        dhcp.get_child(name)
+            .and_then(|el| el.get_text())
+            .map(|text| text.to_string())
+            .unwrap_or_default()
+    };
+
+    // Hosts alone leave the list alone
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(&input), &mut output, &dnsmasq_options())
+        .expect("convert should succeed");
+    assert!(stats.no_dhcp_cleared.is_empty());
+    assert_eq!(dhcp_text(&output, "no_interface"), "lan,opt1");
+
+    let options = MigrationOptions {
+        enable_backend: true,
+        ..dnsmasq_options_create_subnets()
+    };
+    let mut output = Vec::new();
+    let stats =
+        convert_config(Cursor::new(&input), &mut output, &options).expect("convert should succeed");
+    assert_eq!(stats.no_dhcp_cleared, vec!["lan"]);
+    assert_eq!(dhcp_text(&output, "no_interface"), "opt1");
+    assert_eq!(dhcp_text(&output, "default_fw_rules"), "1");
+}
+
+#[test]
+fn test_dnsmasq_created_children_follow_model_order() {
+    let input = fs::read_to_string("fixtures/dnsmasq_no_dhcp_interface.xml")
+        .expect("no_interface fixture should be readable");
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(&input),
+        &mut output,
+        &dnsmasq_options_create_subnets(),
+    )
+    .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dnsmasq = find_descendant_ci(&root, "dnsmasq").expect("output should have dnsmasq");
+    let names: Vec<&str> = dnsmasq
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .map(|el| el.name.as_str())
+        .skip_while(|name| *name != "dhcp")
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "dhcp",
+            "hosts",
+            "hosts",
+            "domainoverrides",
+            "dhcp_tags",
+            "dhcp_ranges",
+            "dhcp_ranges",
+            "dhcp_options",
+            "dhcp_boot",
+        ]
+    );
+}
+
 #[test]
 fn test_dnsmasq_convert_matches_golden_fixtures_v6() {
     let input = fs::read_to_string("fixtures/dnsmasq_v6_minimal.xml")
@@ -395,3 +479,485 @@ fn test_enable_backend_dnsmasq_stats() {
     assert!(stats.backend_enabled_v4);
     assert!(stats.backend_enabled_v6);
 }
+
+#[test]
+fn test_backend_both_converts_kea_and_dnsmasq() {
+    let options = MigrationOptions {
+        backend: Backend::Both,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_BACKEND_BOTH), &mut output, &options)
+        .expect("convert should succeed");
+
+    assert_eq!(stats.isc_mappings_found, 2);
+    assert_eq!(stats.reservations_to_create, 3);
+    assert_eq!(stats.reservations_skipped, 1);
+
+    let per_backend: Vec<_> = stats
+        .per_backend
+        .iter()
+        .map(|(backend, s)| {
+            (
+                backend.to_string(),
+                s.reservations_to_create,
+                s.reservations_skipped,
+            )
+        })
+        .collect();
+    assert_eq!(
+        per_backend,
+        vec![("Kea".to_string(), 2, 0), ("dnsmasq".to_string(), 1, 1)]
+    );
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let dhcp4 = find_descendant_ci(&root, "dhcp4").expect("Kea dhcp4");
+    assert_eq!(reservations_as_fields(dhcp4).len(), 2);
+    let ips: Vec<_> = dnsmasq_hosts(&root)
+        .iter()
+        .filter_map(|h| h.get_child("ip").and_then(|e| e.get_text()))
+        .map(|ip| ip.to_string())
+        .collect();
+    assert_eq!(ips, vec!["192.168.1.11", "192.168.1.10"]);
+}
+
+#[test]
+fn test_backend_both_counts_shared_skips_once() {
+    // The printer is already reserved in both backends
+    let xml = TEST_BACKEND_BOTH.replace(
+        "            </subnets>\n        </dhcp4>",
+        "            </subnets>
+            <reservations>
+                <reservation uuid=\"existing-reservation-1\">
+                    <subnet>test-subnet-uuid-1234</subnet>
+                    <ip_address>192.168.1.11</ip_address>
+                    <hw_address>00:11:22:33:44:66</hw_address>
+                </reservation>
+            </reservations>
+        </dhcp4>",
+    );
+    let options = MigrationOptions {
+        backend: Backend::Both,
+        ..Default::default()
+    };
+    for stats in [
+        scan_config(Cursor::new(&xml), &options).expect("scan should succeed"),
+        convert_config(Cursor::new(&xml), Vec::new(), &options).expect("convert should succeed"),
+    ] {
+        assert_eq!(stats.reservations_to_create, 2);
+        assert_eq!(stats.reservations_skipped, 1);
+        assert_eq!(stats.skips.len(), 1);
+        assert!(stats
+            .per_backend
+            .iter()
+            .all(|(_, s)| s.reservations_skipped == 1));
+    }
+}
+
+#[test]
+fn test_backend_both_scan_reports_per_backend() {
+    let options = MigrationOptions {
+        backend: Backend::Both,
+        ..Default::default()
+    };
+    let stats = scan_config(Cursor::new(TEST_BACKEND_BOTH), &options).expect("scan should succeed");
+
+    assert_eq!(stats.per_backend.len(), 2);
+    assert_eq!(stats.per_backend[0].1.target_subnets_found, 1);
+    assert_eq!(stats.per_backend[1].1.reservations_to_create, 1);
+    assert_eq!(stats.per_backend[1].1.reservations_skipped, 1);
+}
+
+#[test]
+fn test_backend_both_rejects_enable_backend() {
+    let options = MigrationOptions {
+        backend: Backend::Both,
+        enable_backend: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let err = convert_config(Cursor::new(TEST_BACKEND_BOTH), &mut output, &options)
+        .expect_err("enable_backend should be rejected with both backends");
+    assert!(err.to_string().contains("--backend both"));
+}
+
+fn child_names(elem: &Element) -> Vec<String> {
+    elem.children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .map(|e| e.name.clone())
+        .collect()
+}
+
+#[test]
+fn test_dnsmasq_minimal_elements_host() {
+    let mut options = dnsmasq_options();
+    options.minimal_elements = true;
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_DNSMASQ_XML_V6), &mut output, &options)
+        .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let hosts = dnsmasq_hosts(&root);
+    assert_eq!(hosts.len(), 1);
+    let names = child_names(hosts[0]);
+    for kept in ["host", "ip", "client_id", "local", "ignore"] {
+        assert!(names.iter().any(|n| n == kept), "{} should be kept", kept);
+    }
+    for dropped in [
+        "hwaddr",
+        "lease_time",
+        "cnames",
+        "set_tag",
+        "comments",
+        "aliases",
+    ] {
+        assert!(
+            !names.iter().any(|n| n == dropped),
+            "{} should be dropped",
+            dropped
+        );
+    }
+}
+
+#[test]
+fn test_dnsmasq_minimal_elements_range() {
+    let mut options = dnsmasq_options_create_subnets();
+    options.minimal_elements = true;
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_DNSMASQ_V4),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let dnsmasq = find_descendant_ci(&root, "dnsmasq").expect("dnsmasq node");
+    let range = dnsmasq
+        .get_child("dhcp_ranges")
+        .expect("should create a range");
+    let mut names = child_names(range);
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "domain_type",
+            "end_addr",
+            "interface",
+            "nosync",
+            "start_addr",
+            "subnet_mask"
+        ]
+    );
+}
+
+fn dns_host_names(root: &Element) -> Vec<(String, String, String)> {
+    dnsmasq_hosts(root)
+        .iter()
+        .map(|host| {
+            let text = |tag: &str| {
+                host.get_child(tag)
+                    .and_then(|e| e.get_text())
+                    .map(|s| s.to_string())
+                    .unwrap_or_default()
+            };
+            (text("host"), text("domain"), text("ip"))
+        })
+        .collect()
+}
+
+#[test]
+fn test_create_dns_hosts_with_kea_backend() {
+    let options = MigrationOptions {
+        create_dns_hosts: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_CREATE_DNS_HOSTS), &mut output, &options)
+        .expect("convert should succeed");
+
+    assert_eq!(stats.reservations_to_create, 4);
+    assert_eq!(stats.dns_hosts_created, 2);
+    assert_eq!(stats.dns_hosts_updated, 0);
+    assert_eq!(
+        stats.dns_hosts_skipped, 1,
+        "nas conflicts with an existing name"
+    );
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let to_tuple = |h: &str, d: &str, ip: &str| (h.to_string(), d.to_string(), ip.to_string());
+    assert_eq!(
+        dns_host_names(&root),
+        vec![
+            to_tuple("nas", "lan.example", "192.168.1.99"),
+            to_tuple("testhost", "lan.example", "192.168.1.10"),
+            to_tuple("printer", "office.example", "192.168.1.11"),
+        ]
+    );
+    let created = dnsmasq_hosts(&root)[1];
+    assert_eq!(
+        created.get_child("hwaddr").and_then(|e| e.get_text()),
+        None,
+        "DNS-only hosts have no hardware address"
+    );
+}
+
+#[test]
+fn test_create_dns_hosts_fills_domain_on_dnsmasq_hosts() {
+    let mut options = dnsmasq_options();
+    options.create_dns_hosts = true;
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_CREATE_DNS_HOSTS), &mut output, &options)
+        .expect("convert should succeed");
+
+    assert_eq!(stats.reservations_to_create, 4);
+    assert_eq!(stats.dns_hosts_created, 0);
+    assert_eq!(stats.dns_hosts_updated, 2);
+    assert_eq!(stats.dns_hosts_skipped, 1);
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let names = dns_host_names(&root);
+    assert!(names.contains(&(
+        "testhost".to_string(),
+        "lan.example".to_string(),
+        "192.168.1.10".to_string()
+    )));
+    assert!(names.contains(&("nas".to_string(), String::new(), "192.168.1.13".to_string())));
+}
+
+#[test]
+fn test_dnsmasq_convert_rejects_invalid_hosts() {
+    let mut output = Vec::new();
+    let err = convert_config(
+        Cursor::new(TEST_DNSMASQ_HOST_VALIDATION),
+        &mut output,
+        &dnsmasq_options(),
+    )
+    .expect_err("convert should fail validation");
+    assert!(output.is_empty(), "nothing should be written");
+
+    let Some(MigrationError::InvalidDnsmasqHosts { issues }) = err.downcast_ref() else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(
+        issues,
+        &vec![
+            DnsmasqHostIssue::DuplicateName {
+                fqdn: "printer".to_string(),
+                ip: "192.168.1.20".to_string(),
+                other_ip: "192.168.1.5".to_string(),
+            },
+            DnsmasqHostIssue::InvalidHwaddr {
+                host: "laptop".to_string(),
+                hwaddr: "00-11-22-33-44-66".to_string(),
+            },
+            DnsmasqHostIssue::OutsideRanges {
+                host: "camera".to_string(),
+                ip: "10.0.0.50".to_string(),
+            },
+        ]
+    );
+    assert!(err.to_string().starts_with("3 created dnsmasq host(s)"));
+}
+
+#[test]
+fn test_dnsmasq_validation_ignores_existing_hosts() {
+    // The existing host is malformed and out of range, but was already there
+    let input = TEST_DNSMASQ_HOST_VALIDATION
+        .replace(
+            "<hostname>Printer</hostname>",
+            "<hostname>printer2</hostname>",
+        )
+        .replace("00-11-22-33-44-66", "00:11:22:33:44:66")
+        .replace("99:99:99:99:99:99", "not-a-mac")
+        .replace("<ip>192.168.1.5</ip>", "<ip>172.16.0.5</ip>")
+        .replace(
+            "</dnsmasq>",
+            r#"    <dhcp_ranges uuid="opt1-range">
+            <interface>opt1</interface>
+            <start_addr>10.0.0.100</start_addr>
+            <end_addr>10.0.0.200</end_addr>
+        </dhcp_ranges>
+    </dnsmasq>"#,
+        );
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(input), &mut output, &dnsmasq_options())
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 3);
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    assert_eq!(dnsmasq_hosts(&root).len(), 4);
+}
+
+#[test]
+fn test_dnsmasq_skips_non_ethernet_hwaddrs() {
+    let input = TEST_DNSMASQ_XML.replace(
+        "00:11:22:33:44:55",
+        "80:00:02:08:fe:80:00:00:00:00:00:00:00:02:c9:03:00:0a:0b:0c",
+    );
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(input), &mut output, &dnsmasq_options())
+        .expect("convert should succeed");
+    assert_eq!(stats.isc_mappings_found, 1);
+    assert_eq!(stats.reservations_to_create, 0);
+    assert_eq!(stats.reservations_skipped, 1);
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    assert!(dnsmasq_hosts(&root).is_empty());
+}
+
+#[test]
+fn test_dnsmasq_dual_identifier_mappings() {
+    let options = dnsmasq_options();
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_DUAL_ID_DNSMASQ_XML), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.reservations_skipped, 1);
+
+    let root = Element::parse(Cursor::new(output)).expect("parse output");
+    let host = root
+        .get_child("dnsmasq")
+        .and_then(|d| {
+            d.children.iter().filter_map(|n| n.as_element()).find(|h| {
+                h.get_child("ip").and_then(|e| e.get_text()).as_deref() == Some("192.168.1.10")
+            })
+        })
+        .expect("dual-identified host");
+    let text = |name: &str| host.get_child(name).and_then(|e| e.get_text());
+    assert_eq!(text("hwaddr").as_deref(), Some("00:11:22:33:44:55"));
+    assert_eq!(text("client_id").as_deref(), Some("01:00:11:22:33:44:55"));
+}
+
+#[test]
+fn test_dnsmasq_reports_prefix_delegation_unsupported() {
+    let options = dnsmasq_options();
+    let stats =
+        scan_config(Cursor::new(TEST_PREFIX_V6_XML), &options).expect("scan should succeed");
+    assert_eq!(stats.reservations_v6_to_create, 3);
+    assert_eq!(stats.prefix_reservations_v6, 0);
+    assert_eq!(stats.prefixes_v6_unmigrated, 3);
+
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_PREFIX_V6_XML), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_v6_to_create, 3);
+    assert_eq!(stats.prefixes_v6_unmigrated, 3);
+    let root = Element::parse(Cursor::new(output)).expect("parse output");
+    let hosts = dnsmasq_hosts(&root);
+    assert_eq!(hosts.len(), 3);
+    assert!(hosts.iter().all(|h| dnsmasq_host_fields(h)
+        .iter()
+        .all(|(_, value)| !value.contains("2001:db8:100::"))));
+}
+
+#[test]
+fn test_dnsmasq_hostname_policy_fqdn() {
+    let options = MigrationOptions {
+        hostname_policy: HostnamePolicy::Fqdn,
+        hostname_domain: Some("example.lan".to_string()),
+        ..dnsmasq_options()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_HOSTNAME_POLICY_XML), &mut output, &options)
+        .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("parse output");
+    let names: Vec<_> = dnsmasq_hosts(&root)
+        .into_iter()
+        .map(|host| {
+            let text = |name: &str| {
+                host.get_child(name)
+                    .and_then(|e| e.get_text())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            (text("host"), text("domain"))
+        })
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("nas".to_string(), "example.lan".to_string()),
+            ("printer".to_string(), "example.lan".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_generated_fixture_migrates_to_dnsmasq() {
+    let spec = FixtureSpec {
+        existing_per_iface: 3,
+        ..Default::default()
+    };
+    let xml = generate_fixture(&spec).expect("generate fixture");
+    let options = MigrationOptions {
+        backend: Backend::Dnsmasq,
+        ..Default::default()
+    };
+    let stats = scan_config(Cursor::new(&xml), &options).expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 20);
+    assert_eq!(stats.reservations_to_create, 14);
+    assert_eq!(stats.reservations_skipped, 6);
+}
+
+#[test]
+fn test_dnsmasq_host_flags() {
+    // (host, local, ignore) of each created host, in order
+    let flags = |options: &MigrationOptions| {
+        let mut output = Vec::new();
+        convert_config(Cursor::new(TEST_DNSMASQ_HOST_TAGS), &mut output, options)
+            .expect("convert should succeed");
+        let root = Element::parse(Cursor::new(output)).expect("valid XML");
+        root.get_child("dnsmasq")
+            .expect("dnsmasq")
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .filter(|e| e.name == "hosts")
+            .map(|host| {
+                let text = |tag: &str| {
+                    host.get_child(tag)
+                        .and_then(|e| e.get_text())
+                        .map(|t| t.to_string())
+                        .unwrap_or_default()
+                };
+                (text("host"), text("local"), text("ignore"))
+            })
+            .collect::<Vec<_>>()
+    };
+    let row = |host: &str, local: &str, ignore: &str| {
+        (host.to_string(), local.to_string(), ignore.to_string())
+    };
+
+    let options = MigrationOptions {
+        backend: Backend::Dnsmasq,
+        ..Default::default()
+    };
+    assert_eq!(
+        flags(&options),
+        vec![
+            row("printer", "0", "0"),
+            row("guest", "0", "0"),
+            row("nas", "0", "0"),
+            row("desktop", "0", "0"),
+        ]
+    );
+
+    let options = MigrationOptions {
+        backend: Backend::Dnsmasq,
+        dnsmasq_hosts_local: true,
+        dnsmasq_host_tags: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        flags(&options),
+        vec![
+            row("", "1", "0"),
+            row("guest", "1", "1"),
+            row("nas", "0", "0"),
+            row("desktop", "1", "0"),
+        ]
+    );
+}
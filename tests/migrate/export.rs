@@ -0,0 +1,340 @@
+use super::common::*;
+use isc2kea::{export_config, export_config_split, ExportFormat, MigrationOptions};
+use std::io::Cursor;
+use std::path::Path;
+// export tests
+// ---------------------------------------------------------------------------
+
+fn export(xml: &str, format: ExportFormat) -> (String, isc2kea::MigrationStats) {
+    let mut output = Vec::new();
+    let stats = export_config(
+        Cursor::new(xml),
+        &mut output,
+        format,
+        &MigrationOptions::default(),
+    )
+    .expect("export should succeed");
+    (String::from_utf8(output).expect("UTF-8 output"), stats)
+}
+
+fn export_routeros(xml: &str) -> (String, isc2kea::MigrationStats) {
+    export(xml, ExportFormat::Routeros)
+}
+
+#[test]
+fn test_export_routeros_pool_and_network() {
+    let (rsc, stats) = export_routeros(TEST_EXPORT);
+
+    assert_eq!(stats.target_subnets_found, 1);
+    assert!(rsc.contains(
+        "/ip pool add name=\"opt3-pool\" ranges=10.30.0.100-10.30.0.200 comment=\"opt3 (IoT VLAN)\"\n"
+    ));
+    assert!(rsc.contains(
+        "/ip dhcp-server network add address=10.30.0.0/24 gateway=10.30.0.1 \
+         dns-server=10.30.0.1,1.1.1.1 ntp-server=10.30.0.10 domain=\"iot.example.com\" \
+         comment=\"opt3 (IoT VLAN)\"\n"
+    ));
+}
+
+#[test]
+fn test_export_routeros_leases_and_bindings() {
+    let (rsc, stats) = export_routeros(TEST_EXPORT);
+
+    assert_eq!(stats.isc_mappings_found, 2);
+    assert_eq!(stats.reservations_to_create, 2);
+    assert_eq!(stats.reservations_v6_to_create, 1);
+    assert_eq!(stats.reservations_v6_skipped, 1, "non-hex DUID is skipped");
+
+    let leases: Vec<&str> = rsc
+        .lines()
+        .filter(|l| l.starts_with("/ip dhcp-server lease add"))
+        .collect();
+    assert_eq!(
+        leases,
+        vec![
+            "/ip dhcp-server lease add address=10.30.0.50 mac-address=04:D9:F5:CB:9B:54 \
+             comment=\"camera - Front \\\"door\\\" camera\"",
+            "/ip dhcp-server lease add address=10.30.0.51 mac-address=04:D9:F5:CB:9B:55",
+        ]
+    );
+    assert!(rsc.contains(
+        "/ipv6 dhcp-server binding add address=2001:db8:30::50/128 \
+         duid=0x00010001aabbccdd001122334455 comment=\"camera\"\n"
+    ));
+    assert!(!rsc.contains("2001:db8:30::51"));
+}
+
+#[test]
+fn test_export_skips_disabled_interfaces() {
+    let (rsc, stats) = export_routeros(TEST_DISABLED_ISC_INTERFACE);
+
+    assert!(stats.isc_mappings_disabled_iface > 0);
+    assert_eq!(
+        rsc.lines()
+            .filter(|l| l.starts_with("/ip dhcp-server lease add"))
+            .count(),
+        stats.reservations_to_create
+    );
+}
+
+#[test]
+fn test_export_uci_dhcp_section() {
+    let (uci, stats) = export(TEST_EXPORT, ExportFormat::Uci);
+
+    assert_eq!(stats.target_subnets_found, 1);
+    assert!(uci.contains(
+        "# opt3 (IoT VLAN)\n\
+         config dhcp 'opt3'\n\
+         \toption interface 'opt3'\n\
+         \toption start '100'\n\
+         \toption limit '101'\n\
+         \tlist dhcp_option '6,10.30.0.1,1.1.1.1'\n\
+         \tlist dhcp_option '3,10.30.0.1'\n\
+         \tlist dhcp_option '15,iot.example.com'\n\
+         \tlist dhcp_option '42,10.30.0.10'\n"
+    ));
+}
+
+#[test]
+fn test_export_uci_hosts() {
+    let (uci, stats) = export(TEST_EXPORT, ExportFormat::Uci);
+
+    assert_eq!(stats.reservations_to_create, 2);
+    assert_eq!(stats.reservations_v6_to_create, 1);
+    assert_eq!(stats.reservations_v6_skipped, 1, "non-hex DUID is skipped");
+    assert!(uci.contains(
+        "# Front \"door\" camera\n\
+         config host\n\
+         \toption name 'camera'\n\
+         \toption mac '04:d9:f5:cb:9b:54'\n\
+         \toption ip '10.30.0.50'\n"
+    ));
+    assert!(uci.contains(
+        "\nconfig host\n\
+         \toption mac '04:d9:f5:cb:9b:55'\n\
+         \toption ip '10.30.0.51'\n"
+    ));
+    assert!(uci.contains(
+        "config host\n\
+         \toption name 'camera'\n\
+         \toption duid '00010001aabbccdd001122334455'\n\
+         \toption hostid '50'\n"
+    ));
+}
+
+#[test]
+fn test_export_kea_dhcp4() {
+    let (json, stats) = export(TEST_EXPORT, ExportFormat::KeaDhcp4);
+
+    assert_eq!(stats.target_subnets_found, 1);
+    assert_eq!(stats.reservations_to_create, 2);
+    assert_eq!(stats.reservations_skipped, 0);
+    assert!(json.starts_with("// Kea Dhcp4 configuration exported by isc2kea"));
+    assert!(json.contains(
+        r#"    "Dhcp4": {
+        "subnet4": [
+            {
+                "id": 1,
+                "subnet": "10.30.0.0/24",
+                "pools": [
+                    {
+                        "pool": "10.30.0.100 - 10.30.0.200"
+                    }
+                ],
+                "option-data": [
+                    {
+                        "name": "domain-name-servers",
+                        "data": "10.30.0.1,1.1.1.1"
+                    },"#
+    ));
+    assert!(json.contains(
+        r#"                    {
+                        "hw-address": "04:d9:f5:cb:9b:54",
+                        "ip-address": "10.30.0.50",
+                        "hostname": "camera",
+                        "user-context": {
+                            "description": "Front \"door\" camera"
+                        }
+                    },"#
+    ));
+    assert!(json.contains(r#""isc-interface": "opt3 (IoT VLAN)""#));
+}
+
+#[test]
+fn test_export_kea_dhcp4_custom_option_defs() {
+    let (json, stats) = export(TEST_EXPORT_CUSTOM_OPTIONS, ExportFormat::KeaDhcp4);
+
+    assert_eq!(stats.custom_options_exported, 4);
+    assert_eq!(stats.option_defs_exported, 3);
+    assert!(json.contains(
+        r#"    "Dhcp4": {
+        "option-def": [
+            {
+                "name": "isc-option-150",
+                "code": 150,
+                "type": "ipv4-address",
+                "array": true,
+                "space": "dhcp4"
+            },
+            {
+                "name": "isc-option-224",
+                "code": 224,
+                "type": "binary",
+                "space": "dhcp4"
+            },
+            {
+                "name": "isc-option-252",
+                "code": 252,
+                "type": "string",
+                "space": "dhcp4"
+            }
+        ],"#
+    ));
+    assert!(json.contains(
+        r#"                "option-data": [
+                    {
+                        "name": "isc-option-252",
+                        "code": 252,
+                        "data": "http://wpad.lan/wpad.dat"
+                    },
+                    {
+                        "code": 66,
+                        "data": "tftp.lan"
+                    },
+                    {
+                        "name": "isc-option-150",
+                        "code": 150,
+                        "data": "192.168.1.5, 192.168.1.6"
+                    },
+                    {
+                        "name": "isc-option-224",
+                        "code": 224,
+                        "csv-format": false,
+                        "data": "01020a"
+                    }
+                ],"#
+    ));
+
+    let (json, _) = export(TEST_EXPORT_CUSTOM_OPTIONS, ExportFormat::KeaDhcp6);
+    assert!(!json.contains("option-def"));
+}
+
+#[test]
+fn test_export_classless_routes() {
+    let (json, _) = export(TEST_CLASSLESS_ROUTES, ExportFormat::KeaDhcp4);
+    assert!(json.contains(
+        r#"                    {
+                        "code": 121,
+                        "data": "10.0.0.0/8 - 192.168.1.2, 172.16.5.0/24 - 192.168.1.3"
+                    },
+                    {
+                        "name": "isc-option-249",
+                        "code": 249,
+                        "csv-format": false,
+                        "data": "080ac0a8010218ac1005c0a80103"
+                    }"#
+    ));
+    assert!(json.contains(
+        r#"                "name": "isc-option-249",
+                "code": 249,
+                "type": "binary","#
+    ));
+
+    let (uci, _) = export(TEST_CLASSLESS_ROUTES, ExportFormat::Uci);
+    assert!(
+        uci.contains("\tlist dhcp_option '121,10.0.0.0/8,192.168.1.2,172.16.5.0/24,192.168.1.3'\n")
+    );
+    assert!(uci.contains("\tlist dhcp_option '249,10.0.0.0/8,"));
+}
+
+#[test]
+fn test_export_kea_dhcp4_netboot() {
+    let (json, _) = export(TEST_CREATE_SUBNETS_KEA_NETBOOT, ExportFormat::KeaDhcp4);
+
+    assert!(json.contains(
+        r#""subnet": "10.22.1.0/24",
+                "next-server": "10.22.1.5","#
+    ));
+    assert!(json.contains(
+        r#"                    {
+                        "name": "boot-file-name",
+                        "data": "pxelinux.0"
+                    }"#
+    ));
+    assert!(json.contains(r#""data": "tftp.lan""#));
+    assert_eq!(json.matches("next-server").count(), 1);
+}
+
+#[test]
+fn test_export_kea_dhcp6_subnet_from_interface() {
+    let (json, stats) = export(TEST_EXPORT, ExportFormat::KeaDhcp6);
+
+    assert_eq!(stats.target_subnets_found, 1);
+    assert_eq!(stats.reservations_v6_to_create, 1);
+    assert_eq!(stats.reservations_v6_skipped, 1, "non-hex DUID is skipped");
+    assert!(json.contains(r#""subnet": "2001:db8:30::/64""#));
+    assert!(!json.contains("\"pools\""));
+    assert!(json.contains(
+        r#""duid": "00:01:00:01:AA:BB:CC:DD:00:11:22:33:44:55",
+                        "ip-addresses": [
+                            "2001:db8:30::50"
+                        ],"#
+    ));
+}
+
+#[test]
+fn test_export_split_output_per_subnet() {
+    let out = Path::new("/etc/kea/kea-dhcp4.conf");
+    let (files, stats) = export_config_split(
+        Cursor::new(TEST_SORT_RESERVATIONS_KEA),
+        out,
+        ExportFormat::KeaDhcp4,
+        &MigrationOptions::default(),
+    )
+    .expect("split export should succeed");
+
+    assert_eq!(files.len(), stats.target_subnets_found + 1);
+    assert_eq!(files[0].path, out);
+    let includes: Vec<&str> = files[0]
+        .contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with("<?include"))
+        .collect();
+    assert_eq!(includes.len(), files.len() - 1);
+    for (idx, file) in files[1..].iter().enumerate() {
+        let name = file.path.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with(&format!("kea-dhcp4-subnet{}-", idx + 1)));
+        assert!(includes[idx].starts_with(&format!("<?include \"{}\"?>", file.path.display())));
+        assert!(file.contents.starts_with("{\n    \"id\": "));
+        assert!(!file.contents.contains("Dhcp4"));
+    }
+    let reservations: usize = files[1..]
+        .iter()
+        .map(|f| f.contents.matches("\"hw-address\"").count())
+        .sum();
+    assert_eq!(reservations, stats.reservations_to_create);
+}
+
+#[test]
+fn test_export_split_requires_kea_format() {
+    let err = export_config_split(
+        Cursor::new(TEST_EXPORT),
+        Path::new("dhcp.rsc"),
+        ExportFormat::Routeros,
+        &MigrationOptions::default(),
+    )
+    .expect_err("split is Kea-only");
+    assert!(err.to_string().contains("only supported for the kea-dhcp4"));
+}
+
+#[test]
+fn test_export_gateway_none() {
+    let (uci, _) = export(TEST_GATEWAY_NONE, ExportFormat::Uci);
+    assert!(uci.contains("\tlist dhcp_option '3'\n"));
+    assert_eq!(uci.matches("dhcp_option '3").count(), 1);
+
+    let (kea, _) = export(TEST_GATEWAY_NONE, ExportFormat::KeaDhcp4);
+    assert!(!kea.contains("\"routers\""));
+    assert!(kea.contains("\"9.9.9.9\""));
+}
@@ -1,5 +1,14 @@
 use super::common::*;
-use isc2kea::{convert_config, scan_config, MigrationOptions};
+use isc2kea::{
+    convert_config, extract_isc_mappings, extract_isc_mappings_v6, generate_fixture,
+    iter_isc_mappings, iter_isc_options_v4, iter_isc_ranges, merge_config, parse_isc_leases,
+    plan_config, scan_config, validate_input, validate_output, verify_findings, AttributeOrder,
+    Backend, Clock, ConfigLimits, DuplicateUuid, EmitMode, FieldOverrides, FindingCategory,
+    FindingChange, FirewallRulesStatus, FixtureSpec, HostnamePolicy, KeaInterfacePolicy,
+    LeaseStatus, MigrationError, MigrationOptions, MigrationPlan, OrphanedIfacePolicy,
+    OrphanedMapping, OutputStyle, ParsedConfig, ReservationOrder, Severity, Source,
+    UnmatchedMapping, CREATED_ATTR, ORIGIN_ATTR,
+};
 use std::fs;
 use std::io::Cursor;
 use xmltree::Element;
@@ -197,6 +206,7 @@ fn test_skip_duplicate_ip() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -246,6 +256,10 @@ fn test_skip_duplicate_v6_duid() {
     assert_eq!(stats.isc_mappings_v6_found, 1);
     assert_eq!(stats.reservations_v6_to_create, 0);
     assert_eq!(stats.reservations_v6_skipped, 1);
+    assert!(matches!(
+        stats.skips.as_slice(),
+        [MigrationError::DuplicateDuid { .. }]
+    ));
 }
 #[test]
 fn test_error_on_no_matching_subnet() {
@@ -259,6 +273,7 @@ fn test_error_on_no_matching_subnet() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>172.16.0.10</ipaddr>
@@ -286,10 +301,85 @@ fn test_error_on_no_matching_subnet() {
         result.is_err(),
         "Should fail when IP doesn't match any subnet"
     );
-    assert!(result
-        .unwrap_err()
-        .to_string()
-        .contains("does not match any configured subnet"));
+    let err = result.unwrap_err();
+    assert!(err.to_string().contains("fall outside every Kea subnet"));
+    assert_eq!(
+        err.downcast_ref::<MigrationError>(),
+        Some(&MigrationError::MappingsOutsideSubnets {
+            mappings: vec![UnmatchedMapping {
+                iface: "lan".to_string(),
+                ip: "172.16.0.10".to_string(),
+                iface_cidr: Some("172.16.0.0/24".to_string()),
+                candidates: vec!["192.168.1.0/24".to_string()],
+            }]
+        })
+    );
+}
+
+#[test]
+fn test_error_lists_every_mapping_outside_subnets() {
+    let xml = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddr>192.168.1.1</ipaddr>
+            <subnet>24</subnet>
+        </lan>
+        <opt1>
+            <descr>IoT</descr>
+            <ipaddr>10.22.1.1</ipaddr>
+            <subnet>24</subnet>
+        </opt1>
+    </interfaces>
+    <dhcpd>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:01</mac>
+                <ipaddr>192.168.1.10</ipaddr>
+            </staticmap>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <staticmap>
+                <mac>00:11:22:33:44:02</mac>
+                <ipaddr>10.22.1.10</ipaddr>
+            </staticmap>
+            <staticmap>
+                <mac>00:11:22:33:44:03</mac>
+                <ipaddr>10.22.1.11</ipaddr>
+            </staticmap>
+        </opt1>
+    </dhcpd>
+    <Kea>
+        <dhcp4>
+            <subnets>
+                <subnet4 uuid="lan-subnet">
+                    <subnet>192.168.1.0/24</subnet>
+                </subnet4>
+                <subnet4 uuid="old-iot-subnet">
+                    <subnet>10.22.0.0/24</subnet>
+                </subnet4>
+            </subnets>
+            <reservations></reservations>
+        </dhcp4>
+    </Kea>
+</opnsense>
+"#;
+
+    let err = convert_config(
+        Cursor::new(xml),
+        &mut Vec::new(),
+        &MigrationOptions::default(),
+    )
+    .expect_err("convert should fail for mappings outside every subnet");
+    let message = err.to_string();
+    assert!(message.starts_with("2 static mapping(s) fall outside every Kea subnet"));
+    assert!(message.contains(
+        "opt1 (IoT): 10.22.1.10 (interface network 10.22.1.0/24; nearest subnets 10.22.0.0/24, 192.168.1.0/24)"
+    ));
+    assert!(message.contains("opt1 (IoT): 10.22.1.11 (interface network 10.22.1.0/24;"));
+    assert!(message.contains("--create-subnets"));
 }
 
 #[test]
@@ -308,6 +398,7 @@ fn test_error_on_interface_mismatch() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -357,6 +448,7 @@ fn test_dnsmasq_error_on_interface_mismatch() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -394,6 +486,7 @@ fn test_error_when_kea_not_configured() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -429,6 +522,7 @@ fn test_error_when_kea_has_no_subnets() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -470,6 +564,7 @@ fn test_handles_isc_duplicates() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -506,6 +601,494 @@ fn test_handles_isc_duplicates() {
     assert_eq!(stats.reservations_skipped, 1, "Should skip 1 duplicate");
 }
 
+#[test]
+fn test_merges_duplicated_dhcpd_sections() {
+    let root = Element::parse(Cursor::new(TEST_DUPLICATED_DHCPD_SECTIONS)).expect("valid XML");
+
+    let mappings = extract_isc_mappings(&root).expect("extract should succeed");
+    let found: Vec<(&str, &str)> = mappings
+        .iter()
+        .map(|m| (m.ipaddr.as_str(), m.hostname.as_deref().unwrap_or("")))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("192.168.1.10", "first"),
+            ("192.168.1.11", "second"),
+            ("192.168.1.12", "third"),
+        ],
+        "copies are dropped and the first conflicting entry wins"
+    );
+
+    let stats = scan_config(
+        Cursor::new(TEST_DUPLICATED_DHCPD_SECTIONS),
+        &MigrationOptions::default(),
+    )
+    .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 3);
+    assert_eq!(stats.reservations_to_create, 3);
+    assert_eq!(
+        stats.isc_ranges_found, 1,
+        "lan keeps the range from the first section"
+    );
+}
+
+#[test]
+fn test_iterator_extraction_matches_collected() {
+    let root = Element::parse(Cursor::new(TEST_DUPLICATED_DHCPD_SECTIONS)).expect("valid XML");
+
+    let streamed: Vec<_> = iter_isc_mappings(&root).collect();
+    assert_eq!(
+        streamed,
+        extract_isc_mappings(&root).expect("extract should succeed")
+    );
+
+    let first = iter_isc_mappings(&root)
+        .find(|m| m.hostname.as_deref() == Some("second"))
+        .expect("mapping found");
+    assert_eq!(first.ipaddr, "192.168.1.11");
+
+    let ranges: Vec<_> = iter_isc_ranges(&root).map(|r| (r.from, r.to)).collect();
+    assert_eq!(
+        ranges,
+        vec![("192.168.1.100".to_string(), "192.168.1.200".to_string())]
+    );
+    assert_eq!(iter_isc_options_v4(&root).count(), 0);
+}
+
+#[test]
+fn test_emit_changes_only() {
+    let options = MigrationOptions {
+        emit: EmitMode::ChangesOnly,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_XML), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(
+        stats.changed_sections,
+        vec!["opnsense/Kea/dhcp4".to_string()]
+    );
+
+    let output = String::from_utf8(output).expect("utf8");
+    let fragment = output
+        .strip_prefix("<!-- opnsense/Kea/dhcp4 -->\n")
+        .expect("fragment starts with its path");
+    let dhcp4 = Element::parse(Cursor::new(fragment)).expect("fragment is standalone XML");
+    assert_eq!(dhcp4.name, "dhcp4");
+    assert!(dhcp4.get_child("reservations").is_some());
+    assert!(
+        !output.contains("<dhcpd>"),
+        "unchanged sections are left out"
+    );
+}
+
+#[test]
+fn test_output_style() {
+    let input = TEST_XML.replace(
+        "<dhcp4>",
+        r#"<dhcp4 version="1.0.4" persisted_at="1700000000">"#,
+    );
+
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(&input),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    let output = String::from_utf8(output).expect("utf8");
+    assert!(output.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opnsense>\n  <"));
+    assert!(output.contains(r#"<dhcp4 version="1.0.4" persisted_at="1700000000">"#));
+
+    let options = MigrationOptions {
+        output_style: OutputStyle {
+            indent: 4,
+            declaration: false,
+            attribute_order: AttributeOrder::Sorted,
+        },
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(&input), &mut output, &options).expect("convert should succeed");
+    let output = String::from_utf8(output).expect("utf8");
+    assert!(output.starts_with("<opnsense>\n    <"));
+    assert!(output.contains(r#"<dhcp4 persisted_at="1700000000" version="1.0.4">"#));
+    assert!(output.contains("\n            <reservations>"));
+}
+
+#[test]
+fn test_ha_sync_requires_allow_ha() {
+    let input = TEST_XML.replace(
+        "</opnsense>",
+        r#"<hasync>
+        <synchronizetoip>10.0.0.2</synchronizetoip>
+        <syncitems>aliases,dhcpd,rules</syncitems>
+    </hasync>
+</opnsense>"#,
+    );
+
+    let stats = scan_config(Cursor::new(&input), &MigrationOptions::default())
+        .expect("scan should succeed");
+    let ha = stats.ha_sync.expect("sync detected");
+    assert_eq!(ha.peer, "10.0.0.2");
+    assert_eq!(ha.sections, vec!["dhcpd".to_string()]);
+
+    let err = convert_config(
+        Cursor::new(&input),
+        &mut Vec::new(),
+        &MigrationOptions::default(),
+    )
+    .expect_err("sync primary needs allow_ha");
+    assert_eq!(
+        err.downcast_ref::<MigrationError>(),
+        Some(&MigrationError::HaSyncEnabled {
+            peer: "10.0.0.2".to_string(),
+            sections: "dhcpd".to_string(),
+        })
+    );
+
+    let options = MigrationOptions {
+        allow_ha: true,
+        ..Default::default()
+    };
+    let stats = convert_config(Cursor::new(&input), &mut Vec::new(), &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    assert!(stats.ha_sync.is_some());
+
+    let no_dhcp = input.replace("aliases,dhcpd,rules", "aliases,rules");
+    let stats = convert_config(
+        Cursor::new(&no_dhcp),
+        &mut Vec::new(),
+        &MigrationOptions::default(),
+    )
+    .expect("sync without DHCP items is fine");
+    assert!(stats.ha_sync.is_none());
+}
+
+#[test]
+fn test_migrate_placeholder_mappings() {
+    let input = TEST_XML.replace(
+        "</staticmap>",
+        r#"</staticmap>
+            <staticmap>
+                <mac>AA-BB-CC-DD-EE-FF</mac>
+                <ipaddr></ipaddr>
+                <hostname>spare-laptop</hostname>
+                <descr>reserved for future</descr>
+            </staticmap>"#,
+    );
+
+    let stats = scan_config(Cursor::new(&input), &MigrationOptions::default())
+        .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 1);
+    let placeholders: Vec<(&str, &str)> = stats
+        .isc_placeholders
+        .iter()
+        .map(|p| (p.iface.as_str(), p.mac.as_str()))
+        .collect();
+    assert_eq!(placeholders, vec![("lan", "AA-BB-CC-DD-EE-FF")]);
+    assert_eq!(stats.placeholders_migrated, 0);
+
+    let options = MigrationOptions {
+        migrate_placeholders: true,
+        ..Default::default()
+    };
+    let stats = scan_config(Cursor::new(&input), &options).expect("scan should succeed");
+    assert_eq!(stats.placeholders_migrated, 1);
+
+    let mut output = Vec::new();
+    let stats =
+        convert_config(Cursor::new(&input), &mut output, &options).expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.placeholders_migrated, 1);
+    let root = Element::parse(Cursor::new(output)).unwrap();
+    let dhcp4 = root.get_child("Kea").unwrap().get_child("dhcp4").unwrap();
+    let reservations = reservations_as_fields(dhcp4);
+    let placeholder = &reservations[1];
+    let field = |name: &str| {
+        placeholder
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    };
+    assert_eq!(field("subnet"), Some("test-subnet-uuid-1234"));
+    assert_eq!(field("ip_address"), Some(""));
+    assert_eq!(field("hw_address"), Some("aa:bb:cc:dd:ee:ff"));
+    assert_eq!(field("hostname"), Some("spare-laptop"));
+
+    let options = MigrationOptions {
+        migrate_placeholders: true,
+        backend: Backend::Dnsmasq,
+        ..Default::default()
+    };
+    let err = scan_config(Cursor::new(&input), &options).unwrap_err();
+    assert!(err.to_string().contains("--migrate-placeholders"));
+}
+
+#[test]
+fn test_v6_duplicates_scoped_to_subnet() {
+    let input = r#"<?xml version="1.0"?>
+<opnsense>
+    <interfaces>
+        <lan>
+            <ipaddrv6>2001:db8:42::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </lan>
+        <opt1>
+            <ipaddrv6>2001:db8:43::1</ipaddrv6>
+            <subnetv6>64</subnetv6>
+        </opt1>
+    </interfaces>
+    <dhcpdv6>
+        <lan>
+            <enable>1</enable>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
+                <ipaddrv6>2001:db8:42::11</ipaddrv6>
+            </staticmap>
+        </lan>
+        <opt1>
+            <enable>1</enable>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
+                <ipaddrv6>2001:db8:43::10</ipaddrv6>
+            </staticmap>
+        </opt1>
+    </dhcpdv6>
+    <Kea>
+        <dhcp6>
+            <subnets>
+                <subnet6 uuid="v6-lan">
+                    <subnet>2001:db8:42::/64</subnet>
+                </subnet6>
+                <subnet6 uuid="v6-opt1">
+                    <subnet>2001:db8:43::/64</subnet>
+                </subnet6>
+            </subnets>
+            <reservations>
+                <reservation uuid="existing-v6">
+                    <subnet>v6-lan</subnet>
+                    <ip_address>2001:db8:42::20</ip_address>
+                    <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:55</duid>
+                </reservation>
+            </reservations>
+        </dhcp6>
+    </Kea>
+</opnsense>
+"#;
+
+    // The DUID is taken on lan only, so opt1 gets its reservation
+    let stats =
+        scan_config(Cursor::new(input), &MigrationOptions::default()).expect("scan should succeed");
+    assert_eq!(stats.reservations_v6_to_create, 1);
+    assert_eq!(stats.reservations_v6_skipped, 1);
+
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(input),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).unwrap();
+    let dhcp6 = root.get_child("Kea").unwrap().get_child("dhcp6").unwrap();
+    let created: Vec<String> = reservations_as_fields(dhcp6)
+        .into_iter()
+        .skip(1)
+        .map(|fields| {
+            fields
+                .into_iter()
+                .find(|(name, _)| name == "subnet")
+                .map(|(_, value)| value)
+                .unwrap_or_default()
+        })
+        .collect();
+    assert_eq!(created, vec!["v6-opt1"]);
+
+    let options = MigrationOptions {
+        global_duids: true,
+        ..Default::default()
+    };
+    let stats = scan_config(Cursor::new(input), &options).expect("scan should succeed");
+    assert_eq!(stats.reservations_v6_to_create, 0);
+    assert_eq!(stats.reservations_v6_skipped, 2);
+}
+
+#[test]
+fn test_zone_indexed_and_mapped_v6_addresses() {
+    let input = TEST_XML_V6.replace(
+        "</staticmap>",
+        r#"</staticmap>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:66</duid>
+                <ipaddrv6>2001:db8:42::20%igb0</ipaddrv6>
+            </staticmap>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:77</duid>
+                <ipaddrv6>fe80::1%igb0</ipaddrv6>
+            </staticmap>
+            <staticmap>
+                <duid>00:01:00:01:aa:bb:cc:dd:00:11:22:33:44:88</duid>
+                <ipaddrv6>::ffff:192.0.2.1</ipaddrv6>
+            </staticmap>"#,
+    );
+
+    let stats = scan_config(Cursor::new(&input), &MigrationOptions::default())
+        .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_v6_found, 4);
+    assert_eq!(stats.reservations_v6_to_create, 2);
+    assert_eq!(stats.reservations_v6_skipped, 2);
+    let rejected: Vec<(&str, &str)> = stats
+        .skips
+        .iter()
+        .filter_map(|skip| match skip {
+            MigrationError::UnusableIpv6Address { iface, ip, .. } => {
+                Some((iface.as_str(), ip.as_str()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        rejected,
+        vec![("opt2", "fe80::1%igb0"), ("opt2", "::ffff:192.0.2.1")]
+    );
+    assert!(stats.skips[1].to_string().contains("192.0.2.1 as a DHCPv4"));
+
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(&input),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).unwrap();
+    let dhcp6 = root.get_child("Kea").unwrap().get_child("dhcp6").unwrap();
+    let ips: Vec<String> = reservations_as_fields(dhcp6)
+        .into_iter()
+        .filter_map(|fields| {
+            fields
+                .into_iter()
+                .find(|(name, _)| name == "ip_address")
+                .map(|(_, value)| value)
+        })
+        .collect();
+    assert_eq!(ips, vec!["2001:db8:42::10", "2001:db8:42::20"]);
+}
+
+#[test]
+fn test_scan_lists_manual_actions() {
+    let input = TEST_XML
+        .replace(
+            "<enable>1</enable>",
+            r#"<enable>1</enable>
+            <failover_peerip>192.168.1.2</failover_peerip>
+            <numberoptions>
+                <item>
+                    <number>252</number>
+                    <type>text</type>
+                    <value>"http://wpad.lan/wpad.dat"</value>
+                </item>
+            </numberoptions>"#,
+        )
+        .replace(
+            "</dhcpd>",
+            r#"</dhcpd>
+    <dhcpdv6>
+        <lan>
+            <prefixrange>
+                <from>2001:db8:0:100::</from>
+                <to>2001:db8:0:f00::</to>
+                <prefixlength>56</prefixlength>
+            </prefixrange>
+        </lan>
+    </dhcpdv6>"#,
+        );
+
+    let stats = scan_config(Cursor::new(&input), &MigrationOptions::default())
+        .expect("scan should succeed");
+    assert_eq!(
+        stats.manual_actions,
+        vec![
+            "Set up Kea HA for lan: ISC failover with peer 192.168.1.2 is not migrated.",
+            "Create a Kea prefix delegation pool for 2001:db8:0:100::-2001:db8:0:f00:: (/56) on lan.",
+            "Re-create custom option 252 (\"http://wpad.lan/wpad.dat\") on lan in Kea.",
+        ]
+    );
+
+    let stats = scan_config(Cursor::new(TEST_XML), &MigrationOptions::default())
+        .expect("scan should succeed");
+    assert!(stats.manual_actions.is_empty());
+}
+
+#[test]
+fn test_lease_activity_and_skip_stale() {
+    let recent = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("clock after epoch")
+        .as_secs()
+        - 3 * 86_400;
+    let leases = TEST_LEASE_ACTIVITY_LEASES.replace("{recent}", &recent.to_string());
+    let options = MigrationOptions {
+        leases: parse_isc_leases(&leases),
+        skip_stale_days: Some(180),
+        ..Default::default()
+    };
+
+    let stats =
+        scan_config(Cursor::new(TEST_LEASE_ACTIVITY), &options).expect("scan should succeed");
+    let report: Vec<(&str, LeaseStatus)> = stats
+        .lease_activity
+        .iter()
+        .map(|a| (a.ipaddr.as_str(), a.status))
+        .collect();
+    assert_eq!(
+        report,
+        vec![
+            ("192.168.1.12", LeaseStatus::NeverSeen),
+            ("192.168.1.11", LeaseStatus::Stale),
+            ("192.168.1.13", LeaseStatus::Recent),
+            ("192.168.1.10", LeaseStatus::Online),
+        ],
+        "leases match by MAC or IP, least active first"
+    );
+    assert_eq!(stats.isc_mappings_stale, 1);
+    assert_eq!(
+        stats.reservations_to_create, 3,
+        "only the stale mapping is skipped; never-seen ones are kept"
+    );
+}
+
+#[test]
+fn test_legacy_paths_fallback() {
+    let stats = scan_config(
+        Cursor::new(TEST_LEGACY_INSTALLEDPACKAGES),
+        &MigrationOptions::default(),
+    )
+    .expect("scan should succeed");
+    assert_eq!(
+        stats.isc_mappings_found, 0,
+        "legacy locations are only read with --legacy-paths"
+    );
+
+    let options = MigrationOptions {
+        legacy_paths: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_LEGACY_INSTALLEDPACKAGES),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.isc_mappings_found, 1);
+    assert_eq!(stats.reservations_to_create, 1);
+    let output = String::from_utf8(output).expect("utf8");
+    assert!(output.contains("<hw_address>00:11:22:33:44:55</hw_address>"));
+}
+
 #[test]
 fn test_case_insensitive_kea_tags() {
     let xml_lowercase_kea = r#"<?xml version="1.0"?>
@@ -518,6 +1101,7 @@ fn test_case_insensitive_kea_tags() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -563,6 +1147,7 @@ fn test_case_insensitive_isc_tags() {
     </interfaces>
     <DHCPD>
         <lan>
+            <ENABLE>1</ENABLE>
             <STATICMAP>
                 <MAC>00:11:22:33:44:55</MAC>
                 <IPADDR>192.168.1.10</IPADDR>
@@ -608,6 +1193,7 @@ fn test_fallback_kea_schema() {
     </interfaces>
     <dhcpd>
         <lan>
+            <enable>1</enable>
             <staticmap>
                 <mac>00:11:22:33:44:55</mac>
                 <ipaddr>192.168.1.10</ipaddr>
@@ -861,6 +1447,1368 @@ fn test_enable_backend_kea_stats() {
     assert!(stats.isc_disabled_v6.is_empty());
     assert!(stats.backend_enabled_v4);
     assert!(!stats.backend_enabled_v6);
+    assert_eq!(stats.fwrules_v4, Some(FirewallRulesStatus::Missing));
+    assert_eq!(stats.fwrules_v6, None);
+}
+
+#[test]
+fn test_enable_backend_kea_turns_on_fwrules() {
+    let xml = TEST_ENABLE_BACKEND_KEA.replace(
+        "<enabled>0</enabled>",
+        "<enabled>0</enabled>\n                <fwrules>0</fwrules>",
+    );
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_subnets: true,
+        enable_backend: true,
+        ..Default::default()
+    };
+
+    let stats =
+        convert_config(Cursor::new(xml), &mut output, &options).expect("convert should succeed");
+    assert_eq!(stats.fwrules_v4, Some(FirewallRulesStatus::Enabled));
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let fwrules = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .and_then(|dhcp4| dhcp4.get_child("general"))
+        .and_then(|general| general.get_child("fwrules"))
+        .and_then(|e| e.get_text())
+        .expect("Should have fwrules");
+    assert_eq!(fwrules, "1");
 }
 
 // ---------------------------------------------------------------------------
+
+fn converted_reservation_ips(xml: &str, order: ReservationOrder) -> Vec<String> {
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        reservation_order: order,
+        ..Default::default()
+    };
+    convert_config(Cursor::new(xml), &mut output, &options).expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dhcp4 = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .expect("Should have dhcp4 node");
+    reservations_as_fields(dhcp4)
+        .into_iter()
+        .filter_map(|fields| {
+            fields
+                .into_iter()
+                .find(|(name, _)| name == "ip_address")
+                .map(|(_, value)| value)
+        })
+        .collect()
+}
+
+#[test]
+fn test_sort_reservations_discovery_order() {
+    let ips = converted_reservation_ips(TEST_SORT_RESERVATIONS_KEA, ReservationOrder::Discovery);
+    assert_eq!(
+        ips,
+        vec!["192.168.1.200", "10.0.0.20", "192.168.1.100", "192.168.1.9"]
+    );
+}
+
+#[test]
+fn test_sort_reservations_by_ip() {
+    let ips = converted_reservation_ips(TEST_SORT_RESERVATIONS_KEA, ReservationOrder::Ip);
+    assert_eq!(
+        ips,
+        vec!["192.168.1.200", "10.0.0.20", "192.168.1.9", "192.168.1.100"],
+        "existing reservations stay first, new ones sort numerically"
+    );
+}
+
+#[test]
+fn test_sort_reservations_by_iface_ip() {
+    let ips = converted_reservation_ips(TEST_SORT_RESERVATIONS_KEA, ReservationOrder::IfaceIp);
+    assert_eq!(
+        ips,
+        vec!["192.168.1.200", "192.168.1.9", "192.168.1.100", "10.0.0.20"]
+    );
+}
+
+#[test]
+fn test_enable_backend_kea_merges_interfaces_without_create_subnets() {
+    let input = Cursor::new(TEST_ENABLE_BACKEND_KEA_EXISTING_SUBNETS);
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        enable_backend: true,
+        ..Default::default()
+    };
+
+    let stats = convert_config(input, &mut output, &options).expect("convert should succeed");
+    assert_eq!(stats.interfaces_configured, vec!["lan", "opt1"]);
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let interfaces = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .and_then(|dhcp4| dhcp4.get_child("general"))
+        .and_then(|general| general.get_child("interfaces"))
+        .and_then(|e| e.get_text())
+        .expect("Should have general interfaces");
+    assert_eq!(interfaces, "lan,opt1,opt9");
+}
+
+#[test]
+fn test_ignores_mappings_on_disabled_isc_interfaces() {
+    let options = MigrationOptions::default();
+    let stats = scan_config(Cursor::new(TEST_DISABLED_ISC_INTERFACE), &options)
+        .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 1);
+    assert_eq!(stats.isc_mappings_disabled_iface, 2);
+    assert_eq!(stats.reservations_to_create, 1);
+
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_DISABLED_ISC_INTERFACE),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.isc_mappings_disabled_iface, 2);
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dhcp4 = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .expect("Should have dhcp4 node");
+    let reservations = reservations_as_fields(dhcp4);
+    assert_eq!(reservations.len(), 1);
+    assert!(reservations[0].contains(&("ip_address".to_string(), "192.168.1.10".to_string())));
+}
+
+#[test]
+fn test_include_disabled_isc_interfaces() {
+    let options = MigrationOptions {
+        include_disabled_interfaces: true,
+        ..Default::default()
+    };
+    let stats = scan_config(Cursor::new(TEST_DISABLED_ISC_INTERFACE), &options)
+        .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 3);
+    assert_eq!(stats.isc_mappings_disabled_iface, 0);
+    assert_eq!(stats.reservations_to_create, 3);
+}
+
+#[test]
+fn test_orphaned_isc_interface_policies() {
+    let err = scan_config(
+        Cursor::new(TEST_ORPHANED_ISC_INTERFACE),
+        &MigrationOptions::default(),
+    )
+    .expect_err("orphaned sections should fail by default");
+    match err.downcast_ref::<MigrationError>() {
+        Some(MigrationError::OrphanedInterfaceMappings { mappings }) => {
+            assert_eq!(mappings.len(), 2);
+            assert_eq!(
+                mappings[1],
+                OrphanedMapping {
+                    section: "dhcpd".to_string(),
+                    iface: "opt7".to_string(),
+                    ip: "10.77.0.10".to_string(),
+                    client: "00:11:22:33:44:77".to_string(),
+                }
+            );
+            assert!(err
+                .to_string()
+                .contains("  dhcpd/opt7: 10.77.0.10 (00:11:22:33:44:77)"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    let options = MigrationOptions {
+        orphaned_ifaces: OrphanedIfacePolicy::Skip,
+        ..Default::default()
+    };
+    let stats = scan_config(Cursor::new(TEST_ORPHANED_ISC_INTERFACE), &options)
+        .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 1);
+    assert_eq!(stats.isc_mappings_orphaned_iface, 2);
+    assert_eq!(stats.reservations_to_create, 1);
+
+    let options = MigrationOptions {
+        orphaned_ifaces: OrphanedIfacePolicy::SubnetMatch,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_ORPHANED_ISC_INTERFACE),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.isc_mappings_orphaned_iface, 2);
+    assert_eq!(stats.reservations_to_create, 2);
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dhcp4 = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .expect("Should have dhcp4 node");
+    let ips: Vec<String> = reservations_as_fields(dhcp4)
+        .iter()
+        .filter_map(|fields| {
+            fields
+                .iter()
+                .find(|(name, _)| name == "ip_address")
+                .map(|(_, ip)| ip.clone())
+        })
+        .collect();
+    assert_eq!(ips, ["192.168.1.10", "192.168.1.20"]);
+}
+
+#[test]
+fn test_merge_config_adds_only_missing_reservations() {
+    let options = MigrationOptions::default();
+    let mut output = Vec::new();
+    let stats = merge_config(
+        Cursor::new(TEST_MERGE_SOURCE),
+        Cursor::new(TEST_MERGE_TARGET),
+        &mut output,
+        &options,
+    )
+    .expect("merge should succeed");
+
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.reservations_skipped, 1);
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let dhcp4 = find_descendant_ci(&root, "dhcp4").expect("Kea dhcp4");
+    let ips: Vec<_> = reservations_as_fields(dhcp4)
+        .into_iter()
+        .filter_map(|fields| {
+            fields
+                .into_iter()
+                .find(|(name, _)| name == "ip_address")
+                .map(|(_, value)| value)
+        })
+        .collect();
+    assert_eq!(ips, vec!["192.168.1.10", "192.168.1.20"]);
+
+    let subnets = find_descendant_ci(dhcp4, "subnets").expect("subnets");
+    assert_eq!(
+        subnets
+            .children
+            .iter()
+            .filter(|n| n.as_element().is_some())
+            .count(),
+        2
+    );
+    assert!(find_descendant_ci(&root, "dhcpd").is_some());
+}
+
+#[test]
+fn test_merge_config_keeps_target_isc_sections() {
+    let lan = |output: Vec<u8>| {
+        let root = Element::parse(Cursor::new(output)).expect("valid XML");
+        let dhcpd = find_descendant_ci(&root, "dhcpd").expect("dhcpd");
+        find_descendant_ci(dhcpd, "lan").expect("lan").clone()
+    };
+
+    // An earlier convert with --enable-backend left ISC disabled
+    let target = TEST_MERGE_TARGET.replace("<enable>1</enable>", "");
+    let mut output = Vec::new();
+    let stats = merge_config(
+        Cursor::new(TEST_MERGE_SOURCE),
+        Cursor::new(target),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("merge should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    let lan_v4 = lan(output);
+    assert!(find_descendant_ci(&lan_v4, "enable").is_none());
+    assert!(find_descendant_ci(&lan_v4, "staticmap").is_none());
+    assert_eq!(
+        find_descendant_ci(&lan_v4, "domain").and_then(|d| d.get_text()),
+        Some("target.lan".into())
+    );
+
+    // Enabling the backend disables the migrated interfaces in the target's
+    // sections
+    let options = MigrationOptions {
+        enable_backend: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    merge_config(
+        Cursor::new(TEST_MERGE_SOURCE),
+        Cursor::new(TEST_MERGE_TARGET),
+        &mut output,
+        &options,
+    )
+    .expect("merge should succeed");
+    let lan_v4 = lan(output);
+    assert!(find_descendant_ci(&lan_v4, "enable").is_none());
+    assert!(find_descendant_ci(&lan_v4, "domain").is_some());
+}
+
+#[test]
+fn test_convert_warns_when_subnet_exceeds_reservation_limit() {
+    let options = MigrationOptions {
+        limits: ConfigLimits {
+            max_reservations_per_subnet: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_SORT_RESERVATIONS_KEA),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+
+    assert_eq!(stats.warnings.len(), 1);
+    assert!(stats.warnings[0].starts_with("Kea subnet 192.168.1.0/24 has 3 reservations (limit 1)"));
+    assert!(stats.warnings[0].contains("Kea SQL hosts backend"));
+}
+
+#[test]
+fn test_convert_warns_on_long_descriptions() {
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_XML),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    assert!(stats.warnings.is_empty());
+
+    let options = MigrationOptions {
+        limits: ConfigLimits {
+            max_description_len: 5,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_XML), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(
+        stats.warnings,
+        vec!["Kea reservation testhost description is 11 characters long (limit 5)"]
+    );
+}
+
+#[test]
+fn test_stats_include_timing_and_element_counts() {
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_XML),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+
+    fn count(el: &Element) -> usize {
+        1 + el
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .map(count)
+            .sum::<usize>()
+    }
+    let written = Element::parse(Cursor::new(&output)).expect("valid XML");
+    assert_eq!(stats.timing.input_elements, 18);
+    assert_eq!(stats.timing.output_elements, count(&written));
+    assert!(stats.timing.output_elements > stats.timing.input_elements);
+    assert!(!stats.timing.parse.is_zero());
+    assert!(!stats.timing.write.is_zero());
+
+    let scan = scan_config(Cursor::new(TEST_XML), &MigrationOptions::default())
+        .expect("scan should succeed");
+    assert_eq!(scan.timing.input_elements, 18);
+    assert_eq!(scan.timing.output_elements, 18);
+    assert!(scan.timing.write.is_zero());
+}
+
+#[test]
+fn test_parsed_config_shared_between_scan_and_convert() {
+    let options = MigrationOptions::default();
+    let parsed =
+        ParsedConfig::parse(Cursor::new(TEST_XML_WITH_COMMENTS)).expect("parse should succeed");
+
+    let scan = parsed.scan(&options).expect("scan should succeed");
+    let expected_scan =
+        scan_config(Cursor::new(TEST_XML_WITH_COMMENTS), &options).expect("scan should succeed");
+    assert_eq!(
+        scan.reservations_to_create,
+        expected_scan.reservations_to_create
+    );
+    assert_eq!(scan.interface_labels, expected_scan.interface_labels);
+    assert_eq!(scan.timing.parse, parsed.parse_duration());
+
+    // Converting works on a copy, so a second convert sees the same input;
+    // outputs differ only in the generated reservation uuids
+    let mut expected = Vec::new();
+    convert_config(Cursor::new(TEST_XML_WITH_COMMENTS), &mut expected, &options)
+        .expect("convert should succeed");
+    for _ in 0..2 {
+        let mut output = Vec::new();
+        let stats = parsed
+            .convert(&mut output, &options)
+            .expect("convert should succeed");
+        assert_eq!(stats.reservations_to_create, 1);
+        assert_eq!(output.len(), expected.len());
+    }
+    assert!(find_descendant_ci(parsed.root(), "reservation").is_none());
+}
+
+#[test]
+fn test_convert_preserves_comments() {
+    let options = MigrationOptions {
+        enable_backend: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_XML_WITH_COMMENTS), &mut output, &options)
+        .expect("convert should succeed");
+    let output_str = String::from_utf8(output).expect("output should be valid UTF-8");
+
+    for comment in [
+        "<!-- top of file -->",
+        "<!-- printer pool -->",
+        "<!-- main lan -->",
+        "<!-- dns note -->",
+        "<!-- existing reservations -->",
+        "<!-- flipped at cutover -->",
+        "<!-- end of file -->",
+    ] {
+        assert!(
+            output_str.contains(comment),
+            "{} should survive conversion",
+            comment
+        );
+    }
+    assert!(output_str.starts_with(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!-- top of file -->\n<opnsense>"
+    ));
+
+    let root = Element::parse(Cursor::new(output_str.as_bytes())).expect("valid XML");
+    let enabled = find_descendant_ci(&root, "general")
+        .and_then(|g| g.get_child("enabled"))
+        .and_then(|e| e.get_text())
+        .expect("Should have enabled");
+    assert_eq!(enabled, "1");
+}
+
+#[test]
+fn test_register_unbound_creates_overrides() {
+    let options = MigrationOptions {
+        register_unbound: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_REGISTER_UNBOUND), &mut output, &options)
+        .expect("convert should succeed");
+
+    assert_eq!(stats.unbound_hosts_created, 2, "testhost A and AAAA");
+    assert_eq!(
+        stats.unbound_hosts_skipped, 2,
+        "printer exists, nas conflicts"
+    );
+
+    let root = Element::parse(Cursor::new(output)).expect("valid XML");
+    let hosts = find_descendant_ci(&root, "unboundplus")
+        .and_then(|u| u.get_child("hosts"))
+        .expect("Should have Unbound hosts");
+    let overrides: Vec<Vec<String>> = hosts
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .map(|host| {
+            ["hostname", "domain", "rr", "server", "description"]
+                .iter()
+                .map(|tag| {
+                    host.get_child(*tag)
+                        .and_then(|e| e.get_text())
+                        .map(|s| s.to_string())
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(overrides.len(), 4);
+    assert_eq!(
+        overrides[2],
+        vec!["testhost", "home.arpa", "A", "192.168.1.10", "Test Server"]
+    );
+    assert_eq!(
+        overrides[3],
+        vec!["testhost", "home.arpa", "AAAA", "2001:db8:42::10", ""]
+    );
+}
+
+#[test]
+fn test_register_unbound_requires_unbound_config() {
+    let options = MigrationOptions {
+        register_unbound: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let err = convert_config(Cursor::new(TEST_XML), &mut output, &options)
+        .expect_err("should fail without Unbound settings");
+    assert!(err.to_string().contains("Unbound not configured"));
+}
+
+#[test]
+fn test_limit_and_offset_migrate_in_batches() {
+    let options = MigrationOptions {
+        reservation_order: ReservationOrder::Ip,
+        limit: Some(2),
+        ..Default::default()
+    };
+    let mut first = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_SORT_RESERVATIONS_KEA),
+        &mut first,
+        &options,
+    )
+    .expect("first batch should succeed");
+    let batch = stats.batch.expect("batch stats");
+    assert_eq!((batch.offset, batch.selected, batch.total), (0, 2, 3));
+    assert_eq!(stats.reservations_to_create, 2);
+
+    // The next run continues from the previous output
+    let options = MigrationOptions {
+        offset: batch.next_offset(),
+        ..options
+    };
+    let mut second = Vec::new();
+    let stats = convert_config(Cursor::new(first), &mut second, &options)
+        .expect("second batch should succeed");
+    assert_eq!(stats.batch.map(|b| b.remaining()), Some(0));
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.reservations_skipped, 0);
+
+    let root = Element::parse(Cursor::new(second)).expect("valid XML");
+    let dhcp4 = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .expect("Should have dhcp4 node");
+    assert_eq!(reservations_as_fields(dhcp4).len(), 4);
+}
+
+#[test]
+fn test_enable_backend_refused_until_last_batch() {
+    let xml = TEST_ENABLE_BACKEND_KEA.replace(
+        "</range>",
+        "</range>
+            <staticmap><mac>00:11:22:33:44:01</mac><ipaddr>10.22.1.10</ipaddr></staticmap>
+            <staticmap><mac>00:11:22:33:44:02</mac><ipaddr>10.22.1.11</ipaddr></staticmap>",
+    );
+    let options = MigrationOptions {
+        create_subnets: true,
+        enable_backend: true,
+        limit: Some(1),
+        ..Default::default()
+    };
+    let err = convert_config(Cursor::new(xml.as_bytes()), Vec::new(), &options)
+        .expect_err("enable_backend should be refused mid-migration");
+    assert!(err.to_string().contains("1 mapping(s) not yet migrated"));
+
+    let options = MigrationOptions {
+        offset: 1,
+        ..options
+    };
+    let stats = convert_config(Cursor::new(xml.as_bytes()), Vec::new(), &options)
+        .expect("last batch may enable the backend");
+    assert!(stats.backend_enabled_v4);
+}
+
+fn origin_markers(el: &Element) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    if let Some(origin) = el.attributes.get(ORIGIN_ATTR) {
+        found.push((el.name.clone(), origin.clone()));
+    }
+    for child in el.children.iter().filter_map(|n| n.as_element()) {
+        found.extend(origin_markers(child));
+    }
+    found
+}
+
+#[test]
+fn test_mark_created_and_strip_markers() {
+    let options = MigrationOptions {
+        create_subnets: true,
+        mark_created: true,
+        clock: Clock::Fixed(1_767_261_600),
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_KEA_V4),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.markers_added, 2);
+
+    let root = Element::parse(Cursor::new(&output)).expect("valid XML");
+    let origin = format!("v{}", env!("CARGO_PKG_VERSION"));
+    assert_eq!(
+        origin_markers(&root),
+        vec![
+            ("subnet4".to_string(), origin.clone()),
+            ("reservation".to_string(), origin),
+        ]
+    );
+    let subnet = root
+        .get_child("Kea")
+        .and_then(|k| k.get_child("dhcp4"))
+        .and_then(|d| d.get_child("subnets"))
+        .and_then(|s| s.get_child("subnet4"))
+        .expect("created subnet");
+    assert_eq!(
+        subnet.attributes.get(CREATED_ATTR).map(String::as_str),
+        Some("2026-01-01T10:00:00Z")
+    );
+
+    // Re-running marks nothing new; stripping clears the earlier markers
+    let options = MigrationOptions {
+        strip_markers: true,
+        ..Default::default()
+    };
+    let mut stripped = Vec::new();
+    let stats = convert_config(Cursor::new(output), &mut stripped, &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_skipped, 1);
+    assert_eq!(stats.markers_added, 0);
+    assert_eq!(stats.markers_stripped, 2);
+    assert!(!String::from_utf8_lossy(&stripped).contains(CREATED_ATTR));
+    let root = Element::parse(Cursor::new(stripped)).expect("valid XML");
+    assert!(origin_markers(&root).is_empty());
+}
+
+#[test]
+fn test_dual_identifier_mappings() {
+    let options = MigrationOptions::default();
+    let stats = scan_config(Cursor::new(TEST_DUAL_ID_KEA_XML), &options).expect("scan");
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.reservations_skipped, 2);
+
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_DUAL_ID_KEA_XML), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.reservations_skipped, 2);
+
+    let root = Element::parse(Cursor::new(output)).expect("parse output");
+    let reservations = root
+        .get_child("Kea")
+        .and_then(|k| k.get_child("dhcp4"))
+        .and_then(|d| d.get_child("reservations"))
+        .expect("reservations");
+    let created = reservations
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .find(|r| {
+            r.get_child("ip_address")
+                .and_then(|e| e.get_text())
+                .as_deref()
+                == Some("192.168.1.10")
+        })
+        .expect("dual-identified reservation");
+    let text = |name: &str| created.get_child(name).and_then(|e| e.get_text());
+    assert_eq!(text("hw_address").as_deref(), Some("00:11:22:33:44:55"));
+    assert_eq!(text("client_id").as_deref(), Some("01:00:11:22:33:44:55"));
+    assert_eq!(text("hostname").as_deref(), Some("dualhost"));
+}
+
+#[test]
+fn test_verify_findings() {
+    let options = MigrationOptions {
+        enable_backend: true,
+        mark_created: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_XML), &mut output, &options).expect("convert should succeed");
+    let before = Element::parse(Cursor::new(TEST_XML)).expect("valid XML");
+    let after = Element::parse(Cursor::new(&output)).expect("valid XML");
+
+    let findings = verify_findings(&before, &after);
+    let summary: Vec<_> = findings
+        .iter()
+        .map(|f| (f.severity, f.category, f.change))
+        .collect();
+    assert!(summary.contains(&(
+        Severity::Error,
+        FindingCategory::Reservation,
+        FindingChange::Added
+    )));
+    assert!(summary.contains(&(
+        Severity::Warning,
+        FindingCategory::Service,
+        FindingChange::Removed
+    )));
+    let reservation = findings
+        .iter()
+        .find(|f| f.category == FindingCategory::Reservation)
+        .expect("reservation finding");
+    assert!(reservation
+        .path
+        .starts_with("opnsense/Kea/dhcp4/reservations/reservation["));
+    assert!(reservation.detail.contains("ip_address=192.168.1.10"));
+    assert!(reservation.detail.contains("hw_address=00:11:22:33:44:55"));
+    assert!(findings
+        .iter()
+        .any(|f| f.path == "opnsense/dhcpd/lan/enable"));
+
+    // Stripping markers only changes bookkeeping attributes
+    let options = MigrationOptions {
+        strip_markers: true,
+        ..Default::default()
+    };
+    let mut stripped = Vec::new();
+    convert_config(Cursor::new(&output), &mut stripped, &options).expect("convert should succeed");
+    let stripped = Element::parse(Cursor::new(&stripped)).expect("valid XML");
+    let findings = verify_findings(&after, &stripped);
+    assert_eq!(findings.len(), 2);
+    assert!(findings
+        .iter()
+        .all(|f| f.severity == Severity::Info && f.change == FindingChange::Changed));
+    assert!(findings[0]
+        .detail
+        .starts_with(&format!("@{}: ", CREATED_ATTR)));
+    assert!(findings[1]
+        .detail
+        .starts_with(&format!("@{}: v", ORIGIN_ATTR)));
+    assert!(verify_findings(&stripped, &stripped).is_empty());
+}
+
+#[test]
+fn test_prefix_delegation_reservations() {
+    let root = Element::parse(Cursor::new(TEST_PREFIX_V6_XML)).expect("valid XML");
+    let mappings = extract_isc_mappings_v6(&root).expect("extract should succeed");
+    let prefixes: Vec<_> = mappings.iter().map(|m| m.delegated_prefix()).collect();
+    assert_eq!(
+        prefixes,
+        vec![
+            Some("2001:db8:100::/56".to_string()),
+            Some("2001:db8:200::/60".to_string()),
+            None,
+        ]
+    );
+    assert_eq!(mappings[2].prefix.as_deref(), Some("2001:db8:300::"));
+
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_PREFIX_V6_XML),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.reservations_v6_to_create, 3);
+    assert_eq!(stats.prefix_reservations_v6, 2);
+    assert_eq!(stats.prefixes_v6_unmigrated, 1);
+
+    let root = Element::parse(Cursor::new(output)).expect("parse output");
+    let dhcp6 = find_descendant_ci(&root, "dhcp6").expect("dhcp6");
+    let reservations = dhcp6.get_child("reservations").expect("reservations");
+    let prefixes: Vec<_> = reservations
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .map(|r| r.get_child("prefixes").and_then(|p| p.get_text()))
+        .collect();
+    assert_eq!(
+        prefixes,
+        vec![
+            Some("2001:db8:100::/56".into()),
+            Some("2001:db8:200::/60".into()),
+            None,
+        ]
+    );
+
+    let stats = scan_config(
+        Cursor::new(TEST_PREFIX_V6_XML),
+        &MigrationOptions::default(),
+    )
+    .expect("scan should succeed");
+    assert_eq!(stats.prefix_reservations_v6, 2);
+    assert_eq!(stats.prefixes_v6_unmigrated, 1);
+}
+
+#[test]
+fn test_duplicate_uuids_fail_or_regenerate() {
+    let err = scan_config(
+        Cursor::new(TEST_DUPLICATE_UUID_KEA_XML),
+        &MigrationOptions::default(),
+    )
+    .expect_err("duplicate subnet uuids should fail");
+    assert_eq!(
+        err.downcast_ref::<MigrationError>(),
+        Some(&MigrationError::DuplicateUuids {
+            duplicates: vec![DuplicateUuid {
+                uuid: "dup-subnet-uuid".to_string(),
+                section: "Kea".to_string(),
+                elements: vec!["subnet4".to_string(), "subnet4".to_string()],
+            }],
+        })
+    );
+    assert!(err
+        .to_string()
+        .contains("  dup-subnet-uuid (Kea): subnet4, subnet4"));
+
+    let options = MigrationOptions {
+        fix_duplicate_uuids: true,
+        ..Default::default()
+    };
+    let stats = scan_config(Cursor::new(TEST_DUPLICATE_UUID_KEA_XML), &options)
+        .expect("scan should succeed");
+    assert_eq!(stats.uuids_regenerated, 1);
+    assert_eq!(stats.reservations_to_create, 1);
+
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_DUPLICATE_UUID_KEA_XML),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.uuids_regenerated, 1);
+
+    let root = Element::parse(Cursor::new(output)).expect("parse output");
+    let dhcp4 = find_descendant_ci(&root, "dhcp4").expect("dhcp4");
+    let subnets: Vec<_> = dhcp4
+        .get_child("subnets")
+        .expect("subnets")
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .map(|s| s.attributes["uuid"].clone())
+        .collect();
+    assert_eq!(subnets[0], "dup-subnet-uuid");
+    assert_ne!(subnets[1], "dup-subnet-uuid");
+
+    // Each reservation points at the subnet holding its address
+    let reservation_subnets: Vec<_> = reservations_as_fields(dhcp4)
+        .into_iter()
+        .map(|fields| {
+            let field = |name: &str| {
+                fields
+                    .iter()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.clone())
+                    .unwrap_or_default()
+            };
+            (field("ip_address"), field("subnet"))
+        })
+        .collect();
+    assert_eq!(
+        reservation_subnets,
+        vec![
+            ("192.168.1.50".to_string(), subnets[0].clone()),
+            ("192.168.2.50".to_string(), subnets[1].clone()),
+            ("192.168.2.10".to_string(), subnets[1].clone()),
+        ]
+    );
+}
+
+#[test]
+fn test_hostname_policy() {
+    let hostnames = |options: &MigrationOptions| {
+        let mut output = Vec::new();
+        convert_config(Cursor::new(TEST_HOSTNAME_POLICY_XML), &mut output, options)
+            .expect("convert should succeed");
+        let root = Element::parse(Cursor::new(output)).expect("parse output");
+        let dhcp4 = find_descendant_ci(&root, "dhcp4").expect("dhcp4");
+        reservations_as_fields(dhcp4)
+            .into_iter()
+            .filter_map(|fields| {
+                fields
+                    .into_iter()
+                    .find(|(k, _)| k == "hostname")
+                    .map(|(_, v)| v)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        hostnames(&MigrationOptions::default()),
+        vec!["nas", "printer.old.lan"]
+    );
+    assert_eq!(
+        hostnames(&MigrationOptions {
+            hostname_policy: HostnamePolicy::Short,
+            ..Default::default()
+        }),
+        vec!["nas", "printer"]
+    );
+    assert_eq!(
+        hostnames(&MigrationOptions {
+            hostname_policy: HostnamePolicy::Fqdn,
+            hostname_domain: Some("example.lan.".to_string()),
+            ..Default::default()
+        }),
+        vec!["nas.example.lan", "printer.example.lan"]
+    );
+
+    let err = convert_config(
+        Cursor::new(TEST_HOSTNAME_POLICY_XML),
+        Vec::new(),
+        &MigrationOptions {
+            hostname_policy: HostnamePolicy::Fqdn,
+            ..Default::default()
+        },
+    )
+    .expect_err("fqdn without a domain should fail");
+    assert!(err.to_string().contains("--domain"));
+}
+
+#[test]
+fn test_validate_input_rejects_non_opnsense_configs() {
+    let root = Element::parse(Cursor::new(TEST_XML)).unwrap();
+    validate_input(&root).expect("OPNsense config should pass");
+
+    let pfsense = r#"<?xml version="1.0"?><pfsense><version>23.3</version></pfsense>"#;
+    let err = scan_config(Cursor::new(pfsense), &MigrationOptions::default())
+        .expect_err("pfSense root should fail");
+    assert!(matches!(
+        err.downcast_ref::<MigrationError>(),
+        Some(MigrationError::NotOpnsenseConfig { .. })
+    ));
+    assert!(err.to_string().contains("root element is <pfsense>"));
+
+    let empty = Element::parse(Cursor::new("<opnsense><foo/></opnsense>")).unwrap();
+    let err = validate_input(&empty).expect_err("empty opnsense root should fail");
+    assert!(err.to_string().contains("truncated"));
+
+    let other = Element::parse(Cursor::new("<dhcpd><lan/></dhcpd>")).unwrap();
+    let err = validate_input(&other).expect_err("bare section should fail");
+    assert!(err
+        .to_string()
+        .contains("the root element is <dhcpd>, expected <opnsense>"));
+}
+
+#[test]
+fn test_validate_output_before_writing() {
+    let before = Element::parse(Cursor::new(TEST_XML)).unwrap();
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(TEST_XML),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    validate_output(&before, Cursor::new(&output), &Backend::Kea)
+        .expect("converted output should pass");
+
+    let truncated = &output[..output.len() / 2];
+    let err = validate_output(&before, Cursor::new(truncated), &Backend::Kea)
+        .expect_err("truncated output should fail");
+    assert!(matches!(err, MigrationError::InvalidOutput { .. }));
+    assert!(err.to_string().contains("does not parse"));
+
+    let misspelled = String::from_utf8(output)
+        .unwrap()
+        .replace("<hostname>", "<host_name>")
+        .replace("</hostname>", "</host_name>");
+    let err = validate_output(&before, Cursor::new(misspelled), &Backend::Kea)
+        .expect_err("unknown reservation field should fail");
+    assert!(err.to_string().contains("host_name"));
+}
+
+#[test]
+fn test_convert_v4_domain_options() {
+    let input = TEST_XML.replace(
+        "<descr>Test Server</descr>",
+        "<descr>Test Server</descr>\n                <domain>office.lan</domain>\n                <domainsearchlist>office.lan; lab.lan</domainsearchlist>",
+    );
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(&input),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).unwrap();
+    let reservation = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .and_then(|dhcp4| dhcp4.get_child("reservations"))
+        .and_then(|reservations| reservations.get_child("reservation"))
+        .expect("Should have a reservation");
+    let option_data = reservation
+        .get_child("option_data")
+        .expect("Should have option_data");
+    let field = |name: &str| {
+        option_data
+            .get_child(name)
+            .and_then(|e| e.get_text())
+            .map(|text| text.to_string())
+    };
+    assert_eq!(field("domain_name").as_deref(), Some("office.lan"));
+    assert_eq!(
+        field("domain_search").as_deref(),
+        Some("office.lan,lab.lan")
+    );
+    // Per-host DNS servers and gateway still need --create-options
+    assert_eq!(field("domain_name_servers"), None);
+    assert_eq!(field("routers"), None);
+
+    // Without the fields the reservation keeps no option_data
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(TEST_XML),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    assert!(!String::from_utf8(output).unwrap().contains("<option_data>"));
+}
+
+#[test]
+fn test_plan_and_apply() {
+    let input = TEST_XML
+        .replace(
+            "</staticmap>",
+            "</staticmap>\n            <staticmap>\n                <mac>aa:bb:cc:dd:ee:ff</mac>\n                <ipaddr>192.168.1.20</ipaddr>\n            </staticmap>",
+        )
+        .replace(
+            "</subnet4>",
+            "</subnet4>\n                <subnet4 uuid=\"narrow-subnet-uuid\">\n                    <subnet>192.168.1.0/25</subnet>\n                </subnet4>",
+        );
+    let mut plan = plan_config(Cursor::new(&input), &MigrationOptions::default())
+        .expect("plan should succeed");
+    assert_eq!(plan.v4.len(), 2);
+    assert!(plan.v6.is_empty());
+    assert_eq!(plan.v4[0].hostname.as_deref(), Some("testhost"));
+    assert!(plan.v4.iter().all(|entry| entry.subnet.is_some()));
+
+    // Review: drop the second mapping and move the first to the /25
+    plan.v4.retain(|entry| entry.ip == "192.168.1.10");
+    plan.v4[0].subnet = Some("192.168.1.0/25".to_string());
+    let plan = MigrationPlan::parse(&plan.to_text()).expect("plan should parse back");
+
+    let options = MigrationOptions {
+        plan: Some(plan.clone()),
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats =
+        convert_config(Cursor::new(&input), &mut output, &options).expect("apply should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("<subnet>narrow-subnet-uuid</subnet>"));
+    assert!(!output.contains("<ip_address>192.168.1.20</ip_address>"));
+
+    let mut stale = plan.clone();
+    stale.v4[0].ip = "192.168.1.99".to_string();
+    let err = convert_config(
+        Cursor::new(&input),
+        &mut Vec::new(),
+        &MigrationOptions {
+            plan: Some(stale),
+            ..Default::default()
+        },
+    )
+    .expect_err("stale plan should fail");
+    match err.downcast_ref::<MigrationError>() {
+        Some(MigrationError::StalePlan { entries }) => {
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].ip, "192.168.1.99");
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert!(err.to_string().contains("  lan 192.168.1.99 ("));
+
+    let mut elsewhere = plan;
+    elsewhere.v4[0].subnet = Some("10.0.0.0/8".to_string());
+    let err = convert_config(
+        Cursor::new(&input),
+        &mut Vec::new(),
+        &MigrationOptions {
+            plan: Some(elsewhere),
+            ..Default::default()
+        },
+    )
+    .expect_err("subnet outside Kea should fail");
+    assert!(err.to_string().contains("10.0.0.0/8"));
+}
+
+#[test]
+fn test_convert_non_ethernet_hwaddrs() {
+    let infiniband = "80:00:02:08:FE:80:00:00:00:00:00:00:00:02:C9:03:00:0A:0B:0C";
+    let input = TEST_XML.replace("00:11:22:33:44:55", infiniband);
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(&input),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    let output = String::from_utf8(output).expect("output should be valid UTF-8");
+    assert!(output.contains(&format!(
+        "<hw_address>{}</hw_address>",
+        infiniband.to_ascii_lowercase()
+    )));
+
+    // Longer than Kea stores, so reported and skipped
+    let input = TEST_XML.replace("00:11:22:33:44:55", &format!("{}:0d", infiniband));
+    let stats = scan_config(Cursor::new(&input), &MigrationOptions::default())
+        .expect("scan should succeed");
+    assert_eq!(stats.reservations_to_create, 0);
+    assert_eq!(stats.reservations_skipped, 1);
+}
+
+#[test]
+fn test_kea_interfaces_not_listening() {
+    let stats = scan_config(
+        Cursor::new(TEST_KEA_INTERFACES_NOT_LISTENING),
+        &MigrationOptions::default(),
+    )
+    .expect("scan should succeed");
+    assert_eq!(stats.reservations_to_create, 2);
+    assert_eq!(stats.interfaces_not_listening, ["opt1"]);
+
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_KEA_INTERFACES_NOT_LISTENING),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.interfaces_not_listening, ["opt1"]);
+    assert!(stats.interfaces_configured.is_empty());
+
+    let options = MigrationOptions {
+        kea_interfaces: KeaInterfacePolicy::Append,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_KEA_INTERFACES_NOT_LISTENING),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert!(stats.interfaces_not_listening.is_empty());
+    assert_eq!(stats.interfaces_configured, ["opt1"]);
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let interfaces = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .and_then(|dhcp4| dhcp4.get_child("general"))
+        .and_then(|general| general.get_child("interfaces"))
+        .and_then(|e| e.get_text())
+        .expect("Should have interfaces");
+    assert_eq!(interfaces, "lan,opt1");
+}
+
+#[test]
+fn test_hostname_collisions_in_stats() {
+    let stats = scan_config(
+        Cursor::new(TEST_HOSTNAME_COLLISIONS),
+        &MigrationOptions::default(),
+    )
+    .expect("scan should succeed");
+    assert_eq!(stats.hostname_collisions.len(), 1);
+    assert_eq!(stats.hostname_collisions[0].hostname, "printer");
+    assert_eq!(
+        stats.hostname_collisions[0].ips,
+        ["192.168.1.20", "192.168.1.10"]
+    );
+
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_HOSTNAME_COLLISIONS),
+        &mut output,
+        &MigrationOptions::default(),
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 2);
+    assert_eq!(stats.hostname_collisions.len(), 1);
+}
+
+#[test]
+fn test_source_static_leases_plugin() {
+    let stats = scan_config(
+        Cursor::new(TEST_STATIC_LEASES_PLUGIN),
+        &MigrationOptions::default(),
+    )
+    .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 2);
+    assert_eq!(stats.reservations_to_create, 2);
+
+    let options = MigrationOptions {
+        source: Source::Dhcpd,
+        ..Default::default()
+    };
+    let stats =
+        scan_config(Cursor::new(TEST_STATIC_LEASES_PLUGIN), &options).expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 0);
+
+    let options = MigrationOptions {
+        source: Source::DhcpStaticLeases,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(TEST_STATIC_LEASES_PLUGIN),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dhcp4 = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .expect("Should have dhcp4 node");
+    let reservations = reservations_as_fields(dhcp4);
+    assert_eq!(reservations.len(), 2);
+    let field = |fields: &Vec<(String, String)>, name: &str| {
+        fields
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    };
+    assert_eq!(
+        field(&reservations[0], "hostname").as_deref(),
+        Some("printer")
+    );
+    assert_eq!(
+        field(&reservations[1], "description").as_deref(),
+        Some("No interface set")
+    );
+}
+
+#[test]
+fn test_generated_fixture_migrates() {
+    let spec = FixtureSpec {
+        interfaces: 3,
+        maps_per_iface: 200,
+        existing_per_iface: 2,
+        seed: 42,
+    };
+    let xml = generate_fixture(&spec).expect("generate fixture");
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(&xml), &mut output, &MigrationOptions::default())
+        .expect("convert should succeed");
+    assert_eq!(stats.isc_mappings_found, 600);
+    assert_eq!(stats.target_subnets_found, 3);
+    assert_eq!(stats.reservations_to_create, 594);
+    assert_eq!(stats.reservations_skipped, 6);
+}
+
+#[test]
+fn test_link_dual_stack_descriptions() {
+    let options = MigrationOptions {
+        link_dual_stack: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_DUAL_STACK_XML), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.dual_stack_linked, 2);
+    assert_eq!(stats.reservations_to_create, 3);
+    assert_eq!(stats.reservations_v6_to_create, 2);
+
+    let root = Element::parse(Cursor::new(output)).unwrap();
+    let descriptions = |family: &str| -> Vec<(String, String)> {
+        let reservations = root
+            .get_child("Kea")
+            .and_then(|k| k.get_child(family))
+            .and_then(|d| d.get_child("reservations"))
+            .unwrap();
+        reservations
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .map(|r| {
+                let text = |name: &str| {
+                    r.get_child(name)
+                        .and_then(|e| e.get_text())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                (text("ip_address"), text("description"))
+            })
+            .collect()
+    };
+    let pair = |ip: &str, descr: &str| (ip.to_string(), descr.to_string());
+    // Paired by the MAC in the DUID-LLT, then by short hostname
+    assert_eq!(
+        descriptions("dhcp4"),
+        [
+            pair("192.168.1.10", "Printer; dual-stack: 2001:db8:42::10"),
+            pair("192.168.1.20", "dual-stack: 2001:db8:42::20"),
+            pair("192.168.1.30", ""),
+        ]
+    );
+    assert_eq!(
+        descriptions("dhcp6"),
+        [
+            pair("2001:db8:42::10", "dual-stack: 192.168.1.10"),
+            pair("2001:db8:42::20", "dual-stack: 192.168.1.20"),
+        ]
+    );
+
+    // Off by default
+    let stats = scan_config(
+        Cursor::new(TEST_DUAL_STACK_XML),
+        &MigrationOptions::default(),
+    )
+    .expect("scan should succeed");
+    assert_eq!(stats.dual_stack_linked, 0);
+}
+
+#[test]
+fn test_field_overrides_redirect_descriptions() {
+    let options = MigrationOptions {
+        field_overrides: FieldOverrides::parse(
+            "[description]\nfrom = \"hostname\"\n\n[hostname]\ndrop = true\n",
+        )
+        .unwrap(),
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_DUAL_STACK_XML), &mut output, &options)
+        .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).unwrap();
+    let fields = |family: &str| -> Vec<(String, String)> {
+        let reservations = root
+            .get_child("Kea")
+            .and_then(|k| k.get_child(family))
+            .and_then(|d| d.get_child("reservations"))
+            .unwrap();
+        reservations
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .map(|r| {
+                let text = |name: &str| {
+                    r.get_child(name)
+                        .and_then(|e| e.get_text())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                (text("hostname"), text("description"))
+            })
+            .collect()
+    };
+    let pair = |hostname: &str, descr: &str| (hostname.to_string(), descr.to_string());
+    assert_eq!(
+        fields("dhcp4"),
+        [pair("", "printer"), pair("", "laptop"), pair("", "camera")]
+    );
+    assert_eq!(
+        fields("dhcp6"),
+        [pair("", ""), pair("", "Laptop.example.com")]
+    );
+}
@@ -1,5 +1,7 @@
 use super::common::*;
-use isc2kea::{convert_config, MigrationOptions};
+use isc2kea::{
+    convert_config, scan_config, Backend, DnsmasqOptionConflict, MigrationOptions, OptionsMerge,
+};
 use std::io::Cursor;
 use xmltree::Element;
 
@@ -57,6 +59,12 @@ fn test_create_options_kea_v4() {
         .expect("Should have ntp_servers");
     assert_eq!(ntp, "10.22.1.10");
 
+    let wins = option_data
+        .get_child("netbios_name_servers")
+        .and_then(|e| e.get_text())
+        .expect("Should have netbios_name_servers");
+    assert_eq!(wins, "10.22.1.20,10.22.1.21");
+
     let autocollect = subnet4
         .get_child("option_data_autocollect")
         .and_then(|e| e.get_text())
@@ -110,7 +118,8 @@ fn test_create_options_kea_existing_skip_and_force() {
         ..Default::default()
     };
 
-    convert_config(input, &mut output, &options).expect("convert should succeed");
+    let stats = convert_config(input, &mut output, &options).expect("convert should succeed");
+    assert_eq!((stats.options_created, stats.options_skipped), (0, 2));
 
     let output_str = String::from_utf8(output).expect("output should be valid UTF-8");
     let root =
@@ -137,12 +146,13 @@ fn test_create_options_kea_existing_skip_and_force() {
         ..Default::default()
     };
 
-    convert_config(
+    let stats = convert_config(
         Cursor::new(TEST_CREATE_OPTIONS_KEA_V4_EXISTING),
         &mut output_force,
         &options_force,
     )
     .expect("convert should succeed with force");
+    assert_eq!((stats.options_created, stats.options_skipped), (2, 0));
     let output_str = String::from_utf8(output_force).expect("output should be valid UTF-8");
     let root =
         Element::parse(Cursor::new(output_str.as_bytes())).expect("output should be valid XML");
@@ -191,6 +201,13 @@ fn test_create_options_dnsmasq() {
     let ntp = dnsmasq_option_value(&root, "opt1", "42", "").expect("ntp option should exist");
     assert_eq!(ntp, "10.22.1.10");
 
+    let wins = dnsmasq_option_value(&root, "opt1", "44", "").expect("wins option should exist");
+    assert_eq!(wins, "10.22.1.20,10.22.1.21");
+
+    let node_type =
+        dnsmasq_option_value(&root, "opt1", "46", "").expect("node type option should exist");
+    assert_eq!(node_type, "8");
+
     let v6_dns = dnsmasq_option_value(&root, "lan", "", "23").expect("v6 dns option should exist");
     assert_eq!(v6_dns, "fd00:1234:5678:1::1,fd00:1234:5678:1::2");
 
@@ -206,7 +223,8 @@ fn test_create_options_dnsmasq_existing_skip_and_force() {
     let mut options = dnsmasq_options();
     options.create_options = true;
 
-    convert_config(input, &mut output, &options).expect("convert should succeed");
+    let stats = convert_config(input, &mut output, &options).expect("convert should succeed");
+    assert_eq!((stats.options_created, stats.options_skipped), (4, 1));
 
     let output_str = String::from_utf8(output).expect("output should be valid UTF-8");
     let root =
@@ -222,15 +240,609 @@ fn test_create_options_dnsmasq_existing_skip_and_force() {
     options_force.create_options = true;
     options_force.force_options = true;
 
-    convert_config(
+    let stats = convert_config(
         Cursor::new(TEST_CREATE_OPTIONS_DNSMASQ_EXISTING),
         &mut output_force,
         &options_force,
     )
     .expect("convert should succeed with force");
+    assert_eq!((stats.options_created, stats.options_skipped), (5, 0));
     let output_str = String::from_utf8(output_force).expect("output should be valid UTF-8");
     let root =
         Element::parse(Cursor::new(output_str.as_bytes())).expect("output should be valid XML");
     let dns = dnsmasq_option_value(&root, "opt1", "6", "").expect("dns option should exist");
     assert_eq!(dns, "8.8.8.8,1.1.1.1");
 }
+
+/// `option_data` field values of the first `subnet4` after converting `input`.
+fn kea_option_values(input: &str, options: &MigrationOptions, fields: &[&str]) -> Vec<String> {
+    let mut output = Vec::new();
+    convert_config(Cursor::new(input), &mut output, options).expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let option_data = root
+        .get_child("Kea")
+        .and_then(|k| k.get_child("dhcp4"))
+        .and_then(|d| d.get_child("subnets"))
+        .and_then(|s| s.get_child("subnet4"))
+        .and_then(|s| s.get_child("option_data"))
+        .expect("Should have option_data");
+    fields
+        .iter()
+        .map(|field| {
+            option_data
+                .get_child(*field)
+                .and_then(|e| e.get_text())
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn test_create_options_kea_merge_policies() {
+    let fields = ["domain_name_servers", "routers", "domain_name"];
+    let mut options = MigrationOptions {
+        create_options: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        kea_option_values(TEST_CREATE_OPTIONS_KEA_V4_PARTIAL, &options, &fields),
+        ["8.8.8.8", "10.22.1.254", "example.com"]
+    );
+
+    options.options_merge = OptionsMerge::Overwrite;
+    assert_eq!(
+        kea_option_values(TEST_CREATE_OPTIONS_KEA_V4_PARTIAL, &options, &fields),
+        ["8.8.8.8", "10.22.1.1", "example.com"]
+    );
+
+    options.options_merge = OptionsMerge::Skip;
+    assert_eq!(
+        kea_option_values(TEST_CREATE_OPTIONS_KEA_V4_PARTIAL, &options, &fields),
+        ["", "10.22.1.254", ""]
+    );
+
+    // --force-options wins over the merge policy
+    options.force_options = true;
+    assert_eq!(
+        kea_option_values(TEST_CREATE_OPTIONS_KEA_V4_PARTIAL, &options, &fields),
+        ["8.8.8.8", "10.22.1.1", "example.com"]
+    );
+}
+
+#[test]
+fn test_create_options_dnsmasq_merge_skip() {
+    let mut output = Vec::new();
+    let mut options = dnsmasq_options();
+    options.create_options = true;
+    options.options_merge = OptionsMerge::Skip;
+
+    convert_config(
+        Cursor::new(TEST_CREATE_OPTIONS_DNSMASQ_EXISTING),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dns = dnsmasq_option_value(&root, "opt1", "6", "").expect("dns option should exist");
+    assert_eq!(dns, "9.9.9.9");
+    assert!(dnsmasq_option_value(&root, "opt1", "3", "").is_none());
+}
+
+#[test]
+fn test_create_options_kea_v6_extended() {
+    let input = Cursor::new(TEST_CREATE_OPTIONS_V6_EXTENDED);
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_options: true,
+        ..Default::default()
+    };
+
+    convert_config(input, &mut output, &options).expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let option_data = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp6"))
+        .and_then(|dhcp6| dhcp6.get_child("subnets"))
+        .and_then(|s| s.get_child("subnet6"))
+        .and_then(|s| s.get_child("option_data"))
+        .expect("Should have option_data");
+    let value = |tag: &str| {
+        option_data
+            .get_child(tag)
+            .and_then(|e| e.get_text())
+            .map(|s| s.to_string())
+    };
+
+    // Not fields of the OPNsense Kea subnet6 model
+    assert!(value("sntp_servers").is_none());
+    assert!(value("sip_server_addr").is_none());
+    assert!(value("bootfile_url").is_none());
+    assert!(value("dns_servers").is_none());
+}
+
+#[test]
+fn test_create_options_dnsmasq_v6_extended() {
+    let input = Cursor::new(TEST_CREATE_OPTIONS_V6_EXTENDED);
+    let mut output = Vec::new();
+    let mut options = dnsmasq_options();
+    options.create_options = true;
+
+    convert_config(input, &mut output, &options).expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+
+    let sip = dnsmasq_option_value(&root, "lan", "", "22").expect("v6 sip option should exist");
+    assert_eq!(sip, "fd00:1234:5678:1::50");
+
+    let ntp = dnsmasq_option_value(&root, "lan", "", "31").expect("v6 sntp option should exist");
+    assert_eq!(ntp, "fd00:1234:5678:1::123,fd00:1234:5678:1::124");
+
+    let boot =
+        dnsmasq_option_value(&root, "lan", "", "59").expect("v6 bootfile-url option should exist");
+    assert_eq!(boot, "http://[fd00:1234:5678:1::10]/boot.efi");
+}
+
+fn dnsmasq_search_conflict(policy: DnsmasqOptionConflict) -> Element {
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_options: true,
+        dnsmasq_option_conflict: policy,
+        ..dnsmasq_options()
+    };
+    convert_config(
+        Cursor::new(TEST_CREATE_OPTIONS_DNSMASQ_SEARCH_CONFLICT),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    Element::parse(Cursor::new(output)).expect("output should be valid XML")
+}
+
+#[test]
+fn test_create_options_dnsmasq_search_conflict() {
+    let root = dnsmasq_search_conflict(DnsmasqOptionConflict::PreferV4);
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "119", "").as_deref(),
+        Some("home.arpa,corp.example")
+    );
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "", "24").as_deref(),
+        Some("home.arpa,corp.example")
+    );
+
+    let root = dnsmasq_search_conflict(DnsmasqOptionConflict::PreferV6);
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "119", "").as_deref(),
+        Some("v6.home.arpa")
+    );
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "", "24").as_deref(),
+        Some("v6.home.arpa")
+    );
+
+    let root = dnsmasq_search_conflict(DnsmasqOptionConflict::KeepBothTagged);
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "119", "").as_deref(),
+        Some("home.arpa,corp.example")
+    );
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "", "24").as_deref(),
+        Some("v6.home.arpa")
+    );
+    let dnsmasq = find_descendant_ci(&root, "dnsmasq").expect("Should have dnsmasq node");
+    let tags: Vec<&Element> = dnsmasq
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "dhcp_tags")
+        .collect();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(
+        tags[0]
+            .get_child("tag")
+            .and_then(|t| t.get_text())
+            .as_deref(),
+        Some("dhcpv6")
+    );
+    let tag_of = |option: &str, option6: &str| {
+        dnsmasq
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .filter(|e| e.name == "dhcp_options")
+            .find(|e| {
+                e.get_child("option")
+                    .and_then(|t| t.get_text())
+                    .unwrap_or_default()
+                    == option
+                    && e.get_child("option6")
+                        .and_then(|t| t.get_text())
+                        .unwrap_or_default()
+                        == option6
+            })
+            .and_then(|e| e.get_child("tag"))
+            .and_then(|t| t.get_text())
+            .map(|t| t.to_string())
+            .unwrap_or_default()
+    };
+    assert_eq!(tag_of("", "24"), tags[0].attributes["uuid"]);
+    assert_eq!(tag_of("119", ""), "");
+}
+
+#[test]
+fn test_validate_schema_accepts_created_elements() {
+    let cases = [
+        (Backend::Kea, TEST_CREATE_OPTIONS_KEA_V4),
+        (Backend::Kea, TEST_CREATE_OPTIONS_KEA_V6),
+        (Backend::Kea, TEST_CREATE_OPTIONS_V6_EXTENDED),
+        (Backend::Kea, TEST_CREATE_SUBNETS_KEA_NETBOOT),
+        (Backend::Kea, TEST_ENABLE_BACKEND_KEA),
+        (Backend::Kea, TEST_PREFIX_V6_XML),
+        (Backend::Dnsmasq, TEST_CREATE_OPTIONS_DNSMASQ),
+        (Backend::Dnsmasq, TEST_CREATE_SUBNETS_DNSMASQ_V6_RAMODE),
+        (Backend::Dnsmasq, TEST_DUAL_ID_DNSMASQ_XML),
+        (Backend::Kea, TEST_STATICMAP_OPTIONS),
+        (Backend::Dnsmasq, TEST_STATICMAP_OPTIONS),
+    ];
+    for (backend, input) in cases {
+        let options = MigrationOptions {
+            backend: backend.clone(),
+            create_subnets: true,
+            create_options: true,
+            enable_backend: true,
+            validate_schema: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        convert_config(Cursor::new(input), &mut output, &options)
+            .unwrap_or_else(|e| panic!("{:?} convert should pass the schema: {:#}", backend, e));
+    }
+}
+
+#[test]
+fn test_validate_schema_accepts_dns_hosts() {
+    for (input, options) in [
+        (
+            TEST_CREATE_DNS_HOSTS,
+            MigrationOptions {
+                create_dns_hosts: true,
+                validate_schema: true,
+                ..Default::default()
+            },
+        ),
+        (
+            TEST_REGISTER_UNBOUND,
+            MigrationOptions {
+                register_unbound: true,
+                validate_schema: true,
+                ..Default::default()
+            },
+        ),
+    ] {
+        let mut output = Vec::new();
+        convert_config(Cursor::new(input), &mut output, &options)
+            .expect("convert should pass the schema");
+    }
+}
+
+#[test]
+fn test_gateway_none_suppresses_routers_kea() {
+    let routers = |output: &[u8], subnet: usize| {
+        let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+        let subnet4 = root
+            .get_child("Kea")
+            .and_then(|k| k.get_child("dhcp4"))
+            .and_then(|d| d.get_child("subnets"))
+            .and_then(|s| {
+                s.children
+                    .iter()
+                    .filter_map(|n| n.as_element())
+                    .nth(subnet)
+                    .cloned()
+            })
+            .expect("Should have subnet4");
+        let text = |el: Option<&Element>| el.and_then(|e| e.get_text()).map(|t| t.to_string());
+        let option_data = subnet4.get_child("option_data");
+        (
+            text(option_data.and_then(|o| o.get_child("routers"))),
+            text(option_data.and_then(|o| o.get_child("domain_name_servers"))),
+            text(subnet4.get_child("option_data_autocollect")),
+        )
+    };
+
+    // Filling keeps a router someone set in Kea already
+    let options = MigrationOptions {
+        create_options: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_GATEWAY_NONE), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(
+        routers(&output, 0),
+        (
+            Some("192.168.1.254".to_string()),
+            Some("9.9.9.9".to_string()),
+            Some("0".to_string())
+        )
+    );
+    // A blank gateway sets nothing; autocollection off leaves routers unset
+    assert_eq!(
+        routers(&output, 1),
+        (None, Some("8.8.8.8".to_string()), Some("0".to_string()))
+    );
+
+    let options = MigrationOptions {
+        create_options: true,
+        force_options: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_GATEWAY_NONE), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(
+        routers(&output, 0),
+        (None, Some("9.9.9.9".to_string()), Some("0".to_string()))
+    );
+}
+
+#[test]
+fn test_gateway_none_suppresses_routers_dnsmasq() {
+    let mut options = dnsmasq_options();
+    options.create_options = true;
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_GATEWAY_NONE), &mut output, &options)
+        .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(&output)).expect("output should be valid XML");
+
+    let option3: Vec<(String, Option<String>)> = root
+        .get_child("dnsmasq")
+        .expect("Should have dnsmasq node")
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(|e| e.get_child("option").and_then(|o| o.get_text()).as_deref() == Some("3"))
+        .map(|e| {
+            let text = |tag: &str| {
+                e.get_child(tag)
+                    .and_then(|c| c.get_text())
+                    .map(|t| t.to_string())
+            };
+            (text("interface").unwrap_or_default(), text("value"))
+        })
+        .collect();
+    // An empty option 3 on lan; opt1 keeps dnsmasq's default router
+    assert_eq!(option3, vec![("lan".to_string(), None)]);
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "6", "").as_deref(),
+        Some("9.9.9.9")
+    );
+}
+
+#[test]
+fn test_create_options_dnsmasq_classless_routes() {
+    let mut output = Vec::new();
+    let mut options = dnsmasq_options();
+    options.create_options = true;
+
+    convert_config(Cursor::new(TEST_CLASSLESS_ROUTES), &mut output, &options)
+        .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+
+    let routes = "10.0.0.0/8,192.168.1.2,172.16.5.0/24,192.168.1.3";
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "121", "").as_deref(),
+        Some(routes)
+    );
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "249", "").as_deref(),
+        Some(routes)
+    );
+    assert_eq!(dnsmasq_option_value(&root, "opt1", "121", ""), None);
+}
+
+#[test]
+fn test_staticmap_options_kea() {
+    let convert = |create_options: bool| {
+        let options = MigrationOptions {
+            create_options,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        convert_config(Cursor::new(TEST_STATICMAP_OPTIONS), &mut output, &options)
+            .expect("convert should succeed");
+        Element::parse(Cursor::new(output)).expect("output should be valid XML")
+    };
+    let host_options = |root: &Element| -> Vec<Option<(String, String)>> {
+        let reservations = find_descendant_ci(root, "reservations").expect("reservations");
+        reservations
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .map(|r| {
+                let option_data = r.get_child("option_data")?;
+                let text = |tag: &str| {
+                    option_data
+                        .get_child(tag)
+                        .and_then(|e| e.get_text())
+                        .map(|t| t.to_string())
+                        .unwrap_or_default()
+                };
+                Some((text("domain_name_servers"), text("routers")))
+            })
+            .collect()
+    };
+
+    assert_eq!(
+        host_options(&convert(true)),
+        vec![
+            Some((
+                "192.168.1.53,192.168.1.54".to_string(),
+                "192.168.1.254".to_string()
+            )),
+            Some((String::new(), "192.168.1.253".to_string())),
+            None,
+        ]
+    );
+    // Per-host options are options too
+    assert_eq!(host_options(&convert(false)), vec![None, None, None]);
+}
+
+#[test]
+fn test_staticmap_options_dnsmasq() {
+    let mut options = dnsmasq_options();
+    options.create_options = true;
+    let mut output = Vec::new();
+    convert_config(Cursor::new(TEST_STATICMAP_OPTIONS), &mut output, &options)
+        .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dnsmasq = root.get_child("dnsmasq").expect("Should have dnsmasq node");
+    let children: Vec<&Element> = dnsmasq
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .collect();
+    let text = |el: &Element, tag: &str| {
+        el.get_child(tag)
+            .and_then(|e| e.get_text())
+            .map(|t| t.to_string())
+            .unwrap_or_default()
+    };
+    let uuid = |el: &Element| el.attributes.get("uuid").cloned().unwrap_or_default();
+
+    // The existing tag name is not reused
+    let tags: Vec<(String, String)> = children
+        .iter()
+        .filter(|e| e.name == "dhcp_tags")
+        .map(|e| (uuid(e), text(e, "tag")))
+        .collect();
+    let names: Vec<&str> = tags.iter().map(|(_, name)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "host_kids_tablet",
+            "host_kids_tablet_2",
+            "host_192_168_1_11"
+        ]
+    );
+
+    // Each host sets its own tag, and its options match on it
+    let hosts = dnsmasq_hosts(&root);
+    let set_tags: Vec<String> = hosts.iter().map(|h| text(h, "set_tag")).collect();
+    assert_eq!(
+        set_tags,
+        vec![tags[1].0.clone(), tags[2].0.clone(), String::new()]
+    );
+    let tagged: Vec<(String, String, String)> = children
+        .iter()
+        .filter(|e| e.name == "dhcp_options" && !text(e, "tag").is_empty())
+        .map(|e| (text(e, "tag"), text(e, "option"), text(e, "value")))
+        .collect();
+    let expected = [
+        (&tags[1].0, "6", "192.168.1.53,192.168.1.54"),
+        (&tags[1].0, "3", "192.168.1.254"),
+        (&tags[2].0, "3", "192.168.1.253"),
+    ]
+    .map(|(tag, option, value)| (tag.clone(), option.to_string(), value.to_string()));
+    assert_eq!(tagged, expected);
+    // The interface options stay on the interface
+    assert_eq!(
+        dnsmasq_option_value(&root, "lan", "6", "").as_deref(),
+        Some("192.168.1.1")
+    );
+}
+
+#[test]
+fn test_create_options_names_in_server_lists() {
+    let input = TEST_CREATE_OPTIONS_KEA_V4
+        .replace(
+            "<ntpserver>10.22.1.10</ntpserver>",
+            "<ntpserver>opt1</ntpserver><ntpserver>ntp.example.com</ntpserver>",
+        )
+        .replace(
+            "<dnsserver>1.1.1.1</dnsserver>",
+            "<dnsserver>fd00::53</dnsserver>",
+        );
+    let options = MigrationOptions {
+        create_options: true,
+        ..Default::default()
+    };
+    let fields = ["domain_name_servers", "ntp_servers"];
+    assert_eq!(
+        kea_option_values(&input, &options, &fields),
+        ["8.8.8.8", "10.22.1.1"]
+    );
+
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(input.as_bytes()), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.option_values_resolved, 1);
+    let unresolved: Vec<(&str, &str)> = stats
+        .unresolved_option_values
+        .iter()
+        .map(|v| (v.field.as_str(), v.value.as_str()))
+        .collect();
+    assert_eq!(
+        unresolved,
+        [("dnsserver", "fd00::53"), ("ntpserver", "ntp.example.com")]
+    );
+}
+
+#[test]
+fn test_staticmap_gateway_none() {
+    let input = TEST_STATICMAP_OPTIONS.replace(
+        "<gateway>192.168.1.253</gateway>",
+        "<gateway>none</gateway>",
+    );
+
+    // Kea: no "none" router, and a manual step for the subnet's router
+    let options = MigrationOptions {
+        create_options: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    convert_config(Cursor::new(input.as_bytes()), &mut output, &options)
+        .expect("convert should succeed");
+    let text = String::from_utf8(output).expect("output should be valid UTF-8");
+    assert!(!text.contains("<routers>none</routers>"));
+    assert_eq!(text.matches("<routers>192.168.1.254</routers>").count(), 1);
+    let stats = scan_config(Cursor::new(input.as_bytes()), &options).expect("scan should succeed");
+    assert!(
+        stats
+            .manual_actions
+            .iter()
+            .any(|action| action.contains("192.168.1.11 on lan")
+                && action.contains("gateway is none"))
+    );
+
+    // dnsmasq: the host's tag gets an empty option 3
+    let mut options = dnsmasq_options();
+    options.create_options = true;
+    let mut output = Vec::new();
+    convert_config(Cursor::new(input.as_bytes()), &mut output, &options)
+        .expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let host_tag = dnsmasq_hosts(&root)[1]
+        .get_child("set_tag")
+        .and_then(|e| e.get_text())
+        .map(|t| t.to_string())
+        .expect("host sets a tag");
+    let option3: Vec<Option<String>> = root
+        .get_child("dnsmasq")
+        .expect("Should have dnsmasq node")
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(|e| e.get_child("tag").and_then(|t| t.get_text()).as_deref() == Some(&host_tag))
+        .filter(|e| e.get_child("option").and_then(|o| o.get_text()).as_deref() == Some("3"))
+        .map(|e| {
+            e.get_child("value")
+                .and_then(|v| v.get_text())
+                .map(|v| v.to_string())
+        })
+        .collect();
+    assert_eq!(option3, vec![None]);
+}
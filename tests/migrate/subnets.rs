@@ -1,8 +1,45 @@
 use super::common::*;
-use isc2kea::{convert_config, scan_config, MigrationOptions};
+use isc2kea::{
+    convert_config, scan_config, Backend, DnsmasqV6Mode, InvalidIscRange, MigrationError,
+    MigrationOptions, RangeOverlap, RangeOverlapPolicy, SuppressedWarnings,
+};
 use std::io::Cursor;
 use xmltree::Element;
 
+#[test]
+fn test_max_warnings_counts_existing_subnets() {
+    let options = MigrationOptions {
+        create_subnets: true,
+        ..Default::default()
+    };
+    let mut first = Vec::new();
+    convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_KEA_V4),
+        &mut first,
+        &options,
+    )
+    .expect("convert should succeed");
+
+    let stats = convert_config(Cursor::new(&first), &mut Vec::new(), &options)
+        .expect("second convert should succeed");
+    assert!(stats.warnings_suppressed.is_empty());
+
+    let options = MigrationOptions {
+        max_warnings: Some(0),
+        ..options
+    };
+    let stats = convert_config(Cursor::new(&first), &mut Vec::new(), &options)
+        .expect("second convert should succeed");
+    assert_eq!(
+        stats.warnings_suppressed,
+        vec![SuppressedWarnings {
+            summary: "Kea subnets already existed and were skipped".to_string(),
+            count: 1,
+            shown: 0,
+        }]
+    );
+}
+
 #[test]
 fn test_create_subnets_kea_v4() {
     let input = Cursor::new(TEST_CREATE_SUBNETS_KEA_V4);
@@ -39,6 +76,121 @@ fn test_create_subnets_kea_v4() {
     let pools = subnet4.get_child("pools").expect("Should have pools");
     let pool_value = pools.get_text().expect("Should have pool value");
     assert_eq!(pool_value, "10.22.1.100-10.22.1.200");
+
+    let iface = subnet4
+        .get_child("interface")
+        .and_then(|e| e.get_text())
+        .expect("Should have interface");
+    assert_eq!(iface, "opt1");
+
+    let autocollect = subnet4
+        .get_child("option_data_autocollect")
+        .and_then(|e| e.get_text())
+        .expect("Should have option_data_autocollect");
+    assert_eq!(autocollect, "1");
+
+    let option_data = subnet4
+        .get_child("option_data")
+        .expect("Should have option_data");
+    assert!(option_data.get_child("domain_name_servers").is_some());
+    assert!(option_data.get_child("routers").is_some());
+    assert!(subnet4.get_child("match-client-id").is_some());
+    assert!(subnet4.get_child("description").is_some());
+}
+
+#[test]
+fn test_create_subnets_kea_netboot_per_subnet() {
+    let input = Cursor::new(TEST_CREATE_SUBNETS_KEA_NETBOOT);
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_subnets: true,
+        ..Default::default()
+    };
+
+    convert_config(input, &mut output, &options).expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let subnets = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .and_then(|dhcp4| dhcp4.get_child("subnets"))
+        .expect("Should have subnets node");
+    let boot = |cidr: &str| {
+        let subnet4 = subnets
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .find(|e| e.get_child("subnet").and_then(|s| s.get_text()).as_deref() == Some(cidr))
+            .expect("Should have subnet4");
+        let text = |el: Option<&Element>| {
+            el.and_then(|e| e.get_text())
+                .map(|t| t.to_string())
+                .unwrap_or_default()
+        };
+        let option_data = subnet4.get_child("option_data");
+        (
+            text(subnet4.get_child("next_server")),
+            text(option_data.and_then(|o| o.get_child("tftp_server_name"))),
+            text(option_data.and_then(|o| o.get_child("boot_file_name"))),
+        )
+    };
+
+    assert_eq!(
+        boot("10.22.1.0/24"),
+        ("10.22.1.5".into(), String::new(), "pxelinux.0".into())
+    );
+    // Without <netboot>, ISC ignores nextserver
+    assert_eq!(
+        boot("192.168.1.0/24"),
+        (String::new(), "tftp.lan".into(), String::new())
+    );
+}
+
+#[test]
+fn test_create_subnets_kea_edge_prefixes() {
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_subnets: true,
+        ..Default::default()
+    };
+    convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_EDGE_PREFIXES),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let subnets = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .and_then(|dhcp4| dhcp4.get_child("subnets"))
+        .expect("Should have subnets node");
+    let mut pools: Vec<(String, String)> = subnets
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .map(|e| {
+            let text = |tag: &str| {
+                e.get_child(tag)
+                    .and_then(|t| t.get_text())
+                    .map(|t| t.to_string())
+                    .unwrap_or_default()
+            };
+            (text("subnet"), text("pools"))
+        })
+        .collect();
+    pools.sort();
+
+    // Network and broadcast stay out of the /30 pool, both /31 addresses
+    // are usable, and a range of only the network address is dropped
+    assert_eq!(
+        pools,
+        [
+            ("10.0.0.0/30".to_string(), "10.0.0.1-10.0.0.2".to_string()),
+            ("10.0.0.4/31".to_string(), "10.0.0.4-10.0.0.5".to_string()),
+        ]
+    );
 }
 
 #[test]
@@ -77,6 +229,16 @@ fn test_create_subnets_kea_v6() {
     let pools = subnet6.get_child("pools").expect("Should have pools");
     let pool_value = pools.get_text().expect("Should have pool value");
     assert_eq!(pool_value, "fd00:1234:5678:1::100-fd00:1234:5678:1::200");
+
+    let iface = subnet6
+        .get_child("interface")
+        .and_then(|e| e.get_text())
+        .expect("Should have interface");
+    assert_eq!(iface, "lan");
+    assert!(subnet6
+        .get_child("option_data")
+        .and_then(|o| o.get_child("dns_servers"))
+        .is_some());
 }
 
 #[test]
@@ -85,7 +247,9 @@ fn test_create_subnets_dnsmasq_v4() {
     let mut output = Vec::new();
     let options = dnsmasq_options_create_subnets();
 
-    convert_config(input, &mut output, &options).expect("convert should succeed");
+    let stats = convert_config(input, &mut output, &options).expect("convert should succeed");
+    assert_eq!(stats.ranges_created, 1);
+    assert_eq!(stats.subnets_created, 0);
 
     let output_str = String::from_utf8(output).expect("output should be valid UTF-8");
     let root =
@@ -169,6 +333,59 @@ fn test_create_subnets_dnsmasq_v6() {
     assert_eq!(prefix_len, "64");
 }
 
+#[test]
+fn test_create_subnets_dnsmasq_netboot() {
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_DNSMASQ_NETBOOT),
+        &mut output,
+        &dnsmasq_options_create_subnets(),
+    )
+    .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dnsmasq = root.get_child("dnsmasq").expect("Should have dnsmasq node");
+    let text = |el: &Element, tag: &str| {
+        el.get_child(tag)
+            .and_then(|e| e.get_text())
+            .map(|t| t.to_string())
+            .unwrap_or_default()
+    };
+    let boots: Vec<(String, String, String, String)> = dnsmasq
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "dhcp_boot")
+        .map(|e| {
+            (
+                text(e, "interface"),
+                text(e, "filename"),
+                text(e, "servername"),
+                text(e, "address"),
+            )
+        })
+        .collect();
+
+    // lan has no boot file, and opt2 keeps its existing entry
+    assert_eq!(
+        boots,
+        [
+            (
+                "opt2".into(),
+                "undionly.kpxe".into(),
+                String::new(),
+                String::new()
+            ),
+            (
+                "opt1".into(),
+                "pxelinux.0".into(),
+                "tftp.opt1".into(),
+                "10.22.1.5".into()
+            ),
+        ]
+    );
+}
+
 #[test]
 fn test_create_subnets_kea_existing_skip() {
     let input = Cursor::new(TEST_CREATE_SUBNETS_KEA_V4_EXISTING);
@@ -234,6 +451,150 @@ fn test_create_subnets_kea_existing_force() {
     assert_eq!(pool_value, "10.22.1.100-10.22.1.200");
 }
 
+#[test]
+fn test_create_pools_kea_existing_subnet() {
+    let input = Cursor::new(TEST_CREATE_SUBNETS_KEA_V4_EXISTING);
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_pools: true,
+        ..Default::default()
+    };
+
+    let stats = convert_config(input, &mut output, &options).expect("convert should succeed");
+    assert_eq!(stats.subnets_created, 0);
+    assert_eq!(stats.pools_created, 1);
+    assert_eq!(stats.reservations_to_create, 1);
+
+    let root = Element::parse(Cursor::new(&output)).expect("output should be valid XML");
+    let subnets: Vec<&Element> = root
+        .get_child("Kea")
+        .and_then(|k| k.get_child("dhcp4"))
+        .and_then(|d| d.get_child("subnets"))
+        .expect("Should have subnets node")
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "subnet4")
+        .collect();
+    assert_eq!(subnets.len(), 1, "The existing subnet is kept");
+    assert_eq!(
+        subnets[0].attributes.get("uuid").map(String::as_str),
+        Some("existing-subnet")
+    );
+    let pools = subnets[0]
+        .get_child("pools")
+        .and_then(|p| p.get_text())
+        .expect("Should have pools");
+    assert_eq!(pools, "10.22.1.50-10.22.1.60,10.22.1.100-10.22.1.200");
+
+    // A second run finds the pool in place and adds nothing.
+    let stats = convert_config(Cursor::new(&output), &mut Vec::new(), &options)
+        .expect("second convert should succeed");
+    assert_eq!(stats.pools_created, 0);
+    assert!(stats.warnings_suppressed.is_empty());
+}
+
+#[test]
+fn test_create_pools_kea_interface_subnet() {
+    let input = Cursor::new(TEST_CREATE_POOLS_KEA_IFACE);
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_subnets: true,
+        create_pools: true,
+        max_warnings: Some(0),
+        ..Default::default()
+    };
+
+    let stats = convert_config(input, &mut output, &options).expect("convert should succeed");
+    assert_eq!(stats.subnets_created, 0, "opt1 is served by 10.22.0.0/16");
+    assert_eq!(stats.pools_created, 1);
+    assert_eq!(
+        stats.warnings_suppressed,
+        vec![SuppressedWarnings {
+            summary: "Kea pools overlapped existing ones and were skipped".to_string(),
+            count: 1,
+            shown: 0,
+        }]
+    );
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let subnets: Vec<&Element> = root
+        .get_child("Kea")
+        .and_then(|k| k.get_child("dhcp4"))
+        .and_then(|d| d.get_child("subnets"))
+        .expect("Should have subnets node")
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "subnet4")
+        .collect();
+    assert_eq!(subnets.len(), 1);
+    let pools = subnets[0]
+        .get_child("pools")
+        .and_then(|p| p.get_text())
+        .expect("Should have pools");
+    assert_eq!(
+        pools,
+        "10.22.1.10-10.22.1.20,10.22.1.190-10.22.1.220,10.22.1.30-10.22.1.40"
+    );
+}
+
+#[test]
+fn test_create_subnets_kea_force_reuses_uuid() {
+    let input = Cursor::new(TEST_CREATE_SUBNETS_KEA_FORCE_REUSE_UUID);
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_subnets: true,
+        force_subnets: true,
+        ..Default::default()
+    };
+
+    convert_config(input, &mut output, &options).expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dhcp4 = root
+        .get_child("Kea")
+        .and_then(|k| k.get_child("dhcp4"))
+        .expect("Should have dhcp4 node");
+    let subnets: Vec<&Element> = dhcp4
+        .get_child("subnets")
+        .expect("Should have subnets node")
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "subnet4")
+        .collect();
+    assert_eq!(subnets.len(), 1, "Both subnets for the CIDR are replaced");
+    assert_eq!(
+        subnets[0].attributes.get("uuid").map(String::as_str),
+        Some("existing-subnet")
+    );
+    let pools = subnets[0].get_child("pools").and_then(|e| e.get_text());
+    assert_eq!(pools.as_deref(), Some("10.22.1.100-10.22.1.200"));
+
+    // Existing reservations and the migrated one all point at the kept uuid
+    let reservations: Vec<(String, String)> = dhcp4
+        .get_child("reservations")
+        .expect("Should have reservations node")
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .map(|r| {
+            let text = |name| {
+                r.get_child(name)
+                    .and_then(|e| e.get_text())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            (text("ip_address"), text("subnet"))
+        })
+        .collect();
+    assert_eq!(reservations.len(), 3);
+    for (ip, subnet) in &reservations {
+        assert_eq!(subnet, "existing-subnet", "reservation {}", ip);
+    }
+}
+
 #[test]
 fn test_create_subnets_dnsmasq_existing_skip() {
     let input = Cursor::new(TEST_CREATE_SUBNETS_DNSMASQ_V4_EXISTING);
@@ -302,9 +663,16 @@ fn test_create_subnets_range_outside_cidr_errors() {
 
     let err = convert_config(input, &mut output, &options)
         .expect_err("convert should fail for out-of-subnet range");
-    assert!(err
-        .to_string()
-        .contains("not contained within interface subnet"));
+    assert_eq!(
+        err.downcast_ref::<MigrationError>(),
+        Some(&MigrationError::RangeOutsideSubnet {
+            family: "DHCPv4".to_string(),
+            iface: "opt1".to_string(),
+            from: "10.0.1.100".to_string(),
+            to: "10.0.1.200".to_string(),
+            cidr: "10.0.0.0/24".to_string(),
+        })
+    );
 }
 
 #[test]
@@ -318,9 +686,10 @@ fn test_create_subnets_missing_interface_errors() {
 
     let err = convert_config(input, &mut output, &options)
         .expect_err("convert should fail when interface CIDR is missing");
-    assert!(err
-        .to_string()
-        .contains("No interface CIDR found for DHCPv4 interface"));
+    assert!(matches!(
+        err.downcast_ref::<MigrationError>(),
+        Some(MigrationError::MissingInterfaceCidr { family, .. }) if family == "DHCPv4"
+    ));
 }
 
 #[test]
@@ -334,9 +703,10 @@ fn test_create_subnets_dhcp_interface_errors() {
 
     let err = convert_config(input, &mut output, &options)
         .expect_err("convert should fail for DHCP interface");
-    assert!(err
-        .to_string()
-        .contains("No interface CIDR found for DHCPv4 interface"));
+    assert!(matches!(
+        err.downcast_ref::<MigrationError>(),
+        Some(MigrationError::MissingInterfaceCidr { family, .. }) if family == "DHCPv4"
+    ));
 }
 
 #[test]
@@ -350,9 +720,10 @@ fn test_create_subnets_track6_interface_errors() {
 
     let err = convert_config(input, &mut output, &options)
         .expect_err("convert should fail for track6 interface");
-    assert!(err
-        .to_string()
-        .contains("No interface CIDR found for DHCPv6 interface"));
+    assert!(matches!(
+        err.downcast_ref::<MigrationError>(),
+        Some(MigrationError::MissingInterfaceCidr { family, .. }) if family == "DHCPv6"
+    ));
 }
 
 #[test]
@@ -387,7 +758,8 @@ fn test_create_subnets_multiple_ranges_v4() {
         ..Default::default()
     };
 
-    convert_config(input, &mut output, &options).expect("convert should succeed");
+    let stats = convert_config(input, &mut output, &options).expect("convert should succeed");
+    assert_eq!((stats.subnets_created, stats.pools_created), (1, 2));
 
     let output_str = String::from_utf8(output).expect("output should be valid UTF-8");
     let root =
@@ -624,3 +996,433 @@ fn test_create_subnets_dnsmasq_preserves_existing_interfaces() {
     assert!(iface_parts.contains(&"opt1"));
     assert!(iface_parts.contains(&"opt2"));
 }
+
+#[test]
+fn test_create_subnets_unmatched_mappings_grouped_error() {
+    let input = Cursor::new(TEST_CREATE_SUBNETS_UNMATCHED_KEA_V4);
+    let mut output = Vec::new();
+    let options = MigrationOptions {
+        create_subnets: true,
+        ..Default::default()
+    };
+
+    let err = convert_config(input, &mut output, &options)
+        .expect_err("convert should fail for mappings outside every subnet");
+    let message = err.to_string();
+    assert!(message.contains("2 static mapping(s) do not match"));
+    assert!(message.contains(
+        "opt1: 10.22.1.10 (interface network 10.22.1.0/24; nearest subnet 192.168.1.0/24)"
+    ));
+    assert!(message.contains(
+        "opt1: 10.22.1.11 (interface network 10.22.1.0/24; nearest subnet 192.168.1.0/24)"
+    ));
+    assert!(message.contains("--skip-unmatched"));
+}
+
+#[test]
+fn test_create_subnets_skip_unmatched_mappings() {
+    let options = MigrationOptions {
+        create_subnets: true,
+        skip_unmatched: true,
+        ..Default::default()
+    };
+
+    let stats = scan_config(Cursor::new(TEST_CREATE_SUBNETS_UNMATCHED_KEA_V4), &options)
+        .expect("scan should succeed");
+    assert_eq!(stats.isc_mappings_found, 3);
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.reservations_skipped, 2);
+    assert_eq!(stats.skips.len(), 2);
+    assert!(stats
+        .skips
+        .iter()
+        .all(|skip| matches!(skip, MigrationError::SubnetNotFound { .. })));
+
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_UNMATCHED_KEA_V4),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 1);
+    assert_eq!(stats.reservations_skipped, 2);
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dhcp4 = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .expect("Should have dhcp4 node");
+    let reservations = reservations_as_fields(dhcp4);
+    assert_eq!(reservations.len(), 1);
+    assert!(reservations[0].contains(&("ip_address".to_string(), "192.168.1.10".to_string())));
+}
+
+#[test]
+fn test_create_subnets_uses_interface_descr() {
+    let options = MigrationOptions {
+        create_subnets: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    let stats = convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_LABELED),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(
+        stats.interface_labels.get("opt3").map(String::as_str),
+        Some("IoT VLAN")
+    );
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let subnet4 = root
+        .get_child("Kea")
+        .and_then(|kea| kea.get_child("dhcp4"))
+        .and_then(|dhcp4| dhcp4.get_child("subnets"))
+        .and_then(|subnets| subnets.get_child("subnet4"))
+        .expect("Should have subnet4");
+    let descr = subnet4
+        .get_child("description")
+        .and_then(|e| e.get_text())
+        .expect("Should have description");
+    assert_eq!(descr, "IoT VLAN");
+
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_LABELED),
+        &mut output,
+        &dnsmasq_options_create_subnets(),
+    )
+    .expect("convert should succeed");
+
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let range = find_descendant_ci(&root, "dnsmasq")
+        .and_then(|dnsmasq| dnsmasq.get_child("dhcp_ranges"))
+        .expect("Should have dhcp_ranges");
+    let descr = range
+        .get_child("description")
+        .and_then(|e| e.get_text())
+        .expect("Should have description");
+    assert_eq!(descr, "IoT VLAN");
+}
+
+fn converted_dnsmasq_ranges(xml: &str, options: &MigrationOptions) -> Vec<(String, String)> {
+    let mut output = Vec::new();
+    convert_config(Cursor::new(xml), &mut output, options).expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let dnsmasq = find_descendant_ci(&root, "dnsmasq").expect("Should have dnsmasq node");
+    dnsmasq
+        .children
+        .iter()
+        .filter_map(|c| c.as_element())
+        .filter(|e| e.name == "dhcp_ranges")
+        .map(|range| {
+            let text = |tag: &str| {
+                range
+                    .get_child(tag)
+                    .and_then(|e| e.get_text())
+                    .map(|s| s.to_string())
+                    .unwrap_or_default()
+            };
+            (text("start_addr"), text("end_addr"))
+        })
+        .collect()
+}
+
+fn range(start: &str, end: &str) -> (String, String) {
+    (start.to_string(), end.to_string())
+}
+
+#[test]
+fn test_dnsmasq_range_overlap_skips_partial_overlap() {
+    let xml = dnsmasq_overlap_xml("opt1", "10.22.1.150", "10.22.1.250");
+    let ranges = converted_dnsmasq_ranges(&xml, &dnsmasq_options_create_subnets());
+    assert_eq!(ranges, vec![range("10.22.1.150", "10.22.1.250")]);
+}
+
+#[test]
+fn test_dnsmasq_range_overlap_boundaries_are_inclusive() {
+    for (start, end) in [
+        ("10.22.1.50", "10.22.1.100"),
+        ("10.22.1.200", "10.22.1.250"),
+        ("10.22.1.150", "10.22.1.160"),
+        ("10.22.1.10", "10.22.1.250"),
+    ] {
+        let xml = dnsmasq_overlap_xml("opt1", start, end);
+        let ranges = converted_dnsmasq_ranges(&xml, &dnsmasq_options_create_subnets());
+        assert_eq!(
+            ranges,
+            vec![range(start, end)],
+            "{}-{} overlaps",
+            start,
+            end
+        );
+    }
+}
+
+#[test]
+fn test_dnsmasq_range_overlap_allows_adjacent_ranges() {
+    for (start, end) in [("10.22.1.50", "10.22.1.99"), ("10.22.1.201", "10.22.1.250")] {
+        let xml = dnsmasq_overlap_xml("opt1", start, end);
+        let ranges = converted_dnsmasq_ranges(&xml, &dnsmasq_options_create_subnets());
+        assert_eq!(
+            ranges,
+            vec![range(start, end), range("10.22.1.100", "10.22.1.200")],
+            "{}-{} is adjacent",
+            start,
+            end
+        );
+    }
+}
+
+#[test]
+fn test_dnsmasq_range_overlap_ignores_other_interfaces() {
+    let xml = dnsmasq_overlap_xml("opt2", "10.22.1.150", "10.22.1.250");
+    let ranges = converted_dnsmasq_ranges(&xml, &dnsmasq_options_create_subnets());
+    assert_eq!(ranges.len(), 2);
+}
+
+#[test]
+fn test_dnsmasq_range_overlap_force_replaces() {
+    let xml = dnsmasq_overlap_xml("opt1", "10.22.1.150", "10.22.1.250");
+    let options = MigrationOptions {
+        range_overlap: RangeOverlapPolicy::Force,
+        ..dnsmasq_options_create_subnets()
+    };
+    let ranges = converted_dnsmasq_ranges(&xml, &options);
+    assert_eq!(ranges, vec![range("10.22.1.100", "10.22.1.200")]);
+}
+
+#[test]
+fn test_dnsmasq_range_overlap_fail_reports_conflicts() {
+    let xml = dnsmasq_overlap_xml("opt1", "10.22.1.150", "10.22.1.250");
+    let options = MigrationOptions {
+        range_overlap: RangeOverlapPolicy::Fail,
+        ..dnsmasq_options_create_subnets()
+    };
+
+    let err = scan_config(Cursor::new(&xml), &options).expect_err("scan should fail");
+    assert_eq!(
+        err.downcast_ref::<MigrationError>(),
+        Some(&MigrationError::OverlappingRanges {
+            overlaps: vec![RangeOverlap {
+                iface: "opt1".to_string(),
+                from: "10.22.1.100".to_string(),
+                to: "10.22.1.200".to_string(),
+                conflicts: vec!["10.22.1.150-10.22.1.250".to_string()],
+            }],
+        })
+    );
+    let message = err.to_string();
+    assert!(message.contains("1 dnsmasq range(s) overlap existing ranges"));
+    assert!(message.contains("  opt1: 10.22.1.100-10.22.1.200 overlaps 10.22.1.150-10.22.1.250"));
+
+    let mut output = Vec::new();
+    assert!(convert_config(Cursor::new(&xml), &mut output, &options).is_err());
+
+    let xml = dnsmasq_overlap_xml("opt1", "10.22.1.201", "10.22.1.250");
+    scan_config(Cursor::new(&xml), &options).expect("adjacent ranges should not fail");
+
+    // Ranges an earlier convert created are skipped, not reported
+    let mut output = Vec::new();
+    convert_config(Cursor::new(&xml), &mut output, &options).expect("convert should succeed");
+    let ranges = converted_dnsmasq_ranges(&String::from_utf8(output).unwrap(), &options);
+    assert_eq!(ranges.len(), 2);
+}
+
+fn converted_dnsmasq_range_v6(xml: &str, options: &MigrationOptions) -> Vec<(String, String)> {
+    let mut output = Vec::new();
+    convert_config(Cursor::new(xml), &mut output, options).expect("convert should succeed");
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    let range = find_descendant_ci(&root, "dnsmasq")
+        .and_then(|dnsmasq| dnsmasq.get_child("dhcp_ranges"))
+        .expect("Should have dhcp_ranges")
+        .clone();
+    ["start_addr", "end_addr", "mode", "constructor"]
+        .iter()
+        .map(|tag| {
+            let value = range
+                .get_child(*tag)
+                .and_then(|e| e.get_text())
+                .map(|s| s.to_string())
+                .unwrap_or_default();
+            (tag.to_string(), value)
+        })
+        .collect()
+}
+
+#[test]
+fn test_create_subnets_dnsmasq_v6_mode_from_ramode() {
+    let fields = converted_dnsmasq_range_v6(
+        TEST_CREATE_SUBNETS_DNSMASQ_V6_RAMODE,
+        &dnsmasq_options_create_subnets(),
+    );
+    assert!(fields.contains(&("mode".to_string(), "slaac".to_string())));
+    assert!(fields.contains(&("constructor".to_string(), String::new())));
+
+    // Without an ISC ramode the range stays stateful
+    let fields = converted_dnsmasq_range_v6(
+        TEST_CREATE_SUBNETS_DNSMASQ_V6,
+        &dnsmasq_options_create_subnets(),
+    );
+    assert!(fields.contains(&("mode".to_string(), String::new())));
+}
+
+#[test]
+fn test_create_subnets_dnsmasq_v6_mode_override() {
+    let options = MigrationOptions {
+        dnsmasq_v6_mode: DnsmasqV6Mode::RaNames,
+        ..dnsmasq_options_create_subnets()
+    };
+    let fields = converted_dnsmasq_range_v6(TEST_CREATE_SUBNETS_DNSMASQ_V6_RAMODE, &options);
+    assert!(fields.contains(&("mode".to_string(), "ra-names".to_string())));
+}
+
+#[test]
+fn test_create_subnets_dnsmasq_v6_constructor() {
+    let options = MigrationOptions {
+        dnsmasq_constructor: true,
+        ..dnsmasq_options_create_subnets()
+    };
+    let fields = converted_dnsmasq_range_v6(TEST_CREATE_SUBNETS_DNSMASQ_V6_RAMODE, &options);
+    assert!(fields.contains(&("start_addr".to_string(), "::1000".to_string())));
+    assert!(fields.contains(&("end_addr".to_string(), "::2000".to_string())));
+    assert!(fields.contains(&("constructor".to_string(), "lan".to_string())));
+}
+
+fn created_pools(output: &[u8], family: &str, subnet_tag: &str) -> String {
+    let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+    root.get_child("Kea")
+        .and_then(|kea| kea.get_child(family))
+        .and_then(|dhcp| dhcp.get_child("subnets"))
+        .and_then(|subnets| subnets.get_child(subnet_tag))
+        .and_then(|subnet| subnet.get_child("pools"))
+        .and_then(|pools| pools.get_text())
+        .map(|pools| pools.to_string())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_carve_pools_around_reservations() {
+    let options = MigrationOptions {
+        create_subnets: true,
+        carve_pools: true,
+        ..Default::default()
+    };
+
+    let mut output = Vec::new();
+    let stats = convert_config(Cursor::new(TEST_CARVE_POOLS_KEA_V4), &mut output, &options)
+        .expect("convert should succeed");
+    assert_eq!(stats.reservations_to_create, 4);
+    assert_eq!(
+        created_pools(&output, "dhcp4", "subnet4"),
+        "10.22.1.101-10.22.1.149,10.22.1.151-10.22.1.200",
+        "reservations are carved out and a range holding only one is dropped"
+    );
+
+    // A reservation outside the range leaves the pool as it was
+    let mut output = Vec::new();
+    convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_KEA_V6),
+        &mut output,
+        &options,
+    )
+    .expect("convert should succeed");
+    assert_eq!(
+        created_pools(&output, "dhcp6", "subnet6"),
+        "fd00:1234:5678:1::100-fd00:1234:5678:1::200"
+    );
+}
+
+#[test]
+fn test_create_subnets_invalid_ranges() {
+    let options = MigrationOptions {
+        create_subnets: true,
+        ..Default::default()
+    };
+    let err = convert_config(
+        Cursor::new(TEST_CREATE_SUBNETS_INVALID_RANGES),
+        &mut Vec::new(),
+        &options,
+    )
+    .expect_err("invalid ranges should fail");
+
+    // Every bad range is reported, of both families, before any conversion
+    match err.downcast_ref::<MigrationError>() {
+        Some(MigrationError::InvalidRanges { ranges }) => {
+            assert_eq!(ranges.len(), 4);
+            assert_eq!(
+                ranges[0],
+                InvalidIscRange {
+                    iface: "lan".to_string(),
+                    family: "DHCPv4".to_string(),
+                    from: "192.168.1.200".to_string(),
+                    to: "192.168.1.100".to_string(),
+                    problem: "ends before it starts".to_string(),
+                }
+            );
+            let message = err.to_string();
+            assert!(message.contains("opt1 (IoT): DHCPv4 range 192.168.2.100-fd00::200 mixes"));
+            assert!(message.contains("'192.168.3.300' is not an IP address"));
+            assert!(message
+                .contains("  lan: DHCPv6 range 192.168.1.10-192.168.1.20 holds IPv4 addresses"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_create_subnets_kea_merge_ranges() {
+    let mut options = MigrationOptions {
+        backend: Backend::Kea,
+        create_subnets: true,
+        ..Default::default()
+    };
+    let pools = |options: &MigrationOptions| {
+        let mut output = Vec::new();
+        let stats = convert_config(
+            Cursor::new(TEST_CREATE_SUBNETS_FRAGMENTED_RANGES),
+            &mut output,
+            options,
+        )
+        .expect("convert should succeed");
+        let root = Element::parse(Cursor::new(output)).expect("output should be valid XML");
+        let pools = root
+            .get_child("Kea")
+            .and_then(|kea| kea.get_child("dhcp4"))
+            .and_then(|dhcp4| dhcp4.get_child("subnets"))
+            .and_then(|subnets| subnets.get_child("subnet4"))
+            .and_then(|subnet| subnet.get_child("pools"))
+            .and_then(|pools| pools.get_text())
+            .map(|text| text.to_string())
+            .expect("Should have pools");
+        (stats.ranges_merged, pools)
+    };
+
+    let (merged, unmerged_pools) = pools(&options);
+    assert_eq!(merged, 0);
+    assert_eq!(unmerged_pools.split(',').count(), 4);
+
+    options.merge_ranges = true;
+    assert_eq!(
+        pools(&options),
+        (
+            2,
+            "192.168.1.10-192.168.1.40,192.168.1.100-192.168.1.110".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_scan_dnsmasq_merge_ranges() {
+    let options = MigrationOptions {
+        merge_ranges: true,
+        ..dnsmasq_options_create_subnets()
+    };
+    let stats = scan_config(Cursor::new(TEST_CREATE_SUBNETS_FRAGMENTED_RANGES), &options)
+        .expect("scan should succeed");
+    assert_eq!(stats.isc_ranges_found, 4);
+    assert_eq!(stats.ranges_merged, 2);
+}